@@ -0,0 +1,39 @@
+// Embedding the editor component: a note field in the middle of the terminal.
+// cargo run --example embed_widget, Esc to finish and print what was typed
+use crossterm::{
+    cursor::MoveTo,
+    event::{read, Event},
+    execute, queue,
+    style::Print,
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use quick_notepad::core::widget::{CrosstermSurface, EditorWidget, Rect};
+use std::io::{stdout, Error, Write};
+
+fn main() -> Result<(), Error> {
+    let mut widget = EditorWidget::new("Type a note here.\nArrows move, Ctrl+Z undoes.");
+    terminal::enable_raw_mode()?;
+    execute!(stdout(), EnterAlternateScreen)?;
+
+    let result = (|| loop {
+        let (columns, rows) = terminal::size()?;
+        let area = Rect { x: 4, y: 3, width: columns.saturating_sub(8), height: rows.saturating_sub(6) };
+        let mut surface = CrosstermSurface(stdout());
+        queue!(surface.0, Clear(ClearType::All), MoveTo(4, 1), Print("Note (Esc to finish):"))?;
+        widget.render(&mut surface, area)?;
+        surface.0.flush()?;
+
+        if let Event::Key(key) = read()? {
+            // Keys the widget doesn't use belong to the host, here Esc ends the example
+            if !widget.handle_key(key) && key.code == crossterm::event::KeyCode::Esc {
+                return Ok::<(), Error>(());
+            }
+        }
+    })();
+
+    execute!(stdout(), LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    result?;
+    println!("{}", widget.text());
+    Ok(())
+}
@@ -0,0 +1,54 @@
+// Embedding the editing engine: a tiny sed that replaces text in a range of lines and can undo it.
+// cargo run --example replace_lines -- 's/foo/bar/g' 2:10 < input.txt
+use quick_notepad::core::{
+    batch::{self, LineRange},
+    buffer::Buffer,
+    edit_history::{Edit, EditHistory, EditOperation},
+    replace::Substitution,
+    selection::Position,
+};
+use std::io::Read;
+
+fn main() -> Result<(), String> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let [expr, range] = args.as_slice() else {
+        return Err("usage: replace_lines 's/old/new/g' FIRST:LAST < input".to_string());
+    };
+    let substitution = Substitution::parse(expr)?;
+    let range = LineRange::parse(range)?;
+
+    let mut text = String::new();
+    std::io::stdin().read_to_string(&mut text).map_err(|e| e.to_string())?;
+    let (patched, count) = batch::replace(&text, &substitution, Some(&range));
+    print!("{}", patched);
+    eprintln!("{} replacements", count);
+
+    // The same change made on a buffer, one undoable step per changed line
+    let mut buffer = Buffer::from_string(text.clone());
+    let mut history = EditHistory::new(100);
+    let last = range.last.unwrap_or(usize::MAX).min(text.lines().count());
+    for line in (range.first - 1)..last {
+        let (new_text, replaced) = substitution.apply(&buffer.lines[line]);
+        if replaced == 0 {
+            continue;
+        }
+        let edit = Edit::ReplaceLines {
+            start_line: line,
+            old_lines: vec![buffer.lines[line].clone()],
+            new_lines: vec![new_text],
+        };
+        edit.apply(&mut buffer.lines);
+        history.push(EditOperation {
+            edit,
+            cursor_before: Position::default(),
+            cursor_after: Position::default(),
+            scroll_before: 0,
+            scroll_after: 0,
+        });
+    }
+    while let Some(operation) = history.undo() {
+        operation.edit.reverse(&mut buffer.lines);
+    }
+    assert_eq!(buffer.content(), text.trim_end_matches('\n'), "undoing every edit gives the original back");
+    Ok(())
+}
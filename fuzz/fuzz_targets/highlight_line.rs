@@ -0,0 +1,27 @@
+// Arbitrary bytes through the highlighter of every language: unterminated strings and comments,
+// stray brackets, huge lines and the U+FFFD left where invalid UTF-8 was
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use quick_notepad::core::syntax;
+
+const FILE_TYPES: [&str; 36] = [
+    "Rust", "Python", "JavaScript", "TypeScript", "React JSX", "React TSX", "C", "C++", "C#", "Java",
+    "Kotlin", "Scala", "Go", "Ruby", "PHP", "Swift", "Shell Script", "Bash Script", "Zsh Script",
+    "HTML", "XML", "Vue", "CSS", "Sass", "JSON", "TOML", "YAML", "Config", "Config File",
+    "Environment", "SQL Query", "Markdown", "Dart", "Zig", "Nim", "Plain Text",
+];
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&pick, rest)) = data.split_first() else {
+        return;
+    };
+    let text = String::from_utf8_lossy(rest);
+    let file_type = FILE_TYPES.get(pick as usize).map(|name| name.to_string());
+    let keywords = ["TODO".to_string(), "FIXME".to_string()];
+    let highlighter = syntax::SyntaxHighlighter::new(file_type).with_todo_keywords(&keywords);
+    for line in text.split('\n') {
+        // Tokens cover the line exactly, nothing dropped or doubled
+        let tokens = highlighter.highlight_line(line);
+        assert_eq!(tokens.iter().map(|token| token.text.as_str()).collect::<String>(), line);
+    }
+});
@@ -0,0 +1,29 @@
+// Arbitrary bytes loaded as a file, the way the editor reads them after replacing invalid UTF-8
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use quick_notepad::core::buffer::Buffer;
+
+fuzz_target!(|data: &[u8]| {
+    let buffer = Buffer::from_string(String::from_utf8_lossy(data).into_owned());
+    assert!(buffer.logical_len() >= 1 && buffer.logical_len() <= buffer.lines.len());
+    let _ = buffer.content();
+
+    // Saving and reopening gives back the same lines, except for a \r ending one, which the
+    // line break written after it turns into a \r\n
+    let reopened = Buffer::from_string(buffer.file_text(false));
+    assert_eq!(reopened.logical_len(), buffer.logical_len());
+    for (old, new) in buffer.lines.iter().zip(&reopened.lines) {
+        assert!(new == old || old.strip_suffix('\r') == Some(new));
+    }
+
+    // insert_final_newline only ever adds the one line break, in the file's own line ending
+    // (a lone \r ending the text becomes \r\n, as in any editor)
+    let saved = buffer.file_text(false);
+    let padded = buffer.file_text(true);
+    assert!(padded == saved || (padded.strip_suffix(buffer.line_ending()) == Some(&saved) && !saved.ends_with('\n')));
+
+    // Streaming the save writes exactly what file_text builds
+    let mut streamed = Vec::new();
+    buffer.write_text(&mut streamed, true, &Default::default()).unwrap();
+    assert_eq!(streamed, padded.as_bytes());
+});
@@ -0,0 +1,31 @@
+// Arbitrary queries over arbitrary lines: the first line is the query, searched for plainly and
+// parsed as an s/old/new/ substitution, and the rest is the text
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use quick_notepad::core::{replace::Substitution, search_index::SearchIndex};
+
+fuzz_target!(|data: &[u8]| {
+    let text = String::from_utf8_lossy(data);
+    let (query, rest) = text.split_once('\n').unwrap_or((&text, ""));
+    let mut lines: Vec<String> = rest.split('\n').map(String::from).collect();
+
+    // Columns are byte offsets into the lowercased line, which callers slice it at
+    let mut index = SearchIndex::default();
+    for _ in 0..2 {
+        for (line, column) in index.find_all(&lines, query) {
+            assert!(lines[line].to_lowercase().get(..column).is_some());
+        }
+        // Search again after an edit, through the cached lines
+        lines.rotate_left(1);
+        lines[0].push_str(query);
+    }
+
+    if let Ok(substitution) = Substitution::parse(query) {
+        for line in &lines {
+            for (start, end) in substitution.find(line) {
+                assert!(line.get(start..end).is_some());
+            }
+            let _ = substitution.apply(line);
+        }
+    }
+});
@@ -1,3 +1,9 @@
+use crate::core::conflicts::Resolution;
+use crate::core::hash::HashAlgorithm;
+use crate::core::jumps::Jump;
+use crate::core::transforms::TextTransform;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Action {
     // Movement
     Left,
@@ -36,17 +42,128 @@ pub enum Action {
     Paste,
     ToggleCtrlShortcuts,
     Save,
+    SaveAs,
+    SaveEncrypted,
     New,
     Quit,
+    OpenFile,
     Print,
     Undo,
     Redo,
     Search,
+    RepeatSearch,
+    Replace,
+    CountMatches,
+    ScrollUp(usize), // Mouse wheel, in lines; the caret stays where it is
+    ScrollDown(usize),
+    ToggleAutoWrap,
+    ToggleMouse,
+    ToggleCheckbox,
+    MoveDoneItems,
+    FormatTable,
+    ListTodos,
+    OpenLink,
+    GoToFile,
+    Evaluate,
+    AlignSelection,
+    ReflowParagraph,
+    InsertSequence,
+    ReadFile,
+    WriteSelection,
+    AppendToFile,
+    CompareTabs,
+    FileHistory,
+    NextConflict,
+    ResolveConflict(Resolution),
+    ToggleFold,
+    UnfoldAll,
+    ExpandSelection,
+    ShrinkSelection,
+    WordLeft,
+    WordRight,
+    Jump(Jump),
+    SelectJump(Jump),
+    KillLine,
+    Yank,
+    Transform(TextTransform),
+    Hash(HashAlgorithm),
+    CommandPalette,
 
     // Tab operations
     SwitchTab(usize),
+    DetachTab,
+    ToggleFollow,
+    NewScratch,
+    OpenScratch,
+    FindFile,
+    DeleteFile,
+    RenameFile,
+    RestoreDeletedFile,
+    UsageStats,
     
     // Updateing 
     CheckUpdate,
-    PerformUpdate,
+    RollbackUpdate,
+    ReportIssue,
+}
+
+impl Action {
+    // Actions that change buffer content - blocked for read-only tabs
+    pub fn modifies_buffer(&self) -> bool {
+        matches!(
+            self,
+            Action::NextLine
+                | Action::Backspace
+                | Action::Delete
+                | Action::Cut
+                | Action::Paste
+                | Action::Print
+                | Action::Undo
+                | Action::Redo
+                | Action::ToggleCheckbox
+                | Action::MoveDoneItems
+                | Action::FormatTable
+                | Action::Transform(_)
+                | Action::AlignSelection
+                | Action::ReflowParagraph
+                | Action::InsertSequence
+                | Action::ReadFile
+                | Action::ResolveConflict(_)
+                | Action::KillLine
+                | Action::Yank
+                | Action::Replace
+        )
+    }
+
+    // Motions and line operations a count prefix runs several times; the rest run once
+    pub fn repeatable(&self) -> bool {
+        matches!(
+            self,
+            Action::Left
+                | Action::Right
+                | Action::Up
+                | Action::Down
+                | Action::SelectLeft
+                | Action::SelectRight
+                | Action::SelectUp
+                | Action::SelectDown
+                | Action::WordLeft
+                | Action::WordRight
+                | Action::Jump(_)
+                | Action::SelectJump(_)
+                | Action::NextLine
+                | Action::Backspace
+                | Action::Delete
+                | Action::Paste
+                | Action::Print
+                | Action::Undo
+                | Action::Redo
+                | Action::RepeatSearch
+                | Action::NextConflict
+                | Action::KillLine
+                | Action::Yank
+                | Action::ExpandSelection
+                | Action::ShrinkSelection
+        )
+    }
 }
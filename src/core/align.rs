@@ -0,0 +1,89 @@
+// align module - line up `=`, `:` or any delimiter across a block of lines
+use crate::core::selection::TextPosition;
+
+// Lines covered by a selection; a selection ending at column 0 stops on the line before
+pub fn selected_lines(start: TextPosition, end: TextPosition) -> (usize, usize) {
+    if end.column == 0 && end.line > start.line {
+        (start.line, end.line)
+    } else {
+        (start.line, end.line + 1)
+    }
+}
+
+// The run of non-blank lines around `line`, used when nothing is selected
+pub fn paragraph_bounds(lines: &[String], line: usize) -> (usize, usize) {
+    let is_blank = |idx: usize| lines[idx].trim().is_empty();
+    if line >= lines.len() || is_blank(line) {
+        return (line, line);
+    }
+    let start = (0..line).rev().take_while(|&idx| !is_blank(idx)).last().unwrap_or(line);
+    let end = (line..lines.len()).take_while(|&idx| !is_blank(idx)).last().unwrap_or(line) + 1;
+    (start, end)
+}
+
+// `=` when any line has one (assignments), otherwise `:` (key: value)
+pub fn guess_delimiter(lines: &[String]) -> &'static str {
+    if lines.iter().any(|line| line.contains('=')) { "=" } else { ":" }
+}
+
+// Pad each line at its first delimiter so the delimiters share a column.
+// Colons stay attached to the key ("a:    1"), other delimiters get a space on
+// both sides ("a    = 1"). Lines without the delimiter are left alone.
+pub fn align_on(lines: &[String], delimiter: &str) -> Vec<String> {
+    if delimiter.is_empty() {
+        return lines.to_vec();
+    }
+
+    let split: Vec<Option<(&str, &str)>> = lines
+        .iter()
+        .map(|line| {
+            line.split_once(delimiter)
+                .map(|(left, right)| (left.trim_end(), right.trim_start()))
+        })
+        .collect();
+    let width = split
+        .iter()
+        .flatten()
+        .map(|(left, _)| left.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    lines
+        .iter()
+        .zip(&split)
+        .map(|(line, parts)| match parts {
+            Some((left, right)) => {
+                let pad = " ".repeat(width - left.chars().count());
+                let aligned = if delimiter == ":" {
+                    format!("{}{}{} {}", left, delimiter, pad, right)
+                } else {
+                    format!("{}{} {} {}", left, pad, delimiter, right)
+                };
+                aligned.trim_end().to_string()
+            }
+            None => line.clone(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_align_on() {
+        let lines: Vec<String> = ["let a = 1;", "let total=2;", "// note", "  x  =  3"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(guess_delimiter(&lines), "=");
+        assert_eq!(
+            align_on(&lines, "="),
+            ["let a     = 1;", "let total = 2;", "// note", "  x       = 3"]
+        );
+
+        let yaml: Vec<String> = ["name: quick", "version: 1.0", "key:"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(align_on(&yaml, ":"), ["name:    quick", "version: 1.0", "key:"]);
+        assert_eq!(paragraph_bounds(&lines, 1), (0, 4));
+    }
+}
@@ -0,0 +1,74 @@
+// append module - add text to the end of a file, e.g. for a running notes or log file
+use crate::core::paths;
+use crate::core::crypto;
+use crate::core::timestamp;
+use std::fs::{self, OpenOptions};
+use std::io::{Error, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Separator line from the config template, with {timestamp} filled in (local time)
+pub fn separator(template: &str, secs: u64) -> String {
+    template.replace("{timestamp}", &timestamp::format_readable(secs))
+}
+
+// What goes on the end of a file: a line break if it lacks one, the separator line, then the text
+fn addition(existing: &[u8], text: &str, separator: &str) -> String {
+    let mut out = String::new();
+    if existing.last().is_some_and(|&b| b != b'\n') {
+        out.push('\n');
+    }
+    if !separator.is_empty() {
+        out.push_str(separator);
+        out.push('\n');
+    }
+    out.push_str(text);
+    if !text.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+// Append `text` to the file at `input` (created if missing), under a separator from `template`
+pub fn append_to_file(input: &str, text: &str, template: &str) -> Result<(), Error> {
+    let path = paths::resolve(input);
+    crypto::check_plaintext_path(&path.to_string_lossy())?;
+    let existing = fs::read(&path).unwrap_or_default();
+    if crypto::is_encrypted(&existing) {
+        return Err(Error::new(std::io::ErrorKind::InvalidData, "Cannot append to an encrypted note"));
+    }
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let addition = addition(&existing, text, &separator(template, secs));
+    OpenOptions::new().create(true).append(true).open(path)?.write_all(addition.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_addition() {
+        assert_eq!(separator("--- {timestamp} ---", 1_709_210_096), format!("--- {} ---", timestamp::format_readable(1_709_210_096)));
+        assert_eq!(addition(b"", "note", ""), "note\n");
+        assert_eq!(addition(b"old\n", "note", "--"), "--\nnote\n");
+        assert_eq!(addition(b"old", "a\nb\n", "--"), "\n--\na\nb\n");
+    }
+
+    #[test]
+    fn test_append_refuses_new_encrypted_notes() {
+        let dir = std::env::temp_dir().join(format!("quicknotepad-append-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let note = dir.join("log.qnenc");
+
+        let error = append_to_file(&note.to_string_lossy(), "plain", "").unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::PermissionDenied);
+        assert!(!note.exists());
+        fs::write(&note, "").unwrap();
+        assert!(append_to_file(&note.to_string_lossy(), "plain", "").is_err());
+        assert_eq!(fs::read(&note).unwrap(), b"");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
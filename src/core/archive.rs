@@ -0,0 +1,237 @@
+// archive module - files inside .zip, .tar and .tar.gz archives, listed when the archive is
+// opened and edited in a tab of their own. Saving such a tab rewrites the archive with the
+// new text in place of the old entry, every other entry copied over unchanged
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use std::fs::{self, File};
+use std::io::{Error, ErrorKind, Read, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+// A file inside an archive, what an archive tab saves back into
+#[derive(Clone, Debug, PartialEq)]
+pub struct Member {
+    pub archive: String, // Full path of the archive
+    pub name: String,    // Path of the entry inside it
+}
+
+enum Format {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+fn format(path: &str) -> Option<Format> {
+    let lower = path.to_lowercase();
+    if lower.ends_with(".zip") {
+        Some(Format::Zip)
+    } else if lower.ends_with(".tar.gz") || lower.ends_with(".tgz") {
+        Some(Format::TarGz)
+    } else if lower.ends_with(".tar") {
+        Some(Format::Tar)
+    } else {
+        None
+    }
+}
+
+pub fn is_archive(path: &str) -> bool {
+    format(path).is_some()
+}
+
+// Files in the archive in their stored order, folders left out
+pub fn list(path: &str) -> Result<Vec<String>, Error> {
+    let format = format(path).ok_or_else(|| not_an_archive(path))?;
+    let mut names = Vec::new();
+    match format {
+        Format::Zip => {
+            let mut zip = ZipArchive::new(File::open(path)?).map_err(Error::other)?;
+            for i in 0..zip.len() {
+                let file = zip.by_index_raw(i).map_err(Error::other)?;
+                if file.is_file() {
+                    names.push(file.name().to_string());
+                }
+            }
+        }
+        Format::Tar | Format::TarGz => {
+            for (header, name, _) in read_tar(path, matches!(format, Format::TarGz))? {
+                if header.entry_type().is_file() {
+                    names.push(name);
+                }
+            }
+        }
+    }
+    Ok(names)
+}
+
+// The entry's text; binary entries are refused like any other binary file
+pub fn read(member: &Member) -> Result<String, Error> {
+    let bytes = match format(&member.archive).ok_or_else(|| not_an_archive(&member.archive))? {
+        Format::Zip => {
+            let mut zip = ZipArchive::new(File::open(&member.archive)?).map_err(Error::other)?;
+            let mut file = zip.by_name(&member.name).map_err(|_| missing(member))?;
+            let mut bytes = Vec::new();
+            file.read_to_end(&mut bytes)?;
+            bytes
+        }
+        format => read_tar(&member.archive, matches!(format, Format::TarGz))?
+            .into_iter()
+            .find(|(header, name, _)| header.entry_type().is_file() && *name == member.name)
+            .map(|(_, _, data)| data)
+            .ok_or_else(|| missing(member))?,
+    };
+    if bytes.contains(&0) {
+        return Err(Error::new(ErrorKind::InvalidData, format!("{} is not a text file", member.name)));
+    }
+    String::from_utf8(bytes).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+// Replace the entry's contents with `text`. The new archive is written next to the old one
+// and renamed over it, so a failed write leaves the original untouched
+pub fn write(member: &Member, text: &str) -> Result<(), Error> {
+    let format = format(&member.archive).ok_or_else(|| not_an_archive(&member.archive))?;
+    let temp = format!("{}.quick-tmp", member.archive);
+    let written = match format {
+        Format::Zip => write_zip(member, text, &temp),
+        Format::Tar => write_tar(member, text, File::create(&temp)?).map(drop),
+        Format::TarGz => {
+            let gz = GzEncoder::new(File::create(&temp)?, flate2::Compression::default());
+            write_tar(member, text, gz)?.finish().map(drop)
+        }
+    };
+    match written {
+        Ok(()) => fs::rename(&temp, &member.archive),
+        Err(e) => {
+            let _ = fs::remove_file(&temp);
+            Err(e)
+        }
+    }
+}
+
+fn write_zip(member: &Member, text: &str, temp: &str) -> Result<(), Error> {
+    let mut zip = ZipArchive::new(File::open(&member.archive)?).map_err(Error::other)?;
+    let mut out = ZipWriter::new(File::create(temp)?);
+    let mut found = false;
+    for i in 0..zip.len() {
+        let file = zip.by_index_raw(i).map_err(Error::other)?;
+        if file.name() != member.name {
+            out.raw_copy_file(file).map_err(Error::other)?;
+            continue;
+        }
+        // Keep the entry where it was, compressed the same way
+        let options = SimpleFileOptions::default()
+            .compression_method(file.compression())
+            .unix_permissions(file.unix_mode().unwrap_or(0o644));
+        drop(file);
+        out.start_file(member.name.as_str(), options).map_err(Error::other)?;
+        out.write_all(text.as_bytes())?;
+        found = true;
+    }
+    if !found {
+        return Err(missing(member));
+    }
+    out.finish().map_err(Error::other)?;
+    Ok(())
+}
+
+fn write_tar<W: Write>(member: &Member, text: &str, out: W) -> Result<W, Error> {
+    let gzipped = matches!(format(&member.archive), Some(Format::TarGz));
+    let entries = read_tar(&member.archive, gzipped)?;
+    if !entries.iter().any(|(header, name, _)| header.entry_type().is_file() && *name == member.name) {
+        return Err(missing(member));
+    }
+    let mut builder = tar::Builder::new(out);
+    for (mut header, name, data) in entries {
+        let data = if header.entry_type().is_file() && name == member.name { text.as_bytes().to_vec() } else { data };
+        header.set_size(data.len() as u64);
+        // append_data writes long names as extra GNU entries, so they survive the rewrite
+        builder.append_data(&mut header, &name, data.as_slice())?;
+    }
+    builder.into_inner()
+}
+
+// Every entry with its header, path and data. Archives are read whole: they are opened
+// to edit a file inside, not to stream gigabytes
+fn read_tar(path: &str, gzipped: bool) -> Result<Vec<(tar::Header, String, Vec<u8>)>, Error> {
+    let file = File::open(path)?;
+    let reader: Box<dyn Read> = if gzipped { Box::new(GzDecoder::new(file)) } else { Box::new(file) };
+    let mut tar = tar::Archive::new(reader);
+    let mut entries = Vec::new();
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().into_owned();
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data)?;
+        entries.push((entry.header().clone(), name, data));
+    }
+    Ok(entries)
+}
+
+// "notes.zip/docs/todo.md", the name an archive tab shows
+pub fn display_name(member: &Member) -> String {
+    let archive = Path::new(&member.archive).file_name().map_or(member.archive.clone(), |n| n.to_string_lossy().into_owned());
+    format!("{}/{}", archive, member.name)
+}
+
+fn not_an_archive(path: &str) -> Error {
+    Error::new(ErrorKind::InvalidInput, format!("{} is not a .zip, .tar or .tar.gz archive", path))
+}
+
+fn missing(member: &Member) -> Error {
+    Error::new(ErrorKind::NotFound, format!("{} has no file {}", member.archive, member.name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_inside_zip_and_tar_gz() {
+        let dir = std::env::temp_dir().join(format!("quick_archive_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let zip_path = dir.join("notes.zip").to_string_lossy().into_owned();
+        let mut zip = ZipWriter::new(File::create(&zip_path).unwrap());
+        zip.add_directory("docs/", SimpleFileOptions::default()).unwrap();
+        zip.start_file("docs/todo.md", SimpleFileOptions::default()).unwrap();
+        zip.write_all(b"- milk").unwrap();
+        zip.start_file("logo.png", SimpleFileOptions::default()).unwrap();
+        zip.write_all(b"\x89PNG\0\0").unwrap();
+        zip.finish().unwrap();
+
+        let tar_path = dir.join("site.tgz").to_string_lossy().into_owned();
+        let mut tar = tar::Builder::new(GzEncoder::new(File::create(&tar_path).unwrap(), flate2::Compression::default()));
+        let long_name = format!("{}/index.html", "nested".repeat(20));
+        for (name, data) in [("README", "read me"), (long_name.as_str(), "<p>hi</p>")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_mode(0o644);
+            header.set_size(data.len() as u64);
+            tar.append_data(&mut header, name, data.as_bytes()).unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap();
+
+        for path in [&zip_path, &tar_path] {
+            let names = list(path).unwrap();
+            let member = Member { archive: path.clone(), name: names[names.len() - 1].clone() };
+            if path == &zip_path {
+                assert_eq!(names, ["docs/todo.md", "logo.png"]);
+                assert!(read(&member).is_err()); // Binary
+                let member = Member { name: names[0].clone(), ..member };
+                write(&member, "- milk\n- eggs").unwrap();
+                assert_eq!(read(&member).unwrap(), "- milk\n- eggs");
+                assert_eq!(display_name(&member), "notes.zip/docs/todo.md");
+            } else {
+                assert_eq!(names, ["README", long_name.as_str()]);
+                write(&member, "<p>hello</p>").unwrap();
+                assert_eq!(read(&member).unwrap(), "<p>hello</p>");
+                let readme = Member { name: "README".to_string(), ..member };
+                assert_eq!(read(&readme).unwrap(), "read me");
+            }
+            assert_eq!(list(path).unwrap(), names);
+            let gone = Member { archive: path.clone(), name: "nope.txt".to_string() };
+            assert_eq!(write(&gone, "x").unwrap_err().kind(), ErrorKind::NotFound);
+        }
+        assert!(is_archive("backup.TAR.GZ") && !is_archive("notes.md"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
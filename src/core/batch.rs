@@ -0,0 +1,171 @@
+// batch module - non-interactive editing for shell pipelines: `quick --print 10:20 file`
+// prints a line range, `quick --replace 's/foo/bar/g' file` patches files in place.
+// Without a file both work on stdin and write to stdout
+use crate::core::config::Config;
+use crate::core::crypto;
+use crate::core::history;
+use crate::core::replace::Substitution;
+use std::fs;
+use std::io::{Read, Write};
+
+// 1-based, inclusive line range: "10:20", "10:" to the end, ":20" from the start or just "10"
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineRange {
+    pub first: usize,
+    pub last: Option<usize>,
+}
+
+impl LineRange {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let number = |part: &str| -> Result<Option<usize>, String> {
+            match part.trim() {
+                "" => Ok(None),
+                n => n.parse::<usize>().ok().filter(|&n| n > 0).map(Some).ok_or(format!("'{}' is not a line number", n)),
+            }
+        };
+        let (first, last) = match text.split_once(':') {
+            Some((first, last)) => (number(first)?.unwrap_or(1), number(last)?),
+            None => {
+                let line = number(text)?.ok_or("Missing line number")?;
+                (line, Some(line))
+            }
+        };
+        if last.is_some_and(|last| last < first) {
+            return Err(format!("Range {} ends before it starts", text));
+        }
+        Ok(Self { first, last })
+    }
+
+    pub fn contains(&self, line_number: usize) -> bool {
+        line_number >= self.first && self.last.is_none_or(|last| line_number <= last)
+    }
+}
+
+struct Options {
+    print: Option<LineRange>,
+    replace: Option<Substitution>,
+    lines: Option<LineRange>, // Lines --replace is limited to
+    files: Vec<String>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Options { print: None, replace: None, lines: None, files: Vec::new() };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+            match arg.as_str() {
+                "--print" => options.print = Some(LineRange::parse(value()?)?),
+                "--replace" => options.replace = Some(Substitution::parse(value()?)?),
+                "--lines" => options.lines = Some(LineRange::parse(value()?)?),
+                flag if flag.starts_with("--") => return Err(format!("{} can't be combined with --print or --replace", flag)),
+                file => options.files.push(file.to_string()),
+            }
+        }
+        Ok(options)
+    }
+
+    // What to print or write for `text`, with the number of replacements made
+    fn output(&self, text: &str) -> (String, usize) {
+        let (text, count) = match &self.replace {
+            Some(substitution) => replace(text, substitution, self.lines.as_ref()),
+            None => (text.to_string(), 0),
+        };
+        match &self.print {
+            Some(range) => (print(&text, range), count),
+            None => (text, count),
+        }
+    }
+}
+
+// Each line with its line ending ("\n", "\r\n" or none at the end of the text)
+fn split_lines(text: &str) -> impl Iterator<Item = (&str, &str)> {
+    text.split_inclusive('\n').map(|line| {
+        let body = line.trim_end_matches('\n').trim_end_matches('\r');
+        (body, &line[body.len()..])
+    })
+}
+
+// The lines of `text` in `range`, line endings kept
+pub fn print(text: &str, range: &LineRange) -> String {
+    split_lines(text)
+        .enumerate()
+        .filter(|(i, _)| range.contains(i + 1))
+        .map(|(_, (body, ending))| format!("{}{}", body, ending))
+        .collect()
+}
+
+// `text` with the substitution made on every line in `range`, and the number of replacements
+pub fn replace(text: &str, substitution: &Substitution, range: Option<&LineRange>) -> (String, usize) {
+    let mut count = 0;
+    let result = split_lines(text)
+        .enumerate()
+        .map(|(i, (body, ending))| {
+            if range.is_some_and(|range| !range.contains(i + 1)) {
+                return format!("{}{}", body, ending);
+            }
+            let (body, replaced) = substitution.apply(body);
+            count += replaced;
+            format!("{}{}", body, ending)
+        })
+        .collect();
+    (result, count)
+}
+
+// Run the command line (without the program name). Replacing with --print as well shows the
+// patched lines instead of writing the files
+pub fn run(args: &[String]) -> Result<(), String> {
+    let options = Options::parse(args)?;
+    let mut stdout = std::io::stdout();
+
+    if options.files.is_empty() {
+        let mut text = String::new();
+        std::io::stdin().read_to_string(&mut text).map_err(|e| format!("Cannot read stdin: {}", e))?;
+        let output = options.output(&text).0;
+        return stdout.write_all(output.as_bytes()).map_err(|e| e.to_string());
+    }
+
+    let file_history = Config::load().file_history;
+    for path in &options.files {
+        if crypto::is_encrypted_path(path) {
+            return Err(format!("{}: encrypted notes can only be opened in the editor", path));
+        }
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let (output, count) = options.output(&text);
+        if options.print.is_some() {
+            stdout.write_all(output.as_bytes()).map_err(|e| e.to_string())?;
+        } else if count > 0 {
+            if file_history {
+                let _ = history::backup(path);
+            }
+            fs::write(path, output).map_err(|e| format!("{}: {}", path, e))?;
+        }
+        if options.replace.is_some() {
+            eprintln!("{}: {} replacement{}", path, count, if count == 1 { "" } else { "s" });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_print_and_replace_ranges() {
+        assert_eq!(LineRange::parse("2:3"), Ok(LineRange { first: 2, last: Some(3) }));
+        assert_eq!(LineRange::parse("4"), Ok(LineRange { first: 4, last: Some(4) }));
+        assert_eq!(LineRange::parse(":2"), Ok(LineRange { first: 1, last: Some(2) }));
+        assert_eq!(LineRange::parse("3:"), Ok(LineRange { first: 3, last: None }));
+        assert!(LineRange::parse("5:2").is_err());
+        assert!(LineRange::parse("0").is_err());
+
+        let text = "one foo\r\ntwo foo\nthree foo";
+        assert_eq!(print(text, &LineRange::parse("2:").unwrap()), "two foo\nthree foo");
+
+        let sub = Substitution::parse("s/foo/bar/").unwrap();
+        let (patched, count) = replace(text, &sub, Some(&LineRange::parse(":2").unwrap()));
+        assert_eq!(patched, "one bar\r\ntwo bar\nthree foo");
+        assert_eq!(count, 2);
+    }
+}
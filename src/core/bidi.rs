@@ -0,0 +1,119 @@
+// bidi module - display order for lines mixing left-to-right and right-to-left text (UAX #9,
+// through unicode-bidi)
+use unicode_bidi::{bidi_class, BidiClass, BidiInfo};
+use unicode_segmentation::UnicodeSegmentation;
+use crate::core::display;
+
+pub fn has_rtl(text: &str) -> bool {
+    text.chars().any(|c| matches!(bidi_class(c), BidiClass::R | BidiClass::AL))
+}
+
+// A grapheme as shown on screen
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cell<'a> {
+    pub column: usize, // Char column of the grapheme's first char
+    pub text: &'a str,
+    pub rtl: bool,
+}
+
+// Graphemes of `line` in display order, so combining marks stay on their letters
+pub fn visual_line(line: &str) -> Vec<Cell<'_>> {
+    let mut cells: Vec<Cell> = Vec::new();
+    let mut column = 0;
+    for text in line.graphemes(true) {
+        cells.push(Cell { column, text, rtl: false });
+        column += text.chars().count();
+    }
+    if !has_rtl(line) {
+        return cells;
+    }
+
+    // Runs come back in display order as byte ranges, each whole graphemes as marks share their
+    // letter's level; right-to-left runs show their graphemes backwards
+    let info = BidiInfo::new(line, None);
+    let Some(paragraph) = info.paragraphs.first() else {
+        return cells;
+    };
+    let (levels, runs) = info.visual_runs(paragraph, paragraph.range.clone());
+    let starts: Vec<usize> = line.grapheme_indices(true).map(|(i, _)| i).collect();
+    let mut shown = Vec::with_capacity(cells.len());
+    for run in runs {
+        let rtl = levels[run.start].is_rtl();
+        let first = starts.partition_point(|&i| i < run.start);
+        let last = starts.partition_point(|&i| i < run.end);
+        let run_cells = cells[first..last].iter().map(|cell| Cell { rtl, ..cell.clone() });
+        match rtl {
+            true => shown.extend(run_cells.rev()),
+            false => shown.extend(run_cells),
+        }
+    }
+    shown
+}
+
+
+pub fn width(text: &str) -> usize {
+    display::str_width(text).max(1)
+}
+
+// Screen cell where a caret before char `column` is drawn
+pub fn caret_cell(line: &str, column: usize) -> usize {
+    let cells = visual_line(line);
+    let left = |v: usize| cells[..v].iter().map(|c| width(c.text)).sum::<usize>();
+    let next = cells.iter().enumerate().filter(|(_, c)| c.column >= column).min_by_key(|(_, c)| c.column);
+    match next {
+        // Leading edge of the grapheme after the caret
+        Some((v, cell)) => left(if cell.rtl { v + 1 } else { v }),
+        // Trailing edge of the last grapheme
+        None => match cells.iter().enumerate().max_by_key(|(_, c)| c.column) {
+            Some((v, cell)) => left(if cell.rtl { v } else { v + 1 }),
+            None => 0,
+        },
+    }
+}
+
+// Char column for a caret placed at the left edge of screen cell `cell`
+pub fn column_at_cell(line: &str, cell: usize) -> usize {
+    let cells = visual_line(line);
+    let mut x = 0;
+    for c in &cells {
+        if cell <= x {
+            return if c.rtl { c.column + c.text.chars().count() } else { c.column };
+        }
+        x += width(c.text);
+    }
+    match cells.last() {
+        Some(c) if c.rtl => c.column,
+        _ => line.chars().count(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shown(line: &str) -> String {
+        visual_line(line).iter().map(|c| c.text).collect()
+    }
+
+    #[test]
+    fn test_visual_order_and_caret() {
+        assert_eq!(shown("plain text"), "plain text");
+        assert_eq!(shown("abc אבג def"), "abc גבא def");
+        // Numbers keep their order inside right-to-left text
+        assert_eq!(shown("אב 123 גד"), "דג 123 בא");
+        // A right-to-left paragraph puts embedded English runs right to left
+        assert_eq!(shown("שלום world!"), "!world םולש");
+        // Combining marks stay after their letter
+        assert_eq!(shown("א\u{05B8}ב"), "בא\u{05B8}");
+
+        // Logical caret positions walk the Hebrew run from its right edge
+        let line = "ab אבג";
+        assert_eq!(caret_cell(line, 0), 0);
+        assert_eq!(caret_cell(line, 3), 6);
+        assert_eq!(caret_cell(line, 4), 5);
+        assert_eq!(caret_cell(line, 6), 3);
+        assert_eq!(column_at_cell(line, 6), 3);
+        assert_eq!(column_at_cell(line, 5), 4);
+        assert_eq!(column_at_cell(line, 1), 1);
+    }
+}
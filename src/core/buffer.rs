@@ -1,12 +1,25 @@
 // buffer module responsible for buffer size
+use crate::core::progress::Progress;
+use std::io::{BufWriter, Error, Write};
+
+// Empty lines kept after the text so the caret can move and type below it
+const PADDING: usize = 500;
+
 #[derive(Clone)]
 pub struct Buffer {
     pub lines: Vec<String>,
+    pub final_newline: bool, // The file ended with a line break, written back on save
+    pub crlf: bool,          // Lines were broken with "\r\n", which saving keeps
 }
 
 impl Buffer {
     // handle loading a file
     pub fn from_string(content: String) -> Self {
+        // lines() drops the empty piece after a final line break, so "a\n\n" is "a" and one
+        // blank line, and final_newline brings the break back on save
+        let final_newline = content.ends_with('\n');
+        // The first line break decides how all of them are written back
+        let crlf = content.find('\n').is_some_and(|i| content[..i].ends_with('\r'));
         let mut lines: Vec<String> = content.lines().map(|line| line.to_string()).collect();
 
         // Ensure there is at least one line if the file is empty
@@ -15,11 +28,95 @@ impl Buffer {
         }
         
         // Add buffer space for expansion (500 empty lines after content)
-        for _ in 0..500 {
+        for _ in 0..PADDING {
             lines.push(String::new());
         }
         
-        Self { lines }
+        Self { lines, final_newline, crlf }
+    }
+
+    // The text without blank lines at the end, for comparing and sharing; saves use file_text
+    pub fn content(&self) -> String {
+        let last_line = self
+            .lines
+            .iter()
+            .rposition(|line| !line.is_empty())
+            .unwrap_or(0);
+        let content_lines: Vec<String> = self.lines.iter().take(last_line + 1).cloned().collect();
+        content_lines.join("\n")
+    }
+
+    // The text as it goes to disk: blank lines at the end kept as typed, and a final line
+    // break when the file had one or `insert_final_newline` asks for it
+    pub fn file_text(&self, insert_final_newline: bool) -> String {
+        let mut text = self.lines[..self.logical_len()].join(self.line_ending());
+        if self.ends_with_break(insert_final_newline) {
+            text.push_str(self.line_ending());
+        }
+        text
+    }
+
+    // file_text streamed to `writer` a line at a time, so saving a big file never holds a second
+    // copy of it. Progress counts the bytes written
+    pub fn write_text(&self, writer: impl Write, insert_final_newline: bool, progress: &Progress) -> Result<(), Error> {
+        let lines = &self.lines[..self.logical_len()];
+        let ending = self.line_ending().as_bytes();
+        progress.set_total(self.file_len(insert_final_newline) as u64);
+
+        let mut writer = BufWriter::with_capacity(256 * 1024, writer);
+        for (i, line) in lines.iter().enumerate() {
+            if i > 0 {
+                writer.write_all(ending)?;
+            }
+            writer.write_all(line.as_bytes())?;
+            progress.advance((line.len() + ending.len()) as u64);
+        }
+        if self.ends_with_break(insert_final_newline) {
+            writer.write_all(ending)?;
+        }
+        writer.flush()
+    }
+
+    // Write the text to a file the way write_text lays it out
+    pub fn save(&self, path: &str, insert_final_newline: bool, progress: &Progress) -> Result<(), Error> {
+        self.write_text(std::fs::File::create(path)?, insert_final_newline, progress)
+    }
+
+    // Bytes the text takes on disk, without building it
+    pub fn file_len(&self, insert_final_newline: bool) -> usize {
+        let lines = &self.lines[..self.logical_len()];
+        let breaks = lines.len() - 1 + self.ends_with_break(insert_final_newline) as usize;
+        lines.iter().map(String::len).sum::<usize>() + breaks * self.line_ending().len()
+    }
+
+    pub fn line_ending(&self) -> &'static str {
+        if self.crlf { "\r\n" } else { "\n" }
+    }
+
+    // A final line break goes out when the file had one, or when asked for and there is text
+    fn ends_with_break(&self, insert_final_newline: bool) -> bool {
+        let is_empty = self.logical_len() == 1 && self.lines.first().is_none_or(String::is_empty);
+        self.final_newline || (insert_final_newline && !is_empty)
+    }
+
+    // Number of lines that belong to the text, blank and whitespace-only ones included, for
+    // saving, line counts, select all and moving to the end. The padding stays PADDING lines
+    // long as lines are inserted and removed above it, so whatever is beyond it was added by
+    // the user - but never less than the last line with anything on it, in case padding was
+    // deleted into
+    pub fn logical_len(&self) -> usize {
+        let last_line = self.lines.iter().rposition(|line| !line.is_empty()).map_or(0, |i| i + 1);
+        self.lines.len().saturating_sub(PADDING).max(last_line).clamp(1, self.lines.len().max(1))
+    }
+
+    // Move the lines out without copying them, leaving no lines behind.
+    // A loaded buffer always has at least one line, so an empty one marks text that was moved away
+    pub fn take(&mut self) -> Buffer {
+        Buffer { lines: std::mem::take(&mut self.lines), final_newline: self.final_newline, crlf: self.crlf }
+    }
+
+    pub fn is_taken(&self) -> bool {
+        self.lines.is_empty()
     }
 }
 
@@ -27,9 +124,61 @@ impl Default for Buffer {
     fn default() -> Self {
         let mut lines = Vec::new();
         // generate 500 lines of Buffer
-        for _ in 0..500 {
+        for _ in 0..PADDING {
             lines.push(String::new());
         }
-        Self { lines }
+        Self { lines, final_newline: false, crlf: false }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_text_keeps_trailing_lines() {
+        for text in ["", "\n", "a", "a\n", "a\n\n\n", "a\n  \n", "\n\nb"] {
+            assert_eq!(Buffer::from_string(text.to_string()).file_text(false), text);
+        }
+        assert_eq!(Buffer::from_string("a".to_string()).file_text(true), "a\n");
+        assert_eq!(Buffer::default().file_text(true), "");
+
+        // Enter at the end of the text adds a blank line that is saved, Backspace takes it back
+        let mut buffer = Buffer::from_string("a\n".to_string());
+        buffer.lines.insert(1, String::new());
+        assert_eq!(buffer.file_text(false), "a\n\n");
+        buffer.lines.remove(1);
+        buffer.lines.remove(1); // Delete at the end of the text pulls up a padding line
+        assert_eq!(buffer.file_text(false), "a\n");
+
+        assert_eq!(buffer.logical_len(), 1);
+
+        // Typing far down in the padding keeps the blank lines above it
+        let mut buffer = Buffer::from_string("a".to_string());
+        buffer.lines[3] = "d".to_string();
+        assert_eq!(buffer.file_text(false), "a\n\n\nd");
+        assert_eq!(Buffer::from_string("\n  \n\n".to_string()).logical_len(), 3);
+        assert_eq!(Buffer::default().logical_len(), 1);
+    }
+
+    #[test]
+    fn test_streamed_save_matches_file_text() {
+        for text in ["", "\n", "a", "a\n", "a\r\nb\r\n\r\n", "a\r\nb\nc", "x\n  \n\n", "\r\n"] {
+            let buffer = Buffer::from_string(text.to_string());
+            for insert_final_newline in [false, true] {
+                let progress = Progress::default();
+                let mut written = Vec::new();
+                buffer.write_text(&mut written, insert_final_newline, &progress).unwrap();
+                let expected = buffer.file_text(insert_final_newline);
+                assert_eq!(String::from_utf8(written).unwrap(), expected);
+                assert_eq!(buffer.file_len(insert_final_newline), expected.len());
+                assert_eq!(progress.fraction().unwrap_or(1.0), 1.0);
+            }
+        }
+
+        // CRLF files keep their line endings, LF files theirs
+        assert_eq!(Buffer::from_string("a\r\nb\r\n".to_string()).file_text(false), "a\r\nb\r\n");
+        assert_eq!(Buffer::from_string("a\nb\r\n".to_string()).file_text(false), "a\nb\n");
+        assert_eq!(Buffer::from_string("a\r\nb".to_string()).file_text(true), "a\r\nb\r\n");
     }
 }
\ No newline at end of file
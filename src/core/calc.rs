@@ -0,0 +1,224 @@
+// calc module - evaluate arithmetic expressions for the calculator command
+use std::io::{Error, ErrorKind};
+
+// Evaluate e.g. "(2 + 3) * 0x10 / 2^3", supporting + - * / % ^ and parentheses
+pub fn evaluate(expression: &str) -> Result<f64, Error> {
+    let tokens = tokenize(expression)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let value = parser.sum()?;
+    if parser.pos < parser.tokens.len() {
+        return Err(invalid("Unexpected input after expression"));
+    }
+    if !value.is_finite() {
+        return Err(invalid("Result is not a finite number"));
+    }
+    Ok(value)
+}
+
+// Whole numbers print without a fraction, others with at most 10 decimals
+pub fn format_result(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        return format!("{}", value as i64);
+    }
+    let text = format!("{:.10}", value);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+// Text to add after an expression: "2 + 3" gets " = 5", "2 + 3 =" just " 5"
+pub fn appended_result(expression: &str, result: &str) -> String {
+    if !expression.trim_end().ends_with('=') {
+        format!(" = {}", result)
+    } else if expression.ends_with(' ') {
+        result.to_string()
+    } else {
+        format!(" {}", result)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token {
+    Number(f64),
+    Op(char),
+    Open,
+    Close,
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidInput, message.to_string())
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, Error> {
+    let chars: Vec<char> = expression.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            ' ' | '\t' | '\n' | '_' => i += 1,
+            '(' => {
+                tokens.push(Token::Open);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::Close);
+                i += 1;
+            }
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                tokens.push(Token::Op('^'));
+                i += 2;
+            }
+            '+' | '-' | '*' | '/' | '%' | '^' => {
+                tokens.push(Token::Op(c));
+                i += 1;
+            }
+            'x' | '×' => {
+                tokens.push(Token::Op('*'));
+                i += 1;
+            }
+            '0'..='9' | '.' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '.' || chars[i] == '_') {
+                    // "2x3" is multiplication, "0x1f" is hex
+                    if chars[i] == 'x' && !(i == start + 1 && chars[start] == '0') {
+                        break;
+                    }
+                    i += 1;
+                }
+                let literal: String = chars[start..i].iter().filter(|&&c| c != '_').collect();
+                tokens.push(Token::Number(parse_number(&literal)?));
+            }
+            _ => return Err(invalid(&format!("Unexpected character '{}'", c))),
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn parse_number(literal: &str) -> Result<f64, Error> {
+    let radix = |digits: &str, radix: u32| {
+        i64::from_str_radix(digits, radix)
+            .map(|v| v as f64)
+            .map_err(|_| invalid(&format!("Invalid number '{}'", literal)))
+    };
+    let lower = literal.to_ascii_lowercase();
+    if let Some(hex) = lower.strip_prefix("0x") {
+        radix(hex, 16)
+    } else if let Some(bin) = lower.strip_prefix("0b") {
+        radix(bin, 2)
+    } else if let Some(oct) = lower.strip_prefix("0o") {
+        radix(oct, 8)
+    } else {
+        lower
+            .parse::<f64>()
+            .map_err(|_| invalid(&format!("Invalid number '{}'", literal)))
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<Token> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek();
+        self.pos += 1;
+        token
+    }
+
+    // sum := product (('+' | '-') product)*
+    fn sum(&mut self) -> Result<f64, Error> {
+        let mut value = self.product()?;
+        while let Some(Token::Op(op @ ('+' | '-'))) = self.peek() {
+            self.pos += 1;
+            let rhs = self.product()?;
+            value = if op == '+' { value + rhs } else { value - rhs };
+        }
+        Ok(value)
+    }
+
+    // product := unary (('*' | '/' | '%') unary)*
+    fn product(&mut self) -> Result<f64, Error> {
+        let mut value = self.unary()?;
+        while let Some(Token::Op(op @ ('*' | '/' | '%'))) = self.peek() {
+            self.pos += 1;
+            let rhs = self.unary()?;
+            if op != '*' && rhs == 0.0 {
+                return Err(invalid("Division by zero"));
+            }
+            value = match op {
+                '*' => value * rhs,
+                '/' => value / rhs,
+                _ => value % rhs,
+            };
+        }
+        Ok(value)
+    }
+
+    // unary := ('-' | '+') unary | power
+    fn unary(&mut self) -> Result<f64, Error> {
+        match self.peek() {
+            Some(Token::Op('-')) => {
+                self.pos += 1;
+                Ok(-self.unary()?)
+            }
+            Some(Token::Op('+')) => {
+                self.pos += 1;
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+
+    // power := atom ('^' unary)?   (right associative, so 2^3^2 = 2^9)
+    fn power(&mut self) -> Result<f64, Error> {
+        let base = self.atom()?;
+        if let Some(Token::Op('^')) = self.peek() {
+            self.pos += 1;
+            let exponent = self.unary()?;
+            return Ok(base.powf(exponent));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<f64, Error> {
+        match self.next() {
+            Some(Token::Number(value)) => Ok(value),
+            Some(Token::Open) => {
+                let value = self.sum()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(value),
+                    _ => Err(invalid("Missing closing parenthesis")),
+                }
+            }
+            _ => Err(invalid("Expected a number")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate() {
+        let eval = |s: &str| format_result(evaluate(s).unwrap());
+        assert_eq!(eval("1 + 2 * 3"), "7");
+        assert_eq!(eval("(1 + 2) * 3"), "9");
+        assert_eq!(eval("-2^2 + 2^3^2"), "508");
+        assert_eq!(eval("0xff + 0b1010 - 0o7"), "258");
+        assert_eq!(eval("10 / 4"), "2.5");
+        assert_eq!(eval("1_000 x 3"), "3000");
+        assert_eq!(eval("1/3"), "0.3333333333");
+        assert!(evaluate("1 / 0").is_err());
+        assert!(evaluate("2 +").is_err());
+        assert!(evaluate("(1").is_err());
+        assert_eq!(appended_result("2 + 3", "5"), " = 5");
+        assert_eq!(appended_result("2 + 3 =", "5"), " 5");
+    }
+}
@@ -0,0 +1,555 @@
+// collab module - experimental shared editing over TCP. `quick --host file` serves the text and
+// `quick --join address` edits it from elsewhere. The host orders every edit and the clients
+// rebase their unacknowledged ones over what arrives in between (operational transformation),
+// so everyone ends up with the same text. Anyone who can reach the port can edit: trusted
+// networks only
+use crate::core::buffer::Buffer;
+use crate::core::selection::TextPosition;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Error, ErrorKind, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+
+// Participants' carets, picked by id
+pub const PEER_COLORS: [(u8, u8, u8); 6] = [
+    (230, 90, 90),
+    (90, 170, 230),
+    (120, 200, 90),
+    (230, 170, 60),
+    (190, 110, 220),
+    (70, 200, 180),
+];
+
+// One step over the text, counted in chars
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Component {
+    Retain(usize),
+    Insert(String),
+    Delete(usize),
+}
+
+impl Component {
+    fn len(&self) -> usize {
+        match self {
+            Component::Retain(n) | Component::Delete(n) => *n,
+            Component::Insert(text) => text.chars().count(),
+        }
+    }
+
+    // What is left of a retain or delete after `n` chars of it are used
+    fn shrink(self, n: usize) -> Option<Component> {
+        match self {
+            Component::Retain(m) if m > n => Some(Component::Retain(m - n)),
+            Component::Delete(m) if m > n => Some(Component::Delete(m - n)),
+            _ => None,
+        }
+    }
+}
+
+// An edit as a walk over the whole text: keep, insert or delete
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Operation(Vec<Component>);
+
+impl Operation {
+    // The single replacement turning `old` into `new`, found by trimming what they share at both ends
+    pub fn diff(old: &str, new: &str) -> Self {
+        let old: Vec<char> = old.chars().collect();
+        let new: Vec<char> = new.chars().collect();
+        let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+        let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+        let mut op = Self::default();
+        op.retain(prefix);
+        op.delete(old.len() - prefix - suffix);
+        op.insert(&new[prefix..new.len() - suffix].iter().collect::<String>());
+        op.retain(suffix);
+        op
+    }
+
+    // Length of the text this applies to
+    pub fn base_len(&self) -> usize {
+        self.0.iter().filter(|c| !matches!(c, Component::Insert(_))).map(Component::len).sum()
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        let mut chars = text.chars();
+        let mut result = String::with_capacity(text.len());
+        for component in &self.0 {
+            match component {
+                Component::Retain(n) => result.extend(chars.by_ref().take(*n)),
+                Component::Delete(n) => chars.by_ref().take(*n).for_each(drop),
+                Component::Insert(inserted) => result.push_str(inserted),
+            }
+        }
+        result.extend(chars);
+        result
+    }
+
+    // Where the char at `offset` ends up once this is applied. Text inserted right at it goes after
+    pub fn map(&self, offset: usize) -> usize {
+        let (mut old, mut new) = (0, 0);
+        for component in &self.0 {
+            match component {
+                Component::Retain(n) if old + n > offset => return new + offset - old,
+                Component::Delete(n) if old + n > offset => return new,
+                Component::Insert(_) if old == offset => return new,
+                Component::Retain(n) => (old, new) = (old + n, new + n),
+                Component::Delete(n) => old += n,
+                Component::Insert(text) => new += text.chars().count(),
+            }
+        }
+        new + offset - old
+    }
+
+    fn retain(&mut self, n: usize) {
+        match self.0.last_mut() {
+            _ if n == 0 => {}
+            Some(Component::Retain(m)) => *m += n,
+            _ => self.0.push(Component::Retain(n)),
+        }
+    }
+
+    fn insert(&mut self, text: &str) {
+        match self.0.last_mut() {
+            _ if text.is_empty() => {}
+            Some(Component::Insert(last)) => last.push_str(text),
+            _ => self.0.push(Component::Insert(text.to_string())),
+        }
+    }
+
+    fn delete(&mut self, n: usize) {
+        match self.0.last_mut() {
+            _ if n == 0 => {}
+            Some(Component::Delete(m)) => *m += n,
+            _ => self.0.push(Component::Delete(n)),
+        }
+    }
+}
+
+// Rewrite two edits made to the same text so each applies after the other, giving the same
+// result either way: first then second' equals second then first'. Returns (first', second').
+// The host's edit is always `first`, so its insertions go first when both insert at one spot
+pub fn transform(first: &Operation, second: &Operation) -> (Operation, Operation) {
+    let (mut first_after, mut second_after) = (Operation::default(), Operation::default());
+    let (mut a_rest, mut b_rest) = (first.0.iter().cloned(), second.0.iter().cloned());
+    let (mut a, mut b) = (a_rest.next(), b_rest.next());
+    loop {
+        match (a.take(), b.take()) {
+            (Some(Component::Insert(text)), other) => {
+                second_after.retain(text.chars().count());
+                first_after.insert(&text);
+                (a, b) = (a_rest.next(), other);
+            }
+            (other, Some(Component::Insert(text))) => {
+                first_after.retain(text.chars().count());
+                second_after.insert(&text);
+                (a, b) = (other, b_rest.next());
+            }
+            (Some(x), Some(y)) => {
+                let n = x.len().min(y.len());
+                match (&x, &y) {
+                    (Component::Retain(_), Component::Retain(_)) => {
+                        first_after.retain(n);
+                        second_after.retain(n);
+                    }
+                    (Component::Delete(_), Component::Retain(_)) => first_after.delete(n),
+                    (Component::Retain(_), Component::Delete(_)) => second_after.delete(n),
+                    _ => {} // Both deleted the same chars
+                }
+                a = x.shrink(n).or_else(|| a_rest.next());
+                b = y.shrink(n).or_else(|| b_rest.next());
+            }
+            _ => break, // Done, or the two were made to texts of different lengths
+        }
+    }
+    (first_after, second_after)
+}
+
+// What goes over the wire, one JSON object per line
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Message {
+    Welcome { id: usize, revision: usize, text: String },
+    // From a client: an edit to the text at `revision`. From the host: the edit making `revision`
+    Edit { revision: usize, op: Operation },
+    Ack,
+    Caret { id: usize, name: String, offset: usize },
+    Left { id: usize },
+}
+
+fn send(stream: &mut TcpStream, message: &Message) -> Result<(), Error> {
+    let line = serde_json::to_string(message).map_err(Error::other)?;
+    writeln!(stream, "{}", line)
+}
+
+// Read messages off `stream` on a thread; `wrap` turns each into what the receiver expects,
+// and `closed` is sent once when the connection ends
+fn read_messages<T: Send + 'static>(stream: TcpStream, tx: Sender<T>, wrap: impl Fn(Message) -> T + Send + 'static, closed: T) {
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else { break };
+            // Anything unreadable is skipped rather than ending the session
+            if let Ok(message) = serde_json::from_str(&line) {
+                if tx.send(wrap(message)).is_err() {
+                    return;
+                }
+            }
+        }
+        let _ = tx.send(closed);
+    });
+}
+
+enum HostEvent {
+    Joined(TcpStream),
+    Got(usize, Message),
+    Left(usize),
+}
+
+// The host's side: one thread owning the text and the order of edits
+fn serve(listener: TcpListener, mut text: String) {
+    let (tx, rx) = mpsc::channel();
+    let accept = tx.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if accept.send(HostEvent::Joined(stream)).is_err() {
+                return;
+            }
+        }
+    });
+    thread::spawn(move || {
+        let mut clients: HashMap<usize, TcpStream> = HashMap::new();
+        let mut carets: HashMap<usize, Message> = HashMap::new();
+        let mut history: Vec<Operation> = Vec::new();
+        let mut next_id = 0;
+        for event in rx {
+            match event {
+                HostEvent::Joined(mut stream) => {
+                    let id = next_id;
+                    next_id += 1;
+                    let Ok(reader) = stream.try_clone() else { continue };
+                    let welcome = Message::Welcome { id, revision: history.len(), text: text.clone() };
+                    if send(&mut stream, &welcome).is_err() {
+                        continue;
+                    }
+                    for caret in carets.values() {
+                        let _ = send(&mut stream, caret);
+                    }
+                    read_messages(reader, tx.clone(), move |message| HostEvent::Got(id, message), HostEvent::Left(id));
+                    clients.insert(id, stream);
+                }
+                HostEvent::Got(id, Message::Edit { revision, mut op }) => {
+                    // Rebase the edit over the ones the client hadn't seen yet
+                    for earlier in history.get(revision..).unwrap_or_default() {
+                        op = transform(earlier, &op).1;
+                    }
+                    if revision > history.len() || op.base_len() != text.chars().count() {
+                        continue;
+                    }
+                    text = op.apply(&text);
+                    history.push(op.clone());
+                    let edit = Message::Edit { revision: history.len(), op };
+                    for (&other, stream) in clients.iter_mut() {
+                        let _ = send(stream, if other == id { &Message::Ack } else { &edit });
+                    }
+                }
+                HostEvent::Got(id, Message::Caret { name, offset, .. }) => {
+                    let caret = Message::Caret { id, name, offset };
+                    for (_, stream) in clients.iter_mut().filter(|(&other, _)| other != id) {
+                        let _ = send(stream, &caret);
+                    }
+                    carets.insert(id, caret);
+                }
+                HostEvent::Got(..) => {}
+                // The host's own connection is the first; without it the session is over
+                HostEvent::Left(0) => {
+                    for stream in clients.values() {
+                        let _ = stream.shutdown(Shutdown::Both);
+                    }
+                    return;
+                }
+                HostEvent::Left(id) => {
+                    clients.remove(&id);
+                    carets.remove(&id);
+                    for stream in clients.values_mut() {
+                        let _ = send(stream, &Message::Left { id });
+                    }
+                }
+            }
+        }
+    });
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Peer {
+    pub id: usize,
+    pub name: String,
+    pub position: TextPosition, // Char column
+    offset: usize,
+}
+
+impl Peer {
+    pub fn color(&self) -> (u8, u8, u8) {
+        PEER_COLORS[self.id % PEER_COLORS.len()]
+    }
+}
+
+// What the command line asked for
+#[derive(Clone, Debug, PartialEq)]
+pub enum Role {
+    Host,         // --host: share the file being opened
+    Join(String), // --join address
+}
+
+impl Role {
+    pub fn from_args(args: &[String]) -> Option<Self> {
+        if let Some(i) = args.iter().position(|arg| arg == "--join") {
+            return args.get(i + 1).map(|address| Role::Join(address.clone()));
+        }
+        args.iter().any(|arg| arg == "--host").then_some(Role::Host)
+    }
+}
+
+// One participant's connection, kept on the shared tab
+pub struct Session {
+    stream: TcpStream,
+    incoming: Receiver<Option<Message>>, // None once the connection is gone
+    name: String,
+    revision: usize,         // Host edits applied so far
+    synced: String,          // Text with everything applied: the host's edits and our own
+    sent: Option<Operation>, // Our edit the host hasn't acknowledged yet
+    queued: Vec<Operation>,  // Our edits made since, sent one at a time after it
+    caret: Option<usize>,    // Caret offset last told to the others
+    peers: Vec<Peer>,
+    pub address: String,     // Where to join, shown to the host and in the tab name
+}
+
+impl Session {
+    // Serve `text` on `port` to anyone who joins, and join it ourselves
+    pub fn host(port: u16, text: String, name: &str) -> Result<Self, Error> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        serve(listener, text);
+        let mut session = Self::join(&format!("127.0.0.1:{}", port), name)?;
+        session.address = format!("{}:{}", local_name(), port);
+        Ok(session)
+    }
+
+    // Connect to a host at "address" or "address:port" and take its text
+    pub fn join(address: &str, name: &str) -> Result<Self, Error> {
+        let address = if address.contains(':') { address.to_string() } else { format!("{}:{}", address, DEFAULT_PORT) };
+        let stream = TcpStream::connect(&address)?;
+        let mut lines = BufReader::new(stream.try_clone()?).lines();
+        let welcome = lines.next().ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "The host closed the connection"))??;
+        let Ok(Message::Welcome { revision, text, .. }) = serde_json::from_str(&welcome) else {
+            return Err(Error::new(ErrorKind::InvalidData, format!("{} is not a Quick Notepad session", address)));
+        };
+        let (tx, incoming) = mpsc::channel();
+        read_messages(stream.try_clone()?, tx, Some, None);
+        Ok(Self {
+            stream,
+            incoming,
+            name: name.to_string(),
+            revision,
+            synced: text,
+            sent: None,
+            queued: Vec::new(),
+            caret: None,
+            peers: Vec::new(),
+            address,
+        })
+    }
+
+    // The shared text as of the last sync
+    pub fn text(&self) -> &str {
+        &self.synced
+    }
+
+    pub fn peers(&self) -> &[Peer] {
+        &self.peers
+    }
+
+    // Send what changed in `buffer` since the last call, take in the others' edits and carets,
+    // and move `caret` (char column) along with them. True when others changed the text
+    pub fn sync(&mut self, buffer: &mut Buffer, caret: &mut TextPosition) -> Result<bool, Error> {
+        let content = buffer.content();
+        if content != self.synced {
+            let op = Operation::diff(&self.synced, &content);
+            self.synced = content;
+            for peer in &mut self.peers {
+                peer.offset = op.map(peer.offset);
+            }
+            if self.sent.is_none() {
+                self.send_edit(op)?;
+            } else {
+                self.queued.push(op);
+            }
+        }
+
+        let mut offset = offset_of(&self.synced, *caret);
+        let mut changed = false;
+        loop {
+            let message = match self.incoming.try_recv() {
+                Ok(Some(message)) => message,
+                Err(TryRecvError::Empty) => break,
+                Ok(None) | Err(TryRecvError::Disconnected) => {
+                    return Err(Error::new(ErrorKind::ConnectionAborted, "Disconnected from the shared session"))
+                }
+            };
+            match message {
+                Message::Edit { revision, mut op } => {
+                    // Rebase our own edits in flight over it, and it over them
+                    for pending in self.sent.iter_mut().chain(self.queued.iter_mut()) {
+                        let (op_after, pending_after) = transform(&op, pending);
+                        (op, *pending) = (op_after, pending_after);
+                    }
+                    self.synced = op.apply(&self.synced);
+                    self.revision = revision;
+                    offset = op.map(offset);
+                    for peer in &mut self.peers {
+                        peer.offset = op.map(peer.offset);
+                    }
+                    changed = true;
+                }
+                Message::Ack => {
+                    self.revision += 1;
+                    self.sent = None;
+                    if !self.queued.is_empty() {
+                        let next = self.queued.remove(0);
+                        self.send_edit(next)?;
+                    }
+                }
+                Message::Caret { id, name, offset } => {
+                    let position = TextPosition { line: 0, column: 0 };
+                    match self.peers.iter_mut().find(|peer| peer.id == id) {
+                        Some(peer) => (peer.name, peer.offset) = (name, offset),
+                        None => self.peers.push(Peer { id, name, position, offset }),
+                    }
+                }
+                Message::Left { id } => self.peers.retain(|peer| peer.id != id),
+                Message::Welcome { .. } => {}
+            }
+        }
+
+        if changed {
+            let (final_newline, crlf) = (buffer.final_newline, buffer.crlf);
+            *buffer = Buffer::from_string(self.synced.clone());
+            (buffer.final_newline, buffer.crlf) = (final_newline, crlf);
+            *caret = position_of(&self.synced, offset);
+        }
+        if self.caret != Some(offset) {
+            self.caret = Some(offset);
+            let message = Message::Caret { id: 0, name: self.name.clone(), offset };
+            send(&mut self.stream, &message)?;
+        }
+        for peer in &mut self.peers {
+            peer.position = position_of(&self.synced, peer.offset);
+        }
+        Ok(changed)
+    }
+
+    fn send_edit(&mut self, op: Operation) -> Result<(), Error> {
+        send(&mut self.stream, &Message::Edit { revision: self.revision, op: op.clone() })?;
+        self.sent = Some(op);
+        Ok(())
+    }
+}
+
+// The reader thread holds a copy of the socket, so close it outright; for the host this ends the session
+impl Drop for Session {
+    fn drop(&mut self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+pub const DEFAULT_PORT: u16 = 7878;
+
+// Name shown next to our caret: collab_name from the config, else the login name
+pub fn display_name(configured: &str) -> String {
+    if !configured.is_empty() {
+        return configured.to_string();
+    }
+    std::env::var("USER").or_else(|_| std::env::var("USERNAME")).unwrap_or_else(|_| "guest".to_string())
+}
+
+// This machine's name for the address others join, falling back to a placeholder to fill in
+fn local_name() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok().map(|name| name.trim().to_string()))
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "<this machine>".to_string())
+}
+
+// Char offset of a position, clamped to the text
+fn offset_of(text: &str, position: TextPosition) -> usize {
+    let mut offset = 0;
+    for (index, line) in text.split('\n').enumerate() {
+        let len = line.chars().count();
+        if index == position.line {
+            return offset + position.column.min(len);
+        }
+        offset += len + 1;
+    }
+    offset.saturating_sub(1)
+}
+
+fn position_of(text: &str, offset: usize) -> TextPosition {
+    let before: String = text.chars().take(offset).collect();
+    let line = before.matches('\n').count();
+    let column = before.rsplit('\n').next().map_or(0, |last| last.chars().count());
+    TextPosition { line, column }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_concurrent_edits_converge() {
+        let base = "hello world";
+        let ours = Operation::diff(base, "hello, world");
+        let theirs = Operation::diff(base, "hello big world!");
+        let (theirs_after, ours_after) = transform(&theirs, &ours);
+        let merged = ours_after.apply(&theirs.apply(base));
+        assert_eq!(merged, theirs_after.apply(&ours.apply(base)));
+        assert_eq!(merged, "hello, big world!");
+
+        // Overlapping deletes and inserts at the same spot: the first edit's insertion goes first
+        let a = Operation::diff("abcdef", "aXf");
+        let b = Operation::diff("abcdef", "abYef");
+        let (a_after, b_after) = transform(&a, &b);
+        assert_eq!(b_after.apply(&a.apply("abcdef")), a_after.apply(&b.apply("abcdef")));
+        assert_eq!(Operation::diff("ab", "aZb").map(1), 1);
+        assert_eq!(Operation::diff("ab", "Zab").map(1), 2);
+        assert_eq!(position_of("ab\ncd", offset_of("ab\ncd", TextPosition { line: 1, column: 1 })), TextPosition { line: 1, column: 1 });
+    }
+
+    #[test]
+    fn test_host_and_join() {
+        let port = 47000 + (std::process::id() % 2000) as u16;
+        let mut host = Session::host(port, "shared".to_string(), "host").unwrap();
+        let mut guest = Session::join(&format!("127.0.0.1:{}", port), "guest").unwrap();
+        assert_eq!(guest.text(), "shared");
+
+        // Both type at once; each sees the other's edit and ends up with the same text
+        let (mut host_buffer, mut guest_buffer) = (Buffer::from_string("shared!".to_string()), Buffer::from_string("my shared".to_string()));
+        let (mut host_caret, mut guest_caret) = (TextPosition { line: 0, column: 7 }, TextPosition { line: 0, column: 2 });
+        host.sync(&mut host_buffer, &mut host_caret).unwrap();
+        guest.sync(&mut guest_buffer, &mut guest_caret).unwrap();
+        for _ in 0..50 {
+            host.sync(&mut host_buffer, &mut host_caret).unwrap();
+            guest.sync(&mut guest_buffer, &mut guest_caret).unwrap();
+            let caret_seen = guest.peers().first().is_some_and(|peer| peer.position.column == 10);
+            if host_buffer.content() == "my shared!" && guest_buffer.content() == "my shared!" && caret_seen {
+                break;
+            }
+            thread::sleep(std::time::Duration::from_millis(20));
+        }
+        assert_eq!(host_buffer.content(), "my shared!");
+        assert_eq!(guest_buffer.content(), "my shared!");
+        assert_eq!(host_caret, TextPosition { line: 0, column: 10 });
+        assert_eq!(guest.peers()[0].name, "host");
+        assert_eq!(guest.peers()[0].position, TextPosition { line: 0, column: 10 });
+    }
+}
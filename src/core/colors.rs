@@ -0,0 +1,97 @@
+// colors module - find #RRGGBB and rgb(...) color literals for swatch previews
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorLiteral {
+    pub start: usize, // Byte range of the literal in the line
+    pub end: usize,
+    pub rgb: (u8, u8, u8),
+}
+
+// Color previews make sense for code and config, not prose
+pub fn has_color_literals(filetype: Option<&str>) -> bool {
+    !matches!(filetype, None | Some("Markdown") | Some("Text File") | Some("Log File"))
+}
+
+pub fn find_colors(line: &str) -> Vec<ColorLiteral> {
+    let mut colors = Vec::new();
+    let bytes = line.as_bytes();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let prev_is_word = i > 0 && (bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'_');
+        let found = if prev_is_word {
+            None
+        } else if bytes[i] == b'#' {
+            parse_hex(&line[i..])
+        } else if bytes[i..].starts_with(b"rgb") {
+            parse_rgb(&line[i..])
+        } else {
+            None
+        };
+
+        match found {
+            Some((len, rgb)) => {
+                colors.push(ColorLiteral { start: i, end: i + len, rgb });
+                i += len;
+            }
+            None => i += 1,
+        }
+    }
+
+    colors
+}
+
+// "#RGB", "#RGBA", "#RRGGBB" or "#RRGGBBAA" (alpha is ignored)
+fn parse_hex(text: &str) -> Option<(usize, (u8, u8, u8))> {
+    let digits = text[1..].bytes().take_while(u8::is_ascii_hexdigit).count();
+    let after = text[1 + digits..].chars().next();
+    if after.is_some_and(|c| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+
+    let hex = &text[1..1 + digits];
+    let channel = |range: std::ops::Range<usize>| u8::from_str_radix(&hex[range], 16).ok();
+    let short = |idx: usize| channel(idx..idx + 1).map(|v| v * 17);
+    let rgb = match digits {
+        3 | 4 => (short(0)?, short(1)?, short(2)?),
+        6 | 8 => (channel(0..2)?, channel(2..4)?, channel(4..6)?),
+        _ => return None,
+    };
+    Some((1 + digits, rgb))
+}
+
+// "rgb(255, 0, 0)" or "rgba(255 0 0 / 50%)"
+fn parse_rgb(text: &str) -> Option<(usize, (u8, u8, u8))> {
+    let open = if text.starts_with("rgba(") { 5 } else if text.starts_with("rgb(") { 4 } else { return None };
+    let close = open + text[open..].find(')')?;
+
+    let values: Vec<u8> = text[open..close]
+        .split([',', ' ', '/'])
+        .filter(|part| !part.is_empty())
+        .take(3)
+        .map(|part| match part.strip_suffix('%') {
+            Some(percent) => percent.parse::<f32>().ok().map(|p| (p.clamp(0.0, 100.0) * 2.55).round() as u8),
+            None => part.parse::<f32>().ok().map(|v| v.clamp(0.0, 255.0).round() as u8),
+        })
+        .collect::<Option<_>>()?;
+
+    match values[..] {
+        [r, g, b] => Some((close + 1, (r, g, b))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_colors() {
+        let line = "a { color: #ff8800; border: 1px solid #0f0; background: rgba(10, 20, 30, 0.5) }";
+        let rgbs: Vec<(u8, u8, u8)> = find_colors(line).iter().map(|c| c.rgb).collect();
+        assert_eq!(rgbs, [(255, 136, 0), (0, 255, 0), (10, 20, 30)]);
+
+        // Not colors: attributes, issue numbers, words
+        assert!(find_colors("#[derive(Debug)] fix #12345 a#fff ünïcödé").is_empty());
+    }
+}
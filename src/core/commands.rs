@@ -0,0 +1,172 @@
+// commands module - every command the editor offers with its name and default key, in one registry.
+// Shortcuts resolve keys through it, and the command palette, the shortcuts footer and --shortcuts list it
+use crate::core::actions::Action;
+use crate::core::conflicts::Resolution;
+use crate::core::hash::HashAlgorithm;
+use crate::core::jumps::Jump;
+use crate::core::transforms::TextTransform;
+use crossterm::event::{KeyCode, KeyModifiers};
+
+#[derive(Debug, Clone)]
+pub struct Command {
+    pub name: String,
+    pub action: Action,
+    pub key: Option<(KeyCode, KeyModifiers)>, // None for palette-only commands
+    pub chord: Option<(KeyCode, KeyModifiers)>, // Key pressed before `key` for two-key sequences
+}
+
+fn bound(code: KeyCode, mods: KeyModifiers, action: Action, name: &str) -> Command {
+    Command { name: name.to_string(), action, key: Some((code, mods)), chord: None }
+}
+
+// Ctrl+K then `code`, with or without Ctrl held for the second key
+fn chord(code: KeyCode, action: Action, name: &str) -> Command {
+    Command {
+        name: name.to_string(),
+        action,
+        key: Some((code, KeyModifiers::CONTROL)),
+        chord: Some(CHORD_LEADER),
+    }
+}
+
+fn unbound(action: Action, name: impl Into<String>) -> Command {
+    Command { name: name.into(), action, key: None, chord: None }
+}
+
+pub const CHORD_LEADER: (KeyCode, KeyModifiers) = (KeyCode::Char('k'), KeyModifiers::CONTROL);
+
+// THE ONE PLACE TO ADD COMMANDS AND CHANGE THEIR DEFAULT SHORTCUTS
+pub fn registry() -> Vec<Command> {
+    let mut commands = vec![
+        bound(KeyCode::Left, KeyModifiers::empty(), Action::Left, "Move caret left"),
+        bound(KeyCode::Right, KeyModifiers::empty(), Action::Right, "Move caret right"),
+        bound(KeyCode::Up, KeyModifiers::empty(), Action::Up, "Move caret up"),
+        bound(KeyCode::Down, KeyModifiers::empty(), Action::Down, "Move caret down"),
+        bound(KeyCode::PageUp, KeyModifiers::empty(), Action::Top, "Move to top of view"),
+        bound(KeyCode::PageDown, KeyModifiers::empty(), Action::Bottom, "Move to bottom of view"),
+        bound(KeyCode::Home, KeyModifiers::empty(), Action::MaxLeft, "Move to start of line"),
+        bound(KeyCode::End, KeyModifiers::empty(), Action::MaxRight, "Move to end of line"),
+        bound(KeyCode::Enter, KeyModifiers::empty(), Action::NextLine, "Insert new line"),
+        bound(KeyCode::Tab, KeyModifiers::empty(), Action::Print, "Insert tab (4 spaces)"),
+        bound(KeyCode::Backspace, KeyModifiers::empty(), Action::Backspace, "Delete before cursor"),
+        bound(KeyCode::Delete, KeyModifiers::empty(), Action::Delete, "Delete at cursor"),
+        bound(KeyCode::Char('g'), KeyModifiers::CONTROL, Action::ToggleCtrlShortcuts, "Toggle ctrl shortcuts footer"),
+        bound(KeyCode::Char('p'), KeyModifiers::CONTROL, Action::CommandPalette, "Command palette"),
+        bound(KeyCode::Char('q'), KeyModifiers::CONTROL, Action::Quit, "Quit"),
+        bound(KeyCode::Char('s'), KeyModifiers::CONTROL, Action::Save, "Save"),
+        bound(KeyCode::Char('e'), KeyModifiers::CONTROL, Action::SaveEncrypted, "Save encrypted"),
+        bound(KeyCode::Char('n'), KeyModifiers::CONTROL, Action::New, "New"),
+        bound(KeyCode::Char('c'), KeyModifiers::CONTROL, Action::Copy, "Copy"),
+        bound(KeyCode::Char('v'), KeyModifiers::CONTROL, Action::Paste, "Paste"),
+        bound(KeyCode::Char('x'), KeyModifiers::CONTROL, Action::Cut, "Cut"),
+        bound(KeyCode::Char('a'), KeyModifiers::CONTROL, Action::SelectAll, "Select all"),
+        bound(KeyCode::Char('z'), KeyModifiers::CONTROL, Action::Undo, "Undo"),
+        bound(KeyCode::Char('y'), KeyModifiers::CONTROL, Action::Redo, "Redo"),
+        bound(KeyCode::Char('z'), KeyModifiers::CONTROL | KeyModifiers::SHIFT, Action::Redo, "Redo"),
+        bound(KeyCode::Char('f'), KeyModifiers::CONTROL, Action::Search, "Search"),
+        bound(KeyCode::F(3), KeyModifiers::empty(), Action::RepeatSearch, "Repeat last search"),
+        bound(KeyCode::Char('h'), KeyModifiers::CONTROL, Action::Replace, "Find and replace"),
+        bound(KeyCode::F(1), KeyModifiers::empty(), Action::ToggleCtrlShortcuts, "Show shortcuts"),
+        bound(KeyCode::F(2), KeyModifiers::empty(), Action::RenameFile, "Rename file"),
+        bound(KeyCode::Char('z'), KeyModifiers::ALT, Action::ToggleAutoWrap, "Toggle auto wrap"),
+        bound(KeyCode::Char('d'), KeyModifiers::CONTROL, Action::ToggleCheckbox, "Toggle checkbox"),
+        bound(KeyCode::Char('d'), KeyModifiers::ALT, Action::MoveDoneItems, "Move done items"),
+        bound(KeyCode::Char('t'), KeyModifiers::ALT, Action::FormatTable, "Format Markdown table"),
+        bound(KeyCode::Char('l'), KeyModifiers::ALT, Action::ListTodos, "List TODOs"),
+        bound(KeyCode::Char('o'), KeyModifiers::ALT, Action::OpenLink, "Open link under caret"),
+        bound(KeyCode::Char('g'), KeyModifiers::ALT, Action::GoToFile, "Go to file under caret"),
+        bound(KeyCode::Char('='), KeyModifiers::ALT, Action::Evaluate, "Evaluate expression"),
+        bound(KeyCode::Char('a'), KeyModifiers::ALT, Action::AlignSelection, "Align selection on delimiter"),
+        bound(KeyCode::Char('q'), KeyModifiers::ALT, Action::ReflowParagraph, "Reflow paragraph"),
+        bound(KeyCode::Char('n'), KeyModifiers::ALT, Action::InsertSequence, "Insert number sequence"),
+        bound(KeyCode::Char('c'), KeyModifiers::ALT, Action::CompareTabs, "Compare tabs"),
+        bound(KeyCode::Char('m'), KeyModifiers::ALT, Action::NextConflict, "Next merge conflict"),
+        bound(KeyCode::Char('f'), KeyModifiers::ALT, Action::ToggleFold, "Fold / unfold block"),
+        bound(KeyCode::Char('u'), KeyModifiers::ALT, Action::UnfoldAll, "Unfold all"),
+        bound(KeyCode::Up, KeyModifiers::ALT, Action::ExpandSelection, "Expand selection"),
+        bound(KeyCode::Down, KeyModifiers::ALT, Action::ShrinkSelection, "Shrink selection"),
+        bound(KeyCode::Up, KeyModifiers::CONTROL, Action::Jump(Jump::ParagraphUp), "Previous paragraph"),
+        bound(KeyCode::Down, KeyModifiers::CONTROL, Action::Jump(Jump::ParagraphDown), "Next paragraph"),
+        bound(KeyCode::Left, KeyModifiers::ALT, Action::Jump(Jump::BlockStart), "Start of indented block"),
+        bound(KeyCode::Right, KeyModifiers::ALT, Action::Jump(Jump::BlockEnd), "End of indented block"),
+        bound(KeyCode::Char('u'), KeyModifiers::CONTROL, Action::CheckUpdate, "Check for updates"),
+        bound(KeyCode::Char('l'), KeyModifiers::CONTROL, Action::ToggleFollow, "Follow file (read-only)"),
+        bound(KeyCode::Char('t'), KeyModifiers::CONTROL, Action::NewScratch, "New scratch note"),
+        bound(KeyCode::Char('r'), KeyModifiers::CONTROL, Action::OpenScratch, "Reopen scratch note"),
+        bound(KeyCode::Char('o'), KeyModifiers::CONTROL, Action::FindFile, "Find file in project"),
+    ];
+
+    commands.extend(TextTransform::ALL.iter().map(|t| unbound(Action::Transform(*t), t.name())));
+    commands.extend(HashAlgorithm::ALL.iter().map(|a| unbound(Action::Hash(*a), format!("{} checksum", a.name()))));
+    commands.extend(Resolution::ALL.iter().map(|r| unbound(Action::ResolveConflict(*r), r.name())));
+    commands.extend(
+        Jump::ALL
+            .iter()
+            .map(|j| unbound(Action::SelectJump(*j), format!("Select to {}", j.name().to_lowercase()))),
+    );
+    commands.extend([
+        chord(KeyCode::Char('h'), Action::FileHistory, "File history"),
+        chord(KeyCode::Char('r'), Action::ReadFile, "Read file into buffer"),
+        chord(KeyCode::Char('w'), Action::WriteSelection, "Write selection to file"),
+        chord(KeyCode::Char('a'), Action::AppendToFile, "Append to file"),
+        chord(KeyCode::Char('m'), Action::ToggleMouse, "Toggle mouse capture"),
+        chord(KeyCode::Char('c'), Action::CountMatches, "Count occurrences"),
+        chord(KeyCode::Char('s'), Action::SaveAs, "Save as"),
+        chord(KeyCode::Char('o'), Action::OpenFile, "Open file"),
+        unbound(Action::DeleteFile, "Delete current file"),
+        unbound(Action::RollbackUpdate, "Roll back last update"),
+        unbound(Action::ReportIssue, "Report issue"),
+        unbound(Action::RestoreDeletedFile, "Restore deleted file"),
+        unbound(Action::DetachTab, "Open tab in new window"),
+        unbound(Action::UsageStats, "Usage statistics"),
+    ]);
+    commands
+}
+
+// The single-key command bound to exactly this key
+pub fn find(commands: &[Command], code: KeyCode, mods: KeyModifiers) -> Option<&Command> {
+    commands.iter().find(|command| command.chord.is_none() && command.key == Some((code, mods)))
+}
+
+pub fn is_chord_leader(commands: &[Command], code: KeyCode, mods: KeyModifiers) -> bool {
+    commands.iter().any(|command| command.chord == Some((code, mods)))
+}
+
+// The second key of a sequence started with `leader`; Ctrl may be held or not
+pub fn find_chord(commands: &[Command], leader: (KeyCode, KeyModifiers), code: KeyCode, mods: KeyModifiers) -> Option<&Command> {
+    let mods = if mods.is_empty() { KeyModifiers::CONTROL } else { mods };
+    commands
+        .iter()
+        .find(|command| command.chord == Some(leader) && command.key == Some((code, mods)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_keys_are_unique() {
+        let commands = registry();
+        for (i, command) in commands.iter().enumerate() {
+            if let (Some((code, mods)), None) = (command.key, command.chord) {
+                assert_eq!(find(&commands, code, mods).map(|c| c.name.as_str()), Some(commands[i].name.as_str()));
+            }
+        }
+        assert_eq!(find(&commands, KeyCode::Char('s'), KeyModifiers::CONTROL).unwrap().action, Action::Save);
+    }
+
+    #[test]
+    fn test_chords() {
+        let commands = registry();
+        let (code, mods) = CHORD_LEADER;
+        assert!(is_chord_leader(&commands, code, mods));
+        assert_eq!(find(&commands, KeyCode::Char('h'), KeyModifiers::CONTROL).unwrap().action, Action::Replace);
+        let history = find_chord(&commands, CHORD_LEADER, KeyCode::Char('h'), KeyModifiers::empty());
+        assert_eq!(history.unwrap().action, Action::FileHistory);
+        let count = find_chord(&commands, CHORD_LEADER, KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert_eq!(count.unwrap().action, Action::CountMatches);
+        let open = find_chord(&commands, CHORD_LEADER, KeyCode::Char('o'), KeyModifiers::empty());
+        assert_eq!(open.unwrap().action, Action::OpenFile);
+        assert!(find_chord(&commands, CHORD_LEADER, KeyCode::Char('z'), KeyModifiers::CONTROL).is_none());
+    }
+}
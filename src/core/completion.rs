@@ -0,0 +1,103 @@
+// completion module - file paths typed into prompts: Tab completion, reading and writing them
+use crate::core::crypto;
+use crate::core::paths;
+use std::fs;
+use std::io::Error;
+use std::path::PathBuf;
+
+// Text of the file at `input`, for inserting into another buffer
+pub fn read_text(input: &str) -> Result<String, Error> {
+    let bytes = fs::read(paths::resolve(input))?;
+    if crypto::is_encrypted(&bytes) {
+        return Err(Error::new(std::io::ErrorKind::InvalidData, "Encrypted note, open it in a tab instead"));
+    }
+    let text = String::from_utf8(bytes).map_err(|e| Error::new(std::io::ErrorKind::InvalidData, e))?;
+    Ok(text.replace("\r\n", "\n"))
+}
+
+// Write `text` to the file at `input`, creating or replacing it. Encrypted notes are refused,
+// as this is plaintext
+pub fn write_text(input: &str, text: &str) -> Result<(), Error> {
+    let path = paths::resolve(input);
+    crypto::check_plaintext_path(&path.to_string_lossy())?;
+    fs::write(path, text)
+}
+
+// Extend `input` as far as every matching entry agrees, with the names that matched.
+// Directories end in '/' so the next Tab continues inside them.
+pub fn complete_path(input: &str) -> (String, Vec<String>) {
+    let (dir_part, prefix) = match input.rfind('/') {
+        Some(idx) => input.split_at(idx + 1),
+        None => ("", input),
+    };
+    let dir = if dir_part.is_empty() { PathBuf::from(".") } else { paths::resolve(dir_part) };
+
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            // Hidden files only when asked for with a leading dot
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let is_dir = entry.path().is_dir();
+            Some(if is_dir { format!("{}/", name) } else { name })
+        })
+        .collect();
+    names.sort();
+
+    let Some(first) = names.first() else {
+        return (input.to_string(), names);
+    };
+    let common = names.iter().fold(first.as_str(), |common, name| {
+        let len = common
+            .char_indices()
+            .zip(name.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(common.len().min(name.len()), |((idx, _), _)| idx);
+        &common[..len]
+    });
+    (format!("{}{}", dir_part, common), names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_complete_path() {
+        let dir = std::env::temp_dir().join(format!("quicknotepad-complete-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("notes")).unwrap();
+        fs::write(dir.join("readme.md"), "").unwrap();
+        fs::write(dir.join("report.txt"), "").unwrap();
+        fs::write(dir.join(".hidden"), "").unwrap();
+        let base = format!("{}/", dir.to_string_lossy());
+
+        let (completed, names) = complete_path(&format!("{}re", base));
+        assert_eq!(completed, format!("{}re", base));
+        assert_eq!(names, ["readme.md", "report.txt"]);
+        assert_eq!(complete_path(&format!("{}rea", base)).0, format!("{}readme.md", base));
+        assert_eq!(complete_path(&format!("{}n", base)).0, format!("{}notes/", base));
+        assert_eq!(complete_path(&base).1, ["notes/", "readme.md", "report.txt"]);
+        assert_eq!(complete_path(&format!("{}x", base)).1.len(), 0);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_write_text_refuses_encrypted_notes() {
+        let dir = std::env::temp_dir().join(format!("quicknotepad-write-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let note = dir.join("secret.qnenc");
+
+        let error = write_text(&note.to_string_lossy(), "plain").unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::PermissionDenied);
+        assert!(!note.exists());
+        write_text(&dir.join("plain.txt").to_string_lossy(), "plain").unwrap();
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,187 @@
+// config module - user settings stored in config.toml in the config directory. A config.json
+// from older versions is converted on first load
+use crate::core::collab;
+use crate::core::paths;
+use crate::core::keymap::Keymap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+const CONFIG_FILE: &str = "config.toml";
+const LEGACY_CONFIG_FILE: &str = "config.json"; // Written by versions before config.toml
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Config {
+    pub rainbow_brackets: bool, // Color (), [], {} by nesting depth
+    pub todo_keywords: Vec<String>, // Markers highlighted in comments and listed by Alt+L
+    pub vim_mode: bool, // Modal normal / insert / visual editing in the terminal
+    pub keymap: Keymap, // "default" or "emacs" bindings in the terminal
+    pub keybindings: BTreeMap<String, String>, // Command name to key in the terminal, e.g. "Find and replace": "Ctrl+R"; "" unbinds
+    pub tab_width: usize, // Columns between tab stops when drawing tab characters
+    pub virtual_space: bool, // Caret may sit past the end of a line; typing there pads with spaces
+    pub typewriter_scrolling: bool, // Keep the caret's line in the middle of the screen
+    pub smooth_scrolling: bool, // Animate GUI jumps to search matches and lines
+    pub file_history: bool, // Keep the previous version of a file in the history folder on every save
+    pub append_separator: String, // Line written before text appended to a file, {timestamp} is filled in; empty for none
+    pub usage_stats: bool, // Count keystrokes, undos, saves and time spent in stats.json (never sent anywhere)
+    pub wrap_column: usize, // Column Alt+Q reflows paragraphs to
+    pub auto_wrap: bool, // Break lines at wrap_column while typing in text and Markdown files
+    pub count_prefix: bool, // Esc then digits repeats the next motion or line operation in the terminal
+    pub mouse: bool, // Capture the mouse in the terminal; off leaves select-to-copy to the terminal itself
+    pub scroll_lines: usize, // Lines one mouse wheel notch scrolls
+    pub wheel_moves_caret: bool, // Wheel scrolling takes the caret along with the view instead of leaving it behind
+    pub update_checks: bool, // Offer the update check at all; off hides it for offline machines
+    pub update_proxy: String, // Proxy URL for update requests; empty uses HTTP_PROXY / HTTPS_PROXY from the environment
+    pub update_endpoint: String, // URL returning the latest release as GitHub's API does, for self-hosted mirrors; empty for GitHub
+    pub collab_port: u16, // Port quick --host listens on
+    pub collab_name: String, // Name shown next to your caret in shared tabs; empty uses the login name
+    pub insert_final_newline: bool, // End saved files with a line break even when they had none
+    pub probe_widths: bool, // Ask the terminal at startup how wide it draws emoji sequences
+    pub char_widths: BTreeMap<String, usize>, // Cells the terminal gives particular characters, e.g. "❤️": 1
+    #[serde(skip)]
+    pub load_warning: Option<String>, // Set when the config file could not be parsed and got reset, to tell the user
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            rainbow_brackets: true,
+            todo_keywords: ["TODO", "FIXME", "HACK", "NOTE"].iter().map(|s| s.to_string()).collect(),
+            vim_mode: false,
+            keymap: Keymap::Default,
+            keybindings: BTreeMap::new(),
+            tab_width: 4,
+            virtual_space: false,
+            typewriter_scrolling: false,
+            smooth_scrolling: true,
+            file_history: true,
+            append_separator: "--- {timestamp} ---".to_string(),
+            usage_stats: true,
+            wrap_column: 80,
+            auto_wrap: false,
+            count_prefix: false,
+            mouse: true,
+            scroll_lines: 3,
+            wheel_moves_caret: false,
+            update_checks: true,
+            update_proxy: String::new(),
+            update_endpoint: String::new(),
+            collab_port: collab::DEFAULT_PORT,
+            collab_name: String::new(),
+            insert_final_newline: false,
+            probe_widths: true,
+            char_widths: BTreeMap::new(),
+            load_warning: None,
+        }
+    }
+}
+
+impl Config {
+    // Load the config, writing the defaults out on first run so they can be edited
+    pub fn load() -> Self {
+        Self::load_in(&paths::config_dir())
+    }
+
+    fn load_in(dir: &Path) -> Self {
+        let path = dir.join(CONFIG_FILE);
+        let legacy = dir.join(LEGACY_CONFIG_FILE);
+        if !path.exists() && legacy.exists() {
+            return Self::migrate(&legacy, &path);
+        }
+        if !path.exists() {
+            let config = Self::default();
+            if let Err(e) = config.save_to(&path) {
+                eprintln!("Warning: Could not write config file: {}", e);
+            }
+            return config;
+        }
+
+        match Self::load_from(&path) {
+            Ok(config) => config,
+            Err(e) => Self::recover(&path, &path, e),
+        }
+    }
+
+    // Read the JSON settings older versions wrote, save them as TOML and keep the old file as
+    // config.json.migrated
+    fn migrate(legacy: &Path, path: &Path) -> Self {
+        let loaded = fs::read_to_string(legacy)
+            .and_then(|content| serde_json::from_str(&content).map_err(|e| Error::new(ErrorKind::InvalidData, e)));
+        let config: Self = match loaded {
+            Ok(config) => config,
+            Err(e) => return Self::recover(legacy, path, e),
+        };
+        match config.save_to(path) {
+            Ok(()) => {
+                let _ = fs::rename(legacy, legacy.with_extension("json.migrated"));
+            }
+            Err(e) => eprintln!("Warning: Could not write config file: {}", e),
+        }
+        config
+    }
+
+    // The defaults, after a file that failed to load. An unparsable one is kept around and the
+    // defaults are written to `path` in its place
+    fn recover(broken: &Path, path: &Path, e: Error) -> Self {
+        if e.kind() != ErrorKind::InvalidData {
+            eprintln!("Warning: Could not read config file, using defaults: {}", e);
+            return Self::default();
+        }
+        let mut config = Self::default();
+        config.load_warning = Some(match paths::set_aside(broken) {
+            Ok(backup) => {
+                let _ = config.save_to(path);
+                format!("Config file was invalid ({}), moved it to {} and reset the settings", e, backup.display())
+            }
+            Err(_) => format!("Config file is invalid ({}), using default settings", e),
+        });
+        config
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        self.save_to(&Self::get_config_file_path())
+    }
+
+    fn save_to(&self, path: &Path) -> Result<(), Error> {
+        let toml = toml::to_string_pretty(self).map_err(Error::other)?;
+        fs::write(path, toml)
+    }
+
+    fn load_from(path: &Path) -> Result<Self, Error> {
+        let content = fs::read_to_string(path)?;
+        toml::from_str(&content).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    }
+
+    pub fn get_config_file_path() -> PathBuf {
+        paths::config_dir().join(CONFIG_FILE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_json_is_migrated_to_toml() {
+        let dir = std::env::temp_dir().join(format!("quicknotepad-config-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(LEGACY_CONFIG_FILE), r#"{ "tab_width": 8, "keybindings": { "Find and replace": "Ctrl+R" } }"#).unwrap();
+
+        let config = Config::load_in(&dir);
+        assert_eq!(config.tab_width, 8);
+        assert!(!dir.join(LEGACY_CONFIG_FILE).exists());
+        assert!(dir.join("config.json.migrated").exists());
+        let toml = fs::read_to_string(dir.join(CONFIG_FILE)).unwrap();
+        assert!(toml.contains("tab_width = 8"), "{}", toml);
+
+        // Later loads read the TOML, including settings added to it by hand
+        fs::write(dir.join(CONFIG_FILE), toml.replace("tab_width = 8", "tab_width = 2")).unwrap();
+        let config = Config::load_in(&dir);
+        assert_eq!(config.tab_width, 2);
+        assert_eq!(config.keybindings.get("Find and replace").map(String::as_str), Some("Ctrl+R"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,150 @@
+// conflicts module - find and resolve <<<<<<< / ======= / >>>>>>> merge conflicts
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resolution {
+    Ours,
+    Theirs,
+    Both,
+}
+
+impl Resolution {
+    pub const ALL: [Resolution; 3] = [Resolution::Ours, Resolution::Theirs, Resolution::Both];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Resolution::Ours => "Accept ours (merge conflict)",
+            Resolution::Theirs => "Accept theirs (merge conflict)",
+            Resolution::Both => "Accept both (merge conflict)",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Region {
+    Marker,
+    Ours,
+    Base, // Common ancestor in diff3 style conflicts
+    Theirs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Conflict {
+    pub start: usize,        // "<<<<<<<" line
+    pub base: Option<usize>, // "|||||||" line, if any
+    pub separator: usize,    // "=======" line
+    pub end: usize,          // ">>>>>>>" line
+}
+
+impl Conflict {
+    pub fn contains(&self, line: usize) -> bool {
+        (self.start..=self.end).contains(&line)
+    }
+
+    pub fn region(&self, line: usize) -> Option<Region> {
+        if !self.contains(line) {
+            return None;
+        }
+        let ours_end = self.base.unwrap_or(self.separator);
+        Some(if line == self.start || line == self.separator || line == self.end || Some(line) == self.base {
+            Region::Marker
+        } else if line < ours_end {
+            Region::Ours
+        } else if line < self.separator {
+            Region::Base
+        } else {
+            Region::Theirs
+        })
+    }
+
+    // Lines replacing the whole conflict, markers included
+    pub fn resolve(&self, lines: &[String], resolution: Resolution) -> Vec<String> {
+        let ours = &lines[self.start + 1..self.base.unwrap_or(self.separator)];
+        let theirs = &lines[self.separator + 1..self.end];
+        match resolution {
+            Resolution::Ours => ours.to_vec(),
+            Resolution::Theirs => theirs.to_vec(),
+            Resolution::Both => ours.iter().chain(theirs).cloned().collect(),
+        }
+    }
+}
+
+fn is_marker(line: &str, marker: &str) -> bool {
+    line.strip_prefix(marker).is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+}
+
+pub fn find_conflicts(lines: &[String]) -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+    let mut open: Option<(usize, Option<usize>, Option<usize>)> = None;
+
+    for (idx, line) in lines.iter().enumerate() {
+        let line = line.trim_end();
+        if is_marker(line, "<<<<<<<") {
+            open = Some((idx, None, None));
+        } else if let Some((start, base, separator)) = open.as_mut() {
+            if separator.is_none() && base.is_none() && is_marker(line, "|||||||") {
+                *base = Some(idx);
+            } else if separator.is_none() && line == "=======" {
+                *separator = Some(idx);
+            } else if is_marker(line, ">>>>>>>") {
+                if let Some(separator) = *separator {
+                    conflicts.push(Conflict { start: *start, base: *base, separator, end: idx });
+                }
+                open = None;
+            }
+        }
+    }
+
+    conflicts
+}
+
+// The conflict containing `line`, or else the next one below it (wrapping around)
+pub fn conflict_for(conflicts: &[Conflict], line: usize) -> Option<&Conflict> {
+    conflicts
+        .iter()
+        .find(|c| c.end >= line)
+        .or(conflicts.first())
+}
+
+// First conflict starting below `line`, wrapping to the top
+pub fn next_conflict(conflicts: &[Conflict], line: usize) -> Option<&Conflict> {
+    conflicts
+        .iter()
+        .find(|c| c.start > line)
+        .or(conflicts.first())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_and_resolve() {
+        let lines: Vec<String> = [
+            "fn main() {",
+            "<<<<<<< HEAD",
+            "    ours();",
+            "||||||| base",
+            "    old();",
+            "=======",
+            "    theirs();",
+            ">>>>>>> feature",
+            "}",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        let conflicts = find_conflicts(&lines);
+        assert_eq!(conflicts, [Conflict { start: 1, base: Some(3), separator: 5, end: 7 }]);
+        let conflict = conflicts[0];
+        assert_eq!(conflict.region(2), Some(Region::Ours));
+        assert_eq!(conflict.region(4), Some(Region::Base));
+        assert_eq!(conflict.region(5), Some(Region::Marker));
+        assert_eq!(conflict.region(6), Some(Region::Theirs));
+        assert_eq!(conflict.region(8), None);
+
+        assert_eq!(conflict.resolve(&lines, Resolution::Ours), ["    ours();"]);
+        assert_eq!(conflict.resolve(&lines, Resolution::Both), ["    ours();", "    theirs();"]);
+        assert_eq!(next_conflict(&conflicts, 3), Some(&conflict));
+    }
+}
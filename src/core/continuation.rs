@@ -0,0 +1,114 @@
+// continuation module - carry list markers and comment prefixes over to the next line
+use crate::core::markdown;
+
+#[derive(Debug, PartialEq)]
+pub struct Continuation {
+    pub prefix_len: usize,   // Bytes of the current line taken by the prefix
+    pub next_prefix: String, // Prefix to start the new line with
+    pub indent: String,      // What an empty prefixed line collapses to
+    opens_block: bool,       // "/*" lines continue even when empty
+}
+
+impl Continuation {
+    // True if the line holds nothing but its prefix
+    pub fn is_empty_line(&self, line: &str) -> bool {
+        !self.opens_block && line.get(self.prefix_len..).is_none_or(|rest| rest.trim().is_empty())
+    }
+}
+
+pub fn continuation(line: &str, filetype: Option<&str>) -> Option<Continuation> {
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+
+    if filetype == Some("Markdown") {
+        return list_continuation(line, indent);
+    }
+
+    // Line comments, keeping doc markers like /// and //!
+    if let Some(comment) = trimmed.strip_prefix("//") {
+        let marker_len = 2 + comment.chars().take_while(|&c| c == '/' || c == '!').count();
+        let spaces = trimmed[marker_len..].chars().take_while(|&c| c == ' ').count();
+        let marker = &trimmed[..marker_len];
+        return Some(Continuation {
+            prefix_len: indent.len() + marker_len + spaces,
+            next_prefix: format!("{}{}{}", indent, marker, " ".repeat(spaces.max(1))),
+            indent: indent.to_string(),
+            opens_block: false,
+        });
+    }
+
+    // Block comments: "/**" or "/*" opens, "*" continues, "*/" closes
+    if trimmed.ends_with("*/") {
+        return None;
+    }
+    if trimmed.starts_with("/*") {
+        let opener_len = if trimmed.starts_with("/**") { 3 } else { 2 };
+        return Some(Continuation {
+            prefix_len: indent.len() + opener_len,
+            next_prefix: format!("{} * ", indent),
+            indent: indent.to_string(),
+            opens_block: true,
+        });
+    }
+    if trimmed == "*" || trimmed.starts_with("* ") {
+        return Some(Continuation {
+            prefix_len: (indent.len() + 2).min(line.len()),
+            next_prefix: format!("{}* ", indent),
+            indent: indent.to_string(),
+            opens_block: false,
+        });
+    }
+
+    None
+}
+
+fn list_continuation(line: &str, indent: &str) -> Option<Continuation> {
+    let (_, marker, rest) = markdown::split_list_item(line)?;
+    let marker_text = marker.trim_end();
+
+    // Numbered lists count up, bullets repeat
+    let next_marker = match marker_text.strip_suffix(['.', ')']) {
+        Some(number) => {
+            let next = number.parse::<u64>().ok()? + 1;
+            format!("{}{}", next, &marker_text[number.len()..])
+        }
+        None => marker_text.to_string(),
+    };
+
+    let mut prefix_len = indent.len() + marker.len();
+    let mut next_prefix = format!("{}{} ", indent, next_marker);
+    if markdown::checkbox_state(line).is_some() {
+        prefix_len += 3 + usize::from(rest.len() > 3);
+        next_prefix.push_str("[ ] ");
+    }
+
+    Some(Continuation {
+        prefix_len: prefix_len.min(line.len()),
+        next_prefix,
+        indent: indent.to_string(),
+        opens_block: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_continuation_prefixes() {
+        let md = Some("Markdown");
+        assert_eq!(continuation("  - item", md).unwrap().next_prefix, "  - ");
+        assert_eq!(continuation("9. item", md).unwrap().next_prefix, "10. ");
+        assert_eq!(continuation("- [x] done", md).unwrap().next_prefix, "- [ ] ");
+        assert!(continuation("- [ ] ", md).unwrap().is_empty_line("- [ ] "));
+        assert!(continuation("plain text", md).is_none());
+
+        let rs = Some("Rust");
+        assert_eq!(continuation("    /// docs", rs).unwrap().next_prefix, "    /// ");
+        assert_eq!(continuation("/**", rs).unwrap().next_prefix, " * ");
+        assert!(!continuation("/**", rs).unwrap().is_empty_line("/**"));
+        assert_eq!(continuation(" * more", rs).unwrap().next_prefix, " * ");
+        assert!(continuation(" */", rs).is_none());
+        assert!(!continuation("// text", rs).unwrap().is_empty_line("// text"));
+    }
+}
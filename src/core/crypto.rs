@@ -0,0 +1,104 @@
+// crypto module - AES-256-GCM encrypted notes (.qnenc) with argon2 key derivation
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::Argon2;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+pub const ENCRYPTED_EXTENSION: &str = "qnenc";
+
+// File layout: MAGIC | salt | nonce | ciphertext (with GCM tag)
+const MAGIC: &[u8] = b"QNENC1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+pub fn is_encrypted_path(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(ENCRYPTED_EXTENSION))
+}
+
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+pub fn encrypt(plaintext: &str, passphrase: &str) -> Result<Vec<u8>, Error> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let cipher = cipher_for(passphrase, &salt)?;
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_bytes())
+        .map_err(|_| Error::other("Encryption failed"))?;
+
+    let mut data = Vec::with_capacity(MAGIC.len() + SALT_LEN + NONCE_LEN + ciphertext.len());
+    data.extend_from_slice(MAGIC);
+    data.extend_from_slice(&salt);
+    data.extend_from_slice(&nonce);
+    data.extend_from_slice(&ciphertext);
+    Ok(data)
+}
+
+pub fn decrypt(data: &[u8], passphrase: &str) -> Result<String, Error> {
+    let header_len = MAGIC.len() + SALT_LEN + NONCE_LEN;
+    if !is_encrypted(data) || data.len() < header_len {
+        return Err(Error::new(ErrorKind::InvalidData, "Not an encrypted note"));
+    }
+
+    let salt = &data[MAGIC.len()..MAGIC.len() + SALT_LEN];
+    let nonce = Nonce::from_slice(&data[MAGIC.len() + SALT_LEN..header_len]);
+    let cipher = cipher_for(passphrase, salt)?;
+
+    let plaintext = cipher
+        .decrypt(nonce, &data[header_len..])
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "Wrong passphrase or corrupted file"))?;
+    String::from_utf8(plaintext).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+// Write note content to disk, encrypting it when a passphrase is given.
+// .qnenc files are never written in plaintext.
+pub fn write_file(path: &str, content: &str, passphrase: Option<&str>) -> Result<(), Error> {
+    match passphrase {
+        Some(passphrase) => std::fs::write(path, encrypt(content, passphrase)?),
+        None => {
+            check_plaintext_path(path)?;
+            std::fs::write(path, content)
+        }
+    }
+}
+
+// Error for plaintext about to be written to a .qnenc path, checked before streaming a save
+pub fn check_plaintext_path(path: &str) -> Result<(), Error> {
+    if is_encrypted_path(path) {
+        return Err(Error::new(
+            ErrorKind::PermissionDenied,
+            "Refusing to write an encrypted note without a passphrase",
+        ));
+    }
+    Ok(())
+}
+
+fn cipher_for(passphrase: &str, salt: &[u8]) -> Result<Aes256Gcm, Error> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| Error::other(e.to_string()))?;
+    Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_roundtrip() {
+        let data = encrypt("secret note\nline two", "hunter2").unwrap();
+        assert!(is_encrypted(&data));
+        assert!(!data.windows(6).any(|w| w == b"secret"));
+
+        assert_eq!(decrypt(&data, "hunter2").unwrap(), "secret note\nline two");
+        assert!(decrypt(&data, "wrong").is_err());
+    }
+}
@@ -0,0 +1,233 @@
+// diff module - line diff of two buffers laid out as side-by-side rows
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RowKind {
+    Same,
+    Changed, // Line differs on both sides
+    Removed, // Only on the left
+    Added,   // Only on the right
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiffRow {
+    pub left: Option<usize>, // Line indices into each side
+    pub right: Option<usize>,
+    pub kind: RowKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+// Above this many edits the middle of the files is shown as one replaced block
+const MAX_EDITS: usize = 1000;
+
+// Pair up the lines of `left` and `right`; runs of removed + added lines become Changed rows
+pub fn side_by_side(left: &[String], right: &[String]) -> Vec<DiffRow> {
+    let mut rows = Vec::new();
+    let (mut l, mut r) = (0, 0);
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+
+    for op in diff_ops(left, right) {
+        match op {
+            Op::Delete => {
+                removed.push(l);
+                l += 1;
+            }
+            Op::Insert => {
+                added.push(r);
+                r += 1;
+            }
+            Op::Equal => {
+                flush_change(&mut rows, &mut removed, &mut added);
+                rows.push(DiffRow { left: Some(l), right: Some(r), kind: RowKind::Same });
+                l += 1;
+                r += 1;
+            }
+        }
+    }
+    flush_change(&mut rows, &mut removed, &mut added);
+    rows
+}
+
+fn flush_change(rows: &mut Vec<DiffRow>, removed: &mut Vec<usize>, added: &mut Vec<usize>) {
+    for i in 0..removed.len().max(added.len()) {
+        let (left, right) = (removed.get(i).copied(), added.get(i).copied());
+        let kind = match (left, right) {
+            (Some(_), Some(_)) => RowKind::Changed,
+            (Some(_), None) => RowKind::Removed,
+            _ => RowKind::Added,
+        };
+        rows.push(DiffRow { left, right, kind });
+    }
+    removed.clear();
+    added.clear();
+}
+
+// Row indices where each block of changes begins, for next/previous navigation
+pub fn change_starts(rows: &[DiffRow]) -> Vec<usize> {
+    (0..rows.len())
+        .filter(|&i| rows[i].kind != RowKind::Same && (i == 0 || rows[i - 1].kind == RowKind::Same))
+        .collect()
+}
+
+// Char ranges that differ between two versions of a line, after the common prefix and suffix
+pub fn inline_change(left: &str, right: &str) -> ((usize, usize), (usize, usize)) {
+    let a: Vec<char> = left.chars().collect();
+    let b: Vec<char> = right.chars().collect();
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    ((prefix, a.len() - suffix), (prefix, b.len() - suffix))
+}
+
+// Line ranges of `a` replaced by line ranges of `b`, in order
+pub fn hunks(a: &[String], b: &[String]) -> Vec<(Range<usize>, Range<usize>)> {
+    let mut hunks: Vec<(Range<usize>, Range<usize>)> = Vec::new();
+    let (mut l, mut r) = (0, 0);
+    for op in diff_ops(a, b) {
+        if op == Op::Equal {
+            l += 1;
+            r += 1;
+            continue;
+        }
+        match hunks.last_mut() {
+            Some((old, new)) if old.end == l && new.end == r => {}
+            _ => hunks.push((l..l, r..r)),
+        }
+        let (old, new) = hunks.last_mut().unwrap();
+        if op == Op::Delete {
+            l += 1;
+            old.end = l;
+        } else {
+            r += 1;
+            new.end = r;
+        }
+    }
+    hunks
+}
+
+// Myers' O((N+M)D) shortest edit script, after trimming the common prefix and suffix
+fn diff_ops(a: &[String], b: &[String]) -> Vec<Op> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut ops = vec![Op::Equal; prefix];
+    match myers(a_mid, b_mid) {
+        Some(middle) => ops.extend(middle),
+        None => {
+            ops.extend(std::iter::repeat_n(Op::Delete, a_mid.len()));
+            ops.extend(std::iter::repeat_n(Op::Insert, b_mid.len()));
+        }
+    }
+    ops.extend(std::iter::repeat_n(Op::Equal, suffix));
+    ops
+}
+
+fn myers(a: &[String], b: &[String]) -> Option<Vec<Op>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (a.len() + b.len()).min(MAX_EDITS) as isize;
+    let offset = max + 1;
+    let mut v = vec![0isize; 2 * offset as usize + 1];
+    let mut trace = Vec::new();
+
+    // Furthest x reached on diagonal k in the previous round
+    let pick = |v: &[isize], k: isize, d: isize| {
+        let idx = (k + offset) as usize;
+        if k == -d || (k != d && v[idx - 1] < v[idx + 1]) { (k + 1, v[idx + 1]) } else { (k - 1, v[idx - 1] + 1) }
+    };
+
+    let mut done = false;
+    for d in 0..=max {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let (_, mut x) = pick(&v, k, d);
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[(k + offset) as usize] = x;
+            if x >= n && y >= m {
+                done = true;
+                break;
+            }
+        }
+        if done {
+            break;
+        }
+    }
+    if !done {
+        return None;
+    }
+
+    // Walk the trace backwards from (n, m) to recover the edits
+    let mut ops = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let (prev_k, _) = pick(v, k, d);
+        let prev_x = v[(prev_k + offset) as usize];
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            ops.push(Op::Equal);
+        }
+        if d > 0 {
+            if x == prev_x {
+                y -= 1;
+                ops.push(Op::Insert);
+            } else {
+                x -= 1;
+                ops.push(Op::Delete);
+            }
+        }
+    }
+    ops.reverse();
+    Some(ops)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.split('\n').map(String::from).collect()
+    }
+
+    #[test]
+    fn test_side_by_side() {
+        let left = lines("a\nb\nc\nd\ne");
+        let right = lines("a\nB\nc\ne\nf");
+        let kinds: Vec<RowKind> = side_by_side(&left, &right).iter().map(|row| row.kind).collect();
+        assert_eq!(
+            kinds,
+            [RowKind::Same, RowKind::Changed, RowKind::Same, RowKind::Removed, RowKind::Same, RowKind::Added]
+        );
+
+        let rows = side_by_side(&left, &right);
+        assert_eq!(change_starts(&rows), [1, 3, 5]);
+        assert_eq!(rows[5], DiffRow { left: None, right: Some(4), kind: RowKind::Added });
+        assert_eq!(hunks(&left, &right), [(1..2, 1..2), (3..4, 3..3), (5..5, 4..5)]);
+
+        assert_eq!(inline_change("let x = 1;", "let x = 22;"), ((8, 9), (8, 10)));
+        assert!(side_by_side(&[], &[]).is_empty());
+    }
+}
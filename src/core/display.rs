@@ -0,0 +1,211 @@
+// display module - how a line is drawn: tabs out to the next tab stop, control characters as ^X
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+// A line as drawn, with the position of every original char in it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expanded {
+    pub text: String,
+    offsets: Vec<usize>, // Display char index of each original char, plus one past the end
+}
+
+impl Expanded {
+    // Original char column -> char column in `text`
+    pub fn to_display(&self, column: usize) -> usize {
+        let last = self.offsets.len() - 1;
+        match self.offsets.get(column) {
+            Some(&offset) => offset,
+            None => self.offsets[last] + column - last,
+        }
+    }
+
+    // Grapheme column in `line`, the text this was expanded from -> grapheme column in `text`.
+    // Columns past the end stay that far past it
+    pub fn grapheme_to_display(&self, line: &str, column: usize) -> usize {
+        let (mut graphemes, mut chars) = (0, 0);
+        for grapheme in line.graphemes(true).take(column) {
+            graphemes += 1;
+            chars += grapheme.chars().count();
+        }
+        let display = self.to_display(chars);
+        let byte = self.text.char_indices().nth(display).map_or(self.text.len(), |(i, _)| i);
+        self.text[..byte].graphemes(true).count() + column - graphemes
+    }
+
+    // Char column in `text` -> original char column; inside a tab this is the tab itself
+    pub fn to_logical(&self, column: usize) -> usize {
+        self.offsets.iter().rposition(|&offset| offset <= column).unwrap_or(0)
+    }
+
+    // Cells the line takes on screen
+    pub fn width(&self) -> usize {
+        str_width(&self.text)
+    }
+}
+
+// How wide the terminal really draws the sequences terminals disagree on. The defaults follow
+// unicode-width; the terminal editor measures its terminal at startup and adds "char_widths" from the config
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WidthRules {
+    pub narrow_vs16: bool, // A text character made emoji by U+FE0F keeps its one cell
+    pub split_zwj: bool, // Emoji joined by U+200D are drawn side by side instead of as one glyph
+    pub overrides: BTreeMap<String, usize>, // Cells for single graphemes
+}
+
+static WIDTH_RULES: OnceLock<WidthRules> = OnceLock::new();
+
+// Set once, before anything is measured; later calls are ignored
+pub fn set_width_rules(rules: WidthRules) {
+    let _ = WIDTH_RULES.set(rules);
+}
+
+// Cells `text` takes on screen under the width rules in effect
+pub fn str_width(text: &str) -> usize {
+    match WIDTH_RULES.get() {
+        Some(rules) if !text.is_ascii() => rules.width(text),
+        _ => text.width(),
+    }
+}
+
+impl WidthRules {
+    pub fn width(&self, text: &str) -> usize {
+        text.graphemes(true).map(|grapheme| self.grapheme_width(grapheme)).sum()
+    }
+
+    fn grapheme_width(&self, grapheme: &str) -> usize {
+        if let Some(&cells) = self.overrides.get(grapheme) {
+            return cells;
+        }
+        if self.split_zwj && grapheme.contains('\u{200D}') {
+            return grapheme.split('\u{200D}').map(|part| self.grapheme_width(part)).sum();
+        }
+        if self.narrow_vs16 && grapheme.contains('\u{FE0F}') {
+            return grapheme.replace('\u{FE0F}', "").width();
+        }
+        grapheme.width()
+    }
+
+    // The config's "char_widths" table, keeping single graphemes of one or two cells and naming the rest
+    pub fn with_overrides(mut self, table: &BTreeMap<String, usize>) -> (Self, Vec<String>) {
+        let mut problems = Vec::new();
+        for (grapheme, &cells) in table {
+            if grapheme.graphemes(true).count() != 1 {
+                problems.push(format!("char_widths: \"{}\" is not a single character", grapheme));
+            } else if !(1..=2).contains(&cells) {
+                problems.push(format!("char_widths: \"{}\" can only be 1 or 2 cells wide, not {}", grapheme, cells));
+            } else {
+                self.overrides.insert(grapheme.clone(), cells);
+            }
+        }
+        (self, problems)
+    }
+}
+
+// Lines longer than this many bytes are drawn a window at a time and only highlighted up to it
+pub const LONG_LINE: usize = 10_000;
+
+pub fn is_long(line: &str) -> bool {
+    line.len() > LONG_LINE
+}
+
+// "^I"-style placeholder for ASCII control characters
+pub fn control_placeholder(c: char) -> Option<String> {
+    (c.is_ascii_control() && c != '\t').then(|| format!("^{}", ((c as u8) ^ 0x40) as char))
+}
+
+pub fn expand(line: &str, tab_width: usize) -> Expanded {
+    let mut offsets = Vec::with_capacity(line.len() + 1);
+    let text = expand_with(line, tab_width, |chars| offsets.push(chars));
+    Expanded { text, offsets }
+}
+
+// Cells a line takes on screen, without keeping the column map `expand` builds
+pub fn width(line: &str, tab_width: usize) -> usize {
+    expand_with(line, tab_width, |_| {}).width()
+}
+
+// Expanded text, handing each original char's display column to `offset` as it goes
+fn expand_with(line: &str, tab_width: usize, mut offset: impl FnMut(usize)) -> String {
+    let tab_width = tab_width.max(1);
+    let mut text = String::with_capacity(line.len());
+    let mut chars = 0;
+    // Cells up to `measured` bytes of `text`, the rest is measured when a tab needs it
+    let mut cells = 0;
+    let mut measured = 0;
+
+    for c in line.chars() {
+        offset(chars);
+        if c == '\t' {
+            cells += str_width(&text[measured..]);
+            let spaces = tab_width - cells % tab_width;
+            text.extend(std::iter::repeat_n(' ', spaces));
+            chars += spaces;
+            cells += spaces;
+            measured = text.len();
+        } else if let Some(placeholder) = control_placeholder(c) {
+            text.push_str(&placeholder);
+            chars += 2;
+        } else {
+            text.push(c);
+            chars += 1;
+        }
+    }
+    offset(chars);
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_tabs_and_controls() {
+        let shown = expand("a\tb\u{1b}c", 4);
+        assert_eq!(shown.text, "a   b^[c");
+        assert_eq!(shown.to_display(1), 1);
+        assert_eq!(shown.to_display(2), 4);
+        assert_eq!(shown.to_display(4), 7);
+        assert_eq!(shown.to_display(5), 8);
+        assert_eq!(shown.to_display(7), 10);
+        assert_eq!(shown.to_logical(2), 1);
+        assert_eq!(shown.to_logical(4), 2);
+        assert_eq!(shown.to_logical(6), 3);
+        assert_eq!(shown.to_logical(20), 5);
+        assert_eq!(shown.width(), 8);
+        assert_eq!(width("a\tb\u{1b}c", 4), 8);
+
+        // Tab stops count wide characters by their cells
+        assert_eq!(expand("漢\tx", 4).text, "漢  x");
+        assert_eq!(expand("plain", 8).text, "plain");
+    }
+
+    #[test]
+    fn test_width_rules() {
+        let heart = "\u{2764}\u{FE0F}";
+        let family = "👨\u{200D}👩\u{200D}👧";
+        let unicode = WidthRules::default();
+        assert_eq!((unicode.width(heart), unicode.width(family)), (2, 2));
+
+        let narrow = WidthRules { narrow_vs16: true, split_zwj: true, ..WidthRules::default() };
+        assert_eq!((narrow.width(heart), narrow.width(family)), (1, 6));
+        assert_eq!(narrow.width("a漢b"), 4);
+
+        let table = [("☺".to_string(), 2), ("ab".to_string(), 1), ("x".to_string(), 3)].into_iter().collect();
+        let (rules, problems) = narrow.with_overrides(&table);
+        assert_eq!(rules.width("☺☺"), 4);
+        assert_eq!(problems.len(), 2);
+    }
+
+    #[test]
+    fn test_grapheme_columns_through_expansion() {
+        // "e" + combining acute and a family emoji are one column each but several chars
+        let line = "e\u{301}\t👨‍👩‍👧x\u{1}y";
+        let shown = expand(line, 4);
+        assert_eq!(shown.text, "e\u{301}   👨‍👩‍👧x^Ay");
+        let columns: Vec<usize> = (0..=7).map(|column| shown.grapheme_to_display(line, column)).collect();
+        assert_eq!(columns, [0, 1, 4, 5, 6, 8, 9, 10]);
+        assert_eq!(expand("ab", 4).grapheme_to_display("ab", 1), 1);
+    }
+}
@@ -1,8 +1,17 @@
 // edit_history module - delta-based undo/redo system
-use crate::tui::caret::Position;
+use crate::core::diff;
+use crate::core::selection::Position;
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+// Undo memory allowed per tab before the oldest entries are dropped
+const MEMORY_BUDGET: usize = 32 * 1024 * 1024;
+// Entries holding more text than this are kept deflated until undone
+const COMPRESS_ABOVE: usize = 64 * 1024;
 
 // Represents a single atomic edit operation that can be undone/redone
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Edit {
     // Insert text at a position (line, column, text)
     InsertText {
@@ -41,10 +50,31 @@ pub enum Edit {
         old_text: String,
         new_text: String,
     },
+    // Replace whole lines (for line based commands like checklists)
+    ReplaceLines {
+        start_line: usize,
+        old_lines: Vec<String>,
+        new_lines: Vec<String>,
+    },
+    // Several separate line replacements, ordered by line, stored instead of one large ReplaceLines
+    ReplaceHunks {
+        hunks: Vec<LineHunk>,
+    },
+    // Edits made as one step (like paste over a selection), applied in order and undone together
+    Batch {
+        edits: Vec<Edit>,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LineHunk {
+    pub start_line: usize, // In the buffer before the edit
+    pub old_lines: Vec<String>,
+    pub new_lines: Vec<String>,
 }
 
 // A complete edit operation with before/after cursor state
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct EditOperation {
     pub edit: Edit,
     pub cursor_before: Position,
@@ -53,11 +83,60 @@ pub struct EditOperation {
     pub scroll_after: usize,
 }
 
+// An operation as kept on the undo/redo stacks
+#[derive(Clone)]
+enum Entry {
+    Plain(EditOperation),
+    Deflated(Vec<u8>),
+}
+
+impl Entry {
+    fn new(operation: EditOperation) -> Self {
+        if operation.edit.text_size() <= COMPRESS_ABOVE {
+            return Entry::Plain(operation);
+        }
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::fast());
+        let packed = serde_json::to_vec(&operation)
+            .ok()
+            .and_then(|json| encoder.write_all(&json).ok().and(encoder.finish().ok()));
+        match packed {
+            Some(bytes) => Entry::Deflated(bytes),
+            None => Entry::Plain(operation),
+        }
+    }
+
+    fn operation(&self) -> Option<EditOperation> {
+        match self {
+            Entry::Plain(operation) => Some(operation.clone()),
+            Entry::Deflated(bytes) => {
+                let mut json = Vec::new();
+                DeflateDecoder::new(&bytes[..]).read_to_end(&mut json).ok()?;
+                serde_json::from_slice(&json).ok()
+            }
+        }
+    }
+
+    // Approximate heap use
+    fn size(&self) -> usize {
+        match self {
+            Entry::Plain(operation) => operation.edit.text_size(),
+            Entry::Deflated(bytes) => bytes.len(),
+        }
+    }
+}
+
+// Entries are numbered so the text's state can be recognized after undo and redo
 #[derive(Clone)]
 pub struct EditHistory {
-    undo_stack: Vec<EditOperation>,
-    redo_stack: Vec<EditOperation>,
+    undo_stack: Vec<(u64, Entry)>,
+    redo_stack: Vec<(u64, Entry)>,
     max_history: usize,
+    max_bytes: usize,
+    next_id: u64,
+    base: u64,          // State with nothing left to undo: the last entry dropped for space, or 0
+    saved: Option<u64>, // State at the last save, None once it can't be reached again
+    transaction: Vec<EditOperation>, // Edits collected since begin(), pushed as one entry by end()
+    depth: usize,                    // Open begin() calls
     
     // For grouping rapid edits (like continuous typing)
     last_edit_time: std::time::Instant,
@@ -70,13 +149,49 @@ impl EditHistory {
             undo_stack: Vec::new(),
             redo_stack: Vec::new(),
             max_history,
+            max_bytes: MEMORY_BUDGET,
+            next_id: 1,
+            base: 0,
+            saved: Some(0),
+            transaction: Vec::new(),
+            depth: 0,
             last_edit_time: std::time::Instant::now(),
             grouping_threshold_ms: 500, // Group edits within 500ms
         }
     }
     
+    // Start collecting edits into a single undo step; calls nest, the outermost end() pushes it
+    pub fn begin(&mut self) {
+        self.depth += 1;
+    }
+
+    pub fn end(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+        if self.depth > 0 || self.transaction.is_empty() {
+            return;
+        }
+        let mut operations = std::mem::take(&mut self.transaction);
+        let operation = if operations.len() == 1 {
+            operations.remove(0)
+        } else {
+            let (first, last) = (&operations[0], &operations[operations.len() - 1]);
+            EditOperation {
+                cursor_before: first.cursor_before,
+                cursor_after: last.cursor_after,
+                scroll_before: first.scroll_before,
+                scroll_after: last.scroll_after,
+                edit: Edit::Batch { edits: operations.into_iter().map(|op| op.edit).collect() },
+            }
+        };
+        self.push(operation);
+    }
+
     // Push a new edit operation
-    pub fn push(&mut self, operation: EditOperation) {
+    pub fn push(&mut self, mut operation: EditOperation) {
+        if self.depth > 0 {
+            self.transaction.push(operation);
+            return;
+        }
         // Clear redo stack when new edit is made
         self.redo_stack.clear();
         
@@ -84,30 +199,46 @@ impl EditHistory {
         let now = std::time::Instant::now();
         let should_group = self.can_group_with_last(&operation, now);
         
-        if should_group {
+        // Growing the saved entry would make the saved state unreachable
+        if should_group && !self.is_saved() {
             // Try to merge with last operation
-            if let Some(mut last_op) = self.undo_stack.pop() {
-                if Self::try_merge_operations(&mut last_op, &operation) {
-                    // Merge successful, push back the merged operation
-                    self.undo_stack.push(last_op);
+            if let Some((_, Entry::Plain(last_op))) = self.undo_stack.last_mut() {
+                if Self::try_merge_operations(last_op, &operation) {
                     self.last_edit_time = now;
                     return;
-                } else {
-                    // Merge failed, push back the old operation
-                    self.undo_stack.push(last_op);
                 }
             }
         }
         
         // Add as new operation
-        self.undo_stack.push(operation);
+        operation.edit = operation.edit.compact();
+        self.undo_stack.push((self.next_id, Entry::new(operation)));
+        self.next_id += 1;
         self.last_edit_time = now;
         
-        // Limit stack size
-        if self.undo_stack.len() > self.max_history {
-            self.undo_stack.remove(0);
+        // Limit stack size and memory, always keeping the newest edit
+        let mut bytes: usize = self.undo_stack.iter().map(|(_, entry)| entry.size()).sum();
+        while self.undo_stack.len() > 1 && (self.undo_stack.len() > self.max_history || bytes > self.max_bytes) {
+            let (id, entry) = self.undo_stack.remove(0);
+            self.base = id;
+            bytes -= entry.size();
         }
     }
+
+    // Id of the state the text is in: the newest entry left to undo
+    fn state(&self) -> u64 {
+        self.undo_stack.last().map_or(self.base, |(id, _)| *id)
+    }
+
+    // Remember the current state as the one on disk
+    pub fn mark_saved(&mut self) {
+        self.saved = Some(self.state());
+    }
+
+    // Undo and redo brought the text back to what was last saved
+    pub fn is_saved(&self) -> bool {
+        self.saved == Some(self.state())
+    }
     
     // Check if we can group this edit with the previous one
     fn can_group_with_last(&self, operation: &EditOperation, now: std::time::Instant) -> bool {
@@ -121,7 +252,7 @@ impl EditHistory {
         }
         
         // Only group similar operations on the same line
-        if let Some(last_op) = self.undo_stack.last() {
+        if let Some((_, Entry::Plain(last_op))) = self.undo_stack.last() {
             match (&last_op.edit, &operation.edit) {
                 (Edit::InsertText { line: l1, .. }, Edit::InsertText { line: l2, .. }) => l1 == l2,
                 (Edit::DeleteText { line: l1, .. }, Edit::DeleteText { line: l2, .. }) => l1 == l2,
@@ -133,13 +264,15 @@ impl EditHistory {
     }
     
     // Try to merge two operations (for continuous typing/deleting)
+    // Columns count graphemes, so the texts are measured in graphemes too
     fn try_merge_operations(last: &mut EditOperation, new: &EditOperation) -> bool {
+        use unicode_segmentation::UnicodeSegmentation;
         match (&mut last.edit, &new.edit) {
             // Merge continuous character insertions
             (
                 Edit::InsertText { line: l1, column: c1, text: t1 },
                 Edit::InsertText { line: l2, column: c2, text: t2 }
-            ) if l1 == l2 && *c1 + t1.len() == *c2 => {
+            ) if l1 == l2 && *c1 + t1.graphemes(true).count() == *c2 => {
                 t1.push_str(t2);
                 last.cursor_after = new.cursor_after;
                 last.scroll_after = new.scroll_after;
@@ -149,7 +282,7 @@ impl EditHistory {
             (
                 Edit::DeleteText { line: l1, column: c1, text: t1 },
                 Edit::DeleteText { line: l2, column: c2, text: t2 }
-            ) if l1 == l2 && *c2 == c1.saturating_sub(t2.len()) => {
+            ) if l1 == l2 && *c2 + t2.graphemes(true).count() == *c1 => {
                 // Prepend the newly deleted text
                 *t1 = format!("{}{}", t2, t1);
                 *c1 = *c2;
@@ -163,26 +296,76 @@ impl EditHistory {
     
     // Get the next operation to undo
     pub fn undo(&mut self) -> Option<EditOperation> {
-        if let Some(operation) = self.undo_stack.pop() {
-            self.redo_stack.push(operation.clone());
-            Some(operation)
-        } else {
-            None
-        }
+        let entry = self.undo_stack.pop()?;
+        let operation = entry.1.operation();
+        self.redo_stack.push(entry);
+        operation
     }
     
     // Get the next operation to redo
     pub fn redo(&mut self) -> Option<EditOperation> {
-        if let Some(operation) = self.redo_stack.pop() {
-            self.undo_stack.push(operation.clone());
-            Some(operation)
-        } else {
-            None
-        }
+        let entry = self.redo_stack.pop()?;
+        let operation = entry.1.operation();
+        self.undo_stack.push(entry);
+        operation
     }
 }
 
+// Replace the text between two (line, grapheme column) positions, which may span lines
+fn splice_text(buffer: &mut Vec<String>, start: (usize, usize), end: (usize, usize), text: &str) {
+    use unicode_segmentation::UnicodeSegmentation;
+    let byte = |line: &str, column: usize| {
+        line.grapheme_indices(true).nth(column).map_or(line.len(), |(idx, _)| idx)
+    };
+    let (Some(first), Some(last)) = (buffer.get(start.0), buffer.get(end.0)) else {
+        return;
+    };
+    let joined = format!("{}{}{}", &first[..byte(first, start.1)], text, &last[byte(last, end.1)..]);
+    buffer.splice(start.0..=end.0, joined.split('\n').map(String::from).collect::<Vec<_>>());
+}
+
 impl Edit {
+    // Bytes of text the edit holds
+    fn text_size(&self) -> usize {
+        let lines = |lines: &[String]| lines.iter().map(String::len).sum::<usize>();
+        match self {
+            Edit::InsertText { text, .. } | Edit::DeleteText { text, .. } => text.len(),
+            Edit::InsertLine { remaining_text, .. } => remaining_text.len(),
+            Edit::DeleteLine { content, .. } => content.len(),
+            Edit::JoinLines { .. } => 0,
+            Edit::ReplaceRange { old_text, new_text, .. } => old_text.len() + new_text.len(),
+            Edit::ReplaceLines { old_lines, new_lines, .. } => lines(old_lines) + lines(new_lines),
+            Edit::ReplaceHunks { hunks } => hunks.iter().map(|h| lines(&h.old_lines) + lines(&h.new_lines)).sum(),
+            Edit::Batch { edits } => edits.iter().map(Edit::text_size).sum(),
+        }
+    }
+
+    // Keep only the lines a ReplaceLines actually changed, split into hunks
+    fn compact(self) -> Edit {
+        let Edit::ReplaceLines { start_line, old_lines, new_lines } = self else {
+            return match self {
+                Edit::Batch { edits } => Edit::Batch { edits: edits.into_iter().map(Edit::compact).collect() },
+                edit => edit,
+            };
+        };
+        let mut hunks: Vec<LineHunk> = diff::hunks(&old_lines, &new_lines)
+            .into_iter()
+            .map(|(old, new)| LineHunk {
+                start_line: start_line + old.start,
+                old_lines: old_lines[old].to_vec(),
+                new_lines: new_lines[new].to_vec(),
+            })
+            .collect();
+        match hunks.len() {
+            0 => Edit::ReplaceLines { start_line, old_lines, new_lines },
+            1 => {
+                let hunk = hunks.remove(0);
+                Edit::ReplaceLines { start_line: hunk.start_line, old_lines: hunk.old_lines, new_lines: hunk.new_lines }
+            }
+            _ => Edit::ReplaceHunks { hunks },
+        }
+    }
+
     pub fn apply(&self, buffer: &mut Vec<String>) {
         use unicode_segmentation::UnicodeSegmentation;
         
@@ -221,14 +404,21 @@ impl Edit {
             },
             Edit::InsertLine { line, remaining_text } => {
                 if *line < buffer.len() {
+                    // The text after the split moves down, so it leaves the current line
+                    let current = &mut buffer[*line];
+                    if current.ends_with(remaining_text.as_str()) {
+                        current.truncate(current.len() - remaining_text.len());
+                    }
                     buffer.insert(line + 1, remaining_text.clone());
                 } else {
                     buffer.push(remaining_text.clone());
                 }
             },
             Edit::DeleteLine { line, .. } => {
-                if *line < buffer.len() {
-                    buffer.remove(*line);
+                // The deleted line's text joins the end of the previous one
+                if *line > 0 && *line < buffer.len() {
+                    let content = buffer.remove(*line);
+                    buffer[line - 1].push_str(&content);
                 }
             },
             Edit::JoinLines { line, .. } => {
@@ -240,34 +430,22 @@ impl Edit {
                 }
             },
             Edit::ReplaceRange { start_line, start_column, end_line, end_column, new_text, .. } => {
-                if start_line == end_line {
-                    // Single line replacement
-                    if let Some(line_content) = buffer.get_mut(*start_line) {
-                        let byte_start = line_content
-                            .grapheme_indices(true)
-                            .nth(*start_column)
-                            .map(|(idx, _)| idx)
-                            .unwrap_or(line_content.len());
-                        
-                        let byte_end = line_content
-                            .grapheme_indices(true)
-                            .nth(*end_column)
-                            .map(|(idx, _)| idx)
-                            .unwrap_or(line_content.len());
-                        
-                        line_content.replace_range(byte_start..byte_end, new_text);
-                    }
-                } else {
-                    // Multi-line - remove and insert
-                    for _ in *start_line..=*end_line {
-                        if *start_line < buffer.len() {
-                            buffer.remove(*start_line);
-                        }
-                    }
-                    let new_lines: Vec<&str> = new_text.split('\n').collect();
-                    for (i, line) in new_lines.iter().enumerate() {
-                        buffer.insert(start_line + i, line.to_string());
-                    }
+                splice_text(buffer, (*start_line, *start_column), (*end_line, *end_column), new_text);
+            },
+            Edit::ReplaceLines { start_line, old_lines, new_lines } => {
+                let end = (start_line + old_lines.len()).min(buffer.len());
+                buffer.splice((*start_line).min(end)..end, new_lines.iter().cloned());
+            },
+            Edit::ReplaceHunks { hunks } => {
+                // Last hunk first so earlier line numbers stay valid
+                for hunk in hunks.iter().rev() {
+                    let end = (hunk.start_line + hunk.old_lines.len()).min(buffer.len());
+                    buffer.splice(hunk.start_line.min(end)..end, hunk.new_lines.iter().cloned());
+                }
+            },
+            Edit::Batch { edits } => {
+                for edit in edits {
+                    edit.apply(buffer);
                 }
             },
         }
@@ -314,8 +492,8 @@ impl Edit {
                     }
                 }
             },
-            Edit::DeleteLine { line, content, prev_line_end_len } => {
-                // Re-insert the deleted line
+            Edit::DeleteLine { line, prev_line_end_len, .. } => {
+                // Split the merged text back off the previous line
                 if *line > 0 && *line <= buffer.len() {
                     if let Some(prev) = buffer.get_mut(line - 1) {
                         // Convert grapheme index to byte index for split
@@ -326,7 +504,7 @@ impl Edit {
                             .unwrap_or(prev.len());
                         
                         let split_content = prev.split_off(byte_idx);
-                        buffer.insert(*line, format!("{}{}", content, split_content));
+                        buffer.insert(*line, split_content);
                     }
                 }
             },
@@ -343,26 +521,292 @@ impl Edit {
                     buffer.insert(line + 1, split_content);
                 }
             },
-            Edit::ReplaceRange { start_line, start_column, end_line, end_column, old_text, .. } => {
-                // Replace back with old text (reverse operation)
-                if start_line == end_line {
-                    if let Some(line_content) = buffer.get_mut(*start_line) {
-                        let byte_start = line_content
-                            .grapheme_indices(true)
-                            .nth(*start_column)
-                            .map(|(idx, _)| idx)
-                            .unwrap_or(line_content.len());
-                        
-                        let byte_end = line_content
-                            .grapheme_indices(true)
-                            .nth(*end_column)
-                            .map(|(idx, _)| idx)
-                            .unwrap_or(line_content.len());
-                        
-                        line_content.replace_range(byte_start..byte_end, old_text);
-                    }
+            Edit::ReplaceRange { start_line, start_column, old_text, new_text, .. } => {
+                // The new text now runs from the start to where its last line ends
+                let end = match new_text.rsplit_once('\n') {
+                    Some((_, last)) => (start_line + new_text.matches('\n').count(), last.graphemes(true).count()),
+                    None => (*start_line, start_column + new_text.graphemes(true).count()),
+                };
+                splice_text(buffer, (*start_line, *start_column), end, old_text);
+            },
+            Edit::ReplaceLines { start_line, old_lines, new_lines } => {
+                let end = (start_line + new_lines.len()).min(buffer.len());
+                buffer.splice((*start_line).min(end)..end, old_lines.iter().cloned());
+            },
+            Edit::ReplaceHunks { hunks } => {
+                // Where each hunk starts after the edit, shifted by the hunks before it
+                let mut shift = 0isize;
+                let starts: Vec<usize> = hunks
+                    .iter()
+                    .map(|hunk| {
+                        let start = (hunk.start_line as isize + shift) as usize;
+                        shift += hunk.new_lines.len() as isize - hunk.old_lines.len() as isize;
+                        start
+                    })
+                    .collect();
+                for (hunk, start) in hunks.iter().zip(starts).rev() {
+                    let end = (start + hunk.new_lines.len()).min(buffer.len());
+                    buffer.splice(start.min(end)..end, hunk.old_lines.iter().cloned());
+                }
+            },
+            Edit::Batch { edits } => {
+                for edit in edits.iter().rev() {
+                    edit.reverse(buffer);
                 }
             },
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::selection::TextPosition;
+    use crate::core::widget::text_between;
+    use proptest::prelude::*;
+    use unicode_segmentation::UnicodeSegmentation;
+
+    fn operation(edit: Edit) -> EditOperation {
+        EditOperation { edit, cursor_before: Position::default(), cursor_after: Position::default(), scroll_before: 0, scroll_after: 0 }
+    }
+
+    // Whole clusters that stay whole next to each other: combining accents, ZWJ emoji, flags, wide and tab
+    const CLUSTERS: [&str; 9] = ["a", "Z", " ", "\t", "é", "e\u{301}", "👩\u{200d}💻", "🇵🇱", "界"];
+
+    fn line() -> impl Strategy<Value = String> {
+        prop::collection::vec(prop::sample::select(&CLUSTERS[..]), 0..8).prop_map(|clusters| clusters.concat())
+    }
+
+    fn lines() -> impl Strategy<Value = Vec<String>> {
+        prop::collection::vec(line(), 1..6)
+    }
+
+    fn text() -> impl Strategy<Value = String> {
+        prop::collection::vec(line(), 1..4).prop_map(|lines| lines.join("\n"))
+    }
+
+    // A valid edit of the given kind for the buffer, placed by the picks. Kinds that don't fit
+    // the buffer (joining a single line) fall back to typing
+    fn edit_for(buffer: &[String], kind: usize, picks: [usize; 4], text: &str) -> Edit {
+        let count = |line: &str| line.graphemes(true).count();
+        let lines = buffer.len();
+        let line = picks[0] % lines;
+        let len = count(&buffer[line]);
+        let column = picks[1] % (len + 1);
+        let first_line = text.split('\n').next().unwrap_or_default().to_string();
+        match kind % 9 {
+            1 if column < len => {
+                let end = column + 1 + picks[2] % (len - column);
+                let deleted = buffer[line].graphemes(true).skip(column).take(end - column).collect();
+                Edit::DeleteText { line, column, text: deleted }
+            }
+            2 => Edit::InsertLine { line, remaining_text: buffer[line].graphemes(true).skip(column).collect() },
+            3 if line > 0 => {
+                Edit::DeleteLine { line, content: buffer[line].clone(), prev_line_end_len: count(&buffer[line - 1]) }
+            }
+            4 if line + 1 < lines => Edit::JoinLines { line, first_line_end: len },
+            5 => {
+                let end_line = line + picks[2] % (lines - line);
+                let end_column = match end_line == line {
+                    true => column + picks[3] % (len - column + 1),
+                    false => picks[3] % (count(&buffer[end_line]) + 1),
+                };
+                let old_text = text_between(buffer, TextPosition { line, column }, TextPosition { line: end_line, column: end_column });
+                Edit::ReplaceRange { start_line: line, start_column: column, end_line, end_column, old_text, new_text: text.to_string() }
+            }
+            6 => {
+                let start_line = picks[1] % (lines + 1);
+                let end = start_line + picks[2] % (lines - start_line + 1);
+                let new_lines = text.split('\n').map(String::from).collect();
+                Edit::ReplaceLines { start_line, old_lines: buffer[start_line..end].to_vec(), new_lines }
+            }
+            7 => {
+                // Scattered changes over the whole buffer, which compact into hunks
+                let mut new_lines: Vec<String> = buffer
+                    .iter()
+                    .enumerate()
+                    .map(|(i, line)| if picks[2] >> i & 1 == 1 { format!("{}!", line) } else { line.clone() })
+                    .collect();
+                new_lines.insert(picks[3] % (lines + 1), first_line);
+                Edit::ReplaceLines { start_line: 0, old_lines: buffer.to_vec(), new_lines }.compact()
+            }
+            8 => {
+                let first = edit_for(buffer, picks[1] % 8, [picks[2], picks[3], picks[0], picks[1]], text);
+                let mut after = buffer.to_vec();
+                first.apply(&mut after);
+                let second = edit_for(&after, picks[2] % 8, [picks[3], picks[0], picks[1], picks[2]], text);
+                Edit::Batch { edits: vec![first, second] }
+            }
+            _ => Edit::InsertText { line, column, text: first_line },
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn prop_every_edit_round_trips(buffer in lines(), kind in 0..9usize, picks in any::<[usize; 4]>(), text in text()) {
+            let edit = edit_for(&buffer, kind, picks, &text);
+            let mut after = buffer.clone();
+            edit.apply(&mut after);
+
+            let mut undone = after.clone();
+            edit.reverse(&mut undone);
+            prop_assert_eq!(&undone, &buffer, "reversing {:?}", edit);
+            let mut redone = undone;
+            edit.apply(&mut redone);
+            prop_assert_eq!(&redone, &after, "reapplying {:?}", edit);
+        }
+
+        // Typing runs merge and transactions batch, so undoing lands on some earlier state, and
+        // undoing or redoing everything gets back exactly to the first and last
+        #[test]
+        fn prop_undo_redo_restore_content(
+            buffer in lines(),
+            steps in prop::collection::vec((0..9usize, any::<[usize; 4]>(), text()), 1..12),
+            transaction in any::<(usize, usize)>(),
+            undos in any::<usize>(),
+        ) {
+            let (from, to) = (transaction.0 % steps.len(), transaction.1 % steps.len());
+            let (from, to) = (from.min(to), from.max(to));
+            let mut history = EditHistory::new(500);
+            let mut current = buffer.clone();
+            let mut states = vec![buffer.clone()];
+            for (i, (kind, picks, text)) in steps.iter().enumerate() {
+                if i == from {
+                    history.begin();
+                }
+                let edit = edit_for(&current, *kind, *picks, text);
+                edit.apply(&mut current);
+                history.push(operation(edit));
+                states.push(current.clone());
+                if i == to {
+                    history.end();
+                }
+            }
+            let last = current.clone();
+
+            for _ in 0..undos % (steps.len() + 1) {
+                if let Some(op) = history.undo() {
+                    op.edit.reverse(&mut current);
+                }
+            }
+            prop_assert!(states.contains(&current));
+            while let Some(op) = history.redo() {
+                op.edit.apply(&mut current);
+            }
+            prop_assert_eq!(&current, &last);
+            while let Some(op) = history.undo() {
+                op.edit.reverse(&mut current);
+            }
+            prop_assert_eq!(&current, &buffer);
+        }
+    }
+
+    #[test]
+    fn test_compact_compress_and_evict() {
+        let before: Vec<String> = (0..2000).map(|i| format!("line {} {}", i, "x".repeat(60))).collect();
+        let mut after = before.clone();
+        after[10] = "changed".to_string();
+        after.remove(1500);
+        let mut buffer = after.clone();
+
+        let mut history = EditHistory::new(500);
+        history.push(operation(Edit::ReplaceLines { start_line: 0, old_lines: before.clone(), new_lines: after.clone() }));
+        assert!(matches!(&history.undo_stack[0].1, Entry::Plain(op) if op.edit.text_size() < 200));
+
+        let undone = history.undo().unwrap();
+        undone.edit.reverse(&mut buffer);
+        assert_eq!(buffer, before);
+        history.redo().unwrap().edit.apply(&mut buffer);
+        assert_eq!(buffer, after);
+
+        // A large paste is kept deflated and restored intact
+        let text = "pasted text ".repeat(10_000);
+        history.push(operation(Edit::InsertText { line: 0, column: 0, text: text.clone() }));
+        assert!(matches!(&history.undo_stack[1].1, Entry::Deflated(bytes) if bytes.len() < text.len() / 10));
+        assert!(matches!(history.undo().unwrap().edit, Edit::InsertText { text: t, .. } if t == text));
+
+        history.max_bytes = 100;
+        history.push(operation(Edit::InsertText { line: 0, column: 0, text: "a".repeat(150) }));
+        assert_eq!(history.undo_stack.len(), 1);
+    }
+
+    #[test]
+    fn test_saved_state() {
+        let typed = |column: usize| operation(Edit::InsertText { line: 0, column, text: "a".to_string() });
+        let mut history = EditHistory::new(2);
+        assert!(history.is_saved());
+        history.push(typed(0));
+        assert!(!history.is_saved());
+        history.mark_saved();
+
+        // Typing right after a save starts a new entry instead of growing the saved one
+        history.push(typed(1));
+        assert_eq!(history.undo_stack.len(), 2);
+        history.undo();
+        assert!(history.is_saved());
+        history.undo();
+        assert!(!history.is_saved());
+        history.redo();
+        assert!(history.is_saved());
+
+        // An edit after undoing past the save can't get back to it
+        history.undo();
+        history.push(typed(0));
+        history.undo();
+        assert!(!history.is_saved());
+
+        // Dropping entries for space keeps the save reachable until the first edit after it goes
+        history.redo();
+        history.mark_saved();
+        let new_line = || operation(Edit::InsertLine { line: 0, remaining_text: String::new() });
+        history.push(new_line());
+        history.push(new_line());
+        while history.undo().is_some() {}
+        assert!(history.is_saved());
+        while history.redo().is_some() {}
+        history.push(new_line());
+        while history.undo().is_some() {}
+        assert!(!history.is_saved());
+    }
+
+    #[test]
+    fn test_transaction_undoes_as_one() {
+        let mut buffer = vec!["ab".to_string()];
+        let mut history = EditHistory::new(500);
+        history.begin();
+        for (column, text) in [(0, "x"), (3, "y")] {
+            let edit = Edit::InsertText { line: 0, column, text: text.to_string() };
+            edit.apply(&mut buffer);
+            history.push(operation(edit));
+            // Nested transactions join the outer one
+            history.begin();
+            history.end();
+        }
+        history.end();
+        assert_eq!(buffer, ["xaby"]);
+        assert_eq!(history.undo_stack.len(), 1);
+
+        history.undo().unwrap().edit.reverse(&mut buffer);
+        assert_eq!(buffer, ["ab"]);
+        history.redo().unwrap().edit.apply(&mut buffer);
+        assert_eq!(buffer, ["xaby"]);
+    }
+
+    #[test]
+    fn test_replace_range_across_lines() {
+        let original = vec!["hello world".to_string(), "second".to_string()];
+        let mut buffer = original.clone();
+        // Paste "A\nB" over "lo wor\nsec"
+        let edit = Edit::ReplaceRange {
+            start_line: 0,
+            start_column: 3,
+            end_line: 1,
+            end_column: 3,
+            old_text: "lo world\nsec".to_string(),
+            new_text: "A\nB".to_string(),
+        };
+        edit.apply(&mut buffer);
+        assert_eq!(buffer, ["helA", "Bond"]);
+        edit.reverse(&mut buffer);
+        assert_eq!(buffer, original);
+    }
+}
@@ -0,0 +1,94 @@
+// fetch module - text files from the web for `quick https://...`, opened read-only until saved
+use crate::core::progress::Progress;
+use reqwest::header::CONTENT_TYPE;
+use std::io::{Error, ErrorKind, Read};
+use std::time::Duration;
+
+pub const SAVE_HINT: &str = "Opened read-only from the web - save it to keep an editable local copy";
+
+pub struct Download {
+    pub name: String,              // Last part of the URL path, or the host for a bare site
+    pub extension: Option<String>, // From the name, else guessed from the Content-Type
+    pub text: String,
+}
+
+pub fn is_url(arg: &str) -> bool {
+    let lower = arg.to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://")
+}
+
+// Fetch `url` as UTF-8 text, reporting bytes received. Esc (progress.cancel) stops it
+pub fn download(url: &str, progress: &Progress) -> Result<Download, Error> {
+    let client = reqwest::blocking::Client::builder()
+        .user_agent(concat!("quick-notepad/", env!("CARGO_PKG_VERSION")))
+        .connect_timeout(Duration::from_secs(15))
+        .timeout(None)
+        .build()
+        .map_err(Error::other)?;
+    let mut response = client.get(url).send().and_then(|r| r.error_for_status()).map_err(Error::other)?;
+    let content_type = response.headers().get(CONTENT_TYPE).and_then(|v| v.to_str().ok()).map(String::from);
+    progress.set_total(response.content_length().unwrap_or(0));
+
+    let mut bytes = Vec::new();
+    let mut chunk = vec![0; 64 * 1024];
+    loop {
+        progress.check()?;
+        let read = response.read(&mut chunk)?;
+        if read == 0 {
+            break;
+        }
+        bytes.extend_from_slice(&chunk[..read]);
+        progress.advance(read as u64);
+    }
+    if bytes.contains(&0) {
+        return Err(Error::new(ErrorKind::InvalidData, format!("{} is not a text file", url)));
+    }
+    let text = String::from_utf8(bytes).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+    let name = file_name(url);
+    let extension = match name.rsplit_once('.') {
+        Some((_, ext)) if !ext.is_empty() => Some(ext.to_string()),
+        _ => content_type.as_deref().and_then(extension_for).map(String::from),
+    };
+    Ok(Download { name, extension, text })
+}
+
+// "https://host/dir/notes.md?raw=1" -> "notes.md"; "https://host/" -> "host"
+pub fn file_name(url: &str) -> String {
+    let url = url.split(['?', '#']).next().unwrap_or(url);
+    let mut parts = url.splitn(4, '/');
+    let host = parts.nth(2).unwrap_or(url);
+    parts.next().and_then(|path| path.rsplit('/').find(|part| !part.is_empty())).unwrap_or(host).to_string()
+}
+
+fn extension_for(content_type: &str) -> Option<&'static str> {
+    let mime = content_type.split(';').next()?.trim().to_ascii_lowercase();
+    Some(match mime.as_str() {
+        "application/json" => "json",
+        "text/html" => "html",
+        "text/markdown" => "md",
+        "text/css" => "css",
+        "text/javascript" | "application/javascript" => "js",
+        "text/xml" | "application/xml" => "xml",
+        "application/toml" => "toml",
+        "text/yaml" | "application/yaml" => "yaml",
+        "text/x-python" => "py",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_names() {
+        assert!(is_url("HTTPS://example.com/a.txt"));
+        assert!(!is_url("notes/http.txt"));
+        assert_eq!(file_name("https://example.com/dir/notes.md?raw=1#top"), "notes.md");
+        assert_eq!(file_name("https://example.com/dir/"), "dir");
+        assert_eq!(file_name("http://example.com"), "example.com");
+        assert_eq!(extension_for("application/json; charset=utf-8"), Some("json"));
+        assert_eq!(extension_for("text/plain"), None);
+    }
+}
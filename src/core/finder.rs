@@ -0,0 +1,104 @@
+// finder module - Ctrl+O fuzzy file finder over the project folder: the working directory,
+// which `quick ./src` moves into. Hidden folders and build output are left out
+use crate::core::palette::is_subsequence;
+use std::fs;
+use std::path::Path;
+
+const MAX_FILES: usize = 20_000; // Enough for any project, stops a walk started in / or ~
+const SKIPPED_DIRS: [&str; 3] = ["target", "node_modules", "__pycache__"];
+
+// Paths under `root`, relative to it with / separators, sorted
+pub fn files(root: &Path) -> Vec<String> {
+    let mut found = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with('.') {
+                continue;
+            }
+            let path = entry.path();
+            // Symlinked folders are skipped so a link back up can't loop
+            match entry.file_type() {
+                Ok(kind) if kind.is_dir() && !SKIPPED_DIRS.contains(&name.as_str()) => dirs.push(path),
+                Ok(kind) if kind.is_dir() => {}
+                Ok(kind) if kind.is_file() || (kind.is_symlink() && path.is_file()) => {
+                    let relative = path.strip_prefix(root).unwrap_or(&path);
+                    let parts: Vec<_> = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+                    found.push(parts.join("/"));
+                    if found.len() >= MAX_FILES {
+                        found.sort();
+                        return found;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    found.sort();
+    found
+}
+
+// Paths matching the query letters in order, best first: the file name starting with the
+// query, then containing it, then the path containing it, then any path with the letters
+// in order. Shorter paths win ties
+pub fn filter<'a>(files: &'a [String], query: &str) -> Vec<&'a String> {
+    rank(files, query).into_iter().map(|i| &files[i]).collect()
+}
+
+// Like filter, as positions in `files`
+pub fn rank(files: &[String], query: &str) -> Vec<usize> {
+    let query = query.to_lowercase();
+    let mut matches: Vec<(usize, usize, usize)> = files
+        .iter()
+        .enumerate()
+        .filter_map(|(i, path)| {
+            let lower = path.to_lowercase();
+            let name = lower.rsplit('/').next().unwrap_or(&lower);
+            let rank = if name.starts_with(&query) {
+                0
+            } else if name.contains(&query) {
+                1
+            } else if lower.contains(&query) {
+                2
+            } else if is_subsequence(&query, &lower) {
+                3
+            } else {
+                return None;
+            };
+            Some((rank, path.len(), i))
+        })
+        .collect();
+
+    matches.sort_by_key(|(rank, length, _)| (*rank, *length));
+    matches.into_iter().map(|(_, _, i)| i).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_files() {
+        let root = std::env::temp_dir().join(format!("quick_finder_{}", std::process::id()));
+        for dir in ["src/core", ".git", "target/debug"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in ["src/main.rs", "src/core/tabs.rs", "src/core/finder.rs", ".git/HEAD", "target/debug/quick", "README.md"] {
+            fs::write(root.join(file), "").unwrap();
+        }
+
+        let files = files(&root);
+        assert_eq!(files, ["README.md", "src/core/finder.rs", "src/core/tabs.rs", "src/main.rs"]);
+        assert_eq!(filter(&files, "main"), ["src/main.rs"]);
+        assert_eq!(filter(&files, "rs")[0], "src/main.rs"); // Shortest of the names containing it
+        assert_eq!(filter(&files, "core"), ["src/core/tabs.rs", "src/core/finder.rs"]);
+        assert_eq!(filter(&files, "scf"), ["src/core/finder.rs"]);
+        assert_eq!(filter(&files, "").len(), 4);
+        assert_eq!(rank(&files, "tabs"), [2]);
+        let _ = fs::remove_dir_all(&root);
+    }
+}
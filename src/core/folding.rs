@@ -0,0 +1,256 @@
+// folding module - collapse brace or indentation blocks into a single row
+use serde::{Deserialize, Serialize};
+
+// Lines start+1..=end are hidden behind the `start` line
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Fold {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Fold {
+    pub fn hidden_lines(&self) -> usize {
+        self.end - self.start
+    }
+
+    fn hides(&self, line: usize) -> bool {
+        line > self.start && line <= self.end
+    }
+}
+
+fn indent_of(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+// Block opened by `line`: up to the matching bracket when it ends with one,
+// otherwise the following lines indented deeper than it
+pub fn block_range(lines: &[String], line: usize) -> Option<(usize, usize)> {
+    let text = lines.get(line)?.trim_end();
+    if text.trim().is_empty() {
+        return None;
+    }
+
+    if text.ends_with(['{', '[', '(']) {
+        let mut depth = 0usize;
+        for (idx, next) in lines.iter().enumerate().skip(line) {
+            let scan = if idx == line { text } else { next.as_str() };
+            for c in scan.chars() {
+                match c {
+                    '{' | '[' | '(' => depth += 1,
+                    '}' | ']' | ')' => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+            }
+            if depth == 0 {
+                return (idx > line).then_some((line, idx));
+            }
+        }
+        return None;
+    }
+
+    let indent = indent_of(text);
+    let mut end = line;
+    for (idx, next) in lines.iter().enumerate().skip(line + 1) {
+        if next.trim().is_empty() {
+            continue;
+        }
+        if indent_of(next) <= indent {
+            break;
+        }
+        end = idx;
+    }
+    (end > line).then_some((line, end))
+}
+
+// Innermost block containing `line`, searching upwards for its opening line
+pub fn block_at(lines: &[String], line: usize) -> Option<(usize, usize)> {
+    (0..=line.min(lines.len().saturating_sub(1)))
+        .rev()
+        .filter_map(|start| block_range(lines, start))
+        .find(|&(_, end)| end >= line)
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Folds {
+    folds: Vec<Fold>, // Sorted by start, never overlapping
+}
+
+impl Folds {
+    pub fn from_list(mut folds: Vec<Fold>) -> Self {
+        folds.sort_by_key(|f| f.start);
+        let mut result: Vec<Fold> = Vec::new();
+        for fold in folds {
+            if fold.end > fold.start && result.last().is_none_or(|last| fold.start > last.end) {
+                result.push(fold);
+            }
+        }
+        Self { folds: result }
+    }
+
+    pub fn list(&self) -> &[Fold] {
+        &self.folds
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.folds.is_empty()
+    }
+
+    pub fn is_hidden(&self, line: usize) -> bool {
+        self.folds.iter().any(|f| f.hides(line))
+    }
+
+    pub fn fold_at(&self, start: usize) -> Option<&Fold> {
+        self.folds.iter().find(|f| f.start == start)
+    }
+
+    // Fold the block at `line`, absorbing folds inside it; false when there is no block
+    pub fn fold(&mut self, lines: &[String], line: usize) -> bool {
+        let Some((start, end)) = block_at(lines, line) else {
+            return false;
+        };
+        self.folds.retain(|f| f.end < start || f.start > end);
+        let at = self.folds.partition_point(|f| f.start < start);
+        self.folds.insert(at, Fold { start, end });
+        true
+    }
+
+    // Remove the fold shown on or hiding `line`; false when there is none
+    pub fn unfold(&mut self, line: usize) -> bool {
+        let before = self.folds.len();
+        self.folds.retain(|f| !(f.start == line || f.hides(line)));
+        self.folds.len() != before
+    }
+
+    pub fn toggle(&mut self, lines: &[String], line: usize) -> bool {
+        self.unfold(line) || self.fold(lines, line)
+    }
+
+    pub fn unfold_all(&mut self) {
+        self.folds.clear();
+    }
+
+    // Open any fold hiding `line`, e.g. when search or a jump lands there
+    pub fn reveal(&mut self, line: usize) {
+        self.folds.retain(|f| !f.hides(line));
+    }
+
+    // Next visible line after `line`, skipping folded blocks
+    pub fn next_visible(&self, line: usize) -> usize {
+        match self.fold_at(line) {
+            Some(fold) => fold.end + 1,
+            None => line + 1,
+        }
+    }
+
+    // Previous visible line before `line`, landing on a fold's first line
+    pub fn prev_visible(&self, line: usize) -> usize {
+        let prev = line.saturating_sub(1);
+        self.folds.iter().find(|f| f.hides(prev)).map(|f| f.start).unwrap_or(prev)
+    }
+
+    // The line drawn for `line`: itself, or the first line of the fold hiding it
+    pub fn visible_line(&self, line: usize) -> usize {
+        self.prev_visible(line + 1)
+    }
+
+    // Top line that puts `line` on the last of `rows` rows
+    pub fn top_above(&self, line: usize, rows: usize) -> usize {
+        (1..rows).fold(self.visible_line(line), |top, _| self.prev_visible(top))
+    }
+
+    // Line shown `row` rows below `top`, which is itself a visible line
+    pub fn line_at_row(&self, top: usize, row: usize) -> usize {
+        let mut line = top + row;
+        for fold in self.folds.iter().filter(|f| f.start >= top) {
+            if fold.start >= line {
+                break;
+            }
+            line += fold.hidden_lines();
+        }
+        line
+    }
+
+    // Rows from `top` down to `line`, a hidden line sharing the row of its fold
+    pub fn row_of_line(&self, top: usize, line: usize) -> usize {
+        let hidden: usize = self
+            .folds
+            .iter()
+            .filter(|f| f.start >= top && f.start < line)
+            .map(|f| f.end.min(line) - f.start)
+            .sum();
+        line.saturating_sub(top) - hidden
+    }
+
+    // Drop folds whose block no longer matches the text, e.g. after an edit or external change
+    pub fn validate(&mut self, lines: &[String]) {
+        self.folds.retain(|f| block_range(lines, f.start) == Some((f.start, f.end)));
+    }
+
+    // Keep folds on the same text after `delta` lines were inserted (or removed) at `line`
+    pub fn adjust(&mut self, line: usize, delta: isize) {
+        if delta == 0 {
+            return;
+        }
+        let shift = |n: usize| (n as isize + delta).max(0) as usize;
+        self.folds.retain_mut(|fold| {
+            if fold.start > line {
+                fold.start = shift(fold.start);
+                fold.end = shift(fold.end);
+            } else if fold.end >= line {
+                fold.end = shift(fold.end);
+            }
+            fold.end > fold.start
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fold_blocks() {
+        let lines: Vec<String> = [
+            "fn main() {",
+            "    if x {",
+            "        y();",
+            "    }",
+            "}",
+            "def f():",
+            "    return 1",
+            "",
+            "done",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        assert_eq!(block_range(&lines, 0), Some((0, 4)));
+        assert_eq!(block_at(&lines, 2), Some((1, 3)));
+        assert_eq!(block_range(&lines, 5), Some((5, 6)));
+        assert_eq!(block_range(&lines, 8), None);
+
+        let mut folds = Folds::default();
+        assert!(folds.toggle(&lines, 2));
+        assert_eq!(folds.list(), [Fold { start: 1, end: 3 }]);
+        assert!(folds.is_hidden(3) && !folds.is_hidden(1));
+        assert_eq!(folds.next_visible(1), 4);
+        assert_eq!(folds.prev_visible(4), 1);
+        assert_eq!((folds.line_at_row(0, 1), folds.line_at_row(0, 2)), (1, 4));
+        assert_eq!((folds.row_of_line(0, 4), folds.row_of_line(0, 3)), (2, 1));
+        assert_eq!(folds.row_of_line(4, 6), 2);
+        assert_eq!((folds.visible_line(2), folds.top_above(5, 3)), (1, 1));
+
+        // Folding the outer block absorbs the inner fold
+        assert!(folds.fold(&lines, 0));
+        assert_eq!(folds.list(), [Fold { start: 0, end: 4 }]);
+
+        folds.adjust(0, 2);
+        assert_eq!(folds.list(), [Fold { start: 0, end: 6 }]);
+        assert!(folds.toggle(&lines, 3));
+        assert!(folds.is_empty());
+    }
+}
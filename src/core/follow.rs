@@ -0,0 +1,122 @@
+// follow module - incremental reload of growing files (tail -f style)
+use crate::core::buffer::Buffer;
+use std::fs::File;
+use std::io::{Error, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+
+#[derive(Clone, Debug)]
+pub struct FileFollower {
+    path: PathBuf,
+    offset: u64,             // Bytes of the file already loaded into the buffer
+    line_count: usize,       // Lines of real content (excluding buffer padding)
+    ends_with_newline: bool, // Whether the next chunk starts a fresh line
+}
+
+impl FileFollower {
+    // Start following a file whose current content is already loaded in the buffer
+    pub fn new(path: PathBuf, content: &str) -> Self {
+        Self {
+            path,
+            offset: content.len() as u64,
+            line_count: content.lines().count(),
+            ends_with_newline: content.is_empty() || content.ends_with('\n'),
+        }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_count
+    }
+
+    // Check the file for appended content and apply it to the buffer.
+    // Returns true if the buffer changed.
+    pub fn poll(&mut self, buffer: &mut Buffer) -> Result<bool, Error> {
+        let len = std::fs::metadata(&self.path)?.len();
+
+        if len < self.offset {
+            // File was truncated or rotated - reload it from scratch
+            let content = std::fs::read_to_string(&self.path)?;
+            *buffer = Buffer::from_string(content.clone());
+            *self = Self::new(self.path.clone(), &content);
+            return Ok(true);
+        }
+
+        if len == self.offset {
+            return Ok(false);
+        }
+
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+
+        // Only consume complete UTF-8 sequences; a partially written character
+        // is picked up on the next poll
+        let text = match std::str::from_utf8(&bytes) {
+            Ok(text) => text.to_string(),
+            Err(e) if e.error_len().is_none() => {
+                bytes.truncate(e.valid_up_to());
+                String::from_utf8_lossy(&bytes).into_owned()
+            }
+            Err(_) => String::from_utf8_lossy(&bytes).into_owned(),
+        };
+
+        if text.is_empty() {
+            return Ok(false);
+        }
+        self.offset += bytes.len() as u64;
+        self.append(buffer, &text);
+        Ok(true)
+    }
+
+    fn append(&mut self, buffer: &mut Buffer, text: &str) {
+        let ends_with_newline = text.ends_with('\n');
+        let body = text.strip_suffix('\n').unwrap_or(text);
+        let mut segments = body.split('\n').map(|seg| seg.trim_end_matches('\r'));
+
+        // Continue the last line if it wasn't terminated yet
+        if !self.ends_with_newline && self.line_count > 0 {
+            if let (Some(first), Some(last)) = (segments.next(), buffer.lines.get_mut(self.line_count - 1)) {
+                last.push_str(first);
+            }
+        }
+
+        for segment in segments {
+            buffer.lines.insert(self.line_count, segment.to_string());
+            self.line_count += 1;
+        }
+
+        self.ends_with_newline = ends_with_newline;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_follow_appends_lines() {
+        let path = std::env::temp_dir().join(format!("quick_follow_{}.log", std::process::id()));
+        std::fs::write(&path, "first\nsecond").unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let mut buffer = Buffer::from_string(content.clone());
+        let mut follower = FileFollower::new(path.clone(), &content);
+        assert!(!follower.poll(&mut buffer).unwrap());
+
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b" line\nthird\n").unwrap();
+
+        assert!(follower.poll(&mut buffer).unwrap());
+        assert_eq!(follower.line_count(), 3);
+        assert_eq!(&buffer.lines[..3], &["first", "second line", "third"]);
+
+        // Truncation reloads the whole file
+        std::fs::write(&path, "rotated\n").unwrap();
+        assert!(follower.poll(&mut buffer).unwrap());
+        assert_eq!(follower.line_count(), 1);
+        assert_eq!(buffer.lines[0], "rotated");
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,112 @@
+// history module - earlier versions of saved files in <state directory>/history/<path hash>/<timestamp>
+use crate::core::crypto;
+use crate::core::hash::HashAlgorithm;
+use crate::core::paths;
+use crate::core::timestamp::format_timestamp;
+use std::fs;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Oldest versions of a file are dropped past this many
+const MAX_VERSIONS: usize = 50;
+
+#[derive(Clone, Debug)]
+pub struct Version {
+    pub path: PathBuf,
+    pub name: String, // Save time as YYYYMMDD-HHMMSS (local time)
+}
+
+// One directory per file, named after a hash of its canonical path
+fn history_dir(file: &str) -> PathBuf {
+    let canonical = fs::canonicalize(file).unwrap_or_else(|_| PathBuf::from(file));
+    let digest = HashAlgorithm::Sha256.hex_digest(canonical.to_string_lossy().as_bytes());
+    paths::state_dir().join("history").join(&digest[..16])
+}
+
+// Copy the version of `file` still on disk into its history, before a save overwrites it
+pub fn backup(file: &str) -> Result<(), Error> {
+    let Ok(bytes) = fs::read(file) else {
+        return Ok(()); // Nothing saved yet
+    };
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    save_version(&history_dir(file), &bytes, &format_timestamp(secs))
+}
+
+fn save_version(dir: &Path, bytes: &[u8], stamp: &str) -> Result<(), Error> {
+    let versions = versions_in(dir);
+    // Saving an unchanged file adds nothing new
+    if versions.first().is_some_and(|newest| fs::read(&newest.path).ok().as_deref() == Some(bytes)) {
+        return Ok(());
+    }
+
+    fs::create_dir_all(dir)?;
+    let mut path = dir.join(stamp);
+    let mut suffix = 2;
+    while path.exists() {
+        path = dir.join(format!("{}-{}", stamp, suffix));
+        suffix += 1;
+    }
+    fs::write(path, bytes)?;
+
+    for old in versions.iter().skip(MAX_VERSIONS - 1) {
+        let _ = fs::remove_file(&old.path);
+    }
+    Ok(())
+}
+
+// Saved versions of `file`, newest first
+pub fn list_versions(file: &str) -> Vec<Version> {
+    versions_in(&history_dir(file))
+}
+
+fn versions_in(dir: &Path) -> Vec<Version> {
+    let mut versions: Vec<Version> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().is_file())
+        .map(|entry| Version { name: entry.file_name().to_string_lossy().into_owned(), path: entry.path() })
+        .collect();
+    // Timestamps in the name sort chronologically
+    versions.sort_by(|a, b| b.name.cmp(&a.name));
+    versions
+}
+
+// Text of a version, decrypted with the tab's passphrase for encrypted notes
+pub fn read_version(version: &Version, passphrase: Option<&str>) -> Result<String, Error> {
+    let bytes = fs::read(&version.path)?;
+    if crypto::is_encrypted(&bytes) {
+        let passphrase = passphrase.ok_or_else(|| {
+            Error::new(std::io::ErrorKind::PermissionDenied, "Version is encrypted, unlock the note first")
+        })?;
+        return crypto::decrypt(&bytes, passphrase);
+    }
+    String::from_utf8(bytes).map_err(|e| Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_versions_newest_first_without_duplicates() {
+        let dir = std::env::temp_dir().join(format!("quicknotepad-history-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        save_version(&dir, b"one", "20240101-100000").unwrap();
+        save_version(&dir, b"one", "20240101-100500").unwrap();
+        save_version(&dir, b"two", "20240101-101000").unwrap();
+        save_version(&dir, b"three", "20240101-101000").unwrap();
+
+        let versions = versions_in(&dir);
+        let names: Vec<&str> = versions.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, ["20240101-101000-2", "20240101-101000", "20240101-100000"]);
+        assert_eq!(read_version(&versions[0], None).unwrap(), "three");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
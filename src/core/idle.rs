@@ -0,0 +1,75 @@
+// idle module - deadlines the terminal main loop wakes up for while waiting for input.
+// With nothing scheduled it blocks on the next event and uses no CPU.
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Timer {
+    ClearPrompt, // Footer messages go away after a few seconds
+    Toasts,      // A notice above the footer times out
+    Follow,      // Check a followed file for appended content
+    Chord,       // The second key of a sequence was not pressed in time
+    Collab,      // Trade edits with the others in a shared tab
+}
+
+#[derive(Debug, Default)]
+pub struct Idle {
+    deadlines: Vec<(Timer, Instant)>,
+}
+
+impl Idle {
+    // Fire `timer` at `at`, replacing an earlier schedule of it
+    pub fn schedule(&mut self, timer: Timer, at: Instant) {
+        self.cancel(timer);
+        self.deadlines.push((timer, at));
+    }
+
+    // Fire `timer` at `at` unless it is already waiting
+    pub fn ensure(&mut self, timer: Timer, at: Instant) {
+        if !self.is_scheduled(timer) {
+            self.deadlines.push((timer, at));
+        }
+    }
+
+    pub fn cancel(&mut self, timer: Timer) {
+        self.deadlines.retain(|(scheduled, _)| *scheduled != timer);
+    }
+
+    pub fn is_scheduled(&self, timer: Timer) -> bool {
+        self.deadlines.iter().any(|(scheduled, _)| *scheduled == timer)
+    }
+
+    // How long to wait for input before the next timer is due; None waits indefinitely
+    pub fn timeout(&self, now: Instant) -> Option<Duration> {
+        self.deadlines.iter().map(|(_, at)| at.saturating_duration_since(now)).min()
+    }
+
+    // Timers whose deadline has passed, taken off the schedule
+    pub fn due(&mut self, now: Instant) -> Vec<Timer> {
+        let due = self.deadlines.iter().filter(|(_, at)| *at <= now).map(|(timer, _)| *timer).collect();
+        self.deadlines.retain(|(_, at)| *at > now);
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timers_fire_in_order() {
+        let start = Instant::now();
+        let mut idle = Idle::default();
+        assert_eq!(idle.timeout(start), None);
+
+        idle.schedule(Timer::ClearPrompt, start + Duration::from_secs(2));
+        idle.ensure(Timer::Follow, start + Duration::from_millis(250));
+        idle.ensure(Timer::Follow, start + Duration::from_secs(5));
+        assert_eq!(idle.timeout(start), Some(Duration::from_millis(250)));
+
+        assert_eq!(idle.due(start + Duration::from_millis(300)), [Timer::Follow]);
+        assert!(!idle.is_scheduled(Timer::Follow));
+        assert_eq!(idle.timeout(start + Duration::from_secs(3)), Some(Duration::ZERO));
+        assert_eq!(idle.due(start + Duration::from_secs(3)), [Timer::ClearPrompt]);
+        assert_eq!(idle.timeout(start), None);
+    }
+}
@@ -0,0 +1,152 @@
+// jumps module - moving by blank-line separated paragraphs and by indentation blocks
+use crate::core::line_metrics::LineMetrics;
+use crate::core::selection::TextPosition;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Jump {
+    ParagraphUp,
+    ParagraphDown,
+    BlockStart,
+    BlockEnd,
+}
+
+impl Jump {
+    pub const ALL: [Jump; 4] = [Jump::ParagraphUp, Jump::ParagraphDown, Jump::BlockStart, Jump::BlockEnd];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Jump::ParagraphUp => "Previous paragraph",
+            Jump::ParagraphDown => "Next paragraph",
+            Jump::BlockStart => "Start of indented block",
+            Jump::BlockEnd => "End of indented block",
+        }
+    }
+}
+
+// Where a jump from `line` lands: the blank line around a paragraph, or the first
+// non-blank character at either end of the block. Already at a block's end, it leaves
+// the block for the line with less indentation before or after it
+pub fn target(lines: &[String], line: usize, jump: Jump, metrics: impl Fn(usize) -> LineMetrics) -> TextPosition {
+    let count = lines.len();
+    if count == 0 {
+        return TextPosition { line: 0, column: 0 };
+    }
+    let line = line.min(count - 1);
+    let blank = |i: usize| metrics(i).is_blank();
+    let first_non_blank = |line: usize| TextPosition {
+        line,
+        column: lines[line].chars().take_while(|c| c.is_whitespace()).count(),
+    };
+
+    match jump {
+        Jump::ParagraphDown => {
+            let mut i = line;
+            while i < count && blank(i) {
+                i += 1;
+            }
+            // Only blank lines below, as in the buffer's padding
+            if i == count {
+                return TextPosition { line, column: 0 };
+            }
+            while i < count && !blank(i) {
+                i += 1;
+            }
+            TextPosition { line: i.min(count - 1), column: 0 }
+        }
+        Jump::ParagraphUp => {
+            let mut i = line;
+            while i > 0 && blank(i) {
+                i -= 1;
+            }
+            while i > 0 && !blank(i) {
+                i -= 1;
+            }
+            TextPosition { line: i, column: 0 }
+        }
+        _ if blank(line) => TextPosition { line, column: 0 },
+        Jump::BlockStart => {
+            let indent = metrics(line).indent;
+            let (start, _) = block_bounds(line, indent, count, &metrics);
+            if start < line {
+                return first_non_blank(start);
+            }
+            let header = (0..line).rev().find(|&i| !blank(i) && metrics(i).indent < indent);
+            first_non_blank(header.unwrap_or(line))
+        }
+        Jump::BlockEnd => {
+            let indent = metrics(line).indent;
+            let (_, end) = block_bounds(line, indent, count, &metrics);
+            if end > line {
+                return first_non_blank(end);
+            }
+            let after = (line + 1..count).find(|&i| !blank(i));
+            first_non_blank(after.unwrap_or(line))
+        }
+    }
+}
+
+// First and last non-blank lines around `line` indented at least `indent`, blank lines in between included
+fn block_bounds(line: usize, indent: usize, count: usize, metrics: &impl Fn(usize) -> LineMetrics) -> (usize, usize) {
+    let inside = |i: usize| {
+        let m = metrics(i);
+        m.is_blank() || m.indent >= indent
+    };
+    let blank = |i: usize| metrics(i).is_blank();
+
+    let mut start = line;
+    while start > 0 && inside(start - 1) {
+        start -= 1;
+    }
+    while start < line && blank(start) {
+        start += 1;
+    }
+    let mut end = line;
+    while end + 1 < count && inside(end + 1) {
+        end += 1;
+    }
+    while end > line && blank(end) {
+        end -= 1;
+    }
+    (start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jump(lines: &[&str], line: usize, jump: Jump) -> (usize, usize) {
+        let lines: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+        let pos = target(&lines, line, jump, |i| LineMetrics::measure(&lines[i], 4));
+        (pos.line, pos.column)
+    }
+
+    #[test]
+    fn test_paragraphs() {
+        let text = ["one", "two", "", "", "three", "four", "", ""];
+        assert_eq!(jump(&text, 0, Jump::ParagraphDown), (2, 0));
+        assert_eq!(jump(&text, 2, Jump::ParagraphDown), (6, 0));
+        assert_eq!(jump(&text, 6, Jump::ParagraphDown), (6, 0));
+        assert_eq!(jump(&text, 5, Jump::ParagraphUp), (3, 0));
+        assert_eq!(jump(&text, 3, Jump::ParagraphUp), (0, 0));
+        assert_eq!(jump(&["a", "b"], 0, Jump::ParagraphDown), (1, 0));
+    }
+
+    #[test]
+    fn test_indentation_blocks() {
+        let text = [
+            "def a():",
+            "    x = 1",
+            "",
+            "    if x:",
+            "\ty()",
+            "    return x",
+            "def b():",
+        ];
+        assert_eq!(jump(&text, 3, Jump::BlockStart), (1, 4));
+        assert_eq!(jump(&text, 1, Jump::BlockStart), (0, 0));
+        assert_eq!(jump(&text, 1, Jump::BlockEnd), (5, 4));
+        assert_eq!(jump(&text, 5, Jump::BlockEnd), (6, 0));
+        assert_eq!(jump(&text, 4, Jump::BlockEnd), (5, 4));
+        assert_eq!(jump(&text, 2, Jump::BlockEnd), (2, 0));
+    }
+}
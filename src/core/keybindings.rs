@@ -0,0 +1,245 @@
+// keybindings module - the [keybindings] table in config.toml, remapping registry commands to other keys or key sequences.
+// Keys are written the way --shortcuts prints them ("Ctrl+Shift+F", "Alt+Up Arrow", "Ctrl+K Ctrl+B"), "" unbinds
+use crate::core::commands::{self, Command};
+use crate::core::shortcuts::Shortcuts;
+use crossterm::event::{KeyCode, KeyModifiers};
+use std::collections::BTreeMap;
+
+pub type Key = (KeyCode, KeyModifiers);
+
+// A parsed binding: the key, and the key pressed before it for two-key sequences
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Binding {
+    pub chord: Option<Key>,
+    pub key: Key,
+}
+
+// The registry with the user's bindings laid over it
+pub struct Remapped {
+    pub commands: Vec<Command>,
+    pub custom: Vec<Key>, // First keys the user bound themselves, which win over the keymap profile
+    pub problems: Vec<String>, // Bindings that were skipped and why
+}
+
+pub fn parse(text: &str) -> Result<Option<Binding>, String> {
+    let keys = split_keys(text)
+        .into_iter()
+        .map(|key| parse_key(&key))
+        .collect::<Result<Vec<_>, _>>()?;
+    let binding = match keys[..] {
+        [] => return Ok(None),
+        [key] => Binding { chord: None, key },
+        // The second key of a sequence works with or without Ctrl, as the built-in ones do
+        [leader, (code, mods)] => Binding {
+            chord: Some(leader),
+            key: (code, if mods.is_empty() { KeyModifiers::CONTROL } else { mods }),
+        },
+        _ => return Err(format!("\"{}\" has more than two keys", text)),
+    };
+    // Only the key that runs a command on its own needs to stay clear of typing
+    let first = binding.chord.unwrap_or(binding.key);
+    if let (KeyCode::Char(_), mods) = first {
+        if !mods.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) {
+            return Err(format!("\"{}\" would stop that character from being typed, add Ctrl or Alt", text));
+        }
+    }
+    Ok(Some(binding))
+}
+
+// "Ctrl+K Ctrl+Page Up" -> ["Ctrl+K", "Ctrl+Page Up"]; the two-word names from key_to_string stay together
+fn split_keys(text: &str) -> Vec<String> {
+    let mut keys: Vec<String> = Vec::new();
+    let mut joins_next = false;
+    for word in text.split_whitespace() {
+        match keys.last_mut() {
+            Some(last) if joins_next || word.eq_ignore_ascii_case("arrow") => {
+                last.push(' ');
+                last.push_str(word);
+            }
+            _ => keys.push(word.to_string()),
+        }
+        joins_next = word.to_ascii_lowercase().ends_with("page");
+    }
+    keys
+}
+
+fn parse_key(text: &str) -> Result<Key, String> {
+    let invalid = || format!("\"{}\" is not a key quick knows", text);
+    let mut parts: Vec<&str> = text.split('+').collect();
+    // "Ctrl++" binds the plus key itself
+    if text.ends_with("++") {
+        parts.truncate(parts.len() - 2);
+        parts.push("+");
+    }
+    let name = parts.pop().filter(|name| !name.is_empty()).ok_or_else(invalid)?;
+    let mut mods = KeyModifiers::empty();
+    for part in parts {
+        mods |= match part.to_ascii_lowercase().as_str() {
+            "ctrl" | "control" | "cmd" => KeyModifiers::CONTROL,
+            "alt" | "option" => KeyModifiers::ALT,
+            "shift" => KeyModifiers::SHIFT,
+            _ => return Err(invalid()),
+        };
+    }
+    let lower = name.to_ascii_lowercase();
+    let code = match lower.trim_end_matches(" arrow") {
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "enter" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "delete" => KeyCode::Delete,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "page up" | "pageup" => KeyCode::PageUp,
+        "page down" | "pagedown" => KeyCode::PageDown,
+        "space" => KeyCode::Char(' '),
+        function if function.len() > 1 && function.starts_with('f') => match function[1..].parse() {
+            Ok(number @ 1..=24) => KeyCode::F(number),
+            _ => return Err(invalid()),
+        },
+        _ => {
+            let mut chars = lower.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if !c.is_control() => KeyCode::Char(c),
+                _ => return Err(invalid()),
+            }
+        }
+    };
+    Ok((code, mods))
+}
+
+pub fn remap(bindings: &BTreeMap<String, String>) -> Remapped {
+    let mut commands = commands::registry();
+    let mut custom = Vec::new();
+    let mut problems = Vec::new();
+    let mut claimed: Vec<(Binding, String)> = Vec::new();
+
+    for (name, text) in bindings {
+        let Some(first) = commands.iter().position(|command| command.name.eq_ignore_ascii_case(name)) else {
+            problems.push(format!("Unknown command \"{}\" in keybindings", name));
+            continue;
+        };
+        let binding = match parse(text) {
+            Ok(binding) => binding,
+            Err(e) => {
+                problems.push(format!("Keybinding for {}: {}", name, e));
+                continue;
+            }
+        };
+        if let Some(binding) = binding {
+            let clash = claimed.iter().find(|(other, _)| {
+                other == &binding
+                    || (other.chord.is_none() && binding.chord == Some(other.key))
+                    || (binding.chord.is_none() && other.chord == Some(binding.key))
+            });
+            if let Some((_, other)) = clash {
+                problems.push(format!("Keybinding for {}: {} is already bound to {}", name, text, other));
+                continue;
+            }
+            claimed.push((binding, commands[first].name.clone()));
+            custom.push(binding.chord.unwrap_or(binding.key));
+            // Built-in commands lose the keys taken over, and a taken sequence leader stops being a key of its own
+            for command in commands.iter_mut() {
+                let takes_key = command.chord == binding.chord && command.key == Some(binding.key);
+                let takes_leader = command.chord.is_none() && binding.chord == command.key;
+                let loses_sequence = binding.chord.is_none() && command.chord == Some(binding.key);
+                if takes_key || takes_leader || loses_sequence {
+                    command.key = None;
+                    command.chord = None;
+                }
+            }
+        }
+        // Every entry of a name with several default keys (Redo) gives them up for the new one
+        for command in commands.iter_mut().filter(|command| command.name.eq_ignore_ascii_case(name)) {
+            command.key = None;
+            command.chord = None;
+        }
+        if let Some(binding) = binding {
+            commands[first].key = Some(binding.key);
+            commands[first].chord = binding.chord;
+        }
+    }
+    Remapped { commands, custom, problems }
+}
+
+// Every command with its effective keys, for quick --dump-keys; remapped ones are marked
+pub fn dump(bindings: &BTreeMap<String, String>) -> (String, Vec<String>) {
+    let remapped = remap(bindings);
+    let mut out = String::new();
+    for command in &remapped.commands {
+        let keys = Shortcuts::describe(command).unwrap_or_else(|| "-".to_string());
+        let marker = if bindings.keys().any(|name| name.eq_ignore_ascii_case(&command.name)) { " *" } else { "" };
+        out.push_str(&format!("  {:<22} : {}{}\n", keys, command.name, marker));
+    }
+    (out, remapped.problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bindings(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(name, keys)| (name.to_string(), keys.to_string())).collect()
+    }
+
+    #[test]
+    fn test_parse_reads_what_shortcuts_prints() {
+        for command in commands::registry() {
+            if let Some(text) = Shortcuts::describe(&command) {
+                let Ok(Some(binding)) = parse(&text) else {
+                    if matches!(command.key, Some((KeyCode::Char(_), m)) if m.is_empty()) {
+                        continue;
+                    }
+                    panic!("could not read back {}", text);
+                };
+                assert_eq!((binding.chord, Some(binding.key)), (command.chord, command.key), "{}", text);
+            }
+        }
+        assert_eq!(parse("ctrl+k b").unwrap().unwrap().key, (KeyCode::Char('b'), KeyModifiers::CONTROL));
+        assert_eq!(parse("Alt+PageUp").unwrap().unwrap().key, (KeyCode::PageUp, KeyModifiers::ALT));
+        assert_eq!(parse("Ctrl++").unwrap().unwrap().key, (KeyCode::Char('+'), KeyModifiers::CONTROL));
+        assert_eq!(parse(""), Ok(None));
+        assert!(parse("Hyper+X").is_err());
+        assert!(parse("Ctrl+F99").is_err());
+        assert!(parse("Shift+X").is_err());
+        assert!(parse("Ctrl+A Ctrl+B Ctrl+C").is_err());
+    }
+
+    #[test]
+    fn test_remap_moves_keys_and_reports_problems() {
+        let remapped = remap(&bindings(&[
+            ("Find and replace", "Ctrl+R"),
+            ("redo", "Ctrl+K Ctrl+Z"),
+            ("Save", ""),
+            ("Teleport", "Ctrl+J"),
+            ("Quit", "Ctrl+R"),
+        ]));
+        let commands = &remapped.commands;
+        let replace = commands::find(commands, KeyCode::Char('r'), KeyModifiers::CONTROL).unwrap();
+        assert_eq!(replace.name, "Find and replace");
+        assert!(commands::find(commands, KeyCode::Char('h'), KeyModifiers::CONTROL).is_none());
+        assert!(commands::find(commands, KeyCode::Char('s'), KeyModifiers::CONTROL).is_none());
+        assert!(commands::find(commands, KeyCode::Char('y'), KeyModifiers::CONTROL).is_none());
+        let redo = commands::find_chord(commands, commands::CHORD_LEADER, KeyCode::Char('z'), KeyModifiers::empty());
+        assert_eq!(redo.unwrap().name, "Redo");
+        // Quit keeps Ctrl+Q since its new key was already taken
+        assert_eq!(commands::find(commands, KeyCode::Char('q'), KeyModifiers::CONTROL).unwrap().name, "Quit");
+        assert_eq!(remapped.problems.len(), 2);
+        assert!(remapped.problems[0].contains("already bound to Find and replace"));
+        assert!(remapped.problems[1].contains("Teleport"));
+    }
+
+    #[test]
+    fn test_binding_a_sequence_leader() {
+        // Ctrl+K on its own ends the Ctrl+K sequences, and a new leader can take over a single key
+        let remapped = remap(&bindings(&[("Save", "Ctrl+K"), ("File history", "Ctrl+S H")]));
+        let commands = &remapped.commands;
+        assert!(!commands::is_chord_leader(commands, KeyCode::Char('k'), KeyModifiers::CONTROL));
+        assert!(commands::is_chord_leader(commands, KeyCode::Char('s'), KeyModifiers::CONTROL));
+        assert_eq!(commands::find(commands, KeyCode::Char('k'), KeyModifiers::CONTROL).unwrap().name, "Save");
+        assert!(remapped.problems.is_empty(), "{:?}", remapped.problems);
+    }
+}
@@ -0,0 +1,129 @@
+// keymap module - optional keybinding profiles layered over the default shortcuts
+use crate::core::actions::Action;
+use crate::core::selection::TextPosition;
+use crossterm::event::{KeyCode, KeyModifiers};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Keymap {
+    #[default]
+    Default,
+    Emacs,
+}
+
+impl Keymap {
+    // Bindings that take priority over the default shortcuts
+    pub fn mappings(&self) -> Vec<(KeyCode, KeyModifiers, Action, &'static str)> {
+        match self {
+            Keymap::Default => Vec::new(),
+            Keymap::Emacs => vec![
+                (KeyCode::Char('a'), KeyModifiers::CONTROL, Action::MaxLeft, "Start of line"),
+                (KeyCode::Char('e'), KeyModifiers::CONTROL, Action::MaxRight, "End of line"),
+                (KeyCode::Char('k'), KeyModifiers::ALT, Action::KillLine, "Kill to end of line"),
+                (KeyCode::Char('y'), KeyModifiers::CONTROL, Action::Yank, "Yank last kill"),
+                (KeyCode::Char('f'), KeyModifiers::ALT, Action::WordRight, "Forward word"),
+                (KeyCode::Char('b'), KeyModifiers::ALT, Action::WordLeft, "Backward word"),
+            ],
+        }
+    }
+}
+
+const KILL_RING_SIZE: usize = 30;
+
+#[derive(Debug, Default)]
+pub struct KillRing {
+    entries: Vec<String>, // Most recent last
+}
+
+impl KillRing {
+    // Consecutive kills are joined into one entry, like repeated kill-line in emacs
+    pub fn kill(&mut self, text: String, append: bool) {
+        match self.entries.last_mut() {
+            Some(last) if append => last.push_str(&text),
+            _ => self.entries.push(text),
+        }
+        if self.entries.len() > KILL_RING_SIZE {
+            self.entries.remove(0);
+        }
+    }
+
+    pub fn yank(&self) -> Option<&str> {
+        self.entries.last().map(String::as_str)
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// End of the next word (Alt+F), moving across line breaks
+pub fn word_right(lines: &[String], pos: TextPosition) -> TextPosition {
+    let (mut line, mut column) = (pos.line, pos.column);
+    let mut in_word = false;
+    while line < lines.len() {
+        let chars: Vec<char> = lines[line].chars().collect();
+        while column < chars.len() {
+            match (is_word_char(chars[column]), in_word) {
+                (true, _) => in_word = true,
+                (false, true) => return TextPosition { line, column },
+                (false, false) => {}
+            }
+            column += 1;
+        }
+        if in_word || line + 1 == lines.len() {
+            return TextPosition { line, column };
+        }
+        line += 1;
+        column = 0;
+    }
+    pos
+}
+
+// Start of the previous word (Alt+B), moving across line breaks
+pub fn word_left(lines: &[String], pos: TextPosition) -> TextPosition {
+    let (mut line, mut column) = (pos.line.min(lines.len().saturating_sub(1)), pos.column);
+    let mut in_word = false;
+    loop {
+        let chars: Vec<char> = lines.get(line).map_or(Vec::new(), |l| l.chars().collect());
+        column = column.min(chars.len());
+        while column > 0 {
+            match (is_word_char(chars[column - 1]), in_word) {
+                (true, _) => in_word = true,
+                (false, true) => return TextPosition { line, column },
+                (false, false) => {}
+            }
+            column -= 1;
+        }
+        if in_word || line == 0 {
+            return TextPosition { line, column: 0 };
+        }
+        line -= 1;
+        column = usize::MAX;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_words_and_kill_ring() {
+        let lines: Vec<String> = ["let foo_bar = 1;", "  next"].iter().map(|s| s.to_string()).collect();
+        let pos = |line: usize, column: usize| TextPosition { line, column };
+
+        assert_eq!(word_right(&lines, pos(0, 0)), pos(0, 3));
+        assert_eq!(word_right(&lines, pos(0, 3)), pos(0, 11));
+        assert_eq!(word_right(&lines, pos(0, 15)), pos(1, 6));
+        assert_eq!(word_left(&lines, pos(0, 11)), pos(0, 4));
+        assert_eq!(word_left(&lines, pos(1, 2)), pos(0, 14));
+
+        let mut ring = KillRing::default();
+        assert_eq!(ring.yank(), None);
+        ring.kill("first".to_string(), false);
+        ring.kill("\n".to_string(), true);
+        assert_eq!(ring.yank(), Some("first\n"));
+        ring.kill("second".to_string(), false);
+        assert_eq!(ring.yank(), Some("second"));
+    }
+}
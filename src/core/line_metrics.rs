@@ -0,0 +1,83 @@
+// line_metrics module - per-line grapheme count, display width and indentation, measured once per line.
+// Entries are keyed by line content, so an edited line is simply measured again the next time it is
+// asked for while untouched lines keep their cached numbers.
+use crate::core::display;
+use std::cell::{Cell, RefCell};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use unicode_segmentation::UnicodeSegmentation;
+
+// Forget everything past this many distinct lines, stale entries from edits included
+const MAX_ENTRIES: usize = 20_000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LineMetrics {
+    pub graphemes: usize,
+    pub width: usize,  // Screen cells with tabs and control characters expanded
+    pub indent: usize, // Cells taken by leading whitespace
+}
+
+impl LineMetrics {
+    pub fn measure(line: &str, tab_width: usize) -> Self {
+        let leading = &line[..line.len() - line.trim_start().len()];
+        Self {
+            graphemes: line.graphemes(true).count(),
+            width: display::width(line, tab_width),
+            indent: display::width(leading, tab_width),
+        }
+    }
+
+    // Empty or whitespace only
+    pub fn is_blank(&self) -> bool {
+        self.indent == self.width
+    }
+}
+
+// Looked up from drawing code that only has shared access, hence the RefCell
+#[derive(Default)]
+pub struct LineMetricsCache {
+    entries: RefCell<HashMap<u64, LineMetrics>>,
+    tab_width: Cell<usize>,
+}
+
+impl LineMetricsCache {
+    pub fn get(&self, line: &str, tab_width: usize) -> LineMetrics {
+        let mut entries = self.entries.borrow_mut();
+        // Widths depend on the tab width, so a new one starts over
+        if self.tab_width.replace(tab_width) != tab_width || entries.len() >= MAX_ENTRIES {
+            entries.clear();
+        }
+        *entries.entry(key(line)).or_insert_with(|| LineMetrics::measure(line, tab_width))
+    }
+}
+
+fn key(line: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    line.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_measure() {
+        assert_eq!(LineMetrics::measure("", 4), LineMetrics::default());
+        assert_eq!(LineMetrics::measure("\tlet x = 1;", 4), LineMetrics { graphemes: 11, width: 14, indent: 4 });
+        assert_eq!(LineMetrics::measure("  日本e\u{301}", 4), LineMetrics { graphemes: 5, width: 7, indent: 2 });
+        assert_eq!(LineMetrics::measure(" \t ", 4), LineMetrics { graphemes: 3, width: 5, indent: 5 });
+    }
+
+    #[test]
+    fn test_cache_follows_edits_and_tab_width() {
+        let cache = LineMetricsCache::default();
+        let mut line = "\tfn main() {".to_string();
+        assert_eq!(cache.get(&line, 4).width, 15);
+        line.push('}');
+        assert_eq!(cache.get(&line, 4).graphemes, 13);
+        assert_eq!(cache.get(&line, 8).width, 20);
+        assert_eq!(cache.entries.borrow().len(), 1);
+    }
+}
@@ -0,0 +1,169 @@
+// links module - find URLs and file paths in text and open them
+use crate::core::paths;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+
+const URL_PREFIXES: [&str; 6] = ["https://", "http://", "ftp://", "file://", "mailto:", "www."];
+
+// Byte ranges of the URLs in a line
+pub fn find_urls(line: &str) -> Vec<(usize, usize)> {
+    let mut urls = Vec::new();
+    let mut pos = 0;
+
+    while pos < line.len() {
+        let rest = &line[pos..];
+        let Some((offset, prefix)) = URL_PREFIXES
+            .iter()
+            .filter_map(|prefix| rest.find(prefix).map(|idx| (idx, prefix)))
+            .min_by_key(|(idx, _)| *idx)
+        else {
+            break;
+        };
+
+        let start = pos + offset;
+        let end = line[start..]
+            .find(|c: char| c.is_whitespace() || "<>\"'`|".contains(c))
+            .map(|len| start + len)
+            .unwrap_or(line.len());
+        let end = trim_url_end(line, start, end);
+
+        // "xhttp://" or "awww." are not links
+        let mid_word = line[..start].chars().next_back().is_some_and(char::is_alphanumeric);
+        if !mid_word && end > start + prefix.len() {
+            urls.push((start, end));
+            pos = end;
+        } else {
+            pos = start + prefix.len();
+        }
+    }
+
+    urls
+}
+
+// Drop trailing punctuation and unbalanced closing brackets, e.g. "(see https://a.b/c)."
+fn trim_url_end(line: &str, start: usize, mut end: usize) -> usize {
+    while let Some(last) = line[start..end].chars().next_back() {
+        let url = &line[start..end];
+        let unbalanced = match last {
+            ')' => url.matches('(').count() < url.matches(')').count(),
+            ']' => url.matches('[').count() < url.matches(']').count(),
+            _ => ".,;:!?".contains(last),
+        };
+        if !unbalanced {
+            break;
+        }
+        end -= last.len_utf8();
+    }
+    end
+}
+
+// URL touching byte `column` (the caret may sit right after it)
+pub fn url_at(line: &str, column: usize) -> Option<&str> {
+    find_urls(line)
+        .into_iter()
+        .find(|&(start, end)| start <= column && column <= end)
+        .map(|(start, end)| &line[start..end])
+}
+
+pub fn open_url(url: &str) -> Result<(), Error> {
+    if url.starts_with("www.") {
+        open::that_detached(format!("https://{}", url))
+    } else {
+        open::that_detached(url)
+    }
+}
+
+// File referenced in the text, e.g. "src/main.rs:42:7" from compiler output
+#[derive(Debug, PartialEq)]
+pub struct FileTarget {
+    pub path: PathBuf,
+    pub line: Option<usize>, // 1-based
+}
+
+// Path-like word around byte `column`, split from an optional :line(:col) suffix
+pub fn path_at(line: &str, column: usize) -> Option<(&str, Option<usize>)> {
+    let is_path_char = |c: char| !c.is_whitespace() && !"\"'`()<>[]{},;|".contains(c);
+    let column = column.min(line.len());
+    if !line.is_char_boundary(column) {
+        return None;
+    }
+
+    let start = line[..column]
+        .char_indices()
+        .rev()
+        .find(|&(_, c)| !is_path_char(c))
+        .map(|(idx, c)| idx + c.len_utf8())
+        .unwrap_or(0);
+    let end = line[column..]
+        .find(|c: char| !is_path_char(c))
+        .map(|len| column + len)
+        .unwrap_or(line.len());
+
+    let mut word = line[start..end].trim_end_matches(['.', ':']);
+    if word.is_empty() || word.contains("://") {
+        return None;
+    }
+
+    // Peel off up to two numeric suffixes, the first one is the line
+    let mut numbers = Vec::new();
+    while numbers.len() < 2 {
+        match word.rsplit_once(':') {
+            Some((rest, number)) if !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()) => {
+                numbers.push(number.parse::<usize>().ok()?);
+                word = rest;
+            }
+            _ => break,
+        }
+    }
+
+    let looks_like_path = word.contains(['/', '\\']) || word.rsplit_once('.').is_some_and(|(stem, ext)| {
+        !stem.is_empty() && !ext.is_empty() && ext.chars().all(char::is_alphanumeric)
+    });
+    looks_like_path.then(|| (word, numbers.last().copied()))
+}
+
+// Resolve a path next to `base_dir` (the current file's folder) or the working directory
+pub fn resolve_path(path: &str, base_dir: Option<&Path>) -> Option<PathBuf> {
+    let path = paths::expand(path);
+
+    let candidates = if path.is_absolute() {
+        vec![path]
+    } else {
+        base_dir.map(|dir| dir.join(&path)).into_iter().chain([path]).collect()
+    };
+    candidates
+        .into_iter()
+        .find(|candidate| candidate.is_file())
+        .and_then(|candidate| std::fs::canonicalize(candidate).ok())
+}
+
+pub fn file_target_at(line: &str, column: usize, base_dir: Option<&Path>) -> Option<FileTarget> {
+    let (path, line_number) = path_at(line, column)?;
+    Some(FileTarget {
+        path: resolve_path(path, base_dir)?,
+        line: line_number,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_urls() {
+        let line = "see (https://example.com/a_(b)). or www.rust-lang.org, not xhttp://no";
+        let urls: Vec<&str> = find_urls(line).iter().map(|&(s, e)| &line[s..e]).collect();
+        assert_eq!(urls, ["https://example.com/a_(b)", "www.rust-lang.org"]);
+        assert_eq!(url_at(line, 10), Some("https://example.com/a_(b)"));
+        assert_eq!(url_at(line, 2), None);
+    }
+
+    #[test]
+    fn test_path_at() {
+        let line = "error: --> src/core/tabs.rs:42:7: mismatched types";
+        assert_eq!(path_at(line, 14), Some(("src/core/tabs.rs", Some(42))));
+        assert_eq!(path_at("see notes.md.", 6), Some(("notes.md", None)));
+        assert_eq!(path_at("plain words here", 3), None);
+        assert_eq!(path_at("https://example.com/a.rs", 5), None);
+    }
+}
@@ -0,0 +1,121 @@
+// locks module - advisory lock files (.name.qnlock next to the file) so two instances don't clobber each other
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+
+// Who holds a lock
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LockInfo {
+    pub pid: u32,
+    pub hostname: String,
+}
+
+impl LockInfo {
+    fn ours() -> Self {
+        Self { pid: std::process::id(), hostname: hostname() }
+    }
+
+    // A lock left behind by a process on this machine that has since exited is stale
+    fn is_live(&self) -> bool {
+        if self.hostname != hostname() {
+            return true; // No way to check another machine, trust it
+        }
+        if cfg!(target_os = "linux") {
+            Path::new("/proc").join(self.pid.to_string()).exists()
+        } else {
+            true
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        format!("process {} on {}", self.pid, self.hostname)
+    }
+}
+
+fn hostname() -> String {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .or_else(|_| fs::read_to_string("/etc/hostname"))
+        .ok()
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+fn lock_path(file: &str) -> Option<PathBuf> {
+    let path = Path::new(file);
+    let name = path.file_name()?.to_string_lossy();
+    Some(path.with_file_name(format!(".{}.qnlock", name)))
+}
+
+fn read_lock(file: &str) -> Option<LockInfo> {
+    let content = fs::read_to_string(lock_path(file)?).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn write_lock(file: &str) -> Result<(), Error> {
+    let path = lock_path(file).ok_or_else(|| Error::new(std::io::ErrorKind::InvalidInput, "Not a file"))?;
+    let json = serde_json::to_string(&LockInfo::ours()).map_err(|e| Error::new(std::io::ErrorKind::InvalidData, e))?;
+    fs::write(path, json)
+}
+
+// Take the lock on `file`, or return who holds it when another live instance does.
+// Folders we can't write to just go without a lock.
+pub fn acquire(file: &str) -> Option<LockInfo> {
+    match read_lock(file) {
+        Some(holder) if holder != LockInfo::ours() && holder.is_live() => Some(holder),
+        _ => {
+            let _ = write_lock(file);
+            None
+        }
+    }
+}
+
+// Take the lock even though another instance holds it
+pub fn steal(file: &str) -> Result<(), Error> {
+    write_lock(file)
+}
+
+// Drop the lock on `file` if it is still ours
+pub fn release(file: &str) {
+    if read_lock(file).is_some_and(|holder| holder == LockInfo::ours()) {
+        if let Some(path) = lock_path(file) {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_lifecycle() {
+        let dir = std::env::temp_dir().join(format!("quicknotepad-locks-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("notes.txt").to_string_lossy().into_owned();
+        let lock = dir.join(".notes.txt.qnlock");
+
+        assert_eq!(acquire(&file), None);
+        assert!(lock.exists());
+        // Reopening in the same instance is fine
+        assert_eq!(acquire(&file), None);
+
+        // Another live instance on this machine keeps it
+        let other = LockInfo { pid: 1, hostname: hostname() };
+        fs::write(&lock, serde_json::to_string(&other).unwrap()).unwrap();
+        if cfg!(target_os = "linux") {
+            assert_eq!(acquire(&file), Some(other));
+        }
+        release(&file);
+        assert!(lock.exists());
+
+        steal(&file).unwrap();
+        release(&file);
+        assert!(!lock.exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
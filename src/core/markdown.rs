@@ -0,0 +1,322 @@
+// markdown module - line helpers for Markdown checklists and tables
+use unicode_width::UnicodeWidthStr;
+
+// Split a list item into (indent, marker with its trailing space, rest)
+pub fn split_list_item(line: &str) -> Option<(&str, &str, &str)> {
+    let rest = line.trim_start();
+    let indent = &line[..line.len() - rest.len()];
+
+    let marker_len = if rest.starts_with(['-', '*', '+']) {
+        1
+    } else {
+        let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+        if digits == 0 || !rest[digits..].starts_with(['.', ')']) {
+            return None;
+        }
+        digits + 1
+    };
+
+    let after = &rest[marker_len..];
+    if after.is_empty() {
+        return Some((indent, &rest[..marker_len], ""));
+    }
+    if !after.starts_with(' ') {
+        return None;
+    }
+    Some((indent, &rest[..marker_len + 1], &rest[marker_len + 1..]))
+}
+
+// Some(checked) for task list items like "- [ ] todo" / "- [x] done"
+pub fn checkbox_state(line: &str) -> Option<bool> {
+    let (_, _, rest) = split_list_item(line)?;
+    let state = match rest.get(..3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+    (rest.len() == 3 || rest[3..].starts_with(' ')).then_some(state)
+}
+
+pub fn is_checked_item(line: &str) -> bool {
+    checkbox_state(line) == Some(true)
+}
+
+// Toggle "[ ]" / "[x]", turning plain lines and list items into tasks first
+pub fn toggle_checkbox(line: &str) -> String {
+    match (split_list_item(line), checkbox_state(line)) {
+        (Some((indent, marker, rest)), Some(checked)) => {
+            let box_text = if checked { "[ ]" } else { "[x]" };
+            format!("{}{}{}{}", indent, marker, box_text, &rest[3..])
+        }
+        (Some((indent, marker, rest)), None) => {
+            let marker = if marker.ends_with(' ') { marker.to_string() } else { format!("{} ", marker) };
+            format!("{}{}[ ] {}", indent, marker, rest)
+        }
+        (None, _) => {
+            let rest = line.trim_start();
+            let indent = &line[..line.len() - rest.len()];
+            format!("{}- [ ] {}", indent, rest)
+        }
+    }
+}
+
+fn heading_level(line: &str) -> Option<usize> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    (level > 0 && line[level..].starts_with(' ')).then_some(level)
+}
+
+fn is_done_heading(line: &str) -> bool {
+    heading_level(line).is_some_and(|level| line[level..].trim().eq_ignore_ascii_case("done"))
+}
+
+fn indent_width(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+// Move checked items (and their nested lines) into a "## Done" section,
+// creating it at the end if needed. Returns None if nothing was checked.
+pub fn move_done_items(lines: &[String]) -> Option<Vec<String>> {
+    let mut remaining = Vec::new();
+    let mut done_items = Vec::new();
+    let mut in_done = false;
+    let mut done_level = 0;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = &lines[i];
+
+        if let Some(level) = heading_level(line) {
+            if is_done_heading(line) {
+                in_done = true;
+                done_level = level;
+            } else if level <= done_level {
+                in_done = false;
+            }
+        }
+
+        if in_done || !is_checked_item(line) {
+            remaining.push(line.clone());
+            i += 1;
+            continue;
+        }
+
+        // Take the item together with anything nested under it
+        let indent = indent_width(line);
+        done_items.push(line.clone());
+        i += 1;
+        while i < lines.len() && !lines[i].trim().is_empty() && indent_width(&lines[i]) > indent {
+            done_items.push(lines[i].clone());
+            i += 1;
+        }
+    }
+
+    if done_items.is_empty() {
+        return None;
+    }
+
+    match remaining.iter().position(|line| is_done_heading(line)) {
+        Some(heading) => {
+            let level = heading_level(&remaining[heading]).unwrap_or(2);
+            let section_end = remaining[heading + 1..]
+                .iter()
+                .position(|line| heading_level(line).is_some_and(|l| l <= level))
+                .map(|offset| heading + 1 + offset)
+                .unwrap_or(remaining.len());
+            let insert_at = remaining[heading + 1..section_end]
+                .iter()
+                .rposition(|line| !line.trim().is_empty())
+                .map(|offset| heading + 2 + offset)
+                .unwrap_or(heading + 1);
+            remaining.splice(insert_at..insert_at, done_items);
+        }
+        None => {
+            while remaining.last().is_some_and(|line| line.trim().is_empty()) {
+                remaining.pop();
+            }
+            if !remaining.is_empty() {
+                remaining.push(String::new());
+            }
+            remaining.push("## Done".to_string());
+            remaining.extend(done_items);
+        }
+    }
+
+    Some(remaining)
+}
+
+// Range of table lines (start..end) around `line_idx`, if it is inside a table
+pub fn table_bounds(lines: &[String], line_idx: usize) -> Option<(usize, usize)> {
+    let is_table_line = |line: &String| line.trim_start().starts_with('|');
+    if !lines.get(line_idx).is_some_and(is_table_line) {
+        return None;
+    }
+
+    let start = lines[..line_idx]
+        .iter()
+        .rposition(|line| !is_table_line(line))
+        .map(|idx| idx + 1)
+        .unwrap_or(0);
+    let end = lines[line_idx..]
+        .iter()
+        .position(|line| !is_table_line(line))
+        .map(|offset| line_idx + offset)
+        .unwrap_or(lines.len());
+    Some((start, end))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Align {
+    None,
+    Left,
+    Right,
+    Center,
+}
+
+// Split a table row into trimmed cells, keeping escaped pipes inside cells
+fn split_row(line: &str) -> Vec<String> {
+    let trimmed = line.trim();
+    let inner = trimmed.strip_prefix('|').unwrap_or(trimmed);
+    let inner = match inner.strip_suffix('|') {
+        Some(rest) if !rest.ends_with('\\') => rest,
+        _ => inner,
+    };
+
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut escaped = false;
+    for c in inner.chars() {
+        if c == '|' && !escaped {
+            cells.push(cell.trim().to_string());
+            cell.clear();
+        } else {
+            cell.push(c);
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    cells.push(cell.trim().to_string());
+    cells
+}
+
+fn separator_align(cell: &str) -> Option<Align> {
+    let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+    if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+        return None;
+    }
+    Some(match (cell.starts_with(':'), cell.ends_with(':')) {
+        (true, true) => Align::Center,
+        (true, false) => Align::Left,
+        (false, true) => Align::Right,
+        (false, false) => Align::None,
+    })
+}
+
+fn pad_cell(cell: &str, width: usize, align: Align) -> String {
+    let fill = width.saturating_sub(UnicodeWidthStr::width(cell));
+    match align {
+        Align::Right => format!("{}{}", " ".repeat(fill), cell),
+        Align::Center => format!("{}{}{}", " ".repeat(fill / 2), cell, " ".repeat(fill - fill / 2)),
+        Align::None | Align::Left => format!("{}{}", cell, " ".repeat(fill)),
+    }
+}
+
+// Reflow table rows so every column lines up, normalizing separator rows
+pub fn format_table(lines: &[String]) -> Vec<String> {
+    let indent = lines
+        .first()
+        .map(|line| &line[..line.len() - line.trim_start().len()])
+        .unwrap_or("");
+    let rows: Vec<Vec<String>> = lines.iter().map(|line| split_row(line)).collect();
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+
+    // The first separator row decides the column alignment
+    let separator_of = |row: &Vec<String>| -> Option<Vec<Align>> {
+        row.iter().map(|cell| separator_align(cell)).collect()
+    };
+    let aligns = rows
+        .iter()
+        .find_map(separator_of)
+        .unwrap_or_default();
+    let align_at = |col: usize| aligns.get(col).copied().unwrap_or(Align::None);
+
+    let mut widths = vec![3; columns];
+    for row in rows.iter().filter(|row| separator_of(row).is_none()) {
+        for (col, cell) in row.iter().enumerate() {
+            widths[col] = widths[col].max(UnicodeWidthStr::width(cell.as_str()));
+        }
+    }
+
+    rows.iter()
+        .map(|row| {
+            let cells: Vec<String> = if separator_of(row).is_some() {
+                (0..columns)
+                    .map(|col| {
+                        let width = widths[col];
+                        match align_at(col) {
+                            Align::None => "-".repeat(width),
+                            Align::Left => format!(":{}", "-".repeat(width - 1)),
+                            Align::Right => format!("{}:", "-".repeat(width - 1)),
+                            Align::Center => format!(":{}:", "-".repeat(width - 2)),
+                        }
+                    })
+                    .collect()
+            } else {
+                (0..columns)
+                    .map(|col| {
+                        let cell = row.get(col).map(String::as_str).unwrap_or("");
+                        pad_cell(cell, widths[col], align_at(col))
+                    })
+                    .collect()
+            };
+            format!("{}| {} |", indent, cells.join(" | "))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toggle_checkbox() {
+        assert_eq!(toggle_checkbox("buy milk"), "- [ ] buy milk");
+        assert_eq!(toggle_checkbox("  * buy milk"), "  * [ ] buy milk");
+        assert_eq!(toggle_checkbox("- [ ] buy milk"), "- [x] buy milk");
+        assert_eq!(toggle_checkbox("1. [X] buy milk"), "1. [ ] buy milk");
+        assert!(is_checked_item("- [x] done"));
+        assert!(!is_checked_item("- [x]done"));
+    }
+
+    #[test]
+    fn test_move_done_items() {
+        let lines: Vec<String> = ["# Todo", "- [x] one", "  - detail", "- [ ] two", "", "## Done", "- [x] zero", "", "## Later"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let moved = move_done_items(&lines).unwrap();
+        assert_eq!(
+            moved,
+            ["# Todo", "- [ ] two", "", "## Done", "- [x] zero", "- [x] one", "  - detail", "", "## Later"]
+        );
+
+        let no_section: Vec<String> = vec!["- [x] a".to_string(), "- [ ] b".to_string()];
+        assert_eq!(move_done_items(&no_section).unwrap(), ["- [ ] b", "", "## Done", "- [x] a"]);
+    }
+
+    #[test]
+    fn test_format_table() {
+        let lines: Vec<String> = ["|Name|Qty|", "|:-|-:|", "|apple|3|", "|kiwi \\| lime|12|"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(table_bounds(&lines, 2), Some((0, 4)));
+        assert_eq!(
+            format_table(&lines),
+            [
+                "| Name         | Qty |",
+                "| :----------- | --: |",
+                "| apple        |   3 |",
+                "| kiwi \\| lime |  12 |",
+            ]
+        );
+    }
+}
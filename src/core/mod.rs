@@ -4,4 +4,58 @@ pub mod selection;
 pub mod edit_history;
 pub mod tabs;
 pub mod buffer;
-pub mod updater; 
\ No newline at end of file
+pub mod updater;
+pub mod follow;
+pub mod scratch;
+pub mod crypto;
+pub mod markdown;
+pub mod continuation; pub mod config;
+pub mod todos;
+pub mod links;
+pub mod colors;
+pub mod calc;
+pub mod transforms;
+pub mod palette;
+pub mod hash;
+pub mod align;
+pub mod sequence;
+pub mod diff;
+pub mod conflicts;
+pub mod folding;
+pub mod symbols;
+pub mod smart_select;
+pub mod vim;
+pub mod keymap;
+pub mod keybindings;
+pub mod bidi;
+pub mod display;
+pub mod positions;
+pub mod history;
+pub mod trash;
+pub mod completion;
+pub mod append;
+pub mod timestamp;
+pub mod locks;
+pub mod paths;
+pub mod stats;
+pub mod idle;
+pub mod search_index;
+pub mod line_metrics;
+pub mod jumps;
+pub mod reflow;
+pub mod toasts;
+pub mod progress;
+pub mod commands;
+pub mod repeat;
+pub mod platform;
+pub mod whats_new;
+pub mod report;
+pub mod replace;
+pub mod batch;
+pub mod widget;
+pub mod server;
+pub mod collab;
+pub mod fetch;
+pub mod archive;
+pub mod finder;
+pub mod syntax;
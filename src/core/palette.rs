@@ -0,0 +1,89 @@
+// palette module - searchable list of commands for the command palette (Ctrl+P)
+use crate::core::actions::Action;
+use crate::core::commands;
+use crate::core::shortcuts::Shortcuts;
+use crossterm::event::{KeyCode, KeyModifiers};
+use std::collections::HashSet;
+
+#[derive(Debug, Clone)]
+pub struct PaletteCommand {
+    pub name: String,
+    pub shortcut: Option<String>,
+    pub action: Action,
+}
+
+// Every registered command except plain editing keys, which need no palette entry
+pub fn commands() -> Vec<PaletteCommand> {
+    let mut names = HashSet::new();
+    commands::registry()
+        .into_iter()
+        .filter(|command| command.action != Action::CommandPalette)
+        // A command with a second key (Ctrl+Shift+Z for redo) is listed once, with its first
+        .filter(|command| names.insert(command.name.clone()))
+        .filter(|command| {
+            command.key.is_none_or(|(code, mods)| {
+                mods.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) || matches!(code, KeyCode::F(_))
+            })
+        })
+        .map(|command| PaletteCommand {
+            shortcut: Shortcuts::describe(&command),
+            name: command.name,
+            action: command.action,
+        })
+        .collect()
+}
+
+// Commands whose name contains the query letters in order, best matches first
+pub fn filter<'a>(commands: &'a [PaletteCommand], query: &str) -> Vec<&'a PaletteCommand> {
+    rank(commands, query).into_iter().map(|i| &commands[i]).collect()
+}
+
+// Like filter, as positions in `commands`
+pub fn rank(commands: &[PaletteCommand], query: &str) -> Vec<usize> {
+    let query = query.to_lowercase();
+    let mut matches: Vec<(usize, usize)> = commands
+        .iter()
+        .enumerate()
+        .filter_map(|(i, command)| {
+            let name = command.name.to_lowercase();
+            let rank = if name.starts_with(&query) {
+                0
+            } else if name.contains(&query) {
+                1
+            } else if is_subsequence(&query, &name) {
+                2
+            } else {
+                return None;
+            };
+            Some((rank, i))
+        })
+        .collect();
+
+    matches.sort_by_key(|(rank, _)| *rank);
+    matches.into_iter().map(|(_, i)| i).collect()
+}
+
+pub(crate) fn is_subsequence(query: &str, text: &str) -> bool {
+    let mut chars = text.chars();
+    query.chars().all(|q| chars.any(|c| c == q))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::transforms::TextTransform;
+
+    #[test]
+    fn test_filter_commands() {
+        let commands = commands();
+        let found = filter(&commands, "zzq");
+        assert!(found.is_empty());
+
+        let found = filter(&commands, "base64 d");
+        assert_eq!(found[0].action, Action::Transform(TextTransform::Base64Decode));
+
+        let found = filter(&commands, "sv");
+        assert!(found.iter().any(|c| c.action == Action::Save));
+        assert!(filter(&commands, "").len() == commands.len());
+    }
+}
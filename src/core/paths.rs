@@ -0,0 +1,236 @@
+// paths module - where settings, state, notes and cached files live, per platform conventions.
+// Moves everything out of the old ~/.quicknotepad once.
+use crate::core::timestamp::format_timestamp;
+use directories::ProjectDirs;
+use std::fs;
+use std::io::Error;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Once;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static MIGRATE: Once = Once::new();
+
+pub fn home() -> PathBuf {
+    std::env::var("HOME")
+        .or_else(|_| std::env::var("USERPROFILE"))
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+}
+
+// A path as typed in a prompt or on the command line, made absolute against the
+// working directory with "~" and "$VAR"/"${VAR}" expanded and "." and ".." folded away
+pub fn resolve(input: &str) -> PathBuf {
+    let expanded = expand(input);
+    let absolute = if expanded.is_absolute() {
+        expanded
+    } else {
+        std::env::current_dir().unwrap_or_default().join(expanded)
+    };
+    normalize(&absolute)
+}
+
+// Just the "~" and "$VAR"/"${VAR}" expansion, for paths relative to somewhere else
+pub fn expand(input: &str) -> PathBuf {
+    let expanded = expand_vars(input);
+    match expanded.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => {
+            home().join(rest.trim_start_matches(['/', '\\']))
+        }
+        _ => PathBuf::from(expanded),
+    }
+}
+
+// Unset variables are left as typed, since "$" is a legal file name character
+fn expand_vars(input: &str) -> String {
+    let mut out = String::new();
+    let mut rest = input;
+    while let Some(idx) = rest.find('$') {
+        out.push_str(&rest[..idx]);
+        let after = &rest[idx + 1..];
+        let (name, len) = match after.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            },
+            None => {
+                let end = after.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(after.len());
+                (&after[..end], end)
+            }
+        };
+        match std::env::var(name) {
+            Ok(value) if !name.is_empty() => out.push_str(&value),
+            _ => out.push_str(&rest[idx..idx + 1 + len]),
+        }
+        rest = &after[len..];
+    }
+    out.push_str(rest);
+    out
+}
+
+// Drop "." and apply ".." without looking at the disk
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+// Platform locations from the directories crate: XDG base directories on Linux/BSD,
+// Application Support and Caches on macOS, AppData on Windows
+fn project_dirs() -> Option<ProjectDirs> {
+    ProjectDirs::from("", "", "QuickNotepad")
+}
+
+// Without a home directory everything shares one folder in the temp directory
+fn base_dir(pick: impl Fn(&ProjectDirs) -> &Path) -> PathBuf {
+    project_dirs()
+        .map(|dirs| pick(&dirs).to_path_buf())
+        .unwrap_or_else(|| std::env::temp_dir().join("quicknotepad"))
+}
+
+// Only Linux has a state directory, elsewhere state sits with the machine-local data
+fn state_base() -> PathBuf {
+    base_dir(|dirs| dirs.state_dir().unwrap_or(dirs.data_local_dir()))
+}
+
+fn ensure(dir: PathBuf) -> PathBuf {
+    MIGRATE.call_once(migrate);
+    if let Err(e) = fs::create_dir_all(&dir) {
+        eprintln!("Warning: Could not create {}: {}", dir.display(), e);
+    }
+    dir
+}
+
+// Settings (config.toml)
+pub fn config_dir() -> PathBuf {
+    ensure(base_dir(ProjectDirs::config_dir))
+}
+
+// Things the editor remembers between runs: session, caret positions, file history
+pub fn state_dir() -> PathBuf {
+    ensure(state_base())
+}
+
+// Notes the user wrote: scratch notes
+pub fn data_dir() -> PathBuf {
+    ensure(base_dir(ProjectDirs::data_dir))
+}
+
+// Files that can be thrown away and made again: downloaded release notes
+pub fn cache_dir() -> PathBuf {
+    ensure(base_dir(ProjectDirs::cache_dir))
+}
+
+// Rename a file that failed to parse to name.corrupt-YYYYMMDD-HHMMSS so defaults can take its place
+pub fn set_aside(path: &Path) -> Result<PathBuf, Error> {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let backup = path.with_file_name(format!("{}.corrupt-{}", name, format_timestamp(secs)));
+    fs::rename(path, &backup)?;
+    Ok(backup)
+}
+
+// Move the contents of ~/.quicknotepad from older versions to the new directories
+fn migrate() {
+    let old = home().join(".quicknotepad");
+    migrate_from(&old, &base_dir(ProjectDirs::config_dir), &state_base(), &base_dir(ProjectDirs::data_dir));
+}
+
+fn migrate_from(old: &Path, config: &Path, state: &Path, data: &Path) {
+    let Ok(entries) = fs::read_dir(old) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let target_dir = match name.to_string_lossy().as_ref() {
+            "config.json" => config,
+            "scratch" => data,
+            _ => state,
+        };
+        let target = target_dir.join(&name);
+        if target.exists() {
+            continue;
+        }
+        let moved = fs::create_dir_all(target_dir).and_then(|_| fs::rename(entry.path(), &target));
+        if let Err(e) = moved {
+            eprintln!("Warning: Could not move {} to {}: {}", entry.path().display(), target.display(), e);
+        }
+    }
+
+    // The session points at scratch notes by their full path
+    let session = state.join("tabs.json");
+    if let Ok(content) = fs::read_to_string(&session) {
+        let escaped = |path: PathBuf| serde_json::to_string(&path.to_string_lossy()).unwrap_or_default();
+        let (from, to) = (escaped(old.join("scratch")), escaped(data.join("scratch")));
+        let (from, to) = (from.trim_matches('"'), to.trim_matches('"'));
+        if !from.is_empty() && content.contains(from) {
+            let _ = fs::write(&session, content.replace(from, to));
+        }
+    }
+
+    // Only goes away once everything is out
+    let _ = fs::remove_dir(old);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_from() {
+        let base = std::env::temp_dir().join(format!("quicknotepad-migrate-{}", std::process::id()));
+        let (old, config, state, data) = (base.join("old"), base.join("config"), base.join("state"), base.join("data"));
+        fs::create_dir_all(old.join("scratch")).unwrap();
+        fs::write(old.join("scratch").join("note.txt"), "hi").unwrap();
+        fs::write(old.join("config.json"), "{}").unwrap();
+        let session = serde_json::json!([old.join("scratch").join("note.txt")]).to_string();
+        fs::write(old.join("tabs.json"), session).unwrap();
+
+        migrate_from(&old, &config, &state, &data);
+        assert!(!old.exists());
+        assert!(config.join("config.json").exists());
+        assert_eq!(fs::read_to_string(data.join("scratch").join("note.txt")).unwrap(), "hi");
+        let session = fs::read_to_string(state.join("tabs.json")).unwrap();
+        assert!(session.contains(&*data.join("scratch").to_string_lossy()));
+
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn test_resolve() {
+        std::env::set_var("QUICKNOTEPAD_TEST_DIR", "/srv/notes");
+        assert_eq!(resolve("$QUICKNOTEPAD_TEST_DIR/a.txt"), PathBuf::from("/srv/notes/a.txt"));
+        assert_eq!(resolve("${QUICKNOTEPAD_TEST_DIR}/../b.txt"), PathBuf::from("/srv/b.txt"));
+        assert_eq!(resolve("/tmp/$QUICKNOTEPAD_UNSET_VAR/./c$"), PathBuf::from("/tmp/$QUICKNOTEPAD_UNSET_VAR/c$"));
+        assert_eq!(resolve("~/x.txt"), home().join("x.txt"));
+        assert_eq!(resolve("~"), home());
+        assert_eq!(resolve("~name.txt"), std::env::current_dir().unwrap().join("~name.txt"));
+        assert_eq!(resolve("sub/../d.txt"), std::env::current_dir().unwrap().join("d.txt"));
+    }
+
+    #[test]
+    fn test_set_aside() {
+        let dir = std::env::temp_dir().join(format!("quicknotepad-paths-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("tabs.json");
+        fs::write(&file, "{ not json").unwrap();
+
+        let backup = set_aside(&file).unwrap();
+        assert!(!file.exists());
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "{ not json");
+        assert!(backup.file_name().unwrap().to_string_lossy().starts_with("tabs.json.corrupt-"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,138 @@
+// platform module - the spots where Linux, macOS and Windows differ: where the binary is
+// installed, which release asset fits, swapping the running binary, what the main shortcut
+// modifier is called and clipboard line endings
+use crate::core::paths;
+use std::fs;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+
+pub const EXE_NAME: &str = if cfg!(windows) { "quick.exe" } else { "quick" };
+
+// Modifier the shortcuts are written with: the GUI and terminals that report Cmd use it on macOS
+pub const PRIMARY_MODIFIER: &str = if cfg!(target_os = "macos") { "Cmd" } else { "Ctrl" };
+
+// Where the self-install puts the binary: ~/.local/bin, or the per-user Programs folder on Windows
+pub fn install_dir() -> PathBuf {
+    if cfg!(windows) {
+        let local = std::env::var("LOCALAPPDATA")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| paths::home().join("AppData").join("Local"));
+        local.join("Programs").join("QuickNotepad")
+    } else {
+        paths::home().join(".local").join("bin")
+    }
+}
+
+// Names release builds use for each CPU architecture
+const ARCHES: &[(&str, &[&str])] = &[
+    ("x86_64", &["x86_64", "amd64", "x64"]),
+    ("aarch64", &["aarch64", "arm64"]),
+    ("arm", &["armv7", "armhf"]),
+    ("x86", &["i686", "i386"]),
+    ("riscv64", &["riscv64"]),
+];
+
+// Architecture and OS this binary was built for, e.g. "aarch64-linux"
+pub fn target() -> String {
+    format!("{}-{}", std::env::consts::ARCH, std::env::consts::OS)
+}
+
+// Release asset built for this architecture and OS, either a bare binary or a .tar.gz holding one
+pub fn matches_release_asset(name: &str) -> bool {
+    matches_asset(name, std::env::consts::OS, std::env::consts::ARCH)
+}
+
+fn matches_asset(name: &str, os: &str, arch: &str) -> bool {
+    let name = name.to_lowercase();
+    if name.ends_with(".sha256") || name.ends_with(".zip") {
+        return false;
+    }
+    // Linux builds were published as a bare "quick" before
+    let os_matches = match os {
+        "linux" => name == "quick" || name.contains("linux"),
+        "macos" => name.contains("macos") || name.contains("darwin") || name.contains("apple"),
+        "windows" => name.contains("windows") && (name.ends_with(".exe") || name.ends_with(".tar.gz")),
+        os => name.contains(os),
+    };
+    // Builds without an architecture in the name were only ever made for x86_64
+    let arch_matches = match ARCHES.iter().find(|(_, aliases)| aliases.iter().any(|a| name.contains(a))) {
+        Some((asset_arch, _)) => *asset_arch == arch,
+        None => arch == "x86_64" || name.contains("universal"),
+    };
+    os_matches && arch_matches
+}
+
+pub fn make_executable(path: &Path) -> Result<(), Error> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+// Where the binary an update replaced is kept for rolling back: quick.old, or quick.old.exe
+// on Windows so it can still be run
+pub fn previous_exe(current: &Path) -> PathBuf {
+    current.with_extension(if cfg!(windows) { "old.exe" } else { "old" })
+}
+
+// Put `new` in place of the running binary. Windows can't overwrite a running .exe but can
+// rename it, so it is moved aside to the previous_exe path first
+pub fn replace_exe(current: &Path, new: &Path) -> Result<(), Error> {
+    if cfg!(windows) {
+        let aside = previous_exe(current);
+        let _ = fs::remove_file(&aside);
+        fs::rename(current, &aside)?;
+        if let Err(e) = fs::rename(new, current) {
+            let _ = fs::rename(&aside, current);
+            return Err(e);
+        }
+        return Ok(());
+    }
+    fs::rename(new, current)
+}
+
+// Text from the system clipboard with Windows line endings turned into the buffer's "\n"
+pub fn from_clipboard(text: String) -> String {
+    if text.contains('\r') {
+        text.replace("\r\n", "\n").replace('\r', "\n")
+    } else {
+        text
+    }
+}
+
+// Text for the system clipboard, with "\r\n" line endings on Windows so other programs paste it right
+pub fn to_clipboard(text: String) -> String {
+    if cfg!(windows) {
+        text.replace('\n', "\r\n")
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_release_asset_and_clipboard() {
+        assert!(!matches_release_asset("quick-notepad-1.2.0.tar.gz"));
+        assert!(!matches_release_asset("quick.sha256"));
+        assert!(matches_asset("quick", "linux", "x86_64"));
+        assert!(!matches_asset("quick", "linux", "aarch64"));
+        assert!(matches_asset("quick-linux-x86_64", "linux", "x86_64"));
+        assert!(!matches_asset("quick-linux-x86_64", "linux", "aarch64"));
+        assert!(matches_asset("quick-linux-arm64.tar.gz", "linux", "aarch64"));
+        assert!(!matches_asset("quick-linux-arm64.tar.gz.sha256", "linux", "aarch64"));
+        assert!(!matches_asset("quick-windows-x86_64.exe", "linux", "x86_64"));
+        assert!(matches_asset("quick-windows-x86_64.exe", "windows", "x86_64"));
+        assert!(!matches_asset("quick-windows-x86_64.zip", "windows", "x86_64"));
+        assert!(matches_asset("quick-macos-aarch64", "macos", "aarch64"));
+        assert!(matches_asset("quick-macos-universal", "macos", "aarch64"));
+        assert!(!matches_asset("quick-macos-x86_64", "macos", "aarch64"));
+        assert_eq!(from_clipboard("a\r\nb\rc".to_string()), "a\nb\nc");
+    }
+}
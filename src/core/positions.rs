@@ -0,0 +1,79 @@
+// positions module - last caret and scroll position of each file, kept in positions.json in the state directory
+use crate::core::paths;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Error;
+use std::path::PathBuf;
+
+// Oldest entries are dropped past this many files
+const MAX_FILES: usize = 500;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct FilePosition {
+    pub line: usize,
+    pub column: usize,
+    pub scroll: usize, // First line shown
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Positions {
+    files: Vec<(String, FilePosition)>, // Least recently closed first
+}
+
+// Files are keyed by canonical path so different spellings of one path share an entry
+fn key(path: &str) -> String {
+    fs::canonicalize(path).map_or_else(|_| path.to_string(), |p| p.to_string_lossy().into_owned())
+}
+
+impl Positions {
+    pub fn load() -> Self {
+        fs::read_to_string(Self::file_path())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        let json = serde_json::to_string(self).map_err(|e| Error::new(std::io::ErrorKind::InvalidData, e))?;
+        fs::write(Self::file_path(), json)
+    }
+
+    pub fn get(&self, path: &str) -> Option<FilePosition> {
+        let key = key(path);
+        self.files.iter().find(|(file, _)| *file == key).map(|(_, pos)| *pos)
+    }
+
+    pub fn set(&mut self, path: &str, position: FilePosition) {
+        let key = key(path);
+        self.files.retain(|(file, _)| *file != key);
+        self.files.push((key, position));
+        if self.files.len() > MAX_FILES {
+            self.files.remove(0);
+        }
+    }
+
+    fn file_path() -> PathBuf {
+        paths::state_dir().join("positions.json")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_positions_keep_latest_per_file() {
+        let mut positions = Positions::default();
+        let first = FilePosition { line: 10, column: 2, scroll: 4 };
+        positions.set("/tmp/a.txt", first);
+        positions.set("/tmp/b.txt", FilePosition::default());
+        assert_eq!(positions.get("/tmp/a.txt"), Some(first));
+
+        let moved = FilePosition { line: 3, column: 0, scroll: 0 };
+        positions.set("/tmp/a.txt", moved);
+        assert_eq!(positions.get("/tmp/a.txt"), Some(moved));
+        assert_eq!(positions.files.len(), 2);
+        assert_eq!(positions.files.last().map(|(_, pos)| *pos), Some(moved));
+        assert_eq!(positions.get("/tmp/missing.txt"), None);
+    }
+}
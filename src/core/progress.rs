@@ -0,0 +1,128 @@
+// progress module - long jobs (downloads, big file loads) run on a worker thread and report
+// how far they got through a shared Progress, which the front ends draw as a bar.
+// Dropping or cancelling the job asks the worker to stop at its next check
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+
+#[derive(Default)]
+struct Counters {
+    done: AtomicU64,
+    total: AtomicU64, // 0 while unknown
+    cancelled: AtomicBool,
+}
+
+// Cheap to clone: the job and the code drawing it share the same counters
+#[derive(Clone, Default)]
+pub struct Progress(Arc<Counters>);
+
+impl Progress {
+    pub fn set_total(&self, total: u64) {
+        self.0.total.store(total, Ordering::Relaxed);
+    }
+
+    pub fn advance(&self, amount: u64) {
+        self.0.done.fetch_add(amount, Ordering::Relaxed);
+    }
+
+    // Share of the work done, None while the total is unknown
+    pub fn fraction(&self) -> Option<f32> {
+        let total = self.0.total.load(Ordering::Relaxed);
+        (total > 0).then(|| (self.0.done.load(Ordering::Relaxed) as f32 / total as f32).min(1.0))
+    }
+
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::Relaxed)
+    }
+
+    // Error for a worker to return once it notices it was cancelled
+    pub fn check(&self) -> std::io::Result<()> {
+        if self.is_cancelled() {
+            return Err(std::io::Error::new(std::io::ErrorKind::Interrupted, "Cancelled"));
+        }
+        Ok(())
+    }
+}
+
+pub struct Job<T> {
+    pub label: String,
+    pub progress: Progress,
+    result: Receiver<T>,
+}
+
+impl<T: Send + 'static> Job<T> {
+    pub fn spawn(label: impl Into<String>, work: impl FnOnce(&Progress) -> T + Send + 'static) -> Self {
+        let progress = Progress::default();
+        let (sender, result) = mpsc::channel();
+        let worker = progress.clone();
+        std::thread::spawn(move || {
+            let _ = sender.send(work(&worker));
+        });
+        Self { label: label.into(), progress, result }
+    }
+
+    // The result once the worker is done, without waiting for it
+    pub fn poll(&self) -> Option<T> {
+        self.result.try_recv().ok()
+    }
+
+    // "Loading notes.txt 42%" style text for footers and status bars
+    pub fn status(&self) -> String {
+        match self.progress.fraction() {
+            Some(fraction) => format!("{} {}%", self.label, (fraction * 100.0) as u32),
+            None => format!("{}...", self.label),
+        }
+    }
+}
+
+impl<T> Drop for Job<T> {
+    fn drop(&mut self) {
+        self.progress.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn wait<T: Send + 'static>(job: &Job<T>) -> T {
+        loop {
+            if let Some(result) = job.poll() {
+                return result;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_job_reports_progress() {
+        let job = Job::spawn("Counting", |progress| {
+            progress.set_total(4);
+            for _ in 0..4 {
+                progress.advance(1);
+            }
+            42
+        });
+        assert_eq!(wait(&job), 42);
+        assert_eq!(job.progress.fraction(), Some(1.0));
+        assert_eq!(job.status(), "Counting 100%");
+    }
+
+    #[test]
+    fn test_job_cancel() {
+        let job = Job::spawn("Waiting", |progress| loop {
+            if let Err(e) = progress.check() {
+                return e.kind();
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        });
+        assert_eq!(job.status(), "Waiting...");
+        job.progress.cancel();
+        assert_eq!(wait(&job), std::io::ErrorKind::Interrupted);
+    }
+}
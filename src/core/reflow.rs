@@ -0,0 +1,190 @@
+// reflow module - re-wrap paragraphs to a column, keeping comment prefixes and list indentation
+use crate::core::continuation::continuation;
+use crate::core::line_metrics::LineMetrics;
+use unicode_width::UnicodeWidthStr;
+
+// One line split into the part reflow keeps and the words it moves around
+struct Parsed<'a> {
+    prefix: &'a str, // Kept on the paragraph's first line
+    hanging: String, // Put in front of every line after the first
+    text: &'a str,
+    item: bool,      // A list item starts a paragraph of its own
+    comment: bool,
+}
+
+fn parse<'a>(line: &'a str, filetype: Option<&str>) -> Parsed<'a> {
+    match continuation(line, filetype) {
+        Some(cont) => {
+            let prefix = &line[..cont.prefix_len];
+            let item = filetype == Some("Markdown");
+            // List items hang their following lines under the text, comments repeat the marker
+            let hanging = if item { " ".repeat(prefix.chars().count()) } else { cont.next_prefix };
+            Parsed { prefix, hanging, text: &line[cont.prefix_len..], item, comment: !item }
+        }
+        None => {
+            let text = line.trim_start();
+            let prefix = &line[..line.len() - text.len()];
+            Parsed { prefix, hanging: prefix.to_string(), text, item: false, comment: false }
+        }
+    }
+}
+
+// Block comment openers and closers stay on their own lines
+fn is_fixed(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.starts_with("/*") || trimmed.ends_with("*/")
+}
+
+// Re-wrap every paragraph in `lines` so no line runs past `column` unless a single word does.
+// Blank lines, lines holding only a prefix and block comment delimiters are left where they are
+pub fn reflow(lines: &[String], column: usize, tab_width: usize, filetype: Option<&str>) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut idx = 0;
+    while idx < lines.len() {
+        let first = parse(&lines[idx], filetype);
+        if first.text.trim().is_empty() || is_fixed(&lines[idx]) {
+            result.push(lines[idx].clone());
+            idx += 1;
+            continue;
+        }
+
+        // Comment lines join while the marker matches, anything else while lines stay plain text
+        let mut words: Vec<&str> = first.text.split_whitespace().collect();
+        idx += 1;
+        while idx < lines.len() && !is_fixed(&lines[idx]) {
+            let next = parse(&lines[idx], filetype);
+            let joins = if first.comment { next.comment && next.hanging == first.hanging } else { !next.comment && !next.item };
+            if !joins || next.text.trim().is_empty() {
+                break;
+            }
+            words.extend(next.text.split_whitespace());
+            idx += 1;
+        }
+
+        result.extend(fill(&words, first.prefix, &first.hanging, column, tab_width));
+    }
+    result
+}
+
+// Files that get auto-wrapped while typing
+pub fn is_prose(filetype: Option<&str>) -> bool {
+    matches!(filetype, None | Some("Markdown") | Some("Text File"))
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Wrap {
+    pub keep: String,   // The line up to the break, trailing spaces dropped
+    pub next: String,   // The new line: prefix plus the words moved down
+    pub split: usize,   // Byte in the old line where the moved words start
+    pub prefix: usize,  // Bytes of `next` taken by the prefix
+}
+
+// Break a line that runs past `column` before its last word that does not fit,
+// carrying the comment or list prefix onto the new line
+pub fn hard_wrap(line: &str, column: usize, tab_width: usize, filetype: Option<&str>) -> Option<Wrap> {
+    if LineMetrics::measure(line, tab_width).width <= column {
+        return None;
+    }
+    let parsed = parse(line, filetype);
+    let text_start = line.len() - parsed.text.len();
+
+    // Starts of every word after the first one
+    let starts = parsed
+        .text
+        .char_indices()
+        .zip(parsed.text.chars().skip(1))
+        .filter(|((_, c), next)| c.is_whitespace() && !next.is_whitespace())
+        .map(|((i, c), _)| text_start + i + c.len_utf8());
+    let fits = |split: usize| LineMetrics::measure(line[..split].trim_end(), tab_width).width <= column;
+    let mut split = None;
+    for start in starts {
+        // A first word longer than the column still moves what follows it
+        if split.is_some() && !fits(start) {
+            break;
+        }
+        split = Some(start);
+    }
+
+    let split = split?;
+    Some(Wrap {
+        keep: line[..split].trim_end().to_string(),
+        next: format!("{}{}", parsed.hanging, &line[split..]),
+        split,
+        prefix: parsed.hanging.len(),
+    })
+}
+
+fn fill(words: &[&str], prefix: &str, hanging: &str, column: usize, tab_width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = prefix.to_string();
+    let mut width = LineMetrics::measure(prefix, tab_width).width;
+    let mut empty = true;
+    let hanging_width = LineMetrics::measure(hanging, tab_width).width;
+
+    for word in words {
+        let word_width = word.width();
+        if !empty && width + 1 + word_width > column {
+            lines.push(std::mem::replace(&mut line, hanging.to_string()));
+            width = hanging_width;
+            empty = true;
+        }
+        // The prefix already ends in a space when it has text after it
+        if !empty || (!line.is_empty() && !line.ends_with(char::is_whitespace)) {
+            line.push(' ');
+            width += 1;
+        }
+        line.push_str(word);
+        width += word_width;
+        empty = false;
+    }
+    lines.push(line);
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(lines: &[&str], column: usize, filetype: Option<&str>) -> Vec<String> {
+        let lines: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+        reflow(&lines, column, 4, filetype)
+    }
+
+    #[test]
+    fn test_plain_paragraphs() {
+        assert_eq!(
+            run(&["  one two three", "four", "", "five six seven eight"], 12, None),
+            ["  one two", "  three four", "", "five six", "seven eight"]
+        );
+        assert_eq!(run(&["a_very_long_word b"], 5, None), ["a_very_long_word", "b"]);
+    }
+
+    #[test]
+    fn test_comments_and_lists() {
+        let rs = Some("Rust");
+        assert_eq!(
+            run(&["    /// one two three", "    /// four", "    // five"], 20, rs),
+            ["    /// one two", "    /// three four", "    // five"]
+        );
+        assert_eq!(
+            run(&["/*", " * one two three four", " */"], 12, rs),
+            ["/*", " * one two", " * three", " * four", " */"]
+        );
+
+        let md = Some("Markdown");
+        assert_eq!(
+            run(&["- one two three", "  four", "- [ ] five six seven"], 12, md),
+            ["- one two", "  three four", "- [ ] five", "      six", "      seven"]
+        );
+    }
+
+    #[test]
+    fn test_hard_wrap() {
+        assert_eq!(hard_wrap("one two", 10, 4, None), None);
+        let wrap = hard_wrap("one two  three", 10, 4, None).unwrap();
+        assert_eq!((wrap.keep.as_str(), wrap.next.as_str(), wrap.split), ("one two", "three", 9));
+        assert_eq!(hard_wrap("- one two three", 10, 4, Some("Markdown")).unwrap().next, "  three");
+        assert_eq!(hard_wrap("  long_word x", 6, 4, None).unwrap().keep, "  long_word");
+        assert_eq!(hard_wrap("long_word", 6, 4, None), None);
+    }
+}
@@ -0,0 +1,58 @@
+// repeat module - count prefix for the terminal: Esc followed by digits makes the next
+// motion or line operation run that many times, like `Esc 5 Down` moving five lines
+use crossterm::event::{KeyCode, KeyModifiers};
+
+const MAX_COUNT: usize = 9999;
+
+#[derive(Default)]
+pub struct RepeatCount {
+    armed: bool, // Esc was the last key, so a digit starts a count
+    count: usize,
+}
+
+impl RepeatCount {
+    // Feed a key press. None when it was a digit taken into the count, otherwise the
+    // number of times to run whatever the key does (Esc drops a count and starts a new one)
+    pub fn key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> Option<usize> {
+        if let KeyCode::Char(c @ '0'..='9') = code {
+            // A leading 0 is typed as text
+            if self.armed && (modifiers - KeyModifiers::SHIFT).is_empty() && (self.count > 0 || c != '0') {
+                self.count = (self.count * 10 + c.to_digit(10).unwrap_or(0) as usize).min(MAX_COUNT);
+                return None;
+            }
+        }
+        let times = if code == KeyCode::Esc { 1 } else { self.count.max(1) };
+        self.armed = code == KeyCode::Esc;
+        self.count = 0;
+        Some(times)
+    }
+
+    // Count typed so far, shown in the footer while waiting for the action
+    pub fn pending(&self) -> Option<usize> {
+        (self.count > 0).then_some(self.count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_after_esc() {
+        let mut repeat = RepeatCount::default();
+        assert_eq!(repeat.key(KeyCode::Char('3'), KeyModifiers::NONE), Some(1));
+
+        assert_eq!(repeat.key(KeyCode::Esc, KeyModifiers::NONE), Some(1));
+        assert_eq!(repeat.key(KeyCode::Char('1'), KeyModifiers::NONE), None);
+        assert_eq!(repeat.key(KeyCode::Char('2'), KeyModifiers::NONE), None);
+        assert_eq!(repeat.pending(), Some(12));
+        assert_eq!(repeat.key(KeyCode::Down, KeyModifiers::NONE), Some(12));
+        assert_eq!(repeat.pending(), None);
+        assert_eq!(repeat.key(KeyCode::Char('4'), KeyModifiers::NONE), Some(1));
+
+        // Esc 0 types the 0 and ends the count
+        repeat.key(KeyCode::Esc, KeyModifiers::NONE);
+        assert_eq!(repeat.key(KeyCode::Char('0'), KeyModifiers::NONE), Some(1));
+        assert_eq!(repeat.key(KeyCode::Char('5'), KeyModifiers::NONE), Some(1));
+    }
+}
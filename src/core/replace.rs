@@ -0,0 +1,285 @@
+// replace module - sed-style substitutions like s/foo/bar/g, matched literally line by line
+#[derive(Debug, Clone, PartialEq)]
+pub struct Substitution {
+    pub pattern: String,
+    pub replacement: String,
+    pub global: bool,      // Every match in a line, not just the first
+    pub ignore_case: bool,
+}
+
+impl Substitution {
+    // Parse "s/pattern/replacement/flags"; any character after the s is the delimiter and
+    // may appear escaped with a backslash. Flags are g (global) and i (ignore case)
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let mut chars = expr.chars();
+        if chars.next() != Some('s') {
+            return Err(format!("'{}' is not a substitution like s/old/new/", expr));
+        }
+        let delimiter = chars.next().ok_or("Missing delimiter after s")?;
+        if delimiter.is_alphanumeric() || delimiter == '\\' {
+            return Err(format!("'{}' cannot be used as a delimiter", delimiter));
+        }
+
+        let mut parts = vec![String::new()];
+        let mut escaped = false;
+        for c in chars {
+            match c {
+                _ if escaped => {
+                    // Only the delimiter and backslash need escaping, anything else stays as typed
+                    if c != delimiter && c != '\\' {
+                        parts.last_mut().unwrap().push('\\');
+                    }
+                    parts.last_mut().unwrap().push(c);
+                    escaped = false;
+                }
+                '\\' => escaped = true,
+                _ if c == delimiter && parts.len() < 3 => parts.push(String::new()),
+                _ => parts.last_mut().unwrap().push(c),
+            }
+        }
+        if parts.len() < 3 {
+            return Err(format!("'{}' needs three {} delimiters, like s{d}old{d}new{d}", expr, delimiter, d = delimiter));
+        }
+
+        let flags = parts.pop().unwrap_or_default();
+        let replacement = parts.pop().unwrap_or_default();
+        let pattern = parts.pop().unwrap_or_default();
+        if pattern.is_empty() {
+            return Err("The text to replace is empty".to_string());
+        }
+        if let Some(flag) = flags.chars().find(|c| !matches!(c, 'g' | 'i')) {
+            return Err(format!("Unknown flag '{}', use g and i", flag));
+        }
+        Ok(Self {
+            pattern,
+            replacement,
+            global: flags.contains('g'),
+            ignore_case: flags.contains('i'),
+        })
+    }
+
+    // Byte ranges of the matches in `line`, left to right without overlapping
+    pub fn find(&self, line: &str) -> Vec<(usize, usize)> {
+        let mut found = Vec::new();
+        let mut start = 0;
+        while start < line.len() {
+            match self.match_at(&line[start..]) {
+                Some(len) => {
+                    found.push((start, start + len));
+                    if !self.global {
+                        break;
+                    }
+                    start += len;
+                }
+                None => start += line[start..].chars().next().map_or(1, char::len_utf8),
+            }
+        }
+        found
+    }
+
+    // `line` with the matches replaced, and how many there were
+    pub fn apply(&self, line: &str) -> (String, usize) {
+        let found = self.find(line);
+        if found.is_empty() {
+            return (line.to_string(), 0);
+        }
+        let mut result = String::with_capacity(line.len());
+        let mut last = 0;
+        for &(start, end) in &found {
+            result.push_str(&line[last..start]);
+            result.push_str(&self.replacement);
+            last = end;
+        }
+        result.push_str(&line[last..]);
+        (result, found.len())
+    }
+
+    // Length in bytes of the match at the start of `text`
+    fn match_at(&self, text: &str) -> Option<usize> {
+        let mut chars = text.chars();
+        let mut len = 0;
+        for want in self.pattern.chars() {
+            let c = chars.next()?;
+            let same = c == want || (self.ignore_case && c.to_lowercase().eq(want.to_lowercase()));
+            if !same {
+                return None;
+            }
+            len += c.len_utf8();
+        }
+        Some(len)
+    }
+}
+
+// A reference in a replacement template to part of the match
+#[derive(Debug, Clone, PartialEq)]
+pub enum Group {
+    Index(usize), // $0 is the whole match, $1 and on capture groups
+    Name(String), // ${name}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Case {
+    Upper,
+    Lower,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Group(Group),
+    Next(Case), // \u / \l: the next character only
+    Until(Option<Case>), // \U / \L until \E, which is None
+}
+
+// Replacement text with $0, $1 and ${name} filled in from each match and \u \l \U \L \E changing
+// the case of what follows, as in Perl and vim. $$ is a dollar sign and \\ a backslash; anything
+// else is taken as typed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    pub fn parse(text: &str) -> Self {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            let part = match (c, chars.peek().copied()) {
+                ('$', Some('$')) | ('\\', Some('\\')) => {
+                    chars.next();
+                    literal.push(c);
+                    continue;
+                }
+                ('$', Some(d)) if d.is_ascii_digit() => {
+                    let mut number = String::new();
+                    while let Some(d) = chars.peek().copied().filter(char::is_ascii_digit) {
+                        number.push(d);
+                        chars.next();
+                    }
+                    Part::Group(Group::Index(number.parse().unwrap_or(usize::MAX)))
+                }
+                ('$', Some('{')) => {
+                    let rest: String = chars.clone().skip(1).take_while(|&c| c != '}').collect();
+                    let closed = chars.clone().nth(1 + rest.chars().count()) == Some('}');
+                    let valid = !rest.is_empty() && rest.chars().all(|c| c.is_alphanumeric() || c == '_');
+                    if !closed || !valid {
+                        literal.push(c);
+                        continue;
+                    }
+                    chars.nth(rest.chars().count() + 1);
+                    match rest.parse() {
+                        Ok(index) => Part::Group(Group::Index(index)),
+                        Err(_) => Part::Group(Group::Name(rest)),
+                    }
+                }
+                ('\\', Some(m @ ('u' | 'l' | 'U' | 'L' | 'E'))) => {
+                    chars.next();
+                    match m {
+                        'u' => Part::Next(Case::Upper),
+                        'l' => Part::Next(Case::Lower),
+                        'U' => Part::Until(Some(Case::Upper)),
+                        'L' => Part::Until(Some(Case::Lower)),
+                        _ => Part::Until(None),
+                    }
+                }
+                _ => {
+                    literal.push(c);
+                    continue;
+                }
+            };
+            if !literal.is_empty() {
+                parts.push(Part::Text(std::mem::take(&mut literal)));
+            }
+            parts.push(part);
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Text(literal));
+        }
+        Self { parts }
+    }
+
+    // Capture groups beyond the whole match ($0) that the template refers to
+    pub fn groups(&self) -> impl Iterator<Item = &Group> {
+        self.parts.iter().filter_map(|part| match part {
+            Part::Group(Group::Index(0)) => None,
+            Part::Group(group) => Some(group),
+            _ => None,
+        })
+    }
+
+    // The replacement for one match; groups `captured` has nothing for are left empty
+    pub fn expand<'a>(&self, captured: impl Fn(&Group) -> Option<&'a str>) -> String {
+        let mut out = String::new();
+        let mut until = None;
+        let mut next = None;
+        for part in &self.parts {
+            let text = match part {
+                Part::Text(text) => text.as_str(),
+                Part::Group(group) => captured(group).unwrap_or_default(),
+                Part::Next(case) => {
+                    next = Some(*case);
+                    continue;
+                }
+                Part::Until(case) => {
+                    until = *case;
+                    continue;
+                }
+            };
+            for c in text.chars() {
+                match next.take().or(until) {
+                    Some(Case::Upper) => out.extend(c.to_uppercase()),
+                    Some(Case::Lower) => out.extend(c.to_lowercase()),
+                    None => out.push(c),
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_groups_and_case() {
+        let captures = |group: &Group| match group {
+            Group::Index(0) => Some("hello world"),
+            Group::Index(1) => Some("hello"),
+            Group::Name(name) if name == "second" => Some("world"),
+            _ => None,
+        };
+        let expand = |template: &str| Template::parse(template).expand(captures);
+        assert_eq!(expand("[$0]"), "[hello world]");
+        assert_eq!(expand("${second}, $1"), "world, hello");
+        assert_eq!(expand("\\u$1 \\U${second}\\E!"), "Hello WORLD!");
+        assert_eq!(expand("\\L\\uHELLO"), "Hello");
+        assert_eq!(expand("$9|${nope}|"), "||");
+        assert_eq!(expand("$$1 costs \\\\u ${ $x \\n"), "$1 costs \\u ${ $x \\n");
+        assert_eq!(expand("${1}0"), "hello0");
+
+        let template = Template::parse("$0 \\u$2 ${name}");
+        assert_eq!(template.groups().collect::<Vec<_>>(), [&Group::Index(2), &Group::Name("name".to_string())]);
+        assert_eq!(Template::parse("\\U$0").groups().count(), 0);
+    }
+
+    #[test]
+    fn test_parse_and_apply() {
+        let sub = Substitution::parse("s/foo/bar/g").unwrap();
+        assert_eq!(sub.apply("foo food fo"), ("bar bard fo".to_string(), 2));
+
+        let first_only = Substitution::parse("s|a/b|c\\|d|").unwrap();
+        assert_eq!(first_only.pattern, "a/b");
+        assert_eq!(first_only.replacement, "c|d");
+        assert_eq!(first_only.apply("a/b a/b"), ("c|d a/b".to_string(), 1));
+
+        let ignore_case = Substitution::parse("s/ÉTÉ/winter/gi").unwrap();
+        assert_eq!(ignore_case.apply("été Été"), ("winter winter".to_string(), 2));
+
+        assert!(Substitution::parse("s/foo/bar").is_err());
+        assert!(Substitution::parse("s//bar/").is_err());
+        assert!(Substitution::parse("s/foo/bar/x").is_err());
+        assert!(Substitution::parse("y/foo/bar/").is_err());
+    }
+}
@@ -0,0 +1,75 @@
+// report module - "Report issue": a bug report template with the version, OS, terminal and the
+// session's latest status messages, sent as a prefilled GitHub issue or copied as plain text
+use crate::core::platform;
+
+const ISSUES_URL: &str = "https://github.com/DomanskiFilip/quick_notepad/issues/new";
+const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+pub const REPORT_OPENED: &str = "Opened a new issue in your browser with the details filled in";
+pub const REPORT_COPIED: &str = "No browser opened - the issue template is on the clipboard for github.com/DomanskiFilip/quick_notepad/issues";
+
+pub struct IssueReport {
+    pub text: String, // Markdown body, also what goes on the clipboard
+}
+
+impl IssueReport {
+    // `interface` names the frontend, e.g. from terminal() or "GUI"
+    pub fn new(interface: &str, recent: &[String]) -> Self {
+        let mut text = String::from(
+            "**What happened**\n\n\n**What you expected**\n\n\n**Steps to reproduce**\n\n\n---\n",
+        );
+        text.push_str(&format!("Quick Notepad {} ({})\n", CURRENT_VERSION, platform::target()));
+        text.push_str(&format!("Interface: {}\n", interface));
+        if !recent.is_empty() {
+            text.push_str("\nRecent messages:\n```\n");
+            recent.iter().for_each(|line| text.push_str(&format!("{}\n", line)));
+            text.push_str("```\n");
+        }
+        Self { text }
+    }
+
+    // New-issue page with the template filled in
+    pub fn url(&self) -> String {
+        format!("{}?title={}&body={}", ISSUES_URL, encode("Bug: "), encode(&self.text))
+    }
+}
+
+// The terminal as far as its environment tells: TERM, TERM_PROGRAM and COLORTERM
+pub fn terminal() -> String {
+    let vars: Vec<String> = ["TERM", "TERM_PROGRAM", "COLORTERM"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok().map(|value| format!("{}={}", name, value)))
+        .collect();
+    match vars.is_empty() {
+        true => "terminal".to_string(),
+        false => format!("terminal ({})", vars.join(", ")),
+    }
+}
+
+// Percent-encode everything but unreserved URL characters
+fn encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issue_report() {
+        let report = IssueReport::new("GUI", &["error: Disk full".to_string()]);
+        assert!(report.text.contains(&format!("Quick Notepad {}", CURRENT_VERSION)));
+        assert!(report.text.contains("Interface: GUI\n"));
+        assert!(report.text.contains("```\nerror: Disk full\n```"));
+
+        assert_eq!(encode("a b&c=ü"), "a%20b%26c%3D%C3%BC");
+        let url = report.url();
+        assert!(url.starts_with(ISSUES_URL));
+        assert!(!url[ISSUES_URL.len()..].contains([' ', '\n', '#']));
+    }
+}
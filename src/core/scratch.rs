@@ -0,0 +1,71 @@
+// scratch module - quick notes saved automatically to the scratch folder of the data directory
+use crate::core::paths;
+use crate::core::timestamp::format_timestamp;
+use std::fs;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Debug)]
+pub struct ScratchNote {
+    pub path: PathBuf,
+    pub name: String,
+    pub preview: String, // First non-empty line of the note
+}
+
+pub fn scratch_dir() -> PathBuf {
+    let path = paths::data_dir().join("scratch");
+
+    if let Err(e) = fs::create_dir_all(&path) {
+        eprintln!("Warning: Could not create scratch directory: {}", e);
+    }
+    path
+}
+
+// Timestamped path for a new note, e.g. scratch-20250101-093000.txt
+// `taken` lets the caller skip paths already used by open tabs
+pub fn new_scratch_path(taken: impl Fn(&Path) -> bool) -> PathBuf {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let stamp = format_timestamp(secs);
+    let dir = scratch_dir();
+
+    let mut path = dir.join(format!("scratch-{}.txt", stamp));
+    let mut suffix = 2;
+    while path.exists() || taken(&path) {
+        path = dir.join(format!("scratch-{}-{}.txt", stamp, suffix));
+        suffix += 1;
+    }
+    path
+}
+
+// All saved notes, newest first
+pub fn list_notes() -> Result<Vec<ScratchNote>, Error> {
+    let mut notes = Vec::new();
+
+    for entry in fs::read_dir(scratch_dir())? {
+        let path = entry?.path();
+        let name = match path.file_name() {
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => continue,
+        };
+        if !name.starts_with("scratch-") || !path.is_file() {
+            continue;
+        }
+
+        let content = fs::read_to_string(&path).unwrap_or_default();
+        let preview = content
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .unwrap_or("(empty)")
+            .to_string();
+        notes.push(ScratchNote { path, name, preview });
+    }
+
+    // Timestamps in the name sort chronologically
+    notes.sort_by(|a, b| b.name.cmp(&a.name));
+    Ok(notes)
+}
@@ -0,0 +1,194 @@
+// search_index module - search results, and the cache that keeps repeated searches fast in large files.
+// Each distinct line is lowercased once and gets a small trigram filter; lines whose
+// content changed are rebuilt on the next search, everything else is reused.
+use crate::core::selection::TextPosition;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+struct Entry {
+    lower: String,
+    trigrams: [u64; 4], // Bit set of hashed byte trigrams in `lower`
+    used: bool,
+}
+
+// Match locations of the terminal search, kept per tab
+#[derive(Clone, Debug)]
+pub struct SearchMatch {
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+}
+
+impl SearchMatch {
+    pub fn overlaps(&self, other: &SearchMatch) -> bool {
+        self.line == other.line && self.column < other.column + other.length && other.column < self.column + self.length
+    }
+
+    // Starts and ends inside the range
+    pub fn is_within(&self, (start, end): (TextPosition, TextPosition)) -> bool {
+        (self.line, self.column) >= (start.line, start.column) && (self.line, self.column + self.length) <= (end.line, end.column)
+    }
+}
+
+#[derive(Clone)]
+pub struct SearchState {
+    pub _query: String,
+    pub matches: Vec<SearchMatch>,
+    pub current_match_idx: usize,
+    pub replacement: Option<String>, // What Ctrl+H puts in place of each match
+    pub scope: Option<(TextPosition, TextPosition)>, // Alt+L in the prompts: only matches inside this range count
+    pub done: Vec<SearchMatch>, // Text already replaced or skipped while replacing, left out when searching again
+}
+
+impl SearchState {
+    pub fn new(_query: String, matches: Vec<SearchMatch>) -> Self {
+        Self {
+            _query,
+            matches,
+            current_match_idx: 0,
+            replacement: None,
+            scope: None,
+            done: Vec::new(),
+        }
+    }
+    
+    pub fn next_match(&mut self) {
+        if !self.matches.is_empty() {
+            self.current_match_idx = (self.current_match_idx + 1) % self.matches.len();
+        }
+    }
+    
+    pub fn prev_match(&mut self) {
+        if !self.matches.is_empty() {
+            self.current_match_idx = if self.current_match_idx == 0 {
+                self.matches.len() - 1
+            } else {
+                self.current_match_idx - 1
+            };
+        }
+    }
+    
+    // Pass over the current match for good and go on to the one after it
+    pub fn skip_current(&mut self) {
+        if self.current_match_idx >= self.matches.len() {
+            return;
+        }
+        let m = self.matches.remove(self.current_match_idx);
+        self.done.push(m);
+        if self.current_match_idx == self.matches.len() {
+            self.current_match_idx = 0;
+        }
+    }
+
+    pub fn current_match(&self) -> Option<&SearchMatch> {
+        self.matches.get(self.current_match_idx)
+    }
+}
+
+#[derive(Default)]
+pub struct SearchIndex {
+    entries: HashMap<u64, Entry>, // Keyed by line content, so moved or duplicated lines share one entry
+}
+
+impl SearchIndex {
+    // Line and byte column of every case-insensitive occurrence of `query`
+    pub fn find_all(&mut self, lines: &[String], query: &str) -> Vec<(usize, usize)> {
+        let query = query.to_lowercase();
+        let mut matches = Vec::new();
+        if query.is_empty() {
+            return matches;
+        }
+        let wanted = trigrams(&query);
+
+        for entry in self.entries.values_mut() {
+            entry.used = false;
+        }
+        for (line_idx, line) in lines.iter().enumerate() {
+            let entry = self.entries.entry(key(line)).or_insert_with(|| {
+                let lower = line.to_lowercase();
+                Entry { trigrams: trigrams(&lower), lower, used: false }
+            });
+            entry.used = true;
+
+            // Skip lines missing one of the query's trigrams
+            if entry.trigrams.iter().zip(&wanted).any(|(have, want)| have & want != *want) {
+                continue;
+            }
+            let mut start = 0;
+            while let Some(pos) = entry.lower[start..].find(&query) {
+                matches.push((line_idx, start + pos));
+                // Move past this match to find the next, overlapping ones included
+                start += pos + entry.lower[start + pos..].chars().next().map_or(1, char::len_utf8);
+            }
+        }
+        // Forget lines that were edited away
+        self.entries.retain(|_, entry| entry.used);
+        matches
+    }
+}
+
+fn key(line: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    line.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn trigrams(text: &str) -> [u64; 4] {
+    let mut bits = [0u64; 4];
+    for window in text.as_bytes().windows(3) {
+        let bit = (window[0] as usize * 31 + window[1] as usize * 7 + window[2] as usize) % 256;
+        bits[bit / 64] |= 1 << (bit % 64);
+    }
+    bits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_within_scope() {
+        let at = |line, column| TextPosition { line, column };
+        let scope = (at(1, 4), at(3, 2));
+        let m = |line, column, length| SearchMatch { line, column, length };
+        assert!(m(1, 4, 3).is_within(scope));
+        assert!(m(2, 40, 3).is_within(scope));
+        assert!(m(3, 0, 2).is_within(scope));
+        assert!(!m(1, 3, 3).is_within(scope));
+        assert!(!m(3, 1, 2).is_within(scope));
+        assert!(!m(0, 9, 1).is_within(scope));
+        assert!(m(1, 4, 3).overlaps(&m(1, 6, 1)));
+        assert!(!m(1, 4, 3).overlaps(&m(1, 7, 2)));
+        assert!(!m(1, 4, 3).overlaps(&m(2, 4, 3)));
+    }
+
+    #[test]
+    fn test_skip_current_wraps_around() {
+        let m = |line| SearchMatch { line, column: 0, length: 1 };
+        let mut state = SearchState::new("x".to_string(), vec![m(0), m(1), m(2)]);
+        state.current_match_idx = 2;
+        state.skip_current();
+        assert_eq!(state.current_match().map(|m| m.line), Some(0));
+        state.skip_current();
+        assert_eq!(state.current_match().map(|m| m.line), Some(1));
+        state.skip_current();
+        assert!(state.current_match().is_none());
+        assert_eq!(state.done.iter().map(|m| m.line).collect::<Vec<_>>(), [2, 0, 1]);
+    }
+
+    #[test]
+    fn test_find_all_after_edit() {
+        let mut lines = vec!["Hello world".to_string(), "nothing here".to_string(), "HELLO hello".to_string()];
+        let mut index = SearchIndex::default();
+        assert_eq!(index.find_all(&lines, "hello"), [(0, 0), (2, 0), (2, 6)]);
+        assert_eq!(index.find_all(&lines, "lo"), [(0, 3), (2, 3), (2, 9)]);
+        assert_eq!(index.find_all(&lines, "aaa"), []);
+
+        lines[1] = "say hello".to_string();
+        lines.insert(0, "ééhello".to_string());
+        assert_eq!(index.find_all(&lines, "hello"), [(0, 4), (1, 0), (2, 4), (3, 0), (3, 6)]);
+        assert_eq!(index.find_all(&lines, "aa"), []);
+        assert_eq!(index.entries.len(), 4);
+    }
+}
@@ -1,4 +1,27 @@
 // selection module for text selection data structures
+use serde::{Deserialize, Serialize};
+
+// Screen cell of the terminal caret, kept with each tab and undo entry
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Position {
+    pub x: u16,
+    pub y: u16,
+}
+
+impl Position {
+    pub const MARGIN: u16 = 4; // Width of the margin
+    pub const HEADER: u16 = 2; // Height of the header and breadcrumb bar
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self { 
+            x: Self::MARGIN, 
+            y: Self::HEADER 
+        } 
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct TextPosition {
     pub line: usize,
@@ -33,6 +56,13 @@ impl Selection {
         }
     }
     
+    // Both ends still inside `lines`, for selections kept while the text may have changed
+    pub fn fits(&self, lines: &[String]) -> bool {
+        [self.anchor, self.cursor]
+            .iter()
+            .all(|pos| lines.get(pos.line).is_some_and(|line| pos.column <= line.len()))
+    }
+
     // pub fn clear(&mut self) {
     //     self.cursor = self.anchor;
     // }
@@ -45,4 +75,21 @@ impl Selection {
     //     self.anchor = pos;
     //     self.cursor = pos;
     // }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selection_fits() {
+        let lines = vec!["hello".to_string(), "hi".to_string()];
+        let mut selection = Selection::new(TextPosition { line: 0, column: 5 });
+        selection.update_cursor(TextPosition { line: 1, column: 2 });
+        assert!(selection.fits(&lines));
+        selection.update_cursor(TextPosition { line: 1, column: 3 });
+        assert!(!selection.fits(&lines));
+        selection.update_cursor(TextPosition { line: 2, column: 0 });
+        assert!(!selection.fits(&lines));
+    }
+}
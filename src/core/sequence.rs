@@ -0,0 +1,77 @@
+// sequence module - number sequences typed down a column of lines
+use std::io::{Error, ErrorKind};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sequence {
+    pub start: i64,
+    pub step: i64,
+    pub width: usize, // Zero-pad numbers to at least this many digits
+}
+
+impl Default for Sequence {
+    fn default() -> Self {
+        Self { start: 1, step: 1, width: 0 }
+    }
+}
+
+impl Sequence {
+    // "start [step [width]]" separated by spaces or commas, e.g. "1", "10 10", "1,1,3"
+    pub fn parse(spec: &str) -> Result<Self, Error> {
+        let mut sequence = Self::default();
+        let parts: Vec<&str> = spec.split([' ', ',']).filter(|p| !p.is_empty()).collect();
+        if parts.len() > 3 {
+            return Err(Error::new(ErrorKind::InvalidInput, "Expected: start [step [width]]"));
+        }
+
+        let invalid = |part: &str| Error::new(ErrorKind::InvalidInput, format!("Invalid number '{}'", part));
+        if let Some(part) = parts.first() {
+            sequence.start = part.parse().map_err(|_| invalid(part))?;
+        }
+        if let Some(part) = parts.get(1) {
+            sequence.step = part.parse().map_err(|_| invalid(part))?;
+        }
+        if let Some(part) = parts.get(2) {
+            sequence.width = part.parse().map_err(|_| invalid(part))?;
+        }
+        Ok(sequence)
+    }
+
+    pub fn nth(&self, index: usize) -> String {
+        let value = self.start.saturating_add(self.step.saturating_mul(index as i64));
+        if value < 0 {
+            format!("-{:0width$}", value.unsigned_abs(), width = self.width)
+        } else {
+            format!("{:0width$}", value, width = self.width)
+        }
+    }
+}
+
+// Insert one number per line at char `column`, padding short lines with spaces
+pub fn insert_column(lines: &[String], column: usize, sequence: &Sequence) -> Vec<String> {
+    lines
+        .iter()
+        .enumerate()
+        .map(|(idx, line)| {
+            let len = line.chars().count();
+            let split = line.char_indices().nth(column).map(|(byte, _)| byte).unwrap_or(line.len());
+            let padding = " ".repeat(column.saturating_sub(len));
+            format!("{}{}{}{}", &line[..split], padding, sequence.nth(idx), &line[split..])
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_sequence() {
+        let sequence = Sequence::parse("8, 1, 2").unwrap();
+        let lines: Vec<String> = ["- a", "- b", "", "- d"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(insert_column(&lines, 2, &sequence), ["- 08a", "- 09b", "  10", "- 11d"]);
+
+        assert_eq!(Sequence::parse("").unwrap(), Sequence::default());
+        assert_eq!(Sequence::parse("1 -2").unwrap().nth(1), "-1");
+        assert!(Sequence::parse("one").is_err());
+    }
+}
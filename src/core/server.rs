@@ -0,0 +1,264 @@
+// server module - `quick --server`: the editing engine without a screen, driven by JSON-RPC 2.0
+// on stdio, one request per line and one response per line. For integration tests, remote
+// control from other programs and future frontends. Methods, all taking a "path":
+//   open                          -> { "lines": n }             read the file (or start it empty)
+//   text                          -> "whole text"
+//   edit { start, end, text }     -> { "line", "column" }       replace between two positions
+//   search { query }              -> [{ "line", "column" }]     case-insensitive
+//   undo / redo                   -> true when there was something to undo or redo
+//   save                          -> { "bytes": n }
+//   close                         -> true
+// plus "exit" to stop. Positions are { "line", "column" } with 0-based lines and grapheme columns
+use crate::core::buffer::Buffer;
+use crate::core::crypto;
+use crate::core::edit_history::{Edit, EditHistory, EditOperation};
+use crate::core::paths;
+use crate::core::search_index::SearchIndex;
+use crate::core::selection::{Position, TextPosition};
+use crate::core::widget::text_between;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, Error, Write};
+use unicode_segmentation::UnicodeSegmentation;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const FAILED: i64 = -32000; // The method ran but couldn't do it, e.g. the file is unreadable
+
+struct RpcError(i64, String);
+
+struct Document {
+    buffer: Buffer, // Keeps the line ending and final line break for saving
+    history: EditHistory,
+}
+
+impl Document {
+    // The lines of the text, without the buffer's padding
+    fn lines(&self) -> &[String] {
+        &self.buffer.lines[..self.buffer.logical_len()]
+    }
+}
+
+#[derive(Default)]
+pub struct Server {
+    documents: HashMap<String, Document>, // Keyed by resolved path
+    search: SearchIndex,
+    exiting: bool,
+}
+
+// Serve requests from `input` until it ends or "exit" is called
+pub fn run(input: impl BufRead, mut output: impl Write) -> Result<(), Error> {
+    let mut server = Server::default();
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = server.handle(&line) {
+            writeln!(output, "{}", response)?;
+            output.flush()?;
+        }
+        if server.exiting {
+            break;
+        }
+    }
+    Ok(())
+}
+
+impl Server {
+    // Response to one request line; None for notifications, which have no id
+    pub fn handle(&mut self, request: &str) -> Option<String> {
+        let request: Value = match serde_json::from_str(request) {
+            Ok(request) => request,
+            Err(e) => return Some(error_response(Value::Null, RpcError(PARSE_ERROR, e.to_string()))),
+        };
+        let id = request.get("id").cloned();
+        let Some(method) = request.get("method").and_then(Value::as_str) else {
+            return Some(error_response(id.unwrap_or(Value::Null), RpcError(INVALID_REQUEST, "Missing method".to_string())));
+        };
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+        let result = self.call(method, &params);
+        let id = id?;
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }).to_string(),
+            Err(e) => error_response(id, e),
+        })
+    }
+
+    fn call(&mut self, method: &str, params: &Value) -> Result<Value, RpcError> {
+        if method == "exit" {
+            self.exiting = true;
+            return Ok(Value::Null);
+        }
+        let path = params
+            .get("path")
+            .and_then(Value::as_str)
+            .map(|path| paths::resolve(path).to_string_lossy().into_owned())
+            .ok_or_else(|| RpcError(INVALID_PARAMS, "Missing \"path\"".to_string()))?;
+
+        if method == "open" {
+            let text = match fs::read_to_string(&path) {
+                Ok(text) => text,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+                Err(e) => return Err(RpcError(FAILED, format!("{}: {}", path, e))),
+            };
+            let buffer = Buffer::from_string(text);
+            let count = buffer.logical_len();
+            self.documents.insert(path, Document { buffer, history: EditHistory::new(500) });
+            return Ok(json!({ "lines": count }));
+        }
+
+        let Some(document) = self.documents.get_mut(&path) else {
+            return match method {
+                "text" | "edit" | "search" | "undo" | "redo" | "save" | "close" => {
+                    Err(RpcError(INVALID_PARAMS, format!("{} is not open", path)))
+                }
+                _ => Err(RpcError(METHOD_NOT_FOUND, format!("Unknown method {}", method))),
+            };
+        };
+        match method {
+            "text" => Ok(json!(document.lines().join("\n"))),
+            "edit" => {
+                let start = position(params, "start", document.lines())?;
+                let end = position(params, "end", document.lines())?;
+                if (end.line, end.column) < (start.line, start.column) {
+                    return Err(RpcError(INVALID_PARAMS, "\"end\" is before \"start\"".to_string()));
+                }
+                let text = params.get("text").and_then(Value::as_str).unwrap_or("");
+                let edit = Edit::ReplaceRange {
+                    start_line: start.line,
+                    start_column: start.column,
+                    end_line: end.line,
+                    end_column: end.column,
+                    old_text: text_between(document.lines(), start, end),
+                    new_text: text.to_string(),
+                };
+                edit.apply(&mut document.buffer.lines);
+                document.history.push(EditOperation {
+                    edit,
+                    cursor_before: Position::default(),
+                    cursor_after: Position::default(),
+                    scroll_before: 0,
+                    scroll_after: 0,
+                });
+                let end = match text.rsplit_once('\n') {
+                    Some((_, last)) => json!({ "line": start.line + text.matches('\n').count(), "column": last.graphemes(true).count() }),
+                    None => json!({ "line": start.line, "column": start.column + text.graphemes(true).count() }),
+                };
+                Ok(end)
+            }
+            "search" => {
+                let query = params
+                    .get("query")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| RpcError(INVALID_PARAMS, "Missing \"query\"".to_string()))?;
+                let matches = self.search.find_all(document.lines(), query);
+                // The index reports byte offsets into the lowercased line; count graphemes instead
+                let result = matches.into_iter().map(|(line, byte)| {
+                    let lower = document.buffer.lines[line].to_lowercase();
+                    json!({ "line": line, "column": lower.get(..byte).unwrap_or(&lower).graphemes(true).count() })
+                });
+                Ok(result.collect())
+            }
+            "undo" => Ok(json!(document.history.undo().map(|op| op.edit.reverse(&mut document.buffer.lines)).is_some())),
+            "redo" => Ok(json!(document.history.redo().map(|op| op.edit.apply(&mut document.buffer.lines)).is_some())),
+            "save" => {
+                // Written the way the editor saves: same line endings and final line break, and
+                // never as plaintext into an encrypted note
+                let text = document.buffer.file_text(false);
+                crypto::check_plaintext_path(&path)
+                    .and_then(|()| fs::write(&path, &text))
+                    .map_err(|e| RpcError(FAILED, format!("{}: {}", path, e)))?;
+                document.history.mark_saved();
+                Ok(json!({ "bytes": text.len() }))
+            }
+            "close" => {
+                self.documents.remove(&path);
+                Ok(json!(true))
+            }
+            _ => Err(RpcError(METHOD_NOT_FOUND, format!("Unknown method {}", method))),
+        }
+    }
+}
+
+fn error_response(id: Value, RpcError(code, message): RpcError) -> String {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } }).to_string()
+}
+
+// params[name] as a position inside the text
+fn position(params: &Value, name: &str, lines: &[String]) -> Result<TextPosition, RpcError> {
+    let field = |key: &str| params.get(name).and_then(|p| p.get(key)).and_then(Value::as_u64).map(|n| n as usize);
+    let (Some(line), Some(column)) = (field("line"), field("column")) else {
+        return Err(RpcError(INVALID_PARAMS, format!("\"{}\" needs a line and a column", name)));
+    };
+    match lines.get(line) {
+        Some(text) if column <= text.graphemes(true).count() => Ok(TextPosition { line, column }),
+        _ => Err(RpcError(INVALID_PARAMS, format!("\"{}\" is outside the text", name))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_search_undo_save() {
+        let path = std::env::temp_dir().join(format!("quick_server_{}.txt", std::process::id()));
+        fs::write(&path, "one\ntwo").unwrap();
+        let path = path.to_string_lossy().into_owned();
+        let input = [
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "open", "params": { "path": path } }),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "edit", "params": {
+                "path": path, "start": { "line": 0, "column": 3 }, "end": { "line": 1, "column": 1 }, "text": "!\nT" } }),
+            json!({ "jsonrpc": "2.0", "id": 3, "method": "search", "params": { "path": path, "query": "TW" } }),
+            json!({ "jsonrpc": "2.0", "id": 4, "method": "save", "params": { "path": path } }),
+            json!({ "jsonrpc": "2.0", "id": 5, "method": "undo", "params": { "path": path } }),
+            json!({ "jsonrpc": "2.0", "id": 6, "method": "text", "params": { "path": path } }),
+            json!({ "jsonrpc": "2.0", "id": 7, "method": "bogus", "params": { "path": path } }),
+            json!({ "jsonrpc": "2.0", "method": "exit" }),
+            json!({ "jsonrpc": "2.0", "id": 8, "method": "text", "params": { "path": path } }),
+        ];
+        let input: String = input.iter().map(|request| format!("{}\n", request)).collect();
+        let mut output = Vec::new();
+        run(input.as_bytes(), &mut output).unwrap();
+
+        let responses: Vec<Value> = String::from_utf8(output).unwrap().lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(responses.len(), 7); // The exit notification has no reply and nothing is read after it
+        assert_eq!(responses[0]["result"], json!({ "lines": 2 }));
+        assert_eq!(responses[1]["result"], json!({ "line": 1, "column": 1 }));
+        assert_eq!(responses[2]["result"], json!([{ "line": 1, "column": 0 }]));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one!\nTwo");
+        assert_eq!(responses[4]["result"], json!(true));
+        assert_eq!(responses[5]["result"], json!("one\ntwo"));
+        assert_eq!(responses[6]["error"]["code"], json!(METHOD_NOT_FOUND));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_save_keeps_line_endings_and_refuses_encrypted_notes() {
+        let dir = std::env::temp_dir().join(format!("quick_server_save_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let text = dir.join("crlf.txt");
+        fs::write(&text, "one\r\ntwo\r\n").unwrap();
+        let note = dir.join("new.qnenc").to_string_lossy().into_owned();
+        let text = text.to_string_lossy().into_owned();
+        let mut server = Server::default();
+        let call = |server: &mut Server, method: &str, path: &str| {
+            let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": { "path": path } });
+            serde_json::from_str::<Value>(&server.handle(&request.to_string()).unwrap()).unwrap()
+        };
+
+        assert_eq!(call(&mut server, "open", &text)["result"], json!({ "lines": 2 }));
+        assert_eq!(call(&mut server, "text", &text)["result"], json!("one\ntwo"));
+        assert_eq!(call(&mut server, "save", &text)["result"], json!({ "bytes": 10 }));
+        assert_eq!(fs::read_to_string(&text).unwrap(), "one\r\ntwo\r\n");
+
+        call(&mut server, "open", &note);
+        assert_eq!(call(&mut server, "save", &note)["error"]["code"], json!(FAILED));
+        assert!(!std::path::Path::new(&note).exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
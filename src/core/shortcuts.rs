@@ -1,11 +1,30 @@
 // shortcuts module to handle key mappings
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind, MouseButton};
 use crate::core::actions::Action;
+use crate::core::commands::{self, Command};
+use crate::core::jumps::Jump;
+use crate::core::keybindings::{self, Key};
+use crate::core::keymap::Keymap;
+use std::collections::BTreeMap;
 
 pub struct Shortcuts {
     last_click_time: std::time::Instant,
     last_click_pos: Option<(u16, u16)>,
     click_count: u8,
+    keymap: Keymap,
+    commands: Vec<Command>,
+    custom: Vec<Key>, // Keys bound in the config, which the keymap profile doesn't override
+    scroll_lines: usize, // Lines one wheel notch scrolls
+    pending: Option<((KeyCode, KeyModifiers), std::time::Instant)>, // First key of a sequence and when it expires
+}
+
+// How long the second key of a sequence is waited for
+pub const CHORD_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(1500);
+
+impl Default for Shortcuts {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Shortcuts {
@@ -14,42 +33,106 @@ impl Shortcuts {
             last_click_time: std::time::Instant::now(),
             last_click_pos: None,
             click_count: 0,
+            keymap: Keymap::Default,
+            commands: commands::registry(),
+            custom: Vec::new(),
+            scroll_lines: 3,
+            pending: None,
+        }
+    }
+
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+
+    pub fn set_scroll_lines(&mut self, lines: usize) {
+        self.scroll_lines = lines.max(1);
+    }
+
+    // Lay the config's [keybindings] over the defaults, returning the ones that were skipped and why
+    pub fn set_keybindings(&mut self, bindings: &BTreeMap<String, String>) -> Vec<String> {
+        let remapped = keybindings::remap(bindings);
+        self.commands = remapped.commands;
+        self.custom = remapped.custom;
+        remapped.problems
+    }
+
+    // Single-key commands as (KeyCode, Modifiers, Action, Description), from the command registry
+    fn get_mappings(commands: &[Command]) -> Vec<(KeyCode, KeyModifiers, Action, String)> {
+        commands
+            .iter()
+            .filter(|command| command.chord.is_none())
+            .filter_map(|command| command.key.map(|(code, mods)| (code, mods, command.action, command.name.clone())))
+            .collect()
+    }
+
+    // "Ctrl+K Ctrl+H" style key text for any registered command
+    pub fn describe(command: &Command) -> Option<String> {
+        let (code, mods) = command.key?;
+        let key = Self::key_to_string(code, mods);
+        Some(match command.chord {
+            Some((lead_code, lead_mods)) => format!("{} {}", Self::key_to_string(lead_code, lead_mods), key),
+            None => key,
+        })
+    }
+
+    // The first key of a sequence waiting for its second, for the footer
+    pub fn pending_chord(&self) -> Option<(String, std::time::Instant)> {
+        self.pending.map(|((code, mods), until)| (Self::key_to_string(code, mods), until))
+    }
+
+    pub fn cancel_chord(&mut self) {
+        self.pending = None;
+    }
+
+    // Cmd works like Ctrl on macOS (terminals that pass it on report it as Super), and
+    // Ctrl+Shift+letter arrives as an uppercase letter with or without the Shift flag
+    fn normalize(code: KeyCode, mut mods: KeyModifiers, cmd_is_ctrl: bool) -> (KeyCode, KeyModifiers) {
+        if cmd_is_ctrl && mods.contains(KeyModifiers::SUPER) {
+            mods = (mods - KeyModifiers::SUPER) | KeyModifiers::CONTROL;
+        }
+        match code {
+            KeyCode::Char(c) if c.is_ascii_uppercase() && mods.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+                (KeyCode::Char(c.to_ascii_lowercase()), mods | KeyModifiers::SHIFT)
+            }
+            _ => (code, mods),
         }
     }
 
-    // THE ONE PLACE TO CHANGE SHORTCUTS
-    // Format: (KeyCode, Modifiers, Action, Description)
-    fn get_mappings() -> Vec<(KeyCode, KeyModifiers, Action, &'static str)> {
-        vec![
-            (KeyCode::Left, KeyModifiers::empty(), Action::Left, "Move caret left"),
-            (KeyCode::Right, KeyModifiers::empty(), Action::Right, "Move caret right"),
-            (KeyCode::Up, KeyModifiers::empty(), Action::Up, "Move caret up"),
-            (KeyCode::Down, KeyModifiers::empty(), Action::Down, "Move caret down"),
-            (KeyCode::PageUp, KeyModifiers::empty(), Action::Top, "Move to top of view"),
-            (KeyCode::PageDown, KeyModifiers::empty(), Action::Bottom, "Move to bottom of view"),
-            (KeyCode::Home, KeyModifiers::empty(), Action::MaxLeft, "Move to start of line"),
-            (KeyCode::End, KeyModifiers::empty(), Action::MaxRight, "Move to end of line"),
-            (KeyCode::Enter, KeyModifiers::empty(), Action::NextLine, "Insert new line"),
-            (KeyCode::Tab, KeyModifiers::empty(), Action::Print, "Insert tab (4 spaces)"),
-            (KeyCode::Backspace, KeyModifiers::empty(), Action::Backspace, "Delete before cursor"),
-            (KeyCode::Delete, KeyModifiers::empty(), Action::Delete, "Delete at cursor"),
-            (KeyCode::Char('g'), KeyModifiers::CONTROL, Action::ToggleCtrlShortcuts, "Toggle ctrl shortcuts footer"),
-            (KeyCode::Char('q'), KeyModifiers::CONTROL, Action::Quit, "Quit"),
-            (KeyCode::Char('s'), KeyModifiers::CONTROL, Action::Save, "Save"),
-            (KeyCode::Char('n'), KeyModifiers::CONTROL, Action::New, "New"),
-            (KeyCode::Char('c'), KeyModifiers::CONTROL, Action::Copy, "Copy"),
-            (KeyCode::Char('v'), KeyModifiers::CONTROL, Action::Paste, "Paste"),
-            (KeyCode::Char('x'), KeyModifiers::CONTROL, Action::Cut, "Cut"),
-            (KeyCode::Char('a'), KeyModifiers::CONTROL, Action::SelectAll, "Select all"),
-            (KeyCode::Char('z'), KeyModifiers::CONTROL, Action::Undo, "Undo"),
-            (KeyCode::Char('y'), KeyModifiers::CONTROL, Action::Redo, "Redo"),
-            (KeyCode::Char('f'), KeyModifiers::CONTROL, Action::Search, "Search"),
-            (KeyCode::Char('u'), KeyModifiers::CONTROL, Action::CheckUpdate, "Check for updates"),
-        ]
-    }
-        
     pub fn resolve(&mut self, event: &KeyEvent) -> Option<Action> {
+        let (code, modifiers) = Self::normalize(event.code, event.modifiers, cfg!(target_os = "macos"));
+        let event = &KeyEvent::new(code, modifiers);
+        // Second key of a sequence; anything unbound just ends it
+        if let Some((leader, until)) = self.pending.take() {
+            if std::time::Instant::now() < until {
+                return commands::find_chord(&self.commands, leader, event.code, event.modifiers)
+                    .map(|command| command.action);
+            }
+        }
+
+        // Sequences come before the keymap so a profile can't shadow their first key
+        let custom = self.custom.contains(&(event.code, event.modifiers));
+        if !custom && commands::is_chord_leader(&self.commands, event.code, event.modifiers) {
+            self.pending = Some(((event.code, event.modifiers), std::time::Instant::now() + CHORD_TIMEOUT));
+            return None;
+        }
+        let profile = self.keymap.mappings().into_iter().find(|(code, mods, _, _)| {
+            !custom && *code == event.code && *mods == event.modifiers
+        });
+        if let Some((_, _, action, _)) = profile {
+            return Some(action);
+        }
+        if let Some(command) = commands::find(&self.commands, event.code, event.modifiers) {
+            return Some(command.action);
+        }
+
         match (event.code, event.modifiers) {
+            // Paragraph and block jumps, with Shift extending the selection
+            (KeyCode::Up, m) if m.contains(KeyModifiers::CONTROL) => Some(Self::jump(Jump::ParagraphUp, m)),
+            (KeyCode::Down, m) if m.contains(KeyModifiers::CONTROL) => Some(Self::jump(Jump::ParagraphDown, m)),
+            (KeyCode::Left, m) if m.contains(KeyModifiers::ALT) => Some(Self::jump(Jump::BlockStart, m)),
+            (KeyCode::Right, m) if m.contains(KeyModifiers::ALT) => Some(Self::jump(Jump::BlockEnd, m)),
+
             // Movement with Shift = Selection
             (KeyCode::Left, m) if m.contains(KeyModifiers::SHIFT) => Some(Action::SelectLeft),
             (KeyCode::Right, m) if m.contains(KeyModifiers::SHIFT) => Some(Action::SelectRight),
@@ -60,7 +143,7 @@ impl Shortcuts {
             (KeyCode::Home, m) if m.contains(KeyModifiers::SHIFT) => Some(Action::SelectMaxLeft),
             (KeyCode::End, m) if m.contains(KeyModifiers::SHIFT) => Some(Action::SelectMaxRight),
             
-            // Regular movement (clears selection)
+            // Regular movement (clears selection), whatever other modifiers are held
             (KeyCode::Left, _) => Some(Action::Left),
             (KeyCode::Right, _) => Some(Action::Right),
             (KeyCode::Up, _) => Some(Action::Up),
@@ -74,18 +157,6 @@ impl Shortcuts {
             (KeyCode::Tab, _) => Some(Action::Print),  // Tab inserts 4 spaces
             (KeyCode::Backspace, _) => Some(Action::Backspace),
             (KeyCode::Delete, _) => Some(Action::Delete),
-            (KeyCode::Char('g'), KeyModifiers::CONTROL) => Some(Action::ToggleCtrlShortcuts),
-            (KeyCode::Char('s'), KeyModifiers::CONTROL) => Some(Action::Save),
-            (KeyCode::Char('n'), KeyModifiers::CONTROL) => Some(Action::New),
-            (KeyCode::Char('q'), KeyModifiers::CONTROL) => Some(Action::Quit),
-            (KeyCode::Char('c'), KeyModifiers::CONTROL) => Some(Action::Copy),
-            (KeyCode::Char('v'), KeyModifiers::CONTROL) => Some(Action::Paste),
-            (KeyCode::Char('x'), KeyModifiers::CONTROL) => Some(Action::Cut),
-            (KeyCode::Char('a'), KeyModifiers::CONTROL) => Some(Action::SelectAll),
-            (KeyCode::Char('z'), KeyModifiers::CONTROL) => Some(Action::Undo),
-            (KeyCode::Char('y'), KeyModifiers::CONTROL) => Some(Action::Redo),
-            (KeyCode::Char('f'), KeyModifiers::CONTROL) => Some(Action::Search),
-            (KeyCode::Char('u'), KeyModifiers::CONTROL) => Some(Action::CheckUpdate),
             // Tab switching - Ctrl+Number (existing)
             (KeyCode::Char('1'), KeyModifiers::CONTROL) => Some(Action::SwitchTab(1)),
             (KeyCode::Char('2'), KeyModifiers::CONTROL) => Some(Action::SwitchTab(2)),
@@ -108,17 +179,6 @@ impl Shortcuts {
             (KeyCode::Char('8'), m) if m.contains(KeyModifiers::ALT) => Some(Action::SwitchTab(8)),
             (KeyCode::Char('9'), m) if m.contains(KeyModifiers::ALT) => Some(Action::SwitchTab(9)),
             (KeyCode::Char('0'), m) if m.contains(KeyModifiers::ALT) => Some(Action::SwitchTab(10)),
-            // Tab switching - F1..F10 (no modifier)
-            (KeyCode::F(1), _) => Some(Action::SwitchTab(1)),
-            (KeyCode::F(2), _) => Some(Action::SwitchTab(2)),
-            (KeyCode::F(3), _) => Some(Action::SwitchTab(3)),
-            (KeyCode::F(4), _) => Some(Action::SwitchTab(4)),
-            (KeyCode::F(5), _) => Some(Action::SwitchTab(5)),
-            (KeyCode::F(6), _) => Some(Action::SwitchTab(6)),
-            (KeyCode::F(7), _) => Some(Action::SwitchTab(7)),
-            (KeyCode::F(8), _) => Some(Action::SwitchTab(8)),
-            (KeyCode::F(9), _) => Some(Action::SwitchTab(9)),
-            (KeyCode::F(10), _) => Some(Action::SwitchTab(10)),
             (KeyCode::Char(_c), m) if m.is_empty() || m == KeyModifiers::SHIFT => {
                 Some(Action::Print)
             }
@@ -126,6 +186,14 @@ impl Shortcuts {
         }
     }
     
+    fn jump(jump: Jump, mods: KeyModifiers) -> Action {
+        if mods.contains(KeyModifiers::SHIFT) {
+            Action::SelectJump(jump)
+        } else {
+            Action::Jump(jump)
+        }
+    }
+
     pub fn resolve_mouse(&mut self, event: &MouseEvent) -> Option<Action> {
         match event.kind {
             MouseEventKind::Down(MouseButton::Left) => {
@@ -160,13 +228,16 @@ impl Shortcuts {
                 Some(Action::MouseDrag(event.column, event.row))
             }
             MouseEventKind::Up(MouseButton::Left) => Some(Action::MouseUp(event.column, event.row)),
+            // Shift+wheel is left for horizontal scrolling
+            MouseEventKind::ScrollUp if !event.modifiers.contains(KeyModifiers::SHIFT) => Some(Action::ScrollUp(self.scroll_lines)),
+            MouseEventKind::ScrollDown if !event.modifiers.contains(KeyModifiers::SHIFT) => Some(Action::ScrollDown(self.scroll_lines)),
             _ => None,
         }
     }
     
     // Returns Ctrl shortcuts for footer display
-    pub fn get_ctrl_shortcuts() -> Vec<(String, &'static str)> {
-        Self::get_mappings()
+    pub fn get_ctrl_shortcuts(commands: &[Command]) -> Vec<(String, String)> {
+        Self::get_mappings(commands)
             .into_iter()
             .filter(|(_, mods, _, _)| mods.contains(KeyModifiers::CONTROL))
             .map(|(code, mods, _, desc)| (Self::key_to_short_string(code, mods), desc))
@@ -175,13 +246,15 @@ impl Shortcuts {
     
     // print all shortcuts
     pub fn print_all() {        
-        for (code, mods, _, desc) in Self::get_mappings() {
-            println!("  {:<15} : {}", Self::key_to_string(code, mods), desc);
+        for command in commands::registry() {
+            if let Some(keys) = Self::describe(&command) {
+                println!("  {:<15} : {}", keys, command.name);
+            }
         }
     }
     
     // used to display shortcuts with flag --shortcuts
-    fn key_to_string(code: KeyCode, mods: KeyModifiers) -> String {
+    pub fn key_to_string(code: KeyCode, mods: KeyModifiers) -> String {
         let mut string = String::new();
         if mods.contains(KeyModifiers::CONTROL) { string.push_str("Ctrl+"); }
         if mods.contains(KeyModifiers::ALT) { string.push_str("Alt+"); }
@@ -200,6 +273,7 @@ impl Shortcuts {
             KeyCode::End => string.push_str("End"),
             KeyCode::PageUp => string.push_str("Page Up"),
             KeyCode::PageDown => string.push_str("Page Down"),
+            KeyCode::F(number) => string.push_str(&format!("F{}", number)),
             _ => string.push_str("Unknown"),
         }
         string
@@ -213,8 +287,65 @@ impl Shortcuts {
         if mods.contains(KeyModifiers::SHIFT) { string.push_str("S-"); }
         match code {
             KeyCode::Char(character) => string.push(character.to_ascii_uppercase()),
-            _ => string.push_str("?"),
+            KeyCode::Left => string.push('←'),
+            KeyCode::Right => string.push('→'),
+            KeyCode::Up => string.push('↑'),
+            KeyCode::Down => string.push('↓'),
+            KeyCode::F(number) => string.push_str(&format!("F{}", number)),
+            _ => string.push('?'),
         }
         string
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redo_and_cmd_keys() {
+        let mut shortcuts = Shortcuts::new();
+        // Ctrl+Shift+Z as terminals with and without the Shift flag report it
+        for mods in [KeyModifiers::CONTROL | KeyModifiers::SHIFT, KeyModifiers::CONTROL] {
+            assert_eq!(shortcuts.resolve(&KeyEvent::new(KeyCode::Char('Z'), mods)), Some(Action::Redo));
+        }
+        assert_eq!(shortcuts.resolve(&KeyEvent::new(KeyCode::Char('z'), KeyModifiers::CONTROL)), Some(Action::Undo));
+        assert_eq!(
+            Shortcuts::normalize(KeyCode::Char('s'), KeyModifiers::SUPER, true),
+            (KeyCode::Char('s'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            Shortcuts::normalize(KeyCode::Char('s'), KeyModifiers::SUPER, false),
+            (KeyCode::Char('s'), KeyModifiers::SUPER)
+        );
+    }
+
+    #[test]
+    fn test_wheel_scrolls_configured_lines() {
+        let mut shortcuts = Shortcuts::new();
+        shortcuts.set_scroll_lines(5);
+        let wheel = |kind, modifiers| MouseEvent { kind, column: 10, row: 4, modifiers };
+        assert_eq!(shortcuts.resolve_mouse(&wheel(MouseEventKind::ScrollDown, KeyModifiers::empty())), Some(Action::ScrollDown(5)));
+        assert_eq!(shortcuts.resolve_mouse(&wheel(MouseEventKind::ScrollUp, KeyModifiers::CONTROL)), Some(Action::ScrollUp(5)));
+        assert_eq!(shortcuts.resolve_mouse(&wheel(MouseEventKind::ScrollUp, KeyModifiers::SHIFT)), None);
+    }
+
+    #[test]
+    fn test_config_keys_win_over_keymap() {
+        let mut shortcuts = Shortcuts::new();
+        shortcuts.set_keymap(Keymap::Emacs);
+        let bindings = [("Select all".to_string(), "Ctrl+A".to_string())].into_iter().collect();
+        assert!(shortcuts.set_keybindings(&bindings).is_empty());
+        assert_eq!(shortcuts.resolve(&KeyEvent::new(KeyCode::Char('a'), KeyModifiers::CONTROL)), Some(Action::SelectAll));
+        assert_eq!(shortcuts.resolve(&KeyEvent::new(KeyCode::Char('e'), KeyModifiers::CONTROL)), Some(Action::MaxRight));
+    }
+
+    #[test]
+    fn test_emacs_keymap_keeps_sequences() {
+        let mut shortcuts = Shortcuts::new();
+        shortcuts.set_keymap(Keymap::Emacs);
+        assert_eq!(shortcuts.resolve(&KeyEvent::new(KeyCode::Char('k'), KeyModifiers::CONTROL)), None);
+        assert_eq!(shortcuts.resolve(&KeyEvent::new(KeyCode::Char('h'), KeyModifiers::empty())), Some(Action::FileHistory));
+        assert_eq!(shortcuts.resolve(&KeyEvent::new(KeyCode::Char('k'), KeyModifiers::ALT)), Some(Action::KillLine));
+    }
+}
@@ -0,0 +1,206 @@
+// smart_select module - grow a selection word → string / brackets → line → block → all
+use crate::core::folding;
+use crate::core::selection::TextPosition;
+
+// Ordered (start, end) in char columns, end exclusive
+pub type Range = (TextPosition, TextPosition);
+
+fn pos(line: usize, column: usize) -> TextPosition {
+    TextPosition { line, column }
+}
+
+fn key(p: TextPosition) -> (usize, usize) {
+    (p.line, p.column)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// Smallest range around `range` that is strictly larger; None once everything is selected
+pub fn expand(lines: &[String], range: Range) -> Option<Range> {
+    let text: Vec<Vec<char>> = lines.iter().map(|l| l.chars().collect()).collect();
+    if text.is_empty() {
+        return None;
+    }
+    let (start, end) = range;
+    let line_len = |line: usize| text.get(line).map_or(0, |l| l.len());
+    let mut candidates: Vec<Range> = Vec::new();
+
+    if start.line == end.line {
+        let chars = &text[start.line];
+
+        // Word under the caret
+        let mut from = start.column.min(chars.len());
+        let mut to = end.column.min(chars.len());
+        while from > 0 && is_word_char(chars[from - 1]) {
+            from -= 1;
+        }
+        while to < chars.len() && is_word_char(chars[to]) {
+            to += 1;
+        }
+        candidates.push((pos(start.line, from), pos(start.line, to)));
+
+        // String contents, then the string with its quotes
+        let mut idx = 0;
+        while idx < chars.len() {
+            let quote = chars[idx];
+            if matches!(quote, '"' | '\'' | '`') {
+                let close = (idx + 1..chars.len()).find(|&j| chars[j] == quote && chars[j - 1] != '\\');
+                if let Some(close) = close {
+                    candidates.push((pos(start.line, idx + 1), pos(start.line, close)));
+                    candidates.push((pos(start.line, idx), pos(start.line, close + 1)));
+                    idx = close;
+                }
+            }
+            idx += 1;
+        }
+    }
+
+    // Bracket contents, then the brackets themselves
+    if let Some((open, close)) = enclosing_brackets(&text, start, end) {
+        candidates.push((pos(open.line, open.column + 1), close));
+        candidates.push((open, pos(close.line, close.column + 1)));
+    }
+
+    // Line text without indentation, then the whole lines
+    let indent = text[start.line].iter().take_while(|c| c.is_whitespace()).count();
+    let trimmed_end = text[end.line].iter().rposition(|c| !c.is_whitespace()).map_or(0, |i| i + 1);
+    candidates.push((pos(start.line, indent), pos(end.line, trimmed_end.max(indent))));
+    candidates.push((pos(start.line, 0), pos(end.line, line_len(end.line))));
+
+    // Innermost indentation or brace block around the lines
+    for line in (0..=start.line).rev() {
+        if let Some((block_start, block_end)) = folding::block_range(lines, line) {
+            let block = (pos(block_start, 0), pos(block_end, line_len(block_end)));
+            if block_end >= end.line && block != (pos(start.line, 0), pos(end.line, line_len(end.line))) {
+                candidates.push(block);
+                break;
+            }
+        }
+    }
+
+    let last = text.len() - 1;
+    candidates.push((pos(0, 0), pos(last, line_len(last))));
+
+    // Char offsets let candidates spanning different lines be compared by size
+    let mut offsets = vec![0usize; text.len() + 1];
+    for (idx, chars) in text.iter().enumerate() {
+        offsets[idx + 1] = offsets[idx] + chars.len() + 1;
+    }
+    let offset = |p: TextPosition| offsets[p.line] + p.column;
+
+    candidates
+        .into_iter()
+        .filter(|&(s, e)| key(s) <= key(start) && key(end) <= key(e) && (s, e) != range)
+        .min_by_key(|&(s, e)| offset(e) - offset(s))
+}
+
+// Nearest unmatched opening bracket before `start` and its partner after `end`
+fn enclosing_brackets(text: &[Vec<char>], start: TextPosition, end: TextPosition) -> Option<(TextPosition, TextPosition)> {
+    let mut depth = 0usize;
+    let mut open = None;
+    'back: for line in (0..=start.line).rev() {
+        let chars = &text[line];
+        let upto = if line == start.line { start.column.min(chars.len()) } else { chars.len() };
+        for column in (0..upto).rev() {
+            match chars[column] {
+                ')' | ']' | '}' => depth += 1,
+                '(' | '[' | '{' if depth == 0 => {
+                    open = Some(pos(line, column));
+                    break 'back;
+                }
+                '(' | '[' | '{' => depth -= 1,
+                _ => {}
+            }
+        }
+    }
+    let open = open?;
+
+    depth = 0;
+    for (line, chars) in text.iter().enumerate().skip(end.line) {
+        let from = if line == end.line { end.column.min(chars.len()) } else { 0 };
+        for (column, &c) in chars.iter().enumerate().skip(from) {
+            match c {
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' if depth == 0 => return Some((open, pos(line, column))),
+                ')' | ']' | '}' => depth -= 1,
+                _ => {}
+            }
+        }
+    }
+    None
+}
+
+// Remembers each expansion so shrinking can step back through them
+#[derive(Debug, Default)]
+pub struct SelectionSteps {
+    steps: Vec<Range>, // The original range, then every expansion of it
+}
+
+impl SelectionSteps {
+    pub fn expand(&mut self, lines: &[String], range: Range) -> Option<Range> {
+        if self.steps.last() != Some(&range) {
+            self.steps = vec![range];
+        }
+        let next = expand(lines, range)?;
+        self.steps.push(next);
+        Some(next)
+    }
+
+    // The range before the last expansion; None when `range` was not produced by expand
+    pub fn shrink(&mut self, range: Range) -> Option<Range> {
+        if self.steps.len() < 2 || self.steps.last() != Some(&range) {
+            self.steps.clear();
+            return None;
+        }
+        self.steps.pop();
+        self.steps.last().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_and_shrink() {
+        let lines: Vec<String> = ["fn main() {", "    call(\"some text\", other);", "}"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let caret = pos(1, 13);
+        let mut steps = SelectionSteps::default();
+        let mut grow = |range: Range| steps.expand(&lines, range).unwrap();
+
+        let word = grow((caret, caret));
+        assert_eq!(word, (pos(1, 10), pos(1, 14)));
+        let string = grow(word);
+        assert_eq!(string, (pos(1, 10), pos(1, 19)));
+        let quoted = grow(string);
+        assert_eq!(quoted, (pos(1, 9), pos(1, 20)));
+        let args = grow(quoted);
+        assert_eq!(args, (pos(1, 9), pos(1, 27)));
+        let call = grow(args);
+        assert_eq!(call, (pos(1, 8), pos(1, 28)));
+        let callee = grow(call);
+        assert_eq!(callee, (pos(1, 4), pos(1, 28)));
+        let text = grow(callee);
+        assert_eq!(text, (pos(1, 4), pos(1, 29)));
+        let line = grow(text);
+        assert_eq!(line, (pos(1, 0), pos(1, 29)));
+        let body = grow(line);
+        assert_eq!(body, (pos(0, 11), pos(2, 0)));
+        let braces = grow(body);
+        assert_eq!(braces, (pos(0, 10), pos(2, 1)));
+        let block = grow(braces);
+        assert_eq!(block, (pos(0, 0), pos(2, 1)));
+        assert_eq!(expand(&lines, block), None);
+
+        assert_eq!(steps.shrink(block), Some(braces));
+        assert_eq!(steps.shrink(braces), Some(body));
+        assert_eq!(steps.shrink(body), Some(line));
+        assert_eq!(steps.shrink(line), Some(text));
+        assert_eq!(steps.shrink(word), None);
+    }
+}
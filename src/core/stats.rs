@@ -0,0 +1,130 @@
+// stats module - local usage statistics kept in stats.json in the state directory, never sent anywhere
+use crate::core::paths;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Error;
+use std::path::PathBuf;
+use std::time::Instant;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct Stats {
+    pub sessions: u64,
+    pub seconds: u64, // Time the editor was open
+    pub keystrokes: u64,
+    pub undos: u64,
+    pub saves: u64,
+    pub files: Vec<String>, // Canonical paths of the files saved at least once
+}
+
+impl Stats {
+    pub fn load() -> Self {
+        fs::read_to_string(Self::file_path())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        let json = serde_json::to_string(self).map_err(|e| Error::new(std::io::ErrorKind::InvalidData, e))?;
+        fs::write(Self::file_path(), json)
+    }
+
+    fn add(&mut self, other: &Stats) {
+        self.sessions += other.sessions;
+        self.seconds += other.seconds;
+        self.keystrokes += other.keystrokes;
+        self.undos += other.undos;
+        self.saves += other.saves;
+        for file in &other.files {
+            if !self.files.contains(file) {
+                self.files.push(file.clone());
+            }
+        }
+    }
+
+    // Label and value of each statistic, for display
+    pub fn summary(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("Files edited", self.files.len().to_string()),
+            ("Saves", self.saves.to_string()),
+            ("Keystrokes", self.keystrokes.to_string()),
+            ("Undos", self.undos.to_string()),
+            ("Time in editor", format!("{}h {:02}m", self.seconds / 3600, self.seconds / 60 % 60)),
+            ("Sessions", self.sessions.to_string()),
+        ]
+    }
+
+    fn file_path() -> PathBuf {
+        paths::state_dir().join("stats.json")
+    }
+}
+
+// Counts for the running session, added to the totals on disk when it ends
+// so several instances don't overwrite each other's numbers
+pub struct Tracker {
+    session: Stats,
+    started: Instant,
+}
+
+impl Default for Tracker {
+    fn default() -> Self {
+        Self { session: Stats { sessions: 1, ..Stats::default() }, started: Instant::now() }
+    }
+}
+
+impl Tracker {
+    pub fn keystrokes(&mut self, count: usize) {
+        self.session.keystrokes += count as u64;
+    }
+
+    pub fn undo(&mut self) {
+        self.session.undos += 1;
+    }
+
+    pub fn saved(&mut self, path: &str) {
+        self.session.saves += 1;
+        let file = fs::canonicalize(path).map_or_else(|_| path.to_string(), |p| p.to_string_lossy().into_owned());
+        if !self.session.files.contains(&file) {
+            self.session.files.push(file);
+        }
+    }
+
+    fn current(&self) -> Stats {
+        Stats { seconds: self.started.elapsed().as_secs(), ..self.session.clone() }
+    }
+
+    // Totals on disk plus this session so far
+    pub fn totals(&self) -> Stats {
+        let mut totals = Stats::load();
+        totals.add(&self.current());
+        totals
+    }
+
+    // Add this session to the totals on disk
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.totals().save()?;
+        *self = Self { session: Stats::default(), started: Instant::now() };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_session_to_totals() {
+        let mut totals = Stats { sessions: 2, seconds: 7200, saves: 3, files: vec!["/a".into()], ..Stats::default() };
+        let session = Stats { sessions: 1, seconds: 150, keystrokes: 40, undos: 2, saves: 2, files: vec!["/a".into(), "/b".into()] };
+        totals.add(&session);
+
+        assert_eq!(totals.files, ["/a", "/b"]);
+        let summary = totals.summary();
+        assert_eq!(summary[0], ("Files edited", "2".to_string()));
+        assert_eq!(summary[1], ("Saves", "5".to_string()));
+        assert_eq!(summary[2], ("Keystrokes", "40".to_string()));
+        assert_eq!(summary[4], ("Time in editor", "2h 02m".to_string()));
+        assert_eq!(summary[5], ("Sessions", "3".to_string()));
+    }
+}
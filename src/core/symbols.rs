@@ -0,0 +1,151 @@
+// symbols module - index of functions, types and headings used for breadcrumbs
+use crate::core::folding;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolKind {
+    Function,
+    Type,
+    Module,
+    Heading(usize), // Markdown heading level
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub line: usize,
+    pub end: usize, // Last line of the symbol's body
+}
+
+const MODIFIERS: [&str; 10] =
+    ["pub", "async", "unsafe", "export", "default", "static", "public", "private", "protected", "abstract"];
+
+fn kind_of(keyword: &str) -> Option<SymbolKind> {
+    match keyword {
+        "fn" | "def" | "function" | "func" => Some(SymbolKind::Function),
+        "struct" | "enum" | "trait" | "impl" | "class" | "interface" => Some(SymbolKind::Type),
+        "mod" | "module" | "namespace" => Some(SymbolKind::Module),
+        _ => None,
+    }
+}
+
+// Name declared on a line like `pub async fn name(` or `impl<T> Trait for Type {`
+fn declaration(line: &str) -> Option<(SymbolKind, String)> {
+    let mut rest = line.trim_start();
+    loop {
+        let word_len = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+        let word = &rest[..word_len];
+        let after = &rest[word_len..];
+
+        if MODIFIERS.contains(&word) {
+            // Skip visibility qualifiers such as `pub(crate)`
+            let after = match after.strip_prefix('(') {
+                Some(inner) => inner.split_once(')').map(|(_, tail)| tail)?,
+                None => after,
+            };
+            rest = after.trim_start();
+            continue;
+        }
+
+        let kind = kind_of(word)?;
+        let mut after = after;
+        if word == "impl" {
+            if let Some(generics) = after.strip_prefix('<') {
+                after = generics.split_once('>').map(|(_, tail)| tail)?;
+            }
+            let name = after.trim().trim_end_matches('{').trim_end();
+            return (!name.is_empty()).then(|| (kind, name.to_string()));
+        }
+        if !after.starts_with(char::is_whitespace) {
+            return None;
+        }
+        let after = after.trim_start();
+        let name_len = after.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(after.len());
+        return (name_len > 0).then(|| (kind, after[..name_len].to_string()));
+    }
+}
+
+pub fn find_symbols(lines: &[String], filetype: Option<&str>) -> Vec<Symbol> {
+    if filetype == Some("Markdown") {
+        return find_headings(lines);
+    }
+
+    lines
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, line)| {
+            let (kind, name) = declaration(line)?;
+            let end = folding::block_range(lines, idx).map_or(idx, |(_, end)| end);
+            Some(Symbol { name, kind, line: idx, end })
+        })
+        .collect()
+}
+
+fn find_headings(lines: &[String]) -> Vec<Symbol> {
+    let mut symbols: Vec<Symbol> = Vec::new();
+    let mut in_fence = false;
+
+    for (idx, line) in lines.iter().enumerate() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        let level = line.chars().take_while(|&c| c == '#').count();
+        if in_fence || level == 0 || level > 6 || !line[level..].starts_with(' ') {
+            continue;
+        }
+        // A heading ends where the next heading of the same or a higher level starts
+        for open in symbols.iter_mut().filter(|s| s.end == usize::MAX) {
+            if matches!(open.kind, SymbolKind::Heading(l) if l >= level) {
+                open.end = idx - 1;
+            }
+        }
+        let name = line[level..].trim().to_string();
+        symbols.push(Symbol { name, kind: SymbolKind::Heading(level), line: idx, end: usize::MAX });
+    }
+
+    let last = lines.len().saturating_sub(1);
+    for symbol in symbols.iter_mut().filter(|s| s.end == usize::MAX) {
+        symbol.end = last;
+    }
+    symbols
+}
+
+// Symbols enclosing `line`, outermost first
+pub fn scope_at(symbols: &[Symbol], line: usize) -> Vec<&Symbol> {
+    symbols.iter().filter(|s| s.line <= line && line <= s.end).collect()
+}
+
+pub fn breadcrumbs(symbols: &[Symbol], line: usize) -> String {
+    scope_at(symbols, line)
+        .iter()
+        .map(|s| s.name.as_str())
+        .collect::<Vec<_>>()
+        .join(" › ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.split('\n').map(String::from).collect()
+    }
+
+    #[test]
+    fn test_breadcrumbs() {
+        let code = lines(
+            "pub(crate) mod shapes {\n    impl<T> Display for Square<T> {\n        pub async fn fmt(&self) {\n            draw();\n        }\n    }\n}\nfn main() {}",
+        );
+        let symbols = find_symbols(&code, Some("Rust"));
+        assert_eq!(symbols.len(), 4);
+        assert_eq!(symbols[1].kind, SymbolKind::Type);
+        assert_eq!(breadcrumbs(&symbols, 3), "shapes › Display for Square<T> › fmt");
+        assert_eq!(breadcrumbs(&symbols, 7), "main");
+
+        let doc = lines("# Guide\n## Install\ntext\n```\n# not a heading\n```\n## Usage\nmore");
+        let headings = find_symbols(&doc, Some("Markdown"));
+        assert_eq!(headings.len(), 3);
+        assert_eq!(breadcrumbs(&headings, 4), "Guide › Install");
+        assert_eq!(breadcrumbs(&headings, 7), "Guide › Usage");
+    }
+}
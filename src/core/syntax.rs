@@ -1,4 +1,5 @@
 // Syntax highlighting module with vibrant colors matching the yellow/orange theme
+use crate::core::{display, links, todos};
 use crossterm::style::Color;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -14,9 +15,28 @@ pub enum TokenType {
     Constant,
     Macro,
     Attribute,
+    Done, // Checked Markdown task items
+    Escape,       // Escape sequences like \n or \x41 inside strings
+    FormatSpec,   // Placeholders like {} or %s inside strings
+    Todo,         // TODO/FIXME style markers inside comments
+    Link,         // URLs, drawn underlined
+    Bracket(usize), // Bracket at the given nesting depth
+    MismatchedBracket,
+    ConflictMarker, // <<<<<<< / ======= / >>>>>>> lines
+    SearchMatch,    // Matches of an open search, drawn on a yellow background
     Normal,
 }
 
+// Rotating palette for rainbow brackets
+const BRACKET_COLORS: [Color; 6] = [
+    Color::Rgb { r: 255, g: 215, b: 0 },   // Gold
+    Color::Rgb { r: 218, g: 112, b: 214 }, // Orchid
+    Color::Rgb { r: 0, g: 191, b: 255 },   // Deep Sky Blue
+    Color::Rgb { r: 50, g: 205, b: 50 },   // Lime Green
+    Color::Rgb { r: 255, g: 140, b: 0 },   // Deep Orange
+    Color::Rgb { r: 64, g: 224, b: 208 },  // Turquoise
+];
+
 impl TokenType {
     pub fn color(&self) -> Color {
         match self {
@@ -31,6 +51,15 @@ impl TokenType {
             TokenType::Constant => Color::Rgb { r: 255, g: 165, b: 0 },     // Orange
             TokenType::Macro => Color::Rgb { r: 186, g: 85, b: 211 },       // Medium Orchid
             TokenType::Attribute => Color::Rgb { r: 147, g: 112, b: 219 },  // Medium Purple
+            TokenType::Done => Color::DarkGrey,
+            TokenType::Escape => Color::Rgb { r: 255, g: 160, b: 122 },     // Light Salmon
+            TokenType::FormatSpec => Color::Rgb { r: 135, g: 206, b: 250 }, // Light Sky Blue
+            TokenType::Todo => Color::Rgb { r: 255, g: 255, b: 0 },         // Yellow
+            TokenType::Link => Color::Rgb { r: 100, g: 149, b: 237 },       // Cornflower Blue
+            TokenType::Bracket(depth) => BRACKET_COLORS[depth % BRACKET_COLORS.len()],
+            TokenType::MismatchedBracket => Color::Red,
+            TokenType::ConflictMarker => Color::Magenta,
+            TokenType::SearchMatch => Color::Black,
             TokenType::Normal => Color::White,
         }
     }
@@ -42,16 +71,169 @@ pub struct Token {
     pub token_type: TokenType,
 }
 
+// Tracks open brackets across lines so nested pairs can be colored by depth
+#[derive(Debug, Clone, Default)]
+pub struct BracketColorizer {
+    open: Vec<char>,
+}
+
+impl BracketColorizer {
+    // Replay lines above the viewport to get the nesting at its top
+    pub fn scan(highlighter: &SyntaxHighlighter, lines: &[String]) -> Self {
+        let mut colorizer = Self::default();
+        // Long lines don't take part in the nesting, as when they are drawn
+        for line in lines.iter().filter(|line| !display::is_long(line)) {
+            colorizer.colorize(highlighter.highlight_line(line));
+        }
+        colorizer
+    }
+
+    // Split brackets out of code tokens and give them depth colors.
+    // Brackets inside strings and comments are left alone.
+    pub fn colorize(&mut self, tokens: Vec<Token>) -> Vec<Token> {
+        let mut result = Vec::with_capacity(tokens.len());
+
+        for token in tokens {
+            let skip = matches!(
+                token.token_type,
+                TokenType::String
+                    | TokenType::Escape
+                    | TokenType::FormatSpec
+                    | TokenType::Comment
+                    | TokenType::Todo
+                    | TokenType::Link
+                    | TokenType::Done
+                    | TokenType::ConflictMarker
+            );
+            if skip || !token.text.contains(['(', ')', '[', ']', '{', '}']) {
+                result.push(token);
+                continue;
+            }
+
+            let mut rest = String::new();
+            for ch in token.text.chars() {
+                let bracket_type = match ch {
+                    '(' | '[' | '{' => {
+                        self.open.push(ch);
+                        TokenType::Bracket(self.open.len() - 1)
+                    }
+                    ')' | ']' | '}' => self.close(ch),
+                    _ => {
+                        rest.push(ch);
+                        continue;
+                    }
+                };
+                if !rest.is_empty() {
+                    result.push(Token {
+                        text: std::mem::take(&mut rest),
+                        token_type: token.token_type,
+                    });
+                }
+                result.push(Token {
+                    text: ch.to_string(),
+                    token_type: bracket_type,
+                });
+            }
+            if !rest.is_empty() {
+                result.push(Token {
+                    text: rest,
+                    token_type: token.token_type,
+                });
+            }
+        }
+
+        result
+    }
+
+    fn close(&mut self, ch: char) -> TokenType {
+        let opener = match ch {
+            ')' => '(',
+            ']' => '[',
+            _ => '{',
+        };
+        if self.open.last() == Some(&opener) {
+            self.open.pop();
+            TokenType::Bracket(self.open.len())
+        } else {
+            TokenType::MismatchedBracket
+        }
+    }
+}
+
 pub struct SyntaxHighlighter {
     file_type: Option<String>,
+    todo_keywords: Vec<String>,
 }
 
 impl SyntaxHighlighter {
     pub fn new(file_type: Option<String>) -> Self {
-        Self { file_type }
+        Self {
+            file_type,
+            todo_keywords: Vec::new(),
+        }
+    }
+
+    // Markers like TODO or FIXME to pick out of comments
+    pub fn with_todo_keywords(mut self, keywords: &[String]) -> Self {
+        self.todo_keywords = keywords.to_vec();
+        self
     }
 
     pub fn highlight_line(&self, line: &str) -> Vec<Token> {
+        // Past the cap the rest of a huge line stays plain, so one minified file can't stall drawing
+        if display::is_long(line) {
+            let cut = (0..=display::LONG_LINE).rev().find(|&i| line.is_char_boundary(i)).unwrap_or(0);
+            let mut tokens = self.highlight_line(&line[..cut]);
+            tokens.push(Token { text: line[cut..].to_string(), token_type: TokenType::Normal });
+            return tokens;
+        }
+
+        let mut tokens = self.highlight_syntax(line);
+
+        if !self.todo_keywords.is_empty() {
+            tokens = tokens
+                .into_iter()
+                .flat_map(|token| {
+                    if token.token_type == TokenType::Comment {
+                        split_comment_token(&token.text, &self.todo_keywords)
+                    } else {
+                        vec![token]
+                    }
+                })
+                .collect();
+        }
+
+        // Pick escapes and placeholders out of string literals in code
+        let placeholders = match self.file_type.as_deref() {
+            None | Some("Markdown") => None,
+            Some("Rust") | Some("C#") => Some((true, false)),
+            Some("Python") => Some((true, true)),
+            Some("JSON") | Some("TOML") | Some("YAML") | Some("HTML") | Some("XML") | Some("Vue")
+            | Some("CSS") | Some("Sass") | Some("SQL Query") => Some((false, false)),
+            _ => Some((false, true)),
+        };
+        if let Some((braces, percent)) = placeholders {
+            tokens = tokens
+                .into_iter()
+                .flat_map(|token| {
+                    if token.token_type == TokenType::String {
+                        split_string_token(&token.text, braces, percent)
+                    } else {
+                        vec![token]
+                    }
+                })
+                .collect();
+        }
+
+        let urls = links::find_urls(line);
+        if urls.is_empty() {
+            tokens
+        } else {
+            retype_spans(tokens, &urls, TokenType::Link)
+        }
+    }
+
+    fn highlight_syntax(&self, line: &str) -> Vec<Token> {
         match self.file_type.as_deref() {
             // Rust
             Some("Rust") => self.highlight_rust(line),
@@ -319,7 +501,9 @@ impl SyntaxHighlighter {
                 token_type: TokenType::Operator,
             });
 
-            let value = line[eq_pos + 1..].trim_start();
+            // The value keeps the spacing after the = as typed, so columns line up
+            let rest = &line[eq_pos + 1..];
+            let value = rest.trim_start();
             let value_type = if value.starts_with('"') || value.starts_with('\'') {
                 TokenType::String
             } else if value.parse::<f64>().is_ok() {
@@ -331,7 +515,7 @@ impl SyntaxHighlighter {
             };
 
             tokens.push(Token {
-                text: value.to_string(),
+                text: rest.to_string(),
                 token_type: value_type,
             });
 
@@ -363,6 +547,14 @@ impl SyntaxHighlighter {
             }];
         }
 
+        // Checked task items are dimmed
+        if crate::core::markdown::is_checked_item(line) {
+            return vec![Token {
+                text: line.to_string(),
+                token_type: TokenType::Done,
+            }];
+        }
+
         // Lists
         if trimmed.starts_with('-') || trimmed.starts_with('*') || trimmed.starts_with('+') {
             return vec![Token {
@@ -633,7 +825,9 @@ impl SyntaxHighlighter {
                 token_type: TokenType::Operator,
             });
 
-            let value = line[colon_pos + 1..].trim_start();
+            // The value keeps the spacing after the colon as typed, so columns line up
+            let rest = &line[colon_pos + 1..];
+            let value = rest.trim_start();
             if !value.is_empty() {
                 let value_type = if value.starts_with('"') || value.starts_with('\'') {
                     TokenType::String
@@ -646,9 +840,14 @@ impl SyntaxHighlighter {
                 };
 
                 tokens.push(Token {
-                    text: format!(" {}", value),
+                    text: rest.to_string(),
                     token_type: value_type,
                 });
+            } else if !rest.is_empty() {
+                tokens.push(Token {
+                    text: rest.to_string(),
+                    token_type: TokenType::Normal,
+                });
             }
 
             return tokens;
@@ -891,7 +1090,16 @@ impl SyntaxHighlighter {
         let mut string_char = '"';
         let mut chars = code.chars().peekable();
 
+        let mut escaped = false;
+
         while let Some(ch) = chars.next() {
+            // Escaped characters never end a string
+            if in_string && (escaped || ch == '\\') {
+                current_word.push(ch);
+                escaped = !escaped;
+                continue;
+            }
+
             // String handling
             if ch == '"' || ch == '\'' {
                 if !in_string {
@@ -1016,6 +1224,170 @@ impl SyntaxHighlighter {
     }
 }
 
+// Give the byte ranges in `spans` (sorted, non-overlapping) their own token type
+pub fn retype_spans(tokens: Vec<Token>, spans: &[(usize, usize)], token_type: TokenType) -> Vec<Token> {
+    let mut result = Vec::with_capacity(tokens.len());
+    let mut offset = 0;
+
+    for token in tokens {
+        let start = offset;
+        let end = offset + token.text.len();
+        offset = end;
+
+        let mut cut = start;
+        for &(span_start, span_end) in spans.iter().filter(|&&(s, e)| s < end && e > start) {
+            let span_start = span_start.max(start);
+            let span_end = span_end.min(end);
+            if span_start > cut {
+                result.push(Token {
+                    text: token.text[cut - start..span_start - start].to_string(),
+                    token_type: token.token_type,
+                });
+            }
+            result.push(Token {
+                text: token.text[span_start - start..span_end - start].to_string(),
+                token_type,
+            });
+            cut = span_end;
+        }
+
+        if cut == start {
+            result.push(token);
+        } else if cut < end {
+            result.push(Token {
+                text: token.text[cut - start..].to_string(),
+                token_type: token.token_type,
+            });
+        }
+    }
+
+    result
+}
+
+// Split a comment so TODO style markers get their own tokens
+fn split_comment_token(text: &str, keywords: &[String]) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut last = 0;
+
+    for (start, end) in todos::keyword_spans(text, keywords) {
+        if start < last {
+            continue;
+        }
+        if start > last {
+            tokens.push(Token {
+                text: text[last..start].to_string(),
+                token_type: TokenType::Comment,
+            });
+        }
+        tokens.push(Token {
+            text: text[start..end].to_string(),
+            token_type: TokenType::Todo,
+        });
+        last = end;
+    }
+
+    if last < text.len() || tokens.is_empty() {
+        tokens.push(Token {
+            text: text[last..].to_string(),
+            token_type: TokenType::Comment,
+        });
+    }
+    tokens
+}
+
+// Split a string literal into plain text, escape sequences and format placeholders
+fn split_string_token(text: &str, braces: bool, percent: bool) -> Vec<Token> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut plain = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let special = match chars[i] {
+            '\\' if i + 1 < chars.len() => Some((escape_len(&chars[i..]), TokenType::Escape)),
+            '{' | '}' if braces && chars.get(i + 1) == Some(&chars[i]) => Some((2, TokenType::Escape)),
+            '{' if braces => brace_placeholder_len(&chars[i..]).map(|len| (len, TokenType::FormatSpec)),
+            '%' if percent && chars.get(i + 1) == Some(&'%') => Some((2, TokenType::Escape)),
+            '%' if percent => percent_placeholder_len(&chars[i..]).map(|len| (len, TokenType::FormatSpec)),
+            _ => None,
+        };
+
+        match special {
+            Some((len, token_type)) => {
+                if !plain.is_empty() {
+                    tokens.push(Token {
+                        text: std::mem::take(&mut plain),
+                        token_type: TokenType::String,
+                    });
+                }
+                tokens.push(Token {
+                    text: chars[i..i + len].iter().collect(),
+                    token_type,
+                });
+                i += len;
+            }
+            None => {
+                plain.push(chars[i]);
+                i += 1;
+            }
+        }
+    }
+
+    if !plain.is_empty() {
+        tokens.push(Token {
+            text: plain,
+            token_type: TokenType::String,
+        });
+    }
+    tokens
+}
+
+// Length of the escape at the start of `chars` (which begins with a backslash)
+fn escape_len(chars: &[char]) -> usize {
+    let hex_run = |from: usize, max: usize| {
+        chars[from..].iter().take(max).take_while(|c| c.is_ascii_hexdigit()).count()
+    };
+    match chars[1] {
+        'x' => 2 + hex_run(2, 2),
+        'u' if chars.get(2) == Some(&'{') => chars
+            .iter()
+            .position(|&c| c == '}')
+            .map(|end| end + 1)
+            .unwrap_or(3),
+        'u' => 2 + hex_run(2, 4),
+        '0'..='7' => 1 + chars[1..].iter().take(3).take_while(|&&c| ('0'..='7').contains(&c)).count(),
+        _ => 2,
+    }
+}
+
+// "{}", "{name}", "{0:>8}", "{:?}" style placeholders
+fn brace_placeholder_len(chars: &[char]) -> Option<usize> {
+    let end = chars.iter().position(|&c| c == '}')?;
+    let inner = &chars[1..end];
+    let valid = inner
+        .iter()
+        .all(|&c| c.is_alphanumeric() || "_.:?<>^#+-$= ".contains(c));
+    (valid && !inner.first().is_some_and(|c| c.is_whitespace())).then_some(end + 1)
+}
+
+// printf style placeholders: %s, %5.2f, %-10d, %lld, %v
+fn percent_placeholder_len(chars: &[char]) -> Option<usize> {
+    let mut i = 1;
+    while chars.get(i).is_some_and(|c| "-+ #0".contains(*c)) {
+        i += 1;
+    }
+    while chars.get(i).is_some_and(|c| c.is_ascii_digit() || *c == '*' || *c == '.') {
+        i += 1;
+    }
+    while chars.get(i).is_some_and(|c| "hlLqjzt".contains(*c)) {
+        i += 1;
+    }
+    chars
+        .get(i)
+        .is_some_and(|c| "diouxXeEfFgGaAcspqvTb".contains(*c))
+        .then_some(i + 1)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1033,4 +1405,90 @@ mod tests {
         let tokens = highlighter.highlight_line("// This is a comment");
         assert_eq!(tokens[0].token_type, TokenType::Comment);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_rainbow_brackets() {
+        let highlighter = SyntaxHighlighter::new(Some("Rust".to_string()));
+        let mut colorizer = BracketColorizer::default();
+        let tokens = colorizer.colorize(highlighter.highlight_line("f(a[0], \"(\") }"));
+        let brackets: Vec<TokenType> = tokens
+            .iter()
+            .filter(|t| matches!(t.token_type, TokenType::Bracket(_) | TokenType::MismatchedBracket))
+            .map(|t| t.token_type)
+            .collect();
+        assert_eq!(
+            brackets,
+            [
+                TokenType::Bracket(0),
+                TokenType::Bracket(1),
+                TokenType::Bracket(1),
+                TokenType::Bracket(0),
+                TokenType::MismatchedBracket,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_string_escapes_and_placeholders() {
+        let highlighter = SyntaxHighlighter::new(Some("Rust".to_string()));
+        let tokens = highlighter.highlight_line(r#"println!("{name}: \"{:>8}\"\n{{x}}", v);"#);
+        let special: Vec<(&str, TokenType)> = tokens
+            .iter()
+            .filter(|t| matches!(t.token_type, TokenType::Escape | TokenType::FormatSpec))
+            .map(|t| (t.text.as_str(), t.token_type))
+            .collect();
+        assert_eq!(
+            special,
+            [
+                ("{name}", TokenType::FormatSpec),
+                ("\\\"", TokenType::Escape),
+                ("{:>8}", TokenType::FormatSpec),
+                ("\\\"", TokenType::Escape),
+                ("\\n", TokenType::Escape),
+                ("{{", TokenType::Escape),
+                ("}}", TokenType::Escape),
+            ]
+        );
+
+        let c = SyntaxHighlighter::new(Some("C".to_string()));
+        let tokens = c.highlight_line(r#"printf("%-5.2f%% \x41", x);"#);
+        assert!(tokens.iter().any(|t| t.text == "%-5.2f" && t.token_type == TokenType::FormatSpec));
+        assert!(tokens.iter().any(|t| t.text == "%%" && t.token_type == TokenType::Escape));
+        assert!(tokens.iter().any(|t| t.text == "\\x41" && t.token_type == TokenType::Escape));
+    }
+
+    #[test]
+    fn test_todo_markers_in_comments() {
+        let keywords = vec!["TODO".to_string()];
+        let highlighter = SyntaxHighlighter::new(Some("Rust".to_string())).with_todo_keywords(&keywords);
+        let tokens = highlighter.highlight_line("let todo = TODO; // TODO: later");
+        let markers: Vec<&Token> = tokens.iter().filter(|t| t.token_type == TokenType::Todo).collect();
+        assert_eq!(markers.len(), 1);
+        assert_eq!(tokens.last().unwrap().text, ": later");
+    }
+
+    #[test]
+    fn test_config_values_keep_spacing() {
+        for (file_type, lines) in [("YAML", ["port:    8080", "url:/x", "empty:   "]), ("TOML", ["port = 8080", "name =\t\"x\"", "empty =  "])] {
+            let highlighter = SyntaxHighlighter::new(Some(file_type.to_string()));
+            for line in lines {
+                let text: String = highlighter.highlight_line(line).iter().map(|t| t.text.as_str()).collect();
+                assert_eq!(text, line);
+            }
+            assert_eq!(highlighter.highlight_line(lines[0])[2].token_type, TokenType::Number);
+        }
+    }
+
+    #[test]
+    fn test_long_lines_stay_plain_past_the_cap() {
+        let highlighter = SyntaxHighlighter::new(Some("JavaScript".to_string()));
+        let line = "let x = 1;é".repeat(display::LONG_LINE / 4);
+        let tokens = highlighter.highlight_line(&line);
+        let text: String = tokens.iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(text, line);
+        assert_eq!(tokens[0].token_type, TokenType::Keyword);
+        let rest = tokens.last().unwrap();
+        assert_eq!(rest.token_type, TokenType::Normal);
+        assert!(rest.text.len() >= line.len() - display::LONG_LINE);
+    }
+}
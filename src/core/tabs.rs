@@ -1,12 +1,27 @@
-use crate::tui::view::Buffer;
-use crate::tui::caret::Position;
+use crate::core::archive::{self, Member};
+use crate::core::buffer::Buffer;
+use crate::core::search_index::SearchState;
+use crate::core::selection::Position;
 use crate::core::edit_history::EditHistory;
+use crate::core::crypto;
+use crate::core::fetch;
+use crate::core::collab::{self, Role, Session};
+use crate::core::config::Config;
+use crate::core::folding::{Fold, Folds};
+use crate::core::follow::FileFollower;
+use crate::core::locks::{self, LockInfo};
+use crate::core::paths;
+use crate::core::positions::{FilePosition, Positions};
+use crate::core::progress::Progress;
+use crate::core::selection::Selection;
+use crate::core::scratch;
+use crate::core::trash::{self, Trashed};
 use std::fs;
-use std::io::Error;
+use std::io::{Error, Read};
+use std::path::Path;
 use std::path::PathBuf;
 use serde::{Serialize, Deserialize};
 
-#[derive(Clone)]
 pub struct Tab {
     pub buffer: Buffer,
     pub filename: Option<String>,  // Display name only
@@ -16,6 +31,21 @@ pub struct Tab {
     pub cursor_pos: Position,
     pub has_unsaved_changes: bool,
     pub edit_history: EditHistory,
+    pub read_only: bool,
+    pub follower: Option<FileFollower>, // Set while follow mode is on
+    pub scratch: bool,                  // Scratch notes save themselves on every change
+    pub passphrase: Option<String>,     // Set for encrypted notes, kept in memory only
+    pub locked: bool,                   // Encrypted note waiting for its passphrase
+    pub folds: Folds,                   // Collapsed blocks, kept in the session
+    pub lock_holder: Option<LockInfo>,  // Another instance editing the file, which keeps this tab read-only
+    pub lock_warned: bool,              // The user was told about lock_holder and chose to stay read-only
+    pub selection: Option<Selection>,   // Selection kept while another tab is shown
+    pub search_state: Option<SearchState>, // Terminal search still highlighted in this tab
+    pub last_search: Option<String>,    // Query F3 repeats in this tab
+    pub collab: Option<Session>,        // Shared with others over the network
+    pub url: Option<String>,            // Where a tab opened from the web came from, read-only until saved locally
+    pub archive: Option<Member>,        // File inside a .zip / .tar that saving writes back into
+    pending: Option<TabInfo>,           // Restored session tab whose file hasn't been read yet
 }
 
 impl Tab {
@@ -29,10 +59,109 @@ impl Tab {
             cursor_pos: Position::default(),
             has_unsaved_changes: false,
             edit_history: EditHistory::new(500),
+            read_only: false,
+            follower: None,
+            scratch: false,
+            passphrase: None,
+            locked: false,
+            folds: Folds::default(),
+            lock_holder: None,
+            lock_warned: false,
+            selection: None,
+            search_state: None,
+            last_search: None,
+            collab: None,
+            url: None,
+            archive: None,
+            pending: None,
         }
     }
 
+    // Session tab shown by name only until it is switched to
+    fn placeholder(tab_info: TabInfo) -> Self {
+        let mut tab = Self::new(
+            Buffer::default(),
+            tab_info.filename.clone(),
+            tab_info.filepath.clone(),
+            tab_info.filetype.clone(),
+        );
+        tab.scroll_offset = tab_info.scroll_offset;
+        tab.cursor_pos = Position {
+            x: tab_info.cursor_col,
+            y: tab_info.cursor_line,
+        };
+        tab.read_only = tab_info.read_only;
+        tab.scratch = tab_info.scratch;
+        tab.pending = Some(tab_info);
+        tab
+    }
+
+    // Rebuild a tab saved in the session, reading its file from disk
+    fn restore(tab_info: &TabInfo) -> Result<Self, Error> {
+        let Some(ref filepath) = tab_info.filepath else {
+            // Fallback for old sessions without filepath
+            return Ok(Tab::new(Buffer::default(), tab_info.filename.clone(), None, None));
+        };
+
+        // Use the FULL PATH to load the file
+        match Tab::from_file(filepath) {
+            Ok(mut t) if tab_info.scratch => {
+                t.scratch = true;
+                t.filetype = Some("Scratch".to_string());
+                t.folds = Folds::from_list(tab_info.folds.clone());
+                t.folds.validate(&t.buffer.lines);
+                t.scroll_offset = tab_info.scroll_offset;
+                t.cursor_pos = Position {
+                    x: tab_info.cursor_col,
+                    y: tab_info.cursor_line,
+                };
+                Ok(t)
+            }
+            Ok(mut t) => {
+                // Apply the saved metadata
+                t.filetype = tab_info.filetype.clone();
+                t.scroll_offset = tab_info.scroll_offset;
+                t.cursor_pos = Position {
+                    x: tab_info.cursor_col,
+                    y: tab_info.cursor_line,
+                };
+                t.read_only = tab_info.read_only;
+                t.folds = Folds::from_list(tab_info.folds.clone());
+                t.folds.validate(&t.buffer.lines);
+                if tab_info.follow {
+                    let _ = t.start_follow();
+                }
+                Ok(t)
+            }
+            // Scratch note that was never written to
+            Err(_) if tab_info.scratch => Ok(Tab::new_scratch(PathBuf::from(filepath))),
+            Err(e) => Err(Error::new(e.kind(), format!("Could not load file {}: {}", filepath, e))),
+        }
+    }
+
+    // Empty scratch note, the file is only created once something is typed
+    pub fn new_scratch(path: PathBuf) -> Self {
+        let display_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+        let full_path = path.to_string_lossy().into_owned();
+
+        let mut tab = Self::new(
+            Buffer::default(),
+            display_name,
+            Some(full_path),
+            Some("Scratch".to_string()),
+        );
+        tab.scratch = true;
+        tab
+    }
+
     pub fn from_file(path: &str) -> Result<Self, Error> {
+        Self::load(path, &Progress::default())
+    }
+
+    // from_file reporting bytes read, for big files loaded behind a progress bar
+    pub fn load(path: &str, progress: &Progress) -> Result<Self, Error> {
         let path_buf = std::fs::canonicalize(path)
             .unwrap_or_else(|_| std::path::PathBuf::from(path));
     
@@ -45,14 +174,181 @@ impl Tab {
         // Store the FULL PATH for saving
         let full_path = path_buf.to_string_lossy().into_owned();
     
-        // Extract the file extension
-        let raw_ext = path_buf.extension().map(|ext| ext.to_string_lossy().into_owned());
+        // Extract the file extension (notes.md.qnenc is still Markdown)
+        let mut raw_ext = path_buf.extension().map(|ext| ext.to_string_lossy().into_owned());
+        if crypto::is_encrypted_path(&full_path) {
+            raw_ext = path_buf
+                .file_stem()
+                .and_then(|stem| std::path::Path::new(stem).extension())
+                .map(|ext| ext.to_string_lossy().into_owned());
+        }
         let friendly_filetype = get_friendly_filetype(raw_ext);
     
-        let content = std::fs::read_to_string(&path_buf)?;
+        let bytes = read_with_progress(&path_buf, progress)?;
+
+        // Encrypted notes open locked until the passphrase is entered
+        if crypto::is_encrypted(&bytes) {
+            let mut tab = Self::new(Buffer::default(), Some(display_name), Some(full_path.clone()), friendly_filetype);
+            tab.locked = true;
+            tab.lock_holder = locks::acquire(&full_path);
+            return Ok(tab);
+        }
+
+        let content = String::from_utf8(bytes)
+            .map_err(|e| Error::new(std::io::ErrorKind::InvalidData, e))?;
         let buffer = Buffer::from_string(content);
         
-        Ok(Self::new(buffer, Some(display_name), Some(full_path), friendly_filetype))
+        let mut tab = Self::new(buffer, Some(display_name), Some(full_path.clone()), friendly_filetype);
+        tab.lock_holder = locks::acquire(&full_path);
+        Ok(tab)
+    }
+
+    // A text file from the web, read-only until it is saved somewhere
+    pub fn from_url(url: &str, progress: &Progress) -> Result<Self, Error> {
+        let download = fetch::download(url, progress)?;
+        let filetype = get_friendly_filetype(download.extension);
+        let mut tab = Self::new(Buffer::from_string(download.text), Some(download.name), None, filetype);
+        tab.read_only = true;
+        tab.url = Some(url.to_string());
+        Ok(tab)
+    }
+
+    // A file from inside an archive, saved back into it
+    pub fn from_archive(member: Member) -> Result<Self, Error> {
+        let text = archive::read(&member)?;
+        let extension = Path::new(&member.name).extension().map(|ext| ext.to_string_lossy().into_owned());
+        let mut tab = Self::new(Buffer::from_string(text), Some(archive::display_name(&member)), None, get_friendly_filetype(extension));
+        tab.archive = Some(member);
+        Ok(tab)
+    }
+
+    // Write `content` into the archive this tab came from
+    pub fn save_to_archive(&mut self, content: &str) -> Result<(), Error> {
+        let member = self.archive.as_ref().ok_or_else(|| {
+            Error::new(std::io::ErrorKind::NotFound, "This tab is not from an archive")
+        })?;
+        archive::write(member, content)?;
+        self.mark_saved();
+        Ok(())
+    }
+
+    // Decrypt a locked note with the given passphrase
+    pub fn unlock(&mut self, passphrase: &str) -> Result<(), Error> {
+        let path = self.filepath.clone().ok_or_else(|| {
+            Error::new(std::io::ErrorKind::NotFound, "Encrypted note has no file path")
+        })?;
+
+        let content = crypto::decrypt(&fs::read(&path)?, passphrase)?;
+        self.buffer = Buffer::from_string(content);
+        self.passphrase = Some(passphrase.to_string());
+        self.locked = false;
+        self.has_unsaved_changes = false;
+        Ok(())
+    }
+
+    // Name shown for the tab, falling back to its position for unsaved buffers
+    pub fn display_name(&self, index: usize) -> String {
+        self.filename.clone().unwrap_or_else(|| format!("Tab {}", index + 1))
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.passphrase.is_some() || self.locked
+    }
+
+    // Locked notes and files another instance is editing can't be edited or saved
+    pub fn is_read_only(&self) -> bool {
+        self.read_only || self.locked || self.lock_holder.is_some()
+    }
+
+    // Take the file lock from the instance holding it and allow editing here
+    pub fn steal_lock(&mut self) -> Result<(), Error> {
+        if let Some(path) = &self.filepath {
+            locks::steal(path)?;
+        }
+        self.lock_holder = None;
+        self.lock_warned = false;
+        Ok(())
+    }
+
+    fn release_lock(&self) {
+        // A placeholder never took the lock, which may belong to another instance
+        if let Some(path) = self.filepath.as_ref().filter(|_| self.lock_holder.is_none() && self.pending.is_none()) {
+            locks::release(path);
+        }
+    }
+
+    pub fn is_following(&self) -> bool {
+        self.follower.is_some()
+    }
+
+    // Reload the file from disk and start watching it for appended content
+    pub fn start_follow(&mut self) -> Result<(), Error> {
+        let path = self.filepath.clone().ok_or_else(|| {
+            Error::new(std::io::ErrorKind::NotFound, "Follow mode needs a file on disk")
+        })?;
+
+        let content = fs::read_to_string(&path)?;
+        self.buffer = Buffer::from_string(content.clone());
+        self.follower = Some(FileFollower::new(PathBuf::from(path), &content));
+        self.read_only = true;
+        self.has_unsaved_changes = false;
+        Ok(())
+    }
+
+    pub fn stop_follow(&mut self) {
+        self.follower = None;
+    }
+
+    // The text now matches the file on disk
+    pub fn mark_saved(&mut self) {
+        self.has_unsaved_changes = false;
+        self.edit_history.mark_saved();
+        // A download saved locally is a file like any other
+        if self.url.take().is_some() {
+            self.read_only = false;
+        }
+    }
+
+    // Write a scratch note to disk, skipping notes that were never typed into
+    pub fn save_scratch(&mut self, insert_final_newline: bool) -> Result<(), Error> {
+        let Some(path) = self.filepath.as_ref().filter(|_| self.scratch) else {
+            return Ok(());
+        };
+
+        let content = self.buffer.file_text(insert_final_newline);
+        if content.is_empty() && !std::path::Path::new(path).exists() {
+            return Ok(());
+        }
+
+        crypto::write_file(path, &content, self.passphrase.as_deref())?;
+        self.mark_saved();
+        Ok(())
+    }
+
+    // Caret as a line and column in the text rather than a screen position
+    pub fn text_position(&self) -> FilePosition {
+        FilePosition {
+            line: self.scroll_offset + self.cursor_pos.y.saturating_sub(Position::HEADER) as usize,
+            column: self.cursor_pos.x.saturating_sub(Position::MARGIN) as usize,
+            scroll: self.scroll_offset,
+        }
+    }
+
+    pub fn set_text_position(&mut self, pos: FilePosition) {
+        let line = pos.line.min(self.buffer.lines.len().saturating_sub(1));
+        let column = self.buffer.lines.get(line).map_or(0, |l| pos.column.min(l.chars().count()));
+        self.scroll_offset = pos.scroll.min(line);
+        self.cursor_pos = Position {
+            x: Position::MARGIN + column as u16,
+            y: Position::HEADER + (line - self.scroll_offset) as u16,
+        };
+    }
+
+    // Remember where the caret was so reopening the file lands there again
+    fn remember_position(&self, positions: &mut Positions) {
+        if let Some(path) = self.filepath.as_ref().filter(|_| !self.locked) {
+            positions.set(path, self.text_position());
+        }
     }
 }
 
@@ -65,6 +361,14 @@ struct TabInfo {
     scroll_offset: usize,
     cursor_line: u16,
     cursor_col: u16,
+    #[serde(default)]
+    read_only: bool,
+    #[serde(default)]
+    follow: bool,
+    #[serde(default)]
+    scratch: bool,
+    #[serde(default)]
+    folds: Vec<Fold>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -77,7 +381,9 @@ pub struct TabManager {
     pub tabs: Vec<Tab>,
     pub active_tab_index: usize,
     pub max_tabs: usize,
-    session_file: PathBuf,
+    session_file: Option<PathBuf>, // None for a window's own tabs, which are not part of the session
+    pub trashed: Vec<Trashed>, // Files deleted this session, most recent last
+    pub load_warning: Option<String>, // Set when tabs.json was unreadable JSON and the session got reset
 }
 
 impl TabManager {
@@ -85,33 +391,40 @@ impl TabManager {
         let session_file = Self::get_session_file_path();
         
         // Try to load previous session
-        let manager = if let Ok(session) = Self::load_session(&session_file) {
-            Self::from_session(session)
-        } else {
-            let initial_tab = Tab::new(initial_buffer, filename, None, filetype);
-            Self {
-                tabs: vec![initial_tab],
-                active_tab_index: 0,
-                max_tabs: 10,
-                session_file,
-            }
+        let load_warning = match Self::load_session(&session_file) {
+            Ok(session) => return Self::from_session(session),
+            // Not valid JSON: keep the broken file around and start a fresh session
+            Err(e) if e.kind() == std::io::ErrorKind::InvalidData => Some(match paths::set_aside(&session_file) {
+                Ok(backup) => format!("Session file was invalid ({}), moved it to {} and started a new session", e, backup.display()),
+                Err(_) => format!("Session file is invalid ({}), started a new session", e),
+            }),
+            Err(_) => None,
         };
-        
-        manager
+        let initial_tab = Tab::new(initial_buffer, filename, None, filetype);
+        Self {
+            tabs: vec![initial_tab],
+            active_tab_index: 0,
+            max_tabs: 10,
+            session_file: Some(session_file),
+            trashed: Vec::new(),
+            load_warning,
+        }
     }
 
-    fn get_session_file_path() -> PathBuf {
-        let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-        let mut path = PathBuf::from(home);
-        path.push(".quicknotepad");
-        
-        // Create directory if it doesn't exist
-        if let Err(e) = fs::create_dir_all(&path) {
-            eprintln!("Warning: Could not create .quicknotepad directory: {}", e);
+    // Manager for a single tab shown in its own GUI window, kept out of the session file
+    pub fn detached(tab: Tab) -> Self {
+        Self {
+            tabs: vec![tab],
+            active_tab_index: 0,
+            max_tabs: 1,
+            session_file: None,
+            trashed: Vec::new(),
+            load_warning: None,
         }
-        
-        path.push("tabs.json");
-        path
+    }
+
+    fn get_session_file_path() -> PathBuf {
+        paths::state_dir().join("tabs.json")
     }
 
     fn load_session(path: &PathBuf) -> Result<TabSession, Error> {
@@ -121,58 +434,56 @@ impl TabManager {
     }
 
     fn from_session(session: TabSession) -> Self {
-        let mut tabs = Vec::new();
-        
-        for tab_info in session.tabs {
-            let tab = if let Some(ref filepath) = tab_info.filepath {
-                // Use the FULL PATH to load the file
-                match Tab::from_file(filepath) {
-                    Ok(mut t) => {
-                        // Apply the saved metadata
-                        t.filetype = tab_info.filetype.clone();
-                        t.scroll_offset = tab_info.scroll_offset;
-                        t.cursor_pos = Position {
-                            x: tab_info.cursor_col,
-                            y: tab_info.cursor_line,
-                        };
-                        t
-                    }
-                    Err(e) => {
-                        eprintln!("Could not load file {}: {}", filepath, e);
-                        Tab::new(Buffer::default(), None, None, None)
-                    }
-                }
-            } else {
-                // Fallback for old sessions without filepath
-                Tab::new(Buffer::default(), tab_info.filename.clone(), None, None)
-            };
-            
-            tabs.push(tab);
-        }
-        
+        let active_index = session.active_tab_index.min(session.tabs.len().saturating_sub(1));
+
+        // Only the active tab is read now, the rest load when first switched to
+        let mut tabs: Vec<Tab> = session.tabs.into_iter().enumerate().map(|(i, tab_info)| {
+            if i != active_index && tab_info.filepath.is_some() {
+                return Tab::placeholder(tab_info);
+            }
+            Tab::restore(&tab_info).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                Tab::new(Buffer::default(), None, None, None)
+            })
+        }).collect();
+
         // Ensure at least one tab exists
         if tabs.is_empty() {
             tabs.push(Tab::new(Buffer::default(), None, None, None));
         }
         
-        let active_index = session.active_tab_index.min(tabs.len() - 1);
-        
         Self {
             tabs,
             active_tab_index: active_index,
             max_tabs: 10,
-            session_file: Self::get_session_file_path(),
+            session_file: Some(Self::get_session_file_path()),
+            trashed: Vec::new(),
+            load_warning: None,
         }
     }
 
     pub fn save_session(&self) -> Result<(), Error> {
-        let tab_infos: Vec<TabInfo> = self.tabs.iter().map(|tab| TabInfo {
-            filename: tab.filename.clone(),
-            filepath: tab.filepath.clone(),
-            filetype: tab.filetype.clone(),
-            scroll_offset: tab.scroll_offset,
-            cursor_line: tab.cursor_pos.y,
-            cursor_col: tab.cursor_pos.x,
+        let Some(session_file) = &self.session_file else {
+            return Ok(());
+        };
+        let mut positions = Positions::load();
+        self.tabs.iter().for_each(|tab| tab.remember_position(&mut positions));
+        let _ = positions.save();
+
+        let tab_infos: Vec<TabInfo> = self.tabs.iter().map(|tab| match &tab.pending {
+            Some(tab_info) => tab_info.clone(),
+            None => TabInfo {
+                filename: tab.filename.clone(),
+                filepath: tab.filepath.clone(),
+                filetype: tab.filetype.clone(),
+                scroll_offset: tab.scroll_offset,
+                cursor_line: tab.cursor_pos.y,
+                cursor_col: tab.cursor_pos.x,
+                read_only: tab.read_only,
+                follow: tab.is_following(),
+                scratch: tab.scratch,
+                folds: tab.folds.list().to_vec(),
+            },
         }).collect();
         
         let session = TabSession {
@@ -183,7 +494,7 @@ impl TabManager {
         let json = serde_json::to_string_pretty(&session)
             .map_err(|e| Error::new(std::io::ErrorKind::InvalidData, e))?;
         
-        fs::write(&self.session_file, json)?;
+        fs::write(session_file, json)?;
         Ok(())
     }
 
@@ -207,22 +518,70 @@ impl TabManager {
         }
 
         self.active_tab_index = tab_index;
+        let loaded = self.load_tab(tab_index);
         
         // Save session after switching
         let _ = self.save_session();
-        Ok(())
+        loaded
+    }
+
+    // Read a placeholder tab's file, leaving an empty tab when it can't be loaded
+    pub fn load_tab(&mut self, index: usize) -> Result<(), Error> {
+        let Some(tab_info) = self.tabs.get_mut(index).and_then(|tab| tab.pending.take()) else {
+            return Ok(());
+        };
+        match Tab::restore(&tab_info) {
+            Ok(tab) => {
+                self.tabs[index] = tab;
+                Ok(())
+            }
+            Err(e) => {
+                self.tabs[index] = Tab::new(Buffer::default(), None, None, None);
+                Err(e)
+            }
+        }
     }
 
     // Create new tab at position 1, shift everything else down
     pub fn new_tab(&mut self) -> usize {
+        self.insert_tab(Tab::new(Buffer::default(), None, None, None))
+    }
+
+    // Create a new timestamped scratch note at position 1
+    pub fn new_scratch_tab(&mut self) -> usize {
+        let path = scratch::new_scratch_path(|path| {
+            let path = path.to_string_lossy();
+            self.tabs.iter().any(|tab| tab.filepath.as_deref() == Some(path.as_ref()))
+        });
+        self.insert_tab(Tab::new_scratch(path))
+    }
+
+    // Reopen a past scratch note, keeping it in autosave mode
+    pub fn open_scratch_note(&mut self, path: &str) -> Result<usize, Error> {
+        let index = self.open_file_in_new_tab(path)?;
+        let tab = &mut self.tabs[index];
+        tab.scratch = true;
+        tab.filetype = Some("Scratch".to_string());
+        let _ = self.save_session();
+        Ok(index)
+    }
+
+    // Drop the last tab, keeping its caret position for when the file is reopened
+    fn close_last_tab(&mut self) {
+        if let Some(tab) = self.tabs.pop() {
+            let mut positions = Positions::load();
+            tab.remember_position(&mut positions);
+            let _ = positions.save();
+            tab.release_lock();
+        }
+    }
+
+    fn insert_tab(&mut self, new_tab: Tab) -> usize {
         // If at max capacity, remove the last tab (oldest/least used)
         if self.tabs.len() >= self.max_tabs {
-            self.tabs.pop();
+            self.close_last_tab();
         }
 
-        // Create new tab
-        let new_tab = Tab::new(Buffer::default(), None, None, None);
-        
         // Insert at position 0 (tab 1)
         self.tabs.insert(0, new_tab);
         
@@ -235,6 +594,93 @@ impl TabManager {
         0
     }
 
+    // Remove a tab, leaving an empty one when it was the last
+    pub fn close_tab(&mut self, index: usize) {
+        if index >= self.tabs.len() {
+            return;
+        }
+        self.tabs.remove(index).release_lock();
+        if self.tabs.is_empty() {
+            self.tabs.push(Tab::new(Buffer::default(), None, None, None));
+        }
+        if self.active_tab_index > index || self.active_tab_index >= self.tabs.len() {
+            self.active_tab_index = self.active_tab_index.saturating_sub(1);
+        }
+        let _ = self.load_tab(self.active_tab_index);
+        let _ = self.save_session();
+    }
+
+    // Take a tab out to show it in its own window; the last tab always stays
+    pub fn detach_tab(&mut self, index: usize) -> Result<Tab, Error> {
+        if self.tabs.len() < 2 || index >= self.tabs.len() {
+            return Err(Error::other("The last tab can't be moved to a window"));
+        }
+        self.load_tab(index)?;
+        let tab = self.tabs.remove(index);
+        if self.active_tab_index > index || self.active_tab_index >= self.tabs.len() {
+            self.active_tab_index = self.active_tab_index.saturating_sub(1);
+        }
+        let _ = self.load_tab(self.active_tab_index);
+        let _ = self.save_session();
+        Ok(tab)
+    }
+
+    // Put a tab back from its window as tab 1
+    pub fn attach_tab(&mut self, tab: Tab) -> usize {
+        self.insert_tab(tab)
+    }
+
+    // Move the current tab's file to the trash and close the tab
+    pub fn trash_current_file(&mut self) -> Result<(), Error> {
+        let path = self.current_tab().filepath.clone().ok_or_else(|| {
+            Error::new(std::io::ErrorKind::NotFound, "This tab has no file to delete")
+        })?;
+        let item = trash::move_to_trash(std::path::Path::new(&path))?;
+        self.trashed.push(item);
+        self.close_tab(self.active_tab_index);
+        Ok(())
+    }
+
+    // Rename the current tab's file on disk. A bare name stays in the file's folder,
+    // anything with a slash is resolved like other typed paths. Returns the new full path
+    pub fn rename_current_file(&mut self, input: &str) -> Result<String, Error> {
+        let tab = self.current_tab();
+        let old = tab.filepath.clone().ok_or_else(|| {
+            Error::new(std::io::ErrorKind::NotFound, "Save the file before renaming it")
+        })?;
+        if tab.lock_holder.is_some() {
+            return Err(Error::other("The file is open in another instance"));
+        }
+        let target = match std::path::Path::new(&old).parent() {
+            Some(dir) if !input.contains(std::path::MAIN_SEPARATOR) && !input.contains('/') => dir.join(input),
+            _ => paths::resolve(input),
+        };
+        if target.exists() {
+            return Err(Error::new(std::io::ErrorKind::AlreadyExists, format!("{} already exists", target.display())));
+        }
+        fs::rename(&old, &target)?;
+        locks::release(&old);
+
+        let new = target.to_string_lossy().into_owned();
+        let tab = self.current_tab_mut();
+        tab.filename = target.file_name().map(|name| name.to_string_lossy().into_owned());
+        tab.filetype = get_friendly_filetype(target.extension().map(|ext| ext.to_string_lossy().into_owned()));
+        tab.filepath = Some(new.clone());
+        tab.lock_holder = locks::acquire(&new);
+        let _ = self.save_session();
+        Ok(new)
+    }
+
+    // Put the most recently deleted file back and open it again
+    pub fn restore_trashed_file(&mut self) -> Result<usize, Error> {
+        let item = self.trashed.last().cloned().ok_or_else(|| {
+            Error::new(std::io::ErrorKind::NotFound, "No file deleted this session")
+        })?;
+        trash::restore(&item)?;
+        self.trashed.pop();
+        self.open_file_in_new_tab(&item.original.to_string_lossy())
+    }
+
     // Open file in tab 1, push everything else down
     pub fn open_file_in_new_tab(&mut self, path: &str) -> Result<usize, Error> {
         // Check if file is already open (compare by filepath)
@@ -242,19 +688,72 @@ impl TabManager {
             if let Some(ref filepath) = tab.filepath {
                 if filepath == path {
                     self.active_tab_index = i;
+                    self.load_tab(i)?;
                     let _ = self.save_session();
                     return Ok(i);
                 }
             }
         }
 
+        Ok(self.add_opened_tab(Tab::from_file(path)?))
+    }
+
+    // Open a file from inside an archive in tab 1, or switch to it when it is already open
+    pub fn open_archive_member(&mut self, member: Member) -> Result<usize, Error> {
+        if let Some(i) = self.tabs.iter().position(|tab| tab.archive.as_ref() == Some(&member)) {
+            self.active_tab_index = i;
+            return Ok(i);
+        }
+        Ok(self.add_opened_tab(Tab::from_archive(member)?))
+    }
+
+    // Open a file nobody should edit here, like the release notes, in front
+    pub fn open_read_only_tab(&mut self, path: &str) -> Result<usize, Error> {
+        let index = self.open_file_in_new_tab(path)?;
+        self.tabs[index].read_only = true;
+        Ok(index)
+    }
+
+    // --host shares the tab in front, --join opens the shared text in a new one in front.
+    // Returns what to tell the user
+    pub fn start_collab(&mut self, role: &Role, config: &Config) -> Result<String, Error> {
+        let name = collab::display_name(&config.collab_name);
+        match role {
+            Role::Host => {
+                let tab = self.current_tab_mut();
+                let session = Session::host(config.collab_port, tab.buffer.content(), &name)?;
+                let message = format!("Sharing this tab - others join with quick --join {}", session.address);
+                tab.collab = Some(session);
+                Ok(message)
+            }
+            Role::Join(address) => {
+                let session = Session::join(address, &name)?;
+                let message = format!("Joined the shared session at {}", session.address);
+                let buffer = Buffer::from_string(session.text().to_string());
+                let mut tab = Tab::new(buffer, Some(format!("shared ({})", session.address)), None, None);
+                tab.collab = Some(session);
+                self.add_opened_tab(tab);
+                Ok(message)
+            }
+        }
+    }
+
+    pub fn is_open(&self, path: &str) -> bool {
+        self.tabs.iter().any(|tab| tab.filepath.as_deref() == Some(path))
+    }
+
+    // Put a freshly loaded file in front, the second half of open_file_in_new_tab
+    // for files read on a worker thread
+    pub fn add_opened_tab(&mut self, mut new_tab: Tab) -> usize {
         // If at max capacity, remove last tab
         if self.tabs.len() >= self.max_tabs {
-            self.tabs.pop();
+            self.close_last_tab();
         }
 
-        // Load file
-        let new_tab = Tab::from_file(path)?;
+        // Back where the caret was when it was last closed
+        if let Some(pos) = new_tab.filepath.as_deref().and_then(|p| Positions::load().get(p)) {
+            new_tab.set_text_position(pos);
+        }
         
         // Insert at position 0 (tab 1)
         self.tabs.insert(0, new_tab);
@@ -263,7 +762,7 @@ impl TabManager {
         // Save session
         let _ = self.save_session();
         
-        Ok(0)
+        0
     }
 }
 
@@ -271,10 +770,35 @@ impl TabManager {
 impl Drop for TabManager {
     fn drop(&mut self) {
         let _ = self.save_session();
+        self.tabs.iter().for_each(Tab::release_lock);
     }
 }
 
 // utility for filetypes
+// Files at least this big are loaded on a worker thread behind a progress bar
+pub const LARGE_FILE: u64 = 8 * 1024 * 1024;
+
+pub fn is_large_file(path: &str) -> bool {
+    fs::metadata(path).is_ok_and(|meta| meta.len() >= LARGE_FILE)
+}
+
+fn read_with_progress(path: &Path, progress: &Progress) -> Result<Vec<u8>, Error> {
+    let mut file = fs::File::open(path)?;
+    let size = file.metadata()?.len();
+    progress.set_total(size);
+    let mut bytes = Vec::with_capacity(size as usize);
+    let mut chunk = vec![0; 256 * 1024];
+    loop {
+        progress.check()?;
+        let read = file.read(&mut chunk)?;
+        if read == 0 {
+            return Ok(bytes);
+        }
+        bytes.extend_from_slice(&chunk[..read]);
+        progress.advance(read as u64);
+    }
+}
+
 pub fn get_friendly_filetype(extension: Option<String>) -> Option<String> {
     extension.map(|ext| {
         match ext.to_lowercase().as_str() {
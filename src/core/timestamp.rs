@@ -0,0 +1,41 @@
+// timestamp module - dates and times for file names and appended notes, in the local time zone
+use chrono::{DateTime, Local, TimeZone};
+use std::fmt::Display;
+
+const FILE_STAMP: &str = "%Y%m%d-%H%M%S";
+const READABLE: &str = "%Y-%m-%d %H:%M:%S";
+
+// YYYYMMDD-HHMMSS, which sorts by date in file names
+pub fn format_timestamp(secs: u64) -> String {
+    format(&Local, secs, FILE_STAMP)
+}
+
+// YYYY-MM-DD HH:MM:SS, for people to read
+pub fn format_readable(secs: u64) -> String {
+    format(&Local, secs, READABLE)
+}
+
+fn format<Tz: TimeZone>(zone: &Tz, secs: u64, pattern: &str) -> String
+where
+    Tz::Offset: Display,
+{
+    let time = DateTime::from_timestamp(secs as i64, 0).unwrap_or_default();
+    time.with_timezone(zone).format(pattern).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, Utc};
+
+    #[test]
+    fn test_format_in_time_zone() {
+        assert_eq!(format(&Utc, 0, FILE_STAMP), "19700101-000000");
+        // 2024-02-29 12:34:56 UTC
+        assert_eq!(format(&Utc, 1_709_210_096, FILE_STAMP), "20240229-123456");
+        assert_eq!(format(&Utc, 1_709_210_096, READABLE), "2024-02-29 12:34:56");
+        // Two hours east of UTC the same moment is later on the clock
+        let east = FixedOffset::east_opt(2 * 3600).unwrap();
+        assert_eq!(format(&east, 1_709_210_096, READABLE), "2024-02-29 14:34:56");
+    }
+}
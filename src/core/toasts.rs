@@ -0,0 +1,130 @@
+// toasts module - queue of status messages shown together instead of overwriting each other.
+// Notices like "Saved" go away on their own, errors stay until dismissed
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+const INFO_TIMEOUT: Duration = Duration::from_secs(3);
+const MAX_TOASTS: usize = 4;
+const MAX_RECENT: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToastKind {
+    Info,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Toast {
+    pub kind: ToastKind,
+    pub message: String,
+    expires: Option<Instant>, // None for errors, which wait to be dismissed
+}
+
+#[derive(Default)]
+pub struct Toasts {
+    queue: VecDeque<Toast>, // Oldest first
+    recent: VecDeque<String>, // Every message this session, dismissed or not, for issue reports
+}
+
+impl Toasts {
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.push(ToastKind::Info, message.into(), Instant::now());
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.push(ToastKind::Error, message.into(), Instant::now());
+    }
+
+    pub fn push(&mut self, kind: ToastKind, message: String, now: Instant) {
+        // The same message again moves to the front with a fresh timeout
+        self.queue.retain(|toast| toast.message != message);
+        let expires = (kind == ToastKind::Info).then(|| now + INFO_TIMEOUT);
+        let label = match kind {
+            ToastKind::Info => "info",
+            ToastKind::Error => "error",
+        };
+        self.recent.push_back(format!("{}: {}", label, message));
+        if self.recent.len() > MAX_RECENT {
+            self.recent.pop_front();
+        }
+        self.queue.push_back(Toast { kind, message, expires });
+
+        // Too many: notices make room before errors do
+        while self.queue.len() > MAX_TOASTS {
+            let oldest = self.queue.iter().position(|toast| toast.kind == ToastKind::Info).unwrap_or(0);
+            self.queue.remove(oldest);
+        }
+    }
+
+    // Drop notices past their timeout; true if any went away
+    pub fn expire(&mut self, now: Instant) -> bool {
+        let before = self.queue.len();
+        self.queue.retain(|toast| toast.expires.is_none_or(|at| at > now));
+        self.queue.len() != before
+    }
+
+    // When the next notice goes away, for scheduling a redraw
+    pub fn next_expiry(&self) -> Option<Instant> {
+        self.queue.iter().filter_map(|toast| toast.expires).min()
+    }
+
+    // Clear everything, errors included; true if anything was shown
+    pub fn dismiss(&mut self) -> bool {
+        let shown = !self.queue.is_empty();
+        self.queue.clear();
+        shown
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        self.queue.remove(index);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Toast> {
+        self.queue.iter()
+    }
+
+    // Latest messages of the session, oldest first
+    pub fn recent(&self) -> Vec<String> {
+        self.recent.iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toasts_expire_and_stack() {
+        let start = Instant::now();
+        let mut toasts = Toasts::default();
+        toasts.push(ToastKind::Info, "Saved".to_string(), start);
+        toasts.push(ToastKind::Error, "Disk full".to_string(), start);
+        toasts.push(ToastKind::Info, "Copied".to_string(), start + Duration::from_secs(2));
+        assert_eq!(toasts.next_expiry(), Some(start + INFO_TIMEOUT));
+
+        assert!(toasts.expire(start + Duration::from_secs(4)));
+        let left: Vec<&str> = toasts.iter().map(|t| t.message.as_str()).collect();
+        assert_eq!(left, ["Disk full", "Copied"]);
+        toasts.expire(start + Duration::from_secs(60));
+        assert_eq!(toasts.iter().count(), 1);
+        assert!(toasts.dismiss());
+        assert!(toasts.is_empty());
+    }
+
+    #[test]
+    fn test_toasts_limit_and_repeats() {
+        let now = Instant::now();
+        let mut toasts = Toasts::default();
+        toasts.push(ToastKind::Error, "e".to_string(), now);
+        for i in 0..5 {
+            toasts.push(ToastKind::Info, i.to_string(), now);
+        }
+        toasts.push(ToastKind::Info, "3".to_string(), now);
+        let messages: Vec<&str> = toasts.iter().map(|t| t.message.as_str()).collect();
+        assert_eq!(messages, ["e", "2", "4", "3"]);
+    }
+}
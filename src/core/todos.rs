@@ -0,0 +1,69 @@
+// todos module - find TODO/FIXME style markers in a buffer
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TodoItem {
+    pub line: usize,
+    pub column: usize, // Byte offset of the keyword
+    pub keyword: String,
+    pub text: String, // Rest of the line after the keyword
+}
+
+// Byte ranges of whole-word keyword occurrences in `text`
+pub fn keyword_spans(text: &str, keywords: &[String]) -> Vec<(usize, usize)> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut spans = Vec::new();
+
+    for keyword in keywords.iter().filter(|k| !k.is_empty()) {
+        for (start, _) in text.match_indices(keyword.as_str()) {
+            let end = start + keyword.len();
+            let before_ok = !text[..start].chars().next_back().is_some_and(is_word);
+            let after_ok = !text[end..].chars().next().is_some_and(is_word);
+            if before_ok && after_ok {
+                spans.push((start, end));
+            }
+        }
+    }
+
+    spans.sort_unstable();
+    spans
+}
+
+// First marker on every line, in buffer order
+pub fn find_todos(lines: &[String], keywords: &[String]) -> Vec<TodoItem> {
+    lines
+        .iter()
+        .enumerate()
+        .filter_map(|(line_idx, line)| {
+            let (start, end) = *keyword_spans(line, keywords).first()?;
+            let text = line[end..].trim_start_matches([':', ' ']).trim_end();
+            Some(TodoItem {
+                line: line_idx,
+                column: start,
+                keyword: line[start..end].to_string(),
+                text: text.to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_todos() {
+        let keywords: Vec<String> = ["TODO", "FIXME"].iter().map(|s| s.to_string()).collect();
+        let lines: Vec<String> = ["fn a() {} // TODO: split this", "let TODOS = 1;", "/* FIXME leak */"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        let todos = find_todos(&lines, &keywords);
+        assert_eq!(todos.len(), 2);
+        assert_eq!(todos[0].line, 0);
+        assert_eq!(todos[0].column, 13);
+        assert_eq!(todos[0].text, "split this");
+        assert_eq!(todos[1].keyword, "FIXME");
+        assert_eq!(todos[1].text, "leak */");
+    }
+}
@@ -0,0 +1,116 @@
+// transforms module - selection encoders/decoders (Base64, URL, JSON strings)
+use base64::alphabet;
+use base64::engine::{DecodePaddingMode, Engine, GeneralPurpose, GeneralPurposeConfig};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::io::{Error, ErrorKind};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextTransform {
+    Base64Encode,
+    Base64Decode,
+    UrlEncode,
+    UrlDecode,
+    JsonEscape,
+    JsonUnescape,
+}
+
+impl TextTransform {
+    pub const ALL: [TextTransform; 6] = [
+        TextTransform::Base64Encode,
+        TextTransform::Base64Decode,
+        TextTransform::UrlEncode,
+        TextTransform::UrlDecode,
+        TextTransform::JsonEscape,
+        TextTransform::JsonUnescape,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TextTransform::Base64Encode => "Base64 encode",
+            TextTransform::Base64Decode => "Base64 decode",
+            TextTransform::UrlEncode => "URL encode",
+            TextTransform::UrlDecode => "URL decode",
+            TextTransform::JsonEscape => "JSON escape string",
+            TextTransform::JsonUnescape => "JSON unescape string",
+        }
+    }
+
+    pub fn apply(&self, text: &str) -> Result<String, Error> {
+        match self {
+            TextTransform::Base64Encode => Ok(BASE64.encode(text)),
+            TextTransform::Base64Decode => utf8(base64_decode(text)?),
+            TextTransform::UrlEncode => Ok(utf8_percent_encode(text, UNRESERVED).to_string()),
+            TextTransform::UrlDecode => utf8(url_decode(text)),
+            TextTransform::JsonEscape => {
+                let quoted = serde_json::to_string(text).map_err(Error::other)?;
+                Ok(quoted[1..quoted.len() - 1].to_string())
+            }
+            TextTransform::JsonUnescape => {
+                let inner = text.strip_prefix('"').and_then(|t| t.strip_suffix('"')).unwrap_or(text);
+                serde_json::from_str(&format!("\"{}\"", inner)).map_err(|e| invalid(&e.to_string()))
+            }
+        }
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+fn utf8(bytes: Vec<u8>) -> Result<String, Error> {
+    String::from_utf8(bytes).map_err(|_| invalid("Decoded data is not valid UTF-8 text"))
+}
+
+// Decoding takes the standard and URL-safe alphabets, with or without padding
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+fn base64_decode(text: &str) -> Result<Vec<u8>, Error> {
+    let standard: String = text
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| match c {
+            '-' => '+',
+            '_' => '/',
+            c => c,
+        })
+        .collect();
+    BASE64.decode(standard).map_err(|e| invalid(&format!("Invalid Base64: {}", e)))
+}
+
+// Percent-encode everything except RFC 3986 unreserved characters
+const UNRESERVED: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'_').remove(b'.').remove(b'~');
+
+// Form-style: + is a space too
+fn url_decode(text: &str) -> Vec<u8> {
+    percent_decode_str(&text.replace('+', " ")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transforms_roundtrip() {
+        let text = "héllo wörld?&=\n\"quoted\"";
+        for (encode, decode) in [
+            (TextTransform::Base64Encode, TextTransform::Base64Decode),
+            (TextTransform::UrlEncode, TextTransform::UrlDecode),
+            (TextTransform::JsonEscape, TextTransform::JsonUnescape),
+        ] {
+            let encoded = encode.apply(text).unwrap();
+            assert_eq!(decode.apply(&encoded).unwrap(), text);
+        }
+
+        assert_eq!(TextTransform::Base64Encode.apply("Ma").unwrap(), "TWE=");
+        assert_eq!(TextTransform::UrlEncode.apply("a b/c").unwrap(), "a%20b%2Fc");
+        assert_eq!(TextTransform::JsonEscape.apply("a\"b\n").unwrap(), "a\\\"b\\n");
+        assert!(TextTransform::Base64Decode.apply("@@").is_err());
+        // URL-safe and unpadded Base64, and + as a space in URLs
+        assert_eq!(TextTransform::Base64Decode.apply("w7w_").unwrap(), "ü?");
+        assert_eq!(TextTransform::Base64Decode.apply("TWE").unwrap(), "Ma");
+        assert_eq!(TextTransform::UrlDecode.apply("a+b%21").unwrap(), "a b!");
+    }
+}
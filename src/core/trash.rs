@@ -0,0 +1,53 @@
+// trash module - move files to the desktop trash and put them back, through the trash crate
+use std::fs;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// A file moved to the trash this session, so it can be put back
+#[derive(Clone, Debug)]
+pub struct Trashed {
+    pub original: PathBuf,
+    deleted_at: i64, // seconds since the epoch, to tell it apart from older copies in the trash
+}
+
+fn to_io(e: trash::Error) -> Error {
+    Error::other(e.to_string())
+}
+
+pub fn move_to_trash(path: &Path) -> Result<Trashed, Error> {
+    let original = fs::canonicalize(path)?;
+    let deleted_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    trash::delete(&original).map_err(to_io)?;
+    Ok(Trashed { original, deleted_at })
+}
+
+// Put a trashed file back where it was, refusing to overwrite a new file there
+#[cfg(any(target_os = "windows", all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))))]
+pub fn restore(item: &Trashed) -> Result<(), Error> {
+    if item.original.exists() {
+        return Err(Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            format!("{} exists again, not overwriting it", item.original.display()),
+        ));
+    }
+    // The newest copy of that path deleted no earlier than we deleted it
+    let found = trash::os_limited::list()
+        .map_err(to_io)?
+        .into_iter()
+        .filter(|entry| entry.original_path() == item.original && entry.time_deleted >= item.deleted_at - 1)
+        .max_by_key(|entry| entry.time_deleted)
+        .ok_or_else(|| Error::new(std::io::ErrorKind::NotFound, "The file is no longer in the trash"))?;
+    trash::os_limited::restore_all([found]).map_err(to_io)
+}
+
+#[cfg(not(any(target_os = "windows", all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")))))]
+pub fn restore(_item: &Trashed) -> Result<(), Error> {
+    Err(Error::new(
+        std::io::ErrorKind::Unsupported,
+        "Restoring from the trash is not supported here, use the Finder",
+    ))
+}
@@ -1,5 +1,13 @@
 // module responsible for Auto-update functionality (Linux only)
 use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use crate::core::config::Config;
+use crate::core::platform;
+use crate::core::progress::Progress;
+use crate::core::whats_new;
 use serde::{Deserialize, Serialize};
 
 const GITHUB_REPO: &str = "DomanskiFilip/quick_notepad";
@@ -27,26 +35,38 @@ pub struct UpdateInfo {
 }
 
 pub struct Updater {
-    repo: String,
+    release_url: String,
+    proxy: Option<String>,
 }
 
 impl Updater {
-    pub fn new() -> Self {
-        Self {
-            repo: GITHUB_REPO.to_string(),
+    pub fn new(config: &Config) -> Self {
+        let release_url = match config.update_endpoint.trim() {
+            "" => format!("https://api.github.com/repos/{}/releases/latest", GITHUB_REPO),
+            url => url.to_string(),
+        };
+        let proxy = Some(config.update_proxy.trim().to_string()).filter(|p| !p.is_empty());
+        Self { release_url, proxy }
+    }
+
+    // reqwest picks up HTTP_PROXY / HTTPS_PROXY by itself; a proxy set in the config replaces them
+    fn client(&self, timeout: u64) -> Result<reqwest::blocking::Client, reqwest::Error> {
+        let mut builder = reqwest::blocking::Client::builder()
+            .user_agent("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36")
+            .timeout(std::time::Duration::from_secs(timeout));
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy)?);
         }
+        builder.build()
     }
 
     // Check if an update is available
     pub fn check_for_updates(&self) -> Result<UpdateInfo, Box<dyn std::error::Error>> {
         eprintln!("=== UPDATE CHECK DEBUG ===");
-        eprintln!("Repository: {}", self.repo);
+        eprintln!("Release endpoint: {}", self.release_url);
         eprintln!("Current version: {}", CURRENT_VERSION);
         
-        let client = match reqwest::blocking::Client::builder()
-            .user_agent("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36")
-            .timeout(std::time::Duration::from_secs(10))
-            .build() {
+        let client = match self.client(10) {
                 Ok(c) => {
                     eprintln!("✓ HTTP client created");
                     c
@@ -57,10 +77,7 @@ impl Updater {
                 }
             };
         
-        let release_url = format!("https://api.github.com/repos/{}/releases/latest", self.repo);
-        eprintln!("Fetching: {}", release_url);
-        
-        let response = match client.get(&release_url).send() {
+        let response = match client.get(&self.release_url).send() {
             Ok(r) => {
                 eprintln!("✓ Request sent successfully");
                 r
@@ -76,9 +93,9 @@ impl Updater {
         
         if status.as_u16() == 404 {
             eprintln!("✗ 404 Not Found - Repository or releases don't exist");
-            return Err("Repository not found or no releases available.\n\
-                Repository: DomanskiFilip/quick_notepad\n\
-                Make sure releases exist (not just tags)".into());
+            return Err(format!("Repository not found or no releases available.\n\
+                Endpoint: {}\n\
+                Make sure releases exist (not just tags)", self.release_url).into());
         }
         
         if !status.is_success() {
@@ -125,7 +142,7 @@ impl Updater {
     }
 
     // Compare version strings (simple semantic versioning)
-    fn is_newer_version(current: &str, latest: &str) -> bool {
+    pub fn is_newer_version(current: &str, latest: &str) -> bool {
         let current_parts: Vec<u32> = current
             .split('.')
             .filter_map(|s| s.parse().ok())
@@ -150,14 +167,9 @@ impl Updater {
     }
 
     // Download and install the update
-    pub fn perform_update(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let client = reqwest::blocking::Client::builder()
-            .user_agent("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36")
-            .timeout(std::time::Duration::from_secs(60))
-            .build()?;
-        
-        let release_url = format!("https://api.github.com/repos/{}/releases/latest", self.repo);
-        let response = client.get(&release_url).send()?;
+    pub fn perform_update(&self, progress: &Progress) -> Result<(), Box<dyn std::error::Error>> {
+        let client = self.client(60)?;
+        let response = client.get(&self.release_url).send()?;
         
         if !response.status().is_success() {
             return Err(format!("Failed to fetch release: {}", response.status()).into());
@@ -165,58 +177,204 @@ impl Updater {
         
         let release: GitHubRelease = response.json()?;
         
-        // Find the Linux binary - look for asset named "quick" (no extension)
+        // The build for this architecture and OS, preferring a bare binary over an archive
         let asset = release.assets
             .iter()
-            .find(|a| {
-                let name = a.name.to_lowercase();
-                // Look for binary named "quick" without extension
-                // Skip source archives
-                a.name == "quick" || 
-                (name.contains("linux") && !name.ends_with(".zip") && !name.ends_with(".tar.gz"))
-            })
-            .ok_or("No Linux binary found in release")?;
+            .filter(|a| platform::matches_release_asset(&a.name))
+            .min_by_key(|a| is_tar_gz(&a.name))
+            .ok_or_else(|| format!(
+                "No build for {} in release {}. Found: {}",
+                platform::target(),
+                release.tag_name,
+                release.assets.iter().map(|a| a.name.as_str()).collect::<Vec<_>>().join(", "),
+            ))?;
         
         // Download the binary
-        let download_response = client.get(&asset.browser_download_url).send()?;
+        let mut download_response = client.get(&asset.browser_download_url).send()?;
         
         if !download_response.status().is_success() {
             return Err(format!("Failed to download update: {}", download_response.status()).into());
         }
         
-        let bytes = download_response.bytes()?;
+        // Read in chunks so the progress bar moves and Esc can stop the download
+        progress.set_total(download_response.content_length().unwrap_or(0));
+        let mut bytes = Vec::new();
+        let mut chunk = vec![0; 64 * 1024];
+        loop {
+            progress.check()?;
+            let read = download_response.read(&mut chunk)?;
+            if read == 0 {
+                break;
+            }
+            bytes.extend_from_slice(&chunk[..read]);
+            progress.advance(read as u64);
+        }
+        if is_tar_gz(&asset.name) {
+            bytes = unpack_tar_gz(&bytes)?;
+        }
         
         // Get current executable path
         let current_exe = std::env::current_exe()?;
-        let backup_path = current_exe.with_extension("old");
         
-        // Create backup of current executable
-        fs::copy(&current_exe, &backup_path)?;
+        // Keep the current executable so --rollback can restore it
+        fs::copy(&current_exe, platform::previous_exe(&current_exe))?;
         
         // Write new executable to temp location
         let temp_path = current_exe.with_extension("new");
         fs::write(&temp_path, bytes)?;
         
-        // Make it executable
-        use std::os::unix::fs::PermissionsExt;
-        let mut perms = fs::metadata(&temp_path)?.permissions();
-        perms.set_mode(0o755);
-        fs::set_permissions(&temp_path, perms)?;
+        platform::make_executable(&temp_path)?;
         
         // Replace old executable with new one
-        fs::rename(&temp_path, &current_exe)?;
-        
-        // Clean up backup on success
-        let _ = fs::remove_file(&backup_path);
+        platform::replace_exe(&current_exe, &temp_path)?;
+
+        // For the "What's new" tab when the new version first starts
+        if let Some(notes) = &release.body {
+            let _ = fs::write(whats_new::release_notes_path(), notes);
+        }
         
         Ok(())
     }
+
+    // Put back the binary the last update replaced, once it has shown it still runs. The
+    // version rolled back from becomes the previous one, so a second rollback undoes the first
+    pub fn rollback() -> Result<(), Box<dyn std::error::Error>> {
+        let current_exe = std::env::current_exe()?;
+        let previous = platform::previous_exe(&current_exe);
+        if !previous.exists() {
+            return Err(format!("No previous version to roll back to ({} not found)", previous.display()).into());
+        }
+        Self::check_runs(&previous)?;
+
+        let running = fs::read(&current_exe)?;
+        let staged = current_exe.with_extension("new");
+        fs::copy(&previous, &staged)?;
+        platform::make_executable(&staged)?;
+        platform::replace_exe(&current_exe, &staged)?;
+
+        // Windows already moved the running binary to the previous path
+        if !cfg!(windows) {
+            fs::write(&previous, running)?;
+            platform::make_executable(&previous)?;
+        }
+        Ok(())
+    }
+
+    // Run `quick --shortcuts`, which every version has and which exits straight away
+    fn check_runs(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let mut child = Command::new(path)
+            .arg("--shortcuts")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("The previous version does not start: {}", e))?;
+        let started = Instant::now();
+        loop {
+            if let Some(status) = child.try_wait()? {
+                if !status.success() {
+                    return Err(format!("The previous version exits with an error ({})", status).into());
+                }
+                return Ok(());
+            }
+            if started.elapsed() > Duration::from_secs(5) {
+                let _ = child.kill();
+                return Err("The previous version does not respond".into());
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    }
+}
+
+fn is_tar_gz(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name.ends_with(".tar.gz") || name.ends_with(".tgz")
+}
+
+// Pull the executable out of a .tar.gz release, wherever it sits in the archive
+fn unpack_tar_gz(bytes: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut tar = Vec::new();
+    flate2::read::GzDecoder::new(bytes).read_to_end(&mut tar)?;
+
+    // A tar is 512-byte headers each followed by the entry's data, padded to 512 bytes
+    let mut offset = 0;
+    while offset + 512 <= tar.len() {
+        let header = &tar[offset..offset + 512];
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+        let field = |start: usize, len: usize| {
+            let raw = &header[start..start + len];
+            let end = raw.iter().position(|&b| b == 0).unwrap_or(len);
+            String::from_utf8_lossy(&raw[..end]).trim().to_string()
+        };
+        let name = field(0, 100);
+        let size = usize::from_str_radix(&field(124, 12), 8).map_err(|_| "Release archive is damaged")?;
+        let start = offset + 512;
+        let end = start.checked_add(size).filter(|&end| end <= tar.len()).ok_or("Release archive is truncated")?;
+        let is_file = matches!(header[156], b'0' | 0);
+        if is_file && name.rsplit('/').next() == Some(platform::EXE_NAME) {
+            return Ok(tar[start..end].to_vec());
+        }
+        offset = start + size.div_ceil(512) * 512;
+    }
+    Err(format!("Release archive has no {} inside", platform::EXE_NAME).into())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_release_endpoint_and_proxy() {
+        let mut config = Config::default();
+        let updater = Updater::new(&config);
+        assert_eq!(updater.release_url, format!("https://api.github.com/repos/{}/releases/latest", GITHUB_REPO));
+        assert!(updater.proxy.is_none());
+
+        config.update_endpoint = "https://mirror.example/quick/latest.json".to_string();
+        config.update_proxy = " http://proxy.example:8080 ".to_string();
+        let updater = Updater::new(&config);
+        assert_eq!(updater.release_url, "https://mirror.example/quick/latest.json");
+        assert_eq!(updater.proxy.as_deref(), Some("http://proxy.example:8080"));
+        assert!(updater.client(10).is_ok());
+    }
+
+    #[test]
+    fn test_unpack_tar_gz() {
+        use std::io::Write;
+        fn entry(tar: &mut Vec<u8>, name: &str, data: &[u8]) {
+            let mut header = [0u8; 512];
+            header[..name.len()].copy_from_slice(name.as_bytes());
+            header[124..135].copy_from_slice(format!("{:011o}", data.len()).as_bytes());
+            header[156] = b'0';
+            tar.extend_from_slice(&header);
+            tar.extend_from_slice(data);
+            tar.resize(tar.len().div_ceil(512) * 512, 0);
+        }
+        let mut tar = Vec::new();
+        entry(&mut tar, "quick-linux/README.md", b"read me");
+        entry(&mut tar, &format!("quick-linux/{}", platform::EXE_NAME), b"\x7fELF binary");
+        tar.extend_from_slice(&[0; 1024]);
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(&tar).unwrap();
+        let archive = gz.finish().unwrap();
+
+        assert_eq!(unpack_tar_gz(&archive).unwrap(), b"\x7fELF binary");
+        assert!(is_tar_gz("quick-linux-aarch64.tar.gz"));
+
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(&[0; 1024]).unwrap();
+        assert!(unpack_tar_gz(&gz.finish().unwrap()).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_runs() {
+        assert!(Updater::check_runs(Path::new("/bin/sh")).is_err()); // sh has no --shortcuts
+        assert!(Updater::check_runs(Path::new("/nonexistent/quick")).is_err());
+    }
+
     #[test]
     fn test_version_comparison() {
         assert!(Updater::is_newer_version("1.0.0", "1.0.1"));
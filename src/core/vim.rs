@@ -0,0 +1,446 @@
+// vim module - optional modal editing layer translating keys into actions, moves and operator ranges
+use crate::core::actions::Action;
+use crate::core::jumps::{self, Jump};
+use crate::core::line_metrics::LineMetrics;
+use crate::core::selection::TextPosition;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Mode {
+    #[default]
+    Normal,
+    Insert,
+    Visual,
+}
+
+impl Mode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Mode::Normal => "NORMAL",
+            Mode::Insert => "INSERT",
+            Mode::Visual => "VISUAL",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    Delete,
+    Yank,
+    Change,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VimCommand {
+    Pass,                                        // Not a vim key here - use the regular shortcuts
+    Pending,                                     // Consumed while a count or operator is typed
+    Run(Vec<Action>),                            // Existing actions to perform in order
+    Move(TextPosition),                          // Caret target; extends the selection in Visual mode
+    Apply(Operator, TextPosition, TextPosition), // Char range to delete / yank / change, end exclusive
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    WordForward,
+    WordBack,
+    WordEnd,
+    LineStart,
+    FirstNonBlank,
+    LineEnd,
+    FirstLine,
+    LastLine,
+    ParagraphForward,
+    ParagraphBack,
+}
+
+impl Motion {
+    fn from_key(c: char) -> Option<Self> {
+        Some(match c {
+            'h' => Motion::Left,
+            'l' | ' ' => Motion::Right,
+            'k' => Motion::Up,
+            'j' => Motion::Down,
+            'w' => Motion::WordForward,
+            'b' => Motion::WordBack,
+            'e' => Motion::WordEnd,
+            '0' => Motion::LineStart,
+            '^' => Motion::FirstNonBlank,
+            '$' => Motion::LineEnd,
+            'G' => Motion::LastLine,
+            '}' => Motion::ParagraphForward,
+            '{' => Motion::ParagraphBack,
+            _ => return None,
+        })
+    }
+
+    // Operators on these take whole lines
+    fn linewise(&self) -> bool {
+        matches!(self, Motion::Up | Motion::Down | Motion::FirstLine | Motion::LastLine)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Vim {
+    pub mode: Mode,
+    count: Option<usize>,
+    operator: Option<(Operator, usize)>, // Pending operator and the count typed before it
+    pending_g: bool,
+}
+
+impl Vim {
+    pub fn handle(&mut self, event: &KeyEvent, lines: &[String], cursor: TextPosition) -> VimCommand {
+        if event.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) {
+            if self.mode == Mode::Normal && event.code == KeyCode::Char('r') {
+                return VimCommand::Run(vec![Action::Redo; self.take_count()]);
+            }
+            return VimCommand::Pass;
+        }
+
+        if self.mode == Mode::Insert {
+            if event.code != KeyCode::Esc {
+                return VimCommand::Pass;
+            }
+            self.mode = Mode::Normal;
+            let column = cursor.column.saturating_sub(1);
+            return VimCommand::Move(TextPosition { line: cursor.line, column });
+        }
+
+        let key = match event.code {
+            KeyCode::Char(c) => c,
+            KeyCode::Enter => 'j',
+            KeyCode::Backspace => 'h',
+            KeyCode::Delete => 'x',
+            KeyCode::Esc => {
+                self.reset();
+                self.mode = Mode::Normal;
+                return VimCommand::Pending;
+            }
+            KeyCode::Tab => return VimCommand::Pending,
+            _ => return VimCommand::Pass,
+        };
+        self.normal_key(key, lines, cursor)
+    }
+
+    fn reset(&mut self) {
+        self.count = None;
+        self.operator = None;
+        self.pending_g = false;
+    }
+
+    fn take_count(&mut self) -> usize {
+        let count = self.count.take().unwrap_or(1);
+        self.reset();
+        count
+    }
+
+    fn normal_key(&mut self, key: char, lines: &[String], cursor: TextPosition) -> VimCommand {
+        // Counts, with a leading 0 meaning "start of line"
+        if let Some(digit) = key.to_digit(10) {
+            if digit > 0 || self.count.is_some() {
+                self.count = Some(self.count.unwrap_or(0).saturating_mul(10).saturating_add(digit as usize));
+                return VimCommand::Pending;
+            }
+        }
+
+        let motion = if self.pending_g {
+            self.pending_g = false;
+            match key {
+                'g' => Some(Motion::FirstLine),
+                _ => {
+                    self.reset();
+                    return VimCommand::Pending;
+                }
+            }
+        } else if key == 'g' {
+            self.pending_g = true;
+            return VimCommand::Pending;
+        } else {
+            Motion::from_key(key)
+        };
+
+        if let Some(motion) = motion {
+            let explicit = self.count.is_some() || self.operator.is_some_and(|(_, n)| n > 1);
+            let count = self.count.take().unwrap_or(1) * self.operator.map_or(1, |(_, n)| n);
+            let target = motion_target(lines, cursor, motion, count, explicit);
+            return match self.operator.take() {
+                Some((operator, _)) => self.apply(operator, operator_range(lines, cursor, target, motion, operator)),
+                None => VimCommand::Move(target),
+            };
+        }
+
+        if self.mode == Mode::Visual {
+            self.reset();
+            return match key {
+                'd' | 'x' => self.switch(Mode::Normal, vec![Action::Cut]),
+                'y' => self.switch(Mode::Normal, vec![Action::Copy]),
+                'c' => self.switch(Mode::Insert, vec![Action::Cut]),
+                'v' => self.switch(Mode::Normal, Vec::new()),
+                _ => VimCommand::Pending,
+            };
+        }
+
+        let operator = match key {
+            'd' => Some(Operator::Delete),
+            'y' => Some(Operator::Yank),
+            'c' => Some(Operator::Change),
+            _ => None,
+        };
+        if let Some(operator) = operator {
+            // Doubled operator (dd, yy, cc) works on whole lines
+            if let Some((pending, before)) = self.operator.take() {
+                let count = before * self.count.take().unwrap_or(1);
+                self.reset();
+                if pending != operator {
+                    return VimCommand::Pending;
+                }
+                let last = (cursor.line + count - 1).min(lines.len().saturating_sub(1));
+                return self.apply(operator, line_range(lines, cursor.line, last, operator));
+            }
+            self.operator = Some((operator, self.count.take().unwrap_or(1)));
+            return VimCommand::Pending;
+        }
+
+        let count = self.take_count();
+        let line_len = lines.get(cursor.line).map_or(0, |l| l.chars().count());
+        let at = |column: usize| TextPosition { line: cursor.line, column };
+        match key {
+            'i' => self.switch(Mode::Insert, Vec::new()),
+            'v' => self.switch(Mode::Visual, Vec::new()),
+            'a' => {
+                self.mode = Mode::Insert;
+                VimCommand::Move(at((cursor.column + 1).min(line_len)))
+            }
+            'A' => {
+                self.mode = Mode::Insert;
+                VimCommand::Move(at(line_len))
+            }
+            'I' => {
+                self.mode = Mode::Insert;
+                VimCommand::Move(motion_target(lines, cursor, Motion::FirstNonBlank, 1, false))
+            }
+            'o' => self.switch(Mode::Insert, vec![Action::MaxRight, Action::NextLine]),
+            'O' => self.switch(Mode::Insert, vec![Action::MaxLeft, Action::NextLine, Action::Up]),
+            'x' => VimCommand::Apply(Operator::Delete, cursor, at((cursor.column + count).min(line_len))),
+            'D' => VimCommand::Apply(Operator::Delete, cursor, at(line_len)),
+            'C' => self.apply(Operator::Change, (cursor, at(line_len))),
+            'p' | 'P' => VimCommand::Run(vec![Action::Paste; count]),
+            'u' => VimCommand::Run(vec![Action::Undo; count]),
+            '/' => VimCommand::Run(vec![Action::Search]),
+            _ => VimCommand::Pending,
+        }
+    }
+
+    fn switch(&mut self, mode: Mode, actions: Vec<Action>) -> VimCommand {
+        self.mode = mode;
+        VimCommand::Run(actions)
+    }
+
+    fn apply(&mut self, operator: Operator, (start, end): (TextPosition, TextPosition)) -> VimCommand {
+        if operator == Operator::Change {
+            self.mode = Mode::Insert;
+        }
+        VimCommand::Apply(operator, start, end)
+    }
+}
+
+// Characters as seen by word motions: line ends count as whitespace
+fn char_at(text: &[Vec<char>], p: TextPosition) -> char {
+    text[p.line].get(p.column).copied().unwrap_or('\n')
+}
+
+fn class(c: char) -> u8 {
+    if c.is_whitespace() {
+        0
+    } else if c.is_alphanumeric() || c == '_' {
+        1
+    } else {
+        2
+    }
+}
+
+fn next(text: &[Vec<char>], p: TextPosition) -> Option<TextPosition> {
+    if p.column < text[p.line].len() {
+        Some(TextPosition { line: p.line, column: p.column + 1 })
+    } else if p.line + 1 < text.len() {
+        Some(TextPosition { line: p.line + 1, column: 0 })
+    } else {
+        None
+    }
+}
+
+fn prev(text: &[Vec<char>], p: TextPosition) -> Option<TextPosition> {
+    if p.column > 0 {
+        Some(TextPosition { line: p.line, column: p.column - 1 })
+    } else if p.line > 0 {
+        Some(TextPosition { line: p.line - 1, column: text[p.line - 1].len() })
+    } else {
+        None
+    }
+}
+
+fn motion_target(lines: &[String], cursor: TextPosition, motion: Motion, count: usize, explicit: bool) -> TextPosition {
+    let text: Vec<Vec<char>> = lines.iter().map(|l| l.chars().collect()).collect();
+    if text.is_empty() {
+        return cursor;
+    }
+    let last = text.len() - 1;
+    let len = |line: usize| text[line].len();
+    let clamp = |line: usize, column: usize| TextPosition { line, column: column.min(len(line)) };
+    let first_non_blank = |line: usize| text[line].iter().take_while(|c| c.is_whitespace()).count();
+    let cursor = clamp(cursor.line.min(last), cursor.column);
+
+    match motion {
+        Motion::Left => clamp(cursor.line, cursor.column.saturating_sub(count)),
+        Motion::Right => clamp(cursor.line, cursor.column + count),
+        Motion::Up => clamp(cursor.line.saturating_sub(count), cursor.column),
+        Motion::Down => clamp((cursor.line + count).min(last), cursor.column),
+        Motion::LineStart => clamp(cursor.line, 0),
+        Motion::FirstNonBlank => clamp(cursor.line, first_non_blank(cursor.line)),
+        Motion::LineEnd => clamp(cursor.line, len(cursor.line)),
+        Motion::FirstLine | Motion::LastLine => {
+            let line = match (motion, explicit) {
+                (_, true) => (count - 1).min(last),
+                (Motion::FirstLine, false) => 0,
+                _ => last,
+            };
+            clamp(line, first_non_blank(line))
+        }
+        Motion::WordForward => (0..count).fold(cursor, |mut p, _| {
+            let start = class(char_at(&text, p));
+            while start != 0 && class(char_at(&text, p)) == start {
+                match next(&text, p) {
+                    Some(n) => p = n,
+                    None => return p,
+                }
+            }
+            while class(char_at(&text, p)) == 0 {
+                match next(&text, p) {
+                    Some(n) => p = n,
+                    None => return p,
+                }
+            }
+            p
+        }),
+        Motion::WordBack => (0..count).fold(cursor, |mut p, _| {
+            let Some(mut q) = prev(&text, p) else { return p };
+            while class(char_at(&text, q)) == 0 {
+                match prev(&text, q) {
+                    Some(n) => q = n,
+                    None => return q,
+                }
+            }
+            p = q;
+            let word = class(char_at(&text, p));
+            while let Some(n) = prev(&text, p).filter(|&n| class(char_at(&text, n)) == word) {
+                p = n;
+            }
+            p
+        }),
+        Motion::WordEnd => (0..count).fold(cursor, |p, _| {
+            let Some(mut q) = next(&text, p) else { return p };
+            while class(char_at(&text, q)) == 0 {
+                match next(&text, q) {
+                    Some(n) => q = n,
+                    None => return q,
+                }
+            }
+            let word = class(char_at(&text, q));
+            while let Some(n) = next(&text, q).filter(|&n| class(char_at(&text, n)) == word) {
+                q = n;
+            }
+            q
+        }),
+        Motion::ParagraphForward | Motion::ParagraphBack => {
+            let jump = if motion == Motion::ParagraphForward { Jump::ParagraphDown } else { Jump::ParagraphUp };
+            // Only blank lines matter here, which the tab width doesn't change
+            (0..count).fold(cursor, |p, _| jumps::target(lines, p.line, jump, |i| LineMetrics::measure(&lines[i], 1)))
+        }
+    }
+}
+
+// Range an operator covers when combined with `motion` from `cursor` to `target`
+fn operator_range(
+    lines: &[String],
+    cursor: TextPosition,
+    target: TextPosition,
+    motion: Motion,
+    operator: Operator,
+) -> (TextPosition, TextPosition) {
+    if motion.linewise() {
+        return line_range(lines, cursor.line.min(target.line), cursor.line.max(target.line), operator);
+    }
+    let key = |p: TextPosition| (p.line, p.column);
+    let (start, mut end) = if key(target) < key(cursor) { (target, cursor) } else { (cursor, target) };
+    // `e` includes the character it lands on
+    if motion == Motion::WordEnd {
+        let len = lines.get(end.line).map_or(0, |l| l.chars().count());
+        end.column = (end.column + 1).min(len);
+    }
+    (start, end)
+}
+
+// Whole lines first..=last; deleting and yanking take a line break with them, changing keeps it
+fn line_range(lines: &[String], first: usize, last: usize, operator: Operator) -> (TextPosition, TextPosition) {
+    let len = |line: usize| lines.get(line).map_or(0, |l| l.chars().count());
+    let pos = |line: usize, column: usize| TextPosition { line, column };
+    if operator == Operator::Change {
+        (pos(first, 0), pos(last, len(last)))
+    } else if last + 1 < lines.len() {
+        (pos(first, 0), pos(last + 1, 0))
+    } else if first > 0 {
+        (pos(first - 1, len(first - 1)), pos(last, len(last)))
+    } else {
+        (pos(first, 0), pos(last, len(last)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::empty())
+    }
+
+    #[test]
+    fn test_vim_keys() {
+        let lines: Vec<String> = ["let value = foo(bar);", "second line", "third"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let pos = |line: usize, column: usize| TextPosition { line, column };
+        let mut vim = Vim::default();
+        let mut press = |keys: &str, cursor: TextPosition| {
+            keys.chars().map(|c| vim.handle(&key(c), &lines, cursor)).last().unwrap()
+        };
+
+        assert_eq!(press("w", pos(0, 0)), VimCommand::Move(pos(0, 4)));
+        assert_eq!(press("3w", pos(0, 0)), VimCommand::Move(pos(0, 12)));
+        assert_eq!(press("b", pos(0, 12)), VimCommand::Move(pos(0, 10)));
+        assert_eq!(press("2j", pos(0, 8)), VimCommand::Move(pos(2, 5)));
+        assert_eq!(press("G", pos(0, 0)), VimCommand::Move(pos(2, 0)));
+        assert_eq!(press("2gg", pos(0, 0)), VimCommand::Move(pos(1, 0)));
+        assert_eq!(press("}", pos(0, 5)), VimCommand::Move(pos(2, 0)));
+        assert_eq!(press("{", pos(2, 1)), VimCommand::Move(pos(0, 0)));
+        assert_eq!(press("dw", pos(0, 4)), VimCommand::Apply(Operator::Delete, pos(0, 4), pos(0, 10)));
+        assert_eq!(press("de", pos(0, 4)), VimCommand::Apply(Operator::Delete, pos(0, 4), pos(0, 9)));
+        assert_eq!(press("2dd", pos(0, 3)), VimCommand::Apply(Operator::Delete, pos(0, 0), pos(2, 0)));
+        assert_eq!(press("yy", pos(2, 1)), VimCommand::Apply(Operator::Yank, pos(1, 11), pos(2, 5)));
+        assert_eq!(press("x", pos(1, 0)), VimCommand::Apply(Operator::Delete, pos(1, 0), pos(1, 1)));
+
+        assert_eq!(press("cw", pos(1, 0)), VimCommand::Apply(Operator::Change, pos(1, 0), pos(1, 7)));
+        assert_eq!(vim.mode, Mode::Insert);
+        assert_eq!(vim.handle(&key('q'), &lines, pos(1, 0)), VimCommand::Pass);
+        let esc = KeyEvent::new(KeyCode::Esc, KeyModifiers::empty());
+        assert_eq!(vim.handle(&esc, &lines, pos(1, 3)), VimCommand::Move(pos(1, 2)));
+
+        assert_eq!(vim.handle(&key('v'), &lines, pos(0, 0)), VimCommand::Run(Vec::new()));
+        assert_eq!(vim.mode, Mode::Visual);
+        assert_eq!(vim.handle(&key('y'), &lines, pos(0, 0)), VimCommand::Run(vec![Action::Copy]));
+        assert_eq!(vim.mode, Mode::Normal);
+    }
+}
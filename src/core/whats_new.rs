@@ -0,0 +1,79 @@
+// whats_new module - release notes shown in a read-only tab the first time a new version starts,
+// taken from the bundled CHANGELOG.md or, failing that, the notes of the release the updater installed
+use crate::core::paths;
+use crate::core::updater::Updater;
+use std::fs;
+use std::path::PathBuf;
+
+const CHANGELOG: &str = include_str!("../../CHANGELOG.md");
+const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// Where the updater leaves the downloaded release notes for the next start
+pub fn release_notes_path() -> PathBuf {
+    paths::cache_dir().join("release_notes.md")
+}
+
+// Record this start and, when it is the first of a newer version, write the notes to a file to
+// open. Nothing is shown on a first install or after rolling back to an older version
+pub fn on_launch() -> Option<PathBuf> {
+    let seen_file = paths::state_dir().join("last_version");
+    let previous = fs::read_to_string(&seen_file).ok();
+    if previous.as_deref().map(str::trim) == Some(CURRENT_VERSION) {
+        return None;
+    }
+    let _ = fs::write(&seen_file, CURRENT_VERSION);
+    let previous = previous?;
+    let previous = previous.trim();
+    let downloaded = fs::read_to_string(release_notes_path()).unwrap_or_default();
+    let _ = fs::remove_file(release_notes_path());
+    if !Updater::is_newer_version(previous, CURRENT_VERSION) {
+        return None;
+    }
+
+    let mut notes = changes_between(CHANGELOG, previous, CURRENT_VERSION);
+    if notes.is_empty() {
+        notes = downloaded.trim().to_string();
+    }
+    if notes.is_empty() {
+        return None;
+    }
+    let path = paths::cache_dir().join("whats-new.md");
+    let text = format!("# What's new in Quick Notepad {}\n\nClose this tab when you're done.\n\n{}\n", CURRENT_VERSION, notes);
+    fs::write(&path, text).ok()?;
+    Some(path)
+}
+
+// The "## [x.y.z]" sections of a changelog newer than `after`, up to and including `upto`
+fn changes_between(changelog: &str, after: &str, upto: &str) -> String {
+    let mut sections = Vec::new();
+    let mut keep = false;
+    for line in changelog.lines() {
+        if let Some(heading) = line.strip_prefix("## ") {
+            let version = heading
+                .trim_start_matches(|c: char| !c.is_ascii_digit())
+                .split(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .next()
+                .unwrap_or("");
+            keep = !version.is_empty()
+                && Updater::is_newer_version(after, version)
+                && !Updater::is_newer_version(upto, version);
+        }
+        if keep {
+            sections.push(line);
+        }
+    }
+    sections.join("\n").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changes_between() {
+        let changelog = "# Changelog\n\n## [Unreleased]\n- next\n\n## [1.3.0] - 2026-09-01\n- three\n\n## v1.2.0\n- two\n\n## [1.1.0]\n- one\n";
+        assert_eq!(changes_between(changelog, "1.1.0", "1.3.0"), "## [1.3.0] - 2026-09-01\n- three\n\n## v1.2.0\n- two");
+        assert_eq!(changes_between(changelog, "1.2.0", "1.2.5"), "");
+        assert_eq!(changes_between(changelog, "1.0.0", "1.1.0"), "## [1.1.0]\n- one");
+    }
+}
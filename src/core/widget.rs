@@ -0,0 +1,304 @@
+// widget module - EditorWidget, the editor as a multi-line text field other terminal apps can
+// embed. It keeps its own text, caret, scroll and undo history and draws into whatever
+// rectangle it is given through a Surface, so it works with crossterm directly or any other
+// terminal library that can print a string at a cell
+use crate::core::edit_history::{Edit, EditHistory, EditOperation};
+use crate::core::selection::{Position, TextPosition};
+use crossterm::{
+    cursor::{MoveTo, Show},
+    event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    queue,
+    style::Print,
+};
+use std::io::{Error, Write};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+// Where the widget draws. A ratatui Buffer or a test grid only needs these two calls
+pub trait Surface {
+    // Draw `text` from the cell (x, y); the widget never passes text wider than its rectangle
+    fn print(&mut self, x: u16, y: u16, text: &str) -> Result<(), Error>;
+    // Put the terminal cursor on the caret, called last in every render
+    fn show_cursor(&mut self, x: u16, y: u16) -> Result<(), Error>;
+}
+
+// Any crossterm writer, e.g. stdout(). Output is queued and left for the caller to flush
+pub struct CrosstermSurface<W: Write>(pub W);
+
+impl<W: Write> Surface for CrosstermSurface<W> {
+    fn print(&mut self, x: u16, y: u16, text: &str) -> Result<(), Error> {
+        queue!(self.0, MoveTo(x, y), Print(text))
+    }
+
+    fn show_cursor(&mut self, x: u16, y: u16) -> Result<(), Error> {
+        queue!(self.0, MoveTo(x, y), Show)
+    }
+}
+
+pub struct EditorWidget {
+    lines: Vec<String>,
+    cursor: TextPosition, // Column in graphemes, like the rest of the editor
+    top: usize,           // First line shown
+    left: usize,          // First display column shown, for lines wider than the rectangle
+    page: usize,          // Rows shown in the last render, for Page Up / Page Down
+    history: EditHistory,
+}
+
+impl EditorWidget {
+    pub fn new(text: &str) -> Self {
+        Self {
+            lines: text.split('\n').map(String::from).collect(),
+            cursor: TextPosition { line: 0, column: 0 },
+            top: 0,
+            left: 0,
+            page: 1,
+            history: EditHistory::new(500),
+        }
+    }
+
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    pub fn cursor(&self) -> TextPosition {
+        self.cursor
+    }
+
+    // Edit or move for a key press; false for keys the widget leaves to the host, like Esc and Tab
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if key.kind == KeyEventKind::Release {
+            return false;
+        }
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let (line, column) = (self.cursor.line, self.cursor.column);
+        match key.code {
+            KeyCode::Char('z') if ctrl => self.undo(),
+            KeyCode::Char('y') if ctrl => self.redo(),
+            KeyCode::Char(c) if !ctrl && !key.modifiers.contains(KeyModifiers::ALT) => self.insert(c),
+            KeyCode::Enter => self.replace(self.cursor, self.cursor, "\n"),
+            KeyCode::Backspace if column > 0 => self.replace(TextPosition { line, column: column - 1 }, self.cursor, ""),
+            KeyCode::Backspace if line > 0 => {
+                self.replace(TextPosition { line: line - 1, column: self.line_len(line - 1) }, self.cursor, "")
+            }
+            KeyCode::Delete if column < self.line_len(line) => {
+                self.replace(self.cursor, TextPosition { line, column: column + 1 }, "")
+            }
+            KeyCode::Delete if line + 1 < self.lines.len() => {
+                self.replace(self.cursor, TextPosition { line: line + 1, column: 0 }, "")
+            }
+            KeyCode::Left if column > 0 => self.cursor.column -= 1,
+            KeyCode::Left if line > 0 => self.cursor = TextPosition { line: line - 1, column: self.line_len(line - 1) },
+            KeyCode::Right if column < self.line_len(line) => self.cursor.column += 1,
+            KeyCode::Right if line + 1 < self.lines.len() => self.cursor = TextPosition { line: line + 1, column: 0 },
+            KeyCode::Up => self.move_to_line(line.saturating_sub(1)),
+            KeyCode::Down => self.move_to_line(line + 1),
+            KeyCode::PageUp => self.move_to_line(line.saturating_sub(self.page)),
+            KeyCode::PageDown => self.move_to_line(line + self.page),
+            KeyCode::Home => self.cursor.column = 0,
+            KeyCode::End => self.cursor.column = self.line_len(line),
+            KeyCode::Backspace | KeyCode::Delete | KeyCode::Left | KeyCode::Right => {}
+            _ => return false,
+        }
+        true
+    }
+
+    // Draw the text into `area`, scrolled so the caret is inside it
+    pub fn render(&mut self, surface: &mut impl Surface, area: Rect) -> Result<(), Error> {
+        let (width, height) = (area.width as usize, area.height as usize);
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+        self.page = height;
+        if self.cursor.line < self.top {
+            self.top = self.cursor.line;
+        } else if self.cursor.line >= self.top + height {
+            self.top = self.cursor.line + 1 - height;
+        }
+        let caret_x = self.lines[self.cursor.line].graphemes(true).take(self.cursor.column).map(|g| g.width()).sum::<usize>();
+        if caret_x < self.left {
+            self.left = caret_x;
+        } else if caret_x >= self.left + width {
+            self.left = caret_x + 1 - width;
+        }
+
+        for row in 0..height {
+            let text = self.lines.get(self.top + row).map_or(String::new(), |line| visible_part(line, self.left, width));
+            let padding = width - text.width();
+            surface.print(area.x, area.y + row as u16, &format!("{}{}", text, " ".repeat(padding)))?;
+        }
+        surface.show_cursor(area.x + (caret_x - self.left) as u16, area.y + (self.cursor.line - self.top) as u16)
+    }
+
+    fn line_len(&self, line: usize) -> usize {
+        self.lines[line].graphemes(true).count()
+    }
+
+    fn move_to_line(&mut self, line: usize) {
+        self.cursor.line = line.min(self.lines.len() - 1);
+        self.cursor.column = self.cursor.column.min(self.line_len(self.cursor.line));
+    }
+
+    // Typed characters are recorded as InsertText so a typed word undoes in one step
+    fn insert(&mut self, c: char) {
+        let edit = Edit::InsertText { line: self.cursor.line, column: self.cursor.column, text: c.to_string() };
+        edit.apply(&mut self.lines);
+        self.cursor = caret_after(&edit, false);
+        self.record(edit);
+    }
+
+    fn replace(&mut self, start: TextPosition, end: TextPosition, text: &str) {
+        let edit = Edit::ReplaceRange {
+            start_line: start.line,
+            start_column: start.column,
+            end_line: end.line,
+            end_column: end.column,
+            old_text: text_between(&self.lines, start, end),
+            new_text: text.to_string(),
+        };
+        edit.apply(&mut self.lines);
+        self.cursor = caret_after(&edit, false);
+        self.record(edit);
+    }
+
+    // The widget tracks its caret in text positions, so the screen positions stay unused
+    fn record(&mut self, edit: Edit) {
+        let operation = EditOperation {
+            edit,
+            cursor_before: Position::default(),
+            cursor_after: Position::default(),
+            scroll_before: 0,
+            scroll_after: 0,
+        };
+        self.history.push(operation);
+    }
+
+    fn undo(&mut self) {
+        if let Some(operation) = self.history.undo() {
+            operation.edit.reverse(&mut self.lines);
+            self.cursor = caret_after(&operation.edit, true);
+            self.move_to_line(self.cursor.line);
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(operation) = self.history.redo() {
+            operation.edit.apply(&mut self.lines);
+            self.cursor = caret_after(&operation.edit, false);
+            self.move_to_line(self.cursor.line);
+        }
+    }
+}
+
+// The text from `start` to `end`, which must be in order and inside `lines`
+pub(crate) fn text_between(lines: &[String], start: TextPosition, end: TextPosition) -> String {
+    let byte = |line: &str, column: usize| line.grapheme_indices(true).nth(column).map_or(line.len(), |(i, _)| i);
+    if start.line == end.line {
+        let line = &lines[start.line];
+        return line[byte(line, start.column)..byte(line, end.column)].to_string();
+    }
+    let first = &lines[start.line];
+    let last = &lines[end.line];
+    let mut parts = vec![&first[byte(first, start.column)..]];
+    parts.extend(lines[start.line + 1..end.line].iter().map(String::as_str));
+    parts.push(&last[..byte(last, end.column)]);
+    parts.join("\n")
+}
+
+// Where the caret belongs once `edit` is applied, or reversed when `undone`
+fn caret_after(edit: &Edit, undone: bool) -> TextPosition {
+    let end_of = |line: usize, column: usize, text: &str| match text.rsplit_once('\n') {
+        Some((_, last)) => TextPosition { line: line + text.matches('\n').count(), column: last.graphemes(true).count() },
+        None => TextPosition { line, column: column + text.graphemes(true).count() },
+    };
+    match edit {
+        Edit::InsertText { line, column, .. } if undone => TextPosition { line: *line, column: *column },
+        Edit::InsertText { line, column, text } => end_of(*line, *column, text),
+        Edit::ReplaceRange { start_line, start_column, old_text, new_text, .. } => {
+            end_of(*start_line, *start_column, if undone { old_text } else { new_text })
+        }
+        _ => TextPosition { line: 0, column: 0 },
+    }
+}
+
+// The graphemes of `line` between display columns `left` and `left + width`. A wide
+// character cut by either edge is drawn as spaces
+fn visible_part(line: &str, left: usize, width: usize) -> String {
+    let mut text = String::new();
+    let mut x = 0;
+    for grapheme in line.graphemes(true) {
+        let w = grapheme.width();
+        if x + w > left + width {
+            break;
+        }
+        if x >= left {
+            text.push_str(grapheme);
+        } else if x + w > left {
+            text.push_str(&" ".repeat(x + w - left));
+        }
+        x += w;
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Grid {
+        rows: Vec<(u16, String)>,
+        cursor: (u16, u16),
+    }
+
+    impl Surface for Grid {
+        fn print(&mut self, _x: u16, y: u16, text: &str) -> Result<(), Error> {
+            self.rows.push((y, text.to_string()));
+            Ok(())
+        }
+
+        fn show_cursor(&mut self, x: u16, y: u16) -> Result<(), Error> {
+            self.cursor = (x, y);
+            Ok(())
+        }
+    }
+
+    fn press(widget: &mut EditorWidget, code: KeyCode) -> bool {
+        widget.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn test_edit_undo_and_render() {
+        let mut widget = EditorWidget::new("hello\nworld");
+        press(&mut widget, KeyCode::End);
+        "!".chars().for_each(|c| assert!(press(&mut widget, KeyCode::Char(c))));
+        press(&mut widget, KeyCode::Enter);
+        press(&mut widget, KeyCode::Delete);
+        assert_eq!(widget.text(), "hello!\nworld");
+        press(&mut widget, KeyCode::Backspace);
+        assert_eq!(widget.text(), "hello!world");
+        assert!(!press(&mut widget, KeyCode::Esc));
+
+        widget.handle_key(KeyEvent::new(KeyCode::Char('z'), KeyModifiers::CONTROL));
+        assert_eq!(widget.text(), "hello!\nworld");
+        assert_eq!(widget.cursor(), TextPosition { line: 1, column: 0 });
+
+        // Three columns wide: scrolled right to keep the caret at the end of "hello!" in view
+        press(&mut widget, KeyCode::Up);
+        press(&mut widget, KeyCode::End);
+        let mut grid = Grid::default();
+        widget.render(&mut grid, Rect { x: 10, y: 5, width: 3, height: 1 }).unwrap();
+        assert_eq!(grid.rows, vec![(5, "o! ".to_string())]);
+        assert_eq!(grid.cursor, (12, 5));
+
+        assert_eq!(visible_part("a界b", 0, 2), "a");
+        assert_eq!(visible_part("a界b", 2, 2), " b");
+    }
+}
@@ -1,9 +1,124 @@
 // src/gui/app.rs
-use super::{editor::EditorPanel, state::EditorState, themes};
+use super::{editor::EditorPanel, state::EditorState, themes, toasts};
 use crate::core::actions::Action;
-use crate::core::updater::UpdateInfo;
+use crate::core::calc;
+use crate::core::collab::Role;
+use crate::core::completion;
+use crate::core::align;
+use crate::core::append;
+use crate::core::archive::{self, Member};
+use crate::core::conflicts::Resolution;
+use crate::core::crypto;
+use crate::core::finder;
+use crate::gui::compare::CompareView;
+use crate::gui::detached::DetachedWindow;
+use crate::gui::history::HistoryView;
+use crate::core::hash::HashAlgorithm;
+use crate::core::jumps::Jump;
+use crate::core::palette::{self, PaletteCommand};
+use crate::core::paths;
+use crate::core::links;
+use crate::core::platform;
+use crate::core::report::{IssueReport, REPORT_COPIED, REPORT_OPENED};
+use crate::core::progress::Job;
+use crate::core::sequence::Sequence;
+use crate::core::stats::Stats;
+use crate::core::scratch::{self, ScratchNote};
+use crate::core::selection::TextPosition;
+use crate::core::todos::TodoItem;
+use crate::core::updater::{UpdateInfo, Updater};
+use crate::core::whats_new;
 use egui::{Context, ViewportCommand};
 
+#[derive(Clone, Copy, PartialEq)]
+enum PathPurpose {
+    ReadFile,       // Insert the file at the cursor
+    WriteSelection, // Save the selection to the file
+    Append,         // Add the selection or buffer to the end of the file
+    Rename,         // Move the current file to this name
+}
+
+struct PathPrompt {
+    purpose: PathPurpose,
+    path: String,
+    matches: Vec<String>, // Names the last Tab completion matched
+    overwrite: bool,      // The file exists and the next write replaces it
+}
+
+impl PathPrompt {
+    fn new(purpose: PathPurpose) -> Self {
+        Self { purpose, path: String::new(), matches: Vec::new(), overwrite: false }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum PassphraseMode {
+    Unlock,  // Decrypt a locked note
+    Encrypt, // Choose a passphrase for saving
+}
+
+// Modifier named in menu labels: the shortcuts below use Cmd on macOS and Ctrl elsewhere
+const CMD: &str = platform::PRIMARY_MODIFIER;
+
+// Files under the working directory, the project the file finder searches
+fn project_files() -> Vec<String> {
+    std::env::current_dir().map(|root| finder::files(&root)).unwrap_or_default()
+}
+
+// Map egui shortcuts to our Action enum
+fn shortcuts() -> Vec<(egui::KeyboardShortcut, Action)> {
+    vec![
+        (egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::S), Action::Save),
+        (egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::P), Action::CommandPalette),
+        (egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::E), Action::SaveEncrypted),
+        (egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::N), Action::New),
+        (egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Q), Action::Quit),
+        // Ctrl+Shift+Z ahead of Ctrl+Z, which would also match it
+        (egui::KeyboardShortcut::new(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::Z), Action::Redo),
+        (egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z), Action::Undo),
+        (egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Y), Action::Redo),
+        (egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::C), Action::Copy),
+        (egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::X), Action::Cut),
+        (egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::V), Action::Paste),
+        (egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::F), Action::Search),
+        (egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::F3), Action::RepeatSearch),
+        (egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::F1), Action::ToggleCtrlShortcuts),
+        (egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::F2), Action::RenameFile),
+        (egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::Z), Action::ToggleAutoWrap),
+        (egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::D), Action::ToggleCheckbox),
+        (egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::D), Action::MoveDoneItems),
+        (egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::T), Action::FormatTable),
+        (egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::L), Action::ListTodos),
+        (egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::O), Action::OpenLink),
+        (egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::G), Action::GoToFile),
+        (egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::Equals), Action::Evaluate),
+        (egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::A), Action::AlignSelection),
+        (egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::Q), Action::ReflowParagraph),
+        (egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::N), Action::InsertSequence),
+        (egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::C), Action::CompareTabs),
+        (egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::M), Action::NextConflict),
+        (egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::F), Action::ToggleFold),
+        (egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::U), Action::UnfoldAll),
+        (egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::ArrowUp), Action::ExpandSelection),
+        (egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::ArrowDown), Action::ShrinkSelection),
+        // Shift variants first, the plain shortcuts would also match them
+        (egui::KeyboardShortcut::new(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::ArrowUp), Action::SelectJump(Jump::ParagraphUp)),
+        (egui::KeyboardShortcut::new(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::ArrowDown), Action::SelectJump(Jump::ParagraphDown)),
+        (egui::KeyboardShortcut::new(egui::Modifiers::ALT | egui::Modifiers::SHIFT, egui::Key::ArrowLeft), Action::SelectJump(Jump::BlockStart)),
+        (egui::KeyboardShortcut::new(egui::Modifiers::ALT | egui::Modifiers::SHIFT, egui::Key::ArrowRight), Action::SelectJump(Jump::BlockEnd)),
+        (egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::ArrowUp), Action::Jump(Jump::ParagraphUp)),
+        (egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::ArrowDown), Action::Jump(Jump::ParagraphDown)),
+        (egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::ArrowLeft), Action::Jump(Jump::BlockStart)),
+        (egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::ArrowRight), Action::Jump(Jump::BlockEnd)),
+        (egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::A), Action::SelectAll),
+        (egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::U), Action::CheckUpdate),
+        (egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::L), Action::ToggleFollow),
+        (egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::T), Action::NewScratch),
+        (egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::R), Action::OpenScratch),
+        (egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::O), Action::FindFile),
+    ]
+}
+
 pub struct QuickNotepadApp {
     state: EditorState,
     show_shortcuts: bool,
@@ -12,18 +127,89 @@ pub struct QuickNotepadApp {
     dialog_has_focus: bool,
     show_update_dialog: bool,
     update_info: Option<UpdateInfo>,
+    update_job: Option<Job<Result<(), String>>>, // Download running behind the status bar progress
+    show_scratch_picker: bool,
+    scratch_notes: Vec<ScratchNote>,
+    archive_listing: Option<(String, Vec<String>)>, // Archive whose files are listed to pick from
+    todo_panel: Option<Vec<TodoItem>>,
+    calc_result: Option<Result<String, String>>,
+    palette: Option<(String, Vec<PaletteCommand>)>, // Query and all commands while open
+    finder: Option<(String, Vec<String>)>,          // Query and the project's files while open
+    checksum: Option<(HashAlgorithm, String)>,
+    align_prompt: Option<(String, (usize, usize))>, // Delimiter being typed and the lines to align
+    sequence_prompt: Option<String>,
+    path_prompt: Option<PathPrompt>,
+    compare: Option<CompareView>,
+    history: Option<HistoryView>,
+    detached: Vec<DetachedWindow>, // Tabs moved out into their own windows
+    windows_opened: u64,           // Keeps window ids unique
+    confirm_delete: bool, // Asking before moving the current file to the trash
+    usage_stats: Option<Stats>, // Totals shown in the Usage Statistics window
+    passphrase_dialog: Option<PassphraseMode>,
+    passphrase_input: String,
+    passphrase_confirm: String,
+    passphrase_error: Option<String>,
+    pending_save_path: Option<String>, // Save As target waiting for a passphrase
+    unlock_dismissed: bool,
 }
 
 impl QuickNotepadApp {
-    pub fn new(_cc: &eframe::CreationContext<'_>, file_path: Option<String>) -> Self {
+    pub fn new(_cc: &eframe::CreationContext<'_>, file_path: Option<String>, collab_role: Option<Role>) -> Self {
+        // An archive lists its files to pick from instead of opening as text
+        let archive = file_path.clone().filter(|path| archive::is_archive(path));
+        let mut state = EditorState::new(file_path.clone().filter(|path| !archive::is_archive(path) && !std::path::Path::new(path).is_dir()));
+        // A folder (the working directory by now) opens the file finder on it
+        let folder = file_path.as_deref().is_some_and(|path| std::path::Path::new(path).is_dir());
+        let finder = folder.then(|| (String::new(), project_files()));
+        let archive_listing = archive.and_then(|path| match archive::list(&path) {
+            Ok(names) => Some((path, names)),
+            Err(e) => {
+                state.toasts.error(format!("Cannot read {}: {}", path, e));
+                None
+            }
+        });
+        if let Some(role) = collab_role {
+            match state.tab_manager.start_collab(&role, &state.config) {
+                Ok(message) => state.toasts.info(message),
+                Err(e) => state.toasts.error(format!("Cannot start the shared session: {}", e)),
+            }
+        }
+        // First start of a new version puts its release notes in front
+        if let Some(notes) = whats_new::on_launch() {
+            let _ = state.tab_manager.open_read_only_tab(&notes.to_string_lossy());
+        }
         Self {
-            state: EditorState::new(file_path),
+            state,
             show_shortcuts: false,
             show_save_dialog: false,
             save_filename: String::new(),
             dialog_has_focus: false,
             show_update_dialog: false,
             update_info: None,
+            update_job: None,
+            show_scratch_picker: false,
+            scratch_notes: Vec::new(),
+            archive_listing,
+            todo_panel: None,
+            calc_result: None,
+            palette: None,
+            finder,
+            checksum: None,
+            align_prompt: None,
+            sequence_prompt: None,
+            path_prompt: None,
+            compare: None,
+            history: None,
+            detached: Vec::new(),
+            windows_opened: 0,
+            confirm_delete: false,
+            usage_stats: None,
+            passphrase_dialog: None,
+            passphrase_input: String::new(),
+            passphrase_confirm: String::new(),
+            passphrase_error: None,
+            pending_save_path: None,
+            unlock_dismissed: false,
         }
     }
 
@@ -31,12 +217,27 @@ impl QuickNotepadApp {
         egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
             egui::MenuBar::new().ui(ui, |ui| {
                 ui.menu_button("File", |ui| {
-                    if ui.button("📄 New (Ctrl+N)").clicked() {
+                    if ui.button(format!("📄 New ({}+N)", CMD)).clicked() {
                         self.handle_action(Action::New);
                         ui.close();
                     }
 
-                    if ui.button("💾 Save (Ctrl+S)").clicked() {
+                    if ui.button(format!("📝 New Scratch Note ({}+T)", CMD)).clicked() {
+                        self.handle_action(Action::NewScratch);
+                        ui.close();
+                    }
+
+                    if ui.button(format!("🗒 Scratch Notes... ({}+R)", CMD)).clicked() {
+                        self.handle_action(Action::OpenScratch);
+                        ui.close();
+                    }
+
+                    if ui.button(format!("🔍 Find File... ({}+O)", CMD)).clicked() {
+                        self.handle_action(Action::FindFile);
+                        ui.close();
+                    }
+
+                    if ui.button(format!("💾 Save ({}+S)", CMD)).clicked() {
                         self.handle_action(Action::Save);
                         ui.close();
                     }
@@ -46,67 +247,212 @@ impl QuickNotepadApp {
                         ui.close();
                     }
 
+                    if ui.button(format!("🔒 Save Encrypted... ({}+E)", CMD)).clicked() {
+                        self.handle_action(Action::SaveEncrypted);
+                        ui.close();
+                    }
+
                     ui.separator();
 
-                    if ui.button("❌ Quit (Ctrl+Q)").clicked() {
+                    if ui.button("🕘 File History").clicked() {
+                        self.handle_action(Action::FileHistory);
+                        ui.close();
+                    }
+
+                    if ui.button("🗑 Delete Current File").clicked() {
+                        self.handle_action(Action::DeleteFile);
+                        ui.close();
+                    }
+
+                    if ui.button("♻ Restore Deleted File").clicked() {
+                        self.handle_action(Action::RestoreDeletedFile);
+                        ui.close();
+                    }
+
+                    ui.separator();
+
+                    if ui.button(format!("❌ Quit ({}+Q)", CMD)).clicked() {
                         ctx.send_viewport_cmd(ViewportCommand::Close);
                         ui.close();
                     }
                 });
 
                 ui.menu_button("Edit", |ui| {
-                    if ui.button("↶ Undo (Ctrl+Z)").clicked() {
+                    if ui.button(format!("↶ Undo ({}+Z)", CMD)).clicked() {
                         self.handle_action(Action::Undo);
                         ui.close();
                     }
 
-                    if ui.button("↷ Redo (Ctrl+Y)").clicked() {
+                    if ui.button(format!("↷ Redo ({}+Y / {}+Shift+Z)", CMD, CMD)).clicked() {
                         self.handle_action(Action::Redo);
                         ui.close();
                     }
 
                     ui.separator();
 
-                    if ui.button("📋 Copy (Ctrl+C)").clicked() {
+                    if ui.button(format!("📋 Copy ({}+C)", CMD)).clicked() {
                         self.handle_action(Action::Copy);
                         ui.close();
                     }
 
-                    if ui.button("✂ Cut (Ctrl+X)").clicked() {
+                    if ui.button(format!("✂ Cut ({}+X)", CMD)).clicked() {
                         self.handle_action(Action::Cut);
                         ui.close();
                     }
 
-                    if ui.button("📄 Paste (Ctrl+V)").clicked() {
+                    if ui.button(format!("📄 Paste ({}+V)", CMD)).clicked() {
                         self.handle_action(Action::Paste);
                         ui.close();
                     }
 
                     ui.separator();
 
-                    if ui.button("🔍 Find (Ctrl+F)").clicked() {
+                    if ui.button(format!("🔍 Find ({}+F)", CMD)).clicked() {
                         self.handle_action(Action::Search);
                         ui.close();
                     }
 
-                    if ui.button("🔤 Select All (Ctrl+A)").clicked() {
+                    if ui.button("🔗 Open Link (Alt+O / Ctrl+Click)").clicked() {
+                        self.handle_action(Action::OpenLink);
+                        ui.close();
+                    }
+
+                    if ui.button("🧮 Evaluate Expression (Alt+=)").clicked() {
+                        self.handle_action(Action::Evaluate);
+                        ui.close();
+                    }
+
+                    if ui.button("📐 Align on Delimiter (Alt+A)").clicked() {
+                        self.handle_action(Action::AlignSelection);
+                        ui.close();
+                    }
+
+                    if ui.button("↩ Reflow Paragraph (Alt+Q)").clicked() {
+                        self.handle_action(Action::ReflowParagraph);
+                        ui.close();
+                    }
+
+                    if ui.button("🔢 Insert Number Sequence (Alt+N)").clicked() {
+                        self.handle_action(Action::InsertSequence);
+                        ui.close();
+                    }
+
+                    if ui.button("📥 Read File Into Buffer...").clicked() {
+                        self.handle_action(Action::ReadFile);
+                        ui.close();
+                    }
+
+                    if ui.button("📤 Write Selection to File...").clicked() {
+                        self.handle_action(Action::WriteSelection);
+                        ui.close();
+                    }
+
+                    if ui.button("📎 Append to File...").clicked() {
+                        self.handle_action(Action::AppendToFile);
+                        ui.close();
+                    }
+
+                    if ui.button("⇆ Compare Tabs (Alt+C)").clicked() {
+                        self.handle_action(Action::CompareTabs);
+                        ui.close();
+                    }
+
+                    ui.menu_button("⚔ Merge Conflicts", |ui| {
+                        if ui.button("Next Conflict (Alt+M)").clicked() {
+                            self.handle_action(Action::NextConflict);
+                            ui.close();
+                        }
+                        for resolution in Resolution::ALL {
+                            if ui.button(resolution.name()).clicked() {
+                                self.handle_action(Action::ResolveConflict(resolution));
+                                ui.close();
+                            }
+                        }
+                    });
+
+                    if ui.button("📂 Go to File (Alt+G)").clicked() {
+                        self.handle_action(Action::GoToFile);
+                        ui.close();
+                    }
+
+                    ui.separator();
+
+                    if ui.button(format!("☑ Toggle Checkbox ({}+D)", CMD)).clicked() {
+                        self.handle_action(Action::ToggleCheckbox);
+                        ui.close();
+                    }
+
+                    if ui.button("✔ Move Done Items (Alt+D)").clicked() {
+                        self.handle_action(Action::MoveDoneItems);
+                        ui.close();
+                    }
+
+                    if ui.button("▦ Format Table (Alt+T)").clicked() {
+                        self.handle_action(Action::FormatTable);
+                        ui.close();
+                    }
+
+                    if ui.button("⬆ Expand Selection (Alt+Up)").clicked() {
+                        self.handle_action(Action::ExpandSelection);
+                        ui.close();
+                    }
+
+                    if ui.button("⬇ Shrink Selection (Alt+Down)").clicked() {
+                        self.handle_action(Action::ShrinkSelection);
+                        ui.close();
+                    }
+
+                    if ui.button(format!("🔤 Select All ({}+A)", CMD)).clicked() {
                         self.handle_action(Action::SelectAll);
                         ui.close();
                     }
                 });
 
                 ui.menu_button("View", |ui| {
+                    if ui.button(format!("⌘ Command Palette ({}+P)", CMD)).clicked() {
+                        self.handle_action(Action::CommandPalette);
+                        ui.close();
+                    }
+
                     if ui.button("⌨ Shortcuts").clicked() {
                         self.show_shortcuts = !self.show_shortcuts;
                         ui.close();
                     }
+
+                    if ui.button("📌 List TODOs (Alt+L)").clicked() {
+                        self.handle_action(Action::ListTodos);
+                        ui.close();
+                    }
+
+                    if ui.button("▸ Fold / Unfold Block (Alt+F)").clicked() {
+                        self.handle_action(Action::ToggleFold);
+                        ui.close();
+                    }
+
+                    if ui.button("▾ Unfold All (Alt+U)").clicked() {
+                        self.handle_action(Action::UnfoldAll);
+                        ui.close();
+                    }
+
+                    if ui.button(format!("📜 Follow File ({}+L)", CMD)).clicked() {
+                        self.handle_action(Action::ToggleFollow);
+                        ui.close();
+                    }
                 });
                 
                 ui.menu_button("Help", |ui| {
-                    if ui.button("🔄 Check for Updates (Ctrl+U)").clicked() {
+                    if self.state.config.update_checks && ui.button(format!("🔄 Check for Updates ({}+U)", CMD)).clicked() {
                         self.handle_action(Action::CheckUpdate);
                         ui.close();
                     }
+                    if ui.button("↩ Roll Back Last Update").clicked() {
+                        self.handle_action(Action::RollbackUpdate);
+                        ui.close();
+                    }
+                    if ui.button("🐞 Report Issue").clicked() {
+                        self.handle_action(Action::ReportIssue);
+                        ui.close();
+                    }
                 });
 
                 ui.menu_button("Tabs", |ui| {
@@ -117,6 +463,13 @@ impl QuickNotepadApp {
                             ui.close();
                         }
                     }
+
+                    ui.separator();
+
+                    if ui.button("🗗 Open Tab in New Window").clicked() {
+                        self.handle_action(Action::DetachTab);
+                        ui.close();
+                    }
                 });
             });
         });
@@ -133,6 +486,22 @@ impl QuickNotepadApp {
                 };
                 ui.label(format!("{}{}", filename, dirty));
 
+                if self.state.is_following() {
+                    ui.label("[FOLLOW]");
+                } else if self.state.is_locked() {
+                    ui.label("[LOCKED]");
+                } else if self.state.is_read_only() {
+                    ui.label("[RO]");
+                } else if self.state.is_encrypted() {
+                    ui.label("[ENC]");
+                } else if self.state.is_shared() {
+                    ui.label("[SHARED]");
+                }
+                for peer in self.state.peers() {
+                    let (r, g, b) = peer.color();
+                    ui.colored_label(egui::Color32::from_rgb(r, g, b), &peer.name);
+                }
+
                 ui.separator();
 
                 ui.label(format!(
@@ -141,18 +510,27 @@ impl QuickNotepadApp {
                     self.state.cursor_pos.column + 1
                 ));
 
+                let update = self.update_job.as_ref().map(|job| (job.status(), &job.progress));
+                let loading = self.state.loading.as_ref().map(|(job, _)| (job.status(), &job.progress));
+                if let Some((status, progress)) = update.or(loading) {
+                    ui.separator();
+                    let fraction = progress.fraction();
+                    ui.add(
+                        egui::ProgressBar::new(fraction.unwrap_or(0.0))
+                            .desired_width(200.0)
+                            .text(status)
+                            .animate(fraction.is_none()),
+                    );
+                    if ui.small_button("Cancel").clicked() {
+                        progress.cancel();
+                    }
+                }
+
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                     ui.label("© Filip Domanski");
                     ui.separator();
 
-                    let line_count = self
-                        .state
-                        .current_buffer()
-                        .lines
-                        .iter()
-                        .rposition(|l| !l.is_empty())
-                        .map(|i| i + 1)
-                        .unwrap_or(1);
+                    let line_count = self.state.current_buffer().logical_len();
                     ui.label(format!("Lines: {}", line_count));
                 });
             });
@@ -165,22 +543,7 @@ impl QuickNotepadApp {
         }
 
         ctx.input_mut(|i| {
-            // Map egui shortcuts to our Action enum
-            let actions = vec![
-                (egui::KeyboardShortcut::new(egui::Modifiers::CTRL, egui::Key::S), Action::Save),
-                (egui::KeyboardShortcut::new(egui::Modifiers::CTRL, egui::Key::N), Action::New),
-                (egui::KeyboardShortcut::new(egui::Modifiers::CTRL, egui::Key::Q), Action::Quit),
-                (egui::KeyboardShortcut::new(egui::Modifiers::CTRL, egui::Key::Z), Action::Undo),
-                (egui::KeyboardShortcut::new(egui::Modifiers::CTRL, egui::Key::Y), Action::Redo),
-                (egui::KeyboardShortcut::new(egui::Modifiers::CTRL, egui::Key::C), Action::Copy),
-                (egui::KeyboardShortcut::new(egui::Modifiers::CTRL, egui::Key::X), Action::Cut),
-                (egui::KeyboardShortcut::new(egui::Modifiers::CTRL, egui::Key::V), Action::Paste),
-                (egui::KeyboardShortcut::new(egui::Modifiers::CTRL, egui::Key::F), Action::Search),
-                (egui::KeyboardShortcut::new(egui::Modifiers::CTRL, egui::Key::A), Action::SelectAll),
-                (egui::KeyboardShortcut::new(egui::Modifiers::CTRL, egui::Key::U), Action::CheckUpdate),
-            ];
-
-            for (shortcut, action) in actions {
+            for (shortcut, action) in shortcuts() {
                 if i.consume_shortcut(&shortcut) {
                     self.handle_action(action);
                 }
@@ -194,43 +557,86 @@ impl QuickNotepadApp {
                     7 => egui::Key::Num7, 8 => egui::Key::Num8, 9 => egui::Key::Num9,
                     _ => egui::Key::Num0,
                 };
-                if i.consume_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::CTRL, key)) {
+                if i.consume_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, key)) {
                     self.handle_action(Action::SwitchTab(num));
                 }
             }
         });
     }
 
+    // Editing shortcuts inside a detached window; dialogs and tab commands stay with the main window
+    fn handle_window_shortcuts(&mut self, ctx: &Context) {
+        ctx.input_mut(|i| {
+            for (shortcut, action) in shortcuts() {
+                let editing = matches!(
+                    action,
+                    Action::Undo | Action::Redo | Action::Copy | Action::Cut | Action::Paste | Action::SelectAll
+                ) || (action == Action::Save && self.state.current_filename().is_some());
+                if editing && i.consume_shortcut(&shortcut) {
+                    self.handle_action(action);
+                }
+            }
+        });
+    }
+
+    // Draw the detached windows, putting a tab back in the main window when its window closes
+    fn show_detached(&mut self, ctx: &Context) {
+        for mut window in std::mem::take(&mut self.detached) {
+            // The shared action handler works on `self.state`, so lend it the window's editor
+            let open = window.show(ctx, |ctx, state| {
+                std::mem::swap(&mut self.state, state);
+                self.handle_window_shortcuts(ctx);
+                std::mem::swap(&mut self.state, state);
+            });
+            if open {
+                self.detached.push(window);
+            } else {
+                self.state.tab_manager.attach_tab(window.into_tab());
+            }
+        }
+    }
+
     // Centralized action handler - uses the Action enum from core
     fn handle_action(&mut self, action: Action) {
         match action {
+            Action::Save | Action::SaveEncrypted if self.state.is_locked() => {}
             Action::Save => {
-                if self.state.current_filename().is_some() {
+                // Downloads are named after the URL but have nowhere to go yet
+                if self.state.current_filename().is_some() && !self.state.is_download() {
                     let _ = self.state.save();
                 } else {
                     self.show_save_dialog = true;
                     self.dialog_has_focus = true;
                 }
             }
+            Action::SaveEncrypted => {
+                self.open_passphrase_dialog(PassphraseMode::Encrypt);
+            }
             Action::New => {
                 self.state.tab_manager.new_tab();
             }
             Action::Quit => {
                 // Handle in update loop
             }
+            Action::Undo | Action::Redo if self.state.is_read_only() => {}
             Action::Undo => {
                 if let Some(op) = self.state.current_edit_history().undo() {
+                    self.state.stats.undo();
                     op.edit.reverse(&mut self.state.current_buffer_mut().lines);
+                    self.state.mark_dirty_unless_saved();
                 }
             }
             Action::Redo => {
                 if let Some(op) = self.state.current_edit_history().redo() {
                     op.edit.apply(&mut self.state.current_buffer_mut().lines);
+                    self.state.mark_dirty_unless_saved();
                 }
             }
-            Action::Copy => {
+            Action::Copy if self.state.selection.as_ref().is_some_and(|s| s.is_active()) => {
                 self.state.copy_selection();
+                self.state.toasts.info("Copied");
             }
+            Action::Copy => {}
             Action::Cut => {
                 self.state.cut_selection();
             }
@@ -241,21 +647,205 @@ impl QuickNotepadApp {
                 self.state.search_active = true;
                 self.dialog_has_focus = true;
             }
+            Action::RepeatSearch if self.state.search_query.is_empty() => self.handle_action(Action::Search),
+            Action::RepeatSearch => self.state.next_search_match(),
+            Action::ToggleCtrlShortcuts => self.show_shortcuts = !self.show_shortcuts,
+            Action::ToggleAutoWrap => {
+                let config = &mut self.state.config;
+                config.auto_wrap = !config.auto_wrap;
+                let message = match config.auto_wrap {
+                    true => format!("Auto wrap on at column {}", config.wrap_column),
+                    false => "Auto wrap off".to_string(),
+                };
+                self.state.toasts.info(message);
+            }
+            Action::RenameFile => match self.state.tab_manager.current_tab().filepath.is_some() {
+                true => {
+                    let mut prompt = PathPrompt::new(PathPurpose::Rename);
+                    prompt.path = self.state.current_filename().unwrap_or_default().to_string();
+                    self.path_prompt = Some(prompt);
+                    self.dialog_has_focus = true;
+                }
+                false => self.state.toasts.error("Save the file before renaming it"),
+            },
             Action::SelectAll => {
                 self.state.select_all();
             }
+            Action::ToggleCheckbox => {
+                self.state.toggle_checkbox();
+            }
+            Action::MoveDoneItems => {
+                self.state.move_done_items();
+            }
+            Action::FormatTable => {
+                self.state.format_table();
+            }
+            Action::OpenLink if !self.state.open_link_at(self.state.cursor_pos) => {
+                self.state.toasts.error("No link under the cursor");
+            }
+            Action::GoToFile if !self.state.go_to_file() => {
+                self.state.toasts.error("No existing file under the cursor");
+            }
+            Action::Evaluate => {
+                let (_, _, selected) = self.state.selection_or_line();
+                let expression = selected.trim_end().trim_end_matches('=');
+                self.calc_result = Some(
+                    calc::evaluate(expression)
+                        .map(calc::format_result)
+                        .map_err(|e| e.to_string()),
+                );
+            }
+            Action::AlignSelection => {
+                let (start, end) = self.state.align_range();
+                if start < end {
+                    let lines = &self.state.current_buffer().lines[start..end];
+                    self.align_prompt = Some((align::guess_delimiter(lines).to_string(), (start, end)));
+                    self.dialog_has_focus = true;
+                }
+            }
+            Action::ReflowParagraph => self.state.reflow_paragraph(),
+            Action::InsertSequence => {
+                self.sequence_prompt = Some("1 1".to_string());
+                self.dialog_has_focus = true;
+            }
+            Action::ReadFile => {
+                self.path_prompt = Some(PathPrompt::new(PathPurpose::ReadFile));
+                self.dialog_has_focus = true;
+            }
+            Action::AppendToFile => {
+                self.path_prompt = Some(PathPrompt::new(PathPurpose::Append));
+                self.dialog_has_focus = true;
+            }
+            Action::WriteSelection => {
+                if self.state.selected_text().is_some() {
+                    self.path_prompt = Some(PathPrompt::new(PathPurpose::WriteSelection));
+                    self.dialog_has_focus = true;
+                } else {
+                    self.state.toasts.error("Select some text first");
+                }
+            }
+            Action::NextConflict if !self.state.next_conflict() => {
+                self.state.toasts.info("No merge conflicts");
+            }
+            Action::ResolveConflict(resolution) => self.state.resolve_conflict(resolution),
+            Action::ToggleFold if !self.state.toggle_fold() => {
+                self.state.toasts.error("No block to fold here");
+            }
+            Action::UnfoldAll => self.state.unfold_all(),
+            Action::ExpandSelection => self.state.expand_selection(),
+            Action::ShrinkSelection => self.state.shrink_selection(),
+            Action::Jump(jump) => self.state.jump(jump, false),
+            Action::SelectJump(jump) => self.state.jump(jump, true),
+            Action::CompareTabs => {
+                let tabs = &mut self.state.tab_manager;
+                if tabs.tabs.len() < 2 {
+                    self.state.toasts.error("Open a second tab to compare with");
+                } else {
+                    let current = tabs.active_tab_index;
+                    let other = if current == 0 { 1 } else { current - 1 };
+                    self.compare = Some(CompareView::new(tabs, current, other));
+                }
+            }
+            Action::FileHistory => match self.state.tab_manager.current_tab().filepath.as_deref() {
+                Some(path) => self.history = Some(HistoryView::new(path)),
+                None => self.state.toasts.error("Save the file first to keep its history"),
+            },
+            Action::DeleteFile => {
+                if self.state.tab_manager.current_tab().filepath.is_some() {
+                    self.confirm_delete = true;
+                } else {
+                    self.state.toasts.error("This tab has no file to delete");
+                }
+            }
+            Action::RestoreDeletedFile => {
+                if let Err(e) = self.state.tab_manager.restore_trashed_file() {
+                    self.state.toasts.error(format!("Cannot restore file: {}", e));
+                }
+            }
+            Action::UsageStats => {
+                self.usage_stats = Some(self.state.stats.totals());
+            }
+            Action::CommandPalette => {
+                self.palette = Some((String::new(), palette::commands()));
+                self.dialog_has_focus = true;
+            }
+            Action::Transform(transform) => {
+                if let Err(e) = self.state.apply_transform(transform) {
+                    self.state.toasts.error(format!("{} failed: {}", transform.name(), e));
+                }
+            }
+            Action::Hash(algorithm) => {
+                let digest = algorithm.hex_digest(self.state.selection_or_file().as_bytes());
+                self.checksum = Some((algorithm, digest));
+            }
+            Action::ListTodos => {
+                self.todo_panel = Some(self.state.todos());
+            }
             Action::SwitchTab(num) => {
-                let _ = self.state.tab_manager.switch_to_tab(num);
+                if let Err(e) = self.state.tab_manager.switch_to_tab(num) {
+                    self.state.toasts.error(e.to_string());
+                }
+                self.unlock_dismissed = false;
+            }
+            Action::DetachTab => {
+                let index = self.state.tab_manager.active_tab_index;
+                match self.state.tab_manager.detach_tab(index) {
+                    Ok(tab) => {
+                        self.windows_opened += 1;
+                        self.detached.push(DetachedWindow::new(tab, self.windows_opened));
+                    }
+                    Err(e) => self.state.toasts.error(e.to_string()),
+                }
+                self.unlock_dismissed = false;
+            }
+            Action::ReportIssue => {
+                let report = IssueReport::new("GUI", &self.state.toasts.recent());
+                if links::open_url(&report.url()).is_ok() {
+                    self.state.toasts.info(REPORT_OPENED);
+                } else {
+                    self.state.copy_to_clipboard(report.text);
+                    self.state.toasts.info(REPORT_COPIED);
+                }
             }
+            Action::RollbackUpdate => match Updater::rollback() {
+                Ok(()) => self.state.toasts.info("Rolled back - restart to use the previous version"),
+                Err(e) => self.state.toasts.error(format!("Rollback failed: {}", e)),
+            },
             Action::CheckUpdate => {
                 self.check_for_updates_gui();
             }
+            Action::NewScratch => {
+                self.state.tab_manager.new_scratch_tab();
+            }
+            Action::FindFile => {
+                self.finder = Some((String::new(), project_files()));
+                self.dialog_has_focus = true;
+            }
+            Action::OpenScratch => match scratch::list_notes() {
+                Ok(notes) => {
+                    self.scratch_notes = notes;
+                    self.show_scratch_picker = true;
+                }
+                Err(e) => self.state.toasts.error(format!("Failed to list scratch notes: {}", e)),
+            },
+            Action::ToggleMouse => self.state.toasts.info("Mouse capture only applies to the terminal editor"),
+            Action::ToggleFollow => {
+                let tab = self.state.tab_manager.current_tab_mut();
+                if tab.is_following() {
+                    tab.stop_follow();
+                } else if !tab.read_only {
+                    self.state.toasts.error("Follow mode needs a read-only file (quick --read-only <file>)");
+                } else if let Err(e) = tab.start_follow() {
+                    self.state.toasts.error(format!("Cannot follow file: {}", e));
+                }
+            }
             _ => {}
         }
     }
 
     fn show_save_dialog(&mut self, ctx: &Context) {
         let mut close_dialog = false;
+        let mut save_path = None;
 
         egui::Window::new("Save As")
             .collapsible(false)
@@ -270,20 +860,19 @@ impl QuickNotepadApp {
                         response.request_focus();
                     }
 
-                    if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
-                        if !self.save_filename.is_empty() {
-                            let _ = self.state.save_as(&self.save_filename);
-                            close_dialog = true;
-                        }
+                    if response.lost_focus()
+                        && ui.input(|i| i.key_pressed(egui::Key::Enter))
+                        && !self.save_filename.is_empty()
+                    {
+                        save_path = Some(self.save_filename.clone());
+                        close_dialog = true;
                     }
                 });
 
                 ui.horizontal(|ui| {
-                    if ui.button("Save").clicked() {
-                        if !self.save_filename.is_empty() {
-                            let _ = self.state.save_as(&self.save_filename);
-                            close_dialog = true;
-                        }
+                    if ui.button("Save").clicked() && !self.save_filename.is_empty() {
+                        save_path = Some(self.save_filename.clone());
+                        close_dialog = true;
                     }
 
                     if ui.button("Cancel").clicked() {
@@ -301,43 +890,741 @@ impl QuickNotepadApp {
             self.save_filename.clear();
             self.dialog_has_focus = false;
         }
+
+        if let Some(path) = save_path {
+            self.save_to(&path);
+        }
     }
 
-    fn show_shortcuts_window(&mut self, ctx: &Context) {
-        use crate::core::shortcuts::Shortcuts;
-        
-        egui::Window::new("Keyboard Shortcuts")
-            .collapsible(true)
-            .resizable(true)
+    // Save to a path, asking for a passphrase first when it is a new .qnenc file
+    fn save_to(&mut self, path: &str) {
+        let path = &paths::resolve(path).to_string_lossy().into_owned();
+        if crypto::is_encrypted_path(path) && !self.state.is_encrypted() {
+            self.pending_save_path = Some(path.to_string());
+            self.open_passphrase_dialog(PassphraseMode::Encrypt);
+            return;
+        }
+
+        if let Err(e) = self.state.save_as(path) {
+            self.state.toasts.error(format!("Failed to save: {}", e));
+        }
+    }
+
+    fn open_passphrase_dialog(&mut self, mode: PassphraseMode) {
+        self.passphrase_dialog = Some(mode);
+        self.passphrase_input.clear();
+        self.passphrase_confirm.clear();
+        self.passphrase_error = None;
+        self.dialog_has_focus = true;
+    }
+
+    fn show_passphrase_dialog(&mut self, ctx: &Context, mode: PassphraseMode) {
+        let mut submit = false;
+        let mut cancel = false;
+
+        let title = match mode {
+            PassphraseMode::Unlock => "Unlock Encrypted Note",
+            PassphraseMode::Encrypt => "Encrypt Note",
+        };
+
+        egui::Window::new(title)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
             .show(ctx, |ui| {
-                egui::Grid::new("shortcuts_grid")
-                    .striped(true)
-                    .show(ui, |ui| {
-                        ui.label("Action");
-                        ui.label("Shortcut");
-                        ui.end_row();
+                if mode == PassphraseMode::Unlock {
+                    let name = self.state.current_filename().unwrap_or("note");
+                    ui.label(format!("Passphrase for {}:", name));
+                } else {
+                    ui.label("New passphrase:");
+                }
+                let response = ui.add(egui::TextEdit::singleline(&mut self.passphrase_input).password(true));
+                if self.dialog_has_focus && self.passphrase_input.is_empty() && self.passphrase_confirm.is_empty() {
+                    response.request_focus();
+                }
 
-                        // Get shortcuts from core Shortcuts module
-                        let shortcuts = Shortcuts::get_ctrl_shortcuts();
-                        
-                        for (shortcut, description) in shortcuts {
-                            ui.label(description);
-                            ui.label(shortcut);
-                            ui.end_row();
-                        }
-                    });
+                if mode == PassphraseMode::Encrypt {
+                    ui.label("Confirm passphrase:");
+                    ui.add(egui::TextEdit::singleline(&mut self.passphrase_confirm).password(true));
+                }
 
-                if ui.button("Close").clicked() {
-                    self.show_shortcuts = false;
+                if let Some(error) = &self.passphrase_error {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.button("OK").clicked() {
+                        submit = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel = true;
+                    }
+                });
+
+                if ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    submit = true;
+                }
+                if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    cancel = true;
                 }
             });
-    }
-    
-    fn show_update_dialog(&mut self, ctx: &Context) {
-        let mut close_dialog = false;
-        let mut perform_update = false;
-        
-        if let Some(info) = &self.update_info {
+
+        if submit && !self.passphrase_input.is_empty() {
+            match mode {
+                PassphraseMode::Unlock => {
+                    let passphrase = self.passphrase_input.clone();
+                    match self.state.tab_manager.current_tab_mut().unlock(&passphrase) {
+                        Ok(()) => {
+                            self.state.clamp_cursor();
+                            cancel = true;
+                        }
+                        Err(e) => self.passphrase_error = Some(e.to_string()),
+                    }
+                }
+                PassphraseMode::Encrypt => {
+                    if self.passphrase_input != self.passphrase_confirm {
+                        self.passphrase_error = Some("Passphrases do not match".to_string());
+                    } else {
+                        self.state.tab_manager.current_tab_mut().passphrase =
+                            Some(self.passphrase_input.clone());
+                        cancel = true;
+                        if let Some(path) = self.pending_save_path.take() {
+                            self.save_to(&path);
+                        } else {
+                            self.handle_action(Action::Save);
+                        }
+                    }
+                }
+            }
+        }
+
+        if cancel {
+            if mode == PassphraseMode::Unlock {
+                self.unlock_dismissed = true;
+            }
+            self.passphrase_dialog = None;
+            self.passphrase_input.clear();
+            self.passphrase_confirm.clear();
+            self.pending_save_path = None;
+            self.dialog_has_focus = self.show_save_dialog;
+        }
+    }
+
+    fn show_scratch_picker(&mut self, ctx: &Context) {
+        let mut open_path = None;
+        let mut close_picker = false;
+
+        egui::Window::new("Scratch Notes")
+            .collapsible(false)
+            .resizable(true)
+            .show(ctx, |ui| {
+                if self.scratch_notes.is_empty() {
+                    ui.label("No scratch notes yet (Ctrl+T creates one)");
+                }
+
+                egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    for note in &self.scratch_notes {
+                        if ui.button(format!("{}  —  {}", note.name, note.preview)).clicked() {
+                            open_path = Some(note.path.to_string_lossy().into_owned());
+                        }
+                    }
+                });
+
+                if ui.button("Close").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    close_picker = true;
+                }
+            });
+
+        if let Some(path) = open_path {
+            if let Err(e) = self.state.tab_manager.open_scratch_note(&path) {
+                self.state.toasts.error(format!("Failed to open scratch note: {}", e));
+            }
+            close_picker = true;
+        }
+
+        if close_picker {
+            self.show_scratch_picker = false;
+            self.scratch_notes.clear();
+        }
+    }
+
+    fn show_archive_listing(&mut self, ctx: &Context) {
+        let Some((path, names)) = &self.archive_listing else {
+            return;
+        };
+        let mut open_name = None;
+        let mut close_listing = false;
+        let title = std::path::Path::new(path).file_name().map_or(path.clone(), |n| n.to_string_lossy().into_owned());
+
+        egui::Window::new(title)
+            .collapsible(false)
+            .resizable(true)
+            .show(ctx, |ui| {
+                if names.is_empty() {
+                    ui.label("No files in this archive");
+                }
+
+                egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    for name in names {
+                        if ui.button(name).clicked() {
+                            open_name = Some(name.clone());
+                        }
+                    }
+                });
+
+                if ui.button("Close").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    close_listing = true;
+                }
+            });
+
+        // The listing stays open so several files can be picked in turn
+        if let Some(name) = open_name {
+            let member = Member { archive: path.clone(), name };
+            if let Err(e) = self.state.tab_manager.open_archive_member(member) {
+                self.state.toasts.error(format!("Cannot open the file: {}", e));
+            }
+        }
+
+        if close_listing {
+            self.archive_listing = None;
+        }
+    }
+
+    fn show_palette(&mut self, ctx: &Context) {
+        let Some((mut query, commands)) = self.palette.take() else {
+            return;
+        };
+        let mut chosen = None;
+        let mut close = false;
+
+        egui::Window::new("Command Palette")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 40.0])
+            .show(ctx, |ui| {
+                let input = ui.text_edit_singleline(&mut query);
+                input.request_focus();
+                let matches = palette::filter(&commands, &query);
+
+                if ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    chosen = matches.first().map(|command| command.action);
+                }
+                if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    close = true;
+                }
+
+                ui.separator();
+                egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    for command in &matches {
+                        let label = match &command.shortcut {
+                            Some(shortcut) => format!("{}    {}", command.name, shortcut),
+                            None => command.name.clone(),
+                        };
+                        if ui.selectable_label(false, label).clicked() {
+                            chosen = Some(command.action);
+                        }
+                    }
+                });
+            });
+
+        if chosen.is_none() && !close {
+            self.palette = Some((query, commands));
+            return;
+        }
+        self.dialog_has_focus = false;
+        if let Some(action) = chosen {
+            self.handle_action(action);
+        }
+    }
+
+    fn show_finder(&mut self, ctx: &Context) {
+        let Some((mut query, files)) = self.finder.take() else {
+            return;
+        };
+        let mut chosen = None;
+        let mut close = false;
+
+        egui::Window::new("Find File")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 40.0])
+            .show(ctx, |ui| {
+                let input = ui.text_edit_singleline(&mut query);
+                input.request_focus();
+                let matches = finder::filter(&files, &query);
+
+                if ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    chosen = matches.first().map(|path| path.to_string());
+                }
+                if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    close = true;
+                }
+
+                ui.separator();
+                if files.is_empty() {
+                    ui.label("No files in this folder");
+                }
+                egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    // The first hundred are plenty to pick from; typing narrows the rest
+                    for path in matches.iter().take(100) {
+                        if ui.selectable_label(false, path.as_str()).clicked() {
+                            chosen = Some(path.to_string());
+                        }
+                    }
+                });
+            });
+
+        if chosen.is_none() && !close {
+            self.finder = Some((query, files));
+            return;
+        }
+        self.dialog_has_focus = false;
+        let Some(path) = chosen.and_then(|path| Some(std::env::current_dir().ok()?.join(path))) else {
+            return;
+        };
+        let path = path.to_string_lossy().into_owned();
+        if archive::is_archive(&path) {
+            match archive::list(&path) {
+                Ok(names) => self.archive_listing = Some((path, names)),
+                Err(e) => self.state.toasts.error(format!("Cannot read {}: {}", path, e)),
+            }
+        } else {
+            self.state.open_path(path, None);
+        }
+    }
+
+    fn show_align_prompt(&mut self, ctx: &Context) {
+        let Some((mut delimiter, range)) = self.align_prompt.take() else {
+            return;
+        };
+        let mut apply = false;
+        let mut close = false;
+
+        egui::Window::new("Align on Delimiter")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Delimiter:");
+                    ui.text_edit_singleline(&mut delimiter).request_focus();
+                });
+                ui.horizontal(|ui| {
+                    apply = ui.button("Align").clicked() || ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    close = ui.button("Cancel").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape));
+                });
+            });
+
+        if apply && !delimiter.trim().is_empty() {
+            self.state.align_lines(range, delimiter.trim());
+        } else if !apply && !close {
+            self.align_prompt = Some((delimiter, range));
+            return;
+        }
+        self.dialog_has_focus = false;
+    }
+
+    fn show_path_prompt(&mut self, ctx: &Context) {
+        let Some(mut prompt) = self.path_prompt.take() else {
+            return;
+        };
+        let mut apply = false;
+        let mut close = false;
+        let (title, button) = match prompt.purpose {
+            PathPurpose::ReadFile => ("Read File Into Buffer", "Insert"),
+            PathPurpose::WriteSelection => ("Write Selection to File", "Write"),
+            PathPurpose::Append => ("Append to File", "Append"),
+            PathPurpose::Rename => ("Rename File", "Rename"),
+        };
+
+        egui::Window::new(title)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                // Tab completes the path instead of moving focus
+                let complete = ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Tab));
+                if complete {
+                    (prompt.path, prompt.matches) = completion::complete_path(&prompt.path);
+                    prompt.overwrite = false;
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Path:");
+                    let mut output = egui::TextEdit::singleline(&mut prompt.path).desired_width(400.0).show(ui);
+                    if complete {
+                        let end = egui::text::CCursor::new(prompt.path.chars().count());
+                        output.state.cursor.set_char_range(Some(egui::text::CCursorRange::one(end)));
+                        output.state.store(ui.ctx(), output.response.id);
+                    }
+                    if output.response.changed() {
+                        prompt.overwrite = false;
+                    }
+                    output.response.request_focus();
+                });
+                if prompt.matches.len() > 1 {
+                    ui.label(prompt.matches.join("  "));
+                }
+                if prompt.overwrite {
+                    ui.colored_label(egui::Color32::from_rgb(220, 160, 60), "File exists, write again to overwrite it");
+                }
+                ui.horizontal(|ui| {
+                    let label = if prompt.overwrite { "Overwrite" } else { button };
+                    apply = ui.button(label).clicked() || ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    close = ui.button("Cancel").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape));
+                });
+            });
+
+        let path = prompt.path.trim().to_string();
+        if apply && !path.is_empty() {
+            match prompt.purpose {
+                PathPurpose::ReadFile => {
+                    if let Err(e) = self.state.read_file_at_cursor(&path) {
+                        self.state.toasts.error(format!("Cannot read {}: {}", path, e));
+                    }
+                }
+                PathPurpose::WriteSelection => {
+                    // The first attempt on an existing file only asks for confirmation
+                    if !prompt.overwrite && paths::resolve(&path).exists() {
+                        prompt.overwrite = true;
+                        self.path_prompt = Some(prompt);
+                        return;
+                    }
+                    let text = self.state.selected_text().unwrap_or_default();
+                    if let Err(e) = completion::write_text(&path, &text) {
+                        self.state.toasts.error(format!("Cannot write {}: {}", path, e));
+                    }
+                }
+                PathPurpose::Rename => match self.state.tab_manager.rename_current_file(&path) {
+                    Ok(path) => self.state.toasts.info(format!("Renamed to {}", path)),
+                    Err(e) => self.state.toasts.error(format!("Cannot rename: {}", e)),
+                },
+                PathPurpose::Append => {
+                    let text = self.state.selection_or_file();
+                    if let Err(e) = append::append_to_file(&path, &text, &self.state.config.append_separator) {
+                        self.state.toasts.error(format!("Cannot append to {}: {}", path, e));
+                    }
+                }
+            }
+        } else if !apply && !close {
+            self.path_prompt = Some(prompt);
+            return;
+        }
+        self.dialog_has_focus = false;
+    }
+
+    fn show_sequence_prompt(&mut self, ctx: &Context) {
+        let Some(mut spec) = self.sequence_prompt.take() else {
+            return;
+        };
+        let mut apply = false;
+        let mut close = false;
+        let parsed = Sequence::parse(&spec);
+
+        egui::Window::new("Insert Number Sequence")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Start [step [width]]:");
+                    ui.text_edit_singleline(&mut spec).request_focus();
+                });
+                match &parsed {
+                    Ok(sequence) => ui.weak(format!("{}, {}, {} …", sequence.nth(0), sequence.nth(1), sequence.nth(2))),
+                    Err(e) => ui.colored_label(egui::Color32::RED, e.to_string()),
+                };
+                ui.horizontal(|ui| {
+                    apply = ui.button("Insert").clicked() || ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    close = ui.button("Cancel").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape));
+                });
+            });
+
+        if apply {
+            if let Ok(sequence) = parsed {
+                if !self.state.insert_sequence(&sequence) {
+                    self.state.toasts.error("Select the lines to number first");
+                }
+            }
+        } else if !close {
+            self.sequence_prompt = Some(spec);
+            return;
+        }
+        self.dialog_has_focus = false;
+    }
+
+    fn show_checksum(&mut self, ctx: &Context) {
+        let Some((algorithm, digest)) = self.checksum.clone() else {
+            return;
+        };
+        let mut close = false;
+
+        egui::Window::new(format!("{} checksum", algorithm.name()))
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.monospace(&digest);
+                ui.horizontal(|ui| {
+                    if ui.button("Copy").clicked() {
+                        self.state.copy_to_clipboard(digest.clone());
+                        close = true;
+                    }
+                    let editable = !self.state.is_read_only();
+                    if ui.add_enabled(editable, egui::Button::new("Insert")).clicked() {
+                        let pos = self.state.cursor_pos;
+                        self.state.replace_text(pos, pos, &digest);
+                        close = true;
+                    }
+                    if ui.button("Close").clicked() {
+                        close = true;
+                    }
+                });
+                if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    close = true;
+                }
+            });
+
+        if close {
+            self.checksum = None;
+        }
+    }
+
+    fn show_usage_stats(&mut self, ctx: &Context) {
+        let Some(stats) = &self.usage_stats else {
+            return;
+        };
+        let mut open = true;
+
+        egui::Window::new("Usage Statistics")
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                egui::Grid::new("usage_stats").num_columns(2).show(ui, |ui| {
+                    for (label, value) in stats.summary() {
+                        ui.label(label);
+                        ui.label(value);
+                        ui.end_row();
+                    }
+                });
+                ui.label("Kept on this computer only, never sent anywhere.");
+            });
+
+        if !open || ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.usage_stats = None;
+        }
+    }
+
+    // The config or session file could not be parsed and was reset: say what happened
+    fn show_load_warnings(&mut self, ctx: &Context) {
+        let warnings: Vec<String> = [&self.state.config.load_warning, &self.state.tab_manager.load_warning]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect();
+        let mut close = false;
+
+        egui::Window::new("Settings Reset")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                for warning in &warnings {
+                    ui.label(warning);
+                }
+                if ui.button("OK").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    close = true;
+                }
+            });
+
+        if close {
+            self.state.config.load_warning = None;
+            self.state.tab_manager.load_warning = None;
+        }
+    }
+
+    // The file is open in another instance: stay read-only or take over its lock
+    fn show_file_lock_warning(&mut self, ctx: &Context) {
+        let index = self.state.tab_manager.active_tab_index;
+        let tab = self.state.tab_manager.current_tab_mut();
+        let Some(holder) = tab.lock_holder.clone() else {
+            return;
+        };
+        let name = tab.display_name(index);
+
+        egui::Window::new("File In Use")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!("{} is open in {}.", name, holder.describe()));
+                ui.label("Saving here could overwrite changes made there.");
+                ui.horizontal(|ui| {
+                    if ui.button("Open Read-Only").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                        tab.lock_warned = true;
+                    }
+                    if ui.button("Steal Lock").clicked() {
+                        if let Err(e) = tab.steal_lock() {
+                            self.state.toasts.error(format!("Cannot take the file lock: {}", e));
+                        }
+                        tab.lock_warned = true;
+                    }
+                });
+            });
+    }
+
+    fn show_delete_confirm(&mut self, ctx: &Context) {
+        let tab = self.state.tab_manager.current_tab();
+        let name = tab.filename.clone().unwrap_or_default();
+        let unsaved = tab.has_unsaved_changes;
+        let mut close = false;
+
+        egui::Window::new("Delete File")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!("Move {} to the trash?", name));
+                if unsaved {
+                    ui.label("Unsaved changes will be lost.");
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Move to Trash").clicked() {
+                        if let Err(e) = self.state.tab_manager.trash_current_file() {
+                            self.state.toasts.error(format!("Cannot delete file: {}", e));
+                        }
+                        close = true;
+                    }
+                    if ui.button("Cancel").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                        close = true;
+                    }
+                });
+            });
+
+        if close {
+            self.confirm_delete = false;
+        }
+    }
+
+    fn show_calc_result(&mut self, ctx: &Context) {
+        let Some(result) = self.calc_result.clone() else {
+            return;
+        };
+        let mut close = false;
+
+        egui::Window::new("Calculator")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                match &result {
+                    Ok(value) => {
+                        ui.heading(format!("= {}", value));
+                        ui.horizontal(|ui| {
+                            let editable = !self.state.is_read_only();
+                            if ui.add_enabled(editable, egui::Button::new("Append")).clicked() {
+                                let (_, end, selected) = self.state.selection_or_line();
+                                let appended = calc::appended_result(&selected, value);
+                                self.state.replace_text(end, end, &appended);
+                                close = true;
+                            }
+                            if ui.add_enabled(editable, egui::Button::new("Replace")).clicked() {
+                                let (start, end, _) = self.state.selection_or_line();
+                                self.state.replace_text(start, end, value);
+                                close = true;
+                            }
+                            if ui.button("Close").clicked() {
+                                close = true;
+                            }
+                        });
+                    }
+                    Err(message) => {
+                        ui.colored_label(egui::Color32::RED, message);
+                        if ui.button("Close").clicked() {
+                            close = true;
+                        }
+                    }
+                }
+                if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    close = true;
+                }
+            });
+
+        if close {
+            self.calc_result = None;
+        }
+    }
+
+    fn show_todo_panel(&mut self, ctx: &Context) {
+        let mut jump = None;
+        let mut close_panel = false;
+
+        egui::SidePanel::right("todo_panel").resizable(true).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.heading("TODOs");
+                if ui.button("⟳").on_hover_text("Refresh").clicked() {
+                    self.todo_panel = Some(self.state.todos());
+                }
+                if ui.button("✖").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    close_panel = true;
+                }
+            });
+            ui.separator();
+
+            let todos = self.todo_panel.as_deref().unwrap_or_default();
+            if todos.is_empty() {
+                ui.label(format!("No {} found", self.state.config.todo_keywords.join("/")));
+            }
+
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for todo in todos {
+                    let label = format!("{}  {}: {}", todo.line + 1, todo.keyword, todo.text);
+                    if ui.selectable_label(false, label).clicked() {
+                        jump = Some(TextPosition {
+                            line: todo.line,
+                            column: todo.column,
+                        });
+                    }
+                }
+            });
+        });
+
+        if let Some(pos) = jump {
+            self.state.jump_to(pos);
+        }
+        if close_panel {
+            self.todo_panel = None;
+        }
+    }
+
+    fn show_shortcuts_window(&mut self, ctx: &Context) {
+        use crate::core::shortcuts::Shortcuts;
+        
+        egui::Window::new("Keyboard Shortcuts")
+            .collapsible(true)
+            .resizable(true)
+            .show(ctx, |ui| {
+                egui::Grid::new("shortcuts_grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("Action");
+                        ui.label("Shortcut");
+                        ui.end_row();
+
+                        // Get shortcuts from core Shortcuts module
+                        let shortcuts = Shortcuts::get_ctrl_shortcuts(&crate::core::commands::registry());
+                        
+                        for (shortcut, description) in shortcuts {
+                            ui.label(description);
+                            ui.label(shortcut);
+                            ui.end_row();
+                        }
+                    });
+
+                if ui.button("Close").clicked() {
+                    self.show_shortcuts = false;
+                }
+            });
+    }
+    
+    fn show_update_dialog(&mut self, ctx: &Context) {
+        let mut close_dialog = false;
+        let mut perform_update = false;
+        
+        if let Some(info) = &self.update_info {
             egui::Window::new("Update Available")
                 .collapsible(false)
                 .resizable(false)
@@ -370,18 +1657,10 @@ impl QuickNotepadApp {
         
         if perform_update {
             // Perform update in background
-            use crate::core::updater::Updater;
-            let updater = Updater::new();
-            
-            match updater.perform_update() {
-                Ok(_) => {
-                    eprintln!("Update successful! Please restart the application.");
-                    ctx.send_viewport_cmd(ViewportCommand::Close);
-                }
-                Err(e) => {
-                    eprintln!("Update failed: {}", e);
-                }
-            }
+            let updater = Updater::new(&self.state.config);
+            self.update_job = Some(Job::spawn("Downloading update", move |progress| {
+                updater.perform_update(progress).map_err(|e| e.to_string())
+            }));
         }
         
         if close_dialog {
@@ -390,10 +1669,30 @@ impl QuickNotepadApp {
         }
     }
 
+    // Finish background jobs, repainting while any is still running so its bar moves
+    fn poll_jobs(&mut self, ctx: &Context) {
+        if let Some(result) = self.update_job.as_ref().and_then(Job::poll) {
+            self.update_job = None;
+            match result {
+                Ok(()) => {
+                    self.state.toasts.info("Update successful! Please restart the application.");
+                    ctx.send_viewport_cmd(ViewportCommand::Close);
+                }
+                Err(e) => self.state.toasts.error(format!("Update failed: {}", e)),
+            }
+        }
+        self.state.poll_loading();
+        if self.update_job.is_some() || self.state.loading.is_some() {
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
+    }
+
     fn check_for_updates_gui(&mut self) {
-        use crate::core::updater::Updater;
-        
-        let updater = Updater::new();
+        if !self.state.config.update_checks {
+            self.state.toasts.info("Update checks are turned off in the config");
+            return;
+        }
+        let updater = Updater::new(&self.state.config);
         
         match updater.check_for_updates() {
             Ok(info) => {
@@ -401,11 +1700,11 @@ impl QuickNotepadApp {
                     self.show_update_dialog = true;
                     self.update_info = Some(info);
                 } else {
-                    eprintln!("No updates available. Running version {}", info.current_version);
+                    self.state.toasts.info(format!("No updates available. Running version {}", info.current_version));
                 }
             }
             Err(e) => {
-                eprintln!("Failed to check for updates: {}", e);
+                self.state.toasts.error(format!("Failed to check for updates: {}", e));
             }
         }
     }
@@ -414,13 +1713,35 @@ impl QuickNotepadApp {
 impl eframe::App for QuickNotepadApp {
     fn update(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
         themes::apply_theme(ctx);
+        let keystrokes = ctx.input(|i| {
+            i.events.iter().filter(|event| matches!(event, egui::Event::Key { pressed: true, .. })).count()
+        });
+        self.state.stats.keystrokes(keystrokes);
         self.handle_shortcuts(ctx);
+        self.poll_jobs(ctx);
+
+        // Followed files are polled on a timer rather than every frame
+        if self.state.is_following() {
+            self.state.poll_follow();
+            ctx.request_repaint_after(std::time::Duration::from_millis(250));
+        }
+        // Shared tabs trade edits with the others a few times a second
+        if self.state.is_shared() {
+            self.state.sync_collab();
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
         self.menu_bar(ctx);
         self.status_bar(ctx);
+        if self.todo_panel.is_some() {
+            self.show_todo_panel(ctx);
+        }
 
         egui::CentralPanel::default().show(ctx, |ui| {
             EditorPanel::new(&mut self.state, !self.dialog_has_focus).show(ui);
         });
+        self.state.autosave_scratch();
+        toasts::show(ctx, &mut self.state.toasts);
+        self.show_detached(ctx);
 
         if self.show_save_dialog {
             self.show_save_dialog(ctx);
@@ -429,9 +1750,99 @@ impl eframe::App for QuickNotepadApp {
         if self.show_shortcuts {
             self.show_shortcuts_window(ctx);
         }
+
+        if self.show_scratch_picker {
+            self.show_scratch_picker(ctx);
+        }
+
+        if self.archive_listing.is_some() {
+            self.show_archive_listing(ctx);
+        }
+
+        if self.calc_result.is_some() {
+            self.show_calc_result(ctx);
+        }
+
+        if self.palette.is_some() {
+            self.show_palette(ctx);
+        }
+
+        if self.finder.is_some() {
+            self.show_finder(ctx);
+        }
+
+        if self.checksum.is_some() {
+            self.show_checksum(ctx);
+        }
+
+        if self.confirm_delete {
+            self.show_delete_confirm(ctx);
+        }
+
+        if self.align_prompt.is_some() {
+            self.show_align_prompt(ctx);
+        }
+
+        if self.sequence_prompt.is_some() {
+            self.show_sequence_prompt(ctx);
+        }
+
+        if self.path_prompt.is_some() {
+            self.show_path_prompt(ctx);
+        }
+
+        if let Some(compare) = &mut self.compare {
+            if !compare.show(ctx, &mut self.state.tab_manager) {
+                self.compare = None;
+            }
+        }
+
+        if let Some(history) = &mut self.history {
+            let tab = self.state.tab_manager.current_tab();
+            let current = || tab.buffer.content().split('\n').map(String::from).collect();
+            let (open, restore) = history.show(ctx, tab.passphrase.as_deref(), current);
+            if let Some(content) = restore {
+                self.state.restore_version(&content);
+            }
+            if !open {
+                self.history = None;
+            }
+        }
+
+        let tab = self.state.tab_manager.current_tab();
+        if tab.lock_holder.is_some() && !tab.lock_warned {
+            self.show_file_lock_warning(ctx);
+        }
+
+        if self.state.config.load_warning.is_some() || self.state.tab_manager.load_warning.is_some() {
+            self.show_load_warnings(ctx);
+        }
+
+        if self.usage_stats.is_some() {
+            self.show_usage_stats(ctx);
+        }
+
+        // Locked notes ask for their passphrase as soon as they are shown
+        if self.state.is_locked() && self.passphrase_dialog.is_none() && !self.unlock_dismissed {
+            self.open_passphrase_dialog(PassphraseMode::Unlock);
+        }
+
+        if let Some(mode) = self.passphrase_dialog {
+            self.show_passphrase_dialog(ctx, mode);
+        }
         
         if self.show_update_dialog {
             self.show_update_dialog(ctx);
         }
     }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        // Detached tabs go back into the session
+        for window in std::mem::take(&mut self.detached) {
+            self.state.tab_manager.attach_tab(window.into_tab());
+        }
+        if self.state.config.usage_stats {
+            let _ = self.state.stats.flush();
+        }
+    }
 }
\ No newline at end of file
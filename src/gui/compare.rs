@@ -0,0 +1,182 @@
+// compare - side-by-side diff window for two open tabs
+use crate::core::diff::{self, DiffRow, RowKind};
+use crate::core::tabs::TabManager;
+use egui::{text::LayoutJob, Color32, FontId, TextFormat};
+
+const REMOVED_BG: Color32 = Color32::from_rgb(70, 25, 25);
+const REMOVED_CHANGE_BG: Color32 = Color32::from_rgb(140, 40, 40);
+const ADDED_BG: Color32 = Color32::from_rgb(20, 60, 25);
+const ADDED_CHANGE_BG: Color32 = Color32::from_rgb(35, 120, 45);
+
+pub struct CompareView {
+    pub left: usize, // Tab indices
+    pub right: usize,
+    left_lines: Vec<String>,
+    right_lines: Vec<String>,
+    rows: Vec<DiffRow>,
+    changes: Vec<usize>,
+    current: usize,      // Index into `changes`
+    jump: Option<usize>, // Row to scroll to on the next frame
+}
+
+impl CompareView {
+    pub fn new(tabs: &mut TabManager, left: usize, right: usize) -> Self {
+        let mut view = Self {
+            left,
+            right,
+            left_lines: Vec::new(),
+            right_lines: Vec::new(),
+            rows: Vec::new(),
+            changes: Vec::new(),
+            current: 0,
+            jump: None,
+        };
+        view.refresh(tabs);
+        view
+    }
+
+    fn refresh(&mut self, tabs: &mut TabManager) {
+        // Tabs restored from the session may not have been read yet
+        for index in [self.left, self.right] {
+            if let Err(e) = tabs.load_tab(index) {
+                eprintln!("{}", e);
+            }
+        }
+        let lines = |index: usize| tabs.tabs[index].buffer.content().split('\n').map(String::from).collect();
+        self.left_lines = lines(self.left);
+        self.right_lines = lines(self.right);
+        self.rows = diff::side_by_side(&self.left_lines, &self.right_lines);
+        self.changes = diff::change_starts(&self.rows);
+        self.current = 0;
+        self.jump = self.changes.first().copied();
+    }
+
+    // Returns false once the window is closed
+    pub fn show(&mut self, ctx: &egui::Context, tabs: &mut TabManager) -> bool {
+        if self.left.max(self.right) >= tabs.tabs.len() {
+            return false; // A compared tab was closed
+        }
+        let mut open = true;
+        let mut refresh = false;
+        let names: Vec<String> = tabs.tabs.iter().enumerate().map(|(i, tab)| tab.display_name(i)).collect();
+
+        egui::Window::new("Compare Tabs")
+            .open(&mut open)
+            .default_size([1000.0, 600.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    refresh |= tab_picker(ui, "compare_left", &names, &mut self.left);
+                    ui.label("↔");
+                    refresh |= tab_picker(ui, "compare_right", &names, &mut self.right);
+                    ui.separator();
+
+                    let count = self.changes.len();
+                    if ui.add_enabled(self.current > 0, egui::Button::new("⏶ Previous")).clicked() {
+                        self.current -= 1;
+                        self.jump = Some(self.changes[self.current]);
+                    }
+                    if ui.add_enabled(self.current + 1 < count, egui::Button::new("⏷ Next")).clicked() {
+                        self.current += 1;
+                        self.jump = Some(self.changes[self.current]);
+                    }
+                    if count == 0 {
+                        ui.label("Tabs are identical");
+                    } else {
+                        ui.label(format!("Change {}/{}", self.current + 1, count));
+                    }
+                });
+                ui.separator();
+                show_rows(ui, &self.rows, (&self.left_lines, &self.right_lines), self.jump.take());
+            });
+
+        if refresh {
+            self.refresh(tabs);
+        }
+        open && !ctx.input(|i| i.key_pressed(egui::Key::Escape))
+    }
+
+}
+
+// Diff rows side by side, optionally scrolled to `jump` (also used by the file history window)
+pub fn show_rows(ui: &mut egui::Ui, rows: &[DiffRow], lines: (&[String], &[String]), jump: Option<usize>) {
+    let font = FontId::monospace(13.0);
+    let row_height = ui.fonts_mut(|f| f.row_height(&font)) + 2.0;
+    let half = (ui.available_width() - 12.0) / 2.0;
+
+    let mut scroll = egui::ScrollArea::both().auto_shrink([false, false]);
+    if let Some(row) = jump {
+        // Keep a few rows of context above the change
+        let spaced = row_height + ui.spacing().item_spacing.y;
+        scroll = scroll.vertical_scroll_offset((row.saturating_sub(3) as f32) * spaced);
+    }
+
+    scroll.show_rows(ui, row_height, rows.len(), |ui, range| {
+        for row in &rows[range] {
+            let left = row.left.map(|idx| lines.0[idx].as_str());
+            let right = row.right.map(|idx| lines.1[idx].as_str());
+            let (left_span, right_span) = match (row.kind, left, right) {
+                (RowKind::Changed, Some(l), Some(r)) => {
+                    let (a, b) = diff::inline_change(l, r);
+                    (Some(a), Some(b))
+                }
+                _ => (None, None),
+            };
+            let changed = row.kind != RowKind::Same;
+
+            let left_job =
+                side_job(row.left, left, &font, changed.then_some(REMOVED_BG), left_span, REMOVED_CHANGE_BG);
+            let right_job =
+                side_job(row.right, right, &font, changed.then_some(ADDED_BG), right_span, ADDED_CHANGE_BG);
+
+            ui.horizontal(|ui| {
+                ui.add_sized([half, row_height], egui::Label::new(left_job).truncate());
+                ui.separator();
+                ui.add_sized([half, row_height], egui::Label::new(right_job).truncate());
+            });
+        }
+    });
+}
+
+fn tab_picker(ui: &mut egui::Ui, id: &str, names: &[String], selected: &mut usize) -> bool {
+    let before = *selected;
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(names.get(*selected).cloned().unwrap_or_default())
+        .show_ui(ui, |ui| {
+            for (i, name) in names.iter().enumerate() {
+                ui.selectable_value(selected, i, name);
+            }
+        });
+    *selected != before
+}
+
+// Line number plus text, with the changed part of the line on a stronger background
+fn side_job(
+    number: Option<usize>,
+    text: Option<&str>,
+    font: &FontId,
+    background: Option<Color32>,
+    span: Option<(usize, usize)>,
+    span_background: Color32,
+) -> LayoutJob {
+    let mut job = LayoutJob::default();
+    let gutter = number.map(|idx| format!("{:>4} ", idx + 1)).unwrap_or_else(|| "     ".to_string());
+    job.append(&gutter, 0.0, TextFormat::simple(font.clone(), Color32::from_rgb(180, 160, 60)));
+
+    let Some(text) = text else {
+        return job;
+    };
+    let base = TextFormat {
+        font_id: font.clone(),
+        color: Color32::from_gray(220),
+        background: background.filter(|_| number.is_some()).unwrap_or(Color32::TRANSPARENT),
+        ..Default::default()
+    };
+    let chars: Vec<char> = text.chars().collect();
+    let (start, end) = span.unwrap_or((chars.len(), chars.len()));
+    let piece = |range: std::ops::Range<usize>| chars[range].iter().collect::<String>();
+
+    job.append(&piece(0..start), 0.0, base.clone());
+    job.append(&piece(start..end), 0.0, TextFormat { background: span_background, ..base.clone() });
+    job.append(&piece(end..chars.len()), 0.0, base);
+    job
+}
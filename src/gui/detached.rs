@@ -0,0 +1,43 @@
+// detached - a tab moved out into its own OS window, e.g. to put it on another monitor
+use super::{editor::EditorPanel, state::EditorState, toasts};
+use crate::core::tabs::Tab;
+use egui::{Context, ViewportBuilder, ViewportId};
+
+pub struct DetachedWindow {
+    id: ViewportId,
+    pub state: EditorState, // Cursor, selection and search of this window, holding the tab itself
+}
+
+impl DetachedWindow {
+    pub fn new(tab: Tab, serial: u64) -> Self {
+        Self {
+            id: ViewportId::from_hash_of(("detached", serial)),
+            state: EditorState::for_tab(tab),
+        }
+    }
+
+    pub fn into_tab(self) -> Tab {
+        self.state.into_tab()
+    }
+
+    // Draw the window; `shortcuts` runs first with this window's input and state.
+    // Returns false once the window is closed
+    pub fn show(&mut self, ctx: &Context, mut shortcuts: impl FnMut(&Context, &mut EditorState)) -> bool {
+        let tab = self.state.tab_manager.current_tab();
+        let dirty = if tab.has_unsaved_changes { "*" } else { "" };
+        let title = format!("{}{} - Quick Notepad", tab.filename.as_deref().unwrap_or("[No Name]"), dirty);
+        let builder = ViewportBuilder::default().with_title(title).with_inner_size([900.0, 700.0]);
+
+        let mut open = true;
+        ctx.show_viewport_immediate(self.id, builder, |ctx, _| {
+            shortcuts(ctx, &mut self.state);
+            egui::CentralPanel::default().show(ctx, |ui| {
+                EditorPanel::new(&mut self.state, true).show(ui);
+            });
+            self.state.autosave_scratch();
+            toasts::show(ctx, &mut self.state.toasts);
+            open = !ctx.input(|i| i.viewport().close_requested());
+        });
+        open
+    }
+}
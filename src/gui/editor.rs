@@ -1,7 +1,15 @@
 // src/gui/editor.rs - Editor with proper clipboard handling
 use super::state::EditorState;
+use crate::core::{
+    bidi,
+    colors,
+    display,
+    conflicts::Region,
+    links, markdown, symbols,
+};
 use crate::core::selection::{Selection, TextPosition};
-use egui::{Color32, FontId, Pos2, Rect, Response, Sense, Stroke, Ui};
+use egui::{Color32, FontId, MouseWheelUnit, Pos2, Rect, Response, Sense, Stroke, Ui};
+use unicode_width::UnicodeWidthStr;
 
 pub struct EditorPanel<'a> {
     state: &'a mut EditorState,
@@ -17,12 +25,18 @@ impl<'a> EditorPanel<'a> {
         if self.state.search_active {
             self.show_search_bar(ui);
         }
+        self.state.sync_tab_position();
+        self.state.sync_folds();
+        self.state.refresh_outline();
+        self.show_breadcrumbs(ui);
 
         let available_rect = ui.available_rect_before_wrap();
         let response = ui.allocate_rect(available_rect, Sense::click_and_drag());
 
         if self.accepts_input {
             self.handle_input(ui, &response);
+            // Input may have edited the text since the breadcrumbs were drawn
+            self.state.refresh_outline();
         }
 
         self.render_content(ui, &response, available_rect);
@@ -30,6 +44,16 @@ impl<'a> EditorPanel<'a> {
         response
     }
 
+    // File name followed by the functions, types or headings enclosing the cursor
+    fn show_breadcrumbs(&self, ui: &mut Ui) {
+        let tab = self.state.tab_manager.current_tab();
+        let scope = symbols::breadcrumbs(self.state.symbols(), self.state.cursor_pos.line);
+        let name = tab.filename.as_deref().unwrap_or("untitled");
+        let text = if scope.is_empty() { name.to_string() } else { format!("{} › {}", name, scope) };
+        ui.label(egui::RichText::new(text).monospace().size(12.0).weak());
+        ui.separator();
+    }
+
     fn show_search_bar(&mut self, ui: &mut Ui) {
         ui.horizontal(|ui| {
             ui.label("🔍");
@@ -68,6 +92,7 @@ impl<'a> EditorPanel<'a> {
         let mut should_copy = false;
         let mut should_cut = false;
         let mut paste_text: Option<String> = None;
+        let mut ime_events: Vec<egui::ImeEvent> = Vec::new();
         
         ui.input(|i| {
             for event in &i.events {
@@ -75,6 +100,9 @@ impl<'a> EditorPanel<'a> {
                     egui::Event::Paste(text) => {
                         paste_text = Some(text.clone());
                     }
+                    egui::Event::Ime(ime) => {
+                        ime_events.push(ime.clone());
+                    }
                     egui::Event::Copy => {
                         should_copy = true;
                     }
@@ -116,24 +144,50 @@ impl<'a> EditorPanel<'a> {
             self.state.insert_text(&text);
         }
 
+        // IME composition: the preedit is only drawn, the buffer changes on commit
+        let was_composing = !self.state.ime_preedit.is_empty();
+        for ime in ime_events {
+            match ime {
+                egui::ImeEvent::Preedit(text) => self.state.ime_preedit = text,
+                egui::ImeEvent::Commit(text) => {
+                    self.state.ime_preedit.clear();
+                    if text != "\n" && text != "\r" {
+                        self.state.insert_text(&text);
+                    }
+                }
+                egui::ImeEvent::Disabled => self.state.ime_preedit.clear(),
+                egui::ImeEvent::Enabled => {}
+            }
+        }
+        // Keys pressed while composing (including the one that confirmed it) belong to the IME
+        if was_composing || !self.state.ime_preedit.is_empty() {
+            self.handle_pointer(ui, response);
+            return;
+        }
+
         // Handle text input - but NOT if modifiers are pressed
         ui.input(|i| {
             for event in &i.events {
                 if let egui::Event::Text(text) = event {
-                    if !i.modifiers.ctrl && !i.modifiers.alt && !i.modifiers.command {
-                        if !text.chars().any(|c| c.is_control()) {
-                            self.state.insert_text(text);
+                    if !i.modifiers.ctrl
+                        && !i.modifiers.alt
+                        && !i.modifiers.command
+                        && !text.chars().any(|c| c.is_control())
+                    {
+                        self.state.insert_text(text);
+                        if !text.chars().all(char::is_whitespace) {
+                            self.state.hard_wrap();
                         }
                     }
                 }
             }
         });
 
-        let has_ctrl = ui.input(|i| i.modifiers.ctrl);
+        let has_ctrl = ui.input(|i| i.modifiers.ctrl || i.modifiers.command);
         let has_shift = ui.input(|i| i.modifiers.shift);
 
         if ui.input(|i| i.key_pressed(egui::Key::Enter)) && !has_ctrl {
-            self.state.insert_text("\n");
+            self.state.insert_newline();
         }
 
         if ui.input(|i| i.key_pressed(egui::Key::Backspace)) && !has_ctrl {
@@ -199,7 +253,7 @@ impl<'a> EditorPanel<'a> {
                 .current_buffer()
                 .lines
                 .get(self.state.cursor_pos.line)
-                .map(|l| l.len())
+                .map(|l| l.chars().count())
                 .unwrap_or(0);
 
             if has_shift {
@@ -236,7 +290,11 @@ impl<'a> EditorPanel<'a> {
             }
         }
 
-        // Mouse clicks and dragging
+        self.handle_pointer(ui, response);
+    }
+
+    // Mouse clicks and dragging
+    fn handle_pointer(&mut self, ui: &Ui, response: &Response) {
         if response.clicked() {
             if let Some(pos) = response.interact_pointer_pos() {
                 self.handle_click(ui, pos);
@@ -269,19 +327,20 @@ impl<'a> EditorPanel<'a> {
                 .current_buffer()
                 .lines
                 .get(self.state.cursor_pos.line)
-                .map(|l| l.len())
+                .map(|l| l.chars().count())
                 .unwrap_or(0);
-            if self.state.cursor_pos.column < line_len {
+            if self.state.cursor_pos.column < line_len || self.state.config.virtual_space {
                 self.state.cursor_pos.column += 1;
             }
         }
 
+        let folds = self.state.folds();
         if dy < 0 && self.state.cursor_pos.line > 0 {
-            self.state.cursor_pos.line -= 1;
+            self.state.cursor_pos.line = folds.prev_visible(self.state.cursor_pos.line);
             self.clamp_column();
-        } else if dy > 0 && self.state.cursor_pos.line < self.state.current_buffer().lines.len() - 1
+        } else if dy > 0 && folds.next_visible(self.state.cursor_pos.line) < self.state.current_buffer().lines.len()
         {
-            self.state.cursor_pos.line += 1;
+            self.state.cursor_pos.line = folds.next_visible(self.state.cursor_pos.line);
             self.clamp_column();
         }
     }
@@ -304,9 +363,11 @@ impl<'a> EditorPanel<'a> {
             .current_buffer()
             .lines
             .get(self.state.cursor_pos.line)
-            .map(|l| l.len())
+            .map(|l| l.chars().count())
             .unwrap_or(0);
-        self.state.cursor_pos.column = self.state.cursor_pos.column.min(line_len);
+        if !self.state.config.virtual_space {
+            self.state.cursor_pos.column = self.state.cursor_pos.column.min(line_len);
+        }
     }
 
     fn handle_click(&mut self, ui: &Ui, pos: Pos2) {
@@ -340,48 +401,94 @@ impl<'a> EditorPanel<'a> {
 
         let rect = ui.available_rect_before_wrap();
 
-        let line = ((pos.y - rect.top()) / row_height) as usize + self.state.scroll_offset.0;
-        let column = ((pos.x - rect.left() - margin_width) / char_width).max(0.0) as usize;
-
-        let max_line = self.state.current_buffer().lines.len().saturating_sub(1);
-        let line = line.min(max_line);
-
-        let line_len = self
-            .state
-            .current_buffer()
-            .lines
-            .get(line)
-            .map(|l| l.len())
-            .unwrap_or(0);
-        let column = column.min(line_len);
+        let line = self.state.line_at_row(((pos.y - rect.top()) / row_height).max(0.0) as usize);
+        let cell = ((pos.x - rect.left() - margin_width) / char_width).max(0.0) as usize;
+        let column = self.column_at_cell(line, cell);
 
         TextPosition { line, column }
     }
 
+    // Char column under a screen cell, through tab expansion and right-to-left reordering
+    fn column_at_cell(&self, line: usize, cell: usize) -> usize {
+        self.state.current_buffer().lines.get(line).map_or(0, |l| {
+            let shown = display::expand(l, self.state.config.tab_width);
+            // With virtual space, clicks past the end keep their column
+            if self.state.config.virtual_space && cell > shown.width() {
+                return l.chars().count() + cell - shown.width();
+            }
+            shown.to_logical(bidi::column_at_cell(&shown.text, cell))
+        })
+    }
+
     fn render_content(&mut self, ui: &mut Ui, response: &Response, rect: Rect) {
-        let painter = ui.painter();
+        let painter = ui.painter_at(rect);
         let font_id = FontId::monospace(14.0);
         let row_height = 20.0;
         let char_width = 8.4;
         let margin_width = 40.0;
-        let scroll_line = self.state.scroll_offset.0;
+
+        // Mouse wheel over the text: a notch scrolls scroll_lines, touchpads scroll by distance.
+        // Ctrl+wheel is left to zooming
+        if response.hovered() {
+            let notch = self.state.config.scroll_lines as f32;
+            let page = (rect.height() / row_height).floor();
+            let delta: f32 = ui.input(|i| {
+                i.events.iter().map(|event| match event {
+                    egui::Event::MouseWheel { unit, delta, modifiers } if !modifiers.command && !modifiers.ctrl => match unit {
+                        MouseWheelUnit::Line => delta.y * notch,
+                        MouseWheelUnit::Point => delta.y / row_height,
+                        MouseWheelUnit::Page => delta.y * page,
+                    },
+                    _ => 0.0,
+                }).sum()
+            });
+            if delta != 0.0 {
+                self.state.wheel_scroll(-delta);
+            }
+        }
+
+        // Keep followed files pinned to the bottom
+        if let Some(line_count) = self.state.follow_line_count() {
+            let visible_rows = (rect.height() / row_height) as usize;
+            self.state.scroll_offset.0 = line_count.saturating_sub(visible_rows);
+        } else if self.state.config.typewriter_scrolling {
+            // Typewriter scrolling keeps the cursor's line in the middle of the view
+            let visible_rows = (rect.height() / row_height) as usize;
+            let folds = self.state.folds();
+            let mut line = self.state.cursor_pos.line;
+            for _ in 0..visible_rows / 2 {
+                line = folds.prev_visible(line);
+            }
+            self.state.scroll_offset.0 = line;
+        }
+        // Mid-animation the first row can be part-way scrolled out of view
+        let (scroll_line, scroll_fraction) = match self.state.animated_scroll() {
+            Some(position) => {
+                ui.ctx().request_repaint();
+                let line = position.floor() as usize;
+                (self.state.folds().prev_visible(line + 1), position.fract())
+            }
+            None => (self.state.scroll_offset.0, 0.0),
+        };
     
         // Handle Mouse Interaction
         if response.clicked() || response.dragged() {
             if let Some(mouse_pos) = response.interact_pointer_pos() {
                 let local_pos = mouse_pos - rect.min;
                 
-                let line_count = self.state.current_buffer().lines.len();
-                let clicked_line = ((local_pos.y / row_height) as usize + scroll_line)
-                    .min(line_count.saturating_sub(1));
+                let clicked_line = self.state.line_at_row((local_pos.y / row_height).max(0.0) as usize);
                 
-                let line_len = self.state.current_buffer().lines[clicked_line].len();
-                let clicked_col = ((local_pos.x - margin_width) / char_width).round().max(0.0) as usize;
-                let clicked_col = clicked_col.min(line_len);
+                let clicked_cell = ((local_pos.x - margin_width) / char_width).round().max(0.0) as usize;
+                let clicked_col = self.column_at_cell(clicked_line, clicked_cell);
     
                 let new_pos = TextPosition { line: clicked_line, column: clicked_col };
     
-                if response.clicked() {
+                // Ctrl+Click follows links instead of moving the cursor
+                let opened_link = response.clicked()
+                    && ui.input(|i| i.modifiers.command)
+                    && self.state.open_link_at(new_pos);
+
+                if response.clicked() && !opened_link {
                     self.state.cursor_pos = new_pos;
                     self.state.selection = Some(Selection { anchor: new_pos, cursor: new_pos });
                 } else if response.dragged() {
@@ -395,7 +502,12 @@ impl<'a> EditorPanel<'a> {
     
         // Draw content
         let visible_rows = (rect.height() / row_height) as usize + 1;
-        let end_line = (scroll_line + visible_rows).min(self.state.current_buffer().lines.len());
+        let line_count = self.state.current_buffer().lines.len();
+        let folds = self.state.folds();
+        let shown_lines: Vec<usize> = std::iter::successors(Some(scroll_line), |&line| Some(folds.next_visible(line)))
+            .take_while(|&line| line < line_count)
+            .take(visible_rows)
+            .collect();
     
         // Draw margin background
         let margin_rect = Rect::from_min_size(rect.min, egui::Vec2::new(margin_width, rect.height()));
@@ -405,10 +517,30 @@ impl<'a> EditorPanel<'a> {
             .filter(|s| s.is_active())
             .map(|s| s.get_range());
     
+        let is_markdown = self.state.is_markdown();
+        let show_swatches = self.state.shows_color_swatches();
+        let tab_width = self.state.config.tab_width;
         let buffer = self.state.current_buffer();
+        let conflict_list = self.state.conflicts();
     
-        for (visual_idx, line_idx) in (scroll_line..end_line).enumerate() {
-            let y_pos = rect.top() + visual_idx as f32 * row_height;
+        for (visual_idx, &line_idx) in shown_lines.iter().enumerate() {
+            let y_pos = rect.top() + (visual_idx as f32 - scroll_fraction) * row_height;
+
+            // Merge conflict regions get a tinted background
+            let conflict_fill = match conflict_list.iter().find_map(|c| c.region(line_idx)) {
+                Some(Region::Marker) => Some(Color32::from_rgb(90, 40, 90)),
+                Some(Region::Ours) => Some(Color32::from_rgb(30, 60, 35)),
+                Some(Region::Base) => Some(Color32::from_rgb(50, 50, 50)),
+                Some(Region::Theirs) => Some(Color32::from_rgb(30, 50, 70)),
+                None => None,
+            };
+            if let Some(fill) = conflict_fill {
+                let line_rect = Rect::from_min_max(
+                    Pos2::new(rect.left() + margin_width, y_pos),
+                    Pos2::new(rect.right(), y_pos + row_height),
+                );
+                painter.rect_filled(line_rect, 0.0, fill);
+            }
     
             // Line number
             painter.text(
@@ -422,8 +554,34 @@ impl<'a> EditorPanel<'a> {
             // Line content with selection
             if let Some(line) = buffer.lines.get(line_idx) {
                 let text_pos = Pos2::new(rect.left() + margin_width, y_pos);
+                // Tabs and control characters are painted expanded, with selection columns mapped along
+                let shown = display::expand(line, tab_width);
+                let line = shown.text.as_str();
+                let to_display = |p: TextPosition| {
+                    if p.line == line_idx { TextPosition { line: p.line, column: shown.to_display(p.column) } } else { p }
+                };
+                let selection_range = selection_range.map(|(start, end)| (to_display(start), to_display(end)));
     
-                if let Some((start, end)) = selection_range {
+                if bidi::has_rtl(line) {
+                    // Right-to-left text is painted grapheme by grapheme in visual order
+                    let (sel_start, sel_end) = match selection_range {
+                        Some((start, end)) if line_idx >= start.line && line_idx <= end.line => (
+                            if line_idx == start.line { start.column } else { 0 },
+                            if line_idx == end.line { end.column } else { usize::MAX },
+                        ),
+                        _ => (0, 0),
+                    };
+                    let mut x = text_pos.x;
+                    for cell in bidi::visual_line(line) {
+                        let cell_width = bidi::width(cell.text) as f32 * char_width;
+                        if (sel_start..sel_end).contains(&cell.column) {
+                            let sel_rect = Rect::from_min_size(Pos2::new(x, y_pos), egui::Vec2::new(cell_width, row_height));
+                            painter.rect_filled(sel_rect, 0.0, Color32::from_rgb(50, 100, 200));
+                        }
+                        painter.text(Pos2::new(x, y_pos), egui::Align2::LEFT_TOP, cell.text, font_id.clone(), Color32::WHITE);
+                        x += cell_width;
+                    }
+                } else if let Some((start, end)) = selection_range {
                     if line_idx >= start.line && line_idx <= end.line {
                         let chars: Vec<char> = line.chars().collect();
                         let sel_start = if line_idx == start.line { start.column } else { 0 };
@@ -455,19 +613,115 @@ impl<'a> EditorPanel<'a> {
                     } else {
                         painter.text(text_pos, egui::Align2::LEFT_TOP, line, font_id.clone(), Color32::WHITE);
                     }
+                } else if is_markdown && markdown::is_checked_item(line) {
+                    // Checked task items are dimmed and struck through
+                    let dim = Color32::from_rgb(120, 120, 120);
+                    painter.text(text_pos, egui::Align2::LEFT_TOP, line, font_id.clone(), dim);
+                    let strike_y = y_pos + row_height / 2.0;
+                    let strike_end = text_pos.x + line.chars().count() as f32 * char_width;
+                    painter.line_segment(
+                        [Pos2::new(text_pos.x, strike_y), Pos2::new(strike_end, strike_y)],
+                        Stroke::new(1.0, dim),
+                    );
                 } else {
                     painter.text(text_pos, egui::Align2::LEFT_TOP, line, font_id.clone(), Color32::WHITE);
                 }
+
+                // Underline links
+                for (start, end) in links::find_urls(line) {
+                    let start_x = text_pos.x + line[..start].chars().count() as f32 * char_width;
+                    let end_x = text_pos.x + line[..end].chars().count() as f32 * char_width;
+                    let underline_y = y_pos + row_height - 3.0;
+                    painter.line_segment(
+                        [Pos2::new(start_x, underline_y), Pos2::new(end_x, underline_y)],
+                        Stroke::new(1.0, Color32::from_rgb(100, 149, 237)),
+                    );
+                }
+
+                // Color swatches after the line text
+                if show_swatches {
+                    let line_end_x = text_pos.x + line.chars().count() as f32 * char_width;
+                    for (i, color) in colors::find_colors(line).iter().enumerate() {
+                        let (r, g, b) = color.rgb;
+                        let swatch = Rect::from_min_size(
+                            Pos2::new(line_end_x + 8.0 + i as f32 * 16.0, y_pos + 4.0),
+                            egui::Vec2::splat(12.0),
+                        );
+                        painter.rect_filled(swatch, 2.0, Color32::from_rgb(r, g, b));
+                        painter.rect_stroke(swatch, 2.0, Stroke::new(1.0, Color32::GRAY), egui::StrokeKind::Outside);
+                    }
+                }
             }
     
-            // Cursor
+            // Folded blocks end with a placeholder for the hidden lines
+            if let Some(fold) = folds.fold_at(line_idx) {
+                let line_end_x = rect.left()
+                    + margin_width
+                    + self.state.line_metrics(line_idx).width as f32 * char_width;
+                let label = format!(" … {} lines ", fold.hidden_lines());
+                let label_rect = Rect::from_min_size(
+                    Pos2::new(line_end_x + 8.0, y_pos + 2.0),
+                    egui::Vec2::new(label.chars().count() as f32 * 7.2, row_height - 4.0),
+                );
+                painter.rect_filled(label_rect, 3.0, Color32::from_rgb(60, 60, 60));
+                painter.text(
+                    label_rect.left_center(),
+                    egui::Align2::LEFT_CENTER,
+                    label,
+                    FontId::monospace(12.0),
+                    Color32::from_gray(170),
+                );
+            }
+
+            // Cursor, after any IME preedit drawn in place at the cursor
             if self.state.cursor_pos.line == line_idx {
-                let cx = rect.left() + margin_width + (self.state.cursor_pos.column as f32 * char_width);
+                let cell = buffer.lines.get(line_idx).map_or(0, |l| {
+                    let shown = display::expand(l, tab_width);
+                    let past_end = self.state.cursor_pos.column.saturating_sub(l.chars().count());
+                    bidi::caret_cell(&shown.text, shown.to_display(self.state.cursor_pos.column)) + past_end
+                });
+                let mut cx = rect.left() + margin_width + cell as f32 * char_width;
+                let preedit = &self.state.ime_preedit;
+                if !preedit.is_empty() {
+                    let preedit_rect = Rect::from_min_size(
+                        Pos2::new(cx, y_pos),
+                        egui::Vec2::new(preedit.width() as f32 * char_width, row_height),
+                    );
+                    painter.rect_filled(preedit_rect, 0.0, Color32::from_rgb(45, 45, 45));
+                    painter.text(preedit_rect.min, egui::Align2::LEFT_TOP, preedit, font_id.clone(), Color32::WHITE);
+                    painter.line_segment(
+                        [preedit_rect.left_bottom(), preedit_rect.right_bottom()],
+                        Stroke::new(1.0, Color32::WHITE),
+                    );
+                    cx = preedit_rect.right();
+                }
+                if self.accepts_input {
+                    // Tells the platform IME where to show its candidate window
+                    ui.ctx().output_mut(|o| {
+                        o.ime = Some(egui::output::IMEOutput {
+                            rect,
+                            cursor_rect: Rect::from_min_size(Pos2::new(cx, y_pos), egui::Vec2::new(2.0, row_height)),
+                        });
+                    });
+                }
                 painter.line_segment(
                     [Pos2::new(cx, y_pos), Pos2::new(cx, y_pos + row_height)],
                     Stroke::new(2.0, Color32::YELLOW)
                 );
             }
+
+            // Others' carets in a shared tab, each with its name above it
+            for peer in self.state.peers().iter().filter(|peer| peer.position.line == line_idx) {
+                let cell = buffer.lines.get(line_idx).map_or(0, |l| {
+                    let shown = display::expand(l, tab_width);
+                    bidi::caret_cell(&shown.text, shown.to_display(peer.position.column))
+                });
+                let x = rect.left() + margin_width + cell as f32 * char_width;
+                let (r, g, b) = peer.color();
+                let color = Color32::from_rgb(r, g, b);
+                painter.line_segment([Pos2::new(x, y_pos), Pos2::new(x, y_pos + row_height)], Stroke::new(2.0, color));
+                painter.text(Pos2::new(x + 2.0, y_pos), egui::Align2::LEFT_BOTTOM, &peer.name, FontId::monospace(10.0), color);
+            }
         }
     }
 }
\ No newline at end of file
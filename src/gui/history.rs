@@ -0,0 +1,97 @@
+// history - window listing earlier saved versions of the current file, with a diff against the buffer
+use crate::core::diff::{self, DiffRow};
+use crate::core::history::{self, Version};
+use crate::gui::compare;
+
+pub struct HistoryView {
+    versions: Vec<Version>, // Newest first
+    selected: usize,
+    version_lines: Vec<String>,
+    current_lines: Vec<String>,
+    rows: Vec<DiffRow>,
+    error: Option<String>,
+    jump: Option<usize>, // Row to scroll to on the next frame
+}
+
+impl HistoryView {
+    pub fn new(path: &str) -> Self {
+        Self {
+            versions: history::list_versions(path),
+            selected: 0,
+            version_lines: Vec::new(),
+            current_lines: Vec::new(),
+            rows: Vec::new(),
+            error: None,
+            jump: None,
+        }
+    }
+
+    fn load(&mut self, passphrase: Option<&str>, current: Vec<String>) {
+        self.current_lines = current;
+        let Some(version) = self.versions.get(self.selected) else {
+            return;
+        };
+        match history::read_version(version, passphrase) {
+            Ok(content) => {
+                self.version_lines = content.split('\n').map(String::from).collect();
+                self.error = None;
+            }
+            Err(e) => {
+                self.version_lines.clear();
+                self.error = Some(e.to_string());
+            }
+        }
+        self.rows = diff::side_by_side(&self.version_lines, &self.current_lines);
+        self.jump = diff::change_starts(&self.rows).first().copied();
+    }
+
+    // Returns false once the window is closed, and the text to restore when asked to.
+    // The current text is only split into lines when a version is (re)loaded, not every frame
+    pub fn show(&mut self, ctx: &egui::Context, passphrase: Option<&str>, current: impl Fn() -> Vec<String>) -> (bool, Option<String>) {
+        if self.current_lines.is_empty() {
+            self.load(passphrase, current());
+        }
+        let mut open = true;
+        let mut picked = None;
+        let mut restore = None;
+
+        egui::Window::new("File History")
+            .open(&mut open)
+            .default_size([1000.0, 600.0])
+            .show(ctx, |ui| {
+                if self.versions.is_empty() {
+                    ui.label("No earlier versions of this file yet");
+                    return;
+                }
+
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_salt("history_version")
+                        .selected_text(format!("Saved {}", self.versions[self.selected].name))
+                        .show_ui(ui, |ui| {
+                            for (i, version) in self.versions.iter().enumerate() {
+                                if ui.selectable_label(i == self.selected, &version.name).clicked() {
+                                    picked = Some(i);
+                                }
+                            }
+                        });
+                    ui.label("↔ Current");
+                    ui.separator();
+                    if ui.add_enabled(self.error.is_none(), egui::Button::new("Restore this version")).clicked() {
+                        restore = Some(self.version_lines.join("\n"));
+                    }
+                    if let Some(error) = &self.error {
+                        ui.colored_label(egui::Color32::from_rgb(220, 80, 80), error);
+                    }
+                });
+                ui.separator();
+                compare::show_rows(ui, &self.rows, (&self.version_lines, &self.current_lines), self.jump.take());
+            });
+
+        if let Some(i) = picked {
+            self.selected = i;
+            self.load(passphrase, current());
+        }
+        let open = open && restore.is_none() && !ctx.input(|i| i.key_pressed(egui::Key::Escape));
+        (open, restore)
+    }
+}
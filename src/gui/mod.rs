@@ -1,12 +1,17 @@
 mod app;
+mod compare;
+mod detached;
 mod editor;
+mod history;
 mod state;
 mod themes;
+mod toasts;
 
 pub use app::QuickNotepadApp;
+use crate::core::collab::Role;
 
 // Entry point for GUI mode
-pub fn run(file_path: Option<String>) {
+pub fn run(file_path: Option<String>, collab_role: Option<Role>) {
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([1200.0, 800.0])
@@ -22,17 +27,35 @@ pub fn run(file_path: Option<String>) {
             // Setup custom fonts if needed
             setup_custom_fonts(&cc.egui_ctx);
             
-            Ok(Box::new(QuickNotepadApp::new(cc, file_path)))
+            Ok(Box::new(QuickNotepadApp::new(cc, file_path, collab_role)))
         }),
     );
 }
 
+// System fonts with CJK glyphs, the first one found is used as a fallback for IME input
+const CJK_FONTS: [&str; 7] = [
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/google-noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+    "/System/Library/Fonts/Hiragino Sans GB.ttc",
+    "C:\\Windows\\Fonts\\msyh.ttc",
+    "C:\\Windows\\Fonts\\malgun.ttf",
+];
+
 fn setup_custom_fonts(ctx: &egui::Context) {
-    let fonts = egui::FontDefinitions::default();
-    
-    // Add monospace font for editor
-    // You can add custom fonts here if you want
-    
+    let mut fonts = egui::FontDefinitions::default();
+
+    // The bundled fonts have no CJK glyphs, so append a system font after them
+    if let Some(data) = CJK_FONTS.iter().find_map(|path| std::fs::read(path).ok()) {
+        fonts
+            .font_data
+            .insert("cjk".to_owned(), std::sync::Arc::new(egui::FontData::from_owned(data)));
+        for family in [egui::FontFamily::Proportional, egui::FontFamily::Monospace] {
+            fonts.families.entry(family).or_default().push("cjk".to_owned());
+        }
+    }
+
     ctx.set_fonts(fonts);
 }
 
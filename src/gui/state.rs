@@ -1,10 +1,38 @@
 // state - adapter between core logic and GUI with Wayland-safe clipboard handling
 use crate::core::{
+    align,
     buffer::Buffer,
-    edit_history::EditHistory,
+    collab::Peer,
+    colors,
+    completion,
+    config::Config,
+    conflicts::{self, Conflict, Resolution},
+    fetch,
+    continuation::continuation,
+    crypto,
+    edit_history::{Edit, EditHistory, EditOperation},
+    folding::Folds,
+    history,
+    jumps::{self, Jump},
+    line_metrics::{LineMetrics, LineMetricsCache},
+    links,
+    markdown,
+    platform,
+    positions::FilePosition,
+    progress::{Job, Progress},
+    reflow,
+    search_index::SearchIndex,
     selection::{Selection, TextPosition},
-    tabs::TabManager,
+    sequence::{self, Sequence},
+    toasts::Toasts,
+    smart_select::SelectionSteps,
+    stats::Tracker,
+    symbols::{self, Symbol},
+    tabs::{self, Tab, TabManager},
+    todos::{self, TodoItem},
+    transforms::TextTransform,
 };
+use std::time::{Duration, Instant};
 
 pub struct EditorState {
     pub tab_manager: TabManager,
@@ -13,17 +41,50 @@ pub struct EditorState {
     pub scroll_offset: (usize, usize), // (line, column)
     pub search_query: String,
     pub search_active: bool,
+    pub search_index: SearchIndex,
     pub is_dragging: bool,
+    pub config: Config,
     // Dual clipboard approach:
     // - arboard handles actual system clipboard (works on X11 and most Wayland)
     // - internal clipboard_text as fallback for edge cases
     // - egui events provide Wayland compatibility layer
     clipboard_text: Option<String>,
+    fold_sync: (usize, usize, usize), // (tab, line count, cursor line) seen last frame
+    tab_sync: Option<(usize, Option<String>)>, // (tab, file path) the cursor belonged to last frame
+    selection_steps: SelectionSteps,
+    pub ime_preedit: String, // Uncommitted IME composition, drawn at the cursor but not in the buffer
+    scroll_animation: Option<(f32, Instant)>, // Scroll position the current animation started from, and when
+    wheel_remainder: f32, // Part of a line the wheel has scrolled but not yet moved the view by
+    pub stats: Tracker, // Usage counted this session
+    revision: u64, // Bumped whenever the shown text may have changed
+    outline: Outline,
+    line_metrics: LineMetricsCache,
+    pub toasts: Toasts, // Status messages shown over the bottom right corner
+    pub loading: Option<(Job<TabLoad>, Option<usize>)>, // Big file read on a worker thread, and the line to jump to
+}
+
+// Whole-file scans the editor draws from, redone only when the text or tab changes
+#[derive(Default)]
+struct Outline {
+    key: Option<(usize, usize, usize, u64)>, // (tab, lines allocation, line count, revision) scanned
+    symbols: Vec<Symbol>,
+    conflicts: Vec<Conflict>,
+}
+
+type TabLoad = Result<Tab, std::io::Error>;
+
+const SCROLL_ANIMATION: Duration = Duration::from_millis(200);
+
+// Byte offset of a char column, so multi-byte text (CJK, accents) is never split
+fn byte_index(line: &str, column: usize) -> usize {
+    line.char_indices().nth(column).map_or(line.len(), |(idx, _)| idx)
 }
 
 impl EditorState {
     pub fn new(file_path: Option<String>) -> Self {
-        let tab_manager = if let Some(path) = file_path {
+        // URLs download behind the status bar's progress bar
+        let url = file_path.clone().filter(|path| fetch::is_url(path));
+        let tab_manager = if let Some(path) = file_path.filter(|path| !fetch::is_url(path)) {
             let mut tm = TabManager::new(Buffer::default(), None, None);
             if let Err(e) = tm.open_file_in_new_tab(&path) {
                 eprintln!("Failed to open file: {}", e);
@@ -32,7 +93,28 @@ impl EditorState {
         } else {
             TabManager::new(Buffer::default(), None, None)
         };
+        let mut state = Self::with_tabs(tab_manager);
+        if let Some(url) = url {
+            let job = Job::spawn(format!("Downloading {}", fetch::file_name(&url)), move |progress| {
+                Tab::from_url(&url, progress)
+            });
+            state.loading = Some((job, None));
+        }
+        state
+    }
+
+    // Editor for a tab moved out into its own window
+    pub fn for_tab(tab: Tab) -> Self {
+        Self::with_tabs(TabManager::detached(tab))
+    }
+
+    // Take the tab back out when its window closes
+    pub fn into_tab(mut self) -> Tab {
+        self.sync_tab_position();
+        self.tab_manager.tabs.remove(0)
+    }
 
+    fn with_tabs(tab_manager: TabManager) -> Self {
         Self {
             tab_manager,
             selection: None,
@@ -40,8 +122,22 @@ impl EditorState {
             scroll_offset: (0, 0),
             search_query: String::new(),
             search_active: false,
+            search_index: SearchIndex::default(),
             is_dragging: false,
+            config: Config::load(),
             clipboard_text: None,
+            fold_sync: (0, 0, 0),
+            tab_sync: None,
+            selection_steps: SelectionSteps::default(),
+            ime_preedit: String::new(),
+            scroll_animation: None,
+            wheel_remainder: 0.0,
+            stats: Tracker::default(),
+            revision: 0,
+            outline: Outline::default(),
+            line_metrics: LineMetricsCache::default(),
+            toasts: Toasts::default(),
+            loading: None,
         }
     }
 
@@ -50,9 +146,44 @@ impl EditorState {
     }
 
     pub fn current_buffer_mut(&mut self) -> &mut Buffer {
+        self.revision += 1;
         &mut self.tab_manager.current_tab_mut().buffer
     }
 
+    // Rescan symbols and merge conflicts if the text changed since the last frame.
+    // Switching tabs or reloading a file swaps the lines allocation, which also counts as a change
+    pub fn refresh_outline(&mut self) {
+        let tab = self.tab_manager.current_tab();
+        let lines = &tab.buffer.lines;
+        let key = Some((self.tab_manager.active_tab_index, lines.as_ptr() as usize, lines.len(), self.revision));
+        if self.outline.key == key {
+            return;
+        }
+        self.outline = Outline {
+            key,
+            symbols: symbols::find_symbols(lines, tab.filetype.as_deref()),
+            conflicts: conflicts::find_conflicts(lines),
+        };
+    }
+
+    // Cached grapheme count, width and indentation of a line; empty past the end of the buffer
+    pub fn line_metrics(&self, line_idx: usize) -> LineMetrics {
+        self.current_buffer()
+            .lines
+            .get(line_idx)
+            .map(|line| self.line_metrics.get(line, self.config.tab_width))
+            .unwrap_or_default()
+    }
+
+    // As of the last refresh_outline
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.outline.symbols
+    }
+
+    pub fn conflicts(&self) -> &[Conflict] {
+        &self.outline.conflicts
+    }
+
     pub fn current_edit_history(&mut self) -> &mut EditHistory {
         &mut self.tab_manager.current_tab_mut().edit_history
     }
@@ -66,7 +197,13 @@ impl EditorState {
     }
 
     pub fn mark_clean(&mut self) {
-        self.tab_manager.current_tab_mut().has_unsaved_changes = false;
+        self.tab_manager.current_tab_mut().mark_saved();
+    }
+
+    // Undo and redo may land back on the saved text
+    pub fn mark_dirty_unless_saved(&mut self) {
+        let tab = self.tab_manager.current_tab_mut();
+        tab.has_unsaved_changes = !tab.edit_history.is_saved();
     }
 
     pub fn current_filename(&self) -> Option<&str> {
@@ -77,8 +214,115 @@ impl EditorState {
         self.tab_manager.current_tab_mut().filename = Some(filename);
     }
 
+    pub fn is_read_only(&self) -> bool {
+        self.tab_manager.current_tab().is_read_only()
+    }
+
+    // Opened from an http(s) URL and not saved anywhere yet
+    pub fn is_download(&self) -> bool {
+        self.tab_manager.current_tab().url.is_some()
+    }
+
+    pub fn is_markdown(&self) -> bool {
+        self.tab_manager.current_tab().filetype.as_deref() == Some("Markdown")
+    }
+
+    pub fn shows_color_swatches(&self) -> bool {
+        colors::has_color_literals(self.tab_manager.current_tab().filetype.as_deref())
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.tab_manager.current_tab().locked
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.tab_manager.current_tab().is_encrypted()
+    }
+
+    pub fn is_following(&self) -> bool {
+        self.tab_manager.current_tab().is_following()
+    }
+
+    pub fn follow_line_count(&self) -> Option<usize> {
+        self.tab_manager
+            .current_tab()
+            .follower
+            .as_ref()
+            .map(|f| f.line_count())
+    }
+
+    // Scratch notes save themselves whenever they change
+    pub fn autosave_scratch(&mut self) {
+        let tab = self.tab_manager.current_tab_mut();
+        if tab.scratch && tab.has_unsaved_changes {
+            if let Err(e) = tab.save_scratch(self.config.insert_final_newline) {
+                self.toasts.error(format!("Failed to save scratch note: {}", e));
+            }
+        }
+    }
+
+    // Pick up content appended to a followed file, returns true if the buffer changed
+    pub fn poll_follow(&mut self) -> bool {
+        let tab = self.tab_manager.current_tab_mut();
+        let Some(follower) = tab.follower.as_mut() else {
+            return false;
+        };
+
+        match follower.poll(&mut tab.buffer) {
+            Ok(changed) => {
+                if changed {
+                    self.revision += 1;
+                    let last_line = follower.line_count().saturating_sub(1);
+                    self.cursor_pos = TextPosition { line: last_line, column: 0 };
+                    self.selection = None;
+                }
+                changed
+            }
+            Err(e) => {
+                self.toasts.error(format!("Stopped following: {}", e));
+                tab.stop_follow();
+                false
+            }
+        }
+    }
+
+    pub fn is_shared(&self) -> bool {
+        self.tab_manager.current_tab().collab.is_some()
+    }
+
+    pub fn peers(&self) -> &[Peer] {
+        self.tab_manager.current_tab().collab.as_ref().map_or(&[], |session| session.peers())
+    }
+
+    // Trade edits and carets with the others in a shared tab
+    pub fn sync_collab(&mut self) {
+        let tab = self.tab_manager.current_tab_mut();
+        let Some(session) = tab.collab.as_mut() else {
+            return;
+        };
+        let mut cursor = self.cursor_pos;
+        match session.sync(&mut tab.buffer, &mut cursor) {
+            Ok(false) => {}
+            Ok(true) => {
+                // Undo steps point at text the others may have moved
+                tab.edit_history = EditHistory::new(500);
+                tab.has_unsaved_changes = true;
+                self.cursor_pos = cursor;
+                self.selection = None;
+                self.revision += 1;
+            }
+            Err(e) => {
+                tab.collab = None;
+                self.toasts.error(e.to_string());
+            }
+        }
+    }
+
     // Insert text at cursor position
     pub fn insert_text(&mut self, text: &str) {
+        if self.is_read_only() {
+            return;
+        }
         let pos = self.cursor_pos;
         let buffer = self.current_buffer_mut();
 
@@ -87,6 +331,12 @@ impl EditorState {
             buffer.lines.push(String::new());
         }
 
+        // A cursor in virtual space past the end gets spaces up to it
+        let line_len = buffer.lines[pos.line].chars().count();
+        if pos.column > line_len {
+            buffer.lines[pos.line].push_str(&" ".repeat(pos.column - line_len));
+        }
+
         // Handle newlines
         if text.contains('\n') {
             let lines: Vec<&str> = text.split('\n').collect();
@@ -107,36 +357,79 @@ impl EditorState {
 
             self.cursor_pos = TextPosition {
                 line: pos.line + lines.len() - 1,
-                column: lines[lines.len() - 1].len(),
+                column: lines[lines.len() - 1].chars().count(),
             };
         } else {
             // Single line insert
             let line = &mut buffer.lines[pos.line];
-            if pos.column <= line.len() {
-                line.insert_str(pos.column, text);
-            } else {
-                line.push_str(text);
-            }
+            line.insert_str(byte_index(line, pos.column), text);
             self.cursor_pos = TextPosition {
                 line: pos.line,
-                column: pos.column + text.len(),
+                column: pos.column + text.chars().count(),
             };
         }
 
         self.mark_dirty();
     }
     
+    // Enter, continuing list / comment prefixes onto the new line
+    pub fn insert_newline(&mut self) {
+        if self.is_read_only() {
+            return;
+        }
+
+        let pos = self.cursor_pos;
+        let line = self.current_buffer().lines[pos.line].clone();
+        let filetype = self.tab_manager.current_tab().filetype.clone();
+        let cont = continuation(&line, filetype.as_deref())
+            .filter(|cont| pos.column >= cont.prefix_len)
+            .filter(|_| !self.selection.as_ref().is_some_and(|s| s.is_active()));
+
+        let Some(cont) = cont else {
+            self.insert_text("\n");
+            return;
+        };
+
+        if cont.is_empty_line(&line) {
+            self.cursor_pos.column = cont.indent.len();
+            self.replace_lines(pos.line, vec![cont.indent.clone()], 1);
+            return;
+        }
+
+        let (before, after) = line.split_at(byte_index(&line, pos.column));
+        let new_lines = vec![
+            before.to_string(),
+            format!("{}{}", cont.next_prefix, after.trim_start()),
+        ];
+        self.cursor_pos = TextPosition {
+            line: pos.line + 1,
+            column: cont.next_prefix.len(),
+        };
+        self.replace_lines(pos.line, new_lines, 1);
+    }
+
     pub fn move_cursor(&mut self, dx: isize, dy: isize) {
         let line_count = self.current_buffer().lines.len();
         
-        let mut new_line = self.cursor_pos.line as isize + dy;
-        new_line = new_line.clamp(0, line_count.saturating_sub(1) as isize);
+        // Step over folded blocks one visible line at a time
+        let folds = self.folds();
+        let mut new_line = self.cursor_pos.line;
+        for _ in 0..dy.unsigned_abs() {
+            new_line = if dy < 0 { folds.prev_visible(new_line) } else { folds.next_visible(new_line) };
+        }
+        if new_line >= line_count {
+            new_line = self.cursor_pos.line;
+        }
         
-        self.cursor_pos.line = new_line as usize;
+        self.cursor_pos.line = new_line;
     
-        let line_len = self.current_buffer().lines[self.cursor_pos.line].len();
+        let line_len = self.current_buffer().lines[self.cursor_pos.line].chars().count();
         let mut new_col = self.cursor_pos.column as isize + dx;
-        new_col = new_col.clamp(0, line_len as isize);
+        if self.config.virtual_space {
+            new_col = new_col.max(0);
+        } else {
+            new_col = new_col.clamp(0, line_len as isize);
+        }
         
         self.cursor_pos.column = new_col as usize;
     }
@@ -144,12 +437,15 @@ impl EditorState {
     pub fn clamp_cursor(&mut self) {
         let line_count = self.current_buffer().lines.len();
         self.cursor_pos.line = self.cursor_pos.line.min(line_count.saturating_sub(1));
-        let line_len = self.current_buffer().lines[self.cursor_pos.line].len();
+        let line_len = self.current_buffer().lines[self.cursor_pos.line].chars().count();
         self.cursor_pos.column = self.cursor_pos.column.min(line_len);
     }
 
     // Delete selection or character at cursor
     pub fn delete_at_cursor(&mut self) {
+        if self.is_read_only() {
+            return;
+        }
         if let Some(selection) = self.selection.take() {
             self.delete_selection(selection);
         } else {
@@ -157,8 +453,8 @@ impl EditorState {
             let buffer = self.current_buffer_mut();
             if pos.line < buffer.lines.len() {
                 let line = &mut buffer.lines[pos.line];
-                if pos.column < line.len() {
-                    line.remove(pos.column);
+                if pos.column < line.chars().count() {
+                    line.remove(byte_index(line, pos.column));
                     self.clamp_cursor();
                     self.mark_dirty();
                 }
@@ -168,6 +464,9 @@ impl EditorState {
 
     // Backspace - delete character before cursor
     pub fn backspace(&mut self) {
+        if self.is_read_only() {
+            return;
+        }
         if let Some(selection) = self.selection.take() {
             self.delete_selection(selection);
         } else if self.cursor_pos.column > 0 {
@@ -175,17 +474,20 @@ impl EditorState {
             let buffer = self.current_buffer_mut();
             if pos.line < buffer.lines.len() {
                 let line = &mut buffer.lines[pos.line];
-                if pos.column <= line.len() {
-                    line.remove(pos.column - 1);
+                if pos.column <= line.chars().count() {
+                    line.remove(byte_index(line, pos.column - 1));
                     self.cursor_pos.column -= 1;
                     self.mark_dirty();
+                } else {
+                    // Past the end in virtual space there is nothing to delete, just step back
+                    self.cursor_pos.column -= 1;
                 }
             }
         } else if self.cursor_pos.line > 0 {
             let pos = self.cursor_pos;
             let buffer = self.current_buffer_mut();
             let current_line = buffer.lines[pos.line].clone();
-            let prev_line_len = buffer.lines[pos.line - 1].len();
+            let prev_line_len = buffer.lines[pos.line - 1].chars().count();
             buffer.lines[pos.line - 1].push_str(&current_line);
             buffer.lines.remove(pos.line);
             self.cursor_pos = TextPosition {
@@ -234,8 +536,276 @@ impl EditorState {
         self.mark_dirty();
     }
 
+    // Replace whole lines as one undoable edit (GUI undo restores text only)
+    fn replace_lines(&mut self, start_line: usize, new_lines: Vec<String>, old_len: usize) {
+        let buffer = self.current_buffer_mut();
+        let end = (start_line + old_len).min(buffer.lines.len());
+        let old_lines = buffer.lines[start_line.min(end)..end].to_vec();
+        if old_lines == new_lines {
+            return;
+        }
+
+        let edit = Edit::ReplaceLines { start_line, old_lines, new_lines };
+        edit.apply(&mut buffer.lines);
+        let scroll = self.scroll_offset.0;
+        self.current_edit_history().push(EditOperation {
+            edit,
+            cursor_before: Default::default(),
+            cursor_after: Default::default(),
+            scroll_before: scroll,
+            scroll_after: scroll,
+        });
+        self.mark_dirty();
+        self.clamp_cursor();
+    }
+
+    // Bring back an earlier saved version of the file as one undoable edit
+    pub fn restore_version(&mut self, content: &str) {
+        if self.is_read_only() {
+            return;
+        }
+        let old_len = self.current_buffer().lines.len();
+        self.replace_lines(0, Buffer::from_string(content.to_string()).lines, old_len);
+    }
+
+    pub fn toggle_checkbox(&mut self) {
+        if self.is_read_only() || !self.is_markdown() {
+            return;
+        }
+        let line_idx = self.cursor_pos.line;
+        let line = self.current_buffer().lines[line_idx].clone();
+        let toggled = markdown::toggle_checkbox(&line);
+
+        // Keep the cursor on the same text
+        if self.cursor_pos.column > 0 {
+            let shift = toggled.len() as isize - line.len() as isize;
+            self.cursor_pos.column = (self.cursor_pos.column as isize + shift).max(0) as usize;
+        }
+        self.replace_lines(line_idx, vec![toggled], 1);
+    }
+
+    pub fn move_done_items(&mut self) {
+        if self.is_read_only() || !self.is_markdown() {
+            return;
+        }
+        let buffer = self.current_buffer();
+        let content = buffer.lines[..buffer.logical_len()].to_vec();
+
+        if let Some(new_lines) = markdown::move_done_items(&content) {
+            self.replace_lines(0, new_lines, content.len());
+        }
+    }
+
+    // Reflow the Markdown table under the cursor
+    pub fn format_table(&mut self) {
+        if self.is_read_only() || !self.is_markdown() {
+            return;
+        }
+        let lines = &self.current_buffer().lines;
+        if let Some((start, end)) = markdown::table_bounds(lines, self.cursor_pos.line) {
+            let formatted = markdown::format_table(&lines[start..end]);
+            self.replace_lines(start, formatted, end - start);
+        }
+    }
+
+    // Lines an align command works on: the selected lines, or the paragraph at the cursor
+    pub fn align_range(&self) -> (usize, usize) {
+        match self.selection.as_ref().filter(|s| s.is_active()) {
+            Some(selection) => {
+                let (start, end) = selection.get_range();
+                align::selected_lines(start, end)
+            }
+            None => align::paragraph_bounds(&self.current_buffer().lines, self.cursor_pos.line),
+        }
+    }
+
+    pub fn align_lines(&mut self, (start, end): (usize, usize), delimiter: &str) {
+        if self.is_read_only() {
+            return;
+        }
+        let aligned = align::align_on(&self.current_buffer().lines[start..end], delimiter);
+        self.replace_lines(start, aligned, end - start);
+    }
+
+    // Re-wrap the selected lines (or the paragraph at the cursor) to the configured column
+    pub fn reflow_paragraph(&mut self) {
+        let (start, end) = self.align_range();
+        if self.is_read_only() || start == end {
+            return;
+        }
+        let filetype = self.tab_manager.current_tab().filetype.clone();
+        let lines = &self.current_buffer().lines[start..end];
+        let reflowed = reflow::reflow(lines, self.config.wrap_column, self.config.tab_width, filetype.as_deref());
+        self.selection = None;
+        self.replace_lines(start, reflowed, end - start);
+    }
+
+    // Break the cursor's line at the wrap column once typing runs past it (auto_wrap in text files)
+    pub fn hard_wrap(&mut self) {
+        let filetype = self.tab_manager.current_tab().filetype.clone();
+        if !self.config.auto_wrap || !reflow::is_prose(filetype.as_deref()) {
+            return;
+        }
+        let pos = self.cursor_pos;
+        let line = &self.current_buffer().lines[pos.line];
+        let Some(wrap) = reflow::hard_wrap(line, self.config.wrap_column, self.config.tab_width, filetype.as_deref())
+        else {
+            return;
+        };
+        let split = line[..wrap.split].chars().count();
+        let prefix = wrap.next[..wrap.prefix].chars().count();
+        self.replace_lines(pos.line, vec![wrap.keep, wrap.next], 1);
+        if pos.column >= split {
+            self.cursor_pos = TextPosition { line: pos.line + 1, column: prefix + pos.column - split };
+        }
+    }
+
+    // Number the selected lines at the column where the selection starts
+    pub fn insert_sequence(&mut self, sequence: &Sequence) -> bool {
+        let Some((start, end)) = self.selection.as_ref().filter(|s| s.is_active()).map(|s| s.get_range()) else {
+            return false;
+        };
+        if self.is_read_only() || start.line == end.line {
+            return false;
+        }
+        let lines = &self.current_buffer().lines[start.line..=end.line];
+        let numbered = sequence::insert_column(lines, start.column, sequence);
+        self.selection = None;
+        self.replace_lines(start.line, numbered, end.line - start.line + 1);
+        true
+    }
+
+    // Resolve the merge conflict under the cursor (or the next one)
+    pub fn resolve_conflict(&mut self, resolution: Resolution) {
+        if self.is_read_only() {
+            return;
+        }
+        let lines = &self.current_buffer().lines;
+        let found = conflicts::find_conflicts(lines);
+        if let Some(conflict) = conflicts::conflict_for(&found, self.cursor_pos.line).copied() {
+            let resolved = conflict.resolve(lines, resolution);
+            self.jump_to(TextPosition { line: conflict.start, column: 0 });
+            self.replace_lines(conflict.start, resolved, conflict.end - conflict.start + 1);
+        }
+    }
+
+    // Move to the next merge conflict; false when there are none
+    pub fn next_conflict(&mut self) -> bool {
+        let found = conflicts::find_conflicts(&self.current_buffer().lines);
+        match conflicts::next_conflict(&found, self.cursor_pos.line) {
+            Some(conflict) => {
+                self.jump_to(TextPosition { line: conflict.start, column: 0 });
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn folds(&self) -> &Folds {
+        &self.tab_manager.current_tab().folds
+    }
+
+    // Fold or unfold the block under the cursor; false when there is no block
+    pub fn toggle_fold(&mut self) -> bool {
+        let line = self.cursor_pos.line;
+        let tab = self.tab_manager.current_tab_mut();
+        if !tab.folds.toggle(&tab.buffer.lines, line) {
+            return false;
+        }
+        // Keep the cursor on the placeholder row
+        if let Some(fold) = tab.folds.list().iter().find(|f| (f.start..=f.end).contains(&line)) {
+            self.cursor_pos = TextPosition { line: fold.start, column: 0 };
+        }
+        self.selection = None;
+        true
+    }
+
+    pub fn unfold_all(&mut self) {
+        self.tab_manager.current_tab_mut().folds.unfold_all();
+    }
+
+    // Buffer line shown `row` rows below the top of the editor
+    pub fn line_at_row(&self, row: usize) -> usize {
+        let folds = self.folds();
+        let last = self.current_buffer().lines.len().saturating_sub(1);
+        let mut line = self.scroll_offset.0.min(last);
+        for _ in 0..row {
+            let next = folds.next_visible(line);
+            if next > last {
+                break;
+            }
+            line = next;
+        }
+        line
+    }
+
+    // Keep the tab's caret, selection and search query in step with the editor, picking them back up when another tab or file becomes active
+    pub fn sync_tab_position(&mut self) {
+        let current = Some((self.tab_manager.active_tab_index, self.tab_manager.current_tab().filepath.clone()));
+        if self.tab_sync != current {
+            self.tab_sync = current;
+            let pos = self.tab_manager.current_tab().text_position();
+            self.cursor_pos = TextPosition { line: pos.line, column: pos.column };
+            self.scroll_offset = (pos.scroll, 0);
+            self.scroll_animation = None;
+            let lines = &self.current_buffer().lines;
+            self.selection = self.tab_manager.current_tab().selection.clone().filter(|s| s.fits(lines));
+            // A tab never searched in keeps the query from the one left
+            if let Some(query) = self.tab_manager.current_tab().last_search.clone() {
+                self.search_query = query;
+            }
+            self.clamp_cursor();
+            return;
+        }
+        let tab = self.tab_manager.current_tab_mut();
+        tab.selection = self.selection.clone();
+        if !self.search_query.is_empty() && tab.last_search.as_deref() != Some(self.search_query.as_str()) {
+            tab.last_search = Some(self.search_query.clone());
+        }
+        tab.set_text_position(FilePosition {
+            line: self.cursor_pos.line,
+            column: self.cursor_pos.column,
+            scroll: self.scroll_offset.0,
+        });
+    }
+
+    // Move folds along with edits and keep the cursor and scroll position out of hidden lines
+    pub fn sync_folds(&mut self) {
+        let tab_index = self.tab_manager.active_tab_index;
+        let line_count = self.current_buffer().lines.len();
+        let cursor_line = self.cursor_pos.line;
+        let (last_tab, last_count, last_cursor) = self.fold_sync;
+        self.fold_sync = (tab_index, line_count, cursor_line);
+
+        let tab = self.tab_manager.current_tab_mut();
+        if tab.folds.is_empty() {
+            return;
+        }
+        if last_tab == tab_index && last_count != line_count {
+            tab.folds.adjust(last_cursor.min(cursor_line), line_count as isize - last_count as isize);
+        }
+        tab.folds.validate(&tab.buffer.lines);
+
+        if tab.folds.is_hidden(self.cursor_pos.line) {
+            let start = tab.folds.prev_visible(self.cursor_pos.line + 1);
+            self.cursor_pos = TextPosition { line: start, column: 0 };
+        }
+        if tab.folds.is_hidden(self.scroll_offset.0) {
+            self.scroll_offset.0 = tab.folds.prev_visible(self.scroll_offset.0 + 1);
+        }
+    }
+
     // Save current file
     pub fn save(&mut self) -> Result<(), std::io::Error> {
+        // A file from an archive goes back into it
+        if self.tab_manager.current_tab().archive.is_some() {
+            let content = self.current_buffer().file_text(self.config.insert_final_newline);
+            match self.tab_manager.current_tab_mut().save_to_archive(&content) {
+                Ok(()) => self.toasts.info("Saved into the archive"),
+                Err(e) => self.toasts.error(format!("Failed to save: {}", e)),
+            }
+            return Ok(());
+        }
         if let Some(filename) = self.current_filename() {
             let filename = filename.to_string();
             self.save_as(&filename)?;
@@ -245,20 +815,26 @@ impl EditorState {
 
     // Save as new file
     pub fn save_as(&mut self, path: &str) -> Result<(), std::io::Error> {
-        use std::fs;
+        let insert_final_newline = self.config.insert_final_newline;
 
-        let buffer = self.current_buffer();
-        let last_line = buffer
-            .lines
-            .iter()
-            .rposition(|line| !line.is_empty())
-            .unwrap_or(0);
-        let content_lines: Vec<String> = buffer.lines.iter().take(last_line + 1).cloned().collect();
-        let content = content_lines.join("\n");
-
-        fs::write(path, content)?;
+        if self.config.file_history {
+            let _ = history::backup(path);
+        }
+        // Plain text is streamed out a line at a time instead of being joined up first
+        match self.tab_manager.current_tab().passphrase.as_deref() {
+            Some(passphrase) => {
+                let content = self.current_buffer().file_text(insert_final_newline);
+                crypto::write_file(path, &content, Some(passphrase))?;
+            }
+            None => {
+                crypto::check_plaintext_path(path)?;
+                self.current_buffer().save(path, insert_final_newline, &Progress::default())?;
+            }
+        }
+        self.stats.saved(path);
         self.set_filename(path.to_string());
         self.mark_clean();
+        self.toasts.info("Saved");
 
         let _ = self.tab_manager.save_session();
 
@@ -274,16 +850,19 @@ impl EditorState {
 
             let (start, end) = selection.get_range();
             let text = self.extract_text_range(start, end);
-            
-            // Try to use arboard (works on X11 and most Wayland compositors)
-            if let Ok(mut clipboard) = arboard::Clipboard::new() {
-                let _ = clipboard.set_text(&text);
-            }
-            
-            // Also store internally as fallback
-            self.clipboard_text = Some(text);
+            self.copy_to_clipboard(text);
         }
     }
+
+    pub fn copy_to_clipboard(&mut self, text: String) {
+        // Try to use arboard (works on X11 and most Wayland compositors)
+        if let Ok(mut clipboard) = arboard::Clipboard::new() {
+            let _ = clipboard.set_text(platform::to_clipboard(text.clone()));
+        }
+
+        // Also store internally as fallback
+        self.clipboard_text = Some(text);
+    }
     
     // Get the text from last copy operation
     pub fn get_clipboard_text(&self) -> Option<&str> {
@@ -293,6 +872,9 @@ impl EditorState {
     // Cut selection to clipboard
     pub fn cut_selection(&mut self) {
         self.copy_selection();
+        if self.is_read_only() {
+            return;
+        }
         if let Some(selection) = self.selection.take() {
             if selection.is_active() {
                 self.delete_selection(selection);
@@ -302,6 +884,10 @@ impl EditorState {
 
     // Paste from clipboard using arboard
     pub fn paste_from_clipboard(&mut self) {
+        if self.is_read_only() {
+            return;
+        }
+
         // Delete selection first if active
         if let Some(selection) = self.selection.take() {
             if selection.is_active() {
@@ -311,7 +897,7 @@ impl EditorState {
 
         // Try arboard first
         let text = if let Ok(mut clipboard) = arboard::Clipboard::new() {
-            clipboard.get_text().ok()
+            clipboard.get_text().ok().map(platform::from_clipboard)
         } else {
             None
         };
@@ -325,18 +911,51 @@ impl EditorState {
     }
 
     // Select all text
+    fn selection_range(&self) -> (TextPosition, TextPosition) {
+        match self.selection.as_ref().filter(|s| s.is_active()) {
+            Some(selection) => selection.get_range(),
+            None => (self.cursor_pos, self.cursor_pos),
+        }
+    }
+
+    // Grow the selection to the enclosing word, string, brackets, line, block or file
+    pub fn expand_selection(&mut self) {
+        let range = self.selection_range();
+        let lines = &self.tab_manager.current_tab().buffer.lines;
+        if let Some((start, end)) = self.selection_steps.expand(lines, range) {
+            self.jump_to(end);
+            self.selection = Some(Selection { anchor: start, cursor: end });
+        }
+    }
+
+    // Jump by paragraph or indentation block, extending the selection when `select` is set
+    pub fn jump(&mut self, jump: Jump, select: bool) {
+        let anchor = self.selection.as_ref().map_or(self.cursor_pos, |s| s.anchor);
+        let target = jumps::target(&self.current_buffer().lines, self.cursor_pos.line, jump, |i| self.line_metrics(i));
+        self.jump_to(target);
+        if select {
+            self.selection = Some(Selection { anchor, cursor: target });
+        }
+    }
+
+    // Step back through earlier expansions, or drop the selection when there are none
+    pub fn shrink_selection(&mut self) {
+        match self.selection_steps.shrink(self.selection_range()) {
+            Some((start, end)) if start != end => {
+                self.jump_to(end);
+                self.selection = Some(Selection { anchor: start, cursor: end });
+            }
+            _ => self.selection = None,
+        }
+    }
+
     pub fn select_all(&mut self) {
-        let last_line = self
-            .current_buffer()
-            .lines
-            .iter()
-            .rposition(|line| !line.is_empty())
-            .unwrap_or(0);
+        let last_line = self.current_buffer().logical_len() - 1;
         let last_col = self
             .current_buffer()
             .lines
             .get(last_line)
-            .map(|l| l.len())
+            .map(|l| l.chars().count())
             .unwrap_or(0);
 
         self.selection = Some(Selection {
@@ -390,46 +1009,272 @@ impl EditorState {
         result
     }
 
-    // Search functionality
-    pub fn perform_search(&mut self) {
-        if self.search_query.is_empty() {
+    // The selection, or the whole cursor line when nothing is selected
+    pub fn selection_or_line(&self) -> (TextPosition, TextPosition, String) {
+        let (start, end) = match self.selection.as_ref().filter(|s| s.is_active()) {
+            Some(selection) => selection.get_range(),
+            None => {
+                let line = self.cursor_pos.line;
+                let len = self.current_buffer().lines.get(line).map(|l| l.chars().count()).unwrap_or(0);
+                (TextPosition { line, column: 0 }, TextPosition { line, column: len })
+            }
+        };
+        (start, end, self.extract_text_range(start, end))
+    }
+
+    // Selected text, or the whole file when nothing is selected
+    pub fn selected_text(&self) -> Option<String> {
+        let selection = self.selection.as_ref().filter(|s| s.is_active())?;
+        let (start, end) = selection.get_range();
+        Some(self.extract_text_range(start, end))
+    }
+
+    pub fn selection_or_file(&self) -> String {
+        match self.selection.as_ref().filter(|s| s.is_active()) {
+            Some(selection) => {
+                let (start, end) = selection.get_range();
+                self.extract_text_range(start, end)
+            }
+            None => self.current_buffer().content(),
+        }
+    }
+
+    // Insert another file's contents at the cursor as one undoable edit
+    pub fn read_file_at_cursor(&mut self, path: &str) -> Result<(), std::io::Error> {
+        let text = completion::read_text(path)?;
+        let pos = self.cursor_pos;
+        self.replace_text(pos, pos, &text);
+        Ok(())
+    }
+
+    // Replace the text between two positions as one undoable edit, cursor after it
+    pub fn replace_text(&mut self, start: TextPosition, end: TextPosition, text: &str) {
+        if self.is_read_only() {
             return;
         }
+        let lines = &self.current_buffer().lines;
+        let prefix: String = lines[start.line].chars().take(start.column).collect();
+        let suffix: String = lines[end.line].chars().skip(end.column).collect();
 
-        let query = self.search_query.to_lowercase();
-        let mut matches = Vec::new();
+        self.selection = None;
+        self.cursor_pos = match text.rsplit_once('\n') {
+            Some((before, last_part)) => TextPosition {
+                line: start.line + before.matches('\n').count() + 1,
+                column: last_part.chars().count(),
+            },
+            None => TextPosition {
+                line: start.line,
+                column: prefix.chars().count() + text.chars().count(),
+            },
+        };
+        let combined = format!("{}{}{}", prefix, text, suffix);
+        let new_lines = combined.split('\n').map(String::from).collect();
+        self.replace_lines(start.line, new_lines, end.line - start.line + 1);
+    }
 
-        for (line_idx, line) in self.current_buffer().lines.iter().enumerate() {
-            let line_lower = line.to_lowercase();
-            let mut start = 0;
+    // Run an encode/decode transform over the selection (or cursor line)
+    pub fn apply_transform(&mut self, transform: TextTransform) -> Result<(), std::io::Error> {
+        let (start, end, text) = self.selection_or_line();
+        let result = transform.apply(&text)?;
+        if result != text {
+            self.replace_text(start, end, &result);
+        }
+        Ok(())
+    }
 
-            while let Some(pos) = line_lower[start..].find(&query) {
-                matches.push(TextPosition {
-                    line: line_idx,
-                    column: start + pos,
-                });
-                start += pos + 1;
+    // Open the URL at `pos` in the browser, false if there is none
+    pub fn open_link_at(&mut self, pos: TextPosition) -> bool {
+        let Some(line) = self.current_buffer().lines.get(pos.line) else {
+            return false;
+        };
+        match links::url_at(line, pos.column) {
+            Some(url) => {
+                if let Err(e) = links::open_url(url) {
+                    self.toasts.error(format!("Could not open link: {}", e));
+                }
+                true
             }
+            None => false,
         }
+    }
 
-        if !matches.is_empty() {
-            let first = matches[0];
-            self.cursor_pos = first;
-            self.selection = Some(Selection {
-                anchor: first,
-                cursor: TextPosition {
-                    line: first.line,
-                    column: first.column + self.search_query.len(),
-                },
-            });
+    // Open a path like "src/main.rs:42" under the cursor in a tab, false if there is none
+    pub fn go_to_file(&mut self) -> bool {
+        let base_dir = self
+            .tab_manager
+            .current_tab()
+            .filepath
+            .as_ref()
+            .and_then(|path| std::path::Path::new(path).parent().map(|dir| dir.to_path_buf()));
+        let target = self
+            .current_buffer()
+            .lines
+            .get(self.cursor_pos.line)
+            .and_then(|line| links::file_target_at(line, self.cursor_pos.column, base_dir.as_deref()));
+        let Some(target) = target else {
+            return false;
+        };
+
+        self.open_path(target.path.to_string_lossy().into_owned(), target.line);
+        true
+    }
+
+    // Open a file in tab 1, at a 1-based line when given; big files load on a worker thread
+    pub fn open_path(&mut self, path: String, line: Option<usize>) {
+        if tabs::is_large_file(&path) && !self.tab_manager.is_open(&path) {
+            let name = std::path::Path::new(&path).file_name().map_or(path.clone(), |n| n.to_string_lossy().into_owned());
+            let job = Job::spawn(format!("Loading {}", name), move |progress| Tab::load(&path, progress));
+            self.loading = Some((job, line));
+            return;
         }
+        let opened = self.tab_manager.open_file_in_new_tab(&path).map(|_| ());
+        self.finish_opening(opened, line);
     }
 
+    // Switch to a big file once its worker is done reading it
+    pub fn poll_loading(&mut self) {
+        let Some(tab) = self.loading.as_ref().and_then(|(job, _)| job.poll()) else {
+            return;
+        };
+        let (_, line) = self.loading.take().unwrap();
+        let opened = tab.map(|tab| {
+            if tab.url.is_some() {
+                self.toasts.info(fetch::SAVE_HINT);
+            }
+            self.tab_manager.add_opened_tab(tab);
+        });
+        self.finish_opening(opened, line);
+    }
+
+    fn finish_opening(&mut self, opened: Result<(), std::io::Error>, line: Option<usize>) {
+        if let Err(e) = opened {
+            self.toasts.error(format!("Failed to open file: {}", e));
+            return;
+        }
+        self.sync_tab_position();
+        if let Some(line) = line {
+            self.jump_to(TextPosition { line: line.saturating_sub(1), column: 0 });
+        }
+    }
+
+    pub fn todos(&self) -> Vec<TodoItem> {
+        todos::find_todos(&self.current_buffer().lines, &self.config.todo_keywords)
+    }
+
+    // Move the cursor to `pos`, scrolling it near the top when off screen
+    pub fn jump_to(&mut self, pos: TextPosition) {
+        self.tab_manager.current_tab_mut().folds.reveal(pos.line);
+        self.selection = None;
+        self.cursor_pos = pos;
+        self.clamp_cursor();
+        self.scroll_into_view(pos.line);
+    }
+
+    fn scroll_into_view(&mut self, line: usize) {
+        if line < self.scroll_offset.0 || line > self.scroll_offset.0 + 20 {
+            self.scroll_to(line.saturating_sub(5));
+        }
+    }
+
+    // Make `line` the top line, easing there unless smooth scrolling is turned off
+    pub fn scroll_to(&mut self, line: usize) {
+        if self.config.smooth_scrolling && line != self.scroll_offset.0 {
+            let from = self.animated_scroll().unwrap_or(self.scroll_offset.0 as f32);
+            self.scroll_animation = Some((from, Instant::now()));
+        }
+        self.scroll_offset.0 = line;
+    }
+
+    // Mouse wheel moved the view by `lines` (negative is up). Whole visible lines are scrolled,
+    // taking the cursor along when wheel_moves_caret is on or typewriter scrolling keeps it centered
+    pub fn wheel_scroll(&mut self, lines: f32) {
+        self.wheel_remainder += lines;
+        let steps = self.wheel_remainder.trunc() as isize;
+        self.wheel_remainder = self.wheel_remainder.fract();
+        if steps == 0 {
+            return;
+        }
+        let moves_caret = (self.config.wheel_moves_caret || self.config.typewriter_scrolling) && self.selection.is_none();
+        let last = self.current_buffer().lines.len().saturating_sub(1);
+        let folds = self.folds();
+        let (mut top, mut cursor) = (self.scroll_offset.0.min(last), self.cursor_pos.line);
+        for _ in 0..steps.unsigned_abs() {
+            let (next_top, next_cursor) = match steps > 0 {
+                true => (folds.next_visible(top), folds.next_visible(cursor)),
+                false => (folds.prev_visible(top), folds.prev_visible(cursor)),
+            };
+            if next_top > last || next_top == top {
+                break;
+            }
+            top = next_top;
+            cursor = next_cursor.min(last);
+        }
+        self.scroll_animation = None;
+        self.scroll_offset.0 = top;
+        if moves_caret {
+            self.cursor_pos.line = cursor;
+            self.clamp_cursor();
+        }
+    }
+
+    // Top of the view in fractional lines while an animation runs, None once it has finished
+    pub fn animated_scroll(&mut self) -> Option<f32> {
+        let (from, started) = self.scroll_animation?;
+        let t = started.elapsed().as_secs_f32() / SCROLL_ANIMATION.as_secs_f32();
+        if t >= 1.0 {
+            self.scroll_animation = None;
+            return None;
+        }
+        let eased = 1.0 - (1.0 - t).powi(3);
+        Some(from + (self.scroll_offset.0 as f32 - from) * eased)
+    }
+
+    // Search functionality
+    fn search_matches(&mut self) -> Vec<TextPosition> {
+        let lines = &self.tab_manager.current_tab().buffer.lines;
+        self.search_index
+            .find_all(lines, &self.search_query)
+            .into_iter()
+            .map(|(line, column)| TextPosition { line, column })
+            .collect()
+    }
+
+    fn select_search_match(&mut self, found: TextPosition) {
+        self.tab_manager.current_tab_mut().folds.reveal(found.line);
+        self.scroll_into_view(found.line);
+        self.cursor_pos = found;
+        self.selection = Some(Selection {
+            anchor: found,
+            cursor: TextPosition {
+                line: found.line,
+                column: found.column + self.search_query.len(),
+            },
+        });
+    }
+
+    pub fn perform_search(&mut self) {
+        if let Some(&first) = self.search_matches().first() {
+            self.select_search_match(first);
+        }
+    }
+
+    // The match after the cursor, wrapping around to the first one
     pub fn next_search_match(&mut self) {
-        self.perform_search();
+        let matches = self.search_matches();
+        let at = (self.cursor_pos.line, self.cursor_pos.column);
+        let found = matches.iter().find(|m| (m.line, m.column) > at).or(matches.first());
+        if let Some(&found) = found {
+            self.select_search_match(found);
+        }
     }
 
+    // The match before the cursor, wrapping around to the last one
     pub fn prev_search_match(&mut self) {
-        self.perform_search();
+        let matches = self.search_matches();
+        let at = (self.cursor_pos.line, self.cursor_pos.column);
+        let found = matches.iter().rev().find(|m| (m.line, m.column) < at).or(matches.last());
+        if let Some(&found) = found {
+            self.select_search_match(found);
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,39 @@
+// toasts - status messages stacked over the bottom right corner of a window
+use crate::core::toasts::{ToastKind, Toasts};
+use egui::{Align2, Color32, Context, RichText};
+use std::time::Instant;
+
+pub fn show(ctx: &Context, toasts: &mut Toasts) {
+    let now = Instant::now();
+    toasts.expire(now);
+    if let Some(at) = toasts.next_expiry() {
+        ctx.request_repaint_after(at.saturating_duration_since(now));
+    }
+    if toasts.is_empty() {
+        return;
+    }
+
+    let mut closed = None;
+    egui::Area::new(egui::Id::new("toasts"))
+        .anchor(Align2::RIGHT_BOTTOM, [-12.0, -36.0])
+        .show(ctx, |ui| {
+            for (i, toast) in toasts.iter().enumerate() {
+                let fill = match toast.kind {
+                    ToastKind::Info => Color32::from_rgb(30, 70, 40),
+                    ToastKind::Error => Color32::from_rgb(110, 30, 30),
+                };
+                egui::Frame::popup(ui.style()).fill(fill).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(RichText::new(&toast.message).color(Color32::WHITE));
+                        // Errors stay until closed
+                        if toast.kind == ToastKind::Error && ui.small_button("✕").clicked() {
+                            closed = Some(i);
+                        }
+                    });
+                });
+            }
+        });
+    if let Some(i) = closed {
+        toasts.remove(i);
+    }
+}
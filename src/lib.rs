@@ -0,0 +1,33 @@
+//! The editing engine behind Quick Notepad, usable without its terminal or GUI front ends.
+//!
+//! Everything lives in [`core`]. The parts most useful on their own:
+//!
+//! - [`core::buffer::Buffer`] - the text as lines
+//! - [`core::edit_history::EditHistory`] - undo and redo of [`core::edit_history::EditOperation`]s
+//! - [`core::selection`] - positions and selections in the text
+//! - [`core::search_index::SearchIndex`] and [`core::replace::Substitution`] - finding and replacing
+//! - [`core::batch`] - line ranges and substitutions over whole texts, as `quick --print` / `--replace` do
+//! - [`core::tabs`] - open files with their history, folds and positions
+//! - [`core::server`] - the engine driven over JSON-RPC on stdio, as `quick --server` does
+//! - [`core::syntax::SyntaxHighlighter`] - a line split into highlighted tokens, for 30-odd file types
+//! - [`core::widget::EditorWidget`] - a multi-line text field for other terminal apps, drawn into
+//!   a rectangle through the [`core::widget::Surface`] trait (crossterm support included)
+//!
+//! ```
+//! use quick_notepad::core::{batch, replace::Substitution, search_index::SearchIndex};
+//!
+//! let text = "let foo = 1;\nfoo += 1;\n";
+//! let rename = Substitution::parse("s/foo/count/g").unwrap();
+//! let (renamed, count) = batch::replace(text, &rename, None);
+//! assert_eq!(renamed, "let count = 1;\ncount += 1;\n");
+//! assert_eq!(count, 2);
+//!
+//! let lines: Vec<String> = renamed.lines().map(String::from).collect();
+//! assert_eq!(SearchIndex::default().find_all(&lines, "COUNT"), vec![(0, 4), (1, 0)]);
+//! ```
+//!
+//! Settings, sessions and notes are read from and written to the user's config and state
+//! directories (see [`core::paths`]), so code that should leave them alone sticks to the
+//! buffer, history, selection, search and batch modules.
+
+pub mod core;
@@ -1,4 +1,4 @@
-mod core;
+use quick_notepad::core;
 mod tui;
 mod gui;
 
@@ -7,44 +7,112 @@ use std::env;
 fn main() {
     
     let args: Vec<String> = env::args().collect();
-    let home = env::var("HOME").unwrap_or_default();
-    let expected_path = format!("{}/.local/bin/quick", home);
+    let expected_path = core::platform::install_dir().join(core::platform::EXE_NAME);
     
     // Get current executable path
     let current_exe = env::current_exe().expect("Failed to get current path");
 
     // SELF-INSTALL LOGIC
-    // If we are NOT running from ~/.local/bin and no specific flags are passed, 
+    // If we are NOT running from the install folder (~/.local/bin) and no specific flags are passed, 
     // we assume the user just "clicked" it for the first time.
-    if !current_exe.to_string_lossy().contains(&expected_path) && args.len() == 1 {
+    if !current_exe.to_string_lossy().contains(&*expected_path.to_string_lossy()) && args.len() == 1 {
         println!("✨ Quick Notepad: First-time setup detected...");
         install();
         
         // After installing, launch the GUI
-        gui::run(None);
+        gui::run(None, None);
         return;
     }
     
     // Check if GUI mode is requested
     let gui_mode = args.iter().any(|arg| arg == "--gui");
     
+    // Read-only and follow (tail -f) flags - follow implies read-only
+    let follow = args.iter().any(|arg| arg == "--follow");
+    let read_only = follow || args.iter().any(|arg| arg == "--read-only");
+    
+    // Share the file over the network (--host) or edit one shared elsewhere (--join address)
+    let collab_role = core::collab::Role::from_args(&args);
+    
+    // First argument that isn't a flag is the file to open, with ~ and $VARS expanded.
+    // With --join it is the address instead, and an http(s) URL is downloaded and opened read-only
+    let first_arg = args
+        .iter()
+        .skip(1)
+        .find(|arg| !arg.starts_with("--"))
+        .filter(|_| !matches!(collab_role, Some(core::collab::Role::Join(_))));
+    let url = first_arg.filter(|arg| core::fetch::is_url(arg)).cloned();
+    let file_arg = first_arg
+        .filter(|arg| !core::fetch::is_url(arg))
+        .map(|arg| core::paths::resolve(arg).to_string_lossy().into_owned());
+    // A folder becomes the project root, and the file finder opens on it instead of a tab.
+    // A .zip / .tar lists its files to pick from instead of opening as text
+    let folder = file_arg.clone().filter(|path| std::path::Path::new(path).is_dir());
+    let archive = file_arg.clone().filter(|path| folder.is_none() && core::archive::is_archive(path));
+    let file_arg = file_arg.filter(|path| folder.is_none() && !core::archive::is_archive(path));
+    
     // Check for shortcuts flag (works in both modes)
     if args.iter().any(|arg| arg == "--shortcuts") {
         core::shortcuts::Shortcuts::print_all();
         return;
     }
     
+    // The shortcuts in effect once the config's keybindings are applied; exits 1 when some were skipped
+    if args.iter().any(|arg| arg == "--dump-keys") {
+        let (keys, problems) = core::keybindings::dump(&core::config::Config::load().keybindings);
+        print!("{}", keys);
+        for problem in &problems {
+            eprintln!("quick: {}", problem);
+        }
+        if !problems.is_empty() {
+            std::process::exit(1);
+        }
+        return;
+    }
+    
+    // Headless JSON-RPC on stdio for tests and other programs
+    if args.iter().any(|arg| arg == "--server") {
+        if let Err(e) = core::server::run(std::io::stdin().lock(), std::io::stdout()) {
+            eprintln!("quick: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    
+    // Non-interactive printing and replacing for shell pipelines
+    if args.iter().any(|arg| arg == "--print" || arg == "--replace") {
+        if let Err(e) = core::batch::run(&args[1..]) {
+            eprintln!("quick: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    
+    // Restore the version the last update replaced
+    if args.iter().any(|arg| arg == "--rollback") {
+        match core::updater::Updater::rollback() {
+            Ok(()) => println!("Rolled back to the previous version"),
+            Err(e) => {
+                eprintln!("Rollback failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    
+    if let Some(folder) = &folder {
+        if let Err(e) = env::set_current_dir(folder) {
+            eprintln!("Cannot open folder {}: {}", folder, e);
+            std::process::exit(1);
+        }
+    }
+    
     if gui_mode {
         // Launch GUI mode
-        let file_path = args.iter()
-            .position(|arg| arg != "--gui" && arg != &args[0])
-            .map(|i| args[i].clone());
-        
-        gui::run(file_path);
+        gui::run(url.or(archive).or(folder).or(file_arg), collab_role);
     } else {
         // Launch TUI mode (existing code)
-        let mut editor = if args.len() > 1 {
-            let raw_path = &args[1];
+        let mut editor = if let Some(raw_path) = &file_arg {
             let path_buf = std::fs::canonicalize(raw_path).unwrap_or_else(|_| std::path::PathBuf::from(raw_path));
             
             // Extract full path for the backend
@@ -65,6 +133,11 @@ fn main() {
                     // We pass 'display_name' to the view so only the name shows in the status bar
                     // while the 'full_path' remains stored in the tab for saving logic
                     ed.set_filename_and_filetype(Some(display_name), friendly_type);
+                    if read_only {
+                        if let Err(e) = ed.open_read_only(follow) {
+                            eprintln!("Error following file {}: {}", full_path, e);
+                        }
+                    }
                     ed
                 },
                 Err(e) => {
@@ -77,44 +150,52 @@ fn main() {
             tui::TerminalEditor::new(core::buffer::Buffer::default())
         };
         
+        // Joining, downloading, archives and folders open their own tab, so no empty one is needed
+        if matches!(collab_role, Some(core::collab::Role::Join(_))) || url.is_some() || archive.is_some() || folder.is_some() {
+            editor.start_on_tab_zero = false;
+        }
+        editor.open_finder = folder.is_some();
+        editor.collab_role = collab_role;
+        editor.open_url = url;
+        editor.open_archive = archive;
         editor.run();
     }
     
     fn install() {
         use std::fs;
         use std::env;
-        use std::path::Path;
+        use core::platform;
     
-        let home = env::var("HOME").expect("Could not find HOME directory");
-        let bin_dir = format!("{}/.local/bin", home);
-        let target_bin_path = format!("{}/quick", bin_dir);
-        let icon_dir = format!("{}/.local/share/icons/hicolor/512x512/apps", home);
-        let desktop_dir = format!("{}/.local/share/applications", home);
+        let bin_dir = platform::install_dir();
+        let target_bin_path = bin_dir.join(platform::EXE_NAME);
     
-        // Move the binary to ~/.local/bin
+        // Copy the binary to the install folder
         let current_exe = env::current_exe().expect("Failed to get current path");
         let _ = fs::create_dir_all(&bin_dir);
     
         // Avoid infinite loops: only copy if we aren't already the target
-        if current_exe != Path::new(&target_bin_path) {
+        if current_exe != target_bin_path {
             if let Err(e) = fs::copy(&current_exe, &target_bin_path) {
                 eprintln!("❌ Failed to move binary to bin: {}", e);
             } else {
                 // Set executable permissions on the new copy
-                #[cfg(unix)]
-                {
-                    use std::os::unix::fs::PermissionsExt;
-                    let _ = fs::set_permissions(&target_bin_path, fs::Permissions::from_mode(0o755));
-                }
-                println!("✅ Binary installed to ~/.local/bin");
+                let _ = platform::make_executable(&target_bin_path);
+                println!("✅ Binary installed to {}", bin_dir.display());
             }
         }
     
+        // Menu entries are a freedesktop thing; macOS and Windows users start the binary directly
+        if cfg!(not(all(unix, not(target_os = "macos")))) {
+            return;
+        }
+        let home = core::paths::home();
+        let icon_dir = home.join(".local/share/icons/hicolor/512x512/apps");
+        let desktop_dir = home.join(".local/share/applications");
+    
         // Extract Baked-in Icon
         let icon_bytes = include_bytes!("../assets/icon.png");
         let _ = fs::create_dir_all(&icon_dir);
-        let icon_path = format!("{}/quick_notepad.png", icon_dir);
-        let _ = fs::write(&icon_path, icon_bytes);
+        let _ = fs::write(icon_dir.join("quick_notepad.png"), icon_bytes);
     
         // Create Desktop Entry
         let _ = fs::create_dir_all(&desktop_dir);
@@ -128,11 +209,11 @@ fn main() {
             Categories=Utility;TextEditor;\n\
             Terminal=false\n\
             MimeType=text/plain;\n",
-            bin = target_bin_path
+            bin = target_bin_path.display()
         );
     
-        let _ = fs::write(format!("{}/quick-notepad.desktop", desktop_dir), desktop_entry);
+        let _ = fs::write(desktop_dir.join("quick-notepad.desktop"), desktop_entry);
         
         println!("✅ Desktop integration complete! You can now find Quick Notepad in your menu.");
     }
-}
\ No newline at end of file
+}
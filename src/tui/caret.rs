@@ -5,27 +5,9 @@ use crossterm::{
     style::Print,
     queue,
 };
-use std::io::{ stdout, Error, Write };
+use std::io::{ stdout, Error };
 
-#[derive(Copy, Clone, Debug)]
-pub struct Position {
-    pub x: u16,
-    pub y: u16,
-}
-
-impl Position {
-    pub const MARGIN: u16 = 4; // Width of the margin
-    pub const HEADER: u16 = 1; // Height of the header
-}
-
-impl Default for Position {
-    fn default() -> Self {
-        Self { 
-            x: Self::MARGIN, 
-            y: Self::HEADER 
-        } 
-    }
-}
+pub use crate::core::selection::Position;
 
 pub struct Caret {
     pub color: &'static str,
@@ -61,7 +43,6 @@ impl Caret {
     pub fn move_to(&mut self, pos: Position) -> Result<(), Error> {
         self.position = pos;
         queue!(stdout(), MoveTo(pos.x, pos.y))?;
-        stdout().flush()?;
         Ok(())
     }
     
@@ -0,0 +1,1614 @@
+// commands module - what every action does. Each one runs against an EditorContext: the
+// view, caret, open tabs and edit history of the editor, and the footer question a command
+// asks, which the main loop then feeds keys until it is answered
+use super::caret::{Caret, Position};
+use super::compare::Compare;
+use super::dialog::{Dialog, Followup, Row};
+use super::terminal::Terminal;
+use super::view::{self, Buffer, Purpose, View};
+use crate::core::{
+    actions::Action,
+    align, archive, calc,
+    conflicts::Resolution,
+    crypto,
+    edit_history::{EditHistory, EditOperation},
+    finder,
+    hash::HashAlgorithm,
+    history,
+    jumps::Jump,
+    keymap::{self, KillRing},
+    links, palette, platform,
+    progress::{Job, Progress},
+    reflow,
+    report::{self, IssueReport, REPORT_COPIED, REPORT_OPENED},
+    scratch,
+    selection::TextPosition,
+    stats::Tracker,
+    tabs::{self, get_friendly_filetype, Tab, TabManager},
+    toasts::ToastKind,
+    todos,
+    transforms::TextTransform,
+    updater::Updater,
+};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind};
+use std::io::Error;
+
+pub struct EditorContext<'a> {
+    pub view: &'a mut View,
+    pub caret: &'a mut Caret,
+    pub tabs: &'a mut TabManager,
+    pub kill_ring: &'a mut KillRing,
+    pub kill_appends: bool, // The previous command was a kill, so the next one joins it
+    pub stats: &'a mut Tracker,
+    pub dialog: &'a mut Option<Dialog>,   // Question waiting for an answer in the footer
+    pub compare: &'a mut Option<Compare>, // Diff drawn over the editor, taking the keys until Esc
+    pub quit: &'a mut bool,
+}
+
+impl EditorContext<'_> {
+    // Undo and redo of the current tab
+    pub fn history(&mut self) -> &mut EditHistory {
+        &mut self.tabs.current_tab_mut().edit_history
+    }
+
+    pub fn push_edit(&mut self, operation: Option<EditOperation>) {
+        if let Some(op) = operation {
+            let tab = self.tabs.current_tab_mut();
+            tab.edit_history.push(op);
+            tab.has_unsaved_changes = true;
+        }
+    }
+
+    // Typing or pasting over a selection replaces it, and the deletion undoes along with it
+    fn delete_selection(&mut self) -> Result<(), Error> {
+        let operation = self.view.delete_selection(self.caret)?;
+        self.push_edit(operation);
+        Ok(())
+    }
+
+    // Show the current tab's text and state after switching to it
+    pub fn sync_view_to_tab(&mut self) {
+        let tab = self.tabs.current_tab_mut();
+        // A tab still holding its text was switched to, reloaded or unlocked
+        if !tab.buffer.is_taken() {
+            self.view.buffer = tab.buffer.take();
+            self.view.selection = tab.selection.take().filter(|s| s.fits(&self.view.buffer.lines));
+            self.view.folds = tab.folds.clone();
+            self.view.folds.validate(&self.view.buffer.lines);
+            let line_count = self.view.buffer.lines.len();
+            self.view.search_state =
+                tab.search_state.take().filter(|state| state.matches.iter().all(|m| m.line < line_count));
+            // A tab never searched in repeats the query from the one left
+            if tab.last_search.is_some() {
+                self.view.last_search = tab.last_search.clone();
+            }
+        }
+        let tab = self.tabs.current_tab();
+        self.view.scroll_offset = tab.scroll_offset;
+        self.view.filename = tab.filename.clone();
+        self.view.filetype = tab.filetype.clone();
+        self.view.read_only = tab.read_only || tab.lock_holder.is_some();
+        self.view.following = tab.is_following();
+        self.view.scratch = tab.scratch;
+        self.view.locked = tab.locked;
+        self.view.encrypted = tab.is_encrypted();
+        self.view.shared = tab.collab.is_some();
+        self.view.peers = tab.collab.as_ref().map_or(Vec::new(), |session| session.peers().to_vec());
+        self.view.needs_redraw = true;
+    }
+
+    // Hand the text and state back to the current tab before leaving it
+    pub fn sync_tab_to_view(&mut self) {
+        let tab = self.tabs.current_tab_mut();
+        tab.buffer = self.view.buffer.take();
+        tab.selection = self.view.selection.take();
+        tab.folds = self.view.folds.clone();
+        tab.search_state = self.view.search_state.take();
+        tab.last_search = self.view.last_search.clone();
+        tab.scroll_offset = self.view.scroll_offset;
+        tab.cursor_pos = self.caret.get_position();
+    }
+
+    // False, with a toast, outside Markdown files
+    fn in_markdown(&mut self) -> bool {
+        let markdown = self.view.filetype.as_deref() == Some("Markdown");
+        if !markdown {
+            self.view.notify(ToastKind::Error, "Only available in Markdown files".to_string());
+        }
+        markdown
+    }
+
+    // Ask a question in the footer; the main loop feeds it keys until it is answered
+    pub fn ask(&mut self, dialog: Dialog) {
+        *self.dialog = Some(dialog);
+        self.show_dialog();
+    }
+
+    // Put the question in the footer, with the file browser when it has one
+    pub fn show_dialog(&mut self) {
+        if let Some(dialog) = self.dialog.as_ref() {
+            let (kind, message, input) = dialog.prompt();
+            self.view.show_prompt(kind, message);
+            self.view.set_prompt_input(input);
+            self.view.file_picker = dialog.picker().cloned();
+        }
+    }
+
+    // Run a job on a worker thread, drawing its progress in the footer until it finishes.
+    // Esc asks the worker to stop, which then hands back its cancelled result
+    pub fn wait_for<T: Send + 'static>(&mut self, job: Job<T>) -> Result<T, Error> {
+        let result = loop {
+            if let Some(result) = job.poll() {
+                break result;
+            }
+            self.view.progress = Some((job.label.clone(), job.progress.fraction()));
+            self.view.needs_redraw = true;
+            self.view.render_if_needed(self.caret, false)?;
+            Terminal::execute()?;
+            if Terminal::poll_event(std::time::Duration::from_millis(100))? {
+                if let Event::Key(KeyEvent { code: KeyCode::Esc, kind: KeyEventKind::Press, .. }) = Terminal::read_event()? {
+                    job.progress.cancel();
+                }
+            }
+        };
+        self.view.progress = None;
+        self.view.needs_redraw = true;
+        Ok(result)
+    }
+
+    // Scroll a followed file to its last line, with the caret on it
+    pub fn pin_to_bottom(&mut self) -> Result<(), Error> {
+        let line_count = self
+            .tabs
+            .current_tab()
+            .follower
+            .as_ref()
+            .map(|f| f.line_count())
+            .unwrap_or(0);
+        let last_line = line_count.saturating_sub(1);
+
+        let size = Terminal::get_size()?;
+        let visible_rows = size.height.saturating_sub(Position::HEADER + 1) as usize;
+        self.view.scroll_offset = line_count.saturating_sub(visible_rows);
+        self.view.needs_redraw = true;
+        self.view.render_if_needed(self.caret, false)?;
+
+        let (x, y) = view::helpers::text_to_screen_pos(
+            self.view,
+            TextPosition { line: last_line, column: 0 },
+        );
+        self.caret.move_to(Position { x, y })?;
+        Ok(())
+    }
+
+    // Decrypt the current tab, asking again after a wrong passphrase until cancelled
+    pub fn unlock_current_tab(&mut self) {
+        let name = self
+            .tabs
+            .current_tab()
+            .filename
+            .clone()
+            .unwrap_or_default();
+        self.ask(Dialog::passphrase(format!("Passphrase for {}: ", name), Followup::Unlock));
+    }
+
+    // Ask for a new passphrase twice so a typo can't lock the note forever
+    fn ask_new_passphrase(&mut self) {
+        self.ask(Dialog::passphrase("New passphrase: ".to_string(), Followup::NewPassphrase(None)));
+    }
+
+    // Open a file in tab 1, at a 1-based line when given. Big files load behind a progress
+    // bar and archives list their files instead
+    pub fn open_path(&mut self, path: String, line: Option<usize>) -> Result<(), Error> {
+        if archive::is_archive(&path) {
+            self.list_archive(path);
+            return Ok(());
+        }
+        self.sync_tab_to_view();
+        let opened = if tabs::is_large_file(&path) && !self.tabs.is_open(&path) {
+            let name = std::path::Path::new(&path).file_name().map_or(path.clone(), |n| n.to_string_lossy().into_owned());
+            let job = Job::spawn(format!("Loading {}", name), move |progress| Tab::load(&path, progress));
+            self.wait_for(job)?.map(|tab| self.tabs.add_opened_tab(tab))
+        } else {
+            self.tabs.open_file_in_new_tab(&path)
+        };
+        if let Err(e) = opened {
+            self.sync_view_to_tab();
+            self.view.notify(ToastKind::Error, e.to_string());
+            return Ok(());
+        }
+        self.sync_view_to_tab();
+        self.caret.move_to(self.tabs.current_tab().cursor_pos)?;
+
+        if let Some(line_number) = line {
+            let last_line = self.view.buffer.lines.len().saturating_sub(1);
+            let line = line_number.saturating_sub(1).min(last_line);
+            self.view.jump_to(TextPosition { line, column: 0 }, self.caret)?;
+        }
+        self.view.render(self.caret)?;
+        Ok(())
+    }
+
+    // Show the files in an archive to pick one to open
+    pub fn list_archive(&mut self, path: String) {
+        match archive::list(&path) {
+            Ok(names) if names.is_empty() => self.view.notify(ToastKind::Error, format!("{} has no files", path)),
+            Ok(names) => {
+                let message = format!("Open from {}:", std::path::Path::new(&path).file_name().map_or(path.clone(), |n| n.to_string_lossy().into_owned()));
+                self.ask(Dialog::select(message, names.clone(), Followup::OpenFromArchive(path, names)));
+            }
+            Err(e) => self.view.notify(ToastKind::Error, format!("Cannot read {}: {}", path, e)),
+        }
+    }
+
+    // Fuzzy-find a file under the project folder by typing, Up/Down to pick, Enter opens it
+    pub fn find_file(&mut self) -> Result<(), Error> {
+        let root = std::env::current_dir()?;
+        let files = finder::files(&root);
+        if files.is_empty() {
+            self.view.notify(ToastKind::Info, format!("No files in {}", root.display()));
+            return Ok(());
+        }
+        let listed = files.clone();
+        let find = move |query: &str| {
+            finder::rank(&listed, query)
+                .into_iter()
+                .map(|i| Row { item: i, name: listed[i].clone(), detail: None })
+                .collect()
+        };
+        self.ask(Dialog::filter("No matching file".to_string(), find, Followup::OpenFound(root, files)));
+        Ok(())
+    }
+
+    // Ask a search query, Alt+L limiting it to the selection or the caret's line
+    fn ask_query(&mut self, verb: &str, followup: Followup) {
+        let (scope, label) = self.view.search_region(self.caret);
+        self.ask(Dialog::query(verb, scope, label, followup));
+    }
+
+    // Diff the current tab against another one in a view over the editor
+    pub fn compare_with(&mut self, other: usize) -> Result<(), Error> {
+        let current = self.tabs.active_tab_index;
+        if let Err(e) = self.tabs.load_tab(other) {
+            self.view.notify(ToastKind::Error, e.to_string());
+            return Ok(());
+        }
+
+        let tabs = &self.tabs.tabs;
+        let lines = |buffer: &Buffer| buffer.content().split('\n').map(String::from).collect();
+        let compare = Compare::new(
+            (tabs[current].display_name(current), tabs[other].display_name(other)),
+            lines(&self.view.buffer),
+            lines(&tabs[other].buffer),
+        );
+        self.show_compare(compare)
+    }
+
+    // The diff covers the editor until Esc
+    pub fn show_compare(&mut self, mut compare: Compare) -> Result<(), Error> {
+        self.view.covered = true;
+        compare.open()?;
+        *self.compare = Some(compare);
+        Ok(())
+    }
+
+    // The file browser over the text, starting in the current file's folder
+    fn browse_files(&mut self, purpose: Purpose, followup: Followup) -> Result<(), Error> {
+        let dir = self
+            .tabs
+            .current_tab()
+            .filepath
+            .as_ref()
+            .and_then(|path| std::path::Path::new(path).parent().map(|dir| dir.to_path_buf()))
+            .map_or_else(std::env::current_dir, Ok)?;
+        self.ask(Dialog::browse(purpose, dir, followup));
+        Ok(())
+    }
+
+    // Save to the tab's file, or pick one when it has none yet
+    pub fn save_file(&mut self) -> Result<(), Error> {
+        // CRITICAL FIX: Use filepath (full path) instead of filename (display name)
+        let filepath_opt = self.tabs.current_tab().filepath.clone();
+
+        // A file from an archive goes back into it
+        if self.tabs.current_tab().archive.is_some() {
+            let content = self.view.buffer.file_text(self.view.config.insert_final_newline);
+            match self.tabs.current_tab_mut().save_to_archive(&content) {
+                Ok(()) => self.view.notify(ToastKind::Info, "Saved into the archive".to_string()),
+                Err(e) => self.view.notify(ToastKind::Error, format!("Failed to save: {}", e)),
+            }
+            self.view.render_if_needed(self.caret, self.tabs.current_tab().has_unsaved_changes)?;
+            return Ok(());
+        }
+
+        if let Some(filepath) = filepath_opt {
+            match self.write_tab_file(&filepath) {
+                Ok(true) => {
+                    self.tabs.current_tab_mut().mark_saved();
+                    let _ = self.tabs.save_session();
+                    self.view.notify(ToastKind::Info, "Saved".to_string());
+                    self.view.render_if_needed(self.caret, false)?;
+                }
+                Ok(false) => {}
+                Err(e) => return Err(e),
+            }
+        } else {
+            self.browse_files(Purpose::Save, Followup::SaveAs)?;
+        }
+        Ok(())
+    }
+
+    // Save the tab under a new path, which it keeps from now on
+    pub fn save_to(&mut self, full_path: String) -> Result<(), Error> {
+        let path_buf = std::path::PathBuf::from(&full_path);
+        let display_name = path_buf
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| full_path.clone());
+
+        let raw_ext = path_buf.extension()
+            .map(|ext| ext.to_string_lossy().into_owned());
+        let friendly_filetype = get_friendly_filetype(raw_ext);
+
+        // Store BOTH the display name and the full path. A scratch note or archive member
+        // saved somewhere else is an ordinary file from then on
+        let tab = self.tabs.current_tab_mut();
+        tab.filename = Some(display_name.clone());
+        tab.filepath = Some(full_path.clone());
+        tab.filetype = friendly_filetype.clone();
+        tab.archive = None;
+        tab.scratch = false;
+        self.view.scratch = false;
+        self.view.set_filename_and_filetype(Some(display_name), friendly_filetype);
+
+        // Save to the FULL PATH
+        match self.write_tab_file(&full_path) {
+            Ok(true) => {
+                self.tabs.current_tab_mut().mark_saved();
+                let tab = self.tabs.current_tab();
+                self.view.read_only = tab.read_only || tab.lock_holder.is_some();
+                let _ = self.tabs.save_session();
+                self.view.notify(ToastKind::Info, format!("Saved {}", full_path));
+                self.view.render_if_needed(self.caret, false)?;
+            }
+            Ok(false) => {}
+            Err(e) => {
+                self.view.notify(
+                    ToastKind::Error,
+                    format!("Failed to save: {}", e),
+                );
+                self.view.render_if_needed(self.caret, true)?;
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    // Write the tab content, encrypting it when the tab has a passphrase.
+    // Saving to a new .qnenc path asks for one first. Returns false if cancelled.
+    fn write_tab_file(&mut self, path: &str) -> Result<bool, Error> {
+        if crypto::is_encrypted_path(path) && self.tabs.current_tab().passphrase.is_none() {
+            // Saved once the passphrase has been typed twice
+            self.ask_new_passphrase();
+            return Ok(false);
+        }
+
+        if self.view.config.file_history {
+            let _ = history::backup(path);
+        }
+        let insert_final_newline = self.view.config.insert_final_newline;
+        match self.tabs.current_tab().passphrase.clone() {
+            Some(passphrase) => {
+                let content = self.view.buffer.file_text(insert_final_newline);
+                crypto::write_file(path, &content, Some(&passphrase))?;
+            }
+            None => {
+                crypto::check_plaintext_path(path)?;
+                self.stream_to_file(path, insert_final_newline)?;
+            }
+        }
+        self.stats.saved(path);
+        Ok(true)
+    }
+
+    // Plain text goes out a line at a time; big buffers are written on a worker thread while
+    // the footer shows how far it got. There is no stopping halfway, that would leave half a file
+    fn stream_to_file(&mut self, path: &str, insert_final_newline: bool) -> Result<(), Error> {
+        let buffer = &self.view.buffer;
+        let progress = Progress::default();
+        if buffer.file_len(insert_final_newline) < tabs::LARGE_FILE as usize {
+            return buffer.save(path, insert_final_newline, &progress);
+        }
+
+        let name = std::path::Path::new(path).file_name().map_or_else(|| path.into(), |name| name.to_string_lossy());
+        let label = format!("Saving {}", name);
+        std::thread::scope(|scope| {
+            let worker = scope.spawn(|| buffer.save(path, insert_final_newline, &progress));
+            while !worker.is_finished() {
+                view::draw_progress(&label, progress.fraction())?;
+                Terminal::execute()?;
+                std::thread::sleep(std::time::Duration::from_millis(100));
+            }
+            worker.join().unwrap_or_else(|_| Err(Error::other("Saving stopped unexpectedly")))
+        })?;
+        self.view.needs_redraw = true;
+        Ok(())
+    }
+}
+
+pub trait EditorCommand {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error>;
+}
+
+// The command behind an action, None for the ones that are not commands, like mouse events
+pub fn command(action: Action, event: &KeyEvent) -> Option<Box<dyn EditorCommand>> {
+    let command: Box<dyn EditorCommand> = match action {
+        Action::Left => Box::new(Move { motion: Motion::Left, select: false }),
+        Action::Right => Box::new(Move { motion: Motion::Right, select: false }),
+        Action::Up => Box::new(Move { motion: Motion::Up, select: false }),
+        Action::Down => Box::new(Move { motion: Motion::Down, select: false }),
+        Action::Top => Box::new(Move { motion: Motion::Top, select: false }),
+        Action::Bottom => Box::new(Move { motion: Motion::Bottom, select: false }),
+        Action::MaxLeft => Box::new(Move { motion: Motion::LineStart, select: false }),
+        Action::MaxRight => Box::new(Move { motion: Motion::LineEnd, select: false }),
+        Action::SelectLeft => Box::new(Move { motion: Motion::Left, select: true }),
+        Action::SelectRight => Box::new(Move { motion: Motion::Right, select: true }),
+        Action::SelectUp => Box::new(Move { motion: Motion::Up, select: true }),
+        Action::SelectDown => Box::new(Move { motion: Motion::Down, select: true }),
+        Action::SelectTop => Box::new(Move { motion: Motion::Top, select: true }),
+        Action::SelectBottom => Box::new(Move { motion: Motion::Bottom, select: true }),
+        Action::SelectMaxLeft => Box::new(Move { motion: Motion::LineStart, select: true }),
+        Action::SelectMaxRight => Box::new(Move { motion: Motion::LineEnd, select: true }),
+        Action::WordLeft => Box::new(MoveWord { right: false }),
+        Action::WordRight => Box::new(MoveWord { right: true }),
+        Action::Jump(jump) => Box::new(JumpTo { jump, select: false }),
+        Action::SelectJump(jump) => Box::new(JumpTo { jump, select: true }),
+        Action::SelectAll => Box::new(SelectAll),
+        Action::ExpandSelection => Box::new(ExpandSelection),
+        Action::ShrinkSelection => Box::new(ShrinkSelection),
+
+        Action::Print => match event.code {
+            KeyCode::Tab => Box::new(InsertTab),
+            KeyCode::Char(c) => Box::new(Type(c)),
+            _ => return None,
+        },
+        Action::NextLine => Box::new(NewLine),
+        Action::Backspace => Box::new(Backspace),
+        Action::Delete => Box::new(DeleteChar),
+        Action::Undo => Box::new(Undo),
+        Action::Redo => Box::new(Redo),
+        Action::Copy => Box::new(Copy),
+        Action::Cut => Box::new(Cut),
+        Action::Paste => Box::new(Paste),
+        Action::KillLine => Box::new(KillLine),
+        Action::Yank => Box::new(Yank),
+        Action::ToggleCheckbox => Box::new(ToggleCheckbox),
+        Action::MoveDoneItems => Box::new(MoveDoneItems),
+        Action::FormatTable => Box::new(FormatTable),
+        Action::ReflowParagraph => Box::new(ReflowParagraph),
+        Action::Transform(transform) => Box::new(Transform(transform)),
+        Action::NextConflict => Box::new(NextConflict),
+        Action::ResolveConflict(resolution) => Box::new(ResolveConflict(resolution)),
+
+        Action::New => Box::new(NewTab { scratch: false }),
+        Action::NewScratch => Box::new(NewTab { scratch: true }),
+        Action::OpenLink => Box::new(OpenLink),
+        Action::ToggleAutoWrap => Box::new(ToggleAutoWrap),
+        Action::ToggleMouse => Box::new(ToggleMouse),
+        Action::ToggleCtrlShortcuts => Box::new(ToggleShortcuts),
+        Action::UsageStats => Box::new(UsageStats),
+        Action::ReportIssue => Box::new(ReportIssue),
+        Action::ToggleFold => Box::new(ToggleFold),
+        Action::UnfoldAll => Box::new(UnfoldAll),
+        Action::DetachTab => Box::new(GuiOnly("Separate windows are only available in the GUI (quick --gui)")),
+
+        Action::SwitchTab(tab) => Box::new(SwitchTab(tab)),
+        Action::Save => Box::new(Save),
+        Action::SaveAs => Box::new(SaveAs),
+        Action::OpenFile => Box::new(OpenFile),
+        Action::SaveEncrypted => Box::new(SaveEncrypted),
+        Action::RenameFile => Box::new(RenameFile),
+        Action::DeleteFile => Box::new(DeleteFile),
+        Action::RestoreDeletedFile => Box::new(RestoreDeletedFile),
+        Action::FileHistory => Box::new(FileHistory),
+        Action::OpenScratch => Box::new(OpenScratch),
+        Action::FindFile => Box::new(FindFile),
+        Action::GoToFile => Box::new(GoToFile),
+        Action::ReadFile => Box::new(ReadFile),
+        Action::WriteSelection => Box::new(WriteSelection),
+        Action::AppendToFile => Box::new(AppendToFile),
+        Action::CompareTabs => Box::new(CompareTabs),
+        Action::ToggleFollow => Box::new(ToggleFollow),
+        Action::ListTodos => Box::new(ListTodos),
+        Action::Evaluate => Box::new(Evaluate),
+        Action::Hash(algorithm) => Box::new(Hash(algorithm)),
+        Action::AlignSelection => Box::new(AlignSelection),
+        Action::InsertSequence => Box::new(InsertSequence),
+        Action::Search => Box::new(Search),
+        Action::RepeatSearch => Box::new(RepeatSearch),
+        Action::Replace => Box::new(Replace),
+        Action::CountMatches => Box::new(CountMatches),
+        Action::CommandPalette => Box::new(CommandPalette),
+        Action::CheckUpdate => Box::new(CheckUpdate),
+        Action::RollbackUpdate => Box::new(RollbackUpdate),
+        Action::Quit => Box::new(Quit),
+        _ => return None,
+    };
+    Some(command)
+}
+
+#[derive(Clone, Copy)]
+enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    Top,
+    Bottom,
+    LineStart,
+    LineEnd,
+}
+
+// Arrow keys, Home/End and PageUp/PageDown; with Shift they extend the selection
+struct Move {
+    motion: Motion,
+    select: bool,
+}
+
+impl EditorCommand for Move {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        let (view, caret) = (&mut *ctx.view, &mut *ctx.caret);
+        if self.select {
+            let direction = match self.motion {
+                Motion::Left => "left",
+                Motion::Right => "right",
+                Motion::Up => "up",
+                Motion::Down => "down",
+                Motion::Top => "top",
+                Motion::Bottom => "bottom",
+                Motion::LineStart => "max_left",
+                Motion::LineEnd => "max_right",
+            };
+            return view.move_with_selection(direction, caret);
+        }
+        match self.motion {
+            Motion::Left => view.move_left(caret),
+            Motion::Right => view.move_right(caret),
+            Motion::Up => view.move_up(caret),
+            Motion::Down => view.move_down(caret),
+            Motion::Top => view.move_top(caret),
+            Motion::Bottom => view.move_bottom(caret),
+            Motion::LineStart => view.move_max_left(caret),
+            Motion::LineEnd => view.move_max_right(caret),
+        }
+    }
+}
+
+struct MoveWord {
+    right: bool,
+}
+
+impl EditorCommand for MoveWord {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        let cursor = ctx.view.caret_chars(ctx.caret);
+        let target = match self.right {
+            true => keymap::word_right(&ctx.view.buffer.lines, cursor),
+            false => keymap::word_left(&ctx.view.buffer.lines, cursor),
+        };
+        ctx.view.move_to_chars(target, ctx.caret)
+    }
+}
+
+struct JumpTo {
+    jump: Jump,
+    select: bool,
+}
+
+impl EditorCommand for JumpTo {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        ctx.view.jump(self.jump, self.select, ctx.caret)
+    }
+}
+
+struct SelectAll;
+
+impl EditorCommand for SelectAll {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        ctx.view.select_all(ctx.caret)
+    }
+}
+
+struct ExpandSelection;
+
+impl EditorCommand for ExpandSelection {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        ctx.view.expand_selection(ctx.caret)
+    }
+}
+
+struct ShrinkSelection;
+
+impl EditorCommand for ShrinkSelection {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        ctx.view.shrink_selection(ctx.caret)
+    }
+}
+
+// Alt+F: collapse the brace or indentation block under the caret into one row, or open it again
+struct ToggleFold;
+
+impl EditorCommand for ToggleFold {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        if !ctx.view.toggle_fold(ctx.caret)? {
+            ctx.view.notify(ToastKind::Error, "No block to fold here".to_string());
+        }
+        Ok(())
+    }
+}
+
+struct UnfoldAll;
+
+impl EditorCommand for UnfoldAll {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        ctx.view.unfold_all(ctx.caret)
+    }
+}
+
+// A typed character, hard wrapping prose as it goes when auto wrap is on
+struct Type(char);
+
+impl EditorCommand for Type {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        ctx.delete_selection()?;
+        let operation = ctx.view.type_character(self.0, ctx.caret)?;
+        ctx.push_edit(operation);
+        if ctx.view.config.auto_wrap && !self.0.is_whitespace() && reflow::is_prose(ctx.view.filetype.as_deref()) {
+            let operation = ctx.view.hard_wrap(ctx.caret)?;
+            ctx.push_edit(operation);
+        }
+        Ok(())
+    }
+}
+
+// Tab types 4 spaces
+struct InsertTab;
+
+impl EditorCommand for InsertTab {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        ctx.delete_selection()?;
+        for _ in 0..4 {
+            let operation = ctx.view.type_character(' ', ctx.caret)?;
+            ctx.push_edit(operation);
+        }
+        Ok(())
+    }
+}
+
+// Enter, carrying list markers and indentation on to the new line
+struct NewLine;
+
+impl EditorCommand for NewLine {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        ctx.delete_selection()?;
+        let operation = match ctx.view.continue_line(ctx.caret)? {
+            Some(op) => Some(op),
+            None => ctx.view.insert_newline(ctx.caret)?,
+        };
+        ctx.push_edit(operation);
+        Ok(())
+    }
+}
+
+struct Backspace;
+
+impl EditorCommand for Backspace {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        let operation = ctx.view.backspace(ctx.caret)?;
+        ctx.push_edit(operation);
+        Ok(())
+    }
+}
+
+struct DeleteChar;
+
+impl EditorCommand for DeleteChar {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        let operation = ctx.view.delete_char(ctx.caret)?;
+        ctx.push_edit(operation);
+        Ok(())
+    }
+}
+
+struct Undo;
+
+impl EditorCommand for Undo {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        let Some(operation) = ctx.history().undo() else {
+            return Ok(());
+        };
+        ctx.stats.undo();
+        operation.edit.reverse(&mut ctx.view.buffer.lines);
+        ctx.view.scroll_offset = operation.scroll_before;
+        ctx.view.needs_redraw = true;
+        ctx.view.render_if_needed(ctx.caret, ctx.tabs.current_tab().has_unsaved_changes)?;
+        ctx.caret.move_to(operation.cursor_before)?;
+        let tab = ctx.tabs.current_tab_mut();
+        tab.has_unsaved_changes = !tab.edit_history.is_saved();
+        Ok(())
+    }
+}
+
+struct Redo;
+
+impl EditorCommand for Redo {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        let Some(operation) = ctx.history().redo() else {
+            return Ok(());
+        };
+        operation.edit.apply(&mut ctx.view.buffer.lines);
+        ctx.view.scroll_offset = operation.scroll_after;
+        ctx.view.needs_redraw = true;
+        ctx.view.render_if_needed(ctx.caret, ctx.tabs.current_tab().has_unsaved_changes)?;
+        ctx.caret.move_to(operation.cursor_after)?;
+        let tab = ctx.tabs.current_tab_mut();
+        tab.has_unsaved_changes = !tab.edit_history.is_saved();
+        Ok(())
+    }
+}
+
+struct Copy;
+
+impl EditorCommand for Copy {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        match ctx.view.copy_selection() {
+            Ok(_) => ctx.view.notify(ToastKind::Info, "Copied!".into()),
+            Err(e) => ctx.view.notify(ToastKind::Error, e.to_string()),
+        }
+        Ok(())
+    }
+}
+
+struct Cut;
+
+impl EditorCommand for Cut {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        match ctx.view.cut_selection(ctx.caret) {
+            Ok(operation) => ctx.push_edit(operation),
+            Err(e) => ctx.view.notify(ToastKind::Error, e.to_string()),
+        }
+        Ok(())
+    }
+}
+
+struct Paste;
+
+impl EditorCommand for Paste {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        ctx.delete_selection()?;
+        match ctx.view.paste_from_clipboard(ctx.caret) {
+            Ok(operation) => ctx.push_edit(operation),
+            Err(e) => ctx.view.notify(ToastKind::Error, e.to_string()),
+        }
+        Ok(())
+    }
+}
+
+// Alt+K in the Emacs keymap: cut to the end of the line into the kill ring. Kills in a row
+// join into one entry
+struct KillLine;
+
+impl EditorCommand for KillLine {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        if let Some((killed, operation)) = ctx.view.kill_line(ctx.caret)? {
+            ctx.kill_ring.kill(killed, ctx.kill_appends);
+            ctx.push_edit(operation);
+        }
+        Ok(())
+    }
+}
+
+struct Yank;
+
+impl EditorCommand for Yank {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        match ctx.kill_ring.yank().map(String::from) {
+            Some(text) => {
+                let operation = ctx.view.insert_at_caret(ctx.caret, &text)?;
+                ctx.push_edit(operation);
+            }
+            None => ctx.view.notify(ToastKind::Error, "Kill ring is empty".to_string()),
+        }
+        Ok(())
+    }
+}
+
+struct ToggleCheckbox;
+
+impl EditorCommand for ToggleCheckbox {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        if ctx.in_markdown() {
+            let operation = ctx.view.toggle_checkbox(ctx.caret)?;
+            ctx.push_edit(operation);
+        }
+        Ok(())
+    }
+}
+
+struct MoveDoneItems;
+
+impl EditorCommand for MoveDoneItems {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        if ctx.in_markdown() {
+            let operation = ctx.view.move_done_items(ctx.caret)?;
+            ctx.push_edit(operation);
+        }
+        Ok(())
+    }
+}
+
+struct FormatTable;
+
+impl EditorCommand for FormatTable {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        if ctx.in_markdown() {
+            let operation = ctx.view.format_table(ctx.caret)?;
+            ctx.push_edit(operation);
+        }
+        Ok(())
+    }
+}
+
+struct ReflowParagraph;
+
+impl EditorCommand for ReflowParagraph {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        let range = ctx.view.align_range(ctx.caret);
+        if range.0 < range.1 {
+            let operation = ctx.view.reflow_lines(ctx.caret, range)?;
+            ctx.push_edit(operation);
+        }
+        Ok(())
+    }
+}
+
+// An encode/decode transform over the selection (or current line)
+struct Transform(TextTransform);
+
+impl EditorCommand for Transform {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        let (start, end, text) = ctx.view.selection_or_line(ctx.caret);
+        match self.0.apply(&text) {
+            Ok(result) if result != text => {
+                let operation = ctx.view.replace_text(ctx.caret, start, end, &result)?;
+                ctx.push_edit(operation);
+            }
+            Ok(_) => {}
+            Err(e) => ctx.view.notify(ToastKind::Error, format!("{} failed: {}", self.0.name(), e)),
+        }
+        Ok(())
+    }
+}
+
+struct NextConflict;
+
+impl EditorCommand for NextConflict {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        if !ctx.view.next_conflict(ctx.caret)? {
+            ctx.view.notify(ToastKind::Info, "No merge conflicts".to_string());
+        }
+        Ok(())
+    }
+}
+
+struct ResolveConflict(Resolution);
+
+impl EditorCommand for ResolveConflict {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        let operation = ctx.view.resolve_conflict(ctx.caret, self.0)?;
+        ctx.push_edit(operation);
+        Ok(())
+    }
+}
+
+// An empty tab, or a scratch note saved as it is typed
+struct NewTab {
+    scratch: bool,
+}
+
+impl EditorCommand for NewTab {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        ctx.sync_tab_to_view();
+        match self.scratch {
+            true => ctx.tabs.new_scratch_tab(),
+            false => ctx.tabs.new_tab(),
+        };
+        ctx.sync_view_to_tab();
+        ctx.caret.move_to(Position::default())?;
+        ctx.view.render(ctx.caret)
+    }
+}
+
+struct OpenLink;
+
+impl EditorCommand for OpenLink {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        let (line, column) = ctx.view.caret_byte(ctx.caret);
+        let (kind, message) = match links::url_at(line, column) {
+            Some(url) => match links::open_url(url) {
+                Ok(()) => (ToastKind::Info, format!("Opening {}", url)),
+                Err(e) => (ToastKind::Error, format!("Could not open link: {}", e)),
+            },
+            None => (ToastKind::Error, "No link under the caret".to_string()),
+        };
+        ctx.view.notify(kind, message);
+        Ok(())
+    }
+}
+
+struct ToggleAutoWrap;
+
+impl EditorCommand for ToggleAutoWrap {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        let config = &mut ctx.view.config;
+        config.auto_wrap = !config.auto_wrap;
+        let message = match config.auto_wrap {
+            true => format!("Auto wrap on at column {}", config.wrap_column),
+            false => "Auto wrap off".to_string(),
+        };
+        ctx.view.notify(ToastKind::Info, message);
+        Ok(())
+    }
+}
+
+struct ToggleMouse;
+
+impl EditorCommand for ToggleMouse {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        ctx.view.config.mouse = !ctx.view.config.mouse;
+        Terminal::capture_mouse(ctx.view.config.mouse)?;
+        let message = match ctx.view.config.mouse {
+            true => "Mouse on",
+            false => "Mouse off - drag to select and copy with your terminal",
+        };
+        ctx.view.notify(ToastKind::Info, message.to_string());
+        Ok(())
+    }
+}
+
+struct ToggleShortcuts;
+
+impl EditorCommand for ToggleShortcuts {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        ctx.view.toggle_ctrl_shortcuts();
+        ctx.view.render(ctx.caret)
+    }
+}
+
+struct UsageStats;
+
+impl EditorCommand for UsageStats {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        let summary: Vec<String> = ctx
+            .stats
+            .totals()
+            .summary()
+            .into_iter()
+            .map(|(label, value)| format!("{}: {}", label, value))
+            .collect();
+        ctx.view.notify(
+            ToastKind::Info,
+            format!("{} (kept on this computer only)", summary.join(" | ")),
+        );
+        Ok(())
+    }
+}
+
+// Open a prefilled GitHub issue, or put its text on the clipboard without a browser
+struct ReportIssue;
+
+impl EditorCommand for ReportIssue {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        let report = IssueReport::new(&report::terminal(), &ctx.view.toasts.recent());
+        let (kind, message) = if links::open_url(&report.url()).is_ok() {
+            (ToastKind::Info, REPORT_OPENED)
+        } else if arboard::Clipboard::new().and_then(|mut c| c.set_text(platform::to_clipboard(report.text))).is_ok() {
+            (ToastKind::Info, REPORT_COPIED)
+        } else {
+            (ToastKind::Error, "Could not open a browser or use the clipboard to report the issue")
+        };
+        ctx.view.notify(kind, message.to_string());
+        Ok(())
+    }
+}
+
+struct ToggleFollow;
+
+impl EditorCommand for ToggleFollow {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        let tab = ctx.tabs.current_tab_mut();
+
+        if tab.is_following() {
+            tab.stop_follow();
+            ctx.view.following = false;
+            ctx.view.notify(
+                ToastKind::Info,
+                "Follow mode off".to_string(),
+            );
+            return Ok(());
+        }
+
+        if !tab.read_only {
+            ctx.view.notify(
+                ToastKind::Error,
+                "Follow mode needs a read-only file (quick --read-only <file>)".to_string(),
+            );
+            return Ok(());
+        }
+
+        if let Err(e) = tab.start_follow() {
+            ctx.view.notify(
+                ToastKind::Error,
+                format!("Cannot follow file: {}", e),
+            );
+            return Ok(());
+        }
+
+        ctx.sync_view_to_tab();
+        ctx.pin_to_bottom()?;
+        ctx.view.notify(
+            ToastKind::Info,
+            "Following file - new lines appear at the bottom".to_string(),
+        );
+        Ok(())
+    }
+}
+
+struct CheckUpdate;
+
+impl EditorCommand for CheckUpdate {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        if !ctx.view.config.update_checks {
+            ctx.view.notify(ToastKind::Info, "Update checks are turned off in the config".to_string());
+            return Ok(());
+        }
+        // Show checking message
+        ctx.view.show_prompt(
+            crate::tui::view::PromptKind::SearchInfo,
+            "Checking for updates...".to_string(),
+        );
+        ctx.view.needs_redraw = true;
+        ctx.view.render_if_needed(ctx.caret, false)?;
+        Terminal::execute()?;
+
+        // Check for updates in a separate thread to avoid blocking
+        let updater = Updater::new(&ctx.view.config);
+
+        let checked = updater.check_for_updates();
+        ctx.view.clear_prompt();
+        let update_info = match checked {
+            Ok(info) => info,
+            Err(e) => {
+                ctx.view.notify(
+                    ToastKind::Error,
+                    format!("Failed to check for updates: {}", e),
+                );
+                return Ok(());
+            }
+        };
+
+        if !update_info.update_available {
+            ctx.view.notify(
+                ToastKind::Info,
+                format!("You're running the latest version ({})", update_info.current_version),
+            );
+            return Ok(());
+        }
+
+        // Update available - ask before installing
+        ctx.ask(Dialog::confirm(
+            format!(
+                "Update available: v{} → v{}. Install it?",
+                update_info.current_version,
+                update_info.latest_version
+            ),
+            Followup::InstallUpdate,
+        ));
+        Ok(())
+    }
+}
+
+// Browse saved scratch notes in the footer prompt and open the chosen one
+struct OpenScratch;
+
+impl EditorCommand for OpenScratch {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        let notes = scratch::list_notes()?;
+        if notes.is_empty() {
+            ctx.view.notify(
+                ToastKind::Info,
+                "No scratch notes yet (Ctrl+T creates one)".to_string(),
+            );
+            return Ok(());
+        }
+
+        let items = notes.iter().map(|note| format!("{} - {}", note.name, note.preview)).collect();
+        let paths = notes.iter().map(|note| note.path.to_string_lossy().into_owned()).collect();
+        ctx.ask(
+            Dialog::select("Scratch".to_string(), items, Followup::OpenScratch(paths))
+                .help("Up/Down browse, Enter open, Esc cancel"),
+        );
+        Ok(())
+    }
+}
+
+// Open a path like "src/main.rs:42" from the buffer in a tab, at that line
+struct GoToFile;
+
+impl EditorCommand for GoToFile {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        let (line, column) = ctx.view.caret_byte(ctx.caret);
+        let base_dir = ctx
+            .tabs
+            .current_tab()
+            .filepath
+            .as_ref()
+            .and_then(|path| std::path::Path::new(path).parent().map(|dir| dir.to_path_buf()));
+
+        let Some(target) = links::file_target_at(line, column, base_dir.as_deref()) else {
+            ctx.view.notify(
+                ToastKind::Error,
+                "No existing file under the caret".to_string(),
+            );
+            return Ok(());
+        };
+
+        ctx.open_path(target.path.to_string_lossy().into_owned(), target.line)
+    }
+}
+
+// Evaluate the selection (or current line) and offer to append or replace the result
+struct Evaluate;
+
+impl EditorCommand for Evaluate {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        let (start, end, selected) = ctx.view.selection_or_line(ctx.caret);
+        let expression = selected.trim_end().trim_end_matches('=');
+        let result = match calc::evaluate(expression) {
+            Ok(value) => calc::format_result(value),
+            Err(e) => {
+                ctx.view.notify(ToastKind::Error, e.to_string());
+                return Ok(());
+            }
+        };
+
+        if ctx.tabs.current_tab().is_read_only() {
+            ctx.view.notify(ToastKind::Info, format!("= {}", result));
+            return Ok(());
+        }
+
+        ctx.ask(Dialog::keys(
+            format!("= {} | Enter append, R replace, Esc close", result),
+            vec![KeyCode::Enter, KeyCode::Char('r')],
+            Followup::Evaluate { start, end, text: selected, result },
+        ));
+        Ok(())
+    }
+}
+
+// Filter commands by typing, Up/Down to pick, Enter runs the chosen one
+struct CommandPalette;
+
+impl EditorCommand for CommandPalette {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        let commands = palette::commands();
+        let actions = commands.iter().map(|command| command.action).collect();
+        let find = move |query: &str| {
+            palette::rank(&commands, query)
+                .into_iter()
+                .map(|i| Row {
+                    item: i,
+                    name: commands[i].name.clone(),
+                    detail: commands[i].shortcut.clone(),
+                })
+                .collect()
+        };
+        ctx.ask(Dialog::filter("No matching command".to_string(), find, Followup::RunCommand(actions)));
+        Ok(())
+    }
+}
+
+// Ctrl+H: ask for the text and its replacement, then go through the highlighted matches
+// asking about each. Y (or Enter) replaces one, N skips it, A replaces the rest and Q or
+// Esc stops; everything replaced undoes as one step
+struct Replace;
+
+impl EditorCommand for Replace {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        let find = ctx.view.last_search.clone().unwrap_or_default();
+        let (scope, label) = ctx.view.search_region(ctx.caret);
+        ctx.ask(Dialog::replace(find, scope, label, Followup::Replace));
+        Ok(())
+    }
+}
+
+// Ask for a path (Tab completes) and insert that file's contents at the caret, like vim's :r
+struct ReadFile;
+
+impl EditorCommand for ReadFile {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        ctx.ask(Dialog::input("Read file:".to_string(), true, Followup::ReadFile));
+        Ok(())
+    }
+}
+
+// Save just the selection to a prompted path, asking before replacing an existing file
+struct WriteSelection;
+
+impl EditorCommand for WriteSelection {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        if ctx.view.selected_text().is_none() {
+            ctx.view.notify(ToastKind::Error, "Select some text first".to_string());
+            return Ok(());
+        }
+        ctx.ask(Dialog::input("Write selection to:".to_string(), true, Followup::WriteSelection));
+        Ok(())
+    }
+}
+
+// Add the selection (or the whole buffer) to the end of a prompted file, under the configured separator
+struct AppendToFile;
+
+impl EditorCommand for AppendToFile {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        ctx.ask(Dialog::input("Append to:".to_string(), true, Followup::AppendToFile));
+        Ok(())
+    }
+}
+
+// Ask for a delimiter (Enter keeps the guessed one) and line the block up on it
+struct AlignSelection;
+
+impl EditorCommand for AlignSelection {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        let range = ctx.view.align_range(ctx.caret);
+        if range.0 == range.1 {
+            return Ok(());
+        }
+        let guess = align::guess_delimiter(&ctx.view.buffer.lines[range.0..range.1]).to_string();
+        let message = format!("Align on (Enter for '{}'):", guess);
+        ctx.ask(Dialog::input(message, false, Followup::Align { range, guess }));
+        Ok(())
+    }
+}
+
+// Type start, start + step, ... down the column where a multi-line selection starts
+struct InsertSequence;
+
+impl EditorCommand for InsertSequence {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        let range = ctx.view.selection.as_ref().filter(|s| s.is_active()).map(|s| s.get_range());
+        let Some((start, end)) = range.filter(|(start, end)| start.line < end.line) else {
+            ctx.view.notify(
+                ToastKind::Error,
+                "Select the lines to number first".to_string(),
+            );
+            return Ok(());
+        };
+
+        ctx.ask(Dialog::input(
+            "Sequence: start [step [width]] (Enter for 1 1):".to_string(),
+            false,
+            Followup::InsertSequence(start, end),
+        ));
+        Ok(())
+    }
+}
+
+// Browse earlier saved versions of the file; d shows a diff, Enter restores one as an undoable edit
+struct FileHistory;
+
+impl EditorCommand for FileHistory {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        let Some(path) = ctx.tabs.current_tab().filepath.clone() else {
+            ctx.view.notify(
+                ToastKind::Error,
+                "Save the file first to keep its history".to_string(),
+            );
+            return Ok(());
+        };
+        let versions = history::list_versions(&path);
+        if versions.is_empty() {
+            ctx.view.notify(
+                ToastKind::Info,
+                "No earlier versions of this file yet".to_string(),
+            );
+            return Ok(());
+        }
+
+        let items = versions.iter().map(|version| format!("saved {}", version.name)).collect();
+        let passphrase = ctx.tabs.current_tab().passphrase.clone();
+        ctx.ask(
+            Dialog::select("Version".to_string(), items, Followup::RestoreVersion { versions, passphrase })
+                .help("Up/Down browse, d diff, Enter restore, Esc cancel")
+                .extra_keys("d"),
+        );
+        Ok(())
+    }
+}
+
+// Move the current file to the trash after a y/n confirmation and close its tab
+struct DeleteFile;
+
+impl EditorCommand for DeleteFile {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        let tab = ctx.tabs.current_tab();
+        let Some(name) = tab.filepath.as_ref().and(tab.filename.clone()) else {
+            ctx.view.notify(
+                ToastKind::Error,
+                "This tab has no file to delete".to_string(),
+            );
+            return Ok(());
+        };
+        let unsaved = if tab.has_unsaved_changes { " (unsaved changes are lost)" } else { "" };
+        ctx.ask(Dialog::warning(format!("Move {} to the trash{}?", name, unsaved), Followup::DeleteFile));
+        Ok(())
+    }
+}
+
+// Put back the last file deleted this session and open it in tab 1
+struct RestoreDeletedFile;
+
+impl EditorCommand for RestoreDeletedFile {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        ctx.sync_tab_to_view();
+        if let Err(e) = ctx.tabs.restore_trashed_file() {
+            ctx.sync_view_to_tab();
+            ctx.view.notify(ToastKind::Error, e.to_string());
+            return Ok(());
+        }
+        ctx.sync_view_to_tab();
+        ctx.caret.move_to(ctx.tabs.current_tab().cursor_pos)?;
+        ctx.view.render(ctx.caret)?;
+        Ok(())
+    }
+}
+
+// Diff the current tab against another one; with more than two tabs ask which
+struct CompareTabs;
+
+impl EditorCommand for CompareTabs {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        let count = ctx.tabs.tabs.len();
+        let current = ctx.tabs.active_tab_index;
+        if count < 2 {
+            ctx.view.notify(
+                ToastKind::Error,
+                "Open a second tab to compare with".to_string(),
+            );
+            return Ok(());
+        }
+
+        if count == 2 {
+            return ctx.compare_with(1 - current);
+        }
+        let others: Vec<usize> = (0..count).filter(|&i| i != current).collect();
+        let names = others.iter().map(|&i| format!("{}: {}", i + 1, ctx.tabs.tabs[i].display_name(i))).collect();
+        ctx.ask(Dialog::select("Compare with".to_string(), names, Followup::CompareWith(others)));
+        Ok(())
+    }
+}
+
+// Digest of the selection (or whole file): C copies it, I inserts it at the caret
+struct Hash(HashAlgorithm);
+
+impl EditorCommand for Hash {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        let digest = self.0.hex_digest(ctx.view.selection_or_file().as_bytes());
+        let mut keys = vec![KeyCode::Enter, KeyCode::Char('c')];
+        let help = if ctx.tabs.current_tab().is_read_only() {
+            "C copy, Esc close"
+        } else {
+            keys.push(KeyCode::Char('i'));
+            "C copy, I insert, Esc close"
+        };
+        let message = format!("{}: {} | {}", self.0.name(), digest, help);
+        ctx.ask(Dialog::keys(message, keys, Followup::Checksum(digest)));
+        Ok(())
+    }
+}
+
+// Browse TODO markers in the footer, jumping to each one; Esc goes back
+struct ListTodos;
+
+impl EditorCommand for ListTodos {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        let found = todos::find_todos(&ctx.view.buffer.lines, &ctx.view.config.todo_keywords);
+        if found.is_empty() {
+            ctx.view.notify(
+                ToastKind::Info,
+                format!("No {} found", ctx.view.config.todo_keywords.join("/")),
+            );
+            return Ok(());
+        }
+
+        let caret = ctx.caret.get_position();
+        let scroll = ctx.view.scroll_offset;
+        let todos: Vec<TextPosition> = found
+            .iter()
+            .map(|todo| TextPosition {
+                line: todo.line,
+                column: ctx.view.buffer.lines[todo.line][..todo.column].chars().count(),
+            })
+            .collect();
+        ctx.view.jump_to(todos[0], ctx.caret)?;
+        let items = found
+            .iter()
+            .map(|todo| format!("{} line {}: {}", todo.keyword, todo.line + 1, todo.text))
+            .collect();
+        ctx.ask(
+            Dialog::select("Go to".to_string(), items, Followup::GoToTodo { todos, caret, scroll })
+                .help("Up/Down browse, Enter go, Esc back")
+                .preview(),
+        );
+        Ok(())
+    }
+}
+
+// Pick where to save the tab in the file browser, and save it there from now on
+struct SaveAs;
+
+impl EditorCommand for SaveAs {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        ctx.browse_files(Purpose::Save, Followup::SaveAs)
+    }
+}
+
+// Browse for a file to open in a new tab
+struct OpenFile;
+
+impl EditorCommand for OpenFile {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        ctx.browse_files(Purpose::Open, Followup::OpenFile)
+    }
+}
+
+// Encrypt the current tab with a new passphrase and save it
+struct SaveEncrypted;
+
+impl EditorCommand for SaveEncrypted {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        ctx.ask_new_passphrase();
+        Ok(())
+    }
+}
+struct SwitchTab(usize);
+
+impl EditorCommand for SwitchTab {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        ctx.sync_tab_to_view();
+        let switched = ctx.tabs.switch_to_tab(self.0);
+        ctx.sync_view_to_tab();
+        ctx.caret.move_to(ctx.tabs.current_tab().cursor_pos)?;
+        if let Err(e) = switched {
+            ctx.view.notify(ToastKind::Error, e.to_string());
+        }
+        ctx.view.render(ctx.caret)?;
+        if ctx.tabs.current_tab().locked {
+            ctx.unlock_current_tab();
+        }
+        Ok(())
+    }
+}
+
+struct Save;
+
+impl EditorCommand for Save {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        ctx.save_file()
+    }
+}
+
+struct RenameFile;
+
+impl EditorCommand for RenameFile {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        match ctx.tabs.current_tab().filename.clone() {
+            Some(name) if ctx.tabs.current_tab().filepath.is_some() => {
+                let dialog = Dialog::input("Rename to:".to_string(), false, Followup::RenameFile);
+                ctx.ask(dialog.prefilled(name));
+            }
+            _ => ctx.view.notify(ToastKind::Error, "Save the file before renaming it".to_string()),
+        }
+        Ok(())
+    }
+}
+
+struct FindFile;
+
+impl EditorCommand for FindFile {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        ctx.find_file()
+    }
+}
+
+struct Search;
+
+impl EditorCommand for Search {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        ctx.ask_query("Search", Followup::Search);
+        Ok(())
+    }
+}
+
+// The last search again from the caret, or a new one when there was none
+struct RepeatSearch;
+
+impl EditorCommand for RepeatSearch {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        if ctx.view.last_search.is_none() {
+            ctx.ask_query("Search", Followup::Search);
+            return Ok(());
+        }
+        ctx.view.repeat_search(ctx.caret)
+    }
+}
+
+struct CountMatches;
+
+impl EditorCommand for CountMatches {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        ctx.ask_query("Count", Followup::CountMatches);
+        Ok(())
+    }
+}
+
+struct RollbackUpdate;
+
+impl EditorCommand for RollbackUpdate {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        ctx.ask(Dialog::confirm(
+            "Replace this version with the one the last update replaced?".to_string(),
+            Followup::RollbackUpdate,
+        ));
+        Ok(())
+    }
+}
+
+// Quit, asking first when the tab has unsaved changes
+struct Quit;
+
+impl EditorCommand for Quit {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        if ctx.tabs.current_tab().has_unsaved_changes {
+            ctx.ask(Dialog::warning("Unsaved changes. Quit?".to_string(), Followup::Quit));
+        } else {
+            *ctx.quit = true;
+        }
+        Ok(())
+    }
+}
+
+// Commands only the GUI has, saying so
+struct GuiOnly(&'static str);
+
+impl EditorCommand for GuiOnly {
+    fn execute(&self, ctx: &mut EditorContext) -> Result<(), Error> {
+        ctx.view.notify(ToastKind::Error, self.0.to_string());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::commands::registry;
+
+    #[test]
+    fn test_every_registered_action_has_a_command() {
+        // Print is listed for Tab, which inserts spaces
+        let event = KeyEvent::from(KeyCode::Tab);
+        for entry in registry() {
+            assert!(command(entry.action, &event).is_some(), "{} has no command", entry.name);
+        }
+    }
+}
@@ -0,0 +1,219 @@
+// compare module - full screen side-by-side diff of two tabs, or of a file and an earlier version
+use crate::core::diff::{self, DiffRow, RowKind};
+use crate::tui::screen::{self, Screen};
+use crate::tui::terminal::Terminal;
+use crossterm::{
+    cursor::{Hide, Show},
+    event::KeyCode,
+    queue,
+    style::Color,
+};
+use std::io::{stdout, Error};
+use unicode_width::UnicodeWidthChar;
+
+const REMOVED_BG: Color = Color::Rgb { r: 70, g: 25, b: 25 };
+const REMOVED_CHANGE_BG: Color = Color::Rgb { r: 140, g: 40, b: 40 };
+const ADDED_BG: Color = Color::Rgb { r: 20, g: 60, b: 25 };
+const ADDED_CHANGE_BG: Color = Color::Rgb { r: 35, g: 120, b: 45 };
+
+pub struct Compare {
+    names: (String, String),
+    left: Vec<String>,
+    right: Vec<String>,
+    rows: Vec<DiffRow>,
+    changes: Vec<usize>, // First row of every block of changes
+    scroll: usize,
+}
+
+impl Compare {
+    pub fn new(names: (String, String), left: Vec<String>, right: Vec<String>) -> Self {
+        let rows = diff::side_by_side(&left, &right);
+        let changes = diff::change_starts(&rows);
+        Self { names, left, right, rows, changes, scroll: 0 }
+    }
+
+    // Take over the screen, starting at the first change
+    pub fn open(&mut self) -> Result<(), Error> {
+        queue!(stdout(), Hide)?;
+        if let Some(&first) = self.changes.first() {
+            self.scroll_to(first)?;
+        }
+        self.render()
+    }
+
+    // One key from the main loop; n / p jump between changes. False once Esc closes the diff
+    pub fn key(&mut self, code: KeyCode) -> Result<bool, Error> {
+        let page = Self::visible_rows()?;
+        let last = self.rows.len().saturating_sub(1);
+        match code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                Terminal::clear_screen()?;
+                queue!(stdout(), Show)?;
+                return Ok(false);
+            }
+            KeyCode::Up => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::Down => self.scroll = (self.scroll + 1).min(last),
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(page),
+            KeyCode::PageDown => self.scroll = (self.scroll + page).min(last),
+            KeyCode::Home => self.scroll = 0,
+            KeyCode::End => self.scroll = last.saturating_sub(page - 1),
+            KeyCode::Char('n') => {
+                let anchor = self.scroll + Self::context()?;
+                if let Some(&row) = self.changes.iter().find(|&&row| row > anchor) {
+                    self.scroll_to(row)?;
+                }
+            }
+            KeyCode::Char('p') => {
+                let anchor = self.scroll + Self::context()?;
+                if let Some(&row) = self.changes.iter().rev().find(|&&row| row < anchor) {
+                    self.scroll_to(row)?;
+                }
+            }
+            _ => {}
+        }
+        self.render()?;
+        Ok(true)
+    }
+
+    fn visible_rows() -> Result<usize, Error> {
+        Ok(Terminal::get_size()?.height.saturating_sub(2).max(1) as usize)
+    }
+
+    // Rows kept above a change when jumping to it
+    fn context() -> Result<usize, Error> {
+        Ok((Self::visible_rows()? / 4).min(3))
+    }
+
+    fn scroll_to(&mut self, row: usize) -> Result<(), Error> {
+        self.scroll = row.saturating_sub(Self::context()?);
+        Ok(())
+    }
+
+    pub fn render(&self) -> Result<(), Error> {
+        screen::draw(|screen| self.paint(screen))
+    }
+
+    fn paint(&self, screen: &mut Screen) -> Result<(), Error> {
+        let size = Terminal::get_size()?;
+        let half = (size.width.saturating_sub(1) / 2) as usize;
+
+        screen.move_to(0, 0);
+        screen.fg(Color::Yellow);
+        screen.print(&fit(&format!(" {}", self.names.0), half));
+        screen.print("│");
+        screen.print(&fit(&format!(" {}", self.names.1), half));
+        screen.reset();
+        screen.clear_rest_of_line();
+
+        for screen_row in 0..Self::visible_rows()? {
+            let y = screen_row as u16 + 1;
+            screen.move_to(0, y);
+            match self.rows.get(self.scroll + screen_row) {
+                Some(row) => self.draw_row(screen, row, half),
+                None => {
+                    screen.print(&" ".repeat(half));
+                    screen.print("│");
+                }
+            }
+            screen.clear_rest_of_line();
+        }
+
+        let anchor = self.scroll + Self::context()?;
+        let current = self.changes.iter().filter(|&&row| row <= anchor).count();
+        let status = if self.changes.is_empty() {
+            " No differences | Esc close".to_string()
+        } else {
+            format!(
+                " Change {}/{} | n next, p previous, Up/Down scroll, Esc close",
+                current.max(1),
+                self.changes.len()
+            )
+        };
+        screen.move_to(0, size.height - 1);
+        screen.bg(Color::Black);
+        screen.fg(Color::White);
+        screen.print(&fit(&status, size.width as usize));
+        screen.reset();
+        Ok(())
+    }
+
+    fn draw_row(&self, screen: &mut Screen, row: &DiffRow, half: usize) {
+        let left = row.left.map(|idx| self.left[idx].as_str());
+        let right = row.right.map(|idx| self.right[idx].as_str());
+        let (left_span, right_span) = match (row.kind, left, right) {
+            (RowKind::Changed, Some(l), Some(r)) => {
+                let (a, b) = diff::inline_change(l, r);
+                (Some(a), Some(b))
+            }
+            _ => (None, None),
+        };
+
+        let (left_bg, right_bg) = match row.kind {
+            RowKind::Same => (None, None),
+            _ => (left.map(|_| REMOVED_BG), right.map(|_| ADDED_BG)),
+        };
+        draw_side(screen, row.left, left, half, left_bg, left_span, REMOVED_CHANGE_BG);
+        screen.fg(Color::DarkGrey);
+        screen.print("│");
+        screen.reset();
+        draw_side(screen, row.right, right, half, right_bg, right_span, ADDED_CHANGE_BG);
+    }
+}
+
+// One half of a row: line number, then the text with the changed span emphasised
+fn draw_side(
+    screen: &mut Screen,
+    number: Option<usize>,
+    text: Option<&str>,
+    width: usize,
+    background: Option<Color>,
+    span: Option<(usize, usize)>,
+    span_background: Color,
+) {
+    let gutter = match number {
+        Some(idx) => format!("{:>4} ", idx + 1),
+        None => "     ".to_string(),
+    };
+    let gutter_width = gutter.len().min(width);
+    screen.fg(Color::DarkYellow);
+    screen.print(&fit(&gutter, gutter_width));
+    screen.reset();
+    let mut room = width - gutter_width;
+
+    let base = background.unwrap_or(Color::Reset);
+    screen.bg(base);
+    if let Some(text) = text {
+        for (idx, ch) in text.chars().enumerate() {
+            let (ch, w) = match ch {
+                '\t' => (' ', 1),
+                _ => (ch, ch.width().unwrap_or(0)),
+            };
+            if w > room {
+                break;
+            }
+            let in_span = span.is_some_and(|(start, end)| idx >= start && idx < end);
+            screen.bg(if in_span { span_background } else { base });
+            screen.print(ch.encode_utf8(&mut [0; 4]));
+            room -= w;
+        }
+    }
+    screen.bg(base);
+    screen.print(&" ".repeat(room));
+    screen.reset();
+}
+
+// Truncate or pad `text` to exactly `width` columns
+fn fit(text: &str, width: usize) -> String {
+    let mut out = String::new();
+    let mut used = 0;
+    for ch in text.chars() {
+        let w = ch.width().unwrap_or(0);
+        if used + w > width {
+            break;
+        }
+        out.push(ch);
+        used += w;
+    }
+    out.push_str(&" ".repeat(width - used));
+    out
+}
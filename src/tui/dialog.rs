@@ -0,0 +1,482 @@
+// dialog module - questions asked in the footer (yes / no, a line of input, a pick from a list,
+// one of a few keys). The main loop hands them its keys instead of each caller blocking on its
+// own read loop, so idle timers keep running, and runs the follow-up once the question is answered
+use super::caret::Position;
+use super::view::{FilePicker, Pick, PromptKind, Purpose, ReplaceRequest, Scope};
+use crate::core::{actions::Action, completion, history::Version, selection::TextPosition};
+use crossterm::event::{KeyCode, KeyModifiers};
+use std::path::PathBuf;
+
+// What to do with the answer
+pub enum Followup {
+    Quit,
+    InstallUpdate,
+    RollbackUpdate,
+    DeleteFile,
+    StealLock,
+    WriteSelection,
+    RenameFile,
+    OverwriteSelection(String), // Path that already exists
+    CompareWith(Vec<usize>),    // Tab index behind each listed item
+    OpenFromArchive(String, Vec<String>), // Archive path and the entry behind each listed item
+    OpenScratch(Vec<String>),   // Path of each listed note
+    RunCommand(Vec<Action>),    // Action behind each listed command
+    OpenFound(PathBuf, Vec<String>), // Project folder and the files under it
+    GoToTodo { todos: Vec<TextPosition>, caret: Position, scroll: usize }, // Where Esc goes back to
+    RestoreVersion { versions: Vec<Version>, passphrase: Option<String> },
+    Evaluate { start: TextPosition, end: TextPosition, text: String, result: String }, // Where the expression is, and its value
+    Checksum(String),
+    ReadFile,
+    AppendToFile,
+    Align { range: (usize, usize), guess: String },
+    InsertSequence(TextPosition, TextPosition),
+    Unlock,
+    NewPassphrase(Option<String>), // The first entry, once it is being confirmed
+    Search,
+    CountMatches,
+    Replace,
+    OpenFile,
+    SaveAs,
+    ReplaceEach { find: String, replaced: usize }, // Stepping through the matches, and how many were replaced
+}
+
+// Alt+L limits a search to the selection or the caret's line, described by `label`
+struct Region {
+    scope: Scope,
+    label: String,
+    on: bool,
+}
+
+impl Region {
+    fn scope(&self) -> Option<Scope> {
+        self.on.then_some(self.scope)
+    }
+}
+
+// A listed item matching what was typed: its name, and the detail shown while it is highlighted
+pub struct Row {
+    pub item: usize,
+    pub name: String,
+    pub detail: Option<String>,
+}
+
+type Finder = Box<dyn Fn(&str) -> Vec<Row>>;
+
+enum Kind {
+    Confirm { warning: bool }, // Warnings are shown in red
+    Input { text: String, complete_paths: bool, hint: Option<String>, masked: bool },
+    Select { items: Vec<String>, selected: usize, help: &'static str, keys: Vec<char>, preview: bool },
+    Filter { query: String, selected: usize, rows: Vec<Row>, find: Finder },
+    Keys { keys: Vec<KeyCode>, open: bool }, // Open ones stay up after each key
+    Browse(FilePicker),
+    Query { verb: String, text: String, region: Region },
+    Replace { fields: [String; 2], active: usize, region: Region },
+}
+
+pub enum Answer {
+    Yes,
+    No,
+    Text(String),
+    Choice(usize),
+    Key(KeyCode), // Letters lowercased
+    Query(String, Option<Scope>),
+    Replace(ReplaceRequest),
+}
+
+pub enum Step {
+    Open,
+    Peek(Answer), // Something to act on while the question stays up
+    Done(Answer),
+    Cancelled,
+}
+
+pub struct Dialog {
+    pub message: String,
+    pub followup: Followup,
+    kind: Kind,
+}
+
+fn lowercase(code: KeyCode) -> KeyCode {
+    match code {
+        KeyCode::Char(c) => KeyCode::Char(c.to_ascii_lowercase()),
+        other => other,
+    }
+}
+
+fn toggles_scope(code: KeyCode, modifiers: KeyModifiers) -> bool {
+    matches!(code, KeyCode::Char('l') | KeyCode::Char('L')) && modifiers.contains(KeyModifiers::ALT)
+}
+
+impl Dialog {
+    pub fn confirm(message: String, followup: Followup) -> Self {
+        Self { message, followup, kind: Kind::Confirm { warning: false } }
+    }
+
+    // A question whose yes loses something, like quitting with unsaved changes
+    pub fn warning(message: String, followup: Followup) -> Self {
+        Self { message, followup, kind: Kind::Confirm { warning: true } }
+    }
+
+    // Tab completes file paths when `complete_paths` is set
+    pub fn input(message: String, complete_paths: bool, followup: Followup) -> Self {
+        let kind = Kind::Input { text: String::new(), complete_paths, hint: None, masked: false };
+        Self { message, followup, kind }
+    }
+
+    // Input shown as stars and handed back untrimmed
+    pub fn passphrase(message: String, followup: Followup) -> Self {
+        let kind = Kind::Input { text: String::new(), complete_paths: false, hint: None, masked: true };
+        Self { message, followup, kind }
+    }
+
+    // Input that starts out holding `text`
+    pub fn prefilled(mut self, value: String) -> Self {
+        if let Kind::Input { text, .. } = &mut self.kind {
+            *text = value;
+        }
+        self
+    }
+
+    pub fn select(message: String, items: Vec<String>, followup: Followup) -> Self {
+        let kind = Kind::Select { items, selected: 0, help: "Up/Down browse, Enter pick, Esc cancel", keys: Vec::new(), preview: false };
+        Self { message, followup, kind }
+    }
+
+    // What the keys do, in place of the usual list help
+    pub fn help(mut self, text: &'static str) -> Self {
+        if let Kind::Select { help, .. } = &mut self.kind {
+            *help = text;
+        }
+        self
+    }
+
+    // Letters that act on the highlighted item while the list stays up
+    pub fn extra_keys(mut self, letters: &str) -> Self {
+        if let Kind::Select { keys, .. } = &mut self.kind {
+            *keys = letters.chars().collect();
+        }
+        self
+    }
+
+    // Report every move through the list, to show the highlighted item as it is browsed
+    pub fn preview(mut self) -> Self {
+        if let Kind::Select { preview, .. } = &mut self.kind {
+            *preview = true;
+        }
+        self
+    }
+
+    // A list narrowed down by typing; `find` ranks the items matching the query.
+    // The message is shown when nothing matches
+    pub fn filter(message: String, find: impl Fn(&str) -> Vec<Row> + 'static, followup: Followup) -> Self {
+        let rows = find("");
+        let kind = Kind::Filter { query: String::new(), selected: 0, rows, find: Box::new(find) };
+        Self { message, followup, kind }
+    }
+
+    // Answered with one of `keys`; the message says what each does
+    pub fn keys(message: String, keys: Vec<KeyCode>, followup: Followup) -> Self {
+        Self { message, followup, kind: Kind::Keys { keys, open: false } }
+    }
+
+    // Keys acted on one after another until the question is closed
+    pub fn stay_open(mut self) -> Self {
+        if let Kind::Keys { open, .. } = &mut self.kind {
+            *open = true;
+        }
+        self
+    }
+
+    // The file browser over the text, starting in `dir`, answered with the path picked
+    pub fn browse(purpose: Purpose, dir: PathBuf, followup: Followup) -> Self {
+        Self { message: String::new(), followup, kind: Kind::Browse(FilePicker::new(purpose, dir)) }
+    }
+
+    pub fn picker(&self) -> Option<&FilePicker> {
+        match &self.kind {
+            Kind::Browse(picker) => Some(picker),
+            _ => None,
+        }
+    }
+
+    // A search query, Alt+L limiting it to `scope`
+    pub fn query(verb: &str, scope: Scope, label: String, followup: Followup) -> Self {
+        let region = Region { scope, label, on: false };
+        let kind = Kind::Query { verb: verb.to_string(), text: String::new(), region };
+        Self { message: String::new(), followup, kind }
+    }
+
+    // The text to find and its replacement side by side, Tab moving between them
+    pub fn replace(find: String, scope: Scope, label: String, followup: Followup) -> Self {
+        let active = if find.is_empty() { 0 } else { 1 };
+        let region = Region { scope, label, on: false };
+        let kind = Kind::Replace { fields: [find, String::new()], active, region };
+        Self { message: String::new(), followup, kind }
+    }
+
+    // The highlighted item of a list
+    pub fn selected(&self) -> usize {
+        match &self.kind {
+            Kind::Select { selected, .. } => *selected,
+            Kind::Filter { selected, rows, .. } => rows.get(*selected).map_or(0, |row| row.item),
+            _ => 0,
+        }
+    }
+
+    // Feed one key press. Esc answers no to a question and cancels everything else
+    pub fn key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> Step {
+        match &mut self.kind {
+            Kind::Confirm { .. } => match code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => Step::Done(Answer::Yes),
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => Step::Done(Answer::No),
+                _ => Step::Open,
+            },
+            Kind::Input { text, complete_paths, hint, masked } => {
+                *hint = None;
+                match code {
+                    KeyCode::Char(c) => text.push(c),
+                    KeyCode::Backspace => {
+                        text.pop();
+                    }
+                    KeyCode::Tab if *complete_paths => {
+                        let (completed, names) = completion::complete_path(text);
+                        // Several matches and nothing more to fill in: list them in the prompt
+                        if names.len() > 1 && completed == *text {
+                            *hint = Some(names.join(" "));
+                        }
+                        *text = completed;
+                    }
+                    KeyCode::Enter if *masked => return Step::Done(Answer::Text(std::mem::take(text))),
+                    KeyCode::Enter => return Step::Done(Answer::Text(text.trim().to_string())),
+                    KeyCode::Esc => return Step::Cancelled,
+                    _ => {}
+                }
+                Step::Open
+            }
+            Kind::Select { items, selected, keys, preview, .. } => {
+                let before = *selected;
+                match lowercase(code) {
+                    KeyCode::Up => *selected = selected.checked_sub(1).unwrap_or(items.len().saturating_sub(1)),
+                    KeyCode::Down => *selected = (*selected + 1) % items.len().max(1),
+                    KeyCode::Enter if !items.is_empty() => return Step::Done(Answer::Choice(*selected)),
+                    KeyCode::Char(c) if keys.contains(&c) && !items.is_empty() => {
+                        return Step::Peek(Answer::Key(KeyCode::Char(c)))
+                    }
+                    KeyCode::Esc => return Step::Cancelled,
+                    _ => {}
+                }
+                if *preview && *selected != before {
+                    return Step::Peek(Answer::Choice(*selected));
+                }
+                Step::Open
+            }
+            Kind::Filter { query, selected, rows, find } => {
+                match code {
+                    KeyCode::Up => *selected = selected.saturating_sub(1),
+                    KeyCode::Down => *selected = (*selected + 1).min(rows.len().saturating_sub(1)),
+                    KeyCode::Enter => {
+                        return match rows.get(*selected) {
+                            Some(row) => Step::Done(Answer::Choice(row.item)),
+                            None => Step::Cancelled,
+                        }
+                    }
+                    KeyCode::Esc => return Step::Cancelled,
+                    KeyCode::Backspace => {
+                        query.pop();
+                        *rows = find(query);
+                        *selected = 0;
+                    }
+                    KeyCode::Char(c) if !modifiers.contains(KeyModifiers::CONTROL) => {
+                        query.push(c);
+                        *rows = find(query);
+                        *selected = 0;
+                    }
+                    _ => {}
+                }
+                Step::Open
+            }
+            Kind::Keys { keys, open } => match lowercase(code) {
+                KeyCode::Esc => Step::Cancelled,
+                code if keys.contains(&code) && *open => Step::Peek(Answer::Key(code)),
+                code if keys.contains(&code) => Step::Done(Answer::Key(code)),
+                _ => Step::Open,
+            },
+            Kind::Browse(picker) => match picker.key(code, modifiers) {
+                Pick::Open => Step::Open,
+                Pick::Chosen(path) => Step::Done(Answer::Text(path.to_string_lossy().into_owned())),
+                Pick::Cancelled => Step::Cancelled,
+            },
+            Kind::Query { text, region, .. } => {
+                match code {
+                    _ if toggles_scope(code, modifiers) => region.on = !region.on,
+                    KeyCode::Char(c) => text.push(c),
+                    KeyCode::Backspace => {
+                        text.pop();
+                    }
+                    KeyCode::Enter if text.is_empty() => return Step::Cancelled,
+                    KeyCode::Enter => return Step::Done(Answer::Query(std::mem::take(text), region.scope())),
+                    KeyCode::Esc => return Step::Cancelled,
+                    _ => {}
+                }
+                Step::Open
+            }
+            Kind::Replace { fields, active, region } => {
+                match code {
+                    _ if toggles_scope(code, modifiers) => region.on = !region.on,
+                    KeyCode::Char(c) => fields[*active].push(c),
+                    KeyCode::Backspace => {
+                        fields[*active].pop();
+                    }
+                    KeyCode::Tab | KeyCode::BackTab | KeyCode::Up | KeyCode::Down => *active = 1 - *active,
+                    KeyCode::Enter if *active == 0 => *active = 1,
+                    KeyCode::Enter if fields[0].is_empty() => return Step::Cancelled,
+                    KeyCode::Enter => {
+                        let [find, replacement] = std::mem::take(fields);
+                        return Step::Done(Answer::Replace(ReplaceRequest { find, replacement, scope: region.scope() }));
+                    }
+                    KeyCode::Esc => return Step::Cancelled,
+                    _ => {}
+                }
+                Step::Open
+            }
+        }
+    }
+
+    // Footer prompt showing the question and the answer so far, as (kind, message, input)
+    pub fn prompt(&self) -> (PromptKind, String, String) {
+        match &self.kind {
+            Kind::Confirm { warning } => {
+                let kind = if *warning { PromptKind::Error } else { PromptKind::SearchInfo };
+                (kind, format!("{} y / n", self.message), String::new())
+            }
+            Kind::Input { text, hint, masked, .. } => {
+                let message = match hint {
+                    Some(hint) => format!("{} [{}]", self.message, hint),
+                    None => self.message.clone(),
+                };
+                let kind = if *masked { PromptKind::Passphrase } else { PromptKind::Search };
+                (kind, message, text.clone())
+            }
+            Kind::Select { items, selected, help, .. } => {
+                let item = items.get(*selected).map_or("", String::as_str);
+                let message = format!("{} {} ({}/{}) | {}", self.message, item, selected + 1, items.len(), help);
+                (PromptKind::SearchInfo, message, String::new())
+            }
+            Kind::Filter { query, selected, rows, .. } => {
+                let message = if rows.is_empty() {
+                    self.message.clone()
+                } else {
+                    // The highlighted row and the few after it
+                    let listed: Vec<String> = rows[*selected..]
+                        .iter()
+                        .take(5)
+                        .enumerate()
+                        .map(|(i, row)| match (&row.detail, i) {
+                            (Some(detail), 0) => format!("[{} ({})]", row.name, detail),
+                            (None, 0) => format!("[{}]", row.name),
+                            _ => row.name.clone(),
+                        })
+                        .collect();
+                    format!("{}/{}  {}", selected + 1, rows.len(), listed.join("  ·  "))
+                };
+                (PromptKind::Palette, message, query.clone())
+            }
+            Kind::Keys { .. } => (PromptKind::SearchInfo, self.message.clone(), String::new()),
+            Kind::Browse(picker) => {
+                let (message, input) = picker.prompt();
+                (PromptKind::SaveAs, message, input)
+            }
+            Kind::Query { verb, text, region } => {
+                let message = match region.on {
+                    true => format!("{} {}:", verb, region.label),
+                    false => format!("{}:", verb),
+                };
+                (PromptKind::Search, message, text.clone())
+            }
+            Kind::Replace { fields, active, region } => {
+                let kind = PromptKind::Replace { editing_find: *active == 0, scope: region.on.then(|| region.label.clone()) };
+                (kind, fields[0].clone(), fields[1].clone())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(dialog: &mut Dialog, code: KeyCode) -> Step {
+        dialog.key(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_dialog_keys() {
+        let mut dialog = Dialog::input("Path:".to_string(), false, Followup::WriteSelection);
+        for c in "ab".chars() {
+            assert!(matches!(press(&mut dialog, KeyCode::Char(c)), Step::Open));
+        }
+        press(&mut dialog, KeyCode::Backspace);
+        assert_eq!(dialog.prompt().2, "a");
+        assert!(matches!(press(&mut dialog, KeyCode::Enter), Step::Done(Answer::Text(text)) if text == "a"));
+
+        let mut dialog = Dialog::select("Pick".to_string(), vec!["x".into(), "y".into()], Followup::CompareWith(vec![1, 2]));
+        press(&mut dialog, KeyCode::Up);
+        assert!(matches!(press(&mut dialog, KeyCode::Enter), Step::Done(Answer::Choice(1))));
+        assert!(matches!(press(&mut dialog, KeyCode::Esc), Step::Cancelled));
+
+        let mut dialog = Dialog::confirm("Sure?".to_string(), Followup::Quit);
+        assert!(matches!(press(&mut dialog, KeyCode::Char('x')), Step::Open));
+        assert!(matches!(press(&mut dialog, KeyCode::Esc), Step::Done(Answer::No)));
+
+        let mut dialog = Dialog::keys("= 4".to_string(), vec![KeyCode::Enter, KeyCode::Char('r')], Followup::Checksum(String::new()));
+        assert!(matches!(press(&mut dialog, KeyCode::Char('x')), Step::Open));
+        assert!(matches!(press(&mut dialog, KeyCode::Char('R')), Step::Done(Answer::Key(KeyCode::Char('r')))));
+
+        let mut dialog = Dialog::keys("Match".to_string(), vec![KeyCode::Char('y')], Followup::Replace).stay_open();
+        assert!(matches!(press(&mut dialog, KeyCode::Char('Y')), Step::Peek(Answer::Key(KeyCode::Char('y')))));
+        assert!(matches!(press(&mut dialog, KeyCode::Esc), Step::Cancelled));
+    }
+
+    #[test]
+    fn test_list_dialogs() {
+        let items = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let mut dialog = Dialog::select("Go".to_string(), items.clone(), Followup::Unlock).preview().extra_keys("d");
+        assert!(matches!(press(&mut dialog, KeyCode::Down), Step::Peek(Answer::Choice(1))));
+        assert!(matches!(press(&mut dialog, KeyCode::Char('D')), Step::Peek(Answer::Key(KeyCode::Char('d')))));
+        assert_eq!(dialog.selected(), 1);
+
+        let names = items.clone();
+        let find = move |query: &str| {
+            let rows = names.iter().enumerate().filter(|(_, name)| name.contains(query));
+            rows.map(|(item, name)| Row { item, name: name.clone(), detail: None }).collect()
+        };
+        let mut dialog = Dialog::filter("Nothing".to_string(), find, Followup::RunCommand(Vec::new()));
+        assert_eq!(dialog.prompt().1, "1/3  [a]  ·  b  ·  c");
+        press(&mut dialog, KeyCode::Char('c'));
+        assert_eq!(dialog.prompt().2, "c");
+        assert!(matches!(press(&mut dialog, KeyCode::Enter), Step::Done(Answer::Choice(2))));
+        press(&mut dialog, KeyCode::Char('z'));
+        assert_eq!(dialog.prompt().1, "Nothing");
+    }
+
+    #[test]
+    fn test_search_dialogs() {
+        let line = (TextPosition { line: 2, column: 0 }, TextPosition { line: 2, column: 5 });
+        let mut dialog = Dialog::query("Count", line, "in line 3".to_string(), Followup::CountMatches);
+        press(&mut dialog, KeyCode::Char('x'));
+        dialog.key(KeyCode::Char('l'), KeyModifiers::ALT);
+        assert_eq!(dialog.prompt().1, "Count in line 3:");
+        assert!(matches!(press(&mut dialog, KeyCode::Enter), Step::Done(Answer::Query(text, Some(_))) if text == "x"));
+
+        let mut dialog = Dialog::replace("old".to_string(), line, "in line 3".to_string(), Followup::Replace);
+        press(&mut dialog, KeyCode::Char('n'));
+        press(&mut dialog, KeyCode::Tab);
+        press(&mut dialog, KeyCode::Char('s'));
+        let step = press(&mut dialog, KeyCode::Enter);
+        assert!(matches!(step, Step::Open));
+        let Step::Done(Answer::Replace(request)) = press(&mut dialog, KeyCode::Enter) else {
+            panic!("replace dialog did not finish");
+        };
+        assert_eq!((request.find.as_str(), request.replacement.as_str(), request.scope), ("olds", "n", None));
+    }
+}
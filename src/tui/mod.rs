@@ -1,38 +1,95 @@
 // module binding tui logic, consumeing shortcuts and save logic
 pub mod caret;
+mod commands;
+mod compare;
+mod dialog;
+mod screen;
 mod terminal;
-pub mod syntax;
 pub mod view;
 
 use crate::core::{
     actions::Action, 
+    calc,
+    completion,
+    append,
+    archive::Member,
+    collab::Role,
+    edit_history::{EditHistory, EditOperation},
+    fetch,
+    history,
+    idle::{Idle, Timer},
+    paths,
+    progress::Job,
+    repeat::RepeatCount,
+    stats::Tracker,
+    sequence::Sequence,
     shortcuts::Shortcuts, 
-    tabs::{TabManager, get_friendly_filetype},
+    tabs::{Tab, TabManager},
+    toasts::ToastKind,
     updater::Updater,
+    keymap::KillRing,
+    vim::{Mode, Operator, Vim, VimCommand},
+    whats_new,
 };
 use caret::Caret;
-use crossterm::event::{read, Event, KeyCode, KeyEventKind};
+use commands::EditorContext;
+use compare::Compare;
+use dialog::{Answer, Dialog, Followup, Step};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use terminal::Terminal;
-use view::{Buffer, View};
+use view::{Buffer, ReplaceRequest, View};
+
+// How often a followed file is checked for appended content
+const FOLLOW_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+// How often a shared tab trades edits while no keys are pressed
+const COLLAB_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+// How long footer messages stay up
+const PROMPT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
 
 pub struct TerminalEditor {
     tab_manager: TabManager,
     view: View,
     caret: Caret,
     shortcuts: Shortcuts,
+    vim: Option<Vim>, // Set when vim_mode is enabled in the config
+    kill_ring: KillRing,
+    kill_appends: bool, // The previous action was a kill, so the next one joins it
     quit_program: bool,
     pub start_on_tab_zero: bool,
+    pub collab_role: Option<Role>, // Session to host or join once the terminal is up
+    pub open_url: Option<String>, // http(s) file to download once the terminal is up
+    pub open_archive: Option<String>, // .zip / .tar whose files to list once the terminal is up
+    pub open_finder: bool,            // Started on a folder: pick a file from it once the terminal is up
+    stats: Tracker, // Usage counted this session
+    idle: Idle, // Timers due while no input arrives
+    dialog: Option<Dialog>, // Question waiting for an answer in the footer
+    compare: Option<Compare>, // Diff drawn over the editor, taking the keys until Esc
+    repeat: RepeatCount, // Count typed after Esc for the next action
 }
 
 impl TerminalEditor {
     pub fn new(buffer: Buffer) -> Self {
+        let mut tab_manager = TabManager::new(buffer, None, None);
+        let view = View::new(tab_manager.current_tab_mut().buffer.take());
         Self {
-            tab_manager: TabManager::new(buffer.clone(), None, None),
-            view: View::new(buffer),
+            tab_manager,
+            view,
             caret: Caret::new(),
             shortcuts: Shortcuts::new(),
+            vim: None,
+            kill_ring: KillRing::default(),
+            kill_appends: false,
             quit_program: false,
             start_on_tab_zero: true,
+            collab_role: None,
+            open_url: None,
+            open_archive: None,
+            open_finder: false,
+            stats: Tracker::default(),
+            idle: Idle::default(),
+            dialog: None,
+            compare: None,
+            repeat: RepeatCount::default(),
         }
     }
 
@@ -40,16 +97,27 @@ impl TerminalEditor {
         let mut tab_manager = TabManager::new(Buffer::default(), None, None);
         tab_manager.open_file_in_new_tab(path)?;
 
-        let tab = tab_manager.current_tab();
-        let view = View::new(tab.buffer.clone());
+        let view = View::new(tab_manager.current_tab_mut().buffer.take());
 
         Ok(Self {
             tab_manager,
             view,
             caret: Caret::new(),
             shortcuts: Shortcuts::new(),
+            vim: None,
+            kill_ring: KillRing::default(),
+            kill_appends: false,
             quit_program: false,
             start_on_tab_zero: false,
+            collab_role: None,
+            open_url: None,
+            open_archive: None,
+            open_finder: false,
+            stats: Tracker::default(),
+            idle: Idle::default(),
+            dialog: None,
+            compare: None,
+            repeat: RepeatCount::default(),
         })
     }
     
@@ -58,7 +126,7 @@ impl TerminalEditor {
         self.tab_manager.new_tab();
         self.sync_view_to_tab();
         self.caret.move_to(caret::Position::default())?;
-        self.view.render(&mut self.caret)?;
+        self.view.render(&self.caret)?;
         Ok(())
     }
     
@@ -66,131 +134,332 @@ impl TerminalEditor {
         self.tab_manager.current_tab_mut().filename = filename.clone();
         self.view.set_filename_and_filetype(filename, filetype);
     }
-    
-    fn check_and_install_update(&mut self) -> Result<(), std::io::Error> {
-        // Show checking message
-        self.view.show_prompt(
-            crate::tui::view::PromptKind::SearchInfo,
-            "Checking for updates...".to_string(),
-        );
-        self.view.needs_redraw = true;
-        self.view.render_if_needed(&self.caret, false)?;
-        Terminal::execute()?;
-        
-        // Check for updates in a separate thread to avoid blocking
-        let updater = Updater::new();
-        
-        let update_info = match updater.check_for_updates() {
-            Ok(info) => info,
+
+    // Mark the current tab read-only, optionally following appended content
+    pub fn open_read_only(&mut self, follow: bool) -> Result<(), std::io::Error> {
+        let tab = self.tab_manager.current_tab_mut();
+        tab.read_only = true;
+        if follow {
+            tab.start_follow()?;
+        }
+        self.sync_view_to_tab();
+        Ok(())
+    }
+
+    fn run_due_timers(&mut self) -> Result<(), std::io::Error> {
+        for timer in self.idle.due(std::time::Instant::now()) {
+            match timer {
+                Timer::ClearPrompt => {
+                    self.view.clear_prompt();
+                    self.view.render_if_needed(
+                        &self.caret,
+                        self.tab_manager.current_tab().has_unsaved_changes,
+                    )?;
+                }
+                Timer::Toasts => {
+                    self.view.expire_toasts();
+                    self.view.render_if_needed(
+                        &self.caret,
+                        self.tab_manager.current_tab().has_unsaved_changes,
+                    )?;
+                }
+                Timer::Follow => self.poll_follow()?,
+                Timer::Collab => {} // Synced at the top of the main loop
+                Timer::Chord => {
+                    self.shortcuts.cancel_chord();
+                    self.view.pending_keys = None;
+                    self.view.needs_redraw = true;
+                    self.view.render_if_needed(
+                        &self.caret,
+                        self.tab_manager.current_tab().has_unsaved_changes,
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Check the followed file for new content and keep the view pinned to the bottom
+    fn poll_follow(&mut self) -> Result<(), std::io::Error> {
+        let tab = self.tab_manager.current_tab_mut();
+        let Some(follower) = tab.follower.as_mut() else {
+            return Ok(());
+        };
+
+        match follower.poll(&mut self.view.buffer) {
+            Ok(true) => self.context().pin_to_bottom()?,
+            Ok(false) => {}
             Err(e) => {
-                self.view.show_prompt(
-                    crate::tui::view::PromptKind::Error,
-                    format!("Failed to check for updates: {}", e),
+                tab.stop_follow();
+                self.view.following = false;
+                self.view.notify(
+                    ToastKind::Error,
+                    format!("Stopped following: {}", e),
                 );
                 self.view.render_if_needed(&self.caret, false)?;
-                Terminal::execute()?;
-                std::thread::sleep(std::time::Duration::from_secs(3));
-                self.view.clear_prompt();
-                self.view.render_if_needed(&self.caret, false)?;
-                Terminal::execute()?;
-                return Ok(());
             }
-        };
-        
-        if !update_info.update_available {
-            self.view.show_prompt(
-                crate::tui::view::PromptKind::SearchInfo,
-                format!("You're running the latest version ({})", update_info.current_version),
-            );
-            self.view.render_if_needed(&self.caret, false)?;
-            Terminal::execute()?;
-            std::thread::sleep(std::time::Duration::from_secs(2));
-            self.view.clear_prompt();
-            self.view.render_if_needed(&self.caret, false)?;
-            Terminal::execute()?;
+        }
+        Ok(())
+    }
+
+    // Trade edits and carets with the others in a shared tab
+    fn sync_collab(&mut self) -> Result<(), std::io::Error> {
+        let tab = self.tab_manager.current_tab_mut();
+        let Some(session) = tab.collab.as_mut() else {
             return Ok(());
+        };
+        let mut caret = self.view.caret_chars(&self.caret);
+        match session.sync(&mut self.view.buffer, &mut caret) {
+            Ok(changed) => {
+                if session.peers() != self.view.peers.as_slice() {
+                    self.view.peers = session.peers().to_vec();
+                    self.view.needs_redraw = true;
+                }
+                if changed {
+                    // Undo steps point at text the others may have moved
+                    tab.edit_history = EditHistory::new(500);
+                    tab.has_unsaved_changes = true;
+                    self.view.select_chars((caret, caret), &mut self.caret)?;
+                    self.view.selection = None;
+                }
+            }
+            Err(e) => {
+                tab.collab = None;
+                self.view.shared = false;
+                self.view.peers.clear();
+                self.view.notify(ToastKind::Error, e.to_string());
+            }
+        }
+        self.view.render_if_needed(&self.caret, self.tab_manager.current_tab().has_unsaved_changes)
+    }
+
+    fn install_update(&mut self) -> Result<(), std::io::Error> {
+        let updater = Updater::new(&self.view.config);
+        let job = Job::spawn("Downloading update", move |progress| {
+            updater.perform_update(progress).map_err(|e| e.to_string())
+        });
+
+        match self.context().wait_for(job)? {
+            Ok(_) => self.view.notify(
+                ToastKind::Info,
+                "Update successful! Restart the application to use the new version.".to_string(),
+            ),
+            Err(e) => self.view.notify(ToastKind::Error, format!("Update failed: {}", e)),
+        }
+        Ok(())
+    }
+
+    fn ask(&mut self, dialog: Dialog) {
+        self.context().ask(dialog);
+    }
+
+    fn show_dialog(&mut self) {
+        self.context().show_dialog();
+    }
+
+    fn hide_dialog(&mut self) {
+        self.view.clear_prompt();
+        self.view.file_picker = None;
+    }
+
+    fn dialog_key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> Result<(), std::io::Error> {
+        let step = match self.dialog.as_mut() {
+            Some(dialog) => dialog.key(code, modifiers),
+            None => return Ok(()),
+        };
+        match step {
+            Step::Open => self.show_dialog(),
+            Step::Peek(answer) => {
+                if let Some(mut dialog) = self.dialog.take() {
+                    let peeked = self.peek(&mut dialog, answer);
+                    if matches!(peeked, Ok(true)) {
+                        self.dialog = Some(dialog);
+                        self.show_dialog();
+                    } else {
+                        self.hide_dialog();
+                        self.closed(dialog.followup)?;
+                        peeked?;
+                    }
+                }
+            }
+            Step::Cancelled => {
+                if let Some(dialog) = self.dialog.take() {
+                    self.hide_dialog();
+                    self.closed(dialog.followup)?;
+                }
+            }
+            Step::Done(answer) => {
+                if let Some(dialog) = self.dialog.take() {
+                    self.hide_dialog();
+                    self.answer(dialog.followup, answer)?;
+                }
+            }
         }
-        
-        // Update available - show release notes and prompt
-        let message = format!(
-            "Update available: v{} → v{} | Press Y to install, N to cancel",
-            update_info.current_version,
-            update_info.latest_version
-        );
-        
-        self.view.show_prompt(
-            crate::tui::view::PromptKind::SearchInfo,
-            message,
-        );
         self.view.needs_redraw = true;
-        self.view.render_if_needed(&self.caret, false)?;
-        Terminal::execute()?;
-        
-        // Wait for user confirmation
-        loop {
-            match read()? {
-                Event::Key(event) if event.kind == KeyEventKind::Press => {
-                    match event.code {
-                        KeyCode::Char('y') | KeyCode::Char('Y') => {
-                            // User confirmed - perform update
-                            self.view.show_prompt(
-                                crate::tui::view::PromptKind::SearchInfo,
-                                "Downloading update...".to_string(),
-                            );
-                            self.view.render_if_needed(&self.caret, false)?;
-                            Terminal::execute()?;
-                            
-                            match updater.perform_update() {
-                                Ok(_) => {
-                                    self.view.show_prompt(
-                                        crate::tui::view::PromptKind::SearchInfo,
-                                        "Update successful! Restart the application to use the new version.".to_string(),
-                                    );
-                                    self.view.render_if_needed(&self.caret, false)?;
-                                    Terminal::execute()?;
-                                    std::thread::sleep(std::time::Duration::from_secs(3));
-                                    
-                                    // Optionally quit after update
-                                    self.quit_program = true;
-                                }
-                                Err(e) => {
-                                    self.view.show_prompt(
-                                        crate::tui::view::PromptKind::Error,
-                                        format!("Update failed: {}", e),
-                                    );
-                                    self.view.render_if_needed(&self.caret, false)?;
-                                    Terminal::execute()?;
-                                    std::thread::sleep(std::time::Duration::from_secs(3));
-                                }
-                            }
-                            break;
-                        }
-                        KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
-                            // User cancelled
-                            self.view.clear_prompt();
-                            self.view.render_if_needed(&self.caret, false)?;
-                            Terminal::execute()?;
-                            break;
-                        }
-                        _ => {}
+        self.view.render_if_needed(
+            &self.caret,
+            self.tab_manager.current_tab().has_unsaved_changes,
+        )
+    }
+
+    fn answer(&mut self, followup: Followup, answer: Answer) -> Result<(), std::io::Error> {
+        match (followup, answer) {
+            (Followup::Quit, Answer::Yes) => self.quit_program = true,
+            (Followup::InstallUpdate, Answer::Yes) => self.install_update()?,
+            (Followup::RollbackUpdate, Answer::Yes) => match Updater::rollback() {
+                Ok(()) => self.view.notify(ToastKind::Info, "Rolled back - restart to use the previous version".to_string()),
+                Err(e) => self.view.notify(ToastKind::Error, format!("Rollback failed: {}", e)),
+            },
+            (Followup::DeleteFile, Answer::Yes) => self.trash_current_file()?,
+            (Followup::StealLock, answer) => self.finish_lock_warning(matches!(answer, Answer::Yes)),
+            (Followup::WriteSelection, Answer::Text(path)) => self.write_selection(path),
+            (Followup::RenameFile, Answer::Text(name)) if !name.is_empty() => self.rename_file(&name),
+            (Followup::OverwriteSelection(path), Answer::Yes) => self.write_selection_text(&path),
+            (Followup::CompareWith(tabs), Answer::Choice(i)) => self.context().compare_with(tabs[i])?,
+            (Followup::OpenFromArchive(archive, mut names), Answer::Choice(i)) => {
+                self.open_archive_member(Member { archive, name: names.swap_remove(i) })?
+            }
+            (Followup::OpenScratch(paths), Answer::Choice(i)) => self.open_scratch_note(&paths[i])?,
+            (Followup::OpenFound(root, files), Answer::Choice(i)) => {
+                self.context().open_path(root.join(&files[i]).to_string_lossy().into_owned(), None)?
+            }
+            (Followup::RunCommand(actions), Answer::Choice(i)) => {
+                self.run_action(actions[i], 1, &KeyEvent::from(KeyCode::Null))?;
+            }
+            (Followup::GoToTodo { todos, .. }, Answer::Choice(i)) => self.view.jump_to(todos[i], &mut self.caret)?,
+            (Followup::RestoreVersion { versions, passphrase }, Answer::Choice(i)) => {
+                match history::read_version(&versions[i], passphrase.as_deref()) {
+                    Ok(content) => self.restore_version(content)?,
+                    Err(e) => self.view.notify(ToastKind::Error, e.to_string()),
+                }
+            }
+            (Followup::Evaluate { start, end, text, result }, Answer::Key(key)) => {
+                let operation = match key {
+                    KeyCode::Enter => self.view.replace_text(&mut self.caret, end, end, &calc::appended_result(&text, &result))?,
+                    _ => self.view.replace_text(&mut self.caret, start, end, &result)?,
+                };
+                self.push_edit(operation);
+            }
+            (Followup::Checksum(digest), Answer::Key(KeyCode::Char('i'))) => {
+                let pos = view::helpers::get_current_text_pos(&self.view, &self.caret);
+                let operation = self.view.replace_text(&mut self.caret, pos, pos, &digest)?;
+                self.push_edit(operation);
+            }
+            (Followup::Checksum(digest), Answer::Key(_)) => {
+                if let Ok(mut clipboard) = arboard::Clipboard::new() {
+                    let _ = clipboard.set_text(digest);
+                }
+            }
+            (Followup::ReadFile, Answer::Text(input)) if !input.is_empty() => self.read_file_into_buffer(&input)?,
+            (Followup::AppendToFile, Answer::Text(input)) if !input.is_empty() => self.append_to_file(&input),
+            (Followup::Align { range, guess }, Answer::Text(input)) => {
+                let delimiter = if input.is_empty() { guess } else { input };
+                let operation = self.view.align_lines(&mut self.caret, range, &delimiter)?;
+                self.push_edit(operation);
+            }
+            (Followup::InsertSequence(start, end), Answer::Text(spec)) => match Sequence::parse(&spec) {
+                Ok(sequence) => {
+                    let operation = self.view.insert_sequence(&mut self.caret, start, end, &sequence)?;
+                    self.push_edit(operation);
+                }
+                Err(e) => self.view.notify(ToastKind::Error, e.to_string()),
+            },
+            (Followup::Unlock, Answer::Text(passphrase)) if !passphrase.is_empty() => self.unlock_with(&passphrase)?,
+            (Followup::NewPassphrase(None), Answer::Text(passphrase)) if !passphrase.is_empty() => {
+                self.ask(Dialog::passphrase("Confirm passphrase: ".to_string(), Followup::NewPassphrase(Some(passphrase))));
+            }
+            (Followup::NewPassphrase(Some(first)), Answer::Text(confirm)) if !confirm.is_empty() => {
+                if first != confirm {
+                    self.view.notify(ToastKind::Error, "Passphrases do not match".to_string());
+                    return Ok(());
+                }
+                self.tab_manager.current_tab_mut().passphrase = Some(first);
+                self.view.encrypted = true;
+                self.context().save_file()?;
+            }
+            (Followup::Search, Answer::Query(query, scope)) => self.view.search(&mut self.caret, &query, scope)?,
+            (Followup::CountMatches, Answer::Query(query, scope)) => {
+                self.view.count_occurrences(&mut self.caret, &query, scope)?
+            }
+            (Followup::Replace, Answer::Replace(request)) => self.replace_each(request)?,
+            (Followup::OpenFile, Answer::Text(path)) => self.context().open_path(path, None)?,
+            (Followup::SaveAs, Answer::Text(path)) => self.context().save_to(path)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // Act on an answer while its question stays up; false when that closed the question
+    fn peek(&mut self, dialog: &mut Dialog, answer: Answer) -> Result<bool, std::io::Error> {
+        let selected = dialog.selected();
+        match (&mut dialog.followup, answer) {
+            (Followup::GoToTodo { todos, .. }, Answer::Choice(i)) => self.view.jump_to(todos[i], &mut self.caret)?,
+            (Followup::RestoreVersion { versions, passphrase }, Answer::Key(_)) => {
+                let version = &versions[selected];
+                match history::read_version(version, passphrase.as_deref()) {
+                    Ok(content) => {
+                        let compare = Compare::new(
+                            (format!("Saved {}", version.name), "Current".to_string()),
+                            content.split('\n').map(String::from).collect(),
+                            self.view.buffer.content().split('\n').map(String::from).collect(),
+                        );
+                        self.context().show_compare(compare)?;
                     }
+                    Err(e) => self.view.notify(ToastKind::Error, e.to_string()),
                 }
-                _ => {}
             }
+            (Followup::ReplaceEach { replaced, .. }, Answer::Key(key)) => {
+                *replaced += self.replace_step(key)?;
+                dialog.message = self.view.prompt_message();
+                return Ok(self.view.is_search_active());
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    // Esc on a question that changed something while it was up, or one that closed itself
+    fn closed(&mut self, followup: Followup) -> Result<(), std::io::Error> {
+        match followup {
+            Followup::GoToTodo { caret, scroll, .. } => {
+                self.view.scroll_offset = scroll;
+                self.view.needs_redraw = true;
+                self.caret.move_to(caret)?;
+            }
+            Followup::ReplaceEach { find, replaced } => {
+                self.view.clear_search();
+                self.tab_manager.current_tab_mut().edit_history.end();
+                if replaced > 0 {
+                    self.view.notify(ToastKind::Info, format!("Replaced {} of '{}'", replaced, find));
+                }
+            }
+            _ => {}
         }
-        
-        self.view.clear_prompt();
-        self.view.render_if_needed(&self.caret, false)?;
-        Terminal::execute()?;
-        
         Ok(())
     }
 
+    fn close_compare(&mut self) -> Result<(), std::io::Error> {
+        self.compare = None;
+        self.view.covered = false;
+        self.view.needs_redraw = true;
+        self.view.render_if_needed(&self.caret, self.tab_manager.current_tab().has_unsaved_changes)?;
+        self.caret.move_to(self.caret.get_position())
+    }
 
     pub fn run(&mut self) {
         if let Err(error) = Terminal::initialize(&mut self.view, &mut self.caret) {
             eprintln!("Terminal Initialisation Failed: {:?}", error);
         }
+        if self.view.config.vim_mode {
+            self.vim = Some(Vim::default());
+            self.view.vim_mode = Some(Mode::Normal.label());
+        }
+        self.shortcuts.set_keymap(self.view.config.keymap);
+        self.shortcuts.set_scroll_lines(self.view.config.scroll_lines);
+        let problems = self.shortcuts.set_keybindings(&self.view.config.keybindings);
+        if !problems.is_empty() {
+            self.view.notify(ToastKind::Error, problems.join(". "));
+        }
 
         // Create a new tab at start if quick started without a path
         if self.start_on_tab_zero {
@@ -199,363 +468,283 @@ impl TerminalEditor {
                 Err(e) => Err(e),
             };
         }
+
+        // A URL on the command line downloads behind a progress bar, Esc cancels
+        if let Some(url) = self.open_url.take() {
+            self.sync_tab_to_view();
+            let job = Job::spawn(format!("Downloading {}", fetch::file_name(&url)), move |progress| {
+                Tab::from_url(&url, progress)
+            });
+            match self.context().wait_for(job) {
+                Ok(Ok(tab)) => {
+                    self.tab_manager.add_opened_tab(tab);
+                    self.view.notify(ToastKind::Info, fetch::SAVE_HINT.to_string());
+                }
+                Ok(Err(e)) | Err(e) => self.view.notify(ToastKind::Error, format!("Cannot open the URL: {}", e)),
+            }
+            self.sync_view_to_tab();
+        }
+
+        if let Some(path) = self.open_archive.take() {
+            self.context().list_archive(path);
+        }
+
+        if let Some(role) = self.collab_role.take() {
+            self.sync_tab_to_view();
+            match self.tab_manager.start_collab(&role, &self.view.config) {
+                Ok(message) => self.view.notify(ToastKind::Info, message),
+                Err(e) => self.view.notify(ToastKind::Error, format!("Cannot start the shared session: {}", e)),
+            }
+            self.sync_view_to_tab();
+        }
+
+        // First start of a new version puts its release notes in front
+        if let Some(notes) = whats_new::on_launch() {
+            self.sync_tab_to_view();
+            let _ = self.tab_manager.open_read_only_tab(&notes.to_string_lossy());
+        }
         
         self.sync_view_to_tab();
         self.caret
             .move_to(self.tab_manager.current_tab().cursor_pos)
             .ok();
+        if self.tab_manager.current_tab().is_following() {
+            let _ = self.context().pin_to_bottom();
+        }
+        if self.tab_manager.current_tab().locked {
+            self.context().unlock_current_tab();
+        }
+        let _ = self.warn_about_file_lock();
+        self.report_load_warnings();
+        if self.open_finder {
+            let _ = self.context().find_file();
+        }
 
         match self.main_loop() {
             Ok(_) => {}
             Err(e) => {
                 self.view
-                    .show_prompt(crate::tui::view::PromptKind::Error, format!("Error: {}", e));
+                    .notify(ToastKind::Error, format!("Error: {}", e));
                 let _ = self.view.render_if_needed(
                     &self.caret,
                     self.tab_manager.current_tab().has_unsaved_changes,
                 );
-                let _ = Terminal::execute();
             }
         }
 
         if let Err(error) = Terminal::terminate() {
             eprintln!("Terminal Termination Failed: {:?}", error);
         }
+        if self.view.config.usage_stats {
+            let _ = self.stats.flush();
+        }
     }
 
+    // The editing state commands run against
+    fn context(&mut self) -> EditorContext<'_> {
+        EditorContext {
+            view: &mut self.view,
+            caret: &mut self.caret,
+            tabs: &mut self.tab_manager,
+            kill_ring: &mut self.kill_ring,
+            kill_appends: false,
+            stats: &mut self.stats,
+            dialog: &mut self.dialog,
+            compare: &mut self.compare,
+            quit: &mut self.quit_program,
+        }
+    }
+
+    // The shown tab's text lives in the view and moves back into the tab when another
+    // one is shown, so switching tabs never copies a buffer
     fn sync_view_to_tab(&mut self) {
-        let tab = self.tab_manager.current_tab();
-        self.view.buffer = tab.buffer.clone();
-        self.view.scroll_offset = tab.scroll_offset;
-        self.view.filename = tab.filename.clone();
-        self.view.filetype = tab.filetype.clone();
-        self.view.needs_redraw = true;
+        self.context().sync_view_to_tab();
     }
 
     fn sync_tab_to_view(&mut self) {
-        let tab = self.tab_manager.current_tab_mut();
-        tab.buffer = self.view.buffer.clone();
-        tab.scroll_offset = self.view.scroll_offset;
-        tab.cursor_pos = self.caret.get_position();
+        self.context().sync_tab_to_view();
+    }
+
+    // Route a key through the vim layer; None hands it to the regular shortcuts
+    fn vim_keys(&mut self, event: &KeyEvent) -> Result<Option<Vec<Action>>, std::io::Error> {
+        let Some(vim) = self.vim.as_mut() else {
+            return Ok(None);
+        };
+        let cursor = self.view.caret_chars(&self.caret);
+        let was = vim.mode;
+        let command = vim.handle(event, &self.view.buffer.lines, cursor);
+        let mode = vim.mode;
+        if mode != was {
+            self.view.vim_mode = Some(mode.label());
+            self.view.needs_redraw = true;
+        }
+
+        let actions = match command {
+            VimCommand::Pass => return Ok(None),
+            VimCommand::Pending => Vec::new(),
+            VimCommand::Run(actions) => actions,
+            VimCommand::Move(target) => {
+                let anchor = self.view.selection.as_ref().map(|s| s.anchor);
+                self.view.select_chars((target, target), &mut self.caret)?;
+                match (mode, anchor, self.view.selection.as_mut()) {
+                    (Mode::Visual, Some(anchor), Some(selection)) => selection.anchor = anchor,
+                    _ => self.view.selection = None,
+                }
+                Vec::new()
+            }
+            VimCommand::Apply(operator, start, end) => {
+                self.view.select_chars((start, end), &mut self.caret)?;
+                if operator == Operator::Yank {
+                    self.view.copy_selection()?;
+                    self.view.select_chars((start, start), &mut self.caret)?;
+                    self.view.selection = None;
+                    Vec::new()
+                } else {
+                    vec![Action::Cut]
+                }
+            }
+        };
+
+        if was != Mode::Visual && mode == Mode::Visual {
+            self.view.select_chars((cursor, cursor), &mut self.caret)?;
+        }
+        // Leaving visual mode without cutting drops the selection (after copying it for `y`)
+        if was == Mode::Visual && mode != Mode::Visual && !actions.contains(&Action::Cut) {
+            if actions.contains(&Action::Copy) {
+                self.view.copy_selection()?;
+            }
+            self.view.selection = None;
+            self.view.needs_redraw = true;
+            return Ok(Some(Vec::new()));
+        }
+        Ok(Some(actions))
     }
 
     fn main_loop(&mut self) -> Result<(), std::io::Error> {
         loop {
-            if let Some(since) = self.view.prompt_since {
-                if since.elapsed() >= std::time::Duration::from_secs(2) {
-                    self.view.clear_prompt();
-                    let _ = self.view.render_if_needed(
-                        &self.caret,
-                        self.tab_manager.current_tab().has_unsaved_changes,
-                    );
-                    let _ = Terminal::execute();
+            // Wait for input, waking up only when a timer is due
+            let now = std::time::Instant::now();
+            // Questions stay up until they are answered
+            match self.view.prompt_since.filter(|_| self.dialog.is_none()) {
+                Some(since) => self.idle.schedule(Timer::ClearPrompt, since + PROMPT_TIMEOUT),
+                None => self.idle.cancel(Timer::ClearPrompt),
+            }
+            match self.view.toasts.next_expiry() {
+                Some(at) => self.idle.schedule(Timer::Toasts, at),
+                None => self.idle.cancel(Timer::Toasts),
+            }
+            match self.shortcuts.pending_chord() {
+                Some((_, until)) => self.idle.schedule(Timer::Chord, until),
+                None => self.idle.cancel(Timer::Chord),
+            }
+            if self.tab_manager.current_tab().is_following() {
+                self.idle.ensure(Timer::Follow, now + FOLLOW_POLL_INTERVAL);
+            } else {
+                self.idle.cancel(Timer::Follow);
+            }
+            if self.tab_manager.current_tab().collab.is_some() {
+                self.sync_collab()?;
+                self.idle.ensure(Timer::Collab, now + COLLAB_POLL_INTERVAL);
+            } else {
+                self.idle.cancel(Timer::Collab);
+            }
+            if let Some(timeout) = self.idle.timeout(now) {
+                if !Terminal::poll_event(timeout)? {
+                    self.run_due_timers()?;
+                    continue;
                 }
             }
 
-            match read()? {
-                Event::Key(event) => {
-                    if event.kind == KeyEventKind::Press {
-                        if self.view.is_search_active() {
-                            match event.code {
-                                KeyCode::Down => {
-                                    self.view.next_search_match(&mut self.caret)?;
-                                    Terminal::execute()?;
-                                    continue;
-                                }
-                                KeyCode::Up => {
-                                    self.view.prev_search_match(&mut self.caret)?;
-                                    Terminal::execute()?;
-                                    continue;
-                                }
-                                KeyCode::Esc => {
-                                    self.view.clear_search();
-                                    self.view.render(&mut self.caret)?;
-                                    Terminal::execute()?;
-                                    continue;
-                                }
-                                _ => {
-                                    self.view.clear_search();
-                                }
+            match Terminal::read_event()? {
+                Event::Key(event) if event.kind == KeyEventKind::Press => {
+                    self.stats.keystrokes(1);
+                    if let Some(compare) = self.compare.as_mut() {
+                        if !compare.key(event.code)? {
+                            self.close_compare()?;
+                        }
+                        continue;
+                    }
+                    self.view.unpark_caret(&mut self.caret)?;
+                    if self.dialog.is_some() {
+                        self.dialog_key(event.code, event.modifiers)?;
+                        if self.quit_program {
+                            break;
+                        }
+                        self.finish_key()?;
+                        continue;
+                    }
+                    // Esc also clears messages, then does whatever it does otherwise
+                    if event.code == KeyCode::Esc {
+                        self.view.dismiss_toasts();
+                    }
+                    let mut leave_search = false;
+                    if self.view.is_search_active() {
+                        match event.code {
+                            KeyCode::Down => {
+                                self.view.next_search_match(&mut self.caret)?;
+                                continue;
                             }
+                            KeyCode::Up => {
+                                self.view.prev_search_match(&mut self.caret)?;
+                                continue;
+                            }
+                            KeyCode::Esc => {
+                                self.view.clear_search();
+                                self.view.render(&self.caret)?;
+                                continue;
+                            }
+                            _ => leave_search = true,
                         }
-
-                        if let Some(action) = self.shortcuts.resolve(&event) {
-                            match action {
-                                Action::SwitchTab(tab_num) => {
-                                    self.sync_tab_to_view();
-                                    self.tab_manager.switch_to_tab(tab_num)?;
-                                    self.sync_view_to_tab();
-                                    self.caret
-                                        .move_to(self.tab_manager.current_tab().cursor_pos)?;
-                                    self.view.render(&mut self.caret)?;
-                                }
-
-                                Action::Undo => {
-                                    if let Some(operation) =
-                                        self.tab_manager.current_tab_mut().edit_history.undo()
-                                    {
-                                        operation.edit.reverse(&mut self.view.buffer.lines);
-                                        self.view.scroll_offset = operation.scroll_before;
-                                        self.view.needs_redraw = true;
-                                        self.view.render_if_needed(
-                                            &self.caret,
-                                            self.tab_manager.current_tab().has_unsaved_changes,
-                                        )?;
-                                        self.caret.move_to(operation.cursor_before)?;
-                                        self.tab_manager.current_tab_mut().has_unsaved_changes =
-                                            true;
-                                    }
-                                }
-
-                                Action::Redo => {
-                                    if let Some(operation) =
-                                        self.tab_manager.current_tab_mut().edit_history.redo()
-                                    {
-                                        operation.edit.apply(&mut self.view.buffer.lines);
-                                        self.view.scroll_offset = operation.scroll_after;
-                                        self.view.needs_redraw = true;
-                                        self.view.render_if_needed(
-                                            &self.caret,
-                                            self.tab_manager.current_tab().has_unsaved_changes,
-                                        )?;
-                                        self.caret.move_to(operation.cursor_after)?;
-                                        self.tab_manager.current_tab_mut().has_unsaved_changes =
-                                            true;
-                                    }
-                                }
-
-                                Action::Save => self.save_file()?,
-                                
-                                Action::CheckUpdate => {
-                                    self.check_and_install_update()?;
-                                }
-
-                                Action::New => {
-                                    self.sync_tab_to_view();
-                                    self.tab_manager.new_tab();
-                                    self.sync_view_to_tab();
-                                    self.caret.move_to(caret::Position::default())?;
-                                    self.view.render(&mut self.caret)?;
-                                }
-
-                                Action::Search => self.view.search(&mut self.caret)?,
-                                Action::Copy => {
-                                     if let Err(e) = self.view.copy_selection() {
-                                         self.view.show_prompt(crate::tui::view::PromptKind::Error, e.to_string());
-                                     } else {
-                                         self.view.show_prompt(crate::tui::view::PromptKind::Error, "Copied!".into());
-                                     }
-                                 }
-                                 Action::Cut => {
-                                     match self.view.cut_selection(&mut self.caret) {
-                                         Ok(Some(op)) => {
-                                
-                                             let tab = self.tab_manager.current_tab_mut();
-                                
-                                             tab.edit_history.push(op);
-                                
-                                             tab.has_unsaved_changes = true;
-                                         }
-                                
-                                         Err(e) => self.view.show_prompt(crate::tui::view::PromptKind::Error, e.to_string()),
-                                         _ => {}
-                                     }
-                                 }
-                                 Action::Paste => {
-                                     match self.view.paste_from_clipboard(&mut self.caret) {
-                                
-                                         Ok(ops) => {
-                                
-                                             let tab = self.tab_manager.current_tab_mut();
-                                
-                                             if let Some(op) = ops {
-                                                 tab.edit_history.push(op);
-                                                 tab.has_unsaved_changes = true;
-                                             }
-                                         }
-                                         Err(e) => self.view.show_prompt(crate::tui::view::PromptKind::Error, e.to_string()),
-                                     }
-                                 }
-                                        
-
-                                Action::Left => self.view.move_left(&mut self.caret)?,
-                                Action::Right => self.view.move_right(&mut self.caret)?,
-                                Action::Up => self.view.move_up(&mut self.caret)?,
-                                Action::Down => self.view.move_down(&mut self.caret)?,
-                                Action::Top => self.view.move_top(&mut self.caret)?,
-                                Action::Bottom => self.view.move_bottom(&mut self.caret)?,
-                                Action::MaxLeft => self.view.move_max_left(&mut self.caret)?,
-                                Action::MaxRight => self.view.move_max_right(&mut self.caret)?,
-
-                                Action::SelectLeft => {
-                                    self.view.move_with_selection("left", &mut self.caret)?
-                                }
-                                Action::SelectRight => {
-                                    self.view.move_with_selection("right", &mut self.caret)?
-                                }
-                                Action::SelectUp => {
-                                    self.view.move_with_selection("up", &mut self.caret)?
-                                }
-                                Action::SelectDown => {
-                                    self.view.move_with_selection("down", &mut self.caret)?
-                                }
-                                Action::SelectTop => {
-                                    self.view.move_with_selection("top", &mut self.caret)?
-                                }
-                                Action::SelectBottom => {
-                                    self.view.move_with_selection("bottom", &mut self.caret)?
-                                }
-                                Action::SelectMaxLeft => {
-                                    self.view.move_with_selection("max_left", &mut self.caret)?
-                                }
-                                Action::SelectMaxRight => self
-                                    .view
-                                    .move_with_selection("max_right", &mut self.caret)?,
-                                Action::SelectAll => self.view.select_all(&mut self.caret)?,
-
-                                Action::NextLine => {
-                                    if let Some(op) = self.view.insert_newline(&mut self.caret)? {
-                                        self.tab_manager.current_tab_mut().edit_history.push(op);
-                                        self.tab_manager.current_tab_mut().has_unsaved_changes =
-                                            true;
-                                    }
-                                }
-
-                                Action::Backspace => {
-                                    if let Some(op) = self.view.backspace(&mut self.caret)? {
-                                        self.tab_manager.current_tab_mut().edit_history.push(op);
-                                        self.tab_manager.current_tab_mut().has_unsaved_changes =
-                                            true;
-                                    }
-                                }
-
-                                Action::Delete => {
-                                    if let Some(op) = self.view.delete_char(&mut self.caret)? {
-                                        self.tab_manager.current_tab_mut().edit_history.push(op);
-                                        self.tab_manager.current_tab_mut().has_unsaved_changes =
-                                            true;
-                                    }
-                                }
-
-                                Action::ToggleCtrlShortcuts => {
-                                    self.view.toggle_ctrl_shortcuts();
-                                    self.view.render(&mut self.caret)?;
-                                }
-
-                                Action::Quit => {
-                                    if self.tab_manager.current_tab().has_unsaved_changes {
-                                        self.view.show_prompt(
-                                            crate::tui::view::PromptKind::Error,
-                                            "Unsaved changes. Quit? (y/n)".to_string(),
-                                        );
-                                        self.view.needs_redraw = true;
-                                        self.view.render_if_needed(&self.caret, true)?;
-                                        Terminal::execute()?;
-
-                                        loop {
-                                            match read()? {
-                                                Event::Key(ev)
-                                                    if ev.kind == KeyEventKind::Press =>
-                                                {
-                                                    match ev.code {
-                                                        KeyCode::Char('y') | KeyCode::Char('Y') => {
-                                                            self.quit_program = true;
-                                                            break;
-                                                        }
-                                                        KeyCode::Char('n')
-                                                        | KeyCode::Char('N')
-                                                        | KeyCode::Esc => {
-                                                            self.view.clear_prompt();
-                                                            self.view.render_if_needed(
-                                                                &self.caret,
-                                                                true,
-                                                            )?;
-                                                            Terminal::execute()?;
-                                                            break;
-                                                        }
-                                                        _ => {}
-                                                    }
-                                                }
-                                                _ => {}
-                                            }
-                                        }
-                                    } else {
-                                        self.quit_program = true;
-                                    }
-                                }
-
-                                Action::Print => {
-                                    // Handle both Tab and regular characters
-                                    match event.code {
-                                        KeyCode::Tab => {
-                                            // Insert 4 spaces for tab
-                                            if let Some(op) =
-                                                self.view.type_character(' ', &mut self.caret)?
-                                            {
-                                                self.tab_manager
-                                                    .current_tab_mut()
-                                                    .edit_history
-                                                    .push(op);
-                                            }
-                                            if let Some(op) =
-                                                self.view.type_character(' ', &mut self.caret)?
-                                            {
-                                                self.tab_manager
-                                                    .current_tab_mut()
-                                                    .edit_history
-                                                    .push(op);
-                                            }
-                                            if let Some(op) =
-                                                self.view.type_character(' ', &mut self.caret)?
-                                            {
-                                                self.tab_manager
-                                                    .current_tab_mut()
-                                                    .edit_history
-                                                    .push(op);
-                                            }
-                                            if let Some(op) =
-                                                self.view.type_character(' ', &mut self.caret)?
-                                            {
-                                                self.tab_manager
-                                                    .current_tab_mut()
-                                                    .edit_history
-                                                    .push(op);
-                                            }
-                                            self.tab_manager
-                                                .current_tab_mut()
-                                                .has_unsaved_changes = true;
-                                        }
-                                        KeyCode::Char(character) => {
-                                            if let Some(op) = self
-                                                .view
-                                                .type_character(character, &mut self.caret)?
-                                            {
-                                                self.tab_manager
-                                                    .current_tab_mut()
-                                                    .edit_history
-                                                    .push(op);
-                                                self.tab_manager
-                                                    .current_tab_mut()
-                                                    .has_unsaved_changes = true;
-                                            }
-                                        }
-                                        _ => {}
-                                    }
-                                }
-                                _ => {}
+                    }
+                    // Esc then digits sets how many times the next action runs
+                    let mut times = 1;
+                    if self.view.config.count_prefix && self.vim.is_none() {
+                        match self.repeat.key(event.code, event.modifiers) {
+                            Some(count) => times = count,
+                            None => {
+                                self.view.pending_keys = self.repeat.pending().map(|count| format!("Esc {}", count));
+                                self.view.needs_redraw = true;
+                                self.view.render_if_needed(
+                                    &self.caret,
+                                    self.tab_manager.current_tab().has_unsaved_changes,
+                                )?;
+                                continue;
                             }
+                        }
+                    }
 
-                            self.view.render_if_needed(
-                                &self.caret,
-                                self.tab_manager.current_tab().has_unsaved_changes,
-                            )?;
-                            Terminal::execute()?;
+                    let actions = match self.vim_keys(&event)? {
+                        Some(actions) => actions,
+                        None => {
+                            let action = self.shortcuts.resolve(&event);
+                            let chord = self.shortcuts.pending_chord().map(|(keys, _)| keys);
+                            if chord != self.view.pending_keys {
+                                self.view.pending_keys = chord;
+                                self.view.needs_redraw = true;
+                            }
+                            action.into_iter().collect()
                         }
+                    };
+
+                    // Switching tabs keeps the search with the tab it was made in
+                    if leave_search && !actions.iter().any(|action| matches!(action, Action::SwitchTab(_))) {
+                        self.view.clear_search();
                     }
+                    for action in actions {
+                        self.run_action(action, times, &event)?;
+                    }
+                    self.finish_key()?;
                 }
+                Event::Mouse(_) if self.compare.is_some() => {}
                 Event::Mouse(mouse_event) => {
                     if let Some(action) = self.shortcuts.resolve_mouse(&mouse_event) {
+                        if !matches!(action, Action::ScrollUp(_) | Action::ScrollDown(_)) {
+                            // A click puts the caret somewhere new anyway
+                            self.view.parked_caret = None;
+                        }
                         match action {
+                            Action::ScrollUp(lines) => self.view.handle_wheel(-(lines as isize), &mut self.caret)?,
+                            Action::ScrollDown(lines) => self.view.handle_wheel(lines as isize, &mut self.caret)?,
                             Action::MouseDown(x, y) => {
                                 self.view.handle_mouse_down(x, y, &mut self.caret)?
                             }
@@ -573,13 +762,17 @@ impl TerminalEditor {
                             }
                             _ => {}
                         }
-                        Terminal::execute()?;
                     }
                 }
-                Event::Resize(_, _) => self.view.handle_resize(
-                    &mut self.caret,
-                    self.tab_manager.current_tab().has_unsaved_changes,
-                )?,
+                Event::Resize(_, _) => {
+                    self.view.handle_resize(
+                        &mut self.caret,
+                        self.tab_manager.current_tab().has_unsaved_changes,
+                    )?;
+                    if let Some(compare) = &self.compare {
+                        compare.render()?;
+                    }
+                }
                 _ => {}
             }
 
@@ -590,153 +783,291 @@ impl TerminalEditor {
         Ok(())
     }
 
-    fn save_file(&mut self) -> Result<(), std::io::Error> {
-        use std::fs;
-
-        // CRITICAL FIX: Use filepath (full path) instead of filename (display name)
-        let filepath_opt = self.tab_manager.current_tab().filepath.clone();
-
-        if let Some(filepath) = filepath_opt {
-            let last_line = self
-                .view
-                .buffer
-                .lines
-                .iter()
-                .rposition(|line| !line.is_empty())
-                .unwrap_or(0);
-            let content_lines: Vec<String> = self
-                .view
-                .buffer
-                .lines
-                .iter()
-                .take(last_line + 1)
-                .cloned()
-                .collect();
-            let content = content_lines.join("\n");
-
-            match fs::write(&filepath, content) {
-                Ok(_) => {
-                    self.tab_manager.current_tab_mut().has_unsaved_changes = false;
-                    let _ = self.tab_manager.save_session();
-                    self.view.needs_redraw = true;
-                    self.view.render_if_needed(&self.caret, false)?;
-                    Terminal::execute()?;
+    // Run an action `times` over when it repeats. Everything one key press changes, repeats
+    // included, undoes in one step; find and replace groups everything it replaces itself
+    fn run_action(&mut self, action: Action, times: usize, event: &KeyEvent) -> Result<(), std::io::Error> {
+        let times = if action.repeatable() { times } else { 1 };
+        let edits = action.modifies_buffer() && !matches!(action, Action::Undo | Action::Redo | Action::Replace);
+        if edits {
+            self.tab_manager.current_tab_mut().edit_history.begin();
+        }
+        let result = (0..times).try_for_each(|_| self.execute(action, event));
+        if edits {
+            self.tab_manager.current_tab_mut().edit_history.end();
+        }
+        result
+    }
+
+    // What follows every handled key: saving scratch notes, lock warnings and the redraw
+    fn finish_key(&mut self) -> Result<(), std::io::Error> {
+        // Scratch notes are written to disk on every change
+        let tab = self.tab_manager.current_tab();
+        if tab.scratch && tab.has_unsaved_changes {
+            self.autosave_scratch();
+        }
+
+        self.warn_about_file_lock()?;
+        if self.view.config.typewriter_scrolling && !self.tab_manager.current_tab().is_following() {
+            self.view.center_caret(&mut self.caret)?;
+        }
+        self.view.render_if_needed(
+            &self.caret,
+            self.tab_manager.current_tab().has_unsaved_changes,
+        )?;
+        if self.view.prompt.is_none() {
+            self.view.render_breadcrumbs(&self.caret)?;
+        }
+        Ok(())
+    }
+
+    // Run one resolved action against the current tab, once locks and read-only tabs allow it
+    fn execute(&mut self, action: Action, event: &KeyEvent) -> Result<(), std::io::Error> {
+        let kill_appends = std::mem::take(&mut self.kill_appends);
+        let blocked = action.modifies_buffer()
+            || matches!(action, Action::Save | Action::SaveAs | Action::SaveEncrypted);
+        if blocked && self.tab_manager.current_tab().locked {
+            self.context().unlock_current_tab();
+            return Ok(());
+        }
+        if blocked && self.tab_manager.current_tab().lock_holder.is_some() {
+            // Editing a file another instance has open offers to take its lock
+            self.tab_manager.current_tab_mut().lock_warned = false;
+            self.warn_about_file_lock()?;
+            return Ok(());
+        }
+        // Saving a download somewhere is what makes it editable
+        let saving_download = matches!(action, Action::Save | Action::SaveAs) && self.tab_manager.current_tab().url.is_some();
+        if blocked && self.tab_manager.current_tab().read_only && !saving_download {
+            self.view.notify(
+                ToastKind::Error,
+                "Buffer is read-only".to_string(),
+            );
+            self.view.render_if_needed(&self.caret, false)?;
+            return Ok(());
+        }
+
+        // Edits and undo keep caret positions as screen rows, which only match the text unfolded
+        if action.modifies_buffer() {
+            self.view.unfold_all(&mut self.caret)?;
+        }
+        if let Some(command) = commands::command(action, event) {
+            let mut context = self.context();
+            context.kill_appends = kill_appends;
+            command.execute(&mut context)?;
+            self.kill_appends = action == Action::KillLine;
+        }
+        Ok(())
+    }
+
+    fn autosave_scratch(&mut self) {
+        // Lend the text to the tab while it is written
+        let tab = self.tab_manager.current_tab_mut();
+        std::mem::swap(&mut tab.buffer, &mut self.view.buffer);
+        let result = tab.save_scratch(self.view.config.insert_final_newline);
+        std::mem::swap(&mut tab.buffer, &mut self.view.buffer);
+        if let Err(e) = result {
+            self.view.notify(
+                ToastKind::Error,
+                format!("Failed to save scratch note: {}", e),
+            );
+        }
+    }
+
+    fn open_scratch_note(&mut self, path: &str) -> Result<(), std::io::Error> {
+        self.sync_tab_to_view();
+        let opened = self.tab_manager.open_scratch_note(path);
+        self.sync_view_to_tab();
+        opened?;
+        self.caret.move_to(self.tab_manager.current_tab().cursor_pos)?;
+        self.view.render(&self.caret)
+    }
+
+    fn replace_each(&mut self, request: ReplaceRequest) -> Result<(), std::io::Error> {
+        if !self.view.start_replace(&mut self.caret, &request)? {
+            return self.view.render(&self.caret);
+        }
+        // Ended once the question closes, or later edits would keep joining the step
+        self.tab_manager.current_tab_mut().edit_history.begin();
+        let keys = ['y', 'n', 'a', 'q'].map(KeyCode::Char);
+        let keys = [KeyCode::Enter, KeyCode::Up, KeyCode::Down, KeyCode::Tab, KeyCode::BackTab].into_iter().chain(keys);
+        let followup = Followup::ReplaceEach { find: request.find, replaced: 0 };
+        self.ask(Dialog::keys(self.view.prompt_message(), keys.collect(), followup).stay_open());
+        Ok(())
+    }
+
+    // One key while Ctrl+H steps through matches; the number replaced
+    fn replace_step(&mut self, key: KeyCode) -> Result<usize, std::io::Error> {
+        let mut replaced = 0;
+        match key {
+            KeyCode::Enter | KeyCode::Char('y') => {
+                let operation = self.view.replace_current(&mut self.caret)?;
+                replaced += usize::from(operation.is_some());
+                self.push_edit(operation);
+            }
+            KeyCode::Char('n') => self.view.skip_match(&mut self.caret)?,
+            KeyCode::Char('a') => {
+                let operations = self.view.replace_all(&mut self.caret)?;
+                replaced += operations.len();
+                for operation in operations {
+                    self.push_edit(Some(operation));
                 }
-                Err(e) => return Err(e),
             }
-        } else {
-            self.view.show_prompt(
-                crate::tui::view::PromptKind::SaveAs,
-                "Save as: ".to_string(),
+            KeyCode::Down | KeyCode::Tab => self.view.next_search_match(&mut self.caret)?,
+            KeyCode::Up | KeyCode::BackTab => self.view.prev_search_match(&mut self.caret)?,
+            _ => self.view.clear_search(),
+        }
+        Ok(replaced)
+    }
+
+    fn push_edit(&mut self, operation: Option<EditOperation>) {
+        self.context().push_edit(operation);
+    }
+
+    fn read_file_into_buffer(&mut self, input: &str) -> Result<(), std::io::Error> {
+        match completion::read_text(input) {
+            Ok(text) => {
+                let operation = self.view.insert_at_caret(&mut self.caret, &text)?;
+                self.push_edit(operation);
+            }
+            Err(e) => self.view.notify(ToastKind::Error, format!("{}: {}", input, e)),
+        }
+        Ok(())
+    }
+
+    fn write_selection(&mut self, input: String) {
+        if input.is_empty() {
+            return;
+        }
+        if paths::resolve(&input).exists() {
+            let question = format!("{} exists, overwrite it?", input);
+            self.ask(Dialog::confirm(question, Followup::OverwriteSelection(input)));
+            return;
+        }
+        self.write_selection_text(&input);
+    }
+
+    fn write_selection_text(&mut self, input: &str) {
+        let Some(text) = self.view.selected_text() else {
+            return;
+        };
+        match completion::write_text(input, &text) {
+            Ok(()) => self.view.notify(
+                ToastKind::Info,
+                format!("Wrote {} lines to {}", text.lines().count().max(1), input),
+            ),
+            Err(e) => self.view.notify(ToastKind::Error, format!("{}: {}", input, e)),
+        }
+    }
+
+    fn append_to_file(&mut self, input: &str) {
+        let text = self.view.selection_or_file();
+        match append::append_to_file(input, &text, &self.view.config.append_separator) {
+            Ok(()) => self.view.notify(
+                ToastKind::Info,
+                format!("Appended {} lines to {}", text.lines().count().max(1), input),
+            ),
+            Err(e) => self.view.notify(ToastKind::Error, format!("{}: {}", input, e)),
+        }
+    }
+
+    fn restore_version(&mut self, content: String) -> Result<(), std::io::Error> {
+        if self.tab_manager.current_tab().is_read_only() {
+            self.view.notify(
+                ToastKind::Error,
+                "Read-only tab, cannot restore a version".to_string(),
             );
-            self.view.needs_redraw = true;
-            self.view.render_if_needed(
-                &self.caret,
-                self.tab_manager.current_tab().has_unsaved_changes,
-            )?;
-            Terminal::execute()?;
-
-            loop {
-                match read()? {
-                    Event::Key(event) if event.kind == KeyEventKind::Press => {
-                        match event.code {
-                            KeyCode::Char(c) => self.view.append_prompt_char(c),
-                            KeyCode::Backspace => self.view.backspace_prompt(),
-                            KeyCode::Enter => {
-                                if let Some((_, _, input)) = self.view.get_prompt() {
-                                    let filename = input.to_string();
-                                    self.view.clear_prompt();
-                                    if filename.is_empty() {
-                                        break;
-                                    }
-                                    
-                                    // Canonicalize the path to get absolute path
-                                    let path_buf = std::fs::canonicalize(&filename)
-                                        .unwrap_or_else(|_| {
-                                            // If file doesn't exist yet, build absolute path manually
-                                            let mut current_dir = std::env::current_dir().unwrap_or_default();
-                                            current_dir.push(&filename);
-                                            current_dir
-                                        });
-                                    
-                                    let full_path = path_buf.to_string_lossy().into_owned();
-                                    let display_name = path_buf
-                                        .file_name()
-                                        .map(|name| name.to_string_lossy().into_owned())
-                                        .unwrap_or_else(|| filename.clone());
-                                    
-                                    let raw_ext = path_buf.extension()
-                                        .map(|ext| ext.to_string_lossy().into_owned());
-                                    let friendly_filetype = get_friendly_filetype(raw_ext);
-
-                                    // Store BOTH the display name and the full path
-                                    self.tab_manager.current_tab_mut().filename = Some(display_name.clone());
-                                    self.tab_manager.current_tab_mut().filepath = Some(full_path.clone());
-                                    self.tab_manager.current_tab_mut().filetype = friendly_filetype.clone();
-                                    self.view.set_filename_and_filetype(Some(display_name), friendly_filetype);
-
-                                    let last_line = self
-                                        .view
-                                        .buffer
-                                        .lines
-                                        .iter()
-                                        .rposition(|line| !line.is_empty())
-                                        .unwrap_or(0);
-                                    let content_lines: Vec<String> = self
-                                        .view
-                                        .buffer
-                                        .lines
-                                        .iter()
-                                        .take(last_line + 1)
-                                        .cloned()
-                                        .collect();
-                                    let content = content_lines.join("\n");
-
-                                    // Save to the FULL PATH
-                                    match fs::write(&full_path, content) {
-                                        Ok(_) => {
-                                            self.tab_manager
-                                                .current_tab_mut()
-                                                .has_unsaved_changes = false;
-                                            let _ = self.tab_manager.save_session();
-                                            self.view.needs_redraw = true;
-                                            self.view.render_if_needed(&self.caret, false)?;
-                                            Terminal::execute()?;
-                                        }
-                                        Err(e) => {
-                                            self.view.show_prompt(
-                                                crate::tui::view::PromptKind::Error,
-                                                format!("Failed to save: {}", e),
-                                            );
-                                            self.view.render_if_needed(&self.caret, true)?;
-                                            Terminal::execute()?;
-                                            return Err(e);
-                                        }
-                                    }
-                                }
-                                break;
-                            }
-                            KeyCode::Esc => {
-                                self.view.clear_prompt();
-                                self.view.render_if_needed(
-                                    &self.caret,
-                                    self.tab_manager.current_tab().has_unsaved_changes,
-                                )?;
-                                Terminal::execute()?;
-                                break;
-                            }
-                            _ => {}
-                        }
-                        self.view.render_if_needed(
-                            &self.caret,
-                            self.tab_manager.current_tab().has_unsaved_changes,
-                        )?;
-                        Terminal::execute()?;
-                    }
-                    _ => {}
-                }
+            return Ok(());
+        }
+        let operation = self.view.replace_buffer(&mut self.caret, Buffer::from_string(content).lines)?;
+        self.push_edit(operation);
+        Ok(())
+    }
+
+    fn rename_file(&mut self, name: &str) {
+        match self.tab_manager.rename_current_file(name) {
+            Ok(path) => {
+                self.sync_view_to_tab();
+                self.view.notify(ToastKind::Info, format!("Renamed to {}", path));
+            }
+            Err(e) => self.view.notify(ToastKind::Error, e.to_string()),
+        }
+    }
+
+    fn trash_current_file(&mut self) -> Result<(), std::io::Error> {
+        let name = self.tab_manager.current_tab().filename.clone().unwrap_or_default();
+        match self.tab_manager.trash_current_file() {
+            Ok(()) => {
+                self.sync_view_to_tab();
+                self.caret.move_to(self.tab_manager.current_tab().cursor_pos)?;
+                self.view.notify(
+                    ToastKind::Info,
+                    format!("Moved {} to the trash (\"Restore deleted file\" brings it back)", name),
+                );
+            }
+            Err(e) => self.view.notify(ToastKind::Error, e.to_string()),
+        }
+        self.view.needs_redraw = true;
+        Ok(())
+    }
+
+    fn open_archive_member(&mut self, member: Member) -> Result<(), std::io::Error> {
+        self.sync_tab_to_view();
+        if let Err(e) = self.tab_manager.open_archive_member(member) {
+            self.sync_view_to_tab();
+            self.view.notify(ToastKind::Error, e.to_string());
+            return Ok(());
+        }
+        self.sync_view_to_tab();
+        self.caret.move_to(self.tab_manager.current_tab().cursor_pos)?;
+        self.view.render(&self.caret)?;
+        Ok(())
+    }
+
+    fn unlock_with(&mut self, passphrase: &str) -> Result<(), std::io::Error> {
+        match self.tab_manager.current_tab_mut().unlock(passphrase) {
+            Ok(()) => {
+                self.sync_view_to_tab();
+                self.caret
+                    .move_to(self.tab_manager.current_tab().cursor_pos)?;
+                self.view.render(&self.caret)?;
             }
+            Err(e) => self.ask(Dialog::passphrase(format!("{} - try again: ", e), Followup::Unlock)),
         }
         Ok(())
     }
-}
\ No newline at end of file
+
+    // Say what was reset when the config or session file could not be parsed
+    fn report_load_warnings(&mut self) {
+        let warnings: Vec<String> = [self.view.config.load_warning.take(), self.tab_manager.load_warning.take()]
+            .into_iter()
+            .flatten()
+            .collect();
+        if !warnings.is_empty() {
+            self.view.notify(ToastKind::Error, warnings.join(". "));
+        }
+    }
+
+    // Tell the user once when the file is open in another instance, offering to take over its lock
+    fn warn_about_file_lock(&mut self) -> Result<(), std::io::Error> {
+        let tab = self.tab_manager.current_tab();
+        let Some(holder) = tab.lock_holder.as_ref().filter(|_| !tab.lock_warned && self.dialog.is_none()) else {
+            return Ok(());
+        };
+        let name = tab.display_name(self.tab_manager.active_tab_index);
+        let question = format!("{} is open in {}. Steal the lock and edit here? (n keeps it read-only)", name, holder.describe());
+        self.ask(Dialog::confirm(question, Followup::StealLock));
+        Ok(())
+    }
+
+    fn finish_lock_warning(&mut self, steal: bool) {
+        let tab = self.tab_manager.current_tab_mut();
+        tab.lock_warned = true;
+        if steal {
+            if let Err(e) = tab.steal_lock() {
+                self.view.notify(ToastKind::Error, e.to_string());
+            }
+            self.sync_view_to_tab();
+        }
+    }
+
+}
@@ -0,0 +1,236 @@
+// screen module - double-buffered terminal output. Frames are drawn into a grid of cells,
+// compared with what the terminal already shows, and only the changed part of each row is written out.
+use crate::core::display;
+use crate::tui::terminal::Terminal;
+use crossterm::{
+    cursor::MoveTo,
+    queue,
+    style::{Attribute, Attributes, Color, Print, ResetColor, SetAttributes, SetBackgroundColor, SetForegroundColor},
+    terminal::{Clear, ClearType},
+};
+use std::cell::RefCell;
+use std::io::{stdout, Error};
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Clone, Copy, PartialEq)]
+struct Style {
+    fg: Color,
+    bg: Color,
+    attributes: Attributes,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self { fg: Color::Reset, bg: Color::Reset, attributes: Attributes::default() }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+struct Cell {
+    symbol: String, // Empty for the second column of a wide character
+    style: Style,
+}
+
+impl Cell {
+    fn blank(style: Style) -> Self {
+        Self { symbol: " ".to_string(), style }
+    }
+}
+
+pub struct Screen {
+    width: u16,
+    height: u16,
+    shown: Vec<Cell>, // What the terminal displays
+    cells: Vec<Cell>, // The frame being drawn
+    cursor: (u16, u16),
+    style: Style,
+}
+
+thread_local! {
+    static SCREEN: RefCell<Screen> = RefCell::new(Screen {
+        width: 0,
+        height: 0,
+        shown: Vec::new(),
+        cells: Vec::new(),
+        cursor: (0, 0),
+        style: Style::default(),
+    });
+}
+
+// Draw a frame on top of what is on screen, then queue the cells that changed
+pub fn draw(paint: impl FnOnce(&mut Screen) -> Result<(), Error>) -> Result<(), Error> {
+    SCREEN.with(|screen| {
+        let mut screen = screen.borrow_mut();
+        let size = Terminal::get_size()?;
+        screen.begin(size.width, size.height)?;
+        paint(&mut screen)?;
+        screen.flush()
+    })
+}
+
+// The terminal was cleared, so everything gets drawn again on the next frame
+pub fn forget() {
+    SCREEN.with(|screen| {
+        let mut screen = screen.borrow_mut();
+        let blank = vec![Cell::blank(Style::default()); screen.shown.len()];
+        screen.shown = blank;
+    });
+}
+
+impl Screen {
+    fn begin(&mut self, width: u16, height: u16) -> Result<(), Error> {
+        // After a resize the terminal content can't be trusted
+        if (width, height) != (self.width, self.height) {
+            self.width = width;
+            self.height = height;
+            self.shown = vec![Cell::blank(Style::default()); width as usize * height as usize];
+            queue!(stdout(), ResetColor, Clear(ClearType::All))?;
+        }
+        self.cells.clone_from(&self.shown);
+        self.cursor = (0, 0);
+        self.style = Style::default();
+        Ok(())
+    }
+
+    pub fn move_to(&mut self, x: u16, y: u16) {
+        self.cursor = (x, y);
+    }
+
+    pub fn fg(&mut self, color: Color) {
+        self.style.fg = color;
+    }
+
+    pub fn bg(&mut self, color: Color) {
+        self.style.bg = color;
+    }
+
+    // Same meaning as the escape codes: Reset clears colors too, the No* variants undo one attribute
+    pub fn attr(&mut self, attribute: Attribute) {
+        let attributes = &mut self.style.attributes;
+        match attribute {
+            Attribute::Reset => self.style = Style::default(),
+            Attribute::NormalIntensity => {
+                attributes.unset(Attribute::Bold);
+                attributes.unset(Attribute::Dim);
+            }
+            Attribute::NoItalic => attributes.unset(Attribute::Italic),
+            Attribute::NoUnderline => attributes.unset(Attribute::Underlined),
+            Attribute::NotCrossedOut => attributes.unset(Attribute::CrossedOut),
+            other => attributes.set(other),
+        }
+    }
+
+    // ResetColor resets attributes as well
+    pub fn reset(&mut self) {
+        self.style = Style::default();
+    }
+
+    pub fn print(&mut self, text: &str) {
+        let (mut x, y) = (self.cursor.0 as usize, self.cursor.1 as usize);
+        let width = self.width as usize;
+        if y >= self.height as usize {
+            return;
+        }
+        for grapheme in text.graphemes(true) {
+            let w = display::str_width(grapheme);
+            // Combining marks printed on their own join the character before them
+            if w == 0 {
+                let row = &mut self.cells[y * width..y * width + x.min(width)];
+                if let Some(cell) = row.iter_mut().rev().find(|cell| !cell.symbol.is_empty()) {
+                    cell.symbol.push_str(grapheme);
+                }
+                continue;
+            }
+            // No line wrap: anything past the edge is cut off
+            if x + w > width {
+                x = width;
+                break;
+            }
+            self.split_wide(y, x);
+            self.split_wide(y, x + w);
+            let row = y * width;
+            self.cells[row + x] = Cell { symbol: grapheme.to_string(), style: self.style };
+            for column in x + 1..x + w {
+                self.cells[row + column] = Cell { symbol: String::new(), style: self.style };
+            }
+            x += w;
+        }
+        self.cursor.0 = x as u16;
+    }
+
+    // Blank from the cursor to the end of the row in the current background, like Clear(UntilNewLine)
+    pub fn clear_rest_of_line(&mut self) {
+        let (x, y) = (self.cursor.0 as usize, self.cursor.1 as usize);
+        if y >= self.height as usize || x >= self.width as usize {
+            return;
+        }
+        self.split_wide(y, x);
+        let row = y * self.width as usize;
+        let blank = Cell::blank(Style { bg: self.style.bg, ..Style::default() });
+        self.cells[row + x..row + self.width as usize].fill(blank);
+    }
+
+    // Writing at column `x` of row `y` breaks a wide character drawn across it; blank its other half
+    fn split_wide(&mut self, y: usize, x: usize) {
+        let row = y * self.width as usize;
+        if x == 0 || x >= self.width as usize || !self.cells[row + x].symbol.is_empty() {
+            return;
+        }
+        let mut lead = x;
+        while lead > 0 && self.cells[row + lead].symbol.is_empty() {
+            lead -= 1;
+        }
+        let mut end = x;
+        while end < self.width as usize && (end == x || self.cells[row + end].symbol.is_empty()) {
+            end += 1;
+        }
+        for column in lead..end {
+            let style = self.cells[row + column].style;
+            self.cells[row + column] = Cell::blank(style);
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        let mut out = stdout();
+        let width = self.width as usize;
+        let mut current: Option<Style> = None;
+
+        for y in 0..self.height as usize {
+            let row = y * width..(y + 1) * width;
+            let changed = |&i: &usize| self.cells[i] != self.shown[i];
+            let (Some(first), Some(last)) = (row.clone().find(changed), row.clone().rev().find(changed)) else {
+                continue;
+            };
+            // Start on the first column of a wide character
+            let mut start = first;
+            while start > row.start && self.cells[start].symbol.is_empty() {
+                start -= 1;
+            }
+
+            queue!(out, MoveTo((start - row.start) as u16, y as u16))?;
+            for cell in &self.cells[start..=last] {
+                if cell.symbol.is_empty() {
+                    continue;
+                }
+                if current != Some(cell.style) {
+                    queue!(
+                        out,
+                        ResetColor,
+                        SetForegroundColor(cell.style.fg),
+                        SetBackgroundColor(cell.style.bg),
+                        SetAttributes(cell.style.attributes)
+                    )?;
+                    current = Some(cell.style);
+                }
+                queue!(out, Print(&cell.symbol))?;
+            }
+        }
+
+        if current.is_some() {
+            queue!(out, ResetColor)?;
+        }
+        queue!(out, MoveTo(self.cursor.0, self.cursor.1))?;
+        std::mem::swap(&mut self.shown, &mut self.cells);
+        Ok(())
+    }
+}
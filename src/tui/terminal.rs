@@ -1,18 +1,27 @@
 // terminal module responsible for terminal manipulation and information
+use crate::core::{display::{self, WidthRules}, toasts::ToastKind};
 use crate::tui::{
     view::View,
     caret::{ Position, Caret },
+    screen,
 };
 use crossterm::{
-    event::{EnableMouseCapture, DisableMouseCapture},
-    cursor::{ DisableBlinking, EnableBlinking, Hide, Show },
+    event::{
+        poll, read, EnableMouseCapture, DisableMouseCapture, Event, KeyboardEnhancementFlags,
+        PushKeyboardEnhancementFlags, PopKeyboardEnhancementFlags,
+    },
+    cursor::{ self, DisableBlinking, EnableBlinking, Hide, MoveTo, Show },
     queue,
+    style::Print,
     terminal::{ 
         Clear, ClearType, DisableLineWrap, disable_raw_mode, 
-        enable_raw_mode, size, EnterAlternateScreen, LeaveAlternateScreen 
+        enable_raw_mode, size, EnterAlternateScreen, LeaveAlternateScreen,
+        supports_keyboard_enhancement,
     }
 };
 use std::io::{ stdout, Error, Write };
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
 #[derive(Copy, Clone)]
 pub struct Size {
@@ -22,11 +31,26 @@ pub struct Size {
 
 pub struct Terminal;
 
+// Set when the terminal reports keys unambiguously (Ctrl+Shift+letter, Cmd as Super), to undo on exit
+static ENHANCED_KEYS: AtomicBool = AtomicBool::new(false);
+
 impl Terminal {
     
     pub fn initialize(view: &mut View, caret: &mut Caret) -> Result<(), Error> {
         enable_raw_mode()?;
-        queue!(stdout(), EnterAlternateScreen, DisableLineWrap, Hide, EnableMouseCapture )?;
+        queue!(stdout(), EnterAlternateScreen, DisableLineWrap, Hide)?;
+        Self::capture_mouse(view.config.mouse)?;
+        if supports_keyboard_enhancement().unwrap_or(false) {
+            queue!(stdout(), PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES))?;
+            ENHANCED_KEYS.store(true, Ordering::Relaxed);
+        }
+        // Terminals that don't answer the cursor query get the unicode-width defaults
+        let probed = if view.config.probe_widths { Self::probe_widths().unwrap_or_default() } else { WidthRules::default() };
+        let (rules, problems) = probed.with_overrides(&view.config.char_widths);
+        display::set_width_rules(rules);
+        if !problems.is_empty() {
+            view.notify(ToastKind::Error, problems.join(". "));
+        }
         Self::clear_screen()?;
         
         queue!(stdout(), Caret::CARET_SETTINGS.style)?;
@@ -42,6 +66,9 @@ impl Terminal {
 
     pub fn terminate() -> Result<(), Error> {
         Caret::reset_caret_color()?;
+        if ENHANCED_KEYS.swap(false, Ordering::Relaxed) {
+            queue!(stdout(), PopKeyboardEnhancementFlags)?;
+        }
         queue!(stdout(), DisableBlinking, Show, LeaveAlternateScreen, DisableMouseCapture)?;
         disable_raw_mode()?;
         Self::execute()?;
@@ -49,13 +76,33 @@ impl Terminal {
         Ok(())
     }
 
+    // With capture off the terminal handles clicks and drags itself, so its own selection works
+    pub fn capture_mouse(on: bool) -> Result<(), Error> {
+        if on {
+            queue!(stdout(), EnableMouseCapture)
+        } else {
+            queue!(stdout(), DisableMouseCapture)
+        }
+    }
+
+    // Print the emoji sequences terminals draw differently and ask where the cursor ended up.
+    // The screen is cleared right after, so nothing of this stays visible
+    fn probe_widths() -> Result<WidthRules, Error> {
+        let measure = |sample: &str| -> Result<u16, Error> {
+            queue!(stdout(), MoveTo(0, 0), Print(sample))?;
+            Self::execute()?;
+            Ok(cursor::position()?.0)
+        };
+        Ok(WidthRules {
+            narrow_vs16: measure("\u{2764}\u{FE0F}")? == 1,
+            split_zwj: measure("\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}")? > 2,
+            ..WidthRules::default()
+        })
+    }
+
     pub fn clear_screen() -> Result<(), Error> {
         queue!(stdout(), Clear(ClearType::All))?;
-        Ok(())
-    }
-    
-    pub fn clear_rest_of_line() -> Result<(), Error> {
-        queue!(stdout(), Clear(ClearType::UntilNewLine))?;
+        screen::forget();
         Ok(())
     }
 
@@ -63,6 +110,18 @@ impl Terminal {
         stdout().flush()?;
         Ok(())
     }
+
+    // Everything queued for the frame goes out in one flush, right before waiting for input
+    pub fn read_event() -> Result<Event, Error> {
+        Self::execute()?;
+        read()
+    }
+
+    // Like read_event, giving up after `timeout`; true when an event is ready
+    pub fn poll_event(timeout: Duration) -> Result<bool, Error> {
+        Self::execute()?;
+        poll(timeout)
+    }
     
     pub fn get_size() -> Result<Size, Error> {
         let (width, height) = size()?;
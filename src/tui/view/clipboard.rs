@@ -5,6 +5,7 @@ use crate::tui::caret::{Caret, Position};
 use crate::tui::terminal::Terminal;
 use crate::core::selection::TextPosition;
 use crate::core::edit_history::{Edit, EditOperation};
+use crate::core::platform;
 use std::io::Error;
 
 pub fn copy_selection(view: &View) -> Result<(), Error> {
@@ -18,7 +19,7 @@ pub fn copy_selection(view: &View) -> Result<(), Error> {
         
         // Copy to clipboard
         if let Ok(mut clipboard) = arboard::Clipboard::new() {
-            let _ = clipboard.set_text(selected_text);
+            let _ = clipboard.set_text(platform::to_clipboard(selected_text));
         }
     }
     Ok(())
@@ -41,7 +42,7 @@ pub fn paste_from_clipboard(view: &mut View, caret: &mut Caret) -> Result<Option
     // Get text from clipboard
     if let Ok(mut clipboard) = arboard::Clipboard::new() {
         if let Ok(text) = clipboard.get_text() {
-            return insert_text_at_cursor(view, caret, &text);
+            return insert_text_at_cursor(view, caret, &platform::from_clipboard(text));
         }
     }
     
@@ -94,7 +95,7 @@ pub fn delete_selection(view: &mut View, caret: &mut Caret) -> Result<Option<Edi
 }
 
 // Helper: Extract text from selection range
-fn extract_text(view: &View, start: TextPosition, end: TextPosition) -> String {
+pub(super) fn extract_text(view: &View, start: TextPosition, end: TextPosition) -> String {
     let mut result = String::new();
     
     if start.line == end.line {
@@ -191,8 +192,7 @@ fn insert_text_at_cursor(view: &mut View, caret: &mut Caret, text: &str) -> Resu
     let cursor_before = pos;
     let scroll_before = view.scroll_offset;
     
-    let buffer_line_idx = (pos.y.saturating_sub(Position::HEADER)) as usize + view.scroll_offset;
-    let char_pos = (pos.x as usize).saturating_sub(Position::MARGIN as usize);
+    let TextPosition { line: buffer_line_idx, column: char_pos } = super::helpers::get_current_text_pos(view, caret);
     
     // Ensure line exists
     while view.buffer.lines.len() <= buffer_line_idx {
@@ -221,12 +221,12 @@ fn insert_text_at_cursor(view: &mut View, caret: &mut Caret, text: &str) -> Resu
         view.buffer.lines[buffer_line_idx] = format!("{}{}", before, lines[0]);
         
         // Insert all middle and last lines
-        let mut insert_idx = buffer_line_idx + 1;
-        for i in 1..lines.len() {
+        for (i, pasted_line) in lines.iter().enumerate().skip(1) {
+            let insert_idx = buffer_line_idx + i;
             let line_content = if i == lines.len() - 1 {
-                format!("{}{}", lines[i], after)
+                format!("{}{}", pasted_line, after)
             } else {
-                lines[i].to_string()
+                pasted_line.to_string()
             };
             
             if insert_idx < view.buffer.lines.len() {
@@ -234,7 +234,6 @@ fn insert_text_at_cursor(view: &mut View, caret: &mut Caret, text: &str) -> Resu
             } else {
                 view.buffer.lines.push(line_content);
             }
-            insert_idx += 1;
         }
         
         // Calculate final position (in graphemes)
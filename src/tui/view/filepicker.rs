@@ -0,0 +1,375 @@
+// filepicker module - the Save As / Open file browser drawn over the text. One folder is listed
+// at a time, folders first, narrowed to the names starting with what is typed in the footer
+use crate::core::{display, paths};
+use crate::tui::screen::Screen;
+use crossterm::event::{KeyCode, KeyModifiers};
+use crossterm::style::{Attribute, Color};
+use std::fs;
+use std::path::{Path, PathBuf};
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Purpose {
+    Save,
+    Open,
+}
+
+#[derive(Clone)]
+pub struct Entry {
+    pub name: String,
+    pub is_dir: bool,
+}
+
+pub enum Pick {
+    Open,
+    Chosen(PathBuf),
+    Cancelled,
+}
+
+#[derive(Clone)]
+pub struct FilePicker {
+    pub purpose: Purpose,
+    dir: PathBuf,
+    entries: Vec<Entry>,
+    name: String,                // Typed in the footer: narrows the list, and names the file saved
+    selected: usize,             // Among the entries shown
+    browsing: bool,              // Moved through the list since typing, so Enter takes the highlighted entry
+    replacing: Option<PathBuf>,  // Existing file Enter was pressed on once while saving
+    problem: Option<String>,     // Shown instead of the key hints until the next key
+}
+
+// What is in `dir`: ".." unless it is the root, then folders and files by name, ignoring case
+pub fn list(dir: &Path) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = fs::read_dir(dir)
+        .map(|read| {
+            read.flatten()
+                .map(|entry| Entry {
+                    name: entry.file_name().to_string_lossy().into_owned(),
+                    is_dir: entry.path().is_dir(),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    entries.sort_by_key(|entry| (!entry.is_dir, entry.name.to_lowercase()));
+    if dir.parent().is_some() {
+        entries.insert(0, Entry { name: "..".to_string(), is_dir: true });
+    }
+    entries
+}
+
+impl FilePicker {
+    pub fn new(purpose: Purpose, dir: PathBuf) -> Self {
+        let mut picker = Self {
+            purpose,
+            dir: PathBuf::new(),
+            entries: Vec::new(),
+            name: String::new(),
+            selected: 0,
+            browsing: false,
+            replacing: None,
+            problem: None,
+        };
+        picker.enter(dir);
+        picker
+    }
+
+    // Hidden entries only show once a name starting with "." is typed
+    fn shown(&self) -> Vec<&Entry> {
+        let typed = self.name.to_lowercase();
+        self.entries
+            .iter()
+            .filter(|entry| match typed.as_str() {
+                "" => entry.name == ".." || !entry.name.starts_with('.'),
+                _ => entry.name != ".." && entry.name.to_lowercase().starts_with(&typed),
+            })
+            .collect()
+    }
+
+    fn enter(&mut self, dir: PathBuf) {
+        self.dir = fs::canonicalize(&dir).unwrap_or(dir);
+        self.entries = list(&self.dir);
+        self.name.clear();
+        self.selected = 0;
+        self.browsing = false;
+    }
+
+    // The typed name as a path: "~/notes" and "/tmp" go where they say, the rest is in this folder
+    fn typed_path(&self) -> PathBuf {
+        paths::normalize(&self.dir.join(paths::expand(&self.name)))
+    }
+
+    pub fn key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> Pick {
+        self.problem = None;
+        let replacing = self.replacing.take();
+        let count = self.shown().len();
+        match code {
+            KeyCode::Esc => return Pick::Cancelled,
+            KeyCode::Up | KeyCode::Down | KeyCode::PageUp | KeyCode::PageDown | KeyCode::Home | KeyCode::End => {
+                let last = count.saturating_sub(1);
+                self.selected = match code {
+                    KeyCode::Up => self.selected.checked_sub(1).unwrap_or(last),
+                    KeyCode::Down if self.selected >= last => 0,
+                    KeyCode::Down => self.selected + 1,
+                    KeyCode::PageUp => self.selected.saturating_sub(10),
+                    KeyCode::PageDown => (self.selected + 10).min(last),
+                    KeyCode::Home => 0,
+                    _ => last,
+                };
+                self.browsing = true;
+            }
+            KeyCode::Tab => self.complete(),
+            KeyCode::Backspace if self.name.is_empty() => {
+                if let Some(parent) = self.dir.parent() {
+                    self.enter(parent.to_path_buf());
+                }
+            }
+            KeyCode::Backspace => {
+                self.name.pop();
+                self.selected = 0;
+                self.browsing = false;
+            }
+            KeyCode::Char('n') if modifiers.contains(KeyModifiers::CONTROL) => self.make_dir(),
+            KeyCode::Char(c) if c == '/' || c == std::path::MAIN_SEPARATOR => {
+                let target = if self.name.is_empty() { PathBuf::from("/") } else { self.typed_path() };
+                if target.is_dir() {
+                    self.enter(target);
+                } else {
+                    self.problem = Some(format!("No folder {}", target.display()));
+                }
+            }
+            KeyCode::Char(c) if !modifiers.contains(KeyModifiers::CONTROL) => {
+                self.name.push(c);
+                self.selected = 0;
+                self.browsing = false;
+            }
+            KeyCode::Enter => return self.choose(replacing),
+            _ => {}
+        }
+        Pick::Open
+    }
+
+    // The highlighted entry after moving through the list or with nothing typed, else the typed name
+    fn choose(&mut self, replacing: Option<PathBuf>) -> Pick {
+        let highlighted = self.shown().get(self.selected).map(|entry| self.dir.join(&entry.name));
+        let target = if self.browsing || self.name.is_empty() {
+            let Some(path) = highlighted else {
+                return Pick::Open;
+            };
+            path
+        } else {
+            let typed = self.typed_path();
+            match self.purpose {
+                // Opening takes the first file starting with the name when there is none by that name
+                Purpose::Open if !typed.exists() => match highlighted {
+                    Some(path) => path,
+                    None => {
+                        self.problem = Some(format!("No file named {}", self.name));
+                        return Pick::Open;
+                    }
+                },
+                _ => typed,
+            }
+        };
+
+        if target.is_dir() {
+            self.enter(target);
+            return Pick::Open;
+        }
+        if self.purpose == Purpose::Save {
+            if !target.parent().is_some_and(Path::is_dir) {
+                self.problem = Some(format!("No folder to save {} in", target.display()));
+                return Pick::Open;
+            }
+            // Saving over a file asks first: Enter again replaces it
+            if target.exists() && replacing.as_ref() != Some(&target) {
+                self.problem = Some(format!("{} exists, Enter again to replace it", file_name(&target)));
+                self.replacing = Some(target);
+                return Pick::Open;
+            }
+        }
+        Pick::Chosen(target)
+    }
+
+    // Fill in as much of the name as the entries starting with it share; a lone folder is entered
+    fn complete(&mut self) {
+        let shown = self.shown();
+        match shown[..] {
+            [] => self.problem = Some(format!("Nothing here starts with {}", self.name)),
+            [entry] if entry.is_dir => {
+                let dir = self.dir.join(&entry.name);
+                self.enter(dir);
+            }
+            [entry] => self.name = entry.name.clone(),
+            [first, ..] => {
+                let mut common = first.name.clone();
+                for entry in &shown[1..] {
+                    let shared = common
+                        .chars()
+                        .zip(entry.name.chars())
+                        .take_while(|(a, b)| a.to_lowercase().eq(b.to_lowercase()))
+                        .map(|(a, _)| a.len_utf8())
+                        .sum();
+                    common.truncate(shared);
+                }
+                if common.chars().count() > self.name.chars().count() {
+                    self.name = common;
+                }
+                self.selected = 0;
+                self.browsing = false;
+            }
+        }
+    }
+
+    // Ctrl+N: a new folder with the typed name, which the list then moves into
+    fn make_dir(&mut self) {
+        if self.name.is_empty() {
+            self.problem = Some("Type the new folder's name, then Ctrl+N".to_string());
+            return;
+        }
+        let path = self.typed_path();
+        match fs::create_dir(&path) {
+            Ok(()) => self.enter(path),
+            Err(e) => self.problem = Some(format!("Could not create {}: {}", self.name, e)),
+        }
+    }
+
+    // Footer prompt as (message, input): the folder being listed and the name typed
+    pub fn prompt(&self) -> (String, String) {
+        let verb = match self.purpose {
+            Purpose::Save => "Save as",
+            Purpose::Open => "Open",
+        };
+        let dir = self.dir.to_string_lossy();
+        let separator = if dir.ends_with(std::path::MAIN_SEPARATOR) { "" } else { std::path::MAIN_SEPARATOR_STR };
+        (format!("{}: {}{}", verb, dir, separator), self.name.clone())
+    }
+
+    // The list over the text rows from `top`, the last row holding the keys or a problem
+    pub fn paint(&self, screen: &mut Screen, top: u16, rows: usize, width: u16) {
+        let width = width as usize;
+        let shown = self.shown();
+        let room = rows.saturating_sub(1);
+        // Enough of the list above the highlighted entry to keep it on screen
+        let first = (self.selected + 1).saturating_sub(room);
+        for row in 0..room {
+            screen.move_to(0, top + row as u16);
+            screen.reset();
+            screen.clear_rest_of_line();
+            let Some(entry) = shown.get(first + row) else {
+                continue;
+            };
+            let label = if entry.is_dir { format!(" {}/", entry.name) } else { format!(" {}", entry.name) };
+            if entry.is_dir {
+                screen.fg(Color::Blue);
+                screen.attr(Attribute::Bold);
+            }
+            if first + row == self.selected {
+                screen.attr(Attribute::Reverse);
+            }
+            screen.print(&fit(&label, width));
+            screen.reset();
+        }
+
+        screen.move_to(0, top + room as u16);
+        screen.clear_rest_of_line();
+        let status = match &self.problem {
+            Some(problem) => {
+                screen.fg(Color::Red);
+                format!(" {}", problem)
+            }
+            None => {
+                screen.fg(Color::DarkGrey);
+                let enter = match self.purpose {
+                    Purpose::Save => "Enter save",
+                    Purpose::Open => "Enter open",
+                };
+                format!(
+                    " {} of {} | ↑/↓ pick | {} | Tab complete | Ctrl+N new folder | Backspace up | Esc cancel",
+                    shown.len().min(self.selected + 1),
+                    shown.len(),
+                    enter
+                )
+            }
+        };
+        screen.print(&fit(&status, width));
+        screen.reset();
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map_or_else(|| path.to_string_lossy(), |name| name.to_string_lossy()).into_owned()
+}
+
+// Cut to `width` cells and pad out to it
+fn fit(text: &str, width: usize) -> String {
+    let mut out = String::new();
+    let mut used = 0;
+    for grapheme in text.graphemes(true) {
+        let cells = display::str_width(grapheme);
+        if used + cells > width {
+            break;
+        }
+        out.push_str(grapheme);
+        used += cells;
+    }
+    out.push_str(&" ".repeat(width - used));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(picker: &mut FilePicker, text: &str) -> Vec<Pick> {
+        text.chars().map(|c| picker.key(KeyCode::Char(c), KeyModifiers::empty())).collect()
+    }
+
+    #[test]
+    fn test_browse_complete_and_save() {
+        let root = std::env::temp_dir().join(format!("quick_filepicker_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("notes")).unwrap();
+        fs::write(root.join("notes/todo.txt"), "").unwrap();
+        fs::write(root.join("notebook.md"), "").unwrap();
+        fs::write(root.join(".hidden"), "").unwrap();
+        let root = fs::canonicalize(&root).unwrap();
+
+        let mut picker = FilePicker::new(Purpose::Save, root.clone());
+        let names = |picker: &FilePicker| picker.shown().iter().map(|e| e.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&picker), ["..", "notes", "notebook.md"]);
+
+        // "no" leaves both, Tab fills in what they share
+        typed(&mut picker, "no");
+        picker.key(KeyCode::Tab, KeyModifiers::empty());
+        assert_eq!(picker.prompt().1, "note");
+
+        // A new folder is made and entered, and a new name there is saved to
+        typed(&mut picker, "s/archive");
+        picker.key(KeyCode::Char('n'), KeyModifiers::CONTROL);
+        assert_eq!(picker.dir, root.join("notes/archive"));
+        typed(&mut picker, "new.txt");
+        let chosen = picker.key(KeyCode::Enter, KeyModifiers::empty());
+        assert!(matches!(chosen, Pick::Chosen(path) if path == root.join("notes/archive/new.txt")));
+
+        // Backspace with nothing typed goes up, and an existing file needs Enter twice
+        let mut picker = FilePicker::new(Purpose::Save, root.join("notes/archive"));
+        picker.key(KeyCode::Backspace, KeyModifiers::empty());
+        assert_eq!(picker.dir, root.join("notes"));
+        picker.key(KeyCode::Down, KeyModifiers::empty());
+        picker.key(KeyCode::Down, KeyModifiers::empty());
+        assert!(matches!(picker.key(KeyCode::Enter, KeyModifiers::empty()), Pick::Open));
+        let chosen = picker.key(KeyCode::Enter, KeyModifiers::empty());
+        assert!(matches!(chosen, Pick::Chosen(path) if path == root.join("notes/todo.txt")));
+
+        // Opening takes the first file starting with the typed name
+        let mut picker = FilePicker::new(Purpose::Open, root.clone());
+        typed(&mut picker, ".h");
+        assert_eq!(names(&picker), [".hidden"]);
+        picker.key(KeyCode::Backspace, KeyModifiers::empty());
+        picker.key(KeyCode::Backspace, KeyModifiers::empty());
+        typed(&mut picker, "noteb");
+        let chosen = picker.key(KeyCode::Enter, KeyModifiers::empty());
+        assert!(matches!(chosen, Pick::Chosen(path) if path == root.join("notebook.md")));
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,6 +1,6 @@
 // module handling graphemes
 use unicode_segmentation::UnicodeSegmentation;
-use unicode_width::UnicodeWidthStr;
+use crate::core::display;
 
 // Convert grapheme index to byte offset
 pub fn grapheme_to_byte_idx(s: &str, grapheme_idx: usize) -> usize {
@@ -20,9 +20,9 @@ pub fn grapheme_len(s: &str) -> usize {
     s.graphemes(true).count()
 }
 
-// Get visual width of string (accounts for wide characters like emojis)
-pub fn visual_width(s: &str) -> usize {
-    UnicodeWidthStr::width(s)
+// Get visual width of string (accounts for wide characters like emojis, tabs and ^X control placeholders)
+pub fn visual_width(s: &str, tab_width: usize) -> usize {
+    display::expand(s, tab_width).width()
 }
 
 // Extract substring by grapheme indices
@@ -56,4 +56,63 @@ pub fn remove_grapheme_at(s: &mut String, grapheme_idx: usize) -> Option<String>
 pub fn split_at_grapheme(s: &str, grapheme_idx: usize) -> (&str, &str) {
     let byte_idx = grapheme_to_byte_idx(s, grapheme_idx);
     s.split_at(byte_idx)
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    // Letters mixed with lone combining accents, joiners, regional indicators and wide characters,
+    // so clusters of every shape turn up, including ones starting with a stray mark
+    const TEXT: &str = "[ae\t界👩💻🇵🇱\u{301}\u{200d}]{0,12}";
+
+    proptest! {
+        #[test]
+        fn prop_helpers_never_split_clusters(s in TEXT, a in 0..14usize, b in 0..14usize) {
+            let clusters: Vec<&str> = s.graphemes(true).collect();
+            let (start, end) = (a.min(b), a.max(b));
+
+            let idx = grapheme_to_byte_idx(&s, a);
+            prop_assert!(idx == s.len() || s.grapheme_indices(true).any(|(i, _)| i == idx));
+
+            let (left, right) = split_at_grapheme(&s, a);
+            prop_assert_eq!(format!("{}{}", left, right), s.clone());
+            prop_assert_eq!(left.graphemes(true).count(), a.min(clusters.len()));
+            prop_assert_eq!(right.graphemes(true).collect::<Vec<_>>(), clusters[a.min(clusters.len())..].to_vec());
+
+            let slice = grapheme_slice(&s, start, end);
+            let expected = clusters.get(start..end.min(clusters.len())).unwrap_or_default().concat();
+            prop_assert_eq!(slice, expected);
+
+            // Removing takes exactly one whole cluster, inserting lands between two
+            let mut edited = s.clone();
+            let removed = remove_grapheme_at(&mut edited, a);
+            prop_assert_eq!(removed.as_deref(), grapheme_at(&s, a));
+            let mut rest = clusters.clone();
+            if a < rest.len() {
+                rest.remove(a);
+            }
+            prop_assert_eq!(&edited, &rest.concat());
+            let mut inserted = s.clone();
+            insert_at_grapheme(&mut inserted, a, "|");
+            prop_assert_eq!(inserted, format!("{}|{}", left, right));
+        }
+    }
+
+    #[test]
+    fn test_caret_column_finds_link_after_non_ascii() {
+        // Accents and a joined emoji make grapheme, char and byte columns all differ
+        let line = "zażółć 👩\u{200d}💻 see https://example.com/x";
+        let column = line.graphemes(true).position(|g| g == "h").unwrap() + 3;
+        let byte = grapheme_slice(line, 0, column).len();
+        assert_eq!(crate::core::links::url_at(line, byte), Some("https://example.com/x"));
+    }
+
+    #[test]
+    fn test_caret_column_finds_path_after_non_ascii() {
+        let line = "błąd 👩\u{200d}💻 w src/core/tabs.rs:42";
+        let column = line.graphemes(true).position(|g| g == "t").unwrap() + 2;
+        let byte = grapheme_slice(line, 0, column).len();
+        assert_eq!(crate::core::links::path_at(line, byte), Some(("src/core/tabs.rs", Some(42))));
+    }
+}
@@ -1,11 +1,15 @@
 // keyboard logic with edit tracking for undo/redo
-use super::View;
+use super::{View, helpers::{get_current_text_pos, text_to_screen_pos}};
 use super::graphemes::*;
 use crate::tui::{
     terminal::Terminal, 
     caret::{Caret, Position}
 };
-use crate::core::edit_history::{Edit, EditOperation};
+use crate::core::{
+    display,
+    edit_history::{Edit, EditOperation},
+    selection::TextPosition,
+};
 use std::io::Error;
 
 pub fn type_character(view: &mut View, character: char, caret: &mut Caret) -> Result<Option<EditOperation>, Error> {    
@@ -21,8 +25,7 @@ pub fn type_character(view: &mut View, character: char, caret: &mut Caret) -> Re
         return Ok(None);
     }
     
-    let buffer_line_idx = (position.y.saturating_sub(Position::HEADER)) as usize + view.scroll_offset;
-    let char_pos = (position.x as usize).saturating_sub(Position::MARGIN as usize);
+    let TextPosition { line: buffer_line_idx, column: char_pos } = get_current_text_pos(view, caret);
     
     let cursor_before = position;
     let scroll_before = view.scroll_offset;
@@ -31,29 +34,36 @@ pub fn type_character(view: &mut View, character: char, caret: &mut Caret) -> Re
         view.buffer.lines.push(String::new());
     }
 
-    // If at end of screen width, wrap to next line
-    if position.x >= size.width - 1 {
+    // If at end of screen width, wrap to next line; long lines move their window along instead
+    let is_long = display::is_long(&view.buffer.lines[buffer_line_idx]);
+    if position.x >= size.width - 1 && !is_long {
         insert_newline(view, caret)?;
         return type_character(view, character, caret);
     }
 
-    // Insert the character at grapheme position
+    // Insert the character at grapheme position, padding a caret in virtual space out to it
     let line = &mut view.buffer.lines[buffer_line_idx];
     let grapheme_count = grapheme_len(line);
     let grapheme_pos = char_pos.min(grapheme_count);
+    let text = format!("{}{}", " ".repeat(char_pos - grapheme_pos), character);
     
-    insert_at_grapheme(line, grapheme_pos, &character.to_string());
+    insert_at_grapheme(line, grapheme_pos, &text);
 
-    view.render(caret)?;
-    
-    let new_offset = caret.move_right(view.scroll_offset, view.buffer.lines.len())?;
-    view.scroll_offset = new_offset;
+    if is_long {
+        let (x, y) = text_to_screen_pos(view, TextPosition { line: buffer_line_idx, column: char_pos + 1 });
+        view.render(caret)?;
+        caret.move_to(Position { x, y })?;
+    } else {
+        view.render(caret)?;
+        let new_offset = caret.move_right(view.scroll_offset, view.buffer.lines.len())?;
+        view.scroll_offset = new_offset;
+    }
     
     Ok(Some(EditOperation {
         edit: Edit::InsertText {
             line: buffer_line_idx,
             column: grapheme_pos,
-            text: character.to_string(),
+            text,
         },
         cursor_before,
         cursor_after: caret.get_position(),
@@ -77,8 +87,7 @@ pub fn insert_newline(view: &mut View, caret: &mut Caret) -> Result<Option<EditO
     let cursor_before = position;
     let scroll_before = view.scroll_offset;
     
-    let buffer_line_idx = (position.y.saturating_sub(Position::HEADER)) as usize + view.scroll_offset;
-    let char_pos = (position.x as usize).saturating_sub(Position::MARGIN as usize);
+    let TextPosition { line: buffer_line_idx, column: char_pos } = get_current_text_pos(view, caret);
 
     while view.buffer.lines.len() <= buffer_line_idx {
         view.buffer.lines.push(String::new());
@@ -133,8 +142,7 @@ pub fn delete_char(view: &mut View, caret: &mut Caret) -> Result<Option<EditOper
     let cursor_before = pos;
     let scroll_before = view.scroll_offset;
     
-    let buffer_line_idx = (pos.y.saturating_sub(Position::HEADER)) as usize + view.scroll_offset;
-    let char_pos = (pos.x as usize).saturating_sub(Position::MARGIN as usize);
+    let TextPosition { line: buffer_line_idx, column: char_pos } = get_current_text_pos(view, caret);
     
     if buffer_line_idx >= view.buffer.lines.len() {
         return Ok(None);
@@ -192,8 +200,7 @@ pub fn backspace(view: &mut View, caret: &mut Caret) -> Result<Option<EditOperat
     let cursor_before = pos;
     let scroll_before = view.scroll_offset;
     
-    let buffer_line_idx = (pos.y.saturating_sub(Position::HEADER)) as usize + view.scroll_offset;
-    let char_pos = (pos.x as usize).saturating_sub(Position::MARGIN as usize);
+    let TextPosition { line: buffer_line_idx, column: char_pos } = get_current_text_pos(view, caret);
     
     if char_pos > 0 {
         // Delete grapheme before cursor
@@ -201,9 +208,15 @@ pub fn backspace(view: &mut View, caret: &mut Caret) -> Result<Option<EditOperat
             let grapheme_count = grapheme_len(line);
             if char_pos <= grapheme_count {
                 let deleted = remove_grapheme_at(line, char_pos - 1).unwrap_or_default();
-                view.render(caret)?;
-                let new_offset = caret.move_left(view.scroll_offset)?;
-                view.scroll_offset = new_offset;
+                if display::is_long(line) {
+                    let (x, y) = text_to_screen_pos(view, TextPosition { line: buffer_line_idx, column: char_pos - 1 });
+                    view.render(caret)?;
+                    caret.move_to(Position { x, y })?;
+                } else {
+                    view.render(caret)?;
+                    let new_offset = caret.move_left(view.scroll_offset)?;
+                    view.scroll_offset = new_offset;
+                }
                 
                 return Ok(Some(EditOperation {
                     edit: Edit::DeleteText {
@@ -217,6 +230,9 @@ pub fn backspace(view: &mut View, caret: &mut Caret) -> Result<Option<EditOperat
                     scroll_after: view.scroll_offset,
                 }));
             }
+            // Past the end in virtual space there is nothing to delete, just step back
+            let new_offset = caret.move_left(view.scroll_offset)?;
+            view.scroll_offset = new_offset;
         }
     } else if buffer_line_idx > 0 {
         // At beginning of line, merge with previous line
@@ -227,19 +243,14 @@ pub fn backspace(view: &mut View, caret: &mut Caret) -> Result<Option<EditOperat
         view.buffer.lines.remove(buffer_line_idx);
         
         // Move cursor to end of previous line
+        let (x, _) = text_to_screen_pos(view, TextPosition { line: buffer_line_idx - 1, column: prev_line_len });
         if pos.y > Position::HEADER {
             view.render(caret)?;
-            caret.move_to(Position { 
-                x: Position::MARGIN + prev_line_len as u16, 
-                y: pos.y - 1 
-            })?;
+            caret.move_to(Position { x, y: pos.y - 1 })?;
         } else if view.scroll_offset > 0 {
             view.scroll_offset -= 1;
             view.render(caret)?;
-            caret.move_to(Position { 
-                x: Position::MARGIN + prev_line_len as u16, 
-                y: Position::HEADER 
-            })?;
+            caret.move_to(Position { x, y: Position::HEADER })?;
         }
         
         return Ok(Some(EditOperation {
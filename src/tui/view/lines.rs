@@ -0,0 +1,292 @@
+// lines module - line based editing commands (checklists, tables, prefix continuation) with edit tracking
+use super::View;
+use super::graphemes::*;
+use super::helpers::{get_current_text_pos, text_to_screen_pos};
+use crate::core::{
+    align,
+    conflicts::{self, Resolution},
+    continuation::continuation,
+    edit_history::{Edit, EditOperation},
+    markdown,
+    reflow,
+    selection::TextPosition,
+    sequence::{self, Sequence},
+};
+use crate::tui::{
+    caret::{Caret, Position},
+    terminal::Terminal,
+};
+use std::io::Error;
+
+// Replace `old_len` lines starting at `start_line` as a single undoable edit
+pub fn replace_lines(
+    view: &mut View,
+    caret: &mut Caret,
+    start_line: usize,
+    old_len: usize,
+    new_lines: Vec<String>,
+) -> Result<Option<EditOperation>, Error> {
+    let end = (start_line + old_len).min(view.buffer.lines.len());
+    let old_lines = view.buffer.lines[start_line.min(end)..end].to_vec();
+    if old_lines == new_lines {
+        return Ok(None);
+    }
+
+    let cursor_before = caret.get_position();
+    let scroll_before = view.scroll_offset;
+
+    let edit = Edit::ReplaceLines { start_line, old_lines, new_lines };
+    edit.apply(&mut view.buffer.lines);
+
+    // Keep the caret inside its (possibly shorter) line
+    let pos = get_current_text_pos(view, caret);
+    let line_len = view.buffer.lines.get(pos.line).map(|l| grapheme_len(l)).unwrap_or(0);
+    let (x, _) = text_to_screen_pos(view, TextPosition { line: pos.line, column: pos.column.min(line_len) });
+    caret.move_to(Position { x, y: cursor_before.y })?;
+
+    Ok(Some(EditOperation {
+        edit,
+        cursor_before,
+        cursor_after: caret.get_position(),
+        scroll_before,
+        scroll_after: view.scroll_offset,
+    }))
+}
+
+// Replace the text between two positions with `text` (which may span lines),
+// leaving the caret after it
+pub fn replace_text(
+    view: &mut View,
+    caret: &mut Caret,
+    start: TextPosition,
+    end: TextPosition,
+    text: &str,
+) -> Result<Option<EditOperation>, Error> {
+    let first = view.buffer.lines.get(start.line).cloned().unwrap_or_default();
+    let last = view.buffer.lines.get(end.line).cloned().unwrap_or_default();
+    let prefix = grapheme_slice(&first, 0, start.column.min(grapheme_len(&first)));
+    let suffix = grapheme_slice(&last, end.column.min(grapheme_len(&last)), grapheme_len(&last));
+
+    view.selection = None;
+    let combined = format!("{}{}{}", prefix, text, suffix);
+    let new_lines: Vec<String> = combined.split('\n').map(String::from).collect();
+    let mut operation = replace_lines(view, caret, start.line, end.line - start.line + 1, new_lines)?;
+    if let Some(op) = operation.as_mut() {
+        let caret_pos = match text.rsplit_once('\n') {
+            Some((before, last_part)) => TextPosition {
+                line: start.line + before.matches('\n').count() + 1,
+                column: grapheme_len(last_part),
+            },
+            None => TextPosition {
+                line: start.line,
+                column: grapheme_len(&prefix) + grapheme_len(text),
+            },
+        };
+        view.jump_to(caret_pos, caret)?;
+        op.cursor_after = caret.get_position();
+        op.scroll_after = view.scroll_offset;
+    }
+    Ok(operation)
+}
+
+// Emacs Ctrl+K: remove the rest of the line, or the line break when the caret is already at its end
+pub fn kill_line(view: &mut View, caret: &mut Caret) -> Result<Option<(String, Option<EditOperation>)>, Error> {
+    let start = get_current_text_pos(view, caret);
+    let line = view.buffer.lines.get(start.line).cloned().unwrap_or_default();
+    let len = grapheme_len(&line);
+    let (end, killed) = if start.column < len {
+        (TextPosition { line: start.line, column: len }, grapheme_slice(&line, start.column, len))
+    } else if start.line + 1 < view.buffer.lines.len() {
+        (TextPosition { line: start.line + 1, column: 0 }, "\n".to_string())
+    } else {
+        return Ok(None);
+    };
+    let operation = replace_text(view, caret, start, end, "")?;
+    Ok(Some((killed, operation)))
+}
+
+pub fn toggle_checkbox(view: &mut View, caret: &mut Caret) -> Result<Option<EditOperation>, Error> {
+    let line_idx = get_current_text_pos(view, caret).line;
+    let line = view.buffer.lines.get(line_idx).cloned().unwrap_or_default();
+    let toggled = markdown::toggle_checkbox(&line);
+
+    // Shift the caret so it stays on the same text
+    let shift = grapheme_len(&toggled) as isize - grapheme_len(&line) as isize;
+    let mut operation = replace_lines(view, caret, line_idx, 1, vec![toggled])?;
+    if let Some(op) = operation.as_mut() {
+        let pos = caret.get_position();
+        if pos.x > Position::MARGIN {
+            let x = (pos.x as isize + shift).max(Position::MARGIN as isize) as u16;
+            caret.move_to(Position { x, y: pos.y })?;
+            op.cursor_after = caret.get_position();
+        }
+    }
+    Ok(operation)
+}
+
+pub fn move_done_items(view: &mut View, caret: &mut Caret) -> Result<Option<EditOperation>, Error> {
+    let content = view.buffer.lines[..view.buffer.logical_len()].to_vec();
+
+    match markdown::move_done_items(&content) {
+        Some(new_lines) => replace_lines(view, caret, 0, content.len(), new_lines),
+        None => Ok(None),
+    }
+}
+
+// Reflow the Markdown table under the caret
+pub fn format_table(view: &mut View, caret: &mut Caret) -> Result<Option<EditOperation>, Error> {
+    let line_idx = get_current_text_pos(view, caret).line;
+    let Some((start, end)) = markdown::table_bounds(&view.buffer.lines, line_idx) else {
+        return Ok(None);
+    };
+
+    let formatted = markdown::format_table(&view.buffer.lines[start..end]);
+    replace_lines(view, caret, start, end - start, formatted)
+}
+
+// Lines an align command works on: the selected lines, or the paragraph at the caret
+pub fn align_range(view: &View, caret: &Caret) -> (usize, usize) {
+    match view.selection.as_ref().filter(|s| s.is_active()) {
+        Some(selection) => {
+            let (start, end) = selection.get_range();
+            align::selected_lines(start, end)
+        }
+        None => align::paragraph_bounds(&view.buffer.lines, get_current_text_pos(view, caret).line),
+    }
+}
+
+pub fn align_lines(
+    view: &mut View,
+    caret: &mut Caret,
+    (start, end): (usize, usize),
+    delimiter: &str,
+) -> Result<Option<EditOperation>, Error> {
+    let aligned = align::align_on(&view.buffer.lines[start..end], delimiter);
+    replace_lines(view, caret, start, end - start, aligned)
+}
+
+// Re-wrap the lines to the configured column
+pub fn reflow_lines(view: &mut View, caret: &mut Caret, (start, end): (usize, usize)) -> Result<Option<EditOperation>, Error> {
+    let lines = &view.buffer.lines[start..end];
+    let reflowed = reflow::reflow(lines, view.config.wrap_column, view.config.tab_width, view.filetype.as_deref());
+    view.selection = None;
+    replace_lines(view, caret, start, end - start, reflowed)
+}
+
+// Break the caret's line at the wrap column once typing runs past it, taking the caret
+// along when it sits in the words that moved down
+pub fn hard_wrap(view: &mut View, caret: &mut Caret) -> Result<Option<EditOperation>, Error> {
+    let pos = get_current_text_pos(view, caret);
+    let line = &view.buffer.lines[pos.line];
+    let Some(wrap) = reflow::hard_wrap(line, view.config.wrap_column, view.config.tab_width, view.filetype.as_deref())
+    else {
+        return Ok(None);
+    };
+    let split = grapheme_len(&line[..wrap.split]);
+    let target = if pos.column >= split {
+        TextPosition { line: pos.line + 1, column: grapheme_len(&wrap.next[..wrap.prefix]) + pos.column - split }
+    } else {
+        pos
+    };
+
+    let Some(mut operation) = replace_lines(view, caret, pos.line, 1, vec![wrap.keep, wrap.next])? else {
+        return Ok(None);
+    };
+    view.jump_to(target, caret)?;
+    operation.cursor_after = caret.get_position();
+    operation.scroll_after = view.scroll_offset;
+    Ok(Some(operation))
+}
+
+// Number the lines from `start` to `end` at the column where `start` sits
+pub fn insert_sequence(
+    view: &mut View,
+    caret: &mut Caret,
+    start: TextPosition,
+    end: TextPosition,
+    sequence: &Sequence,
+) -> Result<Option<EditOperation>, Error> {
+    let column = grapheme_slice(&view.buffer.lines[start.line], 0, start.column).chars().count();
+    let numbered = sequence::insert_column(&view.buffer.lines[start.line..=end.line], column, sequence);
+    view.selection = None;
+    replace_lines(view, caret, start.line, end.line - start.line + 1, numbered)
+}
+
+// Resolve the conflict under the caret (or the next one) and put the caret at its start
+pub fn resolve_conflict(
+    view: &mut View,
+    caret: &mut Caret,
+    resolution: Resolution,
+) -> Result<Option<EditOperation>, Error> {
+    let line_idx = get_current_text_pos(view, caret).line;
+    let found = conflicts::find_conflicts(&view.buffer.lines);
+    let Some(conflict) = conflicts::conflict_for(&found, line_idx).copied() else {
+        return Ok(None);
+    };
+
+    view.jump_to(TextPosition { line: conflict.start, column: 0 }, caret)?;
+    let resolved = conflict.resolve(&view.buffer.lines, resolution);
+    replace_lines(view, caret, conflict.start, conflict.end - conflict.start + 1, resolved)
+}
+
+// Enter on a list item or comment: carry the prefix over to the new line,
+// or clear it when the line holds nothing else. None means a plain newline.
+pub fn continue_line(view: &mut View, caret: &mut Caret) -> Result<Option<EditOperation>, Error> {
+    if view.selection.as_ref().is_some_and(|s| s.is_active()) {
+        return Ok(None);
+    }
+
+    let size = Terminal::get_size()?;
+    let pos = caret.get_position();
+    if pos.y >= size.height - 1 {
+        return Ok(None);
+    }
+
+    let text_pos = get_current_text_pos(view, caret);
+    let Some(line) = view.buffer.lines.get(text_pos.line).cloned() else {
+        return Ok(None);
+    };
+    let Some(cont) = continuation(&line, view.filetype.as_deref()) else {
+        return Ok(None);
+    };
+    let split = grapheme_to_byte_idx(&line, text_pos.column);
+    if split < cont.prefix_len {
+        return Ok(None);
+    }
+
+    if cont.is_empty_line(&line) {
+        let mut operation = replace_lines(view, caret, text_pos.line, 1, vec![cont.indent.clone()])?;
+        caret.move_to(Position {
+            x: Position::MARGIN + grapheme_len(&cont.indent) as u16,
+            y: pos.y,
+        })?;
+        if let Some(op) = operation.as_mut() {
+            op.cursor_after = caret.get_position();
+        }
+        return Ok(operation);
+    }
+
+    let (before, after) = line.split_at(split);
+    let new_lines = vec![
+        before.to_string(),
+        format!("{}{}", cont.next_prefix, after.trim_start()),
+    ];
+    let mut operation = replace_lines(view, caret, text_pos.line, 1, new_lines)?;
+
+    // Move to the new line, right after its prefix
+    let y = if pos.y < size.height - 2 {
+        pos.y + 1
+    } else {
+        view.scroll_offset += 1;
+        pos.y
+    };
+    caret.move_to(Position {
+        x: Position::MARGIN + grapheme_len(&cont.next_prefix) as u16,
+        y,
+    })?;
+    if let Some(op) = operation.as_mut() {
+        op.cursor_after = caret.get_position();
+        op.scroll_after = view.scroll_offset;
+    }
+    Ok(operation)
+}
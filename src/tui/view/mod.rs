@@ -1,6 +1,8 @@
 // view mod module with corrected EditOperation returns
 mod clipboard;
+mod filepicker;
 mod keyboard;
+mod lines;
 mod mouse;
 mod render;
 mod search;
@@ -9,11 +11,25 @@ mod graphemes;
 
 pub use crate::core::buffer::Buffer;
 use crate::core::{
+    bidi,
+    collab::Peer,
+    config::Config,
+    display,
     edit_history::EditOperation,
+    folding::Folds,
     selection::{Selection, TextPosition},
+    conflicts::{self, Resolution},
+    jumps::Jump,
+    line_metrics::{LineMetrics, LineMetricsCache},
+    search_index::SearchIndex,
+    sequence::Sequence,
+    smart_select::{Range, SelectionSteps},
+    toasts::{ToastKind, Toasts},
 };
-use crate::tui::{caret::Caret, terminal::Terminal};
-pub use search::SearchState;
+use crate::tui::{caret::{Caret, Position}, terminal::Terminal};
+pub use render::draw_progress;
+pub use filepicker::{FilePicker, Pick, Purpose};
+pub use search::{ReplaceRequest, Scope, SearchState};
 use std::io::Error;
 
 // Prompt kind describes the intent of the footer prompt.
@@ -22,6 +38,9 @@ pub(crate) enum PromptKind {
     Error,
     Search,
     SearchInfo,
+    Passphrase,
+    Palette,
+    Replace { editing_find: bool, scope: Option<String> }, // Message holds the text to find, input its replacement
 }
 
 // Prompt state shown in the footer when active.
@@ -36,13 +55,34 @@ pub struct View {
     pub selection: Option<Selection>,
     pub is_dragging: bool,
     pub scroll_offset: usize,
+    pub folds: Folds, // Collapsed blocks of the shown tab, each drawn as one row
+    pub long_line_window: (usize, usize), // Long line being edited and the first grapheme column drawn of it
+    pub parked_caret: Option<TextPosition>, // Where the caret is while the wheel has scrolled its line off screen
     pub filename: Option<String>,
     pub filetype: Option<String>,
     pub prompt_since: Option<std::time::Instant>,
     pub show_shortcuts: bool,
     pub needs_redraw: bool,
+    pub covered: bool, // A full screen view like the diff is up, drawing waits until it closes
     pub search_state: Option<SearchState>,
+    pub last_search: Option<String>, // Query F3 repeats
+    pub search_index: SearchIndex,
+    line_metrics: LineMetricsCache,
+    pub read_only: bool,
+    pub following: bool,
+    pub scratch: bool,
+    pub locked: bool,
+    pub encrypted: bool,
+    pub shared: bool, // Tab shared over the network with --host / --join
+    pub peers: Vec<Peer>, // Others in the shared tab, their carets drawn in their colors
+    pub config: Config,
+    pub selection_steps: SelectionSteps,
+    pub vim_mode: Option<&'static str>, // Mode label shown in the footer while vim keys are on
+    pub pending_keys: Option<String>, // Start of a key sequence or count prefix, shown until it completes
     pub(in crate::tui) prompt: Option<Prompt>,
+    pub file_picker: Option<FilePicker>, // Save As / Open file browser drawn over the text
+    pub toasts: Toasts, // Messages stacked above the footer
+    pub progress: Option<(String, Option<f32>)>, // Running job's label and share done, drawn as a footer bar
     #[allow(dead_code)] // clipboard for wayland must be here even tho rust warns its unused - its not!
     clipboard: Option<arboard::Clipboard>,
 }
@@ -52,6 +92,9 @@ impl View {
         Self {
             buffer,
             scroll_offset: 0,
+            folds: Folds::default(),
+            long_line_window: (0, 0),
+            parked_caret: None,
             filename: None,
             filetype: None,
             prompt_since: None,
@@ -59,12 +102,39 @@ impl View {
             selection: None,
             is_dragging: false,
             prompt: None,
+            file_picker: None,
+            toasts: Toasts::default(),
+            progress: None,
             needs_redraw: true,
+            covered: false,
             search_state: None,
+            last_search: None,
+            search_index: SearchIndex::default(),
+            line_metrics: LineMetricsCache::default(),
+            read_only: false,
+            following: false,
+            scratch: false,
+            locked: false,
+            encrypted: false,
+            shared: false,
+            peers: Vec::new(),
+            config: Config::load(),
+            selection_steps: SelectionSteps::default(),
+            vim_mode: None,
+            pending_keys: None,
             clipboard: arboard::Clipboard::new().ok(),
         }
     }
 
+    // Cached grapheme count, width and indentation of a line; empty past the end of the buffer
+    pub fn line_metrics(&self, line_idx: usize) -> LineMetrics {
+        self.buffer
+            .lines
+            .get(line_idx)
+            .map(|line| self.line_metrics.get(line, self.config.tab_width))
+            .unwrap_or_default()
+    }
+
     pub fn set_filename_and_filetype(&mut self, filename: Option<String>, filetype: Option<String>) {
         self.filename = filename;
         self.filetype = filetype;
@@ -82,22 +152,38 @@ impl View {
         self.needs_redraw = true;
     }
 
-    // Append a character to the current prompt input (for in-UI typing).
-    pub fn append_prompt_char(&mut self, ch: char) {
-        if let Some(p) = &mut self.prompt {
-            p.input.push(ch);
+    // Status messages - stacked above the footer so they never hide a prompt or each other
+    pub fn notify(&mut self, kind: ToastKind, message: String) {
+        self.toasts.push(kind, message, std::time::Instant::now());
+        self.needs_redraw = true;
+    }
+
+    // Esc clears every message, errors included
+    pub fn dismiss_toasts(&mut self) {
+        if self.toasts.dismiss() {
             self.needs_redraw = true;
         }
     }
 
-    // Backspace in the prompt input.
-    pub fn backspace_prompt(&mut self) {
+    pub fn expire_toasts(&mut self) {
+        if self.toasts.expire(std::time::Instant::now()) {
+            self.needs_redraw = true;
+        }
+    }
+
+    // Replace the prompt input, e.g. with a completed path.
+    pub fn set_prompt_input(&mut self, input: String) {
         if let Some(p) = &mut self.prompt {
-            p.input.pop();
+            p.input = input;
             self.needs_redraw = true;
         }
     }
 
+    // What the footer prompt says, empty when there is none
+    pub fn prompt_message(&self) -> String {
+        self.prompt.as_ref().map(|p| p.message.clone()).unwrap_or_default()
+    }
+
     // Clear any active prompt.
     pub fn clear_prompt(&mut self) {
         self.prompt = None;
@@ -105,12 +191,6 @@ impl View {
         self.needs_redraw = true;
     }
 
-    //Get a reference to the prompt if active.
-    pub fn get_prompt(&self) -> Option<(&PromptKind, &str, &str)> {
-        self.prompt
-            .as_ref()
-            .map(|p| (&p.kind, p.message.as_str(), p.input.as_str()))
-    }
 
     pub fn toggle_ctrl_shortcuts(&mut self) {
         self.show_shortcuts = !self.show_shortcuts;
@@ -119,18 +199,29 @@ impl View {
 
     // Rendering
     pub fn render(&self, caret: &Caret) -> Result<(), Error> {
+        if self.covered {
+            return Ok(());
+        }
         render::render_view(self, caret, false)
     }
 
     // render only if needed and clear the flag
     pub fn render_if_needed(&mut self, caret: &Caret, is_dirty: bool) -> Result<(), Error> {
-        if self.needs_redraw {
+        if self.needs_redraw && !self.covered {
             render::render_view(self, caret, is_dirty)?;
             self.needs_redraw = false;
         }
         Ok(())
     }
 
+    // Caret moves within the screen skip the full redraw, so refresh the breadcrumbs alone
+    pub fn render_breadcrumbs(&self, caret: &Caret) -> Result<(), Error> {
+        if self.covered {
+            return Ok(());
+        }
+        render::draw_breadcrumbs(self, caret)
+    }
+
     // Clipboard operations - Return Option<EditOperation>
     pub fn copy_selection(&self) -> Result<(), Error> {
         clipboard::copy_selection(self)
@@ -144,6 +235,15 @@ impl View {
         Ok(result)
     }
 
+    // Remove the selected text, as typing or pasting over it does
+    pub fn delete_selection(&mut self, caret: &mut Caret) -> Result<Option<EditOperation>, Error> {
+        let result = clipboard::delete_selection(self, caret)?;
+        if result.is_some() {
+            self.needs_redraw = true;
+        }
+        Ok(result)
+    }
+
     pub fn paste_from_clipboard(
         &mut self,
         caret: &mut Caret,
@@ -156,12 +256,44 @@ impl View {
     }
 
     // Search in text
-    pub fn search(&mut self, caret: &mut Caret) -> Result<(), Error> {
-        search::search(self, caret)?;
+    pub fn search(&mut self, caret: &mut Caret, query: &str, scope: Option<search::Scope>) -> Result<(), Error> {
+        search::search(self, caret, query, scope)?;
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    pub fn repeat_search(&mut self, caret: &mut Caret) -> Result<(), Error> {
+        search::repeat_search(self, caret)?;
         self.needs_redraw = true;
         Ok(())
     }
 
+    pub fn count_occurrences(&mut self, caret: &mut Caret, query: &str, scope: Option<search::Scope>) -> Result<(), Error> {
+        search::count_occurrences(self, caret, query, scope)
+    }
+
+    // What Alt+L limits a search to, and how the prompt describes it
+    pub fn search_region(&self, caret: &Caret) -> (search::Scope, String) {
+        search::scope_region(self, caret)
+    }
+
+    // Ctrl+H: highlight what to find, with its replacement to put in
+    pub fn start_replace(&mut self, caret: &mut Caret, request: &search::ReplaceRequest) -> Result<bool, Error> {
+        search::start_replace(self, caret, request)
+    }
+
+    pub fn replace_current(&mut self, caret: &mut Caret) -> Result<Option<EditOperation>, Error> {
+        search::replace_current(self, caret)
+    }
+
+    pub fn skip_match(&mut self, caret: &mut Caret) -> Result<(), Error> {
+        search::skip_match(self, caret)
+    }
+
+    pub fn replace_all(&mut self, caret: &mut Caret) -> Result<Vec<EditOperation>, Error> {
+        search::replace_all(self, caret)
+    }
+
     pub fn set_search_state(&mut self, state: Option<SearchState>) {
         self.search_state = state;
     }
@@ -215,6 +347,18 @@ impl View {
         Ok(())
     }
 
+    pub fn handle_wheel(&mut self, lines: isize, caret: &mut Caret) -> Result<(), Error> {
+        mouse::handle_wheel(self, lines, caret)
+    }
+
+    // Scroll back to a caret the wheel left off screen, before anything acts on it
+    pub fn unpark_caret(&mut self, caret: &mut Caret) -> Result<(), Error> {
+        if let Some(pos) = self.parked_caret.take() {
+            mouse::reveal(self, pos, caret)?;
+        }
+        Ok(())
+    }
+
     pub fn handle_triple_click(&mut self, x: u16, y: u16, caret: &mut Caret) -> Result<(), Error> {
         mouse::handle_triple_click(self, x, y, caret)?;
         self.needs_redraw = true;
@@ -242,6 +386,15 @@ impl View {
         Ok(result)
     }
 
+    // Continue list / comment prefixes on Enter, None if a plain newline is needed
+    pub fn continue_line(&mut self, caret: &mut Caret) -> Result<Option<EditOperation>, Error> {
+        let result = lines::continue_line(self, caret)?;
+        if result.is_some() {
+            self.needs_redraw = true;
+        }
+        Ok(result)
+    }
+
     pub fn delete_char(&mut self, caret: &mut Caret) -> Result<Option<EditOperation>, Error> {
         let result = keyboard::delete_char(self, caret)?;
         if result.is_some() {
@@ -258,6 +411,229 @@ impl View {
         Ok(result)
     }
 
+    // Markdown checklist and table commands
+    pub fn toggle_checkbox(&mut self, caret: &mut Caret) -> Result<Option<EditOperation>, Error> {
+        let result = lines::toggle_checkbox(self, caret)?;
+        if result.is_some() {
+            self.needs_redraw = true;
+        }
+        Ok(result)
+    }
+
+    pub fn move_done_items(&mut self, caret: &mut Caret) -> Result<Option<EditOperation>, Error> {
+        let result = lines::move_done_items(self, caret)?;
+        if result.is_some() {
+            self.needs_redraw = true;
+        }
+        Ok(result)
+    }
+
+    // Swap the whole buffer for `new_lines` as one undoable edit, like restoring an earlier version
+    pub fn replace_buffer(&mut self, caret: &mut Caret, new_lines: Vec<String>) -> Result<Option<EditOperation>, Error> {
+        let mut result = lines::replace_lines(self, caret, 0, self.buffer.lines.len(), new_lines)?;
+        let last = self.buffer.lines.len().saturating_sub(1);
+        if let Some(op) = result.as_mut().filter(|_| helpers::get_current_text_pos(self, caret).line > last) {
+            self.jump_to(TextPosition { line: last, column: 0 }, caret)?;
+            op.cursor_after = caret.get_position();
+            op.scroll_after = self.scroll_offset;
+        }
+        self.needs_redraw = true;
+        Ok(result)
+    }
+
+    pub fn format_table(&mut self, caret: &mut Caret) -> Result<Option<EditOperation>, Error> {
+        let result = lines::format_table(self, caret)?;
+        if result.is_some() {
+            self.needs_redraw = true;
+        }
+        Ok(result)
+    }
+
+    // The selection, or the whole current line when nothing is selected
+    pub fn selection_or_line(&self, caret: &Caret) -> (TextPosition, TextPosition, String) {
+        let (start, end) = match self.selection.as_ref().filter(|s| s.is_active()) {
+            Some(selection) => selection.get_range(),
+            None => {
+                let line = helpers::get_current_text_pos(self, caret).line;
+                let len = self.line_metrics(line).graphemes;
+                (TextPosition { line, column: 0 }, TextPosition { line, column: len })
+            }
+        };
+        (start, end, clipboard::extract_text(self, start, end))
+    }
+
+    pub fn align_range(&self, caret: &Caret) -> (usize, usize) {
+        lines::align_range(self, caret)
+    }
+
+    pub fn align_lines(
+        &mut self,
+        caret: &mut Caret,
+        range: (usize, usize),
+        delimiter: &str,
+    ) -> Result<Option<EditOperation>, Error> {
+        let result = lines::align_lines(self, caret, range, delimiter)?;
+        self.needs_redraw = true;
+        Ok(result)
+    }
+
+    pub fn reflow_lines(&mut self, caret: &mut Caret, range: (usize, usize)) -> Result<Option<EditOperation>, Error> {
+        let result = lines::reflow_lines(self, caret, range)?;
+        self.needs_redraw = true;
+        Ok(result)
+    }
+
+    pub fn hard_wrap(&mut self, caret: &mut Caret) -> Result<Option<EditOperation>, Error> {
+        let result = lines::hard_wrap(self, caret)?;
+        self.needs_redraw = true;
+        Ok(result)
+    }
+
+    pub fn insert_sequence(
+        &mut self,
+        caret: &mut Caret,
+        start: TextPosition,
+        end: TextPosition,
+        sequence: &Sequence,
+    ) -> Result<Option<EditOperation>, Error> {
+        let result = lines::insert_sequence(self, caret, start, end, sequence)?;
+        self.needs_redraw = true;
+        Ok(result)
+    }
+
+    pub fn resolve_conflict(
+        &mut self,
+        caret: &mut Caret,
+        resolution: Resolution,
+    ) -> Result<Option<EditOperation>, Error> {
+        let result = lines::resolve_conflict(self, caret, resolution)?;
+        self.needs_redraw = true;
+        Ok(result)
+    }
+
+    // Move the caret to the next merge conflict; false when there are none
+    pub fn next_conflict(&mut self, caret: &mut Caret) -> Result<bool, Error> {
+        let line = helpers::get_current_text_pos(self, caret).line;
+        let found = conflicts::find_conflicts(&self.buffer.lines);
+        match conflicts::next_conflict(&found, line) {
+            Some(conflict) => {
+                self.jump_to(TextPosition { line: conflict.start, column: 0 }, caret)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    pub fn selected_text(&self) -> Option<String> {
+        let selection = self.selection.as_ref().filter(|s| s.is_active())?;
+        let (start, end) = selection.get_range();
+        Some(clipboard::extract_text(self, start, end))
+    }
+
+    // Selected text, or the whole file when nothing is selected
+    pub fn selection_or_file(&self) -> String {
+        match self.selection.as_ref().filter(|s| s.is_active()) {
+            Some(selection) => {
+                let (start, end) = selection.get_range();
+                clipboard::extract_text(self, start, end)
+            }
+            None => self.buffer.content(),
+        }
+    }
+
+    pub fn replace_text(
+        &mut self,
+        caret: &mut Caret,
+        start: TextPosition,
+        end: TextPosition,
+        text: &str,
+    ) -> Result<Option<EditOperation>, Error> {
+        let result = lines::replace_text(self, caret, start, end, text)?;
+        self.needs_redraw = true;
+        Ok(result)
+    }
+
+    // Removed text and its edit; None when there is nothing left to kill
+    pub fn kill_line(&mut self, caret: &mut Caret) -> Result<Option<(String, Option<EditOperation>)>, Error> {
+        let result = lines::kill_line(self, caret)?;
+        self.needs_redraw = true;
+        Ok(result)
+    }
+
+    // Insert `text` at the caret, replacing nothing
+    pub fn insert_at_caret(&mut self, caret: &mut Caret, text: &str) -> Result<Option<EditOperation>, Error> {
+        let pos = helpers::get_current_text_pos(self, caret);
+        self.replace_text(caret, pos, pos, text)
+    }
+
+    // Put the caret on a char-column position without selecting
+    pub fn move_to_chars(&mut self, pos: TextPosition, caret: &mut Caret) -> Result<(), Error> {
+        self.select_chars((pos, pos), caret)?;
+        self.selection = None;
+        Ok(())
+    }
+
+    // Scroll `pos` into view and put the caret on it, opening a fold hiding it
+    pub fn jump_to(&mut self, pos: TextPosition, caret: &mut Caret) -> Result<(), Error> {
+        let size = Terminal::get_size()?;
+        let visible_rows = size.height.saturating_sub(Position::HEADER + 1) as usize;
+        self.folds.reveal(pos.line);
+        if pos.line < self.scroll_offset || self.folds.row_of_line(self.scroll_offset, pos.line) >= visible_rows {
+            self.scroll_offset = self.folds.visible_line(pos.line.saturating_sub(visible_rows / 2));
+        }
+
+        self.selection = None;
+        let (x, y) = helpers::text_to_screen_pos(self, pos);
+        caret.move_to(Position { x, y })?;
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    // Typewriter scrolling: scroll so the caret's line sits in the middle row
+    pub fn center_caret(&mut self, caret: &mut Caret) -> Result<(), Error> {
+        let size = Terminal::get_size()?;
+        let visible_rows = size.height.saturating_sub(Position::HEADER + 1) as usize;
+        let pos = helpers::get_current_text_pos(self, caret);
+        let offset = self.folds.visible_line(pos.line.saturating_sub(visible_rows / 2));
+        if offset != self.scroll_offset {
+            self.scroll_offset = offset;
+            let y = Position::HEADER + self.folds.row_of_line(offset, pos.line) as u16;
+            caret.move_to(Position { x: caret.get_position().x, y })?;
+            self.needs_redraw = true;
+        }
+        Ok(())
+    }
+
+    // Fold or unfold the block under the caret, which stays on the row the block folds into.
+    // False when there is no block there
+    pub fn toggle_fold(&mut self, caret: &mut Caret) -> Result<bool, Error> {
+        let pos = helpers::get_current_text_pos(self, caret);
+        if !self.folds.toggle(&self.buffer.lines, pos.line) {
+            return Ok(false);
+        }
+        // Folding a block whose first line is above the screen scrolls up to it
+        self.scroll_offset = self.folds.visible_line(self.scroll_offset);
+        let pos = match self.folds.list().iter().find(|f| (f.start..=f.end).contains(&pos.line)) {
+            Some(fold) => TextPosition { line: fold.start, column: 0 },
+            None => pos,
+        };
+        self.jump_to(pos, caret)?;
+        Ok(true)
+    }
+
+    // Open every fold, keeping the caret and selection where they are in the text
+    pub fn unfold_all(&mut self, caret: &mut Caret) -> Result<(), Error> {
+        if self.folds.is_empty() {
+            return Ok(());
+        }
+        let pos = helpers::get_current_text_pos(self, caret);
+        let selection = self.selection.take();
+        self.folds.unfold_all();
+        self.jump_to(pos, caret)?;
+        self.selection = selection;
+        Ok(())
+    }
+
     // Movement operations - only mark dirty if scroll changes or selection changes
     pub fn move_up(&mut self, caret: &mut Caret) -> Result<(), Error> {
         let old_offset = self.scroll_offset;
@@ -350,6 +726,45 @@ impl View {
         Ok(())
     }
 
+    pub fn expand_selection(&mut self, caret: &mut Caret) -> Result<(), Error> {
+        selection::expand_selection(self, caret)?;
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    pub fn jump(&mut self, jump: Jump, select: bool, caret: &mut Caret) -> Result<(), Error> {
+        selection::jump(self, jump, select, caret)?;
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    pub fn shrink_selection(&mut self, caret: &mut Caret) -> Result<(), Error> {
+        selection::shrink_selection(self, caret)?;
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    // Caret in char columns, the unit the vim layer works in
+    pub fn caret_chars(&self, caret: &Caret) -> TextPosition {
+        let pos = helpers::get_current_text_pos(self, caret);
+        let line = self.buffer.lines.get(pos.line).map_or("", |l| l.as_str());
+        TextPosition { line: pos.line, column: graphemes::grapheme_slice(line, 0, pos.column).chars().count() }
+    }
+
+    // The caret's line and its byte offset there, for scanning the text under the caret
+    pub fn caret_byte(&self, caret: &Caret) -> (&str, usize) {
+        let pos = helpers::get_current_text_pos(self, caret);
+        let line = self.buffer.lines.get(pos.line).map_or("", |l| l.as_str());
+        (line, graphemes::grapheme_slice(line, 0, pos.column).len())
+    }
+
+    // Select a char-column range with the caret at its end
+    pub fn select_chars(&mut self, range: Range, caret: &mut Caret) -> Result<(), Error> {
+        selection::select_char_range(self, range, caret)?;
+        self.needs_redraw = true;
+        Ok(())
+    }
+
     pub fn handle_resize(&mut self, caret: &mut Caret, is_dirty: bool) -> Result<(), Error> {
         caret.clamp_to_bounds()?;
         self.needs_redraw = true;
@@ -360,22 +775,21 @@ impl View {
 
     // Helper for clamping cursor to line length
     pub(in crate::tui::view) fn clamp_cursor_to_line(
-        &self,
+        &mut self,
         caret: &mut Caret,
     ) -> Result<(), Error> {
         use crate::tui::caret::Position;
 
         let pos = caret.get_position();
-        let buffer_line_idx =
-            (pos.y.saturating_sub(Position::HEADER)) as usize + self.scroll_offset;
+        let text_pos = helpers::get_current_text_pos(self, caret);
 
-        if let Some(line) = self.buffer.lines.get(buffer_line_idx) {
-            let line_end = Position::MARGIN + line.len() as u16;
+        if text_pos.line < self.buffer.lines.len() && !self.config.virtual_space {
+            let line_len = self.line_metrics(text_pos.line).graphemes;
             let size = Terminal::get_size()?;
-            let max_x = line_end.min(size.width - 1);
 
-            if pos.x > max_x {
-                caret.move_to(Position { x: max_x, y: pos.y })?;
+            if text_pos.column > line_len {
+                let (x, _) = helpers::text_to_screen_pos(self, TextPosition { line: text_pos.line, column: line_len });
+                caret.move_to(Position { x: x.min(size.width - 1), y: pos.y })?;
             } else {
                 caret.move_to(pos)?;
             }
@@ -392,6 +806,9 @@ impl Default for View {
         Self {
             buffer: Buffer::default(),
             scroll_offset: 0,
+            folds: Folds::default(),
+            long_line_window: (0, 0),
+            parked_caret: None,
             filename: None,
             filetype: None,
             prompt_since: None,
@@ -399,8 +816,26 @@ impl Default for View {
             selection: None,
             is_dragging: false,
             prompt: None,
+            file_picker: None,
+            toasts: Toasts::default(),
+            progress: None,
             needs_redraw: true,
+            covered: false,
             search_state: None,
+            last_search: None,
+            search_index: SearchIndex::default(),
+            line_metrics: LineMetricsCache::default(),
+            read_only: false,
+            following: false,
+            scratch: false,
+            locked: false,
+            encrypted: false,
+            shared: false,
+            peers: Vec::new(),
+            config: Config::load(),
+            selection_steps: SelectionSteps::default(),
+            vim_mode: None,
+            pending_keys: None,
             clipboard: arboard::Clipboard::new().ok(),
         }
     }
@@ -410,6 +845,7 @@ impl Default for View {
 pub mod helpers {
     use super::*;
     use crate::tui::caret::Position;
+    use unicode_segmentation::UnicodeSegmentation;
 
     pub fn screen_to_text_pos(
         view: &View,
@@ -422,53 +858,93 @@ pub mod helpers {
         let y = screen_y.min(size.height.saturating_sub(2));
 
         // Adjust for margin
-        let x = if screen_x >= Position::MARGIN {
-            screen_x - Position::MARGIN
-        } else {
-            0
-        };
+        let x = screen_x.saturating_sub(Position::MARGIN);
 
-        // Convert screen Y to buffer line index (accounting for header and scroll)
+        // Convert screen Y to buffer line index (accounting for header, scroll and folds)
         let line_idx = if y >= Position::HEADER {
-            (y - Position::HEADER) as usize + view.scroll_offset
+            view.folds.line_at_row(view.scroll_offset, (y - Position::HEADER) as usize)
         } else {
             0
         };
 
-        // Clamp to actual line length
-        let max_col = if let Some(line) = view.buffer.lines.get(line_idx) {
-            line.len()
-        } else {
-            0
-        };
+        // Map the clicked cell back through tab expansion and right-to-left reordering
+        let column = view.buffer.lines.get(line_idx).map_or(0, |line| {
+            let offset = column_offset(view, line_idx);
+            let line = visible_part(view, line_idx, line, size.width as usize);
+            let shown = display::expand(line, view.config.tab_width);
+            // With virtual space, clicks past the end keep their column
+            if view.config.virtual_space && x as usize > shown.width() {
+                return offset + graphemes::grapheme_len(line) + x as usize - shown.width();
+            }
+            let chars = shown.to_logical(bidi::column_at_cell(&shown.text, x as usize));
+            offset + graphemes::grapheme_len(&line.chars().take(chars).collect::<String>())
+        });
 
-        Ok(TextPosition {
-            line: line_idx,
-            column: (x as usize).min(max_col),
-        })
+        Ok(TextPosition { line: line_idx, column })
     }
 
-    pub fn text_to_screen_pos(view: &View, pos: TextPosition) -> (u16, u16) {
-        // Convert buffer line index to screen Y (accounting for header and scroll)
-        let screen_y = if pos.line >= view.scroll_offset {
-            Position::HEADER + (pos.line - view.scroll_offset) as u16
-        } else {
-            Position::HEADER
-        };
+    // On a long line the drawn window slides so the column lands inside it
+    pub fn text_to_screen_pos(view: &mut View, pos: TextPosition) -> (u16, u16) {
+        // Convert buffer line index to screen Y (accounting for header, scroll and folds)
+        let screen_y = Position::HEADER + view.folds.row_of_line(view.scroll_offset, pos.line) as u16;
 
-        let screen_x = (pos.column as u16) + Position::MARGIN;
+        if view.buffer.lines.get(pos.line).is_some_and(|line| display::is_long(line)) {
+            let room = long_line_room();
+            let start = column_offset(view, pos.line);
+            if pos.column < start || pos.column >= start + room {
+                view.long_line_window = (pos.line, pos.column.saturating_sub(room / 2));
+                view.needs_redraw = true;
+            } else {
+                view.long_line_window = (pos.line, start);
+            }
+        }
+
+        let screen_x = (pos.column - column_offset(view, pos.line)) as u16 + Position::MARGIN;
         (screen_x, screen_y)
     }
 
     pub fn get_current_text_pos(view: &View, caret: &Caret) -> TextPosition {
+        if let Some(pos) = view.parked_caret {
+            return pos;
+        }
         let pos = caret.get_position();
-        let buffer_line_idx =
-            (pos.y.saturating_sub(Position::HEADER)) as usize + view.scroll_offset;
+        let buffer_line_idx = view.folds.line_at_row(view.scroll_offset, pos.y.saturating_sub(Position::HEADER) as usize);
         let char_pos = (pos.x as usize).saturating_sub(Position::MARGIN as usize);
 
         TextPosition {
             line: buffer_line_idx,
-            column: char_pos,
+            column: char_pos + column_offset(view, buffer_line_idx),
+        }
+    }
+
+    // Grapheme columns scrolled out to the left of a line; only the long line being edited has any
+    pub fn column_offset(view: &View, line_idx: usize) -> usize {
+        let (line, start) = view.long_line_window;
+        let is_long = view.buffer.lines.get(line_idx).is_some_and(|line| display::is_long(line));
+        if line == line_idx && is_long { start } else { 0 }
+    }
+
+    // What gets drawn of a line: long lines are cut to `width` graphemes from their window,
+    // so nothing walks or highlights the whole line on every frame
+    pub fn visible_part<'a>(view: &View, line_idx: usize, line: &'a str, width: usize) -> &'a str {
+        if !display::is_long(line) {
+            return line;
         }
+        // Minified files are mostly ASCII, where every byte is a grapheme of its own
+        let skip = column_offset(view, line_idx);
+        if line.is_ascii() {
+            let start = skip.min(line.len());
+            return &line[start..(start + width).min(line.len())];
+        }
+        let mut starts = line.grapheme_indices(true).map(|(i, _)| i).skip(skip);
+        let start = starts.next().unwrap_or(line.len());
+        let end = starts.nth(width.saturating_sub(1)).unwrap_or(line.len());
+        &line[start..end]
+    }
+
+    // Text columns a long line's window shows, the last cell being kept for its marker
+    pub fn long_line_room() -> usize {
+        let width = Terminal::get_size().map_or(80, |size| size.width);
+        (width.saturating_sub(Position::MARGIN + 1) as usize).max(1)
     }
 }
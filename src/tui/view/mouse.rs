@@ -3,7 +3,10 @@ use super::{
     View,
     helpers::{screen_to_text_pos, text_to_screen_pos},
 };
-use crate::tui::caret::{Caret, Position};
+use crate::tui::{
+    caret::{Caret, Position},
+    terminal::Terminal,
+};
 use crate::core::selection::{Selection, TextPosition};
 use std::io::Error;
 use super::graphemes::*;
@@ -140,3 +143,55 @@ fn find_word_boundaries(line: &str, col: usize) -> (usize, usize) {
     
     (start, end)
 }
+
+// Wheel: move the view by `lines` (negative is up). With wheel_moves_caret or typewriter scrolling
+// the caret keeps its row and so travels with the text; otherwise it keeps its place in the text,
+// parked while its line is off screen
+pub fn handle_wheel(view: &mut View, lines: isize, caret: &mut Caret) -> Result<(), Error> {
+    let last = view.buffer.lines.len().saturating_sub(1);
+    let top = match lines {
+        0.. => view.folds.line_at_row(view.scroll_offset, lines as usize),
+        _ => (0..lines.unsigned_abs()).fold(view.scroll_offset, |line, _| view.folds.prev_visible(line)),
+    };
+    let top = view.folds.visible_line(top.min(last));
+    if top == view.scroll_offset {
+        return Ok(());
+    }
+    let visible_rows = Terminal::get_size()?.height.saturating_sub(Position::HEADER + 1).max(1) as usize;
+    let pos = super::helpers::get_current_text_pos(view, caret);
+    let selecting = view.selection.as_ref().is_some_and(|s| s.is_active());
+    let moves_caret = (view.config.wheel_moves_caret || view.config.typewriter_scrolling) && !selecting;
+    let row = view.folds.row_of_line(view.scroll_offset, pos.line).min(visible_rows - 1);
+    view.scroll_offset = top;
+    view.needs_redraw = true;
+
+    if moves_caret {
+        view.parked_caret = None;
+        let line = view.folds.visible_line(view.folds.line_at_row(top, row).min(last));
+        let length = grapheme_len(&view.buffer.lines[line]);
+        let column = if view.config.virtual_space { pos.column } else { pos.column.min(length) };
+        let (x, y) = text_to_screen_pos(view, TextPosition { line, column });
+        caret.move_to(Position { x, y })?;
+    } else if pos.line >= top && view.folds.row_of_line(top, pos.line) < visible_rows {
+        view.parked_caret = None;
+        let (x, y) = text_to_screen_pos(view, pos);
+        caret.move_to(Position { x, y })?;
+    } else {
+        view.parked_caret = Some(pos);
+    }
+    view.render(caret)
+}
+
+// Scroll just far enough to show `pos` and put the caret back on it, keeping any selection
+pub fn reveal(view: &mut View, pos: TextPosition, caret: &mut Caret) -> Result<(), Error> {
+    let visible_rows = Terminal::get_size()?.height.saturating_sub(Position::HEADER + 1).max(1) as usize;
+    if pos.line < view.scroll_offset {
+        view.scroll_offset = pos.line;
+    } else if view.folds.row_of_line(view.scroll_offset, pos.line) >= visible_rows {
+        view.scroll_offset = view.folds.top_above(pos.line, visible_rows);
+    }
+    let (x, y) = text_to_screen_pos(view, pos);
+    caret.move_to(Position { x, y })?;
+    view.needs_redraw = true;
+    Ok(())
+}
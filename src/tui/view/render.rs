@@ -1,34 +1,56 @@
 // render module responsible for all the render logic
-use super::View;
+use super::{View, helpers::{self, get_current_text_pos}};
 use super::graphemes::*;
 use unicode_segmentation::UnicodeSegmentation; 
-use crate::core::selection::TextPosition;
+use crate::core::{
+    bidi,
+    colors,
+    display,
+    conflicts::{self, Region},
+    selection::TextPosition,
+    symbols,
+    syntax::{retype_spans, BracketColorizer, SyntaxHighlighter, Token, TokenType},
+    toasts::ToastKind,
+};
 use crate::tui::{
     caret::{Caret, Position},
+    screen::{self, Screen},
     terminal::Terminal,
-    syntax::{SyntaxHighlighter},
 };
 use crossterm::{
-    cursor::MoveTo,
+    cursor::{Hide, Show},
     queue,
-    style::{Attribute, Color, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor},
+    style::{Attribute, Color},
 };
-use std::io::{Error, stdout};
+use std::collections::BTreeMap;
+use std::io::{stdout, Error};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// The terminal cursor is hidden while the wheel has scrolled the caret off screen
+static CURSOR_HIDDEN: AtomicBool = AtomicBool::new(false);
 
 pub fn render_view(view: &View, caret: &Caret, is_dirty: bool) -> Result<(), Error> {
-    let current_pos = caret.get_position();
+    screen::draw(|screen| paint_view(screen, view, caret, is_dirty))?;
+    let hide = view.parked_caret.is_some();
+    if CURSOR_HIDDEN.swap(hide, Ordering::Relaxed) != hide {
+        if hide {
+            queue!(stdout(), Hide)?;
+        } else {
+            queue!(stdout(), Show)?;
+        }
+    }
+    Ok(())
+}
+
+fn paint_view(screen: &mut Screen, view: &View, caret: &Caret, is_dirty: bool) -> Result<(), Error> {
     let size = Terminal::get_size()?;
 
-    draw_header()?;
+    draw_header(screen, size.width);
+    paint_breadcrumbs(screen, view, caret)?;
 
     let visible_rows = (size.height.saturating_sub(Position::HEADER + 1)) as usize;
 
-    let last_non_empty_line = view
-        .buffer
-        .lines
-        .iter()
-        .rposition(|line| !line.is_empty())
-        .unwrap_or(0);
+    let last_text_line = view.buffer.logical_len() - 1;
 
     let selection_range = view
         .selection
@@ -37,141 +59,393 @@ pub fn render_view(view: &View, caret: &Caret, is_dirty: bool) -> Result<(), Err
         .map(|s| s.get_range());
 
     // Create syntax highlighter based on current file type
-    let highlighter = SyntaxHighlighter::new(view.filetype.clone());
+    let highlighter = SyntaxHighlighter::new(view.filetype.clone()).with_todo_keywords(&view.config.todo_keywords);
+    let mut brackets = view.config.rainbow_brackets.then(|| {
+        let above = view.scroll_offset.min(view.buffer.lines.len());
+        BracketColorizer::scan(&highlighter, &view.buffer.lines[..above])
+    });
+
+    let show_swatches = colors::has_color_literals(view.filetype.as_deref());
+    let conflict_list = conflicts::find_conflicts(&view.buffer.lines);
 
     for row in 0..visible_rows {
-        let buffer_line_idx = row + view.scroll_offset;
+        let buffer_line_idx = view.folds.line_at_row(view.scroll_offset, row);
         let terminal_row = row as u16 + Position::HEADER;
 
-        queue!(stdout(), MoveTo(0, terminal_row))?;
-        Terminal::clear_rest_of_line()?;
+        screen.move_to(0, terminal_row);
+        screen.clear_rest_of_line();
 
-        if buffer_line_idx <= last_non_empty_line {
-            draw_margin_line(terminal_row, buffer_line_idx)?;
+        let conflict = conflict_list.iter().find_map(|c| c.region(buffer_line_idx));
+        if buffer_line_idx <= last_text_line {
+            draw_margin_line(screen, terminal_row, buffer_line_idx, conflict);
         }
 
-        if let Some(line) = view.buffer.lines.get(buffer_line_idx) {
-            let max_width = (size.width.saturating_sub(Position::MARGIN)) as usize;
+        if let Some(full_line) = view.buffer.lines.get(buffer_line_idx) {
+            // Long lines are drawn from their window only, the last cell marking there is more
+            let is_long = display::is_long(full_line);
+            let max_width = (size.width.saturating_sub(Position::MARGIN + is_long as u16)) as usize;
+            let skipped = helpers::column_offset(view, buffer_line_idx);
+            let line = helpers::visible_part(view, buffer_line_idx, full_line, max_width);
+            // Tabs and control characters are drawn expanded, so selection columns move with them.
+            // Selections keep grapheme columns, in the expanded text from here on
+            let shown = display::expand(line, view.config.tab_width);
+            let to_display = |p: TextPosition| {
+                if p.line == buffer_line_idx {
+                    TextPosition { line: p.line, column: shown.grapheme_to_display(line, p.column.saturating_sub(skipped)) }
+                } else {
+                    p
+                }
+            };
+            let line_selection = selection_range.map(|(start, end)| (to_display(start), to_display(end)));
             
-            // Truncate by visual width, not grapheme count
+            // Truncate by visual width, not grapheme count; most lines fit and skip the walk
             let mut truncated = String::new();
             let mut current_width = 0;
             
-            for grapheme in line.graphemes(true) {
-                let g_width = visual_width(grapheme);
-                if current_width + g_width > max_width {
-                    break;
+            let fits = (!is_long).then(|| view.line_metrics(buffer_line_idx).width).filter(|width| *width <= max_width);
+            if let Some(width) = fits {
+                truncated.push_str(&shown.text);
+                current_width = width;
+            } else {
+                for grapheme in shown.text.graphemes(true) {
+                    let g_width = visual_width(grapheme, view.config.tab_width);
+                    if current_width + g_width > max_width {
+                        break;
+                    }
+                    truncated.push_str(grapheme);
+                    current_width += g_width;
+                }
+            }
+
+            let mut tokens = highlighter.highlight_line(&truncated);
+            if conflict == Some(Region::Marker) {
+                tokens = vec![Token { text: truncated.clone(), token_type: TokenType::ConflictMarker }];
+            }
+            if let Some(brackets) = brackets.as_mut() {
+                // Color the visible part, but keep nesting from the whole line
+                if truncated.len() == shown.text.len() && !is_long {
+                    tokens = brackets.colorize(tokens);
+                } else {
+                    // A long line's brackets are left out of the nesting of the lines below
+                    tokens = brackets.clone().colorize(tokens);
+                    if !is_long {
+                        brackets.colorize(highlighter.highlight_line(line));
+                    }
+                }
+            }
+
+            // Matches of an open search stand out from the text around them
+            let matches = super::search::line_matches(view, buffer_line_idx);
+            if !matches.is_empty() {
+                let to_byte = |column: usize| {
+                    let chars = grapheme_slice(line, 0, column).chars().count();
+                    let display = shown.to_display(chars);
+                    truncated.char_indices().nth(display).map_or(truncated.len(), |(i, _)| i)
+                };
+                let mut spans: Vec<(usize, usize)> = Vec::new();
+                for (start, end) in matches {
+                    let (start, end) = (to_byte(start.saturating_sub(skipped)), to_byte(end.saturating_sub(skipped)));
+                    // Overlapping matches are drawn as one
+                    match spans.last_mut() {
+                        Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                        _ if start < end => spans.push((start, end)),
+                        _ => {}
+                    }
                 }
-                truncated.push_str(grapheme);
-                current_width += g_width;
+                tokens = retype_spans(tokens, &spans, TokenType::SearchMatch);
+            }
+
+            if bidi::has_rtl(&truncated) {
+                render_bidi_line(screen, &truncated, buffer_line_idx, line_selection, &tokens);
+            } else {
+                render_line_with_selection_and_syntax(
+                    screen,
+                    buffer_line_idx, 
+                    line_selection, 
+                    &tokens
+                );
             }
 
-            render_line_with_selection_and_syntax(
-                &truncated, 
-                buffer_line_idx, 
-                selection_range, 
-                &highlighter
-            )?;
+            // Color swatches go after the text so columns stay untouched
+            let fold = view.folds.fold_at(buffer_line_idx);
+            if show_swatches && truncated.len() == shown.text.len() && !is_long && fold.is_none() {
+                draw_color_swatches(screen, line, max_width.saturating_sub(current_width));
+            }
+            // Folded blocks end with a placeholder for the hidden lines, which still count for bracket nesting
+            if let Some(fold) = fold {
+                let label = format!(" … {} lines ", fold.hidden_lines());
+                if current_width + 1 + label.chars().count() <= max_width {
+                    screen.print(" ");
+                    screen.bg(Color::DarkGrey);
+                    screen.fg(Color::White);
+                    screen.print(&label);
+                    screen.reset();
+                }
+                if let Some(brackets) = brackets.as_mut() {
+                    for hidden in view.buffer.lines.iter().take(fold.end + 1).skip(fold.start + 1) {
+                        brackets.colorize(highlighter.highlight_line(hidden));
+                    }
+                }
+            }
+            if is_long {
+                screen.move_to(size.width.saturating_sub(1), terminal_row);
+                screen.fg(Color::DarkGrey);
+                screen.attr(Attribute::Reverse);
+                screen.print("»");
+                screen.reset();
+            }
         }
     }
 
-    draw_footer(view, caret, is_dirty)?;
-
-    queue!(stdout(), MoveTo(current_pos.x, current_pos.y))?;
+    draw_peer_carets(screen, view, visible_rows, size.width);
+    if let Some(picker) = &view.file_picker {
+        picker.paint(screen, Position::HEADER, visible_rows, size.width);
+    }
+    draw_toasts(screen, view, size.width, size.height - 1);
+    paint_footer(screen, view, caret, is_dirty)?;
+
+    // The cursor waits after the name typed into the file picker
+    let (x, y) = match (&view.file_picker, &view.prompt) {
+        (Some(_), Some(prompt)) => {
+            let typed = display::str_width(&prompt.message) + display::str_width(&prompt.input);
+            ((1 + typed).min(size.width.saturating_sub(1) as usize) as u16, size.height - 1)
+        }
+        _ => caret_screen_pos(view, caret),
+    };
+    screen.move_to(x, y);
     Ok(())
 }
 
-fn draw_header() -> Result<(), Error> {
-    let size = Terminal::get_size()?;
-    queue!(
-        stdout(),
-        MoveTo(0, 0),
-        SetForegroundColor(Color::Yellow),
-        MoveTo(size.width / 2, 0),
-        Print(" Quick Notepad ".to_string()),
-        ResetColor
-    )?;
-    Terminal::clear_rest_of_line()?;
-    Ok(())
+// Others' carets in a shared tab: the character each is on, in their color
+fn draw_peer_carets(screen: &mut Screen, view: &View, visible_rows: usize, width: u16) {
+    for peer in &view.peers {
+        let line = peer.position.line;
+        let row = view.folds.row_of_line(view.scroll_offset, line);
+        if line < view.scroll_offset || view.folds.is_hidden(line) || row >= visible_rows {
+            continue;
+        }
+        let Some(column) = peer.position.column.checked_sub(helpers::column_offset(view, peer.position.line)) else {
+            continue;
+        };
+        let line = view.buffer.lines.get(peer.position.line).map_or("", String::as_str);
+        let line = helpers::visible_part(view, peer.position.line, line, width as usize);
+        let shown = display::expand(line, view.config.tab_width);
+        let chars = grapheme_slice(line, 0, column).chars().count();
+        let x = Position::MARGIN as usize + bidi::caret_cell(&shown.text, shown.to_display(chars));
+        if x >= width as usize {
+            continue;
+        }
+        let symbol = line.graphemes(true).nth(column).filter(|g| !g.chars().any(char::is_control)).unwrap_or(" ");
+        let (r, g, b) = peer.color();
+        screen.move_to(x as u16, row as u16 + Position::HEADER);
+        screen.bg(Color::Rgb { r, g, b });
+        screen.fg(Color::Black);
+        screen.print(symbol);
+        screen.reset();
+    }
+}
+
+// Terminal cursor position: the caret column mapped to its cell after tab expansion,
+// wide characters and right-to-left reordering
+fn caret_screen_pos(view: &View, caret: &Caret) -> (u16, u16) {
+    let current_pos = caret.get_position();
+    let pos = get_current_text_pos(view, caret);
+    if let Some(line) = view.buffer.lines.get(pos.line) {
+        let width = Terminal::get_size().map_or(u16::MAX, |size| size.width);
+        let offset = helpers::column_offset(view, pos.line);
+        let (line, graphemes) = if display::is_long(line) {
+            let part = helpers::visible_part(view, pos.line, line, width as usize);
+            (part, grapheme_len(part))
+        } else {
+            (line.as_str(), view.line_metrics(pos.line).graphemes)
+        };
+        let caret_column = pos.column - offset;
+        let shown = display::expand(line, view.config.tab_width);
+        let column = shown.to_display(grapheme_slice(line, 0, caret_column).chars().count());
+        let past_end = caret_column.saturating_sub(graphemes);
+        let x = Position::MARGIN as usize + bidi::caret_cell(&shown.text, column) + past_end;
+        return ((x as u16).min(width.saturating_sub(1)), current_pos.y);
+    }
+    (current_pos.x, current_pos.y)
+}
+
+// Draw a two-cell block per color literal, as many as fit in `room` cells
+fn draw_color_swatches(screen: &mut Screen, line: &str, room: usize) {
+    let found = colors::find_colors(line);
+    let fits = room.saturating_sub(1) / 3;
+    if found.is_empty() || fits == 0 {
+        return;
+    }
+
+    screen.print(" ");
+    for color in found.iter().take(fits) {
+        let (r, g, b) = color.rgb;
+        screen.print(" ");
+        screen.bg(Color::Rgb { r, g, b });
+        screen.print("  ");
+        screen.reset();
+    }
+}
+
+fn draw_header(screen: &mut Screen, width: u16) {
+    screen.fg(Color::Yellow);
+    screen.move_to(width / 2, 0);
+    screen.print(" Quick Notepad ");
+    screen.reset();
+    screen.clear_rest_of_line();
+}
+
+// File name followed by the functions, types or headings enclosing the caret
+pub fn draw_breadcrumbs(view: &View, caret: &Caret) -> Result<(), Error> {
+    screen::draw(|screen| paint_breadcrumbs(screen, view, caret))
 }
 
-fn draw_margin_line(row: u16, buffer_line_idx: usize) -> Result<(), Error> {
-    queue!(
-        stdout(),
-        MoveTo(0, row),
-        SetForegroundColor(Color::Yellow),
-        Print(format!("{:>3} ", buffer_line_idx + 1)),
-        ResetColor
-    )?;
+fn paint_breadcrumbs(screen: &mut Screen, view: &View, caret: &Caret) -> Result<(), Error> {
+    let line = get_current_text_pos(view, caret).line;
+    let width = Terminal::get_size()?.width as usize;
+    let symbols = symbols::find_symbols(&view.buffer.lines, view.filetype.as_deref());
+    let scope = symbols::breadcrumbs(&symbols, line);
+    let name = view.filename.as_deref().unwrap_or("untitled");
+    let text = if scope.is_empty() { format!(" {}", name) } else { format!(" {} › {}", name, scope) };
+
+    let mut shown = String::new();
+    let mut used = 0;
+    for grapheme in text.graphemes(true) {
+        used += visual_width(grapheme, view.config.tab_width);
+        if used > width {
+            break;
+        }
+        shown.push_str(grapheme);
+    }
+
+    screen.move_to(0, 1);
+    screen.fg(Color::DarkGrey);
+    screen.print(&shown);
+    screen.reset();
+    screen.clear_rest_of_line();
+    let (x, y) = caret_screen_pos(view, caret);
+    screen.move_to(x, y);
     Ok(())
 }
 
-pub fn draw_footer(view: &View, caret: &Caret, is_dirty: bool) -> Result<(), Error> {
+fn draw_margin_line(screen: &mut Screen, row: u16, buffer_line_idx: usize, conflict: Option<Region>) {
+    // Merge conflict sides get a colored gutter
+    let background = match conflict {
+        Some(Region::Marker) => Color::DarkMagenta,
+        Some(Region::Ours) => Color::DarkGreen,
+        Some(Region::Base) => Color::DarkGrey,
+        Some(Region::Theirs) => Color::DarkCyan,
+        None => Color::Reset,
+    };
+    screen.move_to(0, row);
+    screen.bg(background);
+    screen.fg(if conflict.is_some() { Color::White } else { Color::Yellow });
+    screen.print(&format!("{:>3} ", buffer_line_idx + 1));
+    screen.reset();
+}
+
+fn paint_footer(screen: &mut Screen, view: &View, caret: &Caret, is_dirty: bool) -> Result<(), Error> {
     let size = Terminal::get_size()?;
     let footer_row = size.height - 1;
 
     // Clear the footer line and set background
-    queue!(
-        stdout(),
-        MoveTo(0, footer_row),
-        SetBackgroundColor(Color::Black),
-    )?;
-    Terminal::clear_rest_of_line()?;
-
-    // Move back to start of footer
-    queue!(stdout(), MoveTo(0, footer_row))?;
+    screen.move_to(0, footer_row);
+    screen.bg(Color::Black);
+    screen.clear_rest_of_line();
 
     // If shortcuts are toggled, show them. If a prompt is active, render the prompt/footer.
     if view.show_shortcuts {
-        draw_shortcuts_footer()?;
+        draw_shortcuts_footer(screen, &view.config.keybindings)?;
+    } else if let Some((label, fraction)) = &view.progress {
+        draw_progress_footer(screen, label, *fraction, size.width, footer_row);
     } else if view.prompt.is_some() {
-        draw_prompt_footer(view, caret)?;
+        draw_prompt_footer(screen, view, caret)?;
     } else {
-        draw_info_footer(view, caret, is_dirty)?;
+        draw_info_footer(screen, view, caret, is_dirty)?;
     }
 
-    queue!(stdout(), ResetColor)?;
+    screen.reset();
     Ok(())
 }
 
-fn draw_info_footer(view: &View, caret: &Caret, is_dirty: bool) -> Result<(), Error> {
+fn draw_info_footer(screen: &mut Screen, view: &View, caret: &Caret, is_dirty: bool) -> Result<(), Error> {
     let current_pos = caret.get_position();
 
     let size = Terminal::get_size()?;
     let footer_row = size.height - 1;
 
     // Left side: Filename or [No Name] and modified tag
-    queue!(stdout(), MoveTo(1, footer_row))?;
+    screen.move_to(1, footer_row);
     let filename_display = view.filename.as_deref().unwrap_or("[No Name]");
     let modified_tag = if is_dirty { "*" } else { "" };
-    queue!(
-        stdout(),
-        SetBackgroundColor(Color::Black),
-        SetForegroundColor(if is_dirty { Color::Red } else { Color::Yellow }),
-        SetAttribute(Attribute::Bold),
-        Print(format!(" {}{} ", filename_display, modified_tag)),
-        SetAttribute(Attribute::Reset),
-    )?;
+    screen.bg(Color::Black);
+    screen.fg(if is_dirty { Color::Red } else { Color::Yellow });
+    screen.attr(Attribute::Bold);
+    screen.print(&format!(" {}{} ", filename_display, modified_tag));
+    screen.attr(Attribute::Reset);
+
+    // Read-only / follow / encryption / scratch mode tag
+    let mode_tag = if view.following {
+        Some("[FOLLOW]")
+    } else if view.locked {
+        Some("[LOCKED]")
+    } else if view.read_only {
+        Some("[RO]")
+    } else if view.encrypted {
+        Some("[ENC]")
+    } else if view.shared {
+        Some("[SHARED]")
+    } else if view.scratch {
+        Some("[SCRATCH]")
+    } else {
+        None
+    };
+    if let Some(tag) = mode_tag {
+        screen.bg(Color::Black);
+        screen.fg(Color::Cyan);
+        screen.attr(Attribute::Bold);
+        screen.print(&format!("{} ", tag));
+        screen.attr(Attribute::Reset);
+    }
+    let caret_line = get_current_text_pos(view, caret).line;
+    if view.buffer.lines.get(caret_line).is_some_and(|line| display::is_long(line)) {
+        screen.bg(Color::Black);
+        screen.fg(Color::DarkYellow);
+        screen.print(&format!("[LONG LINE from col {}] ", helpers::column_offset(view, caret_line) + 1));
+        screen.reset();
+    }
+    for peer in &view.peers {
+        let (r, g, b) = peer.color();
+        screen.bg(Color::Black);
+        screen.fg(Color::Rgb { r, g, b });
+        screen.print(&format!("{} ", peer.name));
+        screen.reset();
+    }
+    if let Some(mode) = view.vim_mode {
+        screen.bg(Color::Black);
+        screen.fg(Color::Magenta);
+        screen.attr(Attribute::Bold);
+        screen.print(&format!("-- {} -- ", mode));
+        screen.attr(Attribute::Reset);
+    }
+    if let Some(keys) = &view.pending_keys {
+        screen.bg(Color::Black);
+        screen.fg(Color::DarkYellow);
+        screen.attr(Attribute::Bold);
+        screen.print(&format!("{} … ", keys));
+        screen.attr(Attribute::Reset);
+    }
     
     // filetype or [unknown file type]
     let filetype_display = view.filetype.as_deref().unwrap_or("[unknown file type]");
-    queue!(
-        stdout(),
-        SetBackgroundColor(Color::Black),
-        SetForegroundColor(Color::Yellow),
-        SetAttribute(Attribute::Bold),
-        Print(format!(" {} ", filetype_display)),
-        SetAttribute(Attribute::Reset),
-    )?;
-
-    // Calculate stats - find last non-empty line for accurate count
-    let total_lines = view
-        .buffer
-        .lines
-        .iter()
-        .rposition(|line| !line.is_empty())
-        .map(|idx| idx + 1)
-        .unwrap_or(1);
+    screen.bg(Color::Black);
+    screen.fg(Color::Yellow);
+    screen.attr(Attribute::Bold);
+    screen.print(&format!(" {} ", filetype_display));
+    screen.attr(Attribute::Reset);
+
+    // Calculate stats over the text's lines, blank ones at the end included
+    let total_lines = view.buffer.logical_len();
     let total_chars: usize = view
         .buffer
         .lines
@@ -181,68 +455,58 @@ fn draw_info_footer(view: &View, caret: &Caret, is_dirty: bool) -> Result<(), Er
         .sum();
 
     // Current position (adjust for margin)
-    let line_num = current_pos.x.saturating_sub(Position::HEADER) + 1 + view.scroll_offset as u16;
+    let line_num = caret_line + 1;
     let col_num = current_pos.y.saturating_sub(Position::MARGIN - 1);
 
     // Middle-left: Stats
     let stats = format!(" Ln {}, Col {} ", line_num, col_num);
-    queue!(stdout(), SetForegroundColor(Color::White), Print(stats),)?;
+    screen.fg(Color::White);
+    screen.print(&stats);
 
     // Middle: Lines and Characters count
     let counts = format!("Lines: {} | Chars: {} ", total_lines, total_chars);
     let counts_width = counts.len() as u16;
     let middle_pos = (size.width / 2).saturating_sub(counts_width / 2);
-    queue!(
-        stdout(),
-        MoveTo(middle_pos, footer_row),
-        SetBackgroundColor(Color::Black),
-        SetForegroundColor(Color::White),
-        Print(counts),
-    )?;
+    screen.move_to(middle_pos, footer_row);
+    screen.bg(Color::Black);
+    screen.fg(Color::White);
+    screen.print(&counts);
     
     let credits = "© Filip Domanski";
     let credits_width = credits.len() as u16;
     let credits_pos = middle_pos + credits_width;
-    queue!(
-        stdout(),
-        MoveTo(credits_pos, footer_row),
-        SetBackgroundColor(Color::Black),
-        SetForegroundColor(Color::Yellow),
-        SetAttribute(Attribute::Bold),
-        Print(format!(" {} ", credits)),
-        SetAttribute(Attribute::Reset),
-    )?;
+    screen.move_to(credits_pos, footer_row);
+    screen.bg(Color::Black);
+    screen.fg(Color::Yellow);
+    screen.attr(Attribute::Bold);
+    screen.print(&format!(" {} ", credits));
+    screen.attr(Attribute::Reset);
 
     // Right side: Tab hint - show "Ctrl+1-9 for tabs" or actual tab info if we have tab_manager
     let hint = " Ctrl+1-9 for tabs | Ctrl+g for shortcuts ";
     let hint_width = hint.len() as u16;
     let hint_pos = size.width.saturating_sub(hint_width + 1);
-    queue!(
-        stdout(),
-        MoveTo(hint_pos, footer_row),
-        SetForegroundColor(Color::DarkYellow),
-        SetAttribute(Attribute::Italic),
-        Print(hint),
-        SetAttribute(Attribute::Reset),
-    )?;
+    screen.move_to(hint_pos, footer_row);
+    screen.fg(Color::DarkYellow);
+    screen.attr(Attribute::Italic);
+    screen.print(hint);
+    screen.attr(Attribute::Reset);
 
     Ok(())
 }
 
-fn draw_shortcuts_footer() -> Result<(), Error> {
+fn draw_shortcuts_footer(screen: &mut Screen, bindings: &BTreeMap<String, String>) -> Result<(), Error> {
+    use crate::core::keybindings;
     use crate::core::shortcuts::Shortcuts;
 
     let size = Terminal::get_size()?;
     let footer_row = size.height - 1;
 
-    queue!(
-        stdout(),
-        MoveTo(1, footer_row),
-        SetBackgroundColor(Color::Black),
-    )?;
+    screen.move_to(1, footer_row);
+    screen.bg(Color::Black);
 
     // Get shortcuts from Shortcuts module
-    let shortcuts = Shortcuts::get_ctrl_shortcuts();
+    let shortcuts = Shortcuts::get_ctrl_shortcuts(&keybindings::remap(bindings).commands);
 
     let mut current_x = 1;
     for (i, (key, desc)) in shortcuts.iter().enumerate() {
@@ -253,28 +517,23 @@ fn draw_shortcuts_footer() -> Result<(), Error> {
         }
 
         // Draw key in bold yellow
-        queue!(
-            stdout(),
-            MoveTo(current_x, footer_row),
-            SetForegroundColor(Color::DarkYellow),
-            SetAttribute(Attribute::Bold),
-            Print(format!("{}", key)),
-            SetAttribute(Attribute::Reset),
-        )?;
+        screen.move_to(current_x, footer_row);
+        screen.fg(Color::DarkYellow);
+        screen.attr(Attribute::Bold);
+        screen.print(key);
+        screen.attr(Attribute::Reset);
         current_x += key.len() as u16;
 
         // Draw description
-        queue!(
-            stdout(),
-            MoveTo(current_x, footer_row),
-            SetForegroundColor(Color::White),
-            Print(format!(" {} ", desc)),
-        )?;
+        screen.move_to(current_x, footer_row);
+        screen.fg(Color::White);
+        screen.print(&format!(" {} ", desc));
         current_x += desc.len() as u16 + 1;
 
         // Add separator except for last item
         if i < shortcuts.len() - 1 {
-            queue!(stdout(), SetForegroundColor(Color::DarkGrey), Print("│ "),)?;
+            screen.fg(Color::DarkGrey);
+            screen.print("│ ");
             current_x += 2;
         }
     }
@@ -283,65 +542,87 @@ fn draw_shortcuts_footer() -> Result<(), Error> {
 }
 
 // Render a prompt-style footer
-fn draw_prompt_footer(view: &View, _caret: &Caret) -> Result<(), Error> {
+fn draw_prompt_footer(screen: &mut Screen, view: &View, _caret: &Caret) -> Result<(), Error> {
     let size = Terminal::get_size()?;
     let footer_row = size.height - 1;
 
     // Clear and set background for the prompt area
-    queue!(
-        stdout(),
-        MoveTo(0, footer_row),
-        SetBackgroundColor(Color::Black),
-    )?;
-    Terminal::clear_rest_of_line()?;
+    screen.move_to(0, footer_row);
+    screen.bg(Color::Black);
+    screen.clear_rest_of_line();
 
     // Move to content start
-    queue!(stdout(), MoveTo(1, footer_row))?;
+    screen.move_to(1, footer_row);
 
     if let Some(prompt) = &view.prompt {
         // Use local paths for enum to avoid needing extra imports
         match &prompt.kind {
             super::PromptKind::SaveAs => {
-                queue!(
-                    stdout(),
-                    SetForegroundColor(Color::DarkYellow),
-                    SetAttribute(Attribute::Bold),
-                    Print(format!("{}{}", prompt.message, prompt.input)),
-                )?;
-                draw_esc_hint(size.width, footer_row)?;
+                screen.fg(Color::DarkYellow);
+                screen.attr(Attribute::Bold);
+                screen.print(&format!("{}{}", prompt.message, prompt.input));
+                draw_esc_hint(screen, size.width, footer_row);
+            }
+            super::PromptKind::Passphrase => {
+                // Never echo the passphrase itself
+                screen.fg(Color::DarkYellow);
+                screen.attr(Attribute::Bold);
+                screen.print(&format!("{}{}", prompt.message, "*".repeat(prompt.input.chars().count())));
+                draw_esc_hint(screen, size.width, footer_row);
             }
             super::PromptKind::Search => {
-                queue!(
-                    stdout(),
-                    SetForegroundColor(Color::DarkYellow),
-                    SetAttribute(Attribute::Bold),
-                    Print(format!(" {} ", prompt.message)),
-                    SetAttribute(Attribute::Reset),
-                    SetForegroundColor(Color::White),
-                    Print(&prompt.input),
-                )?;
-                draw_esc_hint(size.width, footer_row)?;
+                screen.fg(Color::DarkYellow);
+                screen.attr(Attribute::Bold);
+                screen.print(&format!(" {} ", prompt.message));
+                screen.attr(Attribute::Reset);
+                screen.fg(Color::White);
+                screen.print(&prompt.input);
+                draw_esc_hint(screen, size.width, footer_row);
             }
             super::PromptKind::SearchInfo => {
-                queue!(
-                    stdout(),
-                    SetForegroundColor(Color::Green),
-                    SetAttribute(Attribute::Bold),
-                    Print(" 🔍 "),
-                    SetAttribute(Attribute::Reset),
-                    SetForegroundColor(Color::White),
-                    Print(&prompt.message),
-                )?;
-                draw_esc_hint(size.width, footer_row)?;
+                screen.fg(Color::Green);
+                screen.attr(Attribute::Bold);
+                screen.print(" 🔍 ");
+                screen.attr(Attribute::Reset);
+                screen.fg(Color::White);
+                screen.print(&prompt.message);
+                draw_esc_hint(screen, size.width, footer_row);
+            }
+            super::PromptKind::Palette => {
+                // Query first, matching commands after it
+                screen.fg(Color::DarkYellow);
+                screen.attr(Attribute::Bold);
+                screen.print(" > ");
+                screen.attr(Attribute::Reset);
+                screen.fg(Color::White);
+                screen.print(&prompt.input);
+                screen.fg(Color::DarkGrey);
+                screen.print(&format!("   {}", prompt.message));
+                draw_esc_hint(screen, size.width, footer_row);
+            }
+            super::PromptKind::Replace { editing_find, scope } => {
+                // Both fields on one line, the label of the one being typed in bold
+                let fields = [(" Find: ", &prompt.message, *editing_find), ("   Replace with: ", &prompt.input, !*editing_find)];
+                for (label, text, active) in fields {
+                    screen.fg(Color::DarkYellow);
+                    if active {
+                        screen.attr(Attribute::Bold);
+                    }
+                    screen.print(label);
+                    screen.attr(Attribute::Reset);
+                    screen.fg(Color::White);
+                    screen.print(text);
+                }
+                screen.fg(Color::DarkGrey);
+                screen.print(&format!("   [{}, Alt+L]", scope.as_deref().unwrap_or("whole file")));
+                screen.reset();
+                draw_esc_hint(screen, size.width, footer_row);
             }
             super::PromptKind::Error => {
-                queue!(
-                    stdout(),
-                    SetForegroundColor(Color::Red),
-                    SetAttribute(Attribute::Bold),
-                    Print(format!(" {} ", prompt.message)),
-                    SetAttribute(Attribute::Reset),
-                )?;
+                screen.fg(Color::Red);
+                screen.attr(Attribute::Bold);
+                screen.print(&format!(" {} ", prompt.message));
+                screen.attr(Attribute::Reset);
             }
         }
     }
@@ -350,25 +631,21 @@ fn draw_prompt_footer(view: &View, _caret: &Caret) -> Result<(), Error> {
 }
 
 fn render_line_with_selection_and_syntax(
-    line: &str, 
+    screen: &mut Screen,
     line_idx: usize, 
     selection_range: Option<(TextPosition, TextPosition)>,
-    highlighter: &SyntaxHighlighter,
-) -> Result<(), Error> {
-    // Get syntax tokens for this line
-    let tokens = highlighter.highlight_line(line);
-    
+    tokens: &[Token],
+) {
     if let Some((start, end)) = selection_range {
         let in_selection = line_idx >= start.line && line_idx <= end.line;
 
         if !in_selection {
             // No selection on this line - just render with syntax highlighting
-            render_tokens(&tokens)?;
-            return Ok(());
+            render_tokens(screen, tokens);
+            return;
         }
 
         // Line has selection - need to handle both selection highlighting and syntax
-        let chars: Vec<char> = line.chars().collect();
         let sel_start = if line_idx == start.line {
             start.column
         } else {
@@ -377,137 +654,247 @@ fn render_line_with_selection_and_syntax(
         let sel_end = if line_idx == end.line {
             end.column
         } else {
-            chars.len()
+            usize::MAX
         };
 
-        render_tokens_with_selection(&tokens, sel_start, sel_end)?;
+        render_tokens_with_selection(screen, tokens, sel_start, sel_end);
     } else {
         // No selection at all - just render with syntax highlighting
-        render_tokens(&tokens)?;
+        render_tokens(screen, tokens);
     }
+}
 
-    Ok(())
+// Lines with right-to-left text are printed grapheme by grapheme in visual order,
+// each keeping the color of its token
+fn render_bidi_line(
+    screen: &mut Screen,
+    line: &str,
+    line_idx: usize,
+    selection_range: Option<(TextPosition, TextPosition)>,
+    tokens: &[Token],
+) {
+    let colors: Vec<Color> = tokens
+        .iter()
+        .flat_map(|token| std::iter::repeat_n(token.token_type.color(), token.text.chars().count()))
+        .collect();
+    let (sel_start, sel_end) = match selection_range {
+        Some((start, end)) if line_idx >= start.line && line_idx <= end.line => (
+            if line_idx == start.line { start.column } else { 0 },
+            if line_idx == end.line { end.column } else { usize::MAX },
+        ),
+        _ => (0, 0),
+    };
+    // Cells carry char columns, the selection grapheme columns
+    let starts: Vec<usize> = line
+        .graphemes(true)
+        .scan(0, |chars, g| {
+            let start = *chars;
+            *chars += g.chars().count();
+            Some(start)
+        })
+        .collect();
+
+    for cell in bidi::visual_line(line) {
+        let column = starts.partition_point(|&start| start < cell.column);
+        if (sel_start..sel_end).contains(&column) {
+            print_text_selected(screen, cell.text);
+        } else {
+            print_text_colored(screen, cell.text, colors.get(cell.column).copied().unwrap_or(Color::White));
+        }
+    }
 }
 
 // Helper: Render tokens with syntax highlighting (no selection)
-fn render_tokens(tokens: &[crate::tui::syntax::Token]) -> Result<(), Error> {
+fn render_tokens(screen: &mut Screen, tokens: &[crate::core::syntax::Token]) {
     for token in tokens {
-        print_text_colored(&token.text, token.token_type.color())?;
+        print_token(screen, token);
     }
-    Ok(())
+}
+
+// Helper: Print a token in its color, striking through checked task items
+// bolding TODO markers and underlining links
+fn print_token(screen: &mut Screen, token: &crate::core::syntax::Token) {
+    if token.token_type == crate::core::syntax::TokenType::Link {
+        screen.attr(Attribute::Underlined);
+        print_text_colored(screen, &token.text, token.token_type.color());
+        screen.attr(Attribute::NoUnderline);
+        return;
+    }
+    if token.token_type == crate::core::syntax::TokenType::Todo {
+        screen.attr(Attribute::Bold);
+        print_text_colored(screen, &token.text, token.token_type.color());
+        screen.attr(Attribute::NormalIntensity);
+        return;
+    }
+    if token.token_type == crate::core::syntax::TokenType::ConflictMarker {
+        screen.attr(Attribute::Bold);
+        print_text_colored(screen, &token.text, token.token_type.color());
+        screen.attr(Attribute::NormalIntensity);
+        return;
+    }
+    if token.token_type == crate::core::syntax::TokenType::SearchMatch {
+        screen.bg(Color::DarkYellow);
+        print_text_colored(screen, &token.text, token.token_type.color());
+        return;
+    }
+    if token.token_type == crate::core::syntax::TokenType::Done {
+        screen.attr(Attribute::CrossedOut);
+        print_text_colored(screen, &token.text, token.token_type.color());
+        screen.attr(Attribute::NotCrossedOut);
+        return;
+    }
+    print_text_colored(screen, &token.text, token.token_type.color())
 }
 
 // Helper: Render tokens with both syntax highlighting and selection
 fn render_tokens_with_selection(
-    tokens: &[crate::tui::syntax::Token],
+    screen: &mut Screen,
+    tokens: &[crate::core::syntax::Token],
     sel_start: usize,
     sel_end: usize,
-) -> Result<(), Error> {
-    let mut char_pos = 0;
-    
-    for token in tokens {
-        let token_len = token.text.chars().count();
-        let token_end = char_pos + token_len;
-        
-        // Check if this token overlaps with selection
-        if token_end <= sel_start || char_pos >= sel_end {
-            // Token is completely outside selection
-            print_text_colored(&token.text, token.token_type.color())?;
-        } else if char_pos >= sel_start && token_end <= sel_end {
-            // Token is completely inside selection
-            print_text_selected(&token.text)?;
+) {
+    for (token, text, selected) in selection_pieces(tokens, sel_start, sel_end) {
+        if selected {
+            print_text_selected(screen, text);
+        } else if text.len() == token.text.len() {
+            print_token(screen, token);
         } else {
-            // Token is partially selected - need to split it
-            render_token_partial_selection(token, char_pos, sel_start, sel_end)?;
+            print_token(screen, &Token { text: text.to_string(), token_type: token.token_type });
         }
-        
-        char_pos = token_end;
     }
-    
-    Ok(())
 }
 
-// Helper: Render a token that's partially selected
-fn render_token_partial_selection(
-    token: &crate::tui::syntax::Token,
-    char_pos: usize,
-    sel_start: usize,
-    sel_end: usize,
-) -> Result<(), Error> {
-    let token_chars: Vec<char> = token.text.chars().collect();
-    
-    for (i, ch) in token_chars.iter().enumerate() {
-        let abs_pos = char_pos + i;
-        if abs_pos >= sel_start && abs_pos < sel_end {
-            // Character is selected
-            print_char_selected(*ch)?;
-        } else {
-            // Character is not selected - use syntax color
-            print_char_colored(*ch, token.token_type.color())?;
+// Tokens cut where the selection starts and ends, counting grapheme columns as selections do,
+// each piece with whether it is selected
+fn selection_pieces(tokens: &[Token], sel_start: usize, sel_end: usize) -> Vec<(&Token, &str, bool)> {
+    let mut pieces = Vec::new();
+    let mut column = 0;
+    for token in tokens {
+        let mut start = 0;
+        let mut selected = (sel_start..sel_end).contains(&column);
+        for (byte, _) in token.text.grapheme_indices(true) {
+            let now = (sel_start..sel_end).contains(&column);
+            if now != selected {
+                pieces.push((token, &token.text[start..byte], selected));
+                start = byte;
+                selected = now;
+            }
+            column += 1;
+        }
+        if start < token.text.len() {
+            pieces.push((token, &token.text[start..], selected));
         }
     }
-    
-    Ok(())
+    pieces
 }
 
 // Helper: Print text with a specific color
-fn print_text_colored(text: &str, color: Color) -> Result<(), Error> {
-    queue!(
-        stdout(),
-        SetForegroundColor(color),
-        Print(text),
-        ResetColor
-    )?;
-    Ok(())
+fn print_text_colored(screen: &mut Screen, text: &str, color: Color) {
+    screen.fg(color);
+    screen.print(text);
+    screen.reset();
 }
 
 // Helper: Print text with selection highlighting
-fn print_text_selected(text: &str) -> Result<(), Error> {
-    queue!(
-        stdout(),
-        SetBackgroundColor(Color::DarkBlue),
-        SetForegroundColor(Color::White),
-        Print(text),
-        ResetColor
-    )?;
-    Ok(())
+fn print_text_selected(screen: &mut Screen, text: &str) {
+    screen.bg(Color::DarkBlue);
+    screen.fg(Color::White);
+    screen.print(text);
+    screen.reset();
 }
 
-// Helper: Print a single character with a specific color
-fn print_char_colored(ch: char, color: Color) -> Result<(), Error> {
-    queue!(
-        stdout(),
-        SetForegroundColor(color),
-        Print(ch),
-        ResetColor
-    )?;
-    Ok(())
+// Messages stacked right-aligned over the last text rows, the newest just above the footer
+fn draw_toasts(screen: &mut Screen, view: &View, width: u16, footer_row: u16) {
+    let room = footer_row.saturating_sub(Position::HEADER) as usize;
+    for (i, toast) in view.toasts.iter().rev().take(room).enumerate() {
+        let max = (width as usize).saturating_sub(4);
+        let text: String = toast.message.graphemes(true).scan(0, |used, g| {
+            *used += visual_width(g, view.config.tab_width);
+            (*used <= max).then_some(g)
+        }).collect();
+        let shown = format!(" {} ", text);
+        let x = width.saturating_sub(visual_width(&shown, view.config.tab_width) as u16 + 1);
+        screen.move_to(x, footer_row - 1 - i as u16);
+        match toast.kind {
+            ToastKind::Info => {
+                screen.bg(Color::DarkGreen);
+                screen.fg(Color::White);
+            }
+            ToastKind::Error => {
+                screen.bg(Color::DarkRed);
+                screen.fg(Color::White);
+                screen.attr(Attribute::Bold);
+            }
+        }
+        screen.print(&shown);
+        screen.reset();
+    }
 }
 
-// Helper: Print a single character with selection highlighting
-fn print_char_selected(ch: char) -> Result<(), Error> {
-    queue!(
-        stdout(),
-        SetBackgroundColor(Color::DarkBlue),
-        SetForegroundColor(Color::White),
-        Print(ch),
-        ResetColor
-    )?;
-    Ok(())
+// Footer bar for work that holds the view and can't be cancelled, like streaming a big save.
+// Only the footer row is drawn, the text above stays as it was
+pub fn draw_progress(label: &str, fraction: Option<f32>) -> Result<(), Error> {
+    screen::draw(|screen| {
+        let size = Terminal::get_size()?;
+        let footer_row = size.height - 1;
+        screen.move_to(0, footer_row);
+        screen.bg(Color::Black);
+        screen.clear_rest_of_line();
+        draw_progress_bar(screen, label, fraction, size.width, footer_row);
+        screen.reset();
+        Ok(())
+    })
+}
+
+// Job label followed by a bar, or just the label while the size is unknown
+fn draw_progress_footer(screen: &mut Screen, label: &str, fraction: Option<f32>, width: u16, footer_row: u16) {
+    draw_progress_bar(screen, label, fraction, width, footer_row);
+    draw_esc_hint(screen, width, footer_row);
+}
+
+fn draw_progress_bar(screen: &mut Screen, label: &str, fraction: Option<f32>, width: u16, footer_row: u16) {
+    screen.move_to(1, footer_row);
+    screen.fg(Color::Green);
+    screen.attr(Attribute::Bold);
+    screen.print(&format!(" {} ", label));
+    screen.attr(Attribute::Reset);
+    if let Some(fraction) = fraction {
+        let room = (width as usize).saturating_sub(label.chars().count() + 30).min(40);
+        let filled = (fraction * room as f32).round() as usize;
+        screen.fg(Color::Green);
+        screen.print(&"█".repeat(filled));
+        screen.fg(Color::DarkGrey);
+        screen.print(&"░".repeat(room - filled));
+        screen.print(&format!(" {:>3}%", (fraction * 100.0) as u32));
+    }
 }
 
 // Helper function to draw the Esc hint on the right side of the footer
-fn draw_esc_hint(screen_width: u16, footer_row: u16) -> Result<(), Error> {
+fn draw_esc_hint(screen: &mut Screen, screen_width: u16, footer_row: u16) {
     let hint = " Press Esc to cancel ";
     let hint_width = hint.len() as u16;
     let hint_pos = screen_width.saturating_sub(hint_width + 1);
-    queue!(
-        stdout(),
-        MoveTo(hint_pos, footer_row),
-        SetForegroundColor(Color::DarkYellow),
-        SetAttribute(Attribute::Italic),
-        Print(hint),
-        SetAttribute(Attribute::Reset),
-    )?;
-    Ok(())
-}
\ No newline at end of file
+    screen.move_to(hint_pos, footer_row);
+    screen.fg(Color::DarkYellow);
+    screen.attr(Attribute::Italic);
+    screen.print(hint);
+    screen.attr(Attribute::Reset);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selection_splits_tokens_at_grapheme_columns() {
+        let token = |text: &str, token_type| Token { text: text.to_string(), token_type };
+        // An emoji family and an accented letter are single columns of several chars
+        let tokens = [token("a👨‍👩‍👧", TokenType::Keyword), token("e\u{301}b", TokenType::Normal)];
+        let pieces: Vec<(&str, bool)> =
+            selection_pieces(&tokens, 1, 3).into_iter().map(|(_, text, selected)| (text, selected)).collect();
+        assert_eq!(pieces, [("a", false), ("👨‍👩‍👧", true), ("e\u{301}", true), ("b", false)]);
+
+        let whole: Vec<&str> = selection_pieces(&tokens, 0, usize::MAX).into_iter().map(|(_, text, _)| text).collect();
+        assert_eq!(whole, ["a👨‍👩‍👧", "e\u{301}b"]);
+    }
+}
@@ -4,139 +4,116 @@ use crate::tui::{
     caret::{Caret, Position},
     terminal::Terminal,
 };
+use crate::core::edit_history::{Edit, EditOperation};
+use crate::core::replace::{Group, Template};
 use crate::core::selection::{Selection, TextPosition};
-use crossterm::event::{Event, KeyCode, KeyEventKind, read};
+use crate::core::toasts::ToastKind;
+use crate::tui::view::graphemes::{grapheme_len, grapheme_slice};
 use std::io::Error;
+use unicode_segmentation::UnicodeSegmentation;
 
-// Stores all search match locations
-#[derive(Clone, Debug)]
-pub struct SearchMatch {
-    pub line: usize,
-    pub column: usize,
-    pub length: usize,
-}
+pub use crate::core::search_index::{SearchMatch, SearchState};
+
+pub type Scope = (TextPosition, TextPosition);
 
-pub struct SearchState {
-    pub _query: String,
-    pub matches: Vec<SearchMatch>,
-    pub current_match_idx: usize,
+// What the Ctrl+H prompt asked for
+pub struct ReplaceRequest {
+    pub find: String,
+    pub replacement: String,
+    pub scope: Option<Scope>,
 }
 
-impl SearchState {
-    pub fn new(_query: String, matches: Vec<SearchMatch>) -> Self {
-        Self {
-            _query,
-            matches,
-            current_match_idx: 0,
-        }
-    }
-    
-    pub fn next_match(&mut self) {
-        if !self.matches.is_empty() {
-            self.current_match_idx = (self.current_match_idx + 1) % self.matches.len();
+// Alt+L in the prompts limits matching to the selection, or to the caret's line without one.
+// The region comes with its description for the prompt
+pub fn scope_region(view: &View, caret: &Caret) -> (Scope, String) {
+    let region = match view.selection.as_ref().filter(|s| s.is_active()) {
+        Some(selection) => selection.get_range(),
+        None => {
+            let line = super::helpers::get_current_text_pos(view, caret).line;
+            let length = view.buffer.lines.get(line).map_or(0, |text| grapheme_len(text));
+            (TextPosition { line, column: 0 }, TextPosition { line, column: length })
         }
-    }
-    
-    pub fn prev_match(&mut self) {
-        if !self.matches.is_empty() {
-            self.current_match_idx = if self.current_match_idx == 0 {
-                self.matches.len() - 1
-            } else {
-                self.current_match_idx - 1
-            };
-        }
-    }
-    
-    pub fn current_match(&self) -> Option<&SearchMatch> {
-        self.matches.get(self.current_match_idx)
-    }
+    };
+    (region, describe_scope(view, region))
 }
 
-pub fn search(view: &mut View, caret: &mut Caret) -> Result<(), Error> {
-    // Show search prompt
-    view.show_prompt(
-        super::PromptKind::Search,
-        "Search:".to_string(),
-    );
-    view.needs_redraw = true;
-    view.render_if_needed(caret, false)?;
-    Terminal::execute()?;
-
-    let mut search_query = String::new();
-
-    // Capture input for search query
-    loop {
-        match read()? {
-            Event::Key(event) if event.kind == KeyEventKind::Press => {
-                match event.code {
-                    KeyCode::Char(c) => {
-                        search_query.push(c);
-                        view.append_prompt_char(c);
-                        view.render_if_needed(caret, false)?;
-                        Terminal::execute()?;
-                    }
-                    KeyCode::Backspace => {
-                        search_query.pop();
-                        view.backspace_prompt();
-                        view.render_if_needed(caret, false)?;
-                        Terminal::execute()?;
-                    }
-                    KeyCode::Enter => {
-                        view.clear_prompt();
-                        if !search_query.is_empty() {
-                            perform_search(view, caret, &search_query)?;
-                        }
-                        break;
-                    }
-                    KeyCode::Esc => {
-                        view.clear_prompt();
-                        view.render_if_needed(caret, false)?;
-                        Terminal::execute()?;
-                        break;
-                    }
-                    _ => {}
-                }
-            }
-            _ => {}
-        }
+fn describe_scope(view: &View, (start, end): Scope) -> String {
+    let whole_line = view.buffer.lines.get(start.line).map_or(0, |text| grapheme_len(text));
+    if start.line == end.line && start.column == 0 && end.column == whole_line {
+        format!("in line {}", start.line + 1)
+    } else {
+        "in selection".to_string()
     }
+}
 
-    Ok(())
+pub fn search(view: &mut View, caret: &mut Caret, query: &str, scope: Option<Scope>) -> Result<(), Error> {
+    perform_search(view, caret, query, 0, scope)
+}
+
+// Ctrl+K C: how often the query appears, in the footer. The caret and selection stay where they are
+pub fn count_occurrences(view: &mut View, caret: &mut Caret, query: &str, scope: Option<Scope>) -> Result<(), Error> {
+    let matches = find_all_occurrences(view, query, scope);
+    let mut lines = matches.iter().map(|m| m.line).collect::<Vec<_>>();
+    lines.dedup();
+    let place = scope.map(|s| format!(" {}", describe_scope(view, s))).unwrap_or_default();
+    let message = match (matches.len(), lines.len()) {
+        (0, _) => format!("No matches for '{}'{}", query, place),
+        (1, _) => format!("'{}': 1 match{}", query, place),
+        (n, 1) => format!("'{}': {} matches on 1 line{}", query, n, place),
+        (n, l) => format!("'{}': {} matches on {} lines{}", query, n, l, place),
+    };
+    view.last_search = Some(query.to_string());
+    view.show_prompt(super::PromptKind::SearchInfo, message);
+    view.render(caret)
 }
 
-fn perform_search(view: &mut View, caret: &mut Caret, query: &str) -> Result<(), Error> {
+fn perform_search(view: &mut View, caret: &mut Caret, query: &str, skip: usize, scope: Option<Scope>) -> Result<(), Error> {
     if query.is_empty() {
         return Ok(());
     }
 
     // Find all occurrences
-    let matches = find_all_occurrences(&view.buffer.lines, query);
+    let matches = find_all_occurrences(view, query, scope);
 
     if matches.is_empty() {
         // No match found - show error in prompt
-        view.show_prompt(
-            super::PromptKind::Error,
-            format!("No matches found for '{}'", query),
-        );
-        view.render_if_needed(caret, false)?;
-        Terminal::execute()?;
-        
-        std::thread::sleep(std::time::Duration::from_secs(2));
-        view.clear_prompt();
-        view.render_if_needed(caret, false)?;
-        Terminal::execute()?;
-        return Ok(());
+        view.last_search = Some(query.to_string());
+        view.notify(ToastKind::Error, no_matches(view, query, scope));
+        return view.render_if_needed(caret, false);
     }
 
-    // Find the match closest to current cursor position
-    let current_pos = caret.get_position();
-    let current_line = (current_pos.y.saturating_sub(Position::HEADER)) as usize + view.scroll_offset;
-    let current_col = (current_pos.x as usize).saturating_sub(Position::MARGIN as usize);
+    select_matches(view, caret, query, matches, skip, None, scope)
+}
+
+fn no_matches(view: &View, query: &str, scope: Option<Scope>) -> String {
+    match scope {
+        Some(scope) => format!("No matches found for '{}' {}", query, describe_scope(view, scope)),
+        None => format!("No matches found for '{}'", query),
+    }
+}
+
+// Keep the matches as the search state and go to the one closest to the caret, or the one
+// after it when `skip` is set
+fn select_matches(
+    view: &mut View,
+    caret: &mut Caret,
+    query: &str,
+    matches: Vec<SearchMatch>,
+    skip: usize,
+    replacement: Option<String>,
+    scope: Option<Scope>,
+) -> Result<(), Error> {
+    let current_pos = super::helpers::get_current_text_pos(view, caret);
+    let current_line = current_pos.line;
+    let current_col = current_pos.column + skip;
     
     let closest_idx = find_closest_match(&matches, current_line, current_col);
 
     // Store search state in view
-    let search_state = SearchState::new(query.to_string(), matches);
+    view.last_search = Some(query.to_string());
+    let mut search_state = SearchState::new(query.to_string(), matches);
+    search_state.replacement = replacement;
+    search_state.scope = scope;
     view.set_search_state(Some(search_state));
     view.set_current_match(closest_idx);
 
@@ -146,25 +123,196 @@ fn perform_search(view: &mut View, caret: &mut Caret, query: &str) -> Result<(),
     Ok(())
 }
 
-fn find_all_occurrences(lines: &[String], query: &str) -> Vec<SearchMatch> {
-    let query_lower = query.to_lowercase();
+// F3: the next match of the last query after the caret
+pub fn repeat_search(view: &mut View, caret: &mut Caret) -> Result<(), Error> {
+    match view.last_search.clone() {
+        Some(query) => perform_search(view, caret, &query, 1, None),
+        None => Ok(()),
+    }
+}
+
+// Matches in grapheme columns, like the caret and selections, inside `scope` when there is one.
+// The index reports byte offsets into the lowercased line, so lines beyond ASCII are measured again
+fn find_all_occurrences(view: &mut View, query: &str, scope: Option<Scope>) -> Vec<SearchMatch> {
+    let query_len = query.to_lowercase().len();
+    let mut lowered: Option<(usize, String)> = None;
     let mut matches = Vec::new();
+    for (line, byte) in view.search_index.find_all(&view.buffer.lines, query) {
+        let text = &view.buffer.lines[line];
+        if text.is_ascii() {
+            matches.push(SearchMatch { line, column: byte, length: query_len });
+            continue;
+        }
+        if lowered.as_ref().is_none_or(|(cached, _)| *cached != line) {
+            lowered = Some((line, text.to_lowercase()));
+        }
+        let lower = lowered.as_ref().map_or("", |(_, lower)| lower.as_str());
+        let column = lower.get(..byte).unwrap_or(lower).graphemes(true).count();
+        let length = lower.get(byte..byte + query_len).unwrap_or_default().graphemes(true).count();
+        matches.push(SearchMatch { line, column, length });
+    }
+    if let Some(scope) = scope {
+        matches.retain(|m| m.is_within(scope));
+    }
+    matches
+}
 
-    for (line_idx, line) in lines.iter().enumerate() {
-        let line_lower = line.to_lowercase();
-        let mut start = 0;
+// Grapheme column ranges of the matches on `line`, drawn highlighted while a search is open
+pub fn line_matches(view: &View, line: usize) -> Vec<(usize, usize)> {
+    let Some(state) = &view.search_state else {
+        return Vec::new();
+    };
+    let first = state.matches.partition_point(|m| m.line < line);
+    state.matches[first..]
+        .iter()
+        .take_while(|m| m.line == line)
+        .map(|m| (m.column, m.column + m.length))
+        .collect()
+}
 
-        while let Some(pos) = line_lower[start..].find(&query_lower) {
-            matches.push(SearchMatch {
-                line: line_idx,
-                column: start + pos,
-                length: query.len(),
-            });
-            start += pos + 1; // Move past this match to find next
+// Highlight every match of `query` and go to the first one from the caret, with the footer
+// offering to replace them. False when there are none
+pub fn start_replace(view: &mut View, caret: &mut Caret, request: &ReplaceRequest) -> Result<bool, Error> {
+    // Search is literal, so a match has no groups beyond the whole of it
+    if let Some(group) = Template::parse(&request.replacement).groups().next() {
+        let group = match group {
+            Group::Index(index) => format!("${}", index),
+            Group::Name(name) => format!("${{{}}}", name),
+        };
+        view.notify(ToastKind::Error, format!("{} needs a regex search, only $0 works for now", group));
+        view.needs_redraw = true;
+        return Ok(false);
+    }
+    let matches = find_all_occurrences(view, &request.find, request.scope);
+    if matches.is_empty() {
+        view.last_search = Some(request.find.clone());
+        view.notify(ToastKind::Error, no_matches(view, &request.find, request.scope));
+        view.needs_redraw = true;
+        return Ok(false);
+    }
+    select_matches(view, caret, &request.find, matches, 0, Some(request.replacement.clone()), request.scope)?;
+    Ok(true)
+}
+
+// The edit replacing one match, applied to the buffer. $0 in the replacement is the text matched,
+// which keeps its own case as search ignores it
+fn replace_match(view: &mut View, m: &SearchMatch, replacement: &Template) -> Edit {
+    let old_text = grapheme_slice(&view.buffer.lines[m.line], m.column, m.column + m.length);
+    let new_text = replacement.expand(|group| (group == &Group::Index(0)).then_some(old_text.as_str()));
+    let edit = Edit::ReplaceRange {
+        start_line: m.line,
+        start_column: m.column,
+        end_line: m.line,
+        end_column: m.column + m.length,
+        old_text,
+        new_text,
+    };
+    edit.apply(&mut view.buffer.lines);
+    edit
+}
+
+// Replace the current match and go on to the next one after the new text. Later matches
+// have moved, so the text is searched again; the search closes when nothing is left
+pub fn replace_current(view: &mut View, caret: &mut Caret) -> Result<Option<EditOperation>, Error> {
+    let Some(state) = &view.search_state else {
+        return Ok(None);
+    };
+    let (Some(m), Some(replacement)) = (state.current_match().cloned(), state.replacement.clone()) else {
+        return Ok(None);
+    };
+    let query = state._query.clone();
+    let scope = state.scope;
+    let mut done = state.done.clone();
+    let cursor_before = caret.get_position();
+    let scroll_before = view.scroll_offset;
+
+    let edit = replace_match(view, &m, &Template::parse(&replacement));
+    let after = match &edit {
+        Edit::ReplaceRange { new_text, .. } => m.column + grapheme_len(new_text),
+        _ => m.column + m.length,
+    };
+    // The end of the scope and text already gone through after the new text move with it.
+    // The new text is left out of later matches too, as search ignores case
+    let shift = |line: usize, column: &mut usize| {
+        if line == m.line && *column >= m.column + m.length {
+            *column = *column + after - (m.column + m.length);
         }
+    };
+    let scope = scope.map(|(start, mut end)| {
+        shift(end.line, &mut end.column);
+        (start, end)
+    });
+    done.iter_mut().for_each(|d| shift(d.line, &mut d.column));
+    done.push(SearchMatch { line: m.line, column: m.column, length: after - m.column });
+    let mut matches = find_all_occurrences(view, &query, scope);
+    matches.retain(|n| !done.iter().any(|d| d.overlaps(n)));
+    if matches.is_empty() {
+        clear_search(view);
+        view.jump_to(TextPosition { line: m.line, column: after }, caret)?;
+    } else {
+        let next = matches.iter().position(|n| (n.line, n.column) >= (m.line, after)).unwrap_or(0);
+        if let Some(state) = &mut view.search_state {
+            state.matches = matches;
+            state.current_match_idx = next;
+            state.scope = scope;
+            state.done = done;
+        }
+        move_to_current_match(view, caret)?;
     }
 
-    matches
+    Ok(Some(EditOperation {
+        edit,
+        cursor_before,
+        cursor_after: caret.get_position(),
+        scroll_before,
+        scroll_after: view.scroll_offset,
+    }))
+}
+
+// Leave the current match as it is and go on to the next one, closing the search after the last
+pub fn skip_match(view: &mut View, caret: &mut Caret) -> Result<(), Error> {
+    let Some(state) = &mut view.search_state else {
+        return Ok(());
+    };
+    state.skip_current();
+    if state.matches.is_empty() {
+        clear_search(view);
+        view.render(caret)?;
+        return Ok(());
+    }
+    move_to_current_match(view, caret)
+}
+
+// Replace every match still to go, overlapping ones once, and close the search. The last
+// match goes first so the columns of the ones before it stay right
+pub fn replace_all(view: &mut View, caret: &mut Caret) -> Result<Vec<EditOperation>, Error> {
+    let Some(state) = view.search_state.take() else {
+        return Ok(Vec::new());
+    };
+    let replacement = Template::parse(&state.replacement.unwrap_or_default());
+    let mut kept: Vec<&SearchMatch> = Vec::new();
+    for m in &state.matches {
+        if kept.last().is_none_or(|k| k.line != m.line || k.column + k.length <= m.column) {
+            kept.push(m);
+        }
+    }
+
+    let cursor = caret.get_position();
+    let scroll = view.scroll_offset;
+    let operations = kept
+        .iter()
+        .rev()
+        .map(|m| EditOperation {
+            edit: replace_match(view, m, &replacement),
+            cursor_before: cursor,
+            cursor_after: cursor,
+            scroll_before: scroll,
+            scroll_after: scroll,
+        })
+        .collect();
+    clear_search(view);
+    view.render(caret)?;
+    Ok(operations)
 }
 
 fn find_closest_match(matches: &[SearchMatch], line: usize, col: usize) -> usize {
@@ -200,11 +348,12 @@ fn move_to_current_match(view: &mut View, caret: &mut Caret) -> Result<(), Error
             let size = Terminal::get_size()?;
             let visible_rows = size.height.saturating_sub(Position::HEADER + 1) as usize;
 
-            // Adjust scroll to show the match
+            // Adjust scroll to show the match, opening a fold hiding it
+            view.folds.reveal(m.line);
             if m.line < view.scroll_offset {
                 view.scroll_offset = m.line;
-            } else if m.line >= view.scroll_offset + visible_rows {
-                view.scroll_offset = m.line.saturating_sub(visible_rows / 2);
+            } else if view.folds.row_of_line(view.scroll_offset, m.line) >= visible_rows {
+                view.scroll_offset = view.folds.visible_line(m.line.saturating_sub(visible_rows / 2));
             }
 
             // Create selection for current match
@@ -225,9 +374,14 @@ fn move_to_current_match(view: &mut View, caret: &mut Caret) -> Result<(), Error
             // Update footer to show match info
             let total = search_state.matches.len();
             let current = search_state.current_match_idx + 1;
+            let keys = match search_state.replacement {
+                Some(_) => "Y replace | N skip | A all the rest | Q quit | ↑/↓ move",
+                None => "↑/↓ to navigate",
+            };
+            let scope = search_state.scope.map(|s| format!(" {}", describe_scope(view, s))).unwrap_or_default();
             view.show_prompt(
                 super::PromptKind::SearchInfo,
-                format!("Match {} of {} | ↑/↓ to navigate", current, total),
+                format!("Match {} of {}{} | {}", current, total, scope, keys),
             );
 
             // Move caret to end of match
@@ -236,7 +390,6 @@ fn move_to_current_match(view: &mut View, caret: &mut Caret) -> Result<(), Error
             view.needs_redraw = true;
             view.render(caret)?;
             caret.move_to(Position { x: screen_x, y: screen_y })?;
-            Terminal::execute()?;
         }
     }
 
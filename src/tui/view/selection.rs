@@ -5,7 +5,11 @@ use crate::tui::{
     terminal::Terminal,
     caret::{Caret, Position},
 };
+use crate::core::display;
+use crate::core::jumps::{self, Jump};
 use crate::core::selection::{Selection, TextPosition};
+use crate::core::smart_select::Range;
+use std::cmp::Ordering;
 use std::io::Error;
 
 pub fn move_with_selection(view: &mut View, direction: &str, caret: &mut Caret) -> Result<(), Error> {
@@ -45,52 +49,56 @@ fn perform_movement(view: &mut View, direction: &str, caret: &mut Caret) -> Resu
     match direction {
         "left" => {
             let pos = caret.get_position();
-            let buffer_line_idx = (pos.y.saturating_sub(Position::HEADER)) as usize + view.scroll_offset;
+            let TextPosition { line: buffer_line_idx, column } = helpers::get_current_text_pos(view, caret);
             
-            if pos.x <= Position::MARGIN && buffer_line_idx > 0 {
-                let prev_line_len = view.buffer.lines.get(buffer_line_idx - 1)
-                    .map(|l| grapheme_len(l))
-                    .unwrap_or(0);
+            if pos.x <= Position::MARGIN && column > 0 {
+                // At the left edge of a long line's window, slide it back
+                let (x, y) = helpers::text_to_screen_pos(view, TextPosition { line: buffer_line_idx, column: column - 1 });
+                caret.move_to(Position { x, y })?;
+            } else if pos.x <= Position::MARGIN && buffer_line_idx > 0 {
+                let prev_line = view.folds.prev_visible(buffer_line_idx);
+                let prev_line_len = view.line_metrics(prev_line).graphemes;
+                let (x, _) = helpers::text_to_screen_pos(view, TextPosition { line: prev_line, column: prev_line_len });
                 
                 if pos.y > Position::HEADER {
-                    caret.move_to(Position { 
-                        x: Position::MARGIN + prev_line_len as u16, 
-                        y: pos.y - 1 
-                    })?;
+                    caret.move_to(Position { x, y: pos.y - 1 })?;
                 } else if view.scroll_offset > 0 {
-                    view.scroll_offset -= 1;
+                    view.scroll_offset = prev_line;
                     view.render(caret)?;
-                    caret.move_to(Position { 
-                        x: Position::MARGIN + prev_line_len as u16, 
-                        y: Position::HEADER 
-                    })?;
+                    caret.move_to(Position { x, y: Position::HEADER })?;
                 }
             } else {
                 let new_offset = caret.move_left(view.scroll_offset)?;
-                view.scroll_offset = new_offset;
+                scroll_row(view, new_offset);
             }
         },
         "right" => {
             let pos = caret.get_position();
-            let buffer_line_idx = (pos.y.saturating_sub(Position::HEADER)) as usize + view.scroll_offset;
+            let TextPosition { line: buffer_line_idx, column: char_pos } = helpers::get_current_text_pos(view, caret);
             
-            if let Some(line) = view.buffer.lines.get(buffer_line_idx) {
-                let char_pos = (pos.x as usize).saturating_sub(Position::MARGIN as usize);
-                let line_grapheme_len = grapheme_len(line);
-                let line_end = Position::MARGIN + line_grapheme_len as u16;
+            if buffer_line_idx < view.buffer.lines.len() {
+                let line_grapheme_len = view.line_metrics(buffer_line_idx).graphemes;
                 let size = Terminal::get_size()?;
+                let has_room = char_pos < line_grapheme_len || view.config.virtual_space;
+
+                // Long lines don't wrap to the next row, their window follows the caret
+                if has_room && display::is_long(&view.buffer.lines[buffer_line_idx]) {
+                    let (x, y) = helpers::text_to_screen_pos(view, TextPosition { line: buffer_line_idx, column: char_pos + 1 });
+                    caret.move_to(Position { x, y })?;
+                    return Ok(());
+                }
                 
-                if pos.x < line_end && pos.x < size.width - 1 {
+                if has_room && pos.x < size.width - 1 {
                     let new_offset = caret.move_right(view.scroll_offset, view.buffer.lines.len())?;
-                    view.scroll_offset = new_offset;
+                    scroll_row(view, new_offset);
                     return Ok(());
                 }
                 
-                if char_pos >= line_grapheme_len && buffer_line_idx + 1 < view.buffer.lines.len() {
+                if char_pos >= line_grapheme_len && view.folds.next_visible(buffer_line_idx) < view.buffer.lines.len() {
                     if pos.y < size.height - 2 {
                         caret.move_to(Position { x: Position::MARGIN, y: pos.y + 1 })?;
                     } else {
-                        view.scroll_offset += 1;
+                        view.scroll_offset = view.folds.next_visible(view.scroll_offset);
                         view.render(caret)?;
                         caret.move_to(Position { x: Position::MARGIN, y: pos.y })?;
                     }
@@ -99,19 +107,20 @@ fn perform_movement(view: &mut View, direction: &str, caret: &mut Caret) -> Resu
         },
         "up" => {
             let new_offset = caret.move_up(view.scroll_offset)?;
-            view.scroll_offset = new_offset;
+            scroll_row(view, new_offset);
             view.render(caret)?;
             view.clamp_cursor_to_line(caret)?;
         },
         "down" => {
             let new_offset = caret.move_down(view.scroll_offset, view.buffer.lines.len())?;
-            view.scroll_offset = new_offset;
+            scroll_row(view, new_offset);
             view.render(caret)?;
             view.clamp_cursor_to_line(caret)?;
         },
         "top" => {
             let new_offset = caret.move_top()?;
             view.scroll_offset = new_offset;
+            helpers::text_to_screen_pos(view, TextPosition { line: 0, column: 0 });
             view.render(caret)?;
             caret.move_to(Position { x: Position::MARGIN, y: Position::HEADER })?;
         },
@@ -119,31 +128,26 @@ fn perform_movement(view: &mut View, direction: &str, caret: &mut Caret) -> Resu
             let size = Terminal::get_size()?;
             let visible_rows = (size.height.saturating_sub(Position::HEADER + 1)) as usize;
             
-            let last_line = view.buffer.lines.iter()
-                .rposition(|line| !line.is_empty())
-                .unwrap_or(0);
-            
-            if last_line >= visible_rows {
-                view.scroll_offset = last_line - visible_rows + 1;
-            } else {
-                view.scroll_offset = 0;
-            }
+            let last_line = view.buffer.logical_len() - 1;
+            view.scroll_offset = view.folds.top_above(last_line, visible_rows);
             
             view.render(caret)?;
             caret.move_bottom()?;
             view.clamp_cursor_to_line(caret)?;
         },
         "max_left" => {
+            let line = helpers::get_current_text_pos(view, caret).line;
+            helpers::text_to_screen_pos(view, TextPosition { line, column: 0 });
             caret.move_max_left()?;
         },
         "max_right" => {
             let pos = caret.get_position();
-            let buffer_line_idx = (pos.y.saturating_sub(Position::HEADER)) as usize + view.scroll_offset;
+            let buffer_line_idx = view.folds.line_at_row(view.scroll_offset, pos.y.saturating_sub(Position::HEADER) as usize);
             
-            if let Some(line) = view.buffer.lines.get(buffer_line_idx) {
+            if buffer_line_idx < view.buffer.lines.len() {
                 let size = Terminal::get_size()?;
-                let line_visual_width = visual_width(line);
-                let line_end = Position::MARGIN + line_visual_width as u16;
+                let line_len = view.line_metrics(buffer_line_idx).graphemes;
+                let (line_end, _) = helpers::text_to_screen_pos(view, TextPosition { line: buffer_line_idx, column: line_len });
                 let max_x = line_end.min(size.width - 1);
                 caret.move_to(Position { x: max_x, y: pos.y })?;
             } else {
@@ -155,11 +159,18 @@ fn perform_movement(view: &mut View, direction: &str, caret: &mut Caret) -> Resu
     Ok(())
 }
 
+// The caret scrolled the view a row; past a fold that row is a whole block
+fn scroll_row(view: &mut View, new_offset: usize) {
+    view.scroll_offset = match new_offset.cmp(&view.scroll_offset) {
+        Ordering::Less => view.folds.prev_visible(view.scroll_offset),
+        Ordering::Greater => view.folds.next_visible(view.scroll_offset),
+        Ordering::Equal => new_offset,
+    };
+}
+
 pub fn select_all(view: &mut View, caret: &mut Caret) -> Result<(), Error> {
-    // Find last non-empty line
-    let last_line_idx = view.buffer.lines.iter()
-        .rposition(|line| !line.is_empty())
-        .unwrap_or(0);
+    // Last line of the text, trailing blank lines included
+    let last_line_idx = view.buffer.logical_len() - 1;
     
     let last_line_len = view.buffer.lines.get(last_line_idx)
         .map(|l| grapheme_len(l))
@@ -178,4 +189,61 @@ pub fn select_all(view: &mut View, caret: &mut Caret) -> Result<(), Error> {
     
     view.render(caret)?;
     Ok(())
-}
\ No newline at end of file
+}
+// Selection (or caret) as char columns, the unit smart_select works in
+fn char_range(view: &View, caret: &Caret) -> Range {
+    let (start, end) = match view.selection.as_ref().filter(|s| s.is_active()) {
+        Some(selection) => selection.get_range(),
+        None => {
+            let pos = helpers::get_current_text_pos(view, caret);
+            (pos, pos)
+        }
+    };
+    let to_chars = |pos: TextPosition| {
+        let line = view.buffer.lines.get(pos.line).map_or("", |l| l.as_str());
+        TextPosition { line: pos.line, column: grapheme_slice(line, 0, pos.column).chars().count() }
+    };
+    (to_chars(start), to_chars(end))
+}
+
+pub fn select_char_range(view: &mut View, (start, end): Range, caret: &mut Caret) -> Result<(), Error> {
+    let to_graphemes = |pos: TextPosition| {
+        let prefix: String = view.buffer.lines[pos.line].chars().take(pos.column).collect();
+        TextPosition { line: pos.line, column: grapheme_len(&prefix) }
+    };
+    let (start, end) = (to_graphemes(start), to_graphemes(end));
+    view.jump_to(end, caret)?;
+    view.selection = Some(Selection { anchor: start, cursor: end });
+    Ok(())
+}
+
+pub fn expand_selection(view: &mut View, caret: &mut Caret) -> Result<(), Error> {
+    let range = char_range(view, caret);
+    if let Some(next) = view.selection_steps.expand(&view.buffer.lines, range) {
+        select_char_range(view, next, caret)?;
+    }
+    Ok(())
+}
+
+// Step back through earlier expansions, or drop the selection when there are none
+pub fn shrink_selection(view: &mut View, caret: &mut Caret) -> Result<(), Error> {
+    let range = char_range(view, caret);
+    match view.selection_steps.shrink(range) {
+        Some(previous) if previous.0 != previous.1 => select_char_range(view, previous, caret)?,
+        _ => view.selection = None,
+    }
+    Ok(())
+}
+
+// Paragraph and indentation block jumps, extending the selection when `select` is set.
+// Targets sit after leading whitespace, so their char column is also their grapheme column
+pub fn jump(view: &mut View, jump: Jump, select: bool, caret: &mut Caret) -> Result<(), Error> {
+    let current = helpers::get_current_text_pos(view, caret);
+    let anchor = view.selection.as_ref().map_or(current, |s| s.anchor);
+    let target = jumps::target(&view.buffer.lines, current.line, jump, |i| view.line_metrics(i));
+    view.jump_to(target, caret)?;
+    if select {
+        view.selection = Some(Selection { anchor, cursor: target });
+    }
+    Ok(())
+}